@@ -112,28 +112,154 @@ shaders! {
 }
 */
 
-fn preprocess_wgsl(current_path: impl AsRef<std::path::Path>, original_source: &str) -> String {
-    let current_path = current_path.as_ref();
-    let include_re = regex::Regex::new(r#"\<include!\("(?<path>[^"]*)"\)\s*\{\s*\}"#).unwrap();
-    let mut include_sources = Vec::new();
-    include_sources.push("".to_string());
-    for capture in include_re.captures_iter(original_source) {
-        let path_match = capture.name("path").unwrap();
-        let path = path_match.as_str();
-        let include_path = current_path.join(path);
-        let include_source = read_to_string(&include_path);
-        let include_source = preprocess_wgsl(include_path.parent().unwrap(), &include_source);
-        include_sources.push(include_source);
+// Includes are written `include!("lib/oklab.wgsl") {}`, and each file is only included once per
+// shader, however many files include it. Writing `include!("lib/oklab.wgsl") { namespace: oklab }`
+// instead prefixes the functions, structs, constants, and aliases the file declares, so they are
+// referred to as `oklab::srgb_gamma` and can't collide with those of the including shader. Overrides
+// and module-scope variables are part of the pipeline's interface, which is generated from their
+// names, so those are left as they are. Declared names may not contain `__`, which separates a
+// namespace from the name, so a namespaced name can't collide with any other declaration.
+
+fn include_regex() -> regex::Regex {
+    regex::Regex::new(
+        r#"\<include!\("(?<path>[^"]*)"\)\s*\{\s*(?:namespace\s*:\s*(?<namespace>\w+)\s*)?\}"#,
+    )
+    .unwrap()
+}
+
+/// What `name` becomes when declared in a file included under `namespace`.
+fn namespaced(namespace: &str, name: &str) -> String {
+    format!("{namespace}__{name}")
+}
+
+/// Panics unless `namespace` and each of `names` can be told apart once namespaced. A namespace
+/// ending in `_` would run into the separator, as would a name starting with one.
+fn check_names<'a>(namespace: Option<&str>, names: impl IntoIterator<Item = &'a String>) {
+    if let Some(namespace) = namespace {
+        assert!(
+            !namespace.contains("__") && !namespace.ends_with('_'),
+            "namespace `{namespace}` may not contain `__` or end with `_`"
+        );
     }
-    let mut result = String::new();
-    for (include, split) in include_sources
-        .iter()
-        .zip(include_re.split(original_source))
-    {
-        result.push_str(include);
-        result.push_str(split);
+    for name in names {
+        assert!(
+            !name.contains("__"),
+            "`{name}` may not contain `__`, which is reserved for namespaces"
+        );
+        assert!(
+            namespace.is_none() || !name.starts_with('_'),
+            "`{name}` may not start with `_` in a namespaced file"
+        );
     }
-    result
+}
+
+/// Replaces references like `oklab::srgb_gamma` with the names they refer to.
+fn resolve_namespaced_references(source: &str) -> String {
+    let reference_re = regex::Regex::new(r"\<(?<namespace>\w+)\s*::\s*(?<name>\w+)\>").unwrap();
+    reference_re
+        .replace_all(source, |capture: &regex::Captures| {
+            namespaced(&capture["namespace"], &capture["name"])
+        })
+        .into_owned()
+}
+
+/// The names of the functions, structs, constants, and aliases declared at the top level of
+/// `source`, which is assumed not to indent top-level declarations.
+fn declared_names(source: &str) -> std::collections::HashSet<String> {
+    let declaration_re = regex::Regex::new(
+        r"(?m)^(?:@\w+(?:\([^)]*\))?\s+)*(?:fn|struct|const|alias)\s+(?<name>\w+)",
+    )
+    .unwrap();
+    declaration_re
+        .captures_iter(source)
+        .map(|capture| capture["name"].to_string())
+        .collect()
+}
+
+/// Prefixes each of `names` in `source` with `namespace`. Words after a `.` are members, such as
+/// fields or swizzles, so are left as they are, but struct fields shouldn't otherwise share a name
+/// with a declaration.
+fn add_namespace(
+    namespace: &str,
+    names: &std::collections::HashSet<String>,
+    source: &str,
+) -> String {
+    let word_re = regex::Regex::new(r"(?<member>\.\s*)?\<(?<word>\w+)\>").unwrap();
+    word_re
+        .replace_all(source, |capture: &regex::Captures| {
+            let word = &capture["word"];
+            match capture.name("member") {
+                None if names.contains(word) => namespaced(namespace, word),
+                _ => capture[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[derive(Default)]
+struct Preprocessor {
+    /// Each file included so far, with the namespace it was included under.
+    included: std::collections::HashSet<(std::path::PathBuf, Option<String>)>,
+}
+
+impl Preprocessor {
+    /// Expands the includes of `original_source`, which is in `current_path`, and adds `namespace` to
+    /// the names it declares itself.
+    fn preprocess(
+        &mut self,
+        current_path: &std::path::Path,
+        original_source: &str,
+        namespace: Option<&str>,
+    ) -> String {
+        let include_re = include_regex();
+        let splits: Vec<_> = include_re
+            .split(original_source)
+            .map(resolve_namespaced_references)
+            .collect();
+        let names = declared_names(&splits.concat());
+        check_names(namespace, &names);
+        let splits = match namespace {
+            Some(namespace) => splits
+                .iter()
+                .map(|split| add_namespace(namespace, &names, split))
+                .collect(),
+            None => splits,
+        };
+
+        let mut include_sources = Vec::new();
+        include_sources.push("".to_string());
+        for capture in include_re.captures_iter(original_source) {
+            let include_path = current_path.join(&capture["path"]);
+            let include_namespace = capture.name("namespace").map(|m| m.as_str());
+            let key = (
+                include_path
+                    .canonicalize()
+                    .unwrap_or_else(|_| include_path.clone()),
+                include_namespace.map(str::to_string),
+            );
+            let include_source = if self.included.insert(key) {
+                let include_source = read_to_string(&include_path);
+                self.preprocess(
+                    include_path.parent().unwrap(),
+                    &include_source,
+                    include_namespace,
+                )
+            } else {
+                "".to_string()
+            };
+            include_sources.push(include_source);
+        }
+        let mut result = String::new();
+        for (include, split) in include_sources.iter().zip(&splits) {
+            result.push_str(include);
+            result.push_str(split);
+        }
+        result
+    }
+}
+
+fn preprocess_wgsl(current_path: impl AsRef<std::path::Path>, original_source: &str) -> String {
+    Preprocessor::default().preprocess(current_path.as_ref(), original_source, None)
 }
 
 #[proc_macro]
@@ -172,3 +298,106 @@ pub fn shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     }
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaces_declarations() {
+        let source = "\
+const x = 2.0;
+struct Sample { value: vec2<f32> };
+@must_use fn scale(sample: Sample) -> f32 {
+    return sample.value.x * x;
+}
+override offset: f32 = 0.0;
+";
+        let names = declared_names(source);
+        let mut sorted: Vec<_> = names.iter().map(String::as_str).collect();
+        sorted.sort();
+        assert_eq!(sorted, ["Sample", "scale", "x"]);
+        assert_eq!(
+            add_namespace("lib", &names, source),
+            "\
+const lib__x = 2.0;
+struct lib__Sample { value: vec2<f32> };
+@must_use fn lib__scale(sample: lib__Sample) -> f32 {
+    return sample.value.x * lib__x;
+}
+override offset: f32 = 0.0;
+"
+        );
+        assert_eq!(
+            resolve_namespaced_references("lib::scale(lib :: Sample(vec2(1.0)))"),
+            "lib__scale(lib__Sample(vec2(1.0)))"
+        );
+    }
+
+    #[test]
+    fn includes_files_once() {
+        let dir = std::env::temp_dir().join("wgsl_to_wgpu_macro_includes_files_once");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("common.wgsl"), "fn common() {}\n").unwrap();
+        std::fs::write(
+            dir.join("a.wgsl"),
+            "include!(\"common.wgsl\") {}\nfn a() { common(); }\n",
+        )
+        .unwrap();
+        let source = "\
+include!(\"a.wgsl\") {}
+include!(\"common.wgsl\") {}
+include!(\"a.wgsl\") { namespace: n }
+fn main() { a(); n::a(); }
+";
+        let result = preprocess_wgsl(&dir, source);
+        let lines: Vec<_> = result.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(
+            lines,
+            [
+                "fn common() {}",
+                "fn a() { common(); }",
+                "fn n__a() { common(); }",
+                "fn main() { a(); n__a(); }",
+            ]
+        );
+    }
+
+    #[test]
+    fn namespaced_names_do_not_collide() {
+        let dir = std::env::temp_dir().join("wgsl_to_wgpu_macro_namespaced_names_do_not_collide");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.wgsl"), "fn scale() {}\n").unwrap();
+        // `lib_scale` is what `lib::scale` was once named, so the two used to be declared twice.
+        let source = "\
+include!(\"lib.wgsl\") { namespace: lib }
+fn lib_scale() {}
+fn main() { lib::scale(); lib_scale(); }
+";
+        let result = preprocess_wgsl(&dir, source);
+        let lines: Vec<_> = result.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(
+            lines,
+            [
+                "fn lib__scale() {}",
+                "fn lib_scale() {}",
+                "fn main() { lib__scale(); lib_scale(); }",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved for namespaces")]
+    fn rejects_names_like_namespaced_ones() {
+        preprocess_wgsl(std::env::temp_dir(), "fn lib__scale() {}\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "may not contain `__` or end with `_`")]
+    fn rejects_namespaces_running_into_the_separator() {
+        let dir = std::env::temp_dir().join("wgsl_to_wgpu_macro_rejects_namespaces");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.wgsl"), "fn scale() {}\n").unwrap();
+        preprocess_wgsl(&dir, "include!(\"lib.wgsl\") { namespace: lib_ }\n");
+    }
+}
@@ -0,0 +1,24 @@
+//! Compares how each interpolator fits strokes from action logs exported from the app, or synthetic
+//! strokes if none are given, and prints a report.
+//!
+//! ```sh
+//! cargo run --release --example compare_interpolators -- actions.json...
+//! ```
+
+use stark::engine::ActionLog;
+use stark::util::smoothing_benchmark::{compare_interpolators, format_reports, Trace};
+
+fn main() -> anyhow::Result<()> {
+	let mut traces = Vec::new();
+	for path in std::env::args().skip(1) {
+		let log = ActionLog::decode(&std::fs::read_to_string(&path)?)?;
+		traces.extend(Trace::from_action_log(&path, &log));
+	}
+	if traces.is_empty() {
+		println!("no action logs given, so comparing synthetic strokes");
+		traces = Trace::synthetic();
+	}
+
+	print!("{}", format_reports(&compare_interpolators(&traces)));
+	Ok(())
+}
@@ -0,0 +1,45 @@
+//! Draws a vector drawing exported from the app, headlessly, at any scale. This recovers strokes
+//! when the painting itself was lost, or renders them at a higher resolution than they were painted.
+//!
+//! ```sh
+//! cargo run --example rasterize_vector_drawing -- strokes.json result.png [scale]
+//! ```
+
+use stark::engine::{self, Airbrush, Atlas, CanvasFormat, VectorDrawing, VectorEdit};
+use stark::render::Resources;
+use stark::WgpuContext;
+use std::sync::Arc;
+
+fn main() -> anyhow::Result<()> {
+	let mut args = std::env::args().skip(1);
+	let (Some(drawing_path), Some(output_path)) = (args.next(), args.next()) else {
+		anyhow::bail!("usage: rasterize_vector_drawing <vector drawing> <output png> [scale]");
+	};
+	let scale: f32 = args.next().map_or(Ok(1.0), |scale| scale.parse())?;
+
+	let drawing = VectorDrawing::decode(&std::fs::read_to_string(drawing_path)?)?;
+	let strokes = drawing
+		.edits()
+		.filter(|edit| matches!(edit, VectorEdit::Stroke(_)))
+		.count();
+	println!("drawing {strokes} strokes at {scale}x");
+	if !drawing.is_complete() {
+		println!(
+			"the drawing includes edits without vectors, such as imported images, which are left out"
+		);
+	}
+
+	let context = Arc::new(pollster::block_on(WgpuContext::builder().create())?);
+	let (device, queue) = (context.device(), context.queue());
+	let resources = Arc::new(Resources::new(device));
+	let format = CanvasFormat::default();
+	let mut atlas = Atlas::new(context.clone(), format);
+	let mut airbrush = Airbrush::new(device, queue, &resources, format);
+	drawing.replay(queue, &mut atlas, &mut airbrush, scale)?;
+
+	let texture = engine::export(&atlas).generate(device, queue, &resources)?;
+	let png = pollster::block_on(engine::encode_png(&context, &texture, None))?;
+	std::fs::write(&output_path, png)?;
+	println!("wrote {output_path}");
+	Ok(())
+}
@@ -0,0 +1,50 @@
+//! Replays an action log exported from the app, headlessly, to reproduce bugs in stroke smoothing
+//! or in drawing across charts.
+//!
+//! ```sh
+//! cargo run --example replay_action_log -- actions.json [result.png]
+//! ```
+
+use stark::engine::{self, ActionLog, Airbrush, Atlas, CanvasFormat, History, LoggedAction};
+use stark::render::Resources;
+use stark::WgpuContext;
+use std::sync::Arc;
+
+fn main() -> anyhow::Result<()> {
+	let mut args = std::env::args().skip(1);
+	let Some(log_path) = args.next() else {
+		anyhow::bail!("usage: replay_action_log <action log> [output png]");
+	};
+	let output_path = args.next();
+
+	let log = ActionLog::decode(&std::fs::read_to_string(log_path)?)?;
+	let strokes = log
+		.actions()
+		.filter(|action| matches!(action, LoggedAction::Stroke(_)))
+		.count();
+	println!(
+		"replaying {} actions, {strokes} of them strokes",
+		log.actions().count()
+	);
+
+	let context = Arc::new(pollster::block_on(WgpuContext::builder().create())?);
+	let (device, queue) = (context.device(), context.queue());
+	let resources = Arc::new(Resources::new(device));
+	let format = CanvasFormat::default();
+	let mut atlas = Atlas::new(context.clone(), format);
+	let mut airbrush = Airbrush::new(device, queue, &resources, format);
+	let mut history = History::default();
+	log.replay(queue, &mut atlas, &mut airbrush, &mut history)?;
+
+	let mut touched: Vec<_> = atlas.take_dirty().into_iter().collect();
+	touched.sort();
+	println!("touched {} charts: {touched:?}", touched.len());
+
+	if let Some(output_path) = output_path {
+		let texture = engine::export(&atlas).generate(device, queue, &resources)?;
+		let png = pollster::block_on(engine::encode_png(&context, &texture, None))?;
+		std::fs::write(&output_path, png)?;
+		println!("wrote {output_path}");
+	}
+	Ok(())
+}
@@ -0,0 +1,24 @@
+//! Decodes arbitrary bytes as a project file and as a single saved chart, which is what each of a
+//! project's charts is stored as. Malformed files must be rejected with an error, rather than a
+//! panic or an allocation far larger than anything they could decode to.
+//!
+//! Run with `cargo fuzz run project_file` from the repository root. Saved `.stark` projects make a
+//! good starting corpus.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stark::engine::{ProjectFile, SavedChart};
+
+fuzz_target!(|data: &[u8]| {
+	// Whatever decodes must survive being saved again.
+	if let Ok(project) = ProjectFile::decode(data) {
+		let encoded = project.encode();
+		let decoded = ProjectFile::decode(&encoded).expect("re-encoded project should decode");
+		assert_eq!(decoded.charts.len(), project.charts.len());
+	}
+	if let Ok(chart) = SavedChart::decode(data) {
+		let decoded = SavedChart::decode(&chart.encode()).expect("re-encoded chart should decode");
+		assert_eq!(decoded.texture_data, chart.texture_data);
+	}
+});
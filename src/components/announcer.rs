@@ -0,0 +1,58 @@
+use leptos::prelude::*;
+use leptos_use::use_debounce_fn_with_arg;
+
+/// Announces changes to screen readers, for feedback that is otherwise only visual. Provide it as a
+/// context and show its messages with [`Announcements`].
+#[derive(Clone, Copy)]
+pub struct Announcer(RwSignal<String>);
+
+impl Default for Announcer {
+	fn default() -> Self {
+		Self(RwSignal::new(String::new()))
+	}
+}
+
+impl Announcer {
+	pub fn announce(self, message: impl Into<String>) {
+		let mut message = message.into();
+		// Screen readers don't announce a region being set to the text it already holds, so a repeated
+		// message alternates a trailing space.
+		self.0.update(|current| {
+			if *current == message {
+				message.push('\u{a0}');
+			}
+			*current = message;
+		});
+	}
+
+	/// Announces `describe` of `value` whenever it changes, once it has settled for `delay`
+	/// milliseconds. Its initial value isn't announced.
+	pub fn announce_changes<T>(
+		self,
+		value: impl Fn() -> T + Send + Sync + 'static,
+		describe: impl Fn(T) -> String + 'static,
+		delay: f64,
+	) where
+		T: Clone + PartialEq + Send + Sync + 'static,
+	{
+		let value = Memo::new(move |_| value());
+		let announce =
+			use_debounce_fn_with_arg(move |value: T| self.announce(describe(value)), delay);
+		Effect::new(move |initialized: Option<()>| {
+			let value = value.get();
+			if initialized.is_some() {
+				announce(value);
+			}
+		});
+	}
+}
+
+/// A visually hidden live region reading out the messages of an [`Announcer`].
+#[component]
+pub fn Announcements(announcer: Announcer) -> impl IntoView {
+	view! {
+		<div class="Announcements" role="status" aria-live="polite" aria-atomic="true">
+			{move || announcer.0.get()}
+		</div>
+	}
+}
@@ -0,0 +1,207 @@
+use crate::engine::{self, storage_key, Atlas, PoolExhausted, SavedChart};
+use crate::render::Resources;
+use crate::util::{self, ByteDatabase, ByteStore, ResultExt};
+use crate::WgpuContext;
+use leptos::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const DATABASE_NAME: &str = "stark";
+// Increase this when adding stores.
+const DATABASE_VERSION: u32 = 2;
+const CHARTS_STORE_NAME: &str = "charts";
+/// PNG previews of saved documents, for listing them.
+const THUMBNAILS_STORE_NAME: &str = "thumbnails";
+
+/// The key of the autosaved document in per-document stores.
+const AUTOSAVE_DOCUMENT_KEY: &str = "autosave";
+
+/// How often modified charts are saved.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What an autosaving canvas does with the previously saved document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutosaveMode {
+	/// Load the saved document into the canvas.
+	#[default]
+	Restore,
+	/// Discard the saved document, e.g. because the canvas was deliberately cleared.
+	Replace,
+	/// Keep saving a document that was already restored or replaced by an earlier canvas.
+	Continue,
+}
+
+struct Stores {
+	charts: ByteStore,
+	thumbnails: ByteStore,
+}
+
+impl Stores {
+	async fn open() -> Result<Self, util::JsError> {
+		let database = ByteDatabase::open(
+			DATABASE_NAME,
+			DATABASE_VERSION,
+			&[CHARTS_STORE_NAME, THUMBNAILS_STORE_NAME],
+		)
+		.await?;
+		Ok(Self {
+			charts: database.store(CHARTS_STORE_NAME),
+			thumbnails: database.store(THUMBNAILS_STORE_NAME),
+		})
+	}
+}
+
+/// Periodically saves modified charts in `atlas` to IndexedDB, along with a thumbnail of the
+/// document. Before saving anything, the previously saved document is restored or discarded
+/// according to `mode`, calling `on_restore` once restored charts are in the atlas, with an error if
+/// some didn't fit.
+pub fn use_autosave(
+	atlas: Arc<RwLock<Atlas>>,
+	mode: AutosaveMode,
+	on_restore: impl FnOnce(Result<(), PoolExhausted>) + 'static,
+) {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	let resources: Arc<Resources> = use_context().unwrap();
+
+	let stores = StoredValue::new_local(None::<Rc<Stores>>);
+	{
+		let atlas = atlas.clone();
+		leptos::task::spawn_local(async move {
+			let opened = match Stores::open().await {
+				Ok(opened) => opened,
+				Err(err) => {
+					tracing::warn!("autosave is unavailable: {err:?}");
+					return;
+				}
+			};
+			match mode {
+				AutosaveMode::Restore => {
+					let saved = opened
+						.charts
+						.get_all()
+						.await
+						.ok_or_log()
+						.unwrap_or_default();
+					let restored = restore(&mut atlas.write().unwrap(), saved);
+					on_restore(restored);
+				}
+				AutosaveMode::Replace => {
+					opened.charts.clear().await.ok_or_log();
+					opened
+						.thumbnails
+						.delete(AUTOSAVE_DOCUMENT_KEY)
+						.await
+						.ok_or_log();
+				}
+				AutosaveMode::Continue => {}
+			}
+			// The canvas may have been unmounted in the meantime.
+			stores.try_set_value(Some(Rc::new(opened)));
+		});
+	}
+
+	let is_saving = Rc::new(Cell::new(false));
+	let save = move || {
+		let Some(Some(stores)) = stores.try_get_value() else {
+			return;
+		};
+		if is_saving.replace(true) {
+			return;
+		}
+		let atlas = atlas.clone();
+		let context = context.clone();
+		let resources = resources.clone();
+		let is_saving = is_saving.clone();
+		leptos::task::spawn_local(async move {
+			if save_dirty(&stores.charts, &atlas).await {
+				save_thumbnail(&stores.thumbnails, &atlas, &context, &resources)
+					.await
+					.ok_or_log();
+			}
+			is_saving.set(false);
+		});
+	};
+	util::set_interval_and_clean_up(save.clone(), AUTOSAVE_INTERVAL).ok_or_log();
+
+	// Browsers may discard hidden pages without warning, so don't wait for the interval.
+	let visibility = leptos_use::use_document_visibility();
+	Effect::new(move |_| {
+		if visibility.get() == leptos::web_sys::VisibilityState::Hidden {
+			save();
+		}
+	});
+}
+
+/// Restores the charts that fit, stopping at the first which doesn't.
+fn restore(atlas: &mut Atlas, saved: Vec<Vec<u8>>) -> Result<(), PoolExhausted> {
+	for encoded in saved {
+		let Some(chart) = SavedChart::decode(&encoded).ok_or_log() else {
+			continue;
+		};
+		// Don't clobber anything drawn while the document was loading.
+		if atlas.get_chart(&chart.key).is_none() {
+			atlas.restore_chart(chart)?;
+		}
+	}
+	Ok(())
+}
+
+/// Saves charts modified since the last save, returning whether there were any.
+async fn save_dirty(store: &ByteStore, atlas: &RwLock<Atlas>) -> bool {
+	// Start reading back every modified chart before releasing the lock so that the saved document
+	// is consistent.
+	let saves: Vec<_> = {
+		let mut atlas = atlas.write().unwrap();
+		let dirty = atlas.take_dirty();
+		dirty
+			.into_iter()
+			.map(|key| (key, atlas.save_chart(key)))
+			.collect()
+	};
+	if saves.is_empty() {
+		return false;
+	}
+
+	let mut failed = Vec::new();
+	for (key, save) in saves {
+		let result = match save {
+			Some(save) => {
+				async {
+					let encoded = save.await?.encode();
+					store.put(&storage_key(key), &encoded).await?;
+					anyhow::Ok(())
+				}
+				.await
+			}
+			None => store.delete(&storage_key(key)).await.map_err(Into::into),
+		};
+		if result.ok_or_log().is_none() {
+			failed.push(key);
+		}
+	}
+	atlas.write().unwrap().mark_dirty(failed);
+	true
+}
+
+async fn save_thumbnail(
+	store: &ByteStore,
+	atlas: &RwLock<Atlas>,
+	context: &WgpuContext,
+	resources: &Arc<Resources>,
+) -> anyhow::Result<()> {
+	let texture = engine::thumbnail(&atlas.read().unwrap()).generate(
+		context.device(),
+		context.queue(),
+		resources,
+	);
+	match texture {
+		Some(texture) => {
+			let png = engine::encode_png(context, &texture, None).await?;
+			store.put(AUTOSAVE_DOCUMENT_KEY, &png).await?;
+		}
+		None => store.delete(AUTOSAVE_DOCUMENT_KEY).await?,
+	}
+	Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::components::*;
+use crate::engine::BrushMode;
+use leptos::prelude::*;
+
+/// Chooses whether the brush paints or smudges.
+#[component]
+pub fn BrushModeSetting(mode: RwSignal<BrushMode>) -> impl IntoView {
+	let options = BrushMode::ALL
+		.into_iter()
+		.map(|m| view! { <option value=m.to_string()>{m.to_string()}</option> })
+		.collect_view();
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if let Some(m) = BrushMode::ALL.into_iter().find(|m| m.to_string() == value) {
+			mode.set(m);
+		}
+	};
+
+	view! {
+		<BrushSetting name="Mode">
+			<select on:change=change prop:value=move || mode.get().to_string()>
+				{options}
+			</select>
+		</BrushSetting>
+	}
+}
@@ -0,0 +1,189 @@
+use crate::components::*;
+use crate::util::create_local_derived;
+use crate::view_transform::ViewTransform;
+use crate::*;
+use engine::*;
+use glam::*;
+use leptos::prelude::*;
+use leptos_use::{use_element_size, UseElementSizeReturn};
+use std::sync::{Arc, RwLock};
+use util::LocalCallback;
+use util::ResultExt;
+use util::SetExt;
+
+/// The number of input points in the preview stroke.
+const PREVIEW_STROKE_SAMPLES: usize = 64;
+
+/// Shows the current brush settings applied to a short stroke. The stroke is redrawn into a private
+/// atlas whenever the settings change.
+#[component]
+pub fn BrushPreview(
+	#[prop(into)] brush_color: Signal<Vec3>,
+	#[prop(into)] brush_size: Signal<f64>,
+	#[prop(into)] brush_rate: Signal<f64>,
+	#[prop(into)] brush_opacity: Signal<f64>,
+	// The shape the brush sprays. The built-in default is sprayed until this is set.
+	#[prop(optional, into)] brush_shape: Signal<Option<Arc<BrushShape>>>,
+	// How paint falls off across the brush shape.
+	#[prop(optional, into)] stamp_profile: Signal<StampProfile>,
+	// How far the brush moves between segments, as a fraction of its diameter.
+	#[prop(optional, into)] brush_spacing: Option<Signal<f64>>,
+	// How far segments are randomly shifted across the stroke, as a fraction of the brush's radius.
+	#[prop(optional, into)] brush_scatter: Option<Signal<f64>>,
+	// How much the coverage of each texel varies randomly.
+	#[prop(optional, into)] brush_grain: Option<Signal<f64>>,
+	// The format is fixed for the lifetime of the preview.
+	#[prop(optional)] canvas_format: CanvasFormat,
+) -> impl IntoView {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	let resources: Arc<render::Resources> = use_context().unwrap();
+
+	let node_ref = NodeRef::new();
+	let UseElementSizeReturn { width, height } = use_element_size(node_ref);
+
+	let atlas = Arc::new(RwLock::new(Atlas::new(context.clone(), canvas_format)));
+	let mut airbrush = Airbrush::new(context.device(), context.queue(), &resources, canvas_format);
+	// Keep the grain of the preview stable as the settings change.
+	airbrush.set_seed(Some(0));
+	let airbrush = std::rc::Rc::new(std::cell::RefCell::new(airbrush));
+	let canvas_renderer = Arc::new(CanvasRenderer::new(
+		context.device(),
+		resources.clone(),
+		canvas_format,
+	));
+
+	let (surface_configuration, set_surface_configuration) =
+		signal_local::<Option<wgpu::SurfaceConfiguration>>(None);
+	let surface_texture_format = Memo::new(move |_| surface_configuration.get().map(|c| c.format));
+
+	let render_pipeline = {
+		let canvas_renderer = canvas_renderer.clone();
+		create_local_derived(move || {
+			let pipeline = canvas_renderer.create_pipeline(surface_texture_format.get()?, 1, None);
+			Some(Arc::new(pipeline))
+		})
+	};
+
+	let stroke = create_local_derived(move || {
+		PreviewStroke::new(
+			InputPoint {
+				position: DVec2::ZERO,
+				pressure: 1.0,
+				color: brush_color.get(),
+				size: brush_size.get() as f32,
+				opacity: brush_opacity.get() as f32,
+				rate: brush_rate.get() as f32,
+				tilt: Vec2::ZERO,
+			},
+			PREVIEW_STROKE_SAMPLES,
+		)
+	});
+
+	// Redraw the stroke from scratch whenever a setting changes. The shape is only uploaded again
+	// when it is a different one.
+	let applied_shape = StoredValue::new_local(None::<(Arc<BrushShape>, StampProfile)>);
+	let drawn_trigger = ArcTrigger::new();
+	{
+		let context = context.clone();
+		let atlas = atlas.clone();
+		let drawn_trigger = drawn_trigger.clone();
+		Effect::new(move |_| {
+			let mut airbrush = airbrush.borrow_mut();
+			if let Some(shape) = brush_shape.get() {
+				let profile = stamp_profile.get();
+				let applied = applied_shape.with_value(|applied| {
+					applied.as_ref().is_some_and(|(applied, applied_profile)| {
+						Arc::ptr_eq(applied, &shape) && *applied_profile == profile
+					})
+				});
+				if !applied {
+					airbrush.set_shape(
+						context.device(),
+						context.queue(),
+						&resources,
+						&shape,
+						profile,
+					);
+					applied_shape.set_value(Some((shape, profile)));
+				}
+			}
+			let spacing = brush_spacing.map_or(DEFAULT_BRUSH_SPACING, |spacing| spacing.get() as f32);
+			let scatter = brush_scatter.map_or(DEFAULT_BRUSH_SCATTER, |scatter| scatter.get() as f32);
+			let grain = brush_grain.map_or(DEFAULT_BRUSH_GRAIN, |grain| grain.get() as f32);
+			airbrush.set_spacing(spacing);
+			airbrush.set_scatter(scatter);
+			airbrush.set_grain(grain);
+			let mut atlas = atlas.write().unwrap();
+			atlas.clear();
+			airbrush
+				.draw_stroke(context.queue(), &mut atlas, stroke.get().points)
+				.ok_or_log();
+			drawn_trigger.notify();
+		});
+	}
+
+	// Fit the stroke to the preview.
+	let canvas_view = create_local_derived(move || {
+		let screen_size = dvec2(width.get(), height.get());
+		let stroke_size = stroke.with(|stroke| stroke.size).as_dvec2();
+		let zoom = (screen_size / stroke_size).min_element();
+		let view_transform = ViewTransform {
+			translation: 0.5 * (screen_size - zoom * stroke_size),
+			zoom,
+			..ViewTransform::IDENTITY
+		};
+		CanvasRenderer::canvas_view(&view_transform, screen_size)
+	});
+
+	let render = create_local_derived(move || {
+		let context = context.clone();
+		let atlas = atlas.clone();
+		let canvas_renderer = canvas_renderer.clone();
+		let render_pipeline = render_pipeline.get();
+		let canvas_view = canvas_view.get();
+		// Render again once the stroke is redrawn.
+		drawn_trigger.track();
+		let callback = move |view: wgpu::TextureView| {
+			let Some(render_pipeline) = &render_pipeline else {
+				return;
+			};
+
+			canvas_renderer.write_view(context.queue(), &canvas_view);
+
+			let mut encoder =
+				context
+					.device()
+					.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+						label: Some("BrushPreview::render"),
+					});
+			{
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					label: Some("BrushPreview::render"),
+					color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+						view: &view,
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+							store: wgpu::StoreOp::Store,
+						},
+					})],
+					..Default::default()
+				});
+				canvas_renderer.draw(&mut render_pass, render_pipeline, &atlas.read().unwrap());
+			}
+			context.queue().submit([encoder.finish()]);
+		};
+		Callback::new(callback)
+	});
+
+	let configured = move |configuration: wgpu::SurfaceConfiguration| {
+		set_surface_configuration.try_set_or_log(Some(configuration));
+	};
+	let configured = LocalCallback::new(configured);
+
+	view! {
+		<div class="BrushPreview" node_ref=node_ref>
+			<RenderSurface render=render configured=configured/>
+		</div>
+	}
+}
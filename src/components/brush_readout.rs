@@ -0,0 +1,23 @@
+use crate::util::{self, ResultExt};
+use leptos::prelude::*;
+use std::time::Duration;
+
+/// How long the readout stays after the last change.
+const READOUT_DURATION: Duration = Duration::from_millis(1000);
+
+/// Briefly shows `text` over the canvas, such as the brush size while stepping it from the
+/// keyboard, then clears it.
+#[component]
+pub fn BrushReadout(text: RwSignal<Option<String>>) -> impl IntoView {
+	// Each change restarts the timer, so the readout stays while a key is held.
+	Effect::new(move |_| {
+		if text.with(Option::is_some) {
+			util::set_timeout_and_clean_up(move || text.set(None), READOUT_DURATION).ok_or_log();
+		}
+	});
+	view! {
+		<Show when=move || text.with(Option::is_some)>
+			<div class="BrushReadout">{move || text.get()}</div>
+		</Show>
+	}
+}
@@ -0,0 +1,68 @@
+use crate::components::*;
+use crate::engine::{BrushShape, BrushShapes};
+use crate::util;
+use leptos::prelude::*;
+
+/// Chooses the shape the brush sprays. Grayscale images can be imported as shapes, and are named
+/// after their files.
+#[component]
+pub fn BrushShapeSetting(shapes: RwSignal<BrushShapes>, shape: RwSignal<String>) -> impl IntoView {
+	let error = RwSignal::new(None::<String>);
+	let file_input = NodeRef::<leptos::html::Input>::new();
+
+	let options = move || {
+		shapes.with(|shapes| {
+			shapes
+				.names()
+				.map(|name| view! { <option value=name.to_string()>{name.to_string()}</option> })
+				.collect_view()
+		})
+	};
+	let change = move |e: leptos::ev::Event| shape.set(event_target_value(&e));
+
+	let files_chosen = move |e: leptos::ev::Event| {
+		let Some(file) = util::take_chosen_files(&e).into_iter().next() else {
+			return;
+		};
+		leptos::task::spawn_local(async move {
+			let decoded = match util::read_file(&file).await {
+				Ok(data) => BrushShape::decode(&data).map_err(|err| err.to_string()),
+				Err(err) => Err(format!("{err:?}")),
+			};
+			let file_name = file.name();
+			match decoded {
+				Ok(decoded) => {
+					let name = file_name
+						.rsplit_once('.')
+						.map_or(file_name.as_str(), |(stem, _)| stem)
+						.to_string();
+					error.set(None);
+					shapes.update(|shapes| shapes.insert(name.clone(), decoded));
+					shape.set(name);
+				}
+				Err(err) => error.set(Some(format!("{file_name}: {err}"))),
+			}
+		});
+	};
+
+	view! {
+		<BrushSetting name="Shape">
+			<select on:change=change prop:value=move || shape.get()>
+				{options}
+			</select>
+			<input
+				type="file"
+				accept="image/png"
+				hidden
+				node_ref=file_input
+				on:change=files_chosen
+			/>
+			<button on:click=move |_| {
+				if let Some(file_input) = file_input.get_untracked() {
+					file_input.click();
+				}
+			}>"Import"</button>
+		</BrushSetting>
+		{move || error.get().map(|error| view! { <span class="DocumentSettingNote">{error}</span> })}
+	}
+}
@@ -1,37 +1,142 @@
 use crate::components::*;
-use crate::render::{self, BindingBuffer};
-use crate::util::create_local_derived;
+use crate::coordinates::{CanvasPt, ScreenPx};
+use crate::render;
+use crate::util::{create_local_async_derived, create_local_derived};
+use crate::view_transform::{ViewAnimation, ViewMotion, ViewTransform, ZoomEase, ZoomLimits};
 use crate::*;
 use engine::*;
 use glam::*;
 use leptos::prelude::*;
+use leptos::wasm_bindgen::JsCast;
+use leptos::web_sys;
 use leptos_use::{use_element_size, UseElementSizeReturn};
+use std::collections::VecDeque;
 use std::sync::{Arc, RwLock};
+use util::input_interpolate::InputPoint as StrokePoint;
 use util::CoordinateSource;
 use util::LocalCallback;
 use util::PointerCapture;
+use util::ResultExt;
 use util::SetExt;
 
 const MULTISAMPLE_COUNT: u32 = 4;
 
-fn create_canvas_sampler(device: &wgpu::Device) -> wgpu::Sampler {
-	device.create_sampler(&wgpu::SamplerDescriptor {
-		address_mode_u: wgpu::AddressMode::ClampToEdge,
-		address_mode_v: wgpu::AddressMode::ClampToEdge,
-		address_mode_w: wgpu::AddressMode::ClampToEdge,
-		mag_filter: wgpu::FilterMode::Nearest,
-		min_filter: wgpu::FilterMode::Linear,
-		mipmap_filter: wgpu::FilterMode::Linear,
-		..Default::default()
-	})
-}
+/// The duration of view transitions triggered by commands, in milliseconds.
+const VIEW_ANIMATION_DURATION: f64 = 250.0;
+
+/// How far what [`ViewCommand`]s frame is kept from the edges of the screen, in screen pixels.
+const FRAMING_MARGIN: f64 = 32.0;
+
+/// How far ahead to predict the view while panning, in milliseconds, to upload charts before they
+/// come into view.
+const PREFETCH_LOOKAHEAD: f64 = 250.0;
+
+/// The most charts uploaded ahead of time per view update, so prefetching doesn't itself stall.
+const MAX_PREFETCHED_CHARTS: usize = 2;
+
+/// How much of the stroke the stroke graph shows, in seconds.
+const STROKE_TRACE_DURATION: f32 = 2.0;
+
+/// How far ahead to predict the pointer when the browser doesn't, in seconds. About a frame, which
+/// is roughly how far the stroke trails the pointer.
+const PREDICTION_HORIZON: f32 = 1.0 / 60.0;
+
+/// How far a stroke snapping to guides goes before choosing which to follow, in screen pixels.
+const GUIDE_SNAP_DISTANCE: f32 = 8.0;
+
+/// What the render graph calls the frame being drawn, which each pass draws over in turn.
+const FRAME: &str = "frame";
+
+/// Shared access to the atlas of a [`Canvas`] for tools outside of it, such as export.
+pub type AtlasHandle = RwSignal<Option<Arc<RwLock<Atlas>>>, LocalStorage>;
 
 #[component]
 pub fn Canvas(
-	#[prop(into)] brush_color: Signal<Vec3>,
+	// Set by sampling the canvas with Alt+click.
+	brush_color: RwSignal<Vec3>,
 	#[prop(into)] brush_size: Signal<f64>,
 	#[prop(into)] brush_rate: Signal<f64>,
 	#[prop(into)] brush_opacity: Signal<f64>,
+	// How strongly strokes are stabilized, from 0 to 1.
+	#[prop(into)] smoothing: Signal<f64>,
+	// The longest fully stabilized strokes wait between segments, in seconds.
+	#[prop(optional, into)] smoothing_interval: Option<Signal<f64>>,
+	// How smoothed strokes reach the point where the pointer lifted.
+	#[prop(optional, into)] stroke_end: Signal<util::input_interpolate::StrokeEnd>,
+	#[prop(optional)] zoom_limits: ZoomLimits,
+	// The document shown and edited. Its format is fixed for the lifetime of the canvas.
+	document: Document,
+	// Set to the document's atlas when the canvas is created.
+	#[prop(optional)] atlas_handle: Option<AtlasHandle>,
+	// `PreMultiplied` shows the page behind regions of the canvas which haven't been drawn on,
+	// rather than black, if supported.
+	#[prop(optional)] alpha_mode: Option<wgpu::CompositeAlphaMode>,
+	// How strokes are repeated. The center is the middle of the view when the first stroke is drawn
+	// after this changes.
+	#[prop(optional, into)] symmetry: Signal<SymmetryKind>,
+	// Whether strokes paint, or smudge or mix with the paint already there.
+	#[prop(optional, into)] brush_mode: Signal<BrushMode>,
+	// The shape the brush sprays. The built-in default is sprayed until this is set.
+	#[prop(optional, into)] brush_shape: Signal<Option<Arc<BrushShape>>>,
+	// How paint falls off across the brush shape.
+	#[prop(optional, into)] stamp_profile: Signal<StampProfile>,
+	// How far the brush moves between segments, as a fraction of its diameter.
+	#[prop(optional, into)] brush_spacing: Option<Signal<f64>>,
+	// How far segments are randomly shifted across the stroke, as a fraction of the brush's radius.
+	#[prop(optional, into)] brush_scatter: Option<Signal<f64>>,
+	// How much the coverage of each texel varies randomly, from 0 to 1.
+	#[prop(optional, into)] brush_grain: Option<Signal<f64>>,
+	// How much of the paint under the brush mixing picks up, from 0 to 1.
+	#[prop(optional, into)] brush_pickup: Option<Signal<f64>>,
+	// Called with dropped projects and presets. Dropped images are painted where they land.
+	#[prop(optional, into)] open_file: Option<LocalCallback<(DroppedFileKind, web_sys::File)>>,
+	// While set, dragging selects rather than paints. Painting is confined to the selection, T lifts
+	// what it selects to be moved, scaled, and rotated, and Escape clears it.
+	#[prop(optional, into)] selection_tool: Signal<Option<SelectionTool>>,
+	// While set, dragging crops the document to the dragged rectangle. The whole canvas is shown
+	// meanwhile, so what's outside of the bounds can be brought back in.
+	#[prop(optional, into)] crop_tool: Signal<bool>,
+	// While set, dragging draws a shape, which is stroked with the brush once the drag ends.
+	#[prop(optional, into)] shape_tool: Signal<Option<ShapeTool>>,
+	// While set, clicking places the anchors of a path, which is stroked with the brush once Enter
+	// finishes it. Backspace removes the last anchor, and Escape discards the path.
+	#[prop(optional, into)] pen_tool: Signal<bool>,
+	// Guides drawn over the canvas, which are placed about the center of the view when chosen.
+	#[prop(optional, into)] guides: Signal<Option<GuideKind>>,
+	// While set, strokes follow whichever guide line through where they start they're nearest.
+	#[prop(optional, into)] snap_to_guides: Signal<bool>,
+	// Draws a grid between the canvas's pixels once they're large enough on screen to tell apart.
+	#[prop(optional, into)] pixel_grid: Signal<bool>,
+	// While set, shapes and the pen's anchors are placed at the center of the pixel under the pointer.
+	#[prop(optional, into)] snap_to_pixels: Signal<bool>,
+	// Draws without multisampling, through a surface tuned for latency, and shows the measured
+	// latency. Fixed for the lifetime of the canvas.
+	#[prop(optional)] low_latency: bool,
+	// The colors the canvas is shown in, which determines its surface's format. Fixed for the
+	// lifetime of the canvas.
+	#[prop(optional)] color_space: SurfaceColorSpace,
+	// Gives the canvas pass a depth/stencil attachment, for effects clipped by the stencil. Fixed for
+	// the lifetime of the canvas.
+	#[prop(optional)] depth_stencil: bool,
+	// Graphs the pointer's recent points against the stabilized stroke.
+	#[prop(optional, into)] stroke_graph: Signal<bool>,
+	// Measures the latency from drawing input to each stage of showing it, and shows it over the
+	// canvas. Low latency mode always does.
+	#[prop(optional, into)] latency_hud: Signal<bool>,
+	// Times each pass on the GPU and shows the timings over the canvas.
+	#[prop(optional, into)] gpu_profiler: Signal<bool>,
+	// Shows what is under the pointer over the canvas.
+	#[prop(optional, into)] pick_hud: Signal<bool>,
+	// Draws a provisional tip on strokes out to where the pointer is predicted to be, which hides some
+	// of the latency. It is replaced as the pointer's real points are drawn.
+	#[prop(optional, into)] predict_pointer: Signal<bool>,
+	// Setting this records the commands of the next frame into `captured_frame`, and clears it again.
+	#[prop(optional)] capture_frame: Option<RwSignal<bool>>,
+	#[prop(optional)] captured_frame: Option<RwSignal<Option<render::FrameCapture>>>,
+	// Text briefly shown over the canvas, such as the brush size while it is stepped.
+	#[prop(optional)] brush_readout: Option<RwSignal<Option<String>>>,
+	// Setting this animates the view as the command says, and clears it again.
+	#[prop(optional)] view_command: Option<RwSignal<Option<ViewCommand>>>,
 ) -> impl IntoView {
 	let context: Arc<WgpuContext> = use_context().unwrap();
 	let device = context.device();
@@ -40,24 +145,28 @@ pub fn Canvas(
 	let node_ref = NodeRef::new();
 	let UseElementSizeReturn { width, height } = use_element_size(node_ref);
 
-	let canvas_texture_format = wgpu::TextureFormat::Rgba16Float;
-	let atlas = Atlas::new(context.clone(), canvas_texture_format);
-	let atlas_buffer_layout = atlas.buffer_layout();
-	let atlas = Arc::new(RwLock::new(atlas));
-
-	let canvas_pipeline_layout = resources.canvas.pipeline_layout().get();
-	let canvas_sampler = create_canvas_sampler(&device);
-	let canvas_to_view_buffer = BindingBuffer::init(&Mat4::ZERO)
-		.label("canvas_to_view")
-		.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
-		.create(&device);
-	let canvas_bind_group = canvas_pipeline_layout
-		.bind_group_layouts()
-		.0
-		.bind_group()
-		.chart_sampler(&canvas_sampler)
-		.canvas_to_view(canvas_to_view_buffer.as_entire_buffer_binding())
-		.create();
+	let canvas_format = document.format();
+	let atlas = document.atlas.clone();
+	if let Some(atlas_handle) = atlas_handle {
+		atlas_handle.set(Some(atlas.clone()));
+	}
+
+	let canvas_renderer = Arc::new(CanvasRenderer::new(
+		&device,
+		resources.clone(),
+		canvas_format,
+	));
+
+	let cursor_renderer = Arc::new(CursorRenderer::new(&device, resources.clone()));
+	let stroke_tip_renderer = Arc::new(StrokeTipRenderer::new(&device, resources.clone()));
+	let bounds_renderer = Arc::new(BoundsRenderer::new(&device, resources.clone()));
+	let guides_renderer = Arc::new(GuidesRenderer::new(&device, resources.clone()));
+	// A floating selection is drawn through its own transformed view, so it needs its own renderer.
+	let floating_renderer = Arc::new(CanvasRenderer::new(
+		&device,
+		resources.clone(),
+		canvas_format,
+	));
 
 	let (surface_configuration, set_surface_configuration) =
 		signal_local::<Option<wgpu::SurfaceConfiguration>>(None);
@@ -65,264 +174,1927 @@ pub fn Canvas(
 	let surface_texture_size =
 		Memo::new(move |_| surface_configuration.get().map(|c| (c.width, c.height)));
 
+	// Resolving multisampling costs a pass, which low latency mode skips by drawing straight to the
+	// surface.
+	let sample_count = if low_latency { 1 } else { MULTISAMPLE_COUNT };
+	// Everything drawn in the canvas pass must agree on the depth/stencil format.
+	let depth_stencil_state =
+		depth_stencil.then(|| passthrough_depth_stencil(CANVAS_DEPTH_STENCIL_FORMAT));
+	// Pipelines compile in the background, and nothing is drawn until they are ready.
 	let render_pipeline = {
-		let device = context.device().clone();
-		let canvas_pipeline_layout = canvas_pipeline_layout.clone();
-		let vertex_buffer_layouts = [atlas_buffer_layout];
-		create_local_derived(move || {
-			let pipeline = canvas_pipeline_layout
-				.vs_main_pipeline(wgpu::VertexStepMode::Instance)
-				.primitive(wgpu::PrimitiveState {
-					topology: wgpu::PrimitiveTopology::TriangleStrip,
-					..Default::default()
+		let device = device.clone();
+		let canvas_renderer = canvas_renderer.clone();
+		let depth_stencil_state = depth_stencil_state.clone();
+		create_local_async_derived(move || {
+			let format = surface_texture_format.get()?;
+			let device = device.clone();
+			let canvas_renderer = canvas_renderer.clone();
+			let depth_stencil_state = depth_stencil_state.clone();
+			Some(async move {
+				let pipeline = render::create_pipeline_async(&device, "CanvasRenderer", || {
+					canvas_renderer.create_pipeline(format, sample_count, depth_stencil_state)
 				})
-				.fragment(shaders::canvas::FragmentEntry::fs_main {
-					targets: [Some(wgpu::ColorTargetState {
-						format: surface_texture_format.get()?,
-						// TODO: We will probably need to change this to support layers.
-						blend: Some(wgpu::BlendState::REPLACE),
-						write_mask: wgpu::ColorWrites::ALL,
-					})],
+				.await
+				.ok()?;
+				Some(Arc::new(pipeline))
+			})
+		})
+	};
+
+	let cursor_pipeline = {
+		let device = device.clone();
+		let cursor_renderer = cursor_renderer.clone();
+		let depth_stencil_state = depth_stencil_state.clone();
+		create_local_async_derived(move || {
+			let format = surface_texture_format.get()?;
+			let device = device.clone();
+			let cursor_renderer = cursor_renderer.clone();
+			let depth_stencil_state = depth_stencil_state.clone();
+			Some(async move {
+				let pipeline = render::create_pipeline_async(&device, "CursorRenderer", || {
+					cursor_renderer.create_pipeline(format, sample_count, depth_stencil_state)
 				})
-				.multisample(wgpu::MultisampleState {
-					count: MULTISAMPLE_COUNT,
-					..Default::default()
+				.await
+				.ok()?;
+				Some(Arc::new(pipeline))
+			})
+		})
+	};
+
+	let bounds_pipeline = {
+		let device = device.clone();
+		let bounds_renderer = bounds_renderer.clone();
+		let depth_stencil_state = depth_stencil_state.clone();
+		create_local_async_derived(move || {
+			let format = surface_texture_format.get()?;
+			let device = device.clone();
+			let bounds_renderer = bounds_renderer.clone();
+			let depth_stencil_state = depth_stencil_state.clone();
+			Some(async move {
+				let pipeline = render::create_pipeline_async(&device, "BoundsRenderer", || {
+					bounds_renderer.create_pipeline(format, sample_count, depth_stencil_state)
+				})
+				.await
+				.ok()?;
+				Some(Arc::new(pipeline))
+			})
+		})
+	};
+
+	// Guides are drawn in a pass of their own, directly to the surface.
+	let guides_pipeline = {
+		let device = device.clone();
+		let guides_renderer = guides_renderer.clone();
+		create_local_async_derived(move || {
+			let format = surface_texture_format.get()?;
+			let device = device.clone();
+			let guides_renderer = guides_renderer.clone();
+			Some(async move {
+				let pipeline = render::create_pipeline_async(&device, "GuidesRenderer", || {
+					guides_renderer.create_pipeline(format)
+				})
+				.await
+				.ok()?;
+				Some(Arc::new(pipeline))
+			})
+		})
+	};
+
+	let stroke_tip_pipeline = {
+		let device = device.clone();
+		let stroke_tip_renderer = stroke_tip_renderer.clone();
+		create_local_async_derived(move || {
+			let format = surface_texture_format.get()?;
+			let device = device.clone();
+			let stroke_tip_renderer = stroke_tip_renderer.clone();
+			let depth_stencil_state = depth_stencil_state.clone();
+			Some(async move {
+				let pipeline = render::create_pipeline_async(&device, "StrokeTipRenderer", || {
+					stroke_tip_renderer.create_pipeline(format, sample_count, depth_stencil_state)
 				})
-				.get();
-			Some(Arc::new(pipeline))
+				.await
+				.ok()?;
+				Some(Arc::new(pipeline))
+			})
 		})
 	};
 
+	// The multisampled texture is destroyed as soon as it is replaced or the canvas is unmounted,
+	// rather than waiting for garbage collection.
+	let surface_texture = StoredValue::new_local(None::<wgpu::Texture>);
+	on_cleanup(move || {
+		if let Some(Some(texture)) = surface_texture.try_update_value(Option::take) {
+			texture.destroy();
+		}
+	});
 	let surface_texture_view = {
 		let device = context.device().clone();
 		create_local_derived(move || {
+			if sample_count == 1 {
+				return None;
+			}
 			let size = surface_texture_size.get()?;
-			Some(Arc::new(
-				render::texture()
-					.label("Canvas::surface_texture")
-					.width(size.0)
-					.height(size.1)
-					.sample_count(MULTISAMPLE_COUNT)
-					.format(surface_texture_format.get()?)
-					.usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
-					.create(&device)
-					.create_view(&wgpu::TextureViewDescriptor::default()),
-			))
+			let texture = render::texture()
+				.label("Canvas::surface_texture")
+				.width(size.0)
+				.height(size.1)
+				.sample_count(sample_count)
+				.format(surface_texture_format.get()?)
+				.usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+				.create(&device);
+			let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+			if let Some(Some(previous)) =
+				surface_texture.try_update_value(|t| t.replace(texture))
+			{
+				previous.destroy();
+			}
+			Some(Arc::new(view))
 		})
 	};
 
-	let canvas_to_screen = RwSignal::new(Mat4::from_scale_rotation_translation(
-		Vec3::new(1.0, 1.0, 1.0),
-		Quat::IDENTITY,
-		Vec3::new(-0.0, -0.0, 0.0),
-	));
+	// Likewise for the depth/stencil texture, which matches the surface's size and sample count.
+	let depth_stencil_texture = StoredValue::new_local(None::<wgpu::Texture>);
+	on_cleanup(move || {
+		if let Some(Some(texture)) = depth_stencil_texture.try_update_value(Option::take) {
+			texture.destroy();
+		}
+	});
+	let depth_stencil_view = {
+		let device = context.device().clone();
+		create_local_derived(move || {
+			if !depth_stencil {
+				return None;
+			}
+			let size = surface_texture_size.get()?;
+			let texture = render::texture()
+				.label("Canvas::depth_stencil_texture")
+				.width(size.0)
+				.height(size.1)
+				.sample_count(sample_count)
+				.format(CANVAS_DEPTH_STENCIL_FORMAT)
+				.usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+				.create(&device);
+			let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+			if let Some(Some(previous)) =
+				depth_stencil_texture.try_update_value(|t| t.replace(texture))
+			{
+				previous.destroy();
+			}
+			Some(Arc::new(view))
+		})
+	};
 
-	// This is the mapping from normalized device coordinates to framebuffer coordinates.
-	// Equivalently, it transforms `@builtin(position)` from the vertex to the fragment shader.
-	let view_to_screen = create_local_derived(move || {
-		let scale = 0.5 * vec2(width.get() as f32, height.get() as f32);
-		let scale = vec3(scale.x, scale.y, 1.0);
-		Mat4::from_scale(scale)
-			* Mat4::from_translation(vec3(1.0, 1.0, 0.0))
-			* Mat4::from_scale(vec3(1.0, -1.0, 1.0))
+	let view_transform = document.view_transform;
+	view_transform.update(|v| *v = v.with_zoom_limits(zoom_limits));
+
+	let canvas_view = create_local_derived(move || {
+		CanvasRenderer::canvas_view(&view_transform.get(), dvec2(width.get(), height.get()))
 	});
 
-	let screen_to_view = create_local_derived(move || view_to_screen.get().inverse());
+	let screen_center = move || 0.5 * dvec2(width.get_untracked(), height.get_untracked());
+
+	// The outline of the selection in canvas coordinates, including one being dragged out.
+	let selection_outline = RwSignal::new(
+		atlas
+			.read()
+			.unwrap()
+			.selection()
+			.shape()
+			.map(SelectionShape::outline),
+	);
+	let set_selection = {
+		let atlas = atlas.clone();
+		move |shape: Option<SelectionShape>| {
+			selection_outline.set(shape.as_ref().map(SelectionShape::outline));
+			let mut atlas = atlas.write().unwrap();
+			match shape {
+				Some(shape) => atlas.selection_mut().select(shape),
+				None => atlas.selection_mut().clear(),
+			}
+		}
+	};
 
-	let canvas_to_view = create_local_derived(move || screen_to_view.get() * canvas_to_screen.get());
+	// Evicted charts the view is heading towards are uploaded again a few at a time, so panning
+	// quickly across a large document doesn't stall as they come into view.
+	let view_motion = StoredValue::new_local(ViewMotion::default());
+	{
+		let atlas = atlas.clone();
+		Effect::new(move |_| {
+			let view = view_transform.get();
+			let screen_size = dvec2(width.get_untracked(), height.get_untracked());
+			let center = ScreenPx(0.5 * screen_size).to_canvas(&view).0;
+			view_motion.update_value(|m| m.record(util::performance_now(), center));
+			let offset = view_motion.with_value(|m| m.predicted_offset(PREFETCH_LOOKAHEAD));
+			if offset == DVec2::ZERO {
+				return;
+			}
+			let corners = [
+				DVec2::ZERO,
+				screen_size.with_y(0.0),
+				screen_size,
+				screen_size.with_x(0.0),
+			];
+			let predicted = corners.map(|c| (ScreenPx(c).to_canvas(&view).0 + offset).as_vec2());
+			let region = AABox::containing(predicted.into_iter());
+			let prefetched = atlas
+				.read()
+				.unwrap()
+				.prefetch(region, MAX_PREFETCHED_CHARTS);
+			if prefetched > 0 {
+				tracing::debug!(prefetched, "prefetched charts");
+			}
+		});
+	}
+
+	// The wheel eases the zoom towards where it has been scrolled to, rather than jumping each step.
+	let zoom_ease = StoredValue::new_local(None::<ZoomEase>);
+	let zoom_ease_loop = util::use_animation_frame_loop(move || {
+		let Some(Some(mut ease)) = zoom_ease.try_get_value() else {
+			return false;
+		};
+		let mut view = view_transform.get_untracked();
+		let finished = ease.step(&mut view, util::performance_now());
+		view_transform.set(view);
+		zoom_ease.set_value((!finished).then_some(ease));
+		!finished
+	});
 
-	let screen_to_canvas = create_local_derived(move || canvas_to_screen.get().inverse());
+	// Commands animate the view rather than jumping. Direct manipulation cancels any animation.
+	let view_animation = StoredValue::new_local(None::<ViewAnimation>);
+	let view_animation_loop = util::use_animation_frame_loop(move || {
+		let Some(Some(animation)) = view_animation.try_get_value() else {
+			return false;
+		};
+		let (view, finished) = animation.sample(util::performance_now());
+		view_transform.set(view);
+		if finished {
+			view_animation.set_value(None);
+		}
+		!finished
+	});
+	let animate_view_to = move |target: ViewTransform| {
+		zoom_ease.set_value(None);
+		view_animation.set_value(Some(ViewAnimation::new(
+			view_transform.get_untracked(),
+			target,
+			screen_center(),
+			util::performance_now(),
+			VIEW_ANIMATION_DURATION,
+		)));
+		view_animation_loop();
+	};
+	// Animates the view to frame `bounds`, in canvas coordinates, keeping its rotation.
+	let animate_view_to_frame = {
+		let animate_view_to = animate_view_to.clone();
+		move |bounds: AABox| {
+			let view = view_transform.get_untracked();
+			let (min, max) = (bounds.min().as_dvec2(), bounds.max().as_dvec2());
+			animate_view_to(view.framing(min, max, 2.0 * screen_center(), FRAMING_MARGIN));
+		}
+	};
+	if let Some(view_command) = view_command {
+		let atlas = atlas.clone();
+		let animate_view_to = animate_view_to.clone();
+		Effect::new(move |_| {
+			let Some(command) = view_command.get() else {
+				return;
+			};
+			view_command.set(None);
+			match command {
+				ViewCommand::Reset => {
+					let zoom_limits = view_transform.get_untracked().zoom_limits;
+					animate_view_to(ViewTransform::default().with_zoom_limits(zoom_limits))
+				}
+				// Finding what's drawn reads the charts back, so the view moves once that's done.
+				ViewCommand::FitContent => {
+					let content_bounds = atlas.read().unwrap().content_bounds();
+					let animate_view_to_frame = animate_view_to_frame.clone();
+					leptos::task::spawn_local(async move {
+						if let Some(Some(bounds)) = content_bounds.await.ok_or_log() {
+							animate_view_to_frame(bounds);
+						}
+					});
+				}
+				ViewCommand::FitSelection => {
+					if let Some(bounds) = atlas.read().unwrap().selection().bounds() {
+						animate_view_to_frame(bounds);
+					}
+				}
+				ViewCommand::GoTo(view) => {
+					let zoom_limits = view_transform.get_untracked().zoom_limits;
+					animate_view_to(view.with_zoom_limits(zoom_limits))
+				}
+			}
+		});
+	}
 
+	// Every edit notifies `edit_trigger`, but the canvas is only redrawn for edits to charts in view.
+	// Charts out of view are drawn as they are once the view moves to them.
+	let edit_trigger = ArcTrigger::new();
 	let redraw_trigger = ArcTrigger::new();
+	let notify_edit = {
+		let edit_trigger = edit_trigger.clone();
+		let redraw_trigger = redraw_trigger.clone();
+		move |atlas: &mut Atlas| {
+			edit_trigger.notify();
+			let Some((changed_min, changed_max)) = atlas.take_changed_region() else {
+				return;
+			};
+			let visible = CanvasRenderer::visible_charts(&canvas_view.get_untracked());
+			let in_view = visible.map_or(true, |(min, max)| {
+				changed_min.0 <= max.0
+					&& min.0 <= changed_max.0
+					&& changed_min.1 <= max.1
+					&& min.1 <= changed_max.1
+			});
+			if in_view {
+				redraw_trigger.notify();
+			}
+		}
+	};
+
+	// Let tools outside the canvas observe edits.
+	if let Some(atlas_handle) = atlas_handle {
+		let edit_trigger = edit_trigger.clone();
+		Effect::new(move |_| {
+			edit_trigger.track();
+			atlas_handle.notify();
+		});
+
+		// Don't keep the atlas alive once the canvas is gone, unless another canvas has taken over
+		// the handle, so a closed document's memory is released.
+		let weak_atlas = Arc::downgrade(&atlas);
+		on_cleanup(move || {
+			let is_ours = atlas_handle
+				.try_with_untracked(|a| {
+					a.as_ref()
+						.is_some_and(|a| std::ptr::eq(Arc::as_ptr(a), weak_atlas.as_ptr()))
+				})
+				.unwrap_or(false);
+			if is_ours {
+				atlas_handle.try_set(None);
+			}
+		});
+	}
+
+	// Once everything else from the canvas has been dropped, the tile pool should only be alive if
+	// its document is still open.
+	{
+		let tile_pool = atlas.read().unwrap().tile_pool().downgrade();
+		on_cleanup(move || {
+			leptos::task::spawn_local(async move {
+				match tile_pool.stats() {
+					Some(stats) => tracing::debug!(?stats, "canvas unmounted; document kept"),
+					None => tracing::debug!("canvas unmounted; document memory released"),
+				}
+			});
+		});
+	}
+
+	let history = document.history.clone();
+	let action_log = document.action_log.clone();
+	let vector_drawing = document.vector_drawing.clone();
+	let checkpoints = document.checkpoints.clone();
+	let timelapse = document.timelapse.clone();
+	let record_vector_edit = {
+		let document = document.clone();
+		move |edit: VectorEdit| document.record_vector_edit(edit)
+	};
+
+	// Once an edit is finished, charts it didn't touch may be moved out of GPU memory. This mostly
+	// affects charts only kept for undo.
+	let evict_charts = {
+		let tile_pool = atlas.read().unwrap().tile_pool().clone();
+		move || {
+			let evict = tile_pool.evict();
+			leptos::task::spawn_local(async move {
+				if let Some(evicted @ 1..) = evict.await.ok_or_log() {
+					tracing::debug!(evicted, "evicted charts");
+				}
+			});
+		}
+	};
+
+	// Undo, redo, and zoom are announced for screen readers, where the page provides an announcer.
+	let announcer = use_context::<Announcer>();
+
+	// Edits needing more charts than the GPU can hold are only drawn where there is room, and the
+	// user is told how to carry on.
+	let too_large = document.too_large;
+	let report_exhausted = {
+		let evict_charts = evict_charts.clone();
+		move |result: Result<(), PoolExhausted>| {
+			let Err(error) = result else {
+				return;
+			};
+			// Evicting charts only kept for undo may make room for the next edit.
+			evict_charts();
+			if too_large.get_untracked().is_some() {
+				return;
+			}
+			tracing::error!(%error, "document too large for this GPU");
+			if let Some(announcer) = announcer {
+				announcer.announce("Document too large for this GPU; part of the edit wasn't drawn");
+			}
+			too_large.set(Some(error));
+		}
+	};
+
+	if let Some(mode) = document.take_autosave_mode() {
+		let atlas = atlas.clone();
+		let notify_edit = notify_edit.clone();
+		let record_vector_edit = record_vector_edit.clone();
+		let report_exhausted = report_exhausted.clone();
+		use_autosave(atlas.clone(), mode, move |restored| {
+			// Autosaves only hold pixels.
+			record_vector_edit(VectorEdit::Raster);
+			notify_edit(&mut atlas.write().unwrap());
+			report_exhausted(restored);
+		});
+	}
+
+	// Imported images are centered on `center`, in canvas coordinates.
+	let import_image = {
+		let atlas = atlas.clone();
+		let history = history.clone();
+		let record_vector_edit = record_vector_edit.clone();
+		let resources = resources.clone();
+		let notify_edit = notify_edit.clone();
+		let evict_charts = evict_charts.clone();
+		let report_exhausted = report_exhausted.clone();
+		move |file: web_sys::File, center: CanvasPt| {
+			let atlas = atlas.clone();
+			let history = history.clone();
+			let record_vector_edit = record_vector_edit.clone();
+			let resources = resources.clone();
+			let notify_edit = notify_edit.clone();
+			let evict_charts = evict_charts.clone();
+			let report_exhausted = report_exhausted.clone();
+			leptos::task::spawn_local(async move {
+				let Some(encoded) = util::read_file(&file).await.ok_or_log() else {
+					return;
+				};
+				let Some(image) = ImportedImage::decode(&encoded).ok_or_log() else {
+					return;
+				};
+				let origin = (center.0 - 0.5 * image.size().as_dvec2())
+					.round()
+					.as_ivec2();
+				let drawn = {
+					let mut atlas = atlas.write().unwrap();
+					let mut history = history.borrow_mut();
+					history.begin_edit();
+					history.record(&atlas, image.chart_keys(origin));
+					let drawn = atlas.draw_image(&resources, &image, origin);
+					history.end_edit();
+					notify_edit(&mut atlas);
+					drawn
+				};
+				record_vector_edit(VectorEdit::Raster);
+				evict_charts();
+				report_exhausted(drawn);
+			});
+		}
+	};
+	let file_input = NodeRef::<leptos::html::Input>::new();
+
+	// The selection lifted off of the canvas with T, while it is moved, scaled, and rotated. Enter
+	// stamps it back where it is placed, and Escape puts it back where it was.
+	let floating = StoredValue::new_local(None::<FloatingSelection>);
+	// The corners of the floating selection's bounds, in canvas coordinates, and the handle being
+	// dragged with the canvas point it was last dragged to.
+	let floating_corners = RwSignal::new(None::<[DVec2; 4]>);
+	let transforming = StoredValue::new_local(None::<(TransformHandle, DVec2)>);
+	// A document outlives its canvas, so it isn't left with the selection cut out.
+	{
+		let atlas = atlas.clone();
+		on_cleanup(move || {
+			if let Some(Some(lifted)) = floating.try_update_value(Option::take) {
+				lifted.cancel(&mut atlas.write().unwrap());
+			}
+		});
+	}
+	let update_floating = {
+		let redraw_trigger = redraw_trigger.clone();
+		move || {
+			let placed = floating.with_value(|floating| {
+				let floating = floating.as_ref()?;
+				Some((floating.corners(), floating.placed_shape().outline()))
+			});
+			floating_corners.set(placed.as_ref().map(|(corners, _)| *corners));
+			if let Some((_, outline)) = placed {
+				selection_outline.set(Some(outline));
+			}
+			redraw_trigger.notify();
+		}
+	};
+	let lift_selection = {
+		let atlas = atlas.clone();
+		let history = history.clone();
+		let resources = resources.clone();
+		let notify_edit = notify_edit.clone();
+		let report_exhausted = report_exhausted.clone();
+		let update_floating = update_floating.clone();
+		move || {
+			if floating.with_value(Option::is_some) || history.borrow().is_editing() {
+				return;
+			}
+			let lifted = {
+				let mut atlas = atlas.write().unwrap();
+				let lifted = FloatingSelection::lift(&resources, &mut atlas);
+				notify_edit(&mut atlas);
+				lifted
+			};
+			match lifted {
+				Ok(Some(lifted)) => {
+					floating.set_value(Some(lifted));
+					update_floating();
+					if let Some(announcer) = announcer {
+						announcer.announce("Transforming the selection; Enter applies, Escape cancels");
+					}
+				}
+				Ok(None) => {}
+				Err(error) => report_exhausted(Err(error)),
+			}
+		}
+	};
+	let commit_floating = {
+		let atlas = atlas.clone();
+		let history = history.clone();
+		let record_vector_edit = record_vector_edit.clone();
+		let resources = resources.clone();
+		let notify_edit = notify_edit.clone();
+		let evict_charts = evict_charts.clone();
+		let report_exhausted = report_exhausted.clone();
+		let set_selection = set_selection.clone();
+		let update_floating = update_floating.clone();
+		move || {
+			let Some(Some(lifted)) = floating.try_update_value(Option::take) else {
+				return;
+			};
+			transforming.set_value(None);
+			let placed = lifted.placed_shape();
+			let committed = {
+				let mut atlas = atlas.write().unwrap();
+				let committed = lifted.commit(&resources, &mut atlas, &mut history.borrow_mut());
+				notify_edit(&mut atlas);
+				committed
+			};
+			// The selection moves with what it selected.
+			set_selection(Some(placed));
+			update_floating();
+			record_vector_edit(VectorEdit::Raster);
+			evict_charts();
+			report_exhausted(committed);
+		}
+	};
+	let cancel_floating = {
+		let atlas = atlas.clone();
+		let notify_edit = notify_edit.clone();
+		let update_floating = update_floating.clone();
+		move || {
+			let Some(Some(lifted)) = floating.try_update_value(Option::take) else {
+				return false;
+			};
+			transforming.set_value(None);
+			let mut atlas = atlas.write().unwrap();
+			lifted.cancel(&mut atlas);
+			notify_edit(&mut atlas);
+			selection_outline.set(atlas.selection().shape().map(SelectionShape::outline));
+			update_floating();
+			true
+		}
+	};
+
+	// The airbrush is created once its pipelines have compiled, so that the first stroke doesn't
+	// stall on them. Strokes aren't started until then.
+	let airbrush = std::rc::Rc::new(std::cell::RefCell::new(None::<Airbrush>));
+	let airbrush_ready = RwSignal::new(false);
+	{
+		let context = context.clone();
+		let resources = resources.clone();
+		let airbrush = airbrush.clone();
+		leptos::task::spawn_local(async move {
+			let created =
+				Airbrush::new_async(context.device(), context.queue(), &resources, canvas_format).await;
+			if let Ok(created) = created {
+				*airbrush.borrow_mut() = Some(created);
+				airbrush_ready.try_set(true);
+			}
+		});
+	}
+
+	// Edits older than the history remembers are undone and redone by restoring the canvas from a
+	// checkpoint and replaying the strokes since, after `vector_drawing` is stepped by `action`.
+	// Returns whether that worked, stepping it back if not.
+	let restore_checkpoint = {
+		let context = context.clone();
+		let airbrush = airbrush.clone();
+		let report_exhausted = report_exhausted.clone();
+		move |atlas: &mut Atlas, vector_drawing: &mut VectorDrawing, action: &LoggedAction| {
+			let Some(airbrush) = &mut *airbrush.borrow_mut() else {
+				return false;
+			};
+			let step = |vector_drawing: &mut VectorDrawing, undo: bool| {
+				if undo {
+					vector_drawing.undo()
+				} else {
+					vector_drawing.redo()
+				}
+			};
+			let undo = matches!(action, LoggedAction::Undo);
+			if !step(vector_drawing, undo) {
+				return false;
+			}
+			let restored =
+				checkpoints
+					.borrow()
+					.restore(context.queue(), atlas, airbrush, vector_drawing);
+			match restored {
+				Ok(replayed) => tracing::debug!(replayed, "restored from a checkpoint"),
+				Err(RestoreError::TooLarge(error)) => report_exhausted(Err(error)),
+				Err(error) => {
+					tracing::warn!(%error, "couldn't restore from a checkpoint");
+					step(vector_drawing, !undo);
+					return false;
+				}
+			}
+			true
+		}
+	};
+
+	if let Some(announcer) = announcer {
+		announcer.announce_changes(
+			move || (view_transform.with(|v| v.zoom) * 100.0).round() as i64,
+			|percent| format!("Zoom {percent}%"),
+			500.0,
+		);
+	}
+
+	// The anchors placed with the pen, which its own key listener finishes or discards.
+	let pen_path = StoredValue::new_local(PenPath::default());
+
+	let keydown = {
+		let set_selection = set_selection.clone();
+		let lift_selection = lift_selection.clone();
+		let commit_floating = commit_floating.clone();
+		let cancel_floating = cancel_floating.clone();
+		let atlas = atlas.clone();
+		let history = history.clone();
+		let action_log = action_log.clone();
+		let vector_drawing = vector_drawing.clone();
+		let notify_edit = notify_edit.clone();
+		move |e: leptos::ev::KeyboardEvent| {
+			// Undo with Ctrl+Z, and redo with Ctrl+Shift+Z or Ctrl+Y. macOS uses Cmd instead.
+			if (e.ctrl_key() || e.meta_key()) && !e.alt_key() {
+				// Import with Ctrl+O.
+				if e.key().to_lowercase() == "o" {
+					if let Some(file_input) = file_input.get_untracked() {
+						file_input.click();
+					}
+					e.prevent_default();
+					return;
+				}
+				// Undoing while transforming puts the selection back where it was.
+				if matches!(e.key().to_lowercase().as_str(), "z" | "y") && cancel_floating() {
+					if let Some(announcer) = announcer {
+						announcer.announce("Transform cancelled");
+					}
+					e.prevent_default();
+					return;
+				}
+				let mut history = history.borrow_mut();
+				let mut atlas = atlas.write().unwrap();
+				let mut vector_drawing = vector_drawing.borrow_mut();
+				let (changed, action) = match e.key().to_lowercase().as_str() {
+					"z" if e.shift_key() => (history.redo(&mut atlas), LoggedAction::Redo),
+					"z" => (history.undo(&mut atlas), LoggedAction::Undo),
+					"y" => (history.redo(&mut atlas), LoggedAction::Redo),
+					_ => return,
+				};
+				e.prevent_default();
+				let changed = match (changed, &action) {
+					(true, LoggedAction::Undo) => vector_drawing.undo(),
+					(true, _) => vector_drawing.redo(),
+					(false, _) if history.is_editing() => false,
+					// The history starts afresh from the restored canvas.
+					(false, _) => {
+						let restored = restore_checkpoint(&mut atlas, &mut vector_drawing, &action);
+						if restored {
+							history.clear();
+						}
+						restored
+					}
+				};
+				if let Some(announcer) = announcer {
+					announcer.announce(match (&action, changed) {
+						(LoggedAction::Undo, true) => "Undone",
+						(LoggedAction::Undo, false) => "Nothing to undo",
+						(_, true) => "Redone",
+						(_, false) => "Nothing to redo",
+					});
+				}
+				if changed {
+					action_log.borrow_mut().record(action);
+					notify_edit(&mut atlas);
+				}
+				return;
+			}
+			// Keys typed into a field aren't shortcuts.
+			if e.repeat() || e.alt_key() || is_typing(&e) {
+				return;
+			}
+			let view = view_transform.get_untracked();
+			match e.key().as_str() {
+				// Zoom to 100% about the center of the screen.
+				"1" => {
+					let mut target = view;
+					target.set_zoom_about(screen_center(), 1.0);
+					animate_view_to(target);
+				}
+				// Reset the view.
+				"0" => animate_view_to(ViewTransform::default().with_zoom_limits(view.zoom_limits)),
+				// Reset the rotation with Shift+R, about the center of the screen.
+				"R" => {
+					let mut target = view;
+					target.reset_rotation_about(screen_center());
+					animate_view_to(target);
+				}
+				"t" => lift_selection(),
+				"Enter" | "Escape" if !pen_path.with_value(PenPath::is_empty) => {}
+				"Enter" => commit_floating(),
+				"Escape" => {
+					if !cancel_floating() {
+						set_selection(None);
+					}
+				}
+				_ => {}
+			}
+		}
+	};
+	let keydown_handle = window_event_listener(leptos::ev::keydown, keydown);
+	on_cleanup(move || keydown_handle.remove());
+
+	// While measured, the time from drawing input to each stage of showing it. Each measurement is
+	// also traced, for profiling outside the page.
+	let measure_latency = move || low_latency || latency_hud.get_untracked();
+	let latency_meter = StoredValue::new_local(util::LatencyMeter::default());
+	let latency = RwSignal::new(util::LatencyAverages::default());
+	let record_latency = move |stage: util::LatencyStage, input_time: f64| {
+		let now = util::performance_now();
+		let measured =
+			latency_meter.try_update_value(|m| (m.record(stage, input_time, now), m.averages()));
+		if let Some((measured, averages)) = measured {
+			tracing::debug!(%stage, latency = measured, "input latency");
+			latency.try_set(averages);
+		}
+	};
+
+	// The cursor position in screen pixels, while a mouse or pen is over the canvas, and the pressure
+	// under it. The brush's footprint is outlined there. Moving the cursor redraws wherever the charts
+	// in view are, so it has its own trigger rather than going through `notify_edit`.
+	let cursor_position = StoredValue::new_local(None::<DVec2>);
+	let cursor_pressure = StoredValue::new_local(1.0f32);
+	let cursor_trigger = ArcTrigger::new();
+	// The provisional tip of the stroke being drawn, from the last of its points drawn to where the
+	// pointer is predicted to be, in screen pixels.
+	let stroke_tip = StoredValue::new_local(None::<(StrokePoint, StrokePoint)>);
+	{
+		let cursor_trigger = cursor_trigger.clone();
+		Effect::new(move |_| {
+			brush_size.track();
+			cursor_trigger.notify();
+		});
+	}
+
+	// Captures are recorded by the next frame drawn, so one is drawn even if nothing has changed.
+	if let Some(capture_frame) = capture_frame {
+		let context = context.clone();
+		let redraw_trigger = redraw_trigger.clone();
+		Effect::new(move |_| {
+			if capture_frame.get() {
+				context.frame_capture().capture_next_frame();
+				redraw_trigger.notify();
+			}
+		});
+	}
+
+	// The corners dragged with the crop tool, in canvas coordinates, which are shown as the bounds
+	// until the drag ends.
+	let cropping = StoredValue::new_local(None::<(DVec2, DVec2)>);
+	let bounds = document.bounds;
+	{
+		let edit_trigger = edit_trigger.clone();
+		let redraw_trigger = redraw_trigger.clone();
+		Effect::new(move |_| {
+			bounds.track();
+			crop_tool.track();
+			// Tools outside the canvas export what's within the bounds, so they observe this too.
+			edit_trigger.notify();
+			redraw_trigger.notify();
+		});
+	}
+
+	// Where the guides are placed, and how far their vanishing points are from there, in canvas
+	// coordinates. They're placed about the center of the view once shown.
+	let guide_placement = StoredValue::new(None::<(Vec2, f32)>);
+	{
+		let redraw_trigger = redraw_trigger.clone();
+		Effect::new(move |_| {
+			guides.track();
+			guide_placement.set_value(None);
+			redraw_trigger.notify();
+		});
+	}
+	let current_guides = move || {
+		let kind = guides.get_untracked()?;
+		let (origin, scale) = guide_placement.try_update_value(|placement| {
+			*placement.get_or_insert_with(|| {
+				let view_transform = view_transform.get_untracked();
+				let origin = ScreenPx(screen_center()).to_canvas(&view_transform);
+				let scale = 0.5 * width.get_untracked() / view_transform.zoom;
+				(origin.as_vec2(), scale as f32)
+			})
+		})?;
+		Some(Guides {
+			kind,
+			origin,
+			scale,
+		})
+	};
 
 	let render = {
 		let context = context.clone();
 		let atlas = atlas.clone();
-		let canvas_bind_group = Arc::new(canvas_bind_group);
-		let canvas_to_view_buffer = Arc::new(canvas_to_view_buffer);
+		let canvas_renderer = canvas_renderer.clone();
+		let bounds_renderer = bounds_renderer.clone();
+		let guides_renderer = guides_renderer.clone();
+		let cursor_renderer = cursor_renderer.clone();
+		let stroke_tip_renderer = stroke_tip_renderer.clone();
+		let floating_renderer = floating_renderer.clone();
 		let redraw_trigger = redraw_trigger.clone();
+		let cursor_trigger = cursor_trigger.clone();
 		create_local_derived(move || {
 			let context = context.clone();
 			redraw_trigger.track();
+			cursor_trigger.track();
+			pixel_grid.track();
 			let atlas = atlas.clone();
-			let canvas_bind_group = canvas_bind_group.clone();
-			let canvas_to_view_buffer = canvas_to_view_buffer.clone();
+			let canvas_renderer = canvas_renderer.clone();
+			let bounds_renderer = bounds_renderer.clone();
+			let guides_renderer = guides_renderer.clone();
+			let cursor_renderer = cursor_renderer.clone();
+			let stroke_tip_renderer = stroke_tip_renderer.clone();
+			let floating_renderer = floating_renderer.clone();
 			let render_pipeline = render_pipeline.get();
-			let canvas_to_view = canvas_to_view.get();
+			let bounds_pipeline = bounds_pipeline.get();
+			let guides_pipeline = guides_pipeline.get();
+			let cursor_pipeline = cursor_pipeline.get();
+			let stroke_tip_pipeline = stroke_tip_pipeline.get();
+			let canvas_view = canvas_view.get();
 			// let background_color = thaw::Theme::use_rw_theme()
 			// 	.with(|theme| color_from_css_string(&theme.color.color_neutral_background_static));
 			let surface_texture_view = surface_texture_view.get();
+			let depth_stencil_view = depth_stencil_view.get();
+			// The background is only transparent if the page will show through it.
+			let background = match surface_configuration.get().map(|c| c.alpha_mode) {
+				Some(wgpu::CompositeAlphaMode::PreMultiplied) => wgpu::Color::TRANSPARENT,
+				_ => wgpu::Color::BLACK,
+			};
 			let callback = move |view: wgpu::TextureView| {
 				let Some(render_pipeline) = &render_pipeline else {
 					return;
 				};
-				let Some(surface_texture_view) = &surface_texture_view else {
+				let (target, resolve_target) = match &surface_texture_view {
+					Some(multisampled) => (&**multisampled, Some(&view)),
+					None if sample_count == 1 => (&view, None),
+					None => return,
+				};
+				if depth_stencil && depth_stencil_view.is_none() {
 					return;
+				}
+
+				// The canvas is clipped to the document's bounds, except while cropping.
+				let document_bounds = atlas.read().unwrap().bounds();
+				let clipped_view = match document_bounds {
+					Some(bounds) if !crop_tool.get_untracked() => {
+						CanvasRenderer::bounded_view(&canvas_view, bounds)
+					}
+					_ => canvas_view.clone(),
+				};
+				let clipped_view = if pixel_grid.get_untracked() {
+					CanvasRenderer::pixel_grid_view(&clipped_view)
+				} else {
+					clipped_view
+				};
+				canvas_renderer.write_view(context.queue(), &clipped_view);
+
+				// The bounds being dragged are shown in place of the document's.
+				let shown_bounds = cropping
+					.try_get_value()
+					.flatten()
+					.and_then(|(from, to)| DocumentBounds::spanning(from, to))
+					.or(document_bounds);
+				let bounds_pipeline = match (shown_bounds, &bounds_pipeline) {
+					(Some(bounds), Some(bounds_pipeline)) => {
+						let bounds = BoundsRenderer::bounds(
+							&view_transform.get_untracked(),
+							bounds,
+							OUTSIDE_BOUNDS_OPACITY,
+						);
+						bounds_renderer.write_bounds(context.queue(), &bounds);
+						Some(bounds_pipeline)
+					}
+					_ => None,
 				};
 
-				canvas_to_view_buffer.write(context.queue(), canvas_to_view);
+				let guides_pipeline = match (current_guides(), &guides_pipeline) {
+					(Some(guides), Some(guides_pipeline)) => {
+						let overlay = GuidesRenderer::overlay(
+							&view_transform.get_untracked(),
+							&guides,
+							GUIDE_OPACITY,
+						);
+						guides_renderer.write_overlay(context.queue(), &overlay);
+						Some(guides_pipeline)
+					}
+					_ => None,
+				};
+
+				// A floating selection is drawn over the canvas where it is placed.
+				let floating_view = floating
+					.try_with_value(|floating| Some(floating.as_ref()?.affine()))
+					.flatten()
+					.map(|affine| CanvasRenderer::transformed_view(&canvas_view, affine));
+				if let Some(floating_view) = &floating_view {
+					floating_renderer.write_view(context.queue(), floating_view);
+				}
+
+				let position = cursor_position.try_get_value().flatten();
+				let cursor_pipeline = match (position, &cursor_pipeline) {
+					(Some(position), Some(cursor_pipeline)) => {
+						let pressure = cursor_pressure.try_get_value().unwrap_or(1.0);
+						let cursor = CursorRenderer::cursor(
+							&view_transform.get_untracked(),
+							dvec2(width.get_untracked(), height.get_untracked()),
+							position,
+							brush_size.get_untracked() * pressure as f64,
+							1.0,
+						);
+						cursor_renderer.write_cursor(context.queue(), &cursor);
+						Some(cursor_pipeline)
+					}
+					_ => None,
+				};
+
+				let tip = stroke_tip.try_get_value().flatten();
+				let stroke_tip_pipeline = match (tip, &stroke_tip_pipeline) {
+					(Some((start, end)), Some(stroke_tip_pipeline)) => {
+						let tip = StrokeTipRenderer::tip(
+							&view_transform.get_untracked(),
+							dvec2(width.get_untracked(), height.get_untracked()),
+							start,
+							end,
+							brush_size.get_untracked(),
+							brush_color.get_untracked(),
+							brush_opacity.get_untracked() as f32,
+						);
+						stroke_tip_renderer.write_tip(context.queue(), &tip);
+						Some(stroke_tip_pipeline)
+					}
+					_ => None,
+				};
 
 				let mut encoder =
 					context
-						.device()
+						.profiler()
 						.create_command_encoder(&wgpu::CommandEncoderDescriptor {
 							label: Some("Render Encoder"),
 						});
 
-				{
+				let capture = context.frame_capture();
+				capture.begin_frame();
+				let mut graph = render::RenderGraph::<render::ProfiledEncoder>::new();
+				graph.add_pass("Render Pass", [], [FRAME], |encoder| {
+					capture.begin_pass("Render Pass");
 					let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
 						label: Some("Render Pass"),
 						color_attachments: &[
 							// This is what @location(0) in the fragment shader targets
 							Some(wgpu::RenderPassColorAttachment {
-								view: &surface_texture_view,
-								resolve_target: Some(&view),
+								view: target,
+								resolve_target,
 								ops: wgpu::Operations {
-									load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+									load: wgpu::LoadOp::Clear(background),
 									store: wgpu::StoreOp::Store,
 								},
 							}),
 						],
+						// Nothing outlives the pass, so neither depth nor stencil is stored.
+						depth_stencil_attachment: depth_stencil_view.as_deref().map(|view| {
+							wgpu::RenderPassDepthStencilAttachment {
+								view,
+								depth_ops: Some(wgpu::Operations {
+									load: wgpu::LoadOp::Clear(1.0),
+									store: wgpu::StoreOp::Discard,
+								}),
+								stencil_ops: Some(wgpu::Operations {
+									load: wgpu::LoadOp::Clear(0),
+									store: wgpu::StoreOp::Discard,
+								}),
+							}
+						}),
 						..Default::default()
 					});
-					render_pass.set_pipeline(&render_pipeline);
-					canvas_bind_group.set(&mut render_pass);
+					canvas_renderer.draw(&mut render_pass, render_pipeline, &atlas.read().unwrap());
+					if floating_view.is_some() {
+						floating.with_value(|floating| {
+							if let Some(floating) = floating {
+								floating_renderer.draw_floating(
+									&mut render_pass,
+									render_pipeline,
+									floating,
+									capture,
+								);
+							}
+						});
+					}
+					if let Some(bounds_pipeline) = bounds_pipeline {
+						bounds_renderer.draw(&mut render_pass, bounds_pipeline, capture);
+					}
+					if let Some(stroke_tip_pipeline) = stroke_tip_pipeline {
+						stroke_tip_renderer.draw(&mut render_pass, stroke_tip_pipeline, capture);
+					}
+					if let Some(cursor_pipeline) = cursor_pipeline {
+						cursor_renderer.draw(&mut render_pass, cursor_pipeline, capture);
+					}
+				});
+				// Guides are drawn over the resolved canvas, so they're crisp whatever the multisampling.
+				if let Some(guides_pipeline) = guides_pipeline {
+					graph.add_pass("Overlay Pass", [FRAME], [FRAME], |encoder| {
+						capture.begin_pass("Overlay Pass");
+						let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+							label: Some("Overlay Pass"),
+							color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+								view: &view,
+								resolve_target: None,
+								ops: wgpu::Operations {
+									load: wgpu::LoadOp::Load,
+									store: wgpu::StoreOp::Store,
+								},
+							})],
+							..Default::default()
+						});
+						guides_renderer.draw(&mut render_pass, guides_pipeline, capture);
+					});
+				}
+				graph.encode(&mut encoder).ok_or_log();
+				encoder.submit(context.queue());
+				render::upload_metrics().end_frame();
+				if let Some(frame) = capture.end_frame() {
+					if let Some(captured_frame) = captured_frame {
+						captured_frame.set(Some(frame));
+					}
+					if let Some(capture_frame) = capture_frame {
+						capture_frame.set(false);
+					}
+				}
 
-					let atlas = atlas.read().unwrap();
-					// TODO: Only render the visible tiles.
-					let charts: Vec<_> = atlas.charts().collect();
-					let tiles: Vec<_> = charts.iter().map(|c| c.tile()).collect();
-					draw_tiles(&mut render_pass, 0..4, &tiles);
+				let input_time = latency_meter
+					.try_update_value(|m| m.take_pending())
+					.flatten();
+				if let Some(input_time) = input_time {
+					record_latency(util::LatencyStage::Submitted, input_time);
+					context.queue().on_submitted_work_done(move || {
+						record_latency(util::LatencyStage::Finished, input_time);
+						// The browser presents the frame before running the next frame's callbacks.
+						request_animation_frame(move || {
+							record_latency(util::LatencyStage::Presented, input_time)
+						});
+					});
 				}
-				context.queue().submit([encoder.finish()]);
 			};
 			Callback::new(callback)
 		})
 	};
 
-	let airbrush = Airbrush::new(
-		context.device(),
-		context.queue(),
-		&resources,
-		canvas_texture_format,
-	);
-	let airbrush = std::rc::Rc::new(std::cell::RefCell::new(airbrush));
-
-	let draw = {
+	{
 		let context = context.clone();
+		let resources = resources.clone();
+		let airbrush = airbrush.clone();
+		Effect::new(move |_| {
+			if !airbrush_ready.get() {
+				return;
+			}
+			if let Some(shape) = brush_shape.get() {
+				if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+					airbrush.set_shape(
+						context.device(),
+						context.queue(),
+						&resources,
+						&shape,
+						stamp_profile.get(),
+					);
+				}
+			}
+		});
+	}
+	let loading = move || {
+		!airbrush_ready.get()
+			|| render_pipeline.with(Option::is_none)
+			|| cursor_pipeline.with(Option::is_none)
+	};
+
+	// Limits how much of a stroke is drawn each frame, so that a huge brush dragged quickly can't
+	// stall a weak GPU.
+	let workload_budget =
+		StoredValue::new_local(WorkloadBudget::for_adapter(&context.adapter().get_info()));
+	// Draws the segments batched since the last frame, with a single submission.
+	let draw_batch = {
 		let atlas = atlas.clone();
-		move |drawable: AirbrushDrawable| {
+		let history = history.clone();
+		let airbrush = airbrush.clone();
+		let report_exhausted = report_exhausted.clone();
+		move || {
+			let mut airbrush = airbrush.borrow_mut();
+			let Some(drawable) = airbrush.as_mut().and_then(Airbrush::take_batch) else {
+				return;
+			};
 			let mut atlas = atlas.write().unwrap();
+			history
+				.borrow_mut()
+				.record(&atlas, drawable.get_chart_keys());
+			let drawn = atlas.draw(&drawable);
+			notify_edit(&mut atlas);
+			report_exhausted(drawn);
+		}
+	};
 
-			let mut encoder =
-				context
-					.device()
-					.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-						label: Some("Drawing Encoder"),
-					});
-
-			// Find the minimal set of tiles to write to.
-			for chart_key in drawable.get_chart_keys() {
-				let chart = atlas.get_chart_mut(chart_key);
-				let view = chart.tile().texture_view();
-				let chart_bind_group = chart.tile().write_bind_group();
-
-				{
-					let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-						label: Some("Drawing Pass"),
-						color_attachments: &[
-							// This is what @location(0) in the fragment shader targets
-							Some(wgpu::RenderPassColorAttachment {
-								view: &view,
-								resolve_target: None,
-								ops: wgpu::Operations {
-									load: wgpu::LoadOp::Load,
-									store: wgpu::StoreOp::Store,
-								},
-							}),
-						],
-						..Default::default()
-					});
-					chart_bind_group.set(&mut render_pass);
-					drawable.draw(&mut render_pass);
+	// Batches a segment to a point of the stabilized stroke, in screen pixels.
+	let drag = {
+		let airbrush = airbrush.clone();
+		let context = context.clone();
+		let draw_batch = draw_batch.clone();
+		move |point: util::input_interpolate::InputPoint, tilt: Vec2| {
+			let view = view_transform.get_untracked();
+			let position = ScreenPx::new(point.x as f64, point.y as f64)
+				.to_canvas(&view)
+				.0;
+			// Tilt is reported relative to the screen, so undo the view's rotation.
+			let tilt = Vec2::from_angle(-view.rotation as f32).rotate(tilt);
+			let input_point = InputPoint {
+				position,
+				pressure: point.pressure,
+				color: brush_color.get_untracked(),
+				size: brush_size.get_untracked() as f32,
+				opacity: brush_opacity.get_untracked() as f32,
+				rate: brush_rate.get_untracked() as f32,
+				tilt,
+			};
+			let is_batch_full = {
+				let mut airbrush = (*airbrush).borrow_mut();
+				let Some(airbrush) = airbrush.as_mut() else {
+					return;
+				};
+				if let Some(segment) = airbrush.drag_batched(context.queue(), input_point) {
+					workload_budget.update_value(|b| b.spend(segment.estimated_fragments()));
 				}
+				airbrush.is_batch_full()
+			};
+			if is_batch_full {
+				draw_batch();
+			}
+		}
+	};
+	// Stabilized points waiting to be drawn, with the tilt they were reported with, and the stroke's
+	// spacing before any degradation.
+	let pending_points = StoredValue::new_local(VecDeque::<(StrokePoint, Vec2)>::new());
+	let stroke_spacing = StoredValue::new_local(DEFAULT_BRUSH_SPACING);
+	// Batches pending points until the frame's budget runs out, or batches and draws all of them if
+	// `flush`. Returns whether any are left. While drawing falls behind, the brush's spacing widens to
+	// catch up.
+	let draw_pending = {
+		let airbrush = airbrush.clone();
+		let draw_batch = draw_batch.clone();
+		move |flush: bool| -> bool {
+			workload_budget.update_value(|b| b.update(util::performance_now()));
+			let spacing = workload_budget.with_value(|b| b.spacing(stroke_spacing.get_value()));
+			if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+				airbrush.set_spacing(spacing);
+			}
+			loop {
+				if !flush && workload_budget.with_value(WorkloadBudget::is_exhausted) {
+					return pending_points.with_value(|pending| !pending.is_empty());
+				}
+				let Some(Some((point, tilt))) = pending_points.try_update_value(VecDeque::pop_front)
+				else {
+					if flush {
+						draw_batch();
+						workload_budget.update_value(|b| b.end_frame(false));
+					}
+					return false;
+				};
+				drag(point, tilt);
 			}
-			context.queue().submit(std::iter::once(encoder.finish()));
-			redraw_trigger.notify();
 		}
 	};
+	// Each frame draws whatever was batched since the last, rather than submitting each pointer
+	// event's segments separately.
+	let draw_pending_loop = util::use_animation_frame_loop({
+		let draw_pending = draw_pending.clone();
+		move || {
+			let behind = draw_pending(false);
+			draw_batch();
+			workload_budget.update_value(|b| b.end_frame(behind));
+			behind
+		}
+	});
+	let stabilizer = StoredValue::new_local(util::input_interpolate::Stabilizer::new(0.0));
+	let stroke_trace = RwSignal::new(util::StrokeTrace::new(STROKE_TRACE_DURATION));
+	// The stroke being drawn, as the pointer reported it and as the stabilizer fit it, logged and
+	// added to the vector drawing once it ends.
+	let logged_stroke = StoredValue::new_local(None::<LoggedStroke>);
+	let vector_stroke = StoredValue::new_local(None::<VectorStroke>);
+	let record_segments = move |tilt: Vec2| {
+		let segments = stabilizer
+			.try_update_value(|s| s.take_segments())
+			.unwrap_or_default();
+		let view = view_transform.get_untracked();
+		vector_stroke.update_value(|stroke| {
+			if let Some(stroke) = stroke {
+				stroke.segments.extend(
+					segments
+						.into_iter()
+						.map(|segment| VectorSegment::from_screen(segment, &view, tilt)),
+				);
+			}
+		});
+	};
+	// Frames are captured once a stroke's draws are submitted, and added once they are read back.
+	let capture_timelapse = {
+		let context = context.clone();
+		let resources = resources.clone();
+		let atlas = atlas.clone();
+		move || {
+			let now = util::performance_now();
+			let due = timelapse
+				.borrow_mut()
+				.as_mut()
+				.is_some_and(|recorder| recorder.stroke_finished(now));
+			if !due {
+				return;
+			}
+			let Some(frame) = capture_timelapse_frame(&context, &resources, &atlas.read().unwrap())
+			else {
+				return;
+			};
+			let timelapse = timelapse.clone();
+			leptos::task::spawn_local(async move {
+				if let Some(frame) = frame.await.ok_or_log() {
+					// Recording may have stopped in the meantime.
+					if let Some(recorder) = timelapse.borrow_mut().as_mut() {
+						recorder.push_frame(frame);
+					}
+				}
+			});
+		}
+	};
+	let log_stroke = move || {
+		capture_timelapse();
+		if let Some(Some(stroke)) = logged_stroke.try_update_value(Option::take) {
+			action_log.borrow_mut().record(LoggedAction::Stroke(stroke));
+		}
+		if let Some(Some(stroke)) = vector_stroke.try_update_value(Option::take) {
+			record_vector_edit(VectorEdit::Stroke(stroke));
+		}
+	};
+	// Records what the stabilizer was given, if anything, and what it made of it for the stroke graph.
+	let trace_stroke = move |raw: Option<StrokePoint>, fitted: &[StrokePoint]| {
+		if stroke_graph.get_untracked() {
+			stroke_trace.update(|trace| {
+				if let Some(raw) = raw {
+					trace.record_raw(raw);
+				}
+				trace.record_fitted(fitted.iter().copied());
+			});
+		}
+	};
+
+	// What is under a point on screen, in screen pixels. Strokes are only looked for if `strokes`,
+	// since that searches the whole vector drawing.
+	let pick_at = {
+		let atlas = atlas.clone();
+		let vector_drawing = vector_drawing.clone();
+		move |position: ScreenPx, strokes: bool| {
+			let vector_drawing = vector_drawing.borrow();
+			engine::pick(
+				&view_transform.get_untracked(),
+				&atlas.read().unwrap(),
+				strokes.then_some(&*vector_drawing),
+				position,
+			)
+		}
+	};
+	// What the pick overlay shows, updated as the pointer moves while it is shown.
+	let picked = RwSignal::new(None::<PickResult>);
 
+	// Sets the brush color to the color of the canvas under a point, in screen pixels. Transparent
+	// pixels have no color to sample.
+	let sample_color = {
+		let atlas = atlas.clone();
+		let pick_at = pick_at.clone();
+		move |position: ScreenPx| {
+			let point = pick_at(position, false).point.as_vec2();
+			let sample = atlas.read().unwrap().sample(point);
+			leptos::task::spawn_local(async move {
+				if let Some(color) = sample.await.ok_or_log() {
+					if color.w > 0.0 {
+						brush_color.try_set(color.xyz());
+					}
+				}
+			});
+		}
+	};
 	let touchstart = move |e: leptos::ev::TouchEvent| {
 		e.prevent_default();
 	};
 
-	let keys: KeyboardState = expect_context();
+	// The tools held on by keys in place of the selected one: Space pans, R rotates, Alt samples
+	// colors, and E erases. Each drag keeps the tool it began with.
+	let tool_overrides = StoredValue::new_local(util::ToolOverrides::default());
+	let tool_keydown = move |e: leptos::ev::KeyboardEvent| {
+		if e.repeat() || e.ctrl_key() || e.meta_key() || is_typing(&e) {
+			return;
+		}
+		if let Some(tool) = util::TemporaryTool::for_key(&e.key()) {
+			tool_overrides.update_value(|tools| {
+				tools.press(tool);
+			});
+		}
+	};
+	// Keys are released even while typing, so a tool held before focusing a field isn't stuck on.
+	let tool_keyup = move |e: leptos::ev::KeyboardEvent| {
+		if let Some(tool) = util::TemporaryTool::for_key(&e.key()) {
+			tool_overrides.update_value(|tools| {
+				tools.release(tool);
+			});
+		}
+	};
+	let tool_blur = move |_: leptos::ev::FocusEvent| {
+		tool_overrides.update_value(util::ToolOverrides::release_all);
+	};
+	let tool_keydown_handle = window_event_listener(leptos::ev::keydown, tool_keydown);
+	let tool_keyup_handle = window_event_listener(leptos::ev::keyup, tool_keyup);
+	let tool_blur_handle = window_event_listener(leptos::ev::blur, tool_blur);
+	on_cleanup(move || {
+		tool_keydown_handle.remove();
+		tool_keyup_handle.remove();
+		tool_blur_handle.remove();
+	});
+	let active_tool = move || {
+		tool_overrides
+			.try_with_value(util::ToolOverrides::active)
+			.flatten()
+	};
+	// The mode of a stroke beginning now, which erases while the eraser is held.
+	let stroke_mode = move || match active_tool() {
+		Some(util::TemporaryTool::Eraser) => BrushMode::Erase,
+		_ => brush_mode.get_untracked(),
+	};
+
+	// Two fingers pinch and pan rather than draw.
+	let active_pointers = StoredValue::new_local(util::ActivePointers::default());
 
-	let pointermove = {
+	let symmetry_center = StoredValue::new(None::<Vec2>);
+	Effect::new(move |_| {
+		symmetry.track();
+		symmetry_center.set_value(None);
+	});
+	let current_symmetry = move || {
+		let center = symmetry_center.try_update_value(|center| {
+			*center.get_or_insert_with(|| {
+				let view_transform = view_transform.get_untracked();
+				ScreenPx(screen_center())
+					.to_canvas(&view_transform)
+					.as_vec2()
+			})
+		});
+		Symmetry {
+			kind: symmetry.get_untracked(),
+			center: center.unwrap_or_default(),
+		}
+	};
+
+	// The canvas points dragged through while selecting.
+	let selecting = StoredValue::new_local(None::<Vec<Vec2>>);
+	let canvas_point = {
+		let pick_at = pick_at.clone();
+		move |e: &leptos::ev::PointerEvent| pick_at(e.screen_position(), false).point.as_vec2()
+	};
+	// Where shapes and the pen's anchors are placed.
+	let shape_point = {
+		let canvas_point = canvas_point.clone();
+		move |e: &leptos::ev::PointerEvent| {
+			let point = canvas_point(e);
+			if snap_to_pixels.get_untracked() {
+				pixel_center(point)
+			} else {
+				point
+			}
+		}
+	};
+
+	// The canvas points a shape is dragged from and to, and its outline while it is dragged or
+	// while a path is placed with the pen.
+	let shaping = StoredValue::new_local(None::<(Vec2, Vec2)>);
+	let shape_outline = RwSignal::new(None::<Vec<Vec2>>);
+	// Strokes `segments` with the brush, as a single edit.
+	// While a stroke snaps to guides, which it follows. Its input is projected onto that line before
+	// it's stabilized and interpolated.
+	let guide_snap = StoredValue::new_local(None::<GuideSnap>);
+	let snap_to_guide = move |point: StrokePoint| {
+		let Some(guides) = current_guides() else {
+			return point;
+		};
+		let view = view_transform.get_untracked();
+		let snapped = guide_snap.try_update_value(|snap| {
+			let snap = snap.as_mut()?;
+			let screen = ScreenPx(dvec2(point.x as f64, point.y as f64));
+			let snapped = snap.snap(&guides, screen.to_canvas(&view).as_vec2());
+			Some(CanvasPt(snapped.as_dvec2()).to_screen(&view).0)
+		});
+		match snapped.flatten() {
+			Some(snapped) => StrokePoint {
+				x: snapped.x as f32,
+				y: snapped.y as f32,
+				..point
+			},
+			None => point,
+		}
+	};
+	let stroke_segments = {
+		let context = context.clone();
 		let airbrush = airbrush.clone();
-		let mut input_spline_builder: crate::util::input_interpolate::InputSplineBuilder<crate::util::input_interpolate::CubicInterpolator> = Default::default();
+		let history = history.clone();
+		let draw_batch = draw_batch.clone();
+		let log_stroke = log_stroke.clone();
+		let evict_charts = evict_charts.clone();
+		move |segments: Vec<VectorSegment>| {
+			if !airbrush_ready.get_untracked() {
+				return;
+			}
+			let stroke = VectorStroke {
+				color: brush_color.get_untracked(),
+				size: brush_size.get_untracked() as f32,
+				opacity: brush_opacity.get_untracked() as f32,
+				rate: brush_rate.get_untracked() as f32,
+				mode: brush_mode.get_untracked(),
+				spacing: brush_spacing.map_or(DEFAULT_BRUSH_SPACING, |spacing| {
+					spacing.get_untracked() as f32
+				}),
+				scatter: brush_scatter.map_or(DEFAULT_BRUSH_SCATTER, |scatter| {
+					scatter.get_untracked() as f32
+				}),
+				grain: brush_grain.map_or(DEFAULT_BRUSH_GRAIN, |grain| grain.get_untracked() as f32),
+				pickup: brush_pickup
+					.map_or(DEFAULT_BRUSH_PICKUP, |pickup| pickup.get_untracked() as f32),
+				symmetry: current_symmetry(),
+				seed: fastrand::u64(..),
+				segments,
+			};
+			history.borrow_mut().begin_edit();
+			if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+				stroke.start(airbrush, 1.0);
+			}
+			for input in stroke.input_points(1.0) {
+				let is_batch_full = {
+					let mut airbrush = airbrush.borrow_mut();
+					let Some(airbrush) = airbrush.as_mut() else {
+						break;
+					};
+					airbrush.drag_batched(context.queue(), input);
+					airbrush.is_batch_full()
+				};
+				if is_batch_full {
+					draw_batch();
+				}
+			}
+			draw_batch();
+			if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+				airbrush.stop();
+			}
+			history.borrow_mut().end_edit();
+			vector_stroke.set_value(Some(stroke));
+			log_stroke();
+			evict_charts();
+		}
+	};
+
+	// Discards the pen's path whenever it is put down.
+	Effect::new(move |_| {
+		if !pen_tool.get() {
+			pen_path.set_value(PenPath::default());
+			shape_outline.set(None);
+		}
+	});
+	let pen_keydown = {
+		let stroke_segments = stroke_segments.clone();
+		move |e: leptos::ev::KeyboardEvent| {
+			if pen_path.with_value(PenPath::is_empty) || e.ctrl_key() || e.meta_key() || e.alt_key() {
+				return;
+			}
+			// Backspace in a field deletes a character, not an anchor.
+			if is_typing(&e) {
+				return;
+			}
+			let announcement = match e.key().as_str() {
+				"Enter" => {
+					let path = pen_path.try_update_value(std::mem::take);
+					shape_outline.set(None);
+					match path.and_then(|path| path.segments(1.0)) {
+						Some(segments) => {
+							stroke_segments(segments);
+							"Path drawn"
+						}
+						None => "Path discarded",
+					}
+				}
+				"Escape" => {
+					pen_path.set_value(PenPath::default());
+					shape_outline.set(None);
+					"Path discarded"
+				}
+				"Backspace" => {
+					pen_path.update_value(|path| {
+						path.pop();
+					});
+					shape_outline.set(pen_path.with_value(|path| path.outline(None)));
+					"Anchor removed"
+				}
+				_ => return,
+			};
+			e.prevent_default();
+			if let Some(announcer) = announcer {
+				announcer.announce(announcement);
+			}
+		}
+	};
+	let pen_keydown_handle = window_event_listener(leptos::ev::keydown, pen_keydown);
+	on_cleanup(move || pen_keydown_handle.remove());
+
+	let pointermove = {
+		let draw_pending = draw_pending.clone();
+		let sample_color = sample_color.clone();
+		let cursor_trigger = cursor_trigger.clone();
+		let redraw_trigger = redraw_trigger.clone();
+		let pick_at = pick_at.clone();
+		let canvas_point = canvas_point.clone();
+		let shape_point = shape_point.clone();
+		let update_floating = update_floating.clone();
 		move |e: leptos::ev::PointerEvent| {
+			// A finger covers the brush anyway, and can't hover to show it before drawing.
+			if e.pointer_type() != "touch" {
+				cursor_position.set_value(Some(e.screen_position().0));
+				cursor_pressure.set_value(footprint_pressure(&e));
+				cursor_trigger.notify();
+			}
+			if pick_hud.get_untracked() {
+				picked.set(Some(pick_at(e.screen_position(), true)));
+			}
+
 			let button0 = e.buttons() & 1 != 0;
 			let button1 = e.buttons() & 2 != 0;
 			let button2 = e.buttons() & 4 != 0;
 
-			let screen_to_canvas = screen_to_canvas.get_untracked();
-			let input_curve =
-			input_spline_builder.add_point(crate::util::input_interpolate::InputPoint {
-					t: e.time_stamp() as f32 / 1000.0,
-					x: e.offset_x() as f32,
-					y: e.offset_y() as f32,
-					pressure: e.pressure(),
+			let pinch = active_pointers
+				.try_update_value(|p| p.moved(e.pointer_id(), e.screen_position().0))
+				.flatten();
+			if let Some(pinch) = pinch {
+				view_animation.set_value(None);
+				zoom_ease.set_value(None);
+				view_transform.update(|v| {
+					v.pan(pinch.translation);
+					v.zoom_about(pinch.center, pinch.scale);
+					v.rotate_about(pinch.center, pinch.rotation);
 				});
-			// TODO: Add cuves for y and pressure and use them.
-
-			let movement = {
-				let screen_movement = e.pixel_movement();
-				let movement =
-					screen_to_canvas * vec4(screen_movement.x, screen_movement.y, 0f32, 0f32);
-				movement.xy()
-			};
+				return;
+			}
+			if active_pointers.with_value(|p| p.is_gesture()) {
+				return;
+			}
 
-			let position = {
-				let screen_position = e.pixel_position();
-				let position =
-					screen_to_canvas * vec4(screen_position.x, screen_position.y, 0f32, 1f32);
-				position.xy()
-			};
+			let temporary_tool = active_tool();
 
 			// Pan.
-			if (button0 && keys.is_pressed(" ")) || button2 {
-				canvas_to_screen.update(|m| {
-					*m = (*m) * Mat4::from_translation(vec3(movement.x, movement.y, 0.0));
+			if (button0 && temporary_tool == Some(util::TemporaryTool::Pan)) || button2 {
+				view_animation.set_value(None);
+				zoom_ease.set_value(None);
+				view_transform.update(|v| v.pan(e.pixel_movement().as_dvec2()));
+				return;
+			}
+
+			// Rotate about the center of the screen while R is held.
+			if button0 && temporary_tool == Some(util::TemporaryTool::Rotate) {
+				view_animation.set_value(None);
+				zoom_ease.set_value(None);
+				let to = e.screen_position().0;
+				let from = to - e.pixel_movement().as_dvec2();
+				view_transform.update(|v| v.rotate_dragged_about(screen_center(), from, to));
+				return;
+			}
+
+			if button0 && temporary_tool == Some(util::TemporaryTool::Eyedropper) {
+				sample_color(e.screen_position());
+				return;
+			}
+
+			if button0 {
+				let view = view_transform.get_untracked();
+				let to = e.screen_position().to_canvas(&view).0;
+				let dragged = transforming
+					.try_update_value(|transforming| {
+						let (handle, from) = transforming.as_mut()?;
+						Some((*handle, std::mem::replace(from, to)))
+					})
+					.flatten();
+				if let Some((handle, from)) = dragged {
+					floating.update_value(|floating| {
+						if let Some(floating) = floating {
+							let pivot = floating.pivot();
+							floating.transform = floating.transform.dragged(pivot, handle, from, to);
+						}
+					});
+					update_floating();
+					return;
+				}
+			}
+
+			if button0 && cropping.with_value(Option::is_some) {
+				let view = view_transform.get_untracked();
+				let corner = e.screen_position().to_canvas(&view).0;
+				cropping.update_value(|cropping| {
+					if let Some((_, to)) = cropping {
+						*to = corner;
+					}
 				});
+				redraw_trigger.notify();
+				return;
+			}
+
+			// The path continues to wherever the pen hovers. Anchors are placed as it goes down, so
+			// dragging draws nothing.
+			if pen_tool.get_untracked() && temporary_tool != Some(util::TemporaryTool::Eraser) {
+				if !button0 && !pen_path.with_value(PenPath::is_empty) {
+					let hover = shape_point(&e);
+					shape_outline.set(pen_path.with_value(|path| path.outline(Some(hover))));
+				}
 				return;
 			}
 
+			if let (true, Some(tool)) = (button0, shape_tool.get_untracked()) {
+				let dragged = shaping
+					.try_update_value(|shaping| {
+						let (from, to) = shaping.as_mut()?;
+						*to = shape_point(&e);
+						Some((*from, *to))
+					})
+					.flatten();
+				if let Some((from, to)) = dragged {
+					shape_outline.set(tool.outline(from, to));
+					return;
+				}
+			}
+
+			if let (true, Some(tool)) = (button0, selection_tool.get_untracked()) {
+				let shape = selecting.try_update_value(|points| {
+					let points = points.as_mut()?;
+					points.push(canvas_point(&e));
+					Some(tool.shape(points))
+				});
+				if let Some(shape) = shape.flatten() {
+					selection_outline.set(shape.as_ref().map(SelectionShape::outline));
+					return;
+				}
+			}
+
 			// Draw.
 			if button0 {
-				let mut airbrush: std::cell::RefMut<_> = (*airbrush).borrow_mut();
-
-				let pressure = e.pressure();
-				let input_point = InputPoint {
-					position,
-					pressure,
-					color: brush_color.get_untracked(),
-					size: brush_size.get_untracked() as f32,
-					opacity: brush_opacity.get_untracked() as f32,
-					rate: brush_rate.get_untracked() as f32,
-				};
-				if let Some(drawable) = airbrush.drag(context.queue(), input_point) {
-					draw(drawable);
+				if measure_latency() {
+					latency_meter.update_value(|m| m.input(e.time_stamp()));
 				}
+				let raw = snap_to_guide(stroke_point(&e));
+				logged_stroke.update_value(|stroke| {
+					if let Some(stroke) = stroke {
+						stroke.points.push(LoggedPoint::new(raw, stroke_tilt(&e)));
+					}
+				});
+				let points = stabilizer
+					.try_update_value(|s| s.push(raw))
+					.unwrap_or_default();
+				trace_stroke(Some(raw), &points);
+				let tilt = stroke_tilt(&e);
+				record_segments(tilt);
+				pending_points.update_value(|pending| {
+					pending.extend(points.into_iter().map(|point| (point, tilt)))
+				});
+				// Smudging, mixing, and erasing change the paint already there, which the tip can't
+				// show, and strokes snapping to guides don't follow the pointer.
+				if predict_pointer.get_untracked()
+					&& stroke_mode() == BrushMode::Paint
+					&& guide_snap.with_value(Option::is_none)
+				{
+					let tip = stabilizer.with_value(|stabilizer| {
+						let predicted = predicted_stroke_point(&e)
+							.or_else(|| stabilizer.extrapolate(PREDICTION_HORIZON))?;
+						Some((stabilizer.last_output()?, predicted))
+					});
+					stroke_tip.set_value(tip);
+				}
+				// The frame loop draws the batch. It's started before the canvas is asked to redraw, so
+				// its callback runs first and the batch is shown in the same frame.
+				draw_pending(false);
+				draw_pending_loop();
+				redraw_trigger.notify();
 			}
 		}
 	};
 
 	let pointerdown = {
+		let atlas = atlas.clone();
 		let airbrush = airbrush.clone();
 		let pointermove = pointermove.clone();
+		let history = history.clone();
+		let log_stroke = log_stroke.clone();
+		let draw_pending = draw_pending.clone();
+		let redraw_trigger = redraw_trigger.clone();
 		move |e: leptos::ev::PointerEvent| {
-			(*airbrush).borrow_mut().start();
+			active_pointers.update_value(|p| p.down(e.pointer_id(), e.screen_position().0));
+			let pointers = active_pointers.with_value(|p| p.len());
+			if pointers == 1 {
+				tool_overrides.update_value(|tools| {
+					tools.set_held(util::TemporaryTool::Eyedropper, e.alt_key());
+					tools.begin_drag();
+				});
+			}
+			let temporary_tool = active_tool();
+			match pointers {
+				// Alt+click samples the canvas color.
+				1 if temporary_tool == Some(util::TemporaryTool::Eyedropper) && e.button() == 0 => {
+					sample_color(e.screen_position());
+				}
+				// Panning and rotating move the view rather than drawing.
+				1 if matches!(
+					temporary_tool,
+					Some(util::TemporaryTool::Pan | util::TemporaryTool::Rotate)
+				) => {}
+				// While a selection floats, dragging moves, scales, or rotates it.
+				1 if e.button() == 0 && floating.with_value(Option::is_some) => {
+					let view = view_transform.get_untracked();
+					let point = e.screen_position();
+					let handle = match floating_corners.get_untracked() {
+						Some(corners) => {
+							let corners = corners.map(|p| CanvasPt(p).to_screen(&view).0);
+							transform_handle_at(&corners, point.0)
+						}
+						None => TransformHandle::Move,
+					};
+					transforming.set_value(Some((handle, point.to_canvas(&view).0)));
+				}
+				1 if e.button() == 0 && temporary_tool.is_none() && crop_tool.get_untracked() => {
+					let view = view_transform.get_untracked();
+					let point = e.screen_position().to_canvas(&view).0;
+					cropping.set_value(Some((point, point)));
+				}
+				1 if e.button() == 0
+					&& temporary_tool.is_none()
+					&& selection_tool.get_untracked().is_some() =>
+				{
+					selecting.set_value(Some(vec![canvas_point(&e)]));
+				}
+				1 if e.button() == 0
+					&& temporary_tool.is_none()
+					&& shape_tool.get_untracked().is_some() =>
+				{
+					let point = shape_point(&e);
+					shaping.set_value(Some((point, point)));
+				}
+				1 if e.button() == 0 && temporary_tool.is_none() && pen_tool.get_untracked() => {
+					let point = shape_point(&e);
+					pen_path.update_value(|path| {
+						path.push(point);
+					});
+					shape_outline.set(pen_path.with_value(|path| path.outline(None)));
+				}
+				// Without an airbrush yet, there is nothing to stroke with.
+				1 if !airbrush_ready.get_untracked() => {}
+				1 => {
+					history.borrow_mut().begin_edit();
+					let symmetry = current_symmetry();
+					let mode = stroke_mode();
+					let spacing = brush_spacing.map_or(DEFAULT_BRUSH_SPACING, |spacing| {
+						spacing.get_untracked() as f32
+					});
+					let scatter = brush_scatter.map_or(DEFAULT_BRUSH_SCATTER, |scatter| {
+						scatter.get_untracked() as f32
+					});
+					let grain =
+						brush_grain.map_or(DEFAULT_BRUSH_GRAIN, |grain| grain.get_untracked() as f32);
+					let pickup =
+						brush_pickup.map_or(DEFAULT_BRUSH_PICKUP, |pickup| pickup.get_untracked() as f32);
+					// Each stroke is seeded explicitly so that its log replays the same stamps.
+					let seed = fastrand::u64(..);
+					let snap_distance = GUIDE_SNAP_DISTANCE / view_transform.get_untracked().zoom as f32;
+					guide_snap.set_value(
+						current_guides()
+							.filter(|_| snap_to_guides.get_untracked())
+							.map(|_| GuideSnap::new(canvas_point(&e), snap_distance)),
+					);
+					if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+						airbrush.set_symmetry(symmetry);
+						airbrush.set_mode(mode);
+						airbrush.set_spacing(spacing);
+						airbrush.set_scatter(scatter);
+						airbrush.set_grain(grain);
+						airbrush.set_pickup(pickup);
+						airbrush.set_seed(Some(seed));
+						airbrush.start();
+					}
+					stroke_spacing.set_value(spacing);
+					let smoothing = smoothing.get_untracked() as f32;
+					let smoothing_interval = smoothing_interval.map_or(
+						util::input_interpolate::DEFAULT_STABILIZER_INTERVAL,
+						|interval| interval.get_untracked() as f32,
+					);
+					let stroke_end = stroke_end.get_untracked();
+					stabilizer.set_value(
+						util::input_interpolate::Stabilizer::new(smoothing)
+							.with_max_interval(smoothing_interval)
+							.with_stroke_end(stroke_end),
+					);
+					logged_stroke.set_value(Some(LoggedStroke {
+						view: view_transform.get_untracked(),
+						color: brush_color.get_untracked(),
+						size: brush_size.get_untracked() as f32,
+						opacity: brush_opacity.get_untracked() as f32,
+						rate: brush_rate.get_untracked() as f32,
+						smoothing,
+						smoothing_interval,
+						stroke_end,
+						mode,
+						spacing,
+						scatter,
+						grain,
+						pickup,
+						symmetry,
+						seed,
+						points: Vec::new(),
+					}));
+					vector_stroke.set_value(Some(VectorStroke {
+						color: brush_color.get_untracked(),
+						size: brush_size.get_untracked() as f32,
+						opacity: brush_opacity.get_untracked() as f32,
+						rate: brush_rate.get_untracked() as f32,
+						mode,
+						spacing,
+						scatter,
+						grain,
+						pickup,
+						symmetry,
+						seed,
+						segments: Vec::new(),
+					}));
+				}
+				// A second pointer turns the stroke into a gesture.
+				2 if temporary_tool.is_some_and(|tool| !tool.draws()) => {
+					tool_overrides.update_value(|tools| {
+						tools.end_drag();
+					});
+				}
+				2 if transforming.with_value(Option::is_some) => transforming.set_value(None),
+				2 if shaping.with_value(Option::is_some) => {
+					shaping.set_value(None);
+					shape_outline.set(None);
+				}
+				2 if cropping.with_value(Option::is_some) => {
+					cropping.set_value(None);
+					redraw_trigger.notify();
+				}
+				2 if selecting.with_value(Option::is_some) => {
+					selecting.set_value(None);
+					let atlas = atlas.read().unwrap();
+					selection_outline.set(atlas.selection().shape().map(SelectionShape::outline));
+				}
+				2 => {
+					stroke_tip.set_value(None);
+					draw_pending(true);
+					if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+						airbrush.stop();
+					}
+					history.borrow_mut().end_edit();
+					log_stroke();
+				}
+				_ => {}
+			}
 
 			e.set_pointer_capture();
 			e.prevent_default();
@@ -330,30 +2102,140 @@ pub fn Canvas(
 	};
 
 	let pointerup = {
+		let set_selection = set_selection.clone();
 		let airbrush = airbrush.clone();
+		let redraw_trigger = redraw_trigger.clone();
+		let document = document.clone();
+		let stroke_segments = stroke_segments.clone();
 		move |e: leptos::ev::PointerEvent| {
-			(*airbrush).borrow_mut().stop();
+			if let Some(Some(_)) = stroke_tip.try_update_value(Option::take) {
+				redraw_trigger.notify();
+			}
+			let was_gesture = active_pointers.with_value(|p| p.is_gesture());
+			active_pointers.update_value(|p| p.up(e.pointer_id()));
+			let selected = selecting.try_update_value(Option::take).flatten();
+			let transformed = transforming.try_update_value(Option::take).flatten();
+			let cropped = cropping.try_update_value(Option::take).flatten();
+			let shaped = shaping.try_update_value(Option::take).flatten();
+			let temporary_tool = tool_overrides
+				.try_update_value(util::ToolOverrides::end_drag)
+				.flatten();
+			if temporary_tool.is_some_and(|tool| !tool.draws()) {
+				// Sampling and moving the view leave the canvas as it was.
+			} else if let Some(points) = selected {
+				let tool = selection_tool.get_untracked();
+				set_selection(tool.and_then(|tool| tool.shape(&points)));
+			} else if let Some((from, to)) = cropped {
+				// A click without a drag leaves the bounds as they were.
+				if let Some(bounds) = DocumentBounds::spanning(from, to) {
+					document.set_bounds(Some(bounds));
+					if let Some(announcer) = announcer {
+						let size = bounds.size;
+						announcer.announce(format!("Cropped to {}\u{d7}{}", size.x, size.y));
+					}
+				}
+				redraw_trigger.notify();
+			} else if let Some((from, to)) = shaped {
+				shape_outline.set(None);
+				let segments = shape_tool
+					.get_untracked()
+					.and_then(|tool| tool.segments(from, to, 1.0));
+				if let Some(segments) = segments {
+					stroke_segments(segments);
+				}
+			} else if pen_tool.get_untracked() && temporary_tool.is_none() {
+				// Anchors are placed as the pen goes down.
+			} else if transformed.is_none() && !was_gesture {
+				let points = stabilizer.try_update_value(|s| s.finish()).unwrap_or_default();
+				trace_stroke(None, &points);
+				let tilt = stroke_tilt(&e);
+				record_segments(tilt);
+				pending_points.update_value(|pending| {
+					pending.extend(points.into_iter().map(|point| (point, tilt)))
+				});
+				draw_pending(true);
+				if let Some(airbrush) = &mut *airbrush.borrow_mut() {
+					airbrush.stop();
+				}
+				history.borrow_mut().end_edit();
+				log_stroke();
+				evict_charts();
+			}
 			e.prevent_default();
 		}
 	};
 
-	let wheel = move |e: leptos::ev::WheelEvent| {
-		let screen_to_canvas = screen_to_canvas.get_untracked();
-		let position = {
-			let screen_position = e.pixel_position();
-			let position = screen_to_canvas * vec4(screen_position.x, screen_position.y, 0f32, 1f32);
-			position.xy()
+	let pointerleave = move |_: leptos::ev::PointerEvent| {
+		cursor_position.set_value(None);
+		cursor_trigger.notify();
+	};
+
+	let files_chosen = {
+		let import_image = import_image.clone();
+		move |e: leptos::ev::Event| {
+			let center = ScreenPx(screen_center()).to_canvas(&view_transform.get_untracked());
+			for file in util::take_chosen_files(&e) {
+				import_image(file, center);
+			}
+		}
+	};
+
+	// Entering and leaving children also fire events, so count them to know when a drag has left.
+	let drag_depth = StoredValue::new(0u32);
+	let dragging_files = RwSignal::new(false);
+	let dragenter = move |e: leptos::ev::DragEvent| {
+		if is_dragging_files(&e) {
+			drag_depth.update_value(|depth| *depth += 1);
+			dragging_files.set(true);
+		}
+	};
+	let dragleave = move |e: leptos::ev::DragEvent| {
+		if is_dragging_files(&e) {
+			drag_depth.update_value(|depth| *depth = depth.saturating_sub(1));
+			if drag_depth.get_value() == 0 {
+				dragging_files.set(false);
+			}
+		}
+	};
+	let dragover = move |e: leptos::ev::DragEvent| {
+		// This is required to allow dropping.
+		e.prevent_default();
+	};
+
+	let drop = move |e: leptos::ev::DragEvent| {
+		e.prevent_default();
+		drag_depth.set_value(0);
+		dragging_files.set(false);
+		let Some(files) = e.data_transfer().and_then(|d| d.files()) else {
+			return;
 		};
-		let translation = vec3(position.x, position.y, 0.0);
+		let position = e
+			.screen_position()
+			.to_canvas(&view_transform.get_untracked());
+		for file in (0..files.length()).filter_map(|i| files.get(i)) {
+			match (DroppedFileKind::of(&file), open_file) {
+				(Some(DroppedFileKind::Image), _) => import_image(file, position),
+				(Some(kind), Some(open_file)) => open_file.run((kind, file)),
+				(kind, _) => tracing::warn!(name = %file.name(), ?kind, "ignoring dropped file"),
+			}
+		}
+	};
 
-		let mut scale = 1.272;
-		if e.delta_y() > 0.0 {
-			scale = 1.0 / scale;
+	let wheel = move |e: leptos::ev::WheelEvent| {
+		let page_size = dvec2(width.get_untracked(), height.get_untracked());
+		view_animation.set_value(None);
+		match util::WheelInput::from_event(&e, page_size).gesture() {
+			util::WheelGesture::Zoom(factor) => {
+				let anchor = e.screen_position().0;
+				let (view, now) = (view_transform.get_untracked(), util::performance_now());
+				zoom_ease.update_value(|ease| match ease {
+					Some(ease) => ease.zoom_by(&view, anchor, factor),
+					None => *ease = Some(ZoomEase::new(&view, anchor, factor, now)),
+				});
+				zoom_ease_loop();
+			}
+			util::WheelGesture::Pan(delta) => view_transform.update(|v| v.pan(delta)),
 		}
-		let transform = Mat4::from_translation(translation)
-			* Mat4::from_scale(vec3(scale, scale, 1.0))
-			* Mat4::from_translation(-translation);
-		canvas_to_screen.update(|m| *m = (*m) * transform);
 		e.prevent_default();
 	};
 
@@ -361,6 +2243,7 @@ pub fn Canvas(
 		set_surface_configuration.try_set_or_log(Some(configuration));
 	};
 	let configured = LocalCallback::new(configured);
+	let configure = configure_surface(context.clone(), color_space);
 
 	// let on_fetch_tile_texture_url = Trigger::new();
 	// let texture_url = LocalResource::new(move || {
@@ -373,7 +2256,24 @@ pub fn Canvas(
 	// });
 
 	view! {
-		<div class="Canvas" node_ref=node_ref>
+		<div
+			class="Canvas"
+			class:loading=loading
+			aria-busy=move || loading().to_string()
+			node_ref=node_ref
+			on:dragenter=dragenter
+			on:dragleave=dragleave
+			on:dragover=dragover
+			on:drop=drop
+		>
+			<input
+				type="file"
+				accept="image/png,image/jpeg"
+				multiple
+				hidden
+				node_ref=file_input
+				on:change=files_chosen
+			/>
 			// <div class="debug">
 			// <button on:click=move |_| { on_fetch_tile_texture_url.notify() }>"Fetch tile texture"</button>
 			// // <a href=move || { texture_url.get().map(|s| s.take()).unwrap_or_default() } target="_blank">"Download texture"</a>
@@ -381,13 +2281,87 @@ pub fn Canvas(
 			// </div>
 			<RenderSurface
 				render=render
+				configure=configure
 				configured=configured
+				alpha_mode=alpha_mode
+				low_latency=low_latency
 				on:touchstart=touchstart
 				on:pointermove=pointermove
 				on:pointerdown=pointerdown
-				on:pointerup=pointerup
+				on:pointerup=pointerup.clone()
+				on:pointercancel=pointerup
+				on:pointerleave=pointerleave
 				on:wheel=wheel
 			/>
+			<SelectionOutline outline=selection_outline view_transform=view_transform/>
+			<TransformHandles corners=floating_corners view_transform=view_transform/>
+			<ShapePreview
+				outline=shape_outline
+				width=Signal::derive(move || 2.0 * brush_size.get())
+				view_transform=view_transform
+			/>
+			<DropIndicator active=dragging_files/>
+			<TooLargeNotice error=too_large/>
+			{move || {
+				(low_latency || latency_hud.get()).then(|| view! { <LatencyHud latency=latency/> })
+			}}
+			{move || stroke_graph.get().then(|| view! { <StrokeGraph trace=stroke_trace/> })}
+			{move || gpu_profiler.get().then(|| view! { <ProfilerHud/> })}
+			{move || pick_hud.get().then(|| view! { <PickHud picked=picked/> })}
+			{brush_readout.map(|text| view! { <BrushReadout text=text/> })}
 		</div>
 	}
 }
+
+/// Configures the canvas's surface as by default, but with a format suited to `color_space`. The
+/// pipelines are recreated for whichever format is chosen, which determines how they encode colors.
+fn configure_surface(
+	context: Arc<WgpuContext>,
+	color_space: SurfaceColorSpace,
+) -> ConfigureCallback {
+	LocalCallback::new(move |(surface, width, height): ConfigureArgs| {
+		let mut configuration = surface.get_default_config(context.adapter(), width, height)?;
+		let capabilities = surface.get_capabilities(context.adapter());
+		let format = color_space.choose_format(&capabilities.formats)?;
+		if color_space == SurfaceColorSpace::Extended && format != wgpu::TextureFormat::Rgba16Float {
+			tracing::warn!(?format, "extended range is unsupported by the surface");
+		}
+		configuration.format = format;
+		// The configuration may list sRGB views of the default format.
+		configuration.view_formats.clear();
+		Some(configuration)
+	})
+}
+
+/// The pressure the brush's footprint is outlined at. A hovering pen reports no pressure, so the
+/// full size is shown until it touches down.
+fn footprint_pressure(e: &leptos::ev::PointerEvent) -> f32 {
+	match e.pressure() {
+		pressure if e.buttons() & 1 != 0 && pressure > 0.0 => pressure,
+		_ => 1.0,
+	}
+}
+
+/// The farthest point the browser predicts the pointer will reach, if it predicts any.
+fn predicted_stroke_point(e: &leptos::ev::PointerEvent) -> Option<StrokePoint> {
+	e.get_predicted_events()
+		.iter()
+		.filter_map(|event| event.dyn_into::<web_sys::PointerEvent>().ok())
+		.last()
+		.map(|event| stroke_point(&event))
+}
+
+fn stroke_point(e: &leptos::ev::PointerEvent) -> util::input_interpolate::InputPoint {
+	util::input_interpolate::InputPoint {
+		t: (e.time_stamp() / 1000.0) as f32,
+		x: e.offset_x() as f32,
+		y: e.offset_y() as f32,
+		pressure: e.pressure(),
+	}
+}
+
+/// The stylus tilt relative to the screen. Twist is ignored, since the airbrush is the same all the
+/// way around.
+fn stroke_tilt(e: &leptos::ev::PointerEvent) -> Vec2 {
+	tilt_from_angles(e.tilt_x() as f32, e.tilt_y() as f32)
+}
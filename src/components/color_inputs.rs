@@ -0,0 +1,127 @@
+use crate::util::*;
+use glam::*;
+use leptos::prelude::*;
+
+/// How [`ColorInputs`] enters colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+enum ColorInputMode {
+	#[display("Hex")]
+	Hex,
+	#[display("RGB")]
+	Rgb,
+	#[display("HSV")]
+	Hsv,
+}
+
+impl ColorInputMode {
+	const ALL: [Self; 3] = [Self::Hex, Self::Rgb, Self::Hsv];
+}
+
+/// The name of each channel, the largest value it's entered as, and how much larger that is than
+/// the channel's value, which is between 0 and 1 other than hue, in degrees.
+const RGB_CHANNELS: [(&str, f32, f32); 3] = [
+	("R", 255.0, 255.0),
+	("G", 255.0, 255.0),
+	("B", 255.0, 255.0),
+];
+const HSV_CHANNELS: [(&str, f32, f32); 3] =
+	[("H", 360.0, 1.0), ("S", 100.0, 100.0), ("V", 100.0, 100.0)];
+
+/// Enters `color`, in Oklab, as a hex string or CSS color, as RGB from 0 to 255, or as HSV.
+#[component]
+pub fn ColorInputs(color: RwSignal<Vec3>) -> impl IntoView {
+	let mode = RwSignal::new(ColorInputMode::Hex);
+	let rgb = move || oklab_to_rgb(color.get()).clamp(Vec3::ZERO, Vec3::ONE);
+	let set_rgb = move |rgb: Vec3| color.set(rgb_to_oklab(rgb.clamp(Vec3::ZERO, Vec3::ONE)));
+
+	// Hue and saturation can't be recovered from black or grays, so HSV is kept as entered, and only
+	// follows the color when it's changed some other way.
+	let to_hsv = |color: Vec3| rgb_to_hsv(oklab_to_rgb(color).clamp(Vec3::ZERO, Vec3::ONE));
+	let hsv = RwSignal::new(to_hsv(color.get_untracked()));
+	let entered_hsv_color = StoredValue::new(None::<Vec3>);
+	Effect::new(move |_| {
+		let color = color.get();
+		if entered_hsv_color.get_value() != Some(color) {
+			hsv.set(to_hsv(color));
+		}
+	});
+	let set_hsv = move |entered: Vec3| {
+		hsv.set(entered);
+		let entered_color = rgb_to_oklab(hsv_to_rgb(entered).clamp(Vec3::ZERO, Vec3::ONE));
+		entered_hsv_color.set_value(Some(entered_color));
+		color.set(entered_color);
+	};
+
+	let tabs = ColorInputMode::ALL
+		.into_iter()
+		.map(|m| {
+			view! {
+				<button
+					role="tab"
+					aria-selected=move || (mode.get() == m).to_string()
+					class:active=move || mode.get() == m
+					on:click=move |_| mode.set(m)
+				>
+					{m.to_string()}
+				</button>
+			}
+		})
+		.collect_view();
+
+	let change_hex = move |e: leptos::ev::Event| {
+		if let Some(parsed) = try_color_from_css_string(&event_target_value(&e)) {
+			set_rgb(parsed.xyz());
+		}
+	};
+	// Each channel is entered on its own, with the others as they are.
+	let channels = move |channels: [(&'static str, f32, f32); 3], is_hsv: bool| {
+		let current = move || if is_hsv { hsv.get() } else { rgb() };
+		channels
+			.into_iter()
+			.enumerate()
+			.map(|(i, (name, max, scale))| {
+				let value = move || (current()[i] * scale).round().to_string();
+				let input = move |e: leptos::ev::Event| {
+					let Ok(entered) = event_target_value(&e).parse::<f32>() else {
+						return;
+					};
+					let mut channels = current();
+					channels[i] = entered.clamp(0.0, max) / scale;
+					if is_hsv {
+						set_hsv(channels);
+					} else {
+						set_rgb(channels);
+					}
+				};
+				view! {
+					<label>
+						{name}
+						<input type="number" min=0 max=max step=1 prop:value=value on:change=input/>
+					</label>
+				}
+			})
+			.collect_view()
+	};
+
+	view! {
+		<div class="ColorInputs">
+			<div role="tablist">{tabs}</div>
+			{move || match mode.get() {
+				ColorInputMode::Hex => {
+					view! {
+						<input
+							type="text"
+							aria-label="Hex"
+							spellcheck="false"
+							prop:value=move || rgb_to_hex(rgb())
+							on:change=change_hex
+						/>
+					}
+						.into_any()
+				}
+				ColorInputMode::Rgb => channels(RGB_CHANNELS, false).into_any(),
+				ColorInputMode::Hsv => channels(HSV_CHANNELS, true).into_any(),
+			}}
+		</div>
+	}
+}
@@ -1,4 +1,5 @@
 use super::render_surface;
+use super::ColorInputs;
 use crate::render::BindingBuffer;
 use crate::shaders::color_picker::*;
 use crate::util::*;
@@ -21,24 +22,37 @@ pub fn ColorPicker(color: RwSignal<glam::Vec3>) -> impl IntoView {
 
 	let pipeline_layout = resources.color_picker.pipeline_layout().get();
 	let render_pipeline = {
+		let context = context.clone();
 		let pipeline_layout = pipeline_layout.clone();
-		create_local_derived(move || {
-			let pipeline = pipeline_layout
-				.vs_main_pipeline()
-				.primitive(wgpu::PrimitiveState {
-					topology: wgpu::PrimitiveTopology::TriangleStrip,
-					..Default::default()
-				})
-				.fragment(FragmentEntry::fs_main {
-					targets: [Some(wgpu::ColorTargetState {
-						format: texture_format.get()?,
-						blend: Some(wgpu::BlendState::REPLACE),
-						write_mask: wgpu::ColorWrites::ALL,
-					})],
+		let resources = resources.clone();
+		create_local_async_derived(move || {
+			let format = texture_format.get()?;
+			let context = context.clone();
+			let pipeline_layout = pipeline_layout.clone();
+			let resources = resources.clone();
+			Some(async move {
+				let pipeline = render::create_pipeline_async(context.device(), "ColorPicker", || {
+					pipeline_layout
+						.vs_main_pipeline()
+						.primitive(wgpu::PrimitiveState {
+							topology: wgpu::PrimitiveTopology::TriangleStrip,
+							..Default::default()
+						})
+						.fragment(FragmentEntry::fs_main {
+							targets: [Some(wgpu::ColorTargetState {
+								format,
+								blend: Some(wgpu::BlendState::REPLACE),
+								write_mask: wgpu::ColorWrites::ALL,
+							})],
+						})
+						.overrides(OverrideConstants { proof: None })
+						.maybe_cache(resources.pipeline_cache())
+						.get()
 				})
-				.overrides(OverrideConstants { proof: None })
-				.get();
-			Some(pipeline)
+				.await
+				.ok()?;
+				Some(pipeline)
+			})
 		})
 	};
 
@@ -109,10 +123,8 @@ pub fn ColorPicker(color: RwSignal<glam::Vec3>) -> impl IntoView {
 				return;
 			};
 			let ab = (xy - glam::Vec2::new(-0.09, 0.24)) / 3.8;
-			color.update(|lab| {
-				lab.y = ab.x;
-				lab.z = ab.y;
-			});
+			// Colors outside of the gamut are shown scaled into it, so that's what is picked.
+			color.update(|lab| *lab = clamp_to_srgb_gamut(glam::vec3(lab.x, ab.x, ab.y)));
 		}
 	};
 
@@ -128,11 +140,11 @@ pub fn ColorPicker(color: RwSignal<glam::Vec3>) -> impl IntoView {
 	let configured = LocalCallback::new(configured);
 
 	let style = move || {
-		let fractions = (0..=10).map(|i| i as f32 * 0.1);
-		let color = color.get();
+		let fractions = (0..=10).map(|i| i as f64 * 0.1);
+		let color = color.get().as_dvec3();
 		let colors = fractions
 			.clone()
-			.map(|l| oklab_to_rgb(glam::vec3(l, color.y, color.z)));
+			.map(|l| oklab_to_rgb_f64(clamp_to_srgb_gamut_f64(glam::dvec3(l, color.y, color.z))));
 		let colors = colors.map(|c| {
 			format!(
 				"rgb({},{},{})",
@@ -152,7 +164,11 @@ pub fn ColorPicker(color: RwSignal<glam::Vec3>) -> impl IntoView {
 	};
 
 	view! {
-		<div class="ColorPicker">
+		<div
+			class="ColorPicker"
+			class:loading=move || render_pipeline.with(Option::is_none)
+			aria-busy=move || render_pipeline.with(Option::is_none).to_string()
+		>
 			<render_surface::RenderSurface
 				render=render
 				configured=configured
@@ -180,6 +196,8 @@ pub fn ColorPicker(color: RwSignal<glam::Vec3>) -> impl IntoView {
 				style=style
 				on:input=move |ev| { set_lightness(event_target_value(&ev).parse().unwrap()) }
 			/>
+
+			<ColorInputs color=color/>
 		</div>
 	}
 }
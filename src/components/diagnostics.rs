@@ -0,0 +1,128 @@
+use crate::WgpuContext;
+use itertools::Itertools;
+use leptos::prelude::*;
+use std::sync::Arc;
+
+/// Which adapter to create the context on. It is chosen with the page's `gpu` query parameter, since
+/// changing it means starting over with a new device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AdapterChoice {
+	pub power_preference: wgpu::PowerPreference,
+	pub force_fallback_adapter: bool,
+	pub backends: Option<wgpu::Backends>,
+}
+
+/// Each choice with its query parameter value and label.
+const ADAPTER_CHOICES: [(&str, &str, AdapterChoice); 5] = [
+	(
+		"default",
+		"Default",
+		AdapterChoice {
+			power_preference: wgpu::PowerPreference::None,
+			force_fallback_adapter: false,
+			backends: None,
+		},
+	),
+	(
+		"low-power",
+		"Low power",
+		AdapterChoice {
+			power_preference: wgpu::PowerPreference::LowPower,
+			force_fallback_adapter: false,
+			backends: None,
+		},
+	),
+	(
+		"high-performance",
+		"High performance",
+		AdapterChoice {
+			power_preference: wgpu::PowerPreference::HighPerformance,
+			force_fallback_adapter: false,
+			backends: None,
+		},
+	),
+	(
+		"fallback",
+		"Software",
+		AdapterChoice {
+			power_preference: wgpu::PowerPreference::None,
+			force_fallback_adapter: true,
+			backends: None,
+		},
+	),
+	(
+		"webgl",
+		"WebGL2",
+		AdapterChoice {
+			power_preference: wgpu::PowerPreference::None,
+			force_fallback_adapter: false,
+			backends: Some(wgpu::Backends::GL),
+		},
+	),
+];
+
+impl AdapterChoice {
+	/// Parses the value of the `gpu` query parameter, or `None` if it isn't a choice.
+	pub fn from_query(value: &str) -> Option<Self> {
+		ADAPTER_CHOICES
+			.iter()
+			.find(|(query, ..)| *query == value)
+			.map(|(.., choice)| *choice)
+	}
+}
+
+/// Describes the adapter and what the device was created with, for bug reports, and switches to
+/// another adapter by reloading the page.
+#[component]
+pub fn Diagnostics(choice: AdapterChoice) -> impl IntoView {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	let capabilities = context.capabilities();
+	let adapter = &capabilities.adapter;
+	let limits = &capabilities.limits;
+	let driver = [&adapter.driver, &adapter.driver_info]
+		.into_iter()
+		.filter(|s| !s.is_empty())
+		.join(" ");
+	let features = capabilities
+		.features
+		.iter_names()
+		.map(|(name, _)| name)
+		.join(", ");
+
+	let choices = ADAPTER_CHOICES
+		.iter()
+		.map(|&(query, label, other)| {
+			// A plain link, so the page reloads with a new context.
+			view! {
+				<a href=format!("?gpu={query}") rel="external" class:active=other == choice>
+					{label}
+				</a>
+			}
+		})
+		.collect_view();
+
+	view! {
+		<dl class="Diagnostics">
+			<dt>"Adapter"</dt>
+			<dd>{adapter.name.clone()}</dd>
+			<dt>"Type"</dt>
+			<dd>{format!("{:?} ({:?})", adapter.device_type, adapter.backend)}</dd>
+			<dt>"Mode"</dt>
+			<dd>{if capabilities.compatibility_mode { "Compatibility" } else { "WebGPU" }}</dd>
+			<dt>"Driver"</dt>
+			<dd>{driver}</dd>
+			<dt>"Max texture size"</dt>
+			<dd>{limits.max_texture_dimension_2d}</dd>
+			<dt>"Max texture layers"</dt>
+			<dd>{limits.max_texture_array_layers}</dd>
+			<dt>"Max buffer size"</dt>
+			<dd>{limits.max_buffer_size}</dd>
+			<dt>"Features"</dt>
+			<dd>{features}</dd>
+		</dl>
+		<div class="AdapterChoices">{choices}</div>
+		<span class="DocumentSettingNote">
+			"Reloads with another GPU, for laptops where the default one fails."
+		</span>
+	}
+}
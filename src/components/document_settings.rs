@@ -0,0 +1,159 @@
+use crate::components::*;
+use crate::engine::{CanvasFormat, ColorTag, MAX_DPI, MIN_DPI, PROJECT_FILE_EXTENSION};
+use crate::util::{self, ResultExt};
+use crate::WgpuContext;
+use leptos::prelude::*;
+use std::sync::Arc;
+
+#[component]
+pub fn DocumentSettings(
+	documents: Documents,
+	dpi: RwSignal<f64>,
+	// While set, dragging on the canvas crops the document.
+	crop_tool: RwSignal<bool>,
+) -> impl IntoView {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	let device_features = context.device().features();
+
+	let options = CanvasFormat::ALL
+		.into_iter()
+		.map(|format| {
+			let supported = format.is_supported(context.adapter(), device_features);
+			view! {
+				<option value=format.to_string() disabled=!supported>
+					{format.to_string()}
+				</option>
+			}
+		})
+		.collect_view();
+
+	let canvas_format = move || {
+		documents
+			.active()
+			.map(|document| document.format())
+			.unwrap_or_default()
+	};
+
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		let Some(id) = documents.active_id() else {
+			return;
+		};
+		if let Some(format) = CanvasFormat::ALL
+			.into_iter()
+			.find(|f| f.to_string() == value)
+		{
+			documents.set_format(context.clone(), id, format);
+		}
+	};
+
+	let change_dpi = move |e: leptos::ev::Event| {
+		if let Ok(value) = event_target_value(&e).parse::<f64>() {
+			dpi.set(value.clamp(MIN_DPI, MAX_DPI));
+		}
+	};
+
+	let metadata = move || documents.active().map(|document| document.metadata);
+	let tag_options = ColorTag::ALL
+		.into_iter()
+		.map(|tag| view! { <option value=tag.to_string()>{tag.to_string()}</option> })
+		.collect_view();
+	let color_tag = move || {
+		metadata()
+			.and_then(|metadata| metadata.with(|m| m.color_tag))
+			.map_or(String::new(), |tag| tag.to_string())
+	};
+	let change_color_tag = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		let tag = ColorTag::ALL.into_iter().find(|t| t.to_string() == value);
+		if let Some(metadata) = metadata() {
+			metadata.update(|m| m.color_tag = tag);
+		}
+	};
+	let notes = move || {
+		metadata()
+			.map(|metadata| metadata.with(|m| m.notes.clone()))
+			.unwrap_or_default()
+	};
+	let change_notes = move |e: leptos::ev::Event| {
+		if let Some(metadata) = metadata() {
+			metadata.update(|m| m.notes = event_target_value(&e));
+		}
+	};
+
+	let bounds = move || {
+		documents
+			.active()
+			.and_then(|document| document.bounds.get())
+	};
+	let bounds_note = move || match bounds() {
+		Some(bounds) => format!(
+			"Cropped to {}\u{d7}{} at ({}, {}). Only what's inside is shown and exported.",
+			bounds.size.x, bounds.size.y, bounds.origin.x, bounds.origin.y
+		),
+		None => "Drag on the canvas to crop it.".to_string(),
+	};
+	let change_crop_tool = move |e: leptos::ev::Event| crop_tool.set(event_target_checked(&e));
+	let uncrop = move |_| {
+		if let Some(document) = documents.active() {
+			document.set_bounds(None);
+		}
+	};
+
+	// Saved projects can be opened again by dropping them on the canvas.
+	let saving = RwSignal::new(false);
+	let save_project = move |_| {
+		let Some(document) = documents.active() else {
+			return;
+		};
+		let project = document.atlas.read().unwrap().save_project();
+		let metadata = document.metadata.get_untracked();
+		let filename = format!("{}.{PROJECT_FILE_EXTENSION}", document.name);
+		saving.set(true);
+		leptos::task::spawn_local(async move {
+			if let Some(mut project) = project.await.ok_or_log() {
+				project.metadata = metadata;
+				util::download(&project.encode(), &filename, "application/octet-stream").ok_or_log();
+			}
+			saving.set(false);
+		});
+	};
+
+	view! {
+		<BrushSetting name="Format">
+			<select on:change=change prop:value=move || canvas_format().to_string()>
+				{options}
+			</select>
+			<span class="DocumentSettingNote">"Changing the format clears the canvas."</span>
+		</BrushSetting>
+		<BrushSetting name="DPI">
+			<input
+				type="number"
+				min=MIN_DPI
+				max=MAX_DPI
+				step=1
+				on:change=change_dpi
+				prop:value=move || dpi.get().to_string()
+			/>
+		</BrushSetting>
+		<BrushSetting name="Crop">
+			<input type="checkbox" prop:checked=move || crop_tool.get() on:change=change_crop_tool/>
+			<button on:click=uncrop disabled=move || bounds().is_none()>
+				"Uncrop"
+			</button>
+			<span class="DocumentSettingNote">{bounds_note}</span>
+		</BrushSetting>
+		<BrushSetting name="Tag">
+			<select on:change=change_color_tag prop:value=color_tag>
+				<option value="">"None"</option>
+				{tag_options}
+			</select>
+		</BrushSetting>
+		<BrushSetting name="Notes">
+			<textarea class="DocumentNotes" rows=3 on:change=change_notes prop:value=notes></textarea>
+		</BrushSetting>
+		<button on:click=save_project disabled=move || saving.get()>
+			"Save project"
+		</button>
+	}
+}
@@ -0,0 +1,74 @@
+use crate::components::*;
+use crate::engine::{CanvasFormat, ColorTag};
+use crate::WgpuContext;
+use leptos::prelude::*;
+use std::sync::Arc;
+
+/// A strip of tabs for switching between, opening, and closing documents. Opens a document if
+/// there are none.
+#[component]
+pub fn DocumentTabs(documents: Documents) -> impl IntoView {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	if documents.is_empty_untracked() {
+		documents.open(context.clone(), CanvasFormat::default());
+	}
+
+	let tabs = move || {
+		let autosaved_id = documents.autosaved().map(|document| document.id);
+		documents
+			.list()
+			.into_iter()
+			.map(|(id, name, metadata)| {
+				let can_close = documents.can_close(id);
+				// Only the autosaved document comes back on reload, so it stays open.
+				let close_title = match autosaved_id == Some(id) {
+					true => "The autosaved document can't be closed",
+					false => "Close",
+				};
+				let close = move |e: leptos::ev::MouseEvent| {
+					// Don't also activate the document being closed.
+					e.stop_propagation();
+					documents.close(id);
+				};
+				view! {
+					<div
+						class="DocumentTab"
+						class:active=move || documents.active_id() == Some(id)
+						on:click=move |_| documents.activate(id)
+					>
+						<span
+							class="DocumentTabTag"
+							style:background=move || {
+								metadata
+									.with(|m| m.color_tag.map(ColorTag::css_color))
+									.unwrap_or("transparent")
+							}
+						></span>
+						<span>{name}</span>
+						<button
+							class="DocumentTabClose"
+							title=close_title
+							disabled=!can_close
+							on:click=close
+						>
+							"\u{d7}"
+						</button>
+					</div>
+				}
+			})
+			.collect_view()
+	};
+
+	let open = move |_| {
+		documents.open(context.clone(), CanvasFormat::default());
+	};
+
+	view! {
+		<div class="DocumentTabs">
+			{tabs}
+			<button class="DocumentTab" title="New document" on:click=open>
+				"+"
+			</button>
+		</div>
+	}
+}
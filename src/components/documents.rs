@@ -0,0 +1,304 @@
+use crate::components::{load_view, AutosaveMode};
+use crate::engine::io::OraFile;
+use crate::engine::{
+	ActionLog, Atlas, CanvasFormat, Checkpoints, DocumentBounds, DocumentMetadata, History,
+	ImportError, PoolExhausted, ProjectFile, TimelapseRecorder, VectorDrawing, VectorEdit,
+};
+use crate::render::Resources;
+use crate::view_transform::ViewTransform;
+use crate::WgpuContext;
+use leptos::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+
+pub type DocumentId = u64;
+
+/// An open document: its canvas along with the state for editing and viewing it. This outlives any
+/// [`Canvas`](crate::components::Canvas) showing it, so switching between documents loses nothing.
+#[derive(Clone)]
+pub struct Document {
+	pub id: DocumentId,
+	pub name: String,
+	pub atlas: Arc<RwLock<Atlas>>,
+	pub history: Rc<RefCell<History>>,
+	/// The recent strokes, for attaching to bug reports.
+	pub action_log: Rc<RefCell<ActionLog>>,
+	/// Every stroke, as vectors which can be drawn again at another resolution.
+	pub vector_drawing: Rc<RefCell<VectorDrawing>>,
+	/// Copies of the canvas every so often, from which edits older than the history remembers are
+	/// undone by replaying the strokes since.
+	pub checkpoints: Rc<RefCell<Checkpoints>>,
+	/// Frames of the painting as it progresses, while a timelapse is being recorded.
+	pub timelapse: Rc<RefCell<Option<TimelapseRecorder>>>,
+	pub view_transform: RwSignal<ViewTransform>,
+	/// Views saved to return to, in the order they were saved.
+	pub view_bookmarks: RwSignal<Vec<ViewTransform>>,
+	/// The color tag and notes, which are saved with the project.
+	pub metadata: RwSignal<DocumentMetadata>,
+	/// What the canvas is cropped to, mirroring the atlas' bounds. Set it with [`Self::set_bounds`].
+	pub bounds: RwSignal<Option<DocumentBounds>>,
+	/// Set when the document needed more charts than the GPU can hold, until the
+	/// [`TooLargeNotice`](crate::components::TooLargeNotice) is dismissed.
+	pub too_large: RwSignal<Option<PoolExhausted>>,
+	autosave: Rc<Cell<Option<AutosaveMode>>>,
+}
+
+impl Document {
+	fn new(
+		context: Arc<WgpuContext>,
+		id: DocumentId,
+		name: String,
+		format: CanvasFormat,
+		autosave: Option<AutosaveMode>,
+	) -> Self {
+		Self {
+			id,
+			name,
+			atlas: Arc::new(RwLock::new(Atlas::new(context, format))),
+			history: Default::default(),
+			action_log: Default::default(),
+			vector_drawing: Default::default(),
+			checkpoints: Default::default(),
+			timelapse: Default::default(),
+			view_transform: RwSignal::new(ViewTransform::default()),
+			view_bookmarks: RwSignal::new(Vec::new()),
+			metadata: RwSignal::new(DocumentMetadata::default()),
+			bounds: RwSignal::new(None),
+			too_large: RwSignal::new(None),
+			autosave: Rc::new(Cell::new(autosave)),
+		}
+	}
+
+	pub fn format(&self) -> CanvasFormat {
+		self.atlas.read().unwrap().format()
+	}
+
+	/// Crops the document to `bounds`, or makes it unbounded again.
+	pub fn set_bounds(&self, bounds: Option<DocumentBounds>) {
+		self.atlas.write().unwrap().set_bounds(bounds);
+		self.bounds.set(bounds);
+	}
+
+	/// Records `edit` in the vector drawing, once the atlas shows it, so that it can be checkpointed.
+	pub fn record_vector_edit(&self, edit: VectorEdit) {
+		let mut vector_drawing = self.vector_drawing.borrow_mut();
+		vector_drawing.record(edit);
+		self
+			.checkpoints
+			.borrow_mut()
+			.record(&self.atlas.read().unwrap(), &vector_drawing);
+	}
+
+	/// How a canvas showing this document should autosave it, if at all. The saved document is
+	/// restored or replaced only once, and later canvases carry on saving.
+	pub fn take_autosave_mode(&self) -> Option<AutosaveMode> {
+		let mode = self.autosave.get();
+		if mode.is_some() {
+			self.autosave.set(Some(AutosaveMode::Continue));
+		}
+		mode
+	}
+}
+
+/// The open documents and which of them is shown.
+#[derive(Clone, Copy)]
+pub struct Documents {
+	documents: RwSignal<Vec<Document>, LocalStorage>,
+	active: RwSignal<Option<DocumentId>>,
+	next_id: StoredValue<DocumentId>,
+}
+
+impl Default for Documents {
+	fn default() -> Self {
+		Self {
+			documents: RwSignal::new_local(Vec::new()),
+			active: RwSignal::new(None),
+			next_id: StoredValue::new(0),
+		}
+	}
+}
+
+impl Documents {
+	/// The ID, name, and metadata of each open document, in order.
+	pub fn list(&self) -> Vec<(DocumentId, String, RwSignal<DocumentMetadata>)> {
+		self.documents.with(|documents| {
+			documents
+				.iter()
+				.map(|d| (d.id, d.name.clone(), d.metadata))
+				.collect()
+		})
+	}
+
+	pub fn is_empty_untracked(&self) -> bool {
+		self.documents.with_untracked(Vec::is_empty)
+	}
+
+	pub fn active_id(&self) -> Option<DocumentId> {
+		self.active.get()
+	}
+
+	/// The shown document. This only notifies when a different document is shown.
+	pub fn active(&self) -> Option<Document> {
+		let id = self.active.get()?;
+		self
+			.documents
+			.with_untracked(|documents| documents.iter().find(|d| d.id == id).cloned())
+	}
+
+	/// The autosaved document, if it's still open, whose view is saved by [`super::save_view`].
+	pub fn autosaved(&self) -> Option<Document> {
+		self.documents.with_untracked(|documents| {
+			documents
+				.iter()
+				.find(|d| d.autosave.get().is_some())
+				.cloned()
+		})
+	}
+
+	pub fn activate(&self, id: DocumentId) {
+		self.active.set(Some(id));
+	}
+
+	/// Opens a new blank document and shows it. Only the first document opened is autosaved, and it
+	/// is shown with the view saved with it.
+	pub fn open(&self, context: Arc<WgpuContext>, format: CanvasFormat) -> DocumentId {
+		let id = self.allocate_id();
+		let autosave = (id == 0).then_some(AutosaveMode::Restore);
+		let document = Document::new(
+			context,
+			id,
+			format!("Untitled {}", id + 1),
+			format,
+			autosave,
+		);
+		if let Some(view) = autosave.and_then(|_| load_view()) {
+			document.view_transform.set(view);
+		}
+		self.documents.update(|documents| documents.push(document));
+		self.activate(id);
+		id
+	}
+
+	/// Opens a saved project as a new document and shows it. Projects are not autosaved.
+	pub fn open_project(
+		&self,
+		context: Arc<WgpuContext>,
+		name: String,
+		project: ProjectFile,
+	) -> DocumentId {
+		let id = self.allocate_id();
+		let document = Document::new(context, id, name, project.format, None);
+		document.metadata.set(project.metadata);
+		document.set_bounds(project.bounds);
+		let restored = {
+			let mut atlas = document.atlas.write().unwrap();
+			project
+				.charts
+				.into_iter()
+				.try_for_each(|chart| atlas.restore_chart(chart))
+		};
+		if let Err(error) = restored {
+			tracing::error!(%error, "project too large for this GPU");
+			document.too_large.set(Some(error));
+		}
+		// The project only holds pixels.
+		document.record_vector_edit(VectorEdit::Raster);
+		self.documents.update(|documents| documents.push(document));
+		self.activate(id);
+		id
+	}
+
+	/// Opens an OpenRaster file as a new document and shows it, with its visible layers flattened onto
+	/// the canvas. Nothing is opened if a layer can't be decoded.
+	pub fn open_ora(
+		&self,
+		context: Arc<WgpuContext>,
+		resources: &Resources,
+		name: String,
+		ora: &OraFile,
+	) -> Result<DocumentId, ImportError> {
+		let document = Document::new(
+			context,
+			self.allocate_id(),
+			name,
+			CanvasFormat::default(),
+			None,
+		);
+		ora.draw_to(&mut document.atlas.write().unwrap(), resources)?;
+		// The file only holds pixels.
+		document.record_vector_edit(VectorEdit::Raster);
+		let id = document.id;
+		self.documents.update(|documents| documents.push(document));
+		self.activate(id);
+		Ok(id)
+	}
+
+	/// Closes a document, showing a neighbor if it was active. Its GPU memory is released once the
+	/// canvas showing it, if any, is gone.
+	///
+	/// The autosaved document can't be closed. It's the only one restored on reload, so closing it
+	/// would silently lose the others while bringing back what it last saved.
+	pub fn close(&self, id: DocumentId) {
+		if !self.can_close(id) {
+			return;
+		}
+		let mut neighbor = None;
+		self.documents.update(|documents| {
+			let Some(index) = documents.iter().position(|d| d.id == id) else {
+				return;
+			};
+			documents.remove(index);
+			neighbor = documents
+				.get(index.min(documents.len().saturating_sub(1)))
+				.map(|d| d.id);
+		});
+		if self.active.get_untracked() == Some(id) {
+			self.active.set(neighbor);
+		}
+	}
+
+	/// Whether [`Self::close`] would close the document: it isn't autosaved and isn't the last one.
+	pub fn can_close(&self, id: DocumentId) -> bool {
+		self.documents.with(|documents| {
+			documents.len() > 1
+				&& documents
+					.iter()
+					.any(|d| d.id == id && d.autosave.get().is_none())
+		})
+	}
+
+	/// Replaces a document with a blank one in `format`, returning the new document's ID.
+	pub fn set_format(
+		&self,
+		context: Arc<WgpuContext>,
+		id: DocumentId,
+		format: CanvasFormat,
+	) -> DocumentId {
+		let new_id = self.allocate_id();
+		self.documents.update(|documents| {
+			let Some(document) = documents.iter_mut().find(|d| d.id == id) else {
+				return;
+			};
+			// The autosaved document is replaced, since the canvas has been cleared.
+			let autosave = document.autosave.get().map(|_| AutosaveMode::Replace);
+			let (metadata, bounds) = (document.metadata, document.bounds.get_untracked());
+			let view_bookmarks = document.view_bookmarks;
+			*document = Document::new(context, new_id, document.name.clone(), format, autosave);
+			// Only the pixels are cleared.
+			document.metadata = metadata;
+			document.view_bookmarks = view_bookmarks;
+			document.set_bounds(bounds);
+		});
+		if self.active.get_untracked() == Some(id) {
+			self.activate(new_id);
+		}
+		new_id
+	}
+
+	fn allocate_id(&self) -> DocumentId {
+		let id = self.next_id.get_value();
+		self.next_id.set_value(id + 1);
+		id
+	}
+}
@@ -0,0 +1,146 @@
+use crate::components::*;
+use crate::render::FrameCapture;
+use crate::util::{self, ResultExt};
+use leptos::prelude::*;
+
+/// Settings which trade quality or stability for something else, and tools for diagnosing bugs.
+#[component]
+pub fn ExperimentalSettings(
+	low_latency: RwSignal<bool>,
+	extended_range: RwSignal<bool>,
+	stroke_graph: RwSignal<bool>,
+	gpu_profiler: RwSignal<bool>,
+	latency_hud: RwSignal<bool>,
+	pick_hud: RwSignal<bool>,
+	predict_pointer: RwSignal<bool>,
+	capture_frame: RwSignal<bool>,
+	captured_frame: RwSignal<Option<FrameCapture>>,
+	documents: Documents,
+) -> impl IntoView {
+	let change_low_latency = move |e: leptos::ev::Event| low_latency.set(event_target_checked(&e));
+	let change_extended_range =
+		move |e: leptos::ev::Event| extended_range.set(event_target_checked(&e));
+	let change_stroke_graph = move |e: leptos::ev::Event| stroke_graph.set(event_target_checked(&e));
+	let change_gpu_profiler = move |e: leptos::ev::Event| gpu_profiler.set(event_target_checked(&e));
+	let change_latency_hud = move |e: leptos::ev::Event| latency_hud.set(event_target_checked(&e));
+	let change_pick_hud = move |e: leptos::ev::Event| pick_hud.set(event_target_checked(&e));
+	let change_predict_pointer =
+		move |e: leptos::ev::Event| predict_pointer.set(event_target_checked(&e));
+	let download_captured_frame = move |_| {
+		let Some(encoded) = captured_frame.with(|frame| frame.as_ref().map(FrameCapture::to_json))
+		else {
+			return;
+		};
+		util::download(encoded.as_bytes(), "frame-capture.json", "application/json").ok_or_log();
+	};
+
+	// The log holds no pixels. It can be replayed with the `replay_action_log` example.
+	let export_action_log = move |_| {
+		let Some(document) = documents.active() else {
+			return;
+		};
+		let encoded = document.action_log.borrow().encode();
+		let filename = format!("{}-actions.json", document.name);
+		util::download(encoded.as_bytes(), &filename, "application/json").ok_or_log();
+	};
+	// The strokes can be drawn again, at any scale, with the `rasterize_vector_drawing` example.
+	let export_vector_drawing = move |_| {
+		let Some(document) = documents.active() else {
+			return;
+		};
+		let encoded = document.vector_drawing.borrow().encode();
+		let filename = format!("{}-strokes.json", document.name);
+		util::download(encoded.as_bytes(), &filename, "application/json").ok_or_log();
+	};
+
+	view! {
+		<BrushSetting name="Low latency">
+			<input type="checkbox" prop:checked=move || low_latency.get() on:change=change_low_latency/>
+			<span class="DocumentSettingNote">
+				"Strokes appear sooner, but edges are jagged and the canvas may tear."
+			</span>
+		</BrushSetting>
+		<BrushSetting name="Extended range">
+			<input
+				type="checkbox"
+				prop:checked=move || extended_range.get()
+				on:change=change_extended_range
+			/>
+			<span class="DocumentSettingNote">
+				"Shows colors outside of sRGB on displays that can, where the browser supports it."
+			</span>
+		</BrushSetting>
+		<BrushSetting name="Stroke graph">
+			<input
+				type="checkbox"
+				prop:checked=move || stroke_graph.get()
+				on:change=change_stroke_graph
+			/>
+			<span class="DocumentSettingNote">
+				"Compares the pointer's points with the smoothed stroke, to help tune smoothing."
+			</span>
+		</BrushSetting>
+		<BrushSetting name="GPU profiler">
+			<input
+				type="checkbox"
+				prop:checked=move || gpu_profiler.get()
+				on:change=change_gpu_profiler
+			/>
+			<span class="DocumentSettingNote">
+				"Shows how long each pass takes on the GPU, where the browser allows timestamp queries."
+			</span>
+		</BrushSetting>
+		<BrushSetting name="Latency">
+			<input
+				type="checkbox"
+				prop:checked=move || latency_hud.get()
+				on:change=change_latency_hud
+			/>
+			<span class="DocumentSettingNote">
+				"Shows how long strokes take to be submitted, drawn by the GPU, and presented."
+			</span>
+		</BrushSetting>
+		<BrushSetting name="Pick">
+			<input type="checkbox" prop:checked=move || pick_hud.get() on:change=change_pick_hud/>
+			<span class="DocumentSettingNote">
+				"Shows the chart, tile layer, and latest stroke under the pointer."
+			</span>
+		</BrushSetting>
+		<BrushSetting name="Pointer prediction">
+			<input
+				type="checkbox"
+				prop:checked=move || predict_pointer.get()
+				on:change=change_predict_pointer
+			/>
+			<span class="DocumentSettingNote">
+				"Draws strokes a little ahead of the pointer, where it is predicted to go, so they feel "
+				"more responsive. The guess is corrected as the pointer catches up."
+			</span>
+		</BrushSetting>
+		<button on:click=export_action_log>"Export action log"</button>
+		<span class="DocumentSettingNote">
+			"Recent strokes, without the painting, for attaching to bug reports."
+		</span>
+		<button on:click=export_vector_drawing>"Export strokes"</button>
+		<span class="DocumentSettingNote">
+			"Every stroke as curves, which can be redrawn at a higher resolution."
+		</span>
+		<button on:click=move |_| capture_frame.set(true) disabled=move || capture_frame.get()>
+			"Capture next frame"
+		</button>
+		<span class="DocumentSettingNote">
+			"Lists the pipelines, bind groups, tile layers, and draws of the next frame, where native "
+			"capture tools don't attach."
+		</span>
+		{move || {
+			captured_frame
+				.get()
+				.map(|frame| {
+					view! {
+						<pre class="FrameCapture">{frame.to_string()}</pre>
+						<button on:click=download_captured_frame>"Download capture"</button>
+					}
+				})
+		}}
+	}
+}
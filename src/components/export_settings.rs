@@ -0,0 +1,234 @@
+use crate::components::*;
+use crate::engine::{
+	self, Resampling, TimelapseRecorder, DEFAULT_TIMELAPSE_FRAME_DELAY, MAX_DITHER_BITS,
+	MAX_EXPORT_SCALE, MIN_DITHER_BITS, MIN_EXPORT_SCALE, TIMELAPSE_MIME_TYPE,
+};
+use crate::util::ResultExt;
+use crate::*;
+use leptos::prelude::*;
+use std::sync::Arc;
+
+const EXPORT_SCALES: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+#[component]
+pub fn ExportSettings(
+	atlas: AtlasHandle,
+	#[prop(into)] dpi: Signal<f64>,
+	documents: Documents,
+) -> impl IntoView {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	let resources: Arc<render::Resources> = use_context().unwrap();
+
+	let scale = RwSignal::new(1.0);
+	let resampling = RwSignal::new(Resampling::default());
+	let dither_bits = RwSignal::new(None::<u32>);
+	let exporting = RwSignal::new(false);
+	let error = RwSignal::new(None::<String>);
+
+	let scale_options = EXPORT_SCALES
+		.into_iter()
+		.filter(|s| (MIN_EXPORT_SCALE..=MAX_EXPORT_SCALE).contains(s))
+		.map(|s| view! { <option value=s.to_string()>{format!("{s}x")}</option> })
+		.collect_view();
+	let change_scale = move |e: leptos::ev::Event| {
+		if let Ok(value) = event_target_value(&e).parse() {
+			scale.set(value);
+		}
+	};
+
+	let resampling_options = Resampling::ALL
+		.into_iter()
+		.map(|r| {
+			let supported = r.is_supported(context.device());
+			view! {
+				<option value=r.to_string() disabled=!supported>
+					{r.to_string()}
+				</option>
+			}
+		})
+		.collect_view();
+	let change_resampling = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if let Some(r) = Resampling::ALL.into_iter().find(|r| r.to_string() == value) {
+			resampling.set(r);
+		}
+	};
+
+	let dithering_supported = engine::is_dithering_supported(context.device());
+	let dither_options = (MIN_DITHER_BITS..=MAX_DITHER_BITS)
+		.rev()
+		.map(|bits| view! { <option value=bits.to_string()>{format!("{bits}-bit")}</option> })
+		.collect_view();
+	let dither_value = move || {
+		dither_bits
+			.get()
+			.map_or("off".to_string(), |bits| bits.to_string())
+	};
+	let change_dither_bits = move |e: leptos::ev::Event| {
+		dither_bits.set(event_target_value(&e).parse().ok());
+	};
+
+	// The canvas notifies the handle whenever it changes, so this tracks what's drawn.
+	let export_size = Memo::new(move |_| {
+		let atlas = atlas.get()?;
+		let (_, size) = engine::export_region(&atlas.read().unwrap())?;
+		Some(engine::export_size(size, scale.get()))
+	});
+	let print_size = move || {
+		let size = export_size.get()?;
+		let inches = engine::print_size(size, dpi.get());
+		let centimeters = inches * 2.54;
+		Some(format!(
+			"{}\u{d7}{} px prints at {:.2}\u{d7}{:.2} in ({:.1}\u{d7}{:.1} cm)",
+			size.x, size.y, inches.x, inches.y, centimeters.x, centimeters.y,
+		))
+	};
+
+	// OpenRaster files open in other apps, such as Krita and GIMP, with the canvas as a layer.
+	let export_ora = {
+		let context = context.clone();
+		let resources = resources.clone();
+		move |_| {
+			let Some(atlas) = atlas.get_untracked() else {
+				return;
+			};
+			let ora =
+				engine::io::export_ora(&context, &resources, &[("Canvas", &*atlas.read().unwrap())]);
+			let ora = match ora {
+				Ok(ora) => ora,
+				Err(err) => {
+					error.set(Some(err.to_string()));
+					return;
+				}
+			};
+			error.set(None);
+			exporting.set(true);
+			leptos::task::spawn_local(async move {
+				match ora.await {
+					Ok(ora) => {
+						let filename = format!("stark.{}", engine::io::ORA_FILE_EXTENSION);
+						util::download(&ora.encode(), &filename, engine::io::ORA_MIME_TYPE).ok_or_log();
+					}
+					Err(err) => error.set(Some(err.to_string())),
+				}
+				exporting.set(false);
+			});
+		}
+	};
+
+	// Each document records its own timelapse, which carries on while other documents are shown.
+	let recording_changed = ArcTrigger::new();
+	let recording = {
+		let recording_changed = recording_changed.clone();
+		move || {
+			recording_changed.track();
+			documents
+				.active()
+				.is_some_and(|document| document.timelapse.borrow().is_some())
+		}
+	};
+	let change_recording = move |e: leptos::ev::Event| {
+		let Some(document) = documents.active() else {
+			return;
+		};
+		*document.timelapse.borrow_mut() = event_target_checked(&e).then(TimelapseRecorder::default);
+		recording_changed.notify();
+	};
+	let download_timelapse = move |_| {
+		let Some(document) = documents.active() else {
+			return;
+		};
+		let Some(encoded) = document
+			.timelapse
+			.borrow()
+			.as_ref()
+			.map(|recorder| recorder.encode(DEFAULT_TIMELAPSE_FRAME_DELAY))
+		else {
+			return;
+		};
+		match encoded {
+			Ok(apng) => {
+				error.set(None);
+				let filename = format!("{}-timelapse.png", document.name);
+				util::download(&apng, &filename, TIMELAPSE_MIME_TYPE).ok_or_log();
+			}
+			Err(err) => error.set(Some(err.to_string())),
+		}
+	};
+
+	let export = move |_| {
+		let Some(atlas) = atlas.get_untracked() else {
+			return;
+		};
+		let texture = engine::export(&atlas.read().unwrap())
+			.scale(scale.get_untracked())
+			.resampling(resampling.get_untracked())
+			.maybe_dither_bits(dither_bits.get_untracked())
+			.generate(context.device(), context.queue(), &resources);
+		let texture = match texture {
+			Ok(texture) => texture,
+			Err(err) => {
+				error.set(Some(err.to_string()));
+				return;
+			}
+		};
+		error.set(None);
+		exporting.set(true);
+		let png = engine::encode_png(&context, &texture, Some(dpi.get_untracked()));
+		leptos::task::spawn_local(async move {
+			match png.await {
+				Ok(data) => {
+					util::download(&data, "stark.png", "image/png").ok_or_log();
+				}
+				Err(err) => error.set(Some(err.to_string())),
+			}
+			exporting.set(false);
+		});
+	};
+
+	view! {
+		<BrushSetting name="Scale">
+			<select on:change=change_scale prop:value=move || scale.get().to_string()>
+				{scale_options}
+			</select>
+		</BrushSetting>
+		<BrushSetting name="Resampling">
+			<select
+				on:change=change_resampling
+				prop:value=move || resampling.get().to_string()
+			>
+				{resampling_options}
+			</select>
+		</BrushSetting>
+		<BrushSetting name="Dither">
+			<select
+				on:change=change_dither_bits
+				prop:value=dither_value
+				disabled=!dithering_supported
+			>
+				<option value="off">"Off"</option>
+				{dither_options}
+			</select>
+			<span class="DocumentSettingNote">
+				"Blue noise dithering keeps smooth gradients from banding."
+			</span>
+		</BrushSetting>
+		{move || print_size().map(|size| view! { <span class="DocumentSettingNote">{size}</span> })}
+		<button on:click=export disabled=move || exporting.get()>
+			"Export PNG"
+		</button>
+		<button on:click=export_ora disabled=move || exporting.get()>
+			"Export OpenRaster"
+		</button>
+		<BrushSetting name="Timelapse">
+			<input type="checkbox" prop:checked=recording.clone() on:change=change_recording/>
+			<span class="DocumentSettingNote">
+				"Records a frame as strokes end. Stopping discards the frames."
+			</span>
+		</BrushSetting>
+		<button on:click=download_timelapse disabled=move || !recording()>
+			"Download timelapse"
+		</button>
+		{move || error.get().map(|error| view! { <span class="DocumentSettingNote">{error}</span> })}
+	}
+}
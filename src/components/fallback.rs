@@ -5,6 +5,17 @@ pub fn Initializing() -> impl IntoView {
 	view! { "Initializing..." }
 }
 
+/// Shown in place of what needs WebGPU when running in compatibility mode.
+#[component]
+pub fn Unsupported(#[prop(into)] feature: String) -> impl IntoView {
+	view! {
+		<div class="Unsupported">
+			{feature} " needs WebGPU, which this browser doesn't support. "
+			"Everything else should still work."
+		</div>
+	}
+}
+
 #[component]
 pub fn ErrorList(#[prop(into)] errors: ArcSignal<Errors>) -> impl IntoView {
 	view! {
@@ -0,0 +1,105 @@
+use crate::components::Documents;
+use crate::engine::io::{OraFile, ORA_FILE_EXTENSION, ORA_MIME_TYPE};
+use crate::engine::{BrushPreset, Palette, PresetFile, ProjectFile, PROJECT_FILE_EXTENSION};
+use crate::render::Resources;
+use crate::util::{self, ResultExt};
+use crate::WgpuContext;
+use leptos::prelude::*;
+use leptos::web_sys;
+use std::sync::Arc;
+
+/// What a file dropped on the canvas is taken to be, judging by its type and name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DroppedFileKind {
+	/// Painted onto the canvas where it is dropped.
+	Image,
+	/// Opened as a new document.
+	Project,
+	/// An OpenRaster file from another app, opened as a new document with its layers flattened.
+	OpenRaster,
+	/// Brush presets and palettes to import.
+	Presets,
+}
+
+impl DroppedFileKind {
+	pub fn of(file: &web_sys::File) -> Option<Self> {
+		let name = file.name().to_lowercase();
+		let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+		match (file.type_().as_str(), extension) {
+			("image/png" | "image/jpeg", _) => Some(Self::Image),
+			(_, Some(extension)) if extension == PROJECT_FILE_EXTENSION => Some(Self::Project),
+			(ORA_MIME_TYPE, _) => Some(Self::OpenRaster),
+			(_, Some(extension)) if extension == ORA_FILE_EXTENSION => Some(Self::OpenRaster),
+			("application/json", _) | (_, Some("json")) => Some(Self::Presets),
+			_ => None,
+		}
+	}
+}
+
+/// Opens a dropped project as a new document, or imports dropped presets. Images are left to the
+/// canvas, which knows where they were dropped.
+pub async fn open_dropped_file(
+	kind: DroppedFileKind,
+	file: web_sys::File,
+	context: Arc<WgpuContext>,
+	resources: Arc<Resources>,
+	documents: Documents,
+	brush_presets: RwSignal<Vec<BrushPreset>>,
+	palettes: RwSignal<Vec<Palette>>,
+) {
+	let Some(data) = util::read_file(&file).await.ok_or_log() else {
+		return;
+	};
+	match kind {
+		DroppedFileKind::Image => {}
+		DroppedFileKind::Project => {
+			let Some(project) = ProjectFile::decode(&data).ok_or_log() else {
+				return;
+			};
+			documents.open_project(context, file_stem(&file), project);
+		}
+		DroppedFileKind::OpenRaster => {
+			let Some(ora) = OraFile::decode(&data).ok_or_log() else {
+				return;
+			};
+			documents
+				.open_ora(context, &resources, file_stem(&file), &ora)
+				.ok_or_log();
+		}
+		DroppedFileKind::Presets => {
+			let Some(text) = String::from_utf8(data).ok_or_log() else {
+				return;
+			};
+			let Some(presets) = PresetFile::decode(&text).ok_or_log() else {
+				return;
+			};
+			brush_presets.update(|brush_presets| brush_presets.extend(presets.brushes));
+			palettes.update(|palettes| palettes.extend(presets.palettes));
+		}
+	}
+}
+
+/// The name of `file` without its extension, for naming the document it is opened as.
+fn file_stem(file: &web_sys::File) -> String {
+	let name = file.name();
+	name
+		.rsplit_once('.')
+		.map_or(name.as_str(), |(stem, _)| stem)
+		.into()
+}
+
+/// Whether a drag carries files, as opposed to e.g. text selected on the page.
+pub fn is_dragging_files(e: &leptos::ev::DragEvent) -> bool {
+	e.data_transfer()
+		.is_some_and(|d| d.types().includes(&"Files".into(), 0))
+}
+
+/// Highlights the element it is placed in while files are dragged over it.
+#[component]
+pub fn DropIndicator(#[prop(into)] active: Signal<bool>) -> impl IntoView {
+	view! {
+		<div class="DropIndicator" class:active=move || active.get()>
+			"Drop images, projects, OpenRaster files, or presets"
+		</div>
+	}
+}
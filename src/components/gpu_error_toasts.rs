@@ -0,0 +1,73 @@
+use crate::render::{self, GpuError};
+use crate::util::{self, ResultExt};
+use leptos::prelude::*;
+use std::time::Duration;
+
+/// How long the oldest toast stays after the last error.
+const TOAST_DURATION: Duration = Duration::from_secs(8);
+
+/// How many toasts are shown at once. Older ones are dropped first.
+const MAX_TOASTS: usize = 3;
+
+#[derive(Clone)]
+struct Toast {
+	error: GpuError,
+	/// How many times the error was reported while shown.
+	count: usize,
+}
+
+/// Shows GPU errors reported through [`render::report_gpu_error`] as toasts, which go away by
+/// themselves. The app carries on without whatever failed, so they're only a heads-up.
+#[component]
+pub fn GpuErrorToasts() -> impl IntoView {
+	let toasts = RwSignal::new(Vec::<Toast>::new());
+
+	// An error raised every frame is shown once, counting how often it recurs.
+	let subscription = render::subscribe_gpu_errors(move |error| {
+		toasts.try_update(|toasts| {
+			if let Some(toast) = toasts.iter_mut().find(|toast| toast.error == *error) {
+				toast.count += 1;
+			} else {
+				toasts.push(Toast {
+					error: error.clone(),
+					count: 1,
+				});
+				if toasts.len() > MAX_TOASTS {
+					toasts.remove(0);
+				}
+			}
+		});
+	});
+	on_cleanup(move || drop(subscription));
+
+	// Each change restarts the timer, so toasts stay while errors keep being reported.
+	Effect::new(move |_| {
+		if toasts.with(|toasts| !toasts.is_empty()) {
+			util::set_timeout_and_clean_up(
+				move || toasts.update(|toasts| drop(toasts.remove(0))),
+				TOAST_DURATION,
+			)
+			.ok_or_log();
+		}
+	});
+
+	let toast = move |(index, toast): (usize, Toast)| {
+		let count = (toast.count > 1).then(|| format!(" (×{})", toast.count));
+		view! {
+			<div class="GpuErrorToast" role="alert">
+				<p>
+					<strong>{toast.error.label}{count}</strong>
+				</p>
+				<p>{toast.error.message}</p>
+				<button on:click=move |_| toasts.update(|toasts| drop(toasts.remove(index)))>
+					"Dismiss"
+				</button>
+			</div>
+		}
+	};
+	view! {
+		<div class="GpuErrorToasts">
+			{move || toasts.get().into_iter().enumerate().map(toast).collect_view()}
+		</div>
+	}
+}
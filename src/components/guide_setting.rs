@@ -0,0 +1,58 @@
+use crate::components::*;
+use crate::engine::GuideKind;
+use leptos::prelude::*;
+
+/// Chooses the guides drawn over the canvas, and whether strokes snap to them, as well as whether
+/// the pixel grid is drawn and shapes snap to pixels.
+#[component]
+pub fn GuideSetting(
+	guides: RwSignal<Option<GuideKind>>,
+	snap: RwSignal<bool>,
+	pixel_grid: RwSignal<bool>,
+	snap_to_pixels: RwSignal<bool>,
+) -> impl IntoView {
+	let name = |kind: Option<GuideKind>| kind.map_or("None".to_string(), |k| k.to_string());
+	let kinds = || std::iter::once(None).chain(GuideKind::ALL.map(Some));
+
+	let options = kinds()
+		.map(|k| view! { <option value=name(k)>{name(k)}</option> })
+		.collect_view();
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if let Some(k) = kinds().find(|&k| name(k) == value) {
+			guides.set(k);
+		}
+	};
+	let change_snap = move |e: leptos::ev::Event| snap.set(event_target_checked(&e));
+	let change_pixel_grid = move |e: leptos::ev::Event| pixel_grid.set(event_target_checked(&e));
+	let change_snap_to_pixels =
+		move |e: leptos::ev::Event| snap_to_pixels.set(event_target_checked(&e));
+
+	view! {
+		<BrushSetting name="Guides">
+			<select on:change=change prop:value=move || name(guides.get())>
+				{options}
+			</select>
+			<span class="DocumentSettingNote">"Guides are placed about the center of the view."</span>
+		</BrushSetting>
+		<BrushSetting name="Snap">
+			<input
+				type="checkbox"
+				prop:checked=move || snap.get()
+				disabled=move || guides.get().is_none()
+				on:change=change_snap
+			/>
+		</BrushSetting>
+		<BrushSetting name="Pixel grid">
+			<input type="checkbox" prop:checked=move || pixel_grid.get() on:change=change_pixel_grid/>
+			<span class="DocumentSettingNote">"Shown once pixels are large enough to tell apart."</span>
+		</BrushSetting>
+		<BrushSetting name="Snap to pixels">
+			<input
+				type="checkbox"
+				prop:checked=move || snap_to_pixels.get()
+				on:change=change_snap_to_pixels
+			/>
+		</BrushSetting>
+	}
+}
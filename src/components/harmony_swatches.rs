@@ -0,0 +1,42 @@
+use crate::util::ColorHarmony;
+use glam::*;
+use leptos::prelude::*;
+
+/// Suggests colors going with `color` by a chosen [`ColorHarmony`], setting `color` to whichever is
+/// clicked.
+#[component]
+pub fn HarmonySwatches(color: RwSignal<Vec3>) -> impl IntoView {
+	let harmony = RwSignal::new(ColorHarmony::Complementary);
+
+	let options = ColorHarmony::ALL
+		.map(|h| view! { <option value=h.to_string()>{h.to_string()}</option> })
+		.collect_view();
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if let Some(h) = ColorHarmony::ALL
+			.into_iter()
+			.find(|h| h.to_string() == value)
+		{
+			harmony.set(h);
+		}
+	};
+
+	let swatch = move |swatch_color: Vec3| {
+		let style = format!(
+			"background-color: oklab({} {} {})",
+			swatch_color.x, swatch_color.y, swatch_color.z
+		);
+		view! { <button class="Swatch" style=style on:click=move |_| color.set(swatch_color)></button> }
+	};
+
+	view! {
+		<div class="HarmonySwatches">
+			<select aria-label="Harmony" on:change=change prop:value=move || harmony.get().to_string()>
+				{options}
+			</select>
+			<div class="Swatches">
+				{move || harmony.get().companions(color.get()).into_iter().map(swatch).collect_view()}
+			</div>
+		</div>
+	}
+}
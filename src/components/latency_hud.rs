@@ -0,0 +1,20 @@
+use crate::util::{LatencyAverages, LatencyStage};
+use itertools::Itertools;
+use leptos::prelude::*;
+
+/// Shows the measured pen latency to each stage of drawing over the canvas.
+#[component]
+pub fn LatencyHud(#[prop(into)] latency: Signal<LatencyAverages>) -> impl IntoView {
+	let text = move || {
+		let latency = latency.get();
+		if latency.get(LatencyStage::Submitted).is_none() {
+			return "Latency: draw to measure".to_string();
+		}
+		let stages = LatencyStage::ALL
+			.into_iter()
+			.filter_map(|stage| Some(format!("{stage} {:.1} ms", latency.get(stage)?)))
+			.join(" · ");
+		format!("Latency: {stages}")
+	};
+	view! { <div class="LatencyHud">{text}</div> }
+}
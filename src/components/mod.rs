@@ -12,11 +12,107 @@ pub use canvas::*;
 mod color_picker;
 pub use color_picker::*;
 
-mod keyboard_state;
-pub use keyboard_state::*;
+mod color_inputs;
+pub use color_inputs::*;
+
+mod harmony_swatches;
+pub use harmony_swatches::*;
 
 mod panel;
 pub use panel::*;
 
 mod brush_setting;
 pub use brush_setting::*;
+
+mod brush_preview;
+pub use brush_preview::*;
+
+mod symmetry_setting;
+pub use symmetry_setting::*;
+
+mod brush_mode_setting;
+pub use brush_mode_setting::*;
+
+mod brush_shape_setting;
+pub use brush_shape_setting::*;
+
+mod selection_setting;
+pub use selection_setting::*;
+
+mod shape_setting;
+pub use shape_setting::*;
+
+mod guide_setting;
+pub use guide_setting::*;
+
+mod document_settings;
+pub use document_settings::*;
+
+mod export_settings;
+pub use export_settings::*;
+
+mod view_settings;
+pub use view_settings::*;
+
+mod saved_settings;
+pub use saved_settings::*;
+
+mod autosave;
+pub use autosave::*;
+
+mod documents;
+pub use documents::*;
+
+mod document_tabs;
+pub use document_tabs::*;
+
+mod presets;
+pub use presets::*;
+
+mod file_drop;
+pub use file_drop::*;
+
+mod selection_outline;
+pub use selection_outline::*;
+
+mod transform_handles;
+pub use transform_handles::*;
+
+mod shape_preview;
+pub use shape_preview::*;
+
+mod latency_hud;
+pub use latency_hud::*;
+
+mod profiler_hud;
+pub use profiler_hud::*;
+
+mod pick_hud;
+pub use pick_hud::*;
+
+mod too_large_notice;
+pub use too_large_notice::*;
+
+mod brush_readout;
+pub use brush_readout::*;
+
+mod shortcuts;
+pub use shortcuts::*;
+
+mod stroke_graph;
+pub use stroke_graph::*;
+
+mod experimental_settings;
+pub use experimental_settings::*;
+
+mod announcer;
+pub use announcer::*;
+
+mod pipeline_cache;
+pub use pipeline_cache::*;
+
+mod diagnostics;
+pub use diagnostics::*;
+
+mod gpu_error_toasts;
+pub use gpu_error_toasts::*;
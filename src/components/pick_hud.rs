@@ -0,0 +1,26 @@
+use crate::engine::PickResult;
+use leptos::prelude::*;
+
+/// Shows what is under the pointer over the canvas: where it is on the canvas, which chart and layer
+/// hold it, and which stroke was drawn there last.
+#[component]
+pub fn PickHud(#[prop(into)] picked: Signal<Option<PickResult>>) -> impl IntoView {
+	let text = move || {
+		let Some(picked) = picked.get() else {
+			return "Pick: point at the canvas".to_string();
+		};
+		let point = picked.point.0;
+		let chart = picked.chart;
+		let layer = picked
+			.layer
+			.map_or("none".to_string(), |layer| layer.to_string());
+		let stroke = picked
+			.stroke
+			.map_or("none".to_string(), |stroke| stroke.to_string());
+		format!(
+			"Pick: ({:.1}, {:.1}) · chart ({}, {}) · layer {layer} · stroke {stroke}",
+			point.x, point.y, chart.0, chart.1
+		)
+	};
+	view! { <div class="PickHud">{text}</div> }
+}
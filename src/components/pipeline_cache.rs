@@ -0,0 +1,52 @@
+use crate::render::Resources;
+use crate::util::{self, ByteDatabase, ByteStore, ResultExt};
+use crate::WgpuContext;
+
+// Compiled pipelines are only reusable on the same adapter and driver, so they are kept apart from
+// documents, and losing them only costs the time to compile them again.
+const DATABASE_NAME: &str = "stark-pipeline-cache";
+const DATABASE_VERSION: u32 = 1;
+const STORE_NAME: &str = "pipeline_cache";
+
+/// The key the pipeline cache of `context`'s adapter is saved under, or `None` if it has none.
+fn pipeline_cache_key(context: &WgpuContext) -> Option<String> {
+	if !context
+		.device()
+		.features()
+		.contains(wgpu::Features::PIPELINE_CACHE)
+	{
+		return None;
+	}
+	wgpu::util::pipeline_cache_key(&context.adapter().get_info())
+}
+
+async fn open_store() -> Result<ByteStore, util::JsError> {
+	let database = ByteDatabase::open(DATABASE_NAME, DATABASE_VERSION, &[STORE_NAME]).await?;
+	Ok(database.store(STORE_NAME))
+}
+
+/// Loads the pipeline cache saved by [`save_pipeline_cache`] for `context`'s adapter, for
+/// [`Resources::with_pipeline_cache_data`].
+pub async fn load_pipeline_cache(context: &WgpuContext) -> Option<Vec<u8>> {
+	let key = pipeline_cache_key(context)?;
+	open_store()
+		.await
+		.ok_or_log()?
+		.get(&key)
+		.await
+		.ok_or_log()?
+}
+
+/// Saves the pipeline cache of `resources`, which must have been created for `context`.
+pub async fn save_pipeline_cache(context: &WgpuContext, resources: &Resources) {
+	let Some(key) = pipeline_cache_key(context) else {
+		return;
+	};
+	let Some(data) = resources.pipeline_cache_data() else {
+		return;
+	};
+	let Some(store) = open_store().await.ok_or_log() else {
+		return;
+	};
+	store.put(&key, &data).await.ok_or_log();
+}
@@ -0,0 +1,272 @@
+use crate::components::*;
+use crate::engine::{
+	built_in_brush_presets, BrushMode, BrushPreset, Palette, PresetFile, BUILT_IN_SHAPES,
+	DEFAULT_BRUSH_SHAPE,
+};
+use crate::util::{self, ResultExt};
+use glam::Vec3;
+use leptos::prelude::*;
+use leptos::web_sys;
+
+/// Reads each of `files` as a preset file, passing its contents to `import`. Problems are reported
+/// through `error`.
+fn import_preset_files(
+	files: impl IntoIterator<Item = web_sys::File>,
+	error: RwSignal<Option<String>>,
+	import: impl Fn(PresetFile) + Clone + 'static,
+) {
+	for file in files {
+		let import = import.clone();
+		leptos::task::spawn_local(async move {
+			let decoded = match util::read_file(&file).await {
+				Ok(data) => String::from_utf8(data)
+					.map_err(|err| err.to_string())
+					.and_then(|text| PresetFile::decode(&text).map_err(|err| err.to_string())),
+				Err(err) => Err(format!("{err:?}")),
+			};
+			match decoded {
+				Ok(decoded) => {
+					error.set(None);
+					import(decoded);
+				}
+				Err(err) => error.set(Some(format!("{}: {err}", file.name()))),
+			}
+		});
+	}
+}
+
+/// Import and export buttons for preset files, which also accept files dropped anywhere on
+/// `children`.
+#[component]
+fn PresetFileControls(
+	// The file to export, or `None` if there is nothing to export.
+	export: impl Fn() -> Option<PresetFile> + 'static,
+	filename: &'static str,
+	import: impl Fn(PresetFile) + Clone + 'static,
+	children: Children,
+) -> impl IntoView {
+	let error = RwSignal::new(None::<String>);
+	let file_input = NodeRef::<leptos::html::Input>::new();
+
+	let files_chosen = {
+		let import = import.clone();
+		move |e: leptos::ev::Event| {
+			import_preset_files(util::take_chosen_files(&e), error, import.clone());
+		}
+	};
+	let dragover = move |e: leptos::ev::DragEvent| {
+		// This is required to allow dropping.
+		e.prevent_default();
+	};
+	let drop = move |e: leptos::ev::DragEvent| {
+		e.prevent_default();
+		if let Some(files) = e.data_transfer().and_then(|d| d.files()) {
+			let files = (0..files.length()).filter_map(|i| files.get(i));
+			import_preset_files(files, error, import.clone());
+		}
+	};
+	let export = move |_| {
+		if let Some(file) = export() {
+			util::download(file.encode().as_bytes(), filename, "application/json").ok_or_log();
+		}
+	};
+
+	view! {
+		<div class="PresetDropTarget" on:dragover=dragover on:drop=drop>
+			{children()}
+			<div class="PresetFileControls">
+				<input
+					type="file"
+					accept="application/json,.json"
+					multiple
+					hidden
+					node_ref=file_input
+					on:change=files_chosen
+				/>
+				<button on:click=move |_| {
+					if let Some(file_input) = file_input.get_untracked() {
+						file_input.click();
+					}
+				}>"Import"</button>
+				<button on:click=export>"Export"</button>
+			</div>
+			{move || error.get().map(|error| view! { <span class="DocumentSettingNote">{error}</span> })}
+		</div>
+	}
+}
+
+/// Saved brush settings. Presets can be shared as files, and dropping one here imports its brushes.
+#[component]
+pub fn BrushPresets(
+	// The presets saved or imported by the user. The built-in presets are always shown first.
+	presets: RwSignal<Vec<BrushPreset>>,
+	// The size slider's value, which is the square root of the brush size.
+	input_brush_size: RwSignal<f64>,
+	brush_opacity: RwSignal<f64>,
+	brush_rate: RwSignal<f64>,
+	smoothing: RwSignal<f64>,
+	// The name of the brush shape.
+	brush_shape: RwSignal<String>,
+	brush_spacing: RwSignal<f64>,
+	brush_scatter: RwSignal<f64>,
+	brush_grain: RwSignal<f64>,
+	brush_hardness: RwSignal<f64>,
+	brush_spread: RwSignal<f64>,
+	brush_mode: RwSignal<BrushMode>,
+	brush_pickup: RwSignal<f64>,
+) -> impl IntoView {
+	let save = move |_| {
+		let size = input_brush_size.get_untracked();
+		// Presets only refer to built-in shapes, so an imported shape isn't saved.
+		let shape = brush_shape.get_untracked();
+		let shape = match BUILT_IN_SHAPES.contains(&shape.as_str()) {
+			true => shape,
+			false => DEFAULT_BRUSH_SHAPE.into(),
+		};
+		presets.update(|presets| {
+			let preset = BrushPreset {
+				name: format!("Preset {}", presets.len() + 1),
+				shape,
+				size: size * size,
+				opacity: brush_opacity.get_untracked(),
+				rate: brush_rate.get_untracked(),
+				smoothing: smoothing.get_untracked(),
+				spacing: brush_spacing.get_untracked(),
+				scatter: brush_scatter.get_untracked(),
+				grain: brush_grain.get_untracked(),
+				hardness: brush_hardness.get_untracked(),
+				spread: brush_spread.get_untracked(),
+				mode: brush_mode.get_untracked(),
+				pickup: brush_pickup.get_untracked(),
+			};
+			presets.push(preset);
+		});
+	};
+	let apply = move |preset: &BrushPreset| {
+		input_brush_size.set(preset.size.sqrt());
+		brush_opacity.set(preset.opacity);
+		brush_rate.set(preset.rate);
+		smoothing.set(preset.smoothing);
+		brush_shape.set(preset.shape.clone());
+		brush_spacing.set(preset.spacing);
+		brush_scatter.set(preset.scatter);
+		brush_grain.set(preset.grain);
+		brush_hardness.set(preset.hardness);
+		brush_spread.set(preset.spread);
+		brush_mode.set(preset.mode);
+		brush_pickup.set(preset.pickup);
+	};
+	// Whether the brush is set up as `preset` is, so that it is highlighted until a setting changes.
+	let is_current = move |preset: &BrushPreset| {
+		let size = input_brush_size.get();
+		(size * size - preset.size).abs() <= 1e-6 * preset.size
+			&& brush_opacity.get() == preset.opacity
+			&& brush_rate.get() == preset.rate
+			&& smoothing.get() == preset.smoothing
+			&& brush_shape.with(|shape| *shape == preset.shape)
+			&& brush_spacing.get() == preset.spacing
+			&& brush_scatter.get() == preset.scatter
+			&& brush_grain.get() == preset.grain
+			&& brush_hardness.get() == preset.hardness
+			&& brush_spread.get() == preset.spread
+			&& brush_mode.get() == preset.mode
+			&& brush_pickup.get() == preset.pickup
+	};
+	let preset_button = move |preset: BrushPreset| {
+		let name = preset.name.clone();
+		let current = {
+			let preset = preset.clone();
+			move || is_current(&preset)
+		};
+		view! {
+			<button class:active=current on:click=move |_| apply(&preset)>
+				{name}
+			</button>
+		}
+	};
+	let built_in = built_in_brush_presets()
+		.into_iter()
+		.map(preset_button)
+		.collect_view();
+
+	let export = move || {
+		let brushes = presets.get_untracked();
+		(!brushes.is_empty()).then(|| PresetFile {
+			brushes,
+			..Default::default()
+		})
+	};
+	let import = move |file: PresetFile| presets.update(|presets| presets.extend(file.brushes));
+
+	view! {
+		<BrushSetting name="Presets">
+			<PresetFileControls export=export filename="brushes.json" import=import>
+				<div class="BrushPresets">
+					{built_in}
+					<For
+						each=move || presets.get().into_iter().enumerate()
+						key=|(index, preset)| (*index, preset.name.clone())
+						children=move |(_, preset)| preset_button(preset)
+					/>
+					<button on:click=save>"Save"</button>
+				</div>
+			</PresetFileControls>
+		</BrushSetting>
+	}
+}
+
+/// Palettes of colors to pick from. Like [`BrushPresets`], palettes can be shared as files.
+#[component]
+pub fn Palettes(palettes: RwSignal<Vec<Palette>>, color: RwSignal<Vec3>) -> impl IntoView {
+	// Colors are added to the last palette, which is created if needed.
+	let add = move |_| {
+		let color = color.get_untracked();
+		palettes.update(|palettes| {
+			if palettes.is_empty() {
+				palettes.push(Palette {
+					name: "Palette".into(),
+					colors: Vec::new(),
+				});
+			}
+			palettes.last_mut().unwrap().colors.push(color);
+		});
+	};
+
+	let export = move || {
+		let palettes = palettes.get_untracked();
+		(!palettes.is_empty()).then(|| PresetFile {
+			palettes,
+			..Default::default()
+		})
+	};
+	let import = move |file: PresetFile| palettes.update(|palettes| palettes.extend(file.palettes));
+
+	let swatch = move |swatch_color: Vec3| {
+		let style = format!(
+			"background-color: oklab({} {} {})",
+			swatch_color.x, swatch_color.y, swatch_color.z
+		);
+		view! { <button class="Swatch" style=style on:click=move |_| color.set(swatch_color)></button> }
+	};
+
+	view! {
+		<PresetFileControls export=export filename="palettes.json" import=import>
+			{move || {
+				palettes
+					.get()
+					.into_iter()
+					.map(|palette| {
+						view! {
+							<BrushSetting name=palette.name>
+								<div class="Swatches">
+									{palette.colors.into_iter().map(swatch).collect_view()}
+								</div>
+							</BrushSetting>
+						}
+					})
+					.collect_view()
+			}}
+			<button on:click=add>"Add color"</button>
+		</PresetFileControls>
+	}
+}
@@ -0,0 +1,58 @@
+use crate::render::{self, PassTimings};
+use crate::util::{self, ResultExt};
+use crate::WgpuContext;
+use leptos::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the shown timings are refreshed. Every frame would be too fast to read.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Times each pass on the GPU while shown, and lists how long they take over the canvas, along with
+/// how much was uploaded for the last frame.
+#[component]
+pub fn ProfilerHud() -> impl IntoView {
+	let context: Arc<WgpuContext> = use_context().unwrap();
+	let supported = context.profiler().is_supported();
+	context.profiler().set_enabled(true);
+	let timings = RwSignal::new(PassTimings::default());
+	let uploaded = RwSignal::new(0);
+	{
+		let context = context.clone();
+		let refresh = move || {
+			timings.set(context.profiler().timings());
+			uploaded.set(render::upload_metrics().last_frame_bytes());
+		};
+		util::set_interval_and_clean_up(refresh, REFRESH_INTERVAL).ok_or_log();
+	}
+	on_cleanup(move || context.profiler().set_enabled(false));
+
+	let rows = move || {
+		timings.with(|timings| {
+			timings
+				.iter()
+				.map(|(label, timing)| {
+					let text = format!("{label}: {:.2} ms", timing.average);
+					view! { <div>{text}</div> }
+				})
+				.collect_view()
+		})
+	};
+	let empty = move || timings.with(PassTimings::is_empty);
+	let uploads = move || format!("Uploaded: {:.1} KiB/frame", uploaded.get() as f64 / 1024.0);
+	view! {
+		<div class="ProfilerHud">
+			<div>{uploads}</div>
+			{if supported {
+				view! {
+					<Show when=move || !empty() fallback=|| "GPU time: draw to measure">
+						{rows}
+					</Show>
+				}
+					.into_any()
+			} else {
+				"GPU time: unsupported on this device".into_any()
+			}}
+		</div>
+	}
+}
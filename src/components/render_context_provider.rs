@@ -10,9 +10,25 @@ use std::sync::Arc;
 #[component]
 pub fn RenderContextProvider(
 	#[prop(optional, into)] initializing_fallback: ViewFnOnce,
+	// Which adapter to prefer where there are several, e.g. on laptops with two GPUs. Fixed for the
+	// lifetime of the provider.
+	#[prop(optional)] power_preference: wgpu::PowerPreference,
+	// Uses a software adapter. Fixed for the lifetime of the provider.
+	#[prop(optional)] force_fallback_adapter: bool,
+	// Which backends to consider, or `None` to pick them by what the browser supports. Fixed for the
+	// lifetime of the provider.
+	#[prop(optional)] backends: Option<wgpu::Backends>,
 	children: ChildrenFn,
 ) -> impl IntoView {
-	let resource = LocalResource::new(|| async { WgpuContext::new().await.map(Arc::new) });
+	let resource = LocalResource::new(move || async move {
+		WgpuContext::builder()
+			.power_preference(power_preference)
+			.force_fallback_adapter(force_fallback_adapter)
+			.maybe_backends(backends)
+			.create()
+			.await
+			.map(Arc::new)
+	});
 
 	view! {
 		<ErrorBoundary fallback=move |errors| view! { <ErrorList errors/> }>
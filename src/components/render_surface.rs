@@ -26,10 +26,30 @@ static_assertions::assert_impl_all!(Result<(), RenderSurfaceError>: leptos::Into
 
 pub type WgpuSurface = Rc<wgpu::Surface<'static>>;
 
+/// Creates the canvas's context ahead of wgpu with the `desynchronized` hint, which lets the browser
+/// show the canvas without waiting to composite it with the rest of the page. wgpu is given the same
+/// context when it asks for one. Only WebGL contexts accept the hint.
+#[cfg(all(target_arch = "wasm32", not(target_os = "emscripten")))]
+fn request_desynchronized_context(adapter: &wgpu::Adapter, element: &web_sys::HtmlCanvasElement) {
+	if adapter.get_info().backend != wgpu::Backend::Gl {
+		trace!("desynchronized contexts are only supported by WebGL");
+		return;
+	}
+	// Other than the hint, these must match the options wgpu requests.
+	let options = js_sys::Object::new();
+	for (key, value) in [("antialias", false), ("desynchronized", true)] {
+		js_sys::Reflect::set(&options, &key.into(), &value.into()).ok_or_log();
+	}
+	element
+		.get_context_with_context_options("webgl2", &options)
+		.ok_or_log();
+}
+
 #[tracing::instrument(err)]
 fn create_surface(
 	context: Arc<WgpuContext>,
 	element: web_sys::HtmlCanvasElement,
+	low_latency: bool,
 ) -> Result<WgpuSurface, RenderSurfaceError> {
 	use RenderSurfaceError::*;
 
@@ -38,6 +58,9 @@ fn create_surface(
 
 	#[cfg(all(target_arch = "wasm32", not(target_os = "emscripten")))]
 	{
+		if low_latency {
+			request_desynchronized_context(context.adapter(), &element);
+		}
 		surface = Ok(context
 			.instance()
 			.create_surface(wgpu::SurfaceTarget::Canvas(element))?);
@@ -71,6 +94,12 @@ pub fn RenderSurface(
 	#[prop(optional, into)] configured: Option<ConfiguredCallback>,
 	#[prop(default = 250.0, into)] min_configure_interval: f64,
 	#[prop(optional, into)] render_size: Option<WriteSignal<(u32, u32)>>,
+	// How the rendered image is composited with the page, overriding the configuration when the
+	// surface supports it. `PreMultiplied` lets the page show through transparent regions.
+	#[prop(optional_no_strip)] alpha_mode: Option<wgpu::CompositeAlphaMode>,
+	// Trades smoothness for latency by requesting a desynchronized context, where supported, and
+	// queuing at most one frame. Fixed once the surface is created.
+	#[prop(optional)] low_latency: bool,
 ) -> impl IntoView {
 	let context: Arc<WgpuContext> = use_context().unwrap();
 
@@ -82,7 +111,7 @@ pub fn RenderSurface(
 			let context = context.clone();
 			node_ref
 				.get()
-				.and_then(move |element| create_surface(context, element).ok_or_log())
+				.and_then(move |element| create_surface(context, element, low_latency).ok_or_log())
 		})
 	};
 
@@ -151,10 +180,21 @@ pub fn RenderSurface(
 		let context = context.clone();
 		move |args: ConfigureArgs| -> bool {
 			let surface = args.0.clone();
-			let Some(configuration) = configure(args.clone()) else {
+			let Some(mut configuration) = configure(args.clone()) else {
 				warn!(?args, "Failed to configure surface");
 				return false;
 			};
+			if low_latency {
+				configuration.desired_maximum_frame_latency = 1;
+			}
+			if let Some(alpha_mode) = alpha_mode {
+				let capabilities = surface.get_capabilities(context.adapter());
+				if capabilities.alpha_modes.contains(&alpha_mode) {
+					configuration.alpha_mode = alpha_mode;
+				} else {
+					warn!(?alpha_mode, "unsupported composite alpha mode");
+				}
+			}
 			surface.configure(context.device(), &configuration);
 			clear_needs_reconfigure();
 			if let Some(configured) = &configured {
@@ -0,0 +1,72 @@
+use crate::engine::{self, BrushMode, StampProfile};
+use crate::util::{self, ResultExt};
+use crate::view_transform::ViewTransform;
+use glam::Vec3;
+
+// Settings are small and needed before anything is shown, so they are kept in local storage rather
+// than with documents in IndexedDB.
+const BRUSH_SETTINGS_KEY: &str = "stark-brush-settings";
+const VIEW_KEY: &str = "stark-view";
+
+/// The brush as it was last set up, restored when the page loads.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct BrushSettings {
+	/// In Oklab.
+	pub color: Vec3,
+	/// The value of the size slider, whose square is the brush size.
+	pub input_size: f64,
+	pub opacity: f64,
+	pub rate: f64,
+	pub smoothing: f64,
+	/// In milliseconds, as the slider shows it.
+	pub smoothing_interval: f64,
+	pub mode: BrushMode,
+	pub shape: String,
+	pub spacing: f64,
+	pub scatter: f64,
+	pub grain: f64,
+	pub pickup: f64,
+	pub hardness: f64,
+	pub spread: f64,
+}
+
+impl Default for BrushSettings {
+	fn default() -> Self {
+		Self {
+			color: Vec3::new(0.5, 0.0, 0.0),
+			input_size: 16.0,
+			opacity: 1.0,
+			rate: 25.0,
+			smoothing: 0.0,
+			smoothing_interval: (util::input_interpolate::DEFAULT_STABILIZER_INTERVAL * 1000.0) as f64,
+			mode: BrushMode::Paint,
+			shape: engine::DEFAULT_BRUSH_SHAPE.to_string(),
+			spacing: engine::DEFAULT_BRUSH_SPACING as f64,
+			scatter: engine::DEFAULT_BRUSH_SCATTER as f64,
+			grain: engine::DEFAULT_BRUSH_GRAIN as f64,
+			pickup: engine::DEFAULT_BRUSH_PICKUP as f64,
+			hardness: StampProfile::default().hardness as f64,
+			spread: StampProfile::default().spread as f64,
+		}
+	}
+}
+
+/// The brush settings saved by [`save_brush_settings`], or the defaults if there are none.
+pub fn load_brush_settings() -> BrushSettings {
+	util::load_from_local_storage(BRUSH_SETTINGS_KEY).unwrap_or_default()
+}
+
+pub fn save_brush_settings(settings: &BrushSettings) {
+	util::save_to_local_storage(BRUSH_SETTINGS_KEY, settings).ok_or_log();
+}
+
+/// The view saved by [`save_view`], if there is one. Zoom limits aren't saved, so the canvas applies
+/// its own.
+pub fn load_view() -> Option<ViewTransform> {
+	util::load_from_local_storage(VIEW_KEY)
+}
+
+pub fn save_view(view: &ViewTransform) {
+	util::save_to_local_storage(VIEW_KEY, view).ok_or_log();
+}
@@ -0,0 +1,32 @@
+use crate::coordinates::CanvasPt;
+use crate::view_transform::ViewTransform;
+use glam::Vec2;
+use itertools::Itertools;
+use leptos::prelude::*;
+
+/// Draws the outline of the selection over the canvas.
+#[component]
+pub fn SelectionOutline(
+	// In canvas coordinates.
+	#[prop(into)] outline: Signal<Option<Vec<Vec2>>>,
+	#[prop(into)] view_transform: Signal<ViewTransform>,
+) -> impl IntoView {
+	let points = move || {
+		let view_transform = view_transform.get();
+		outline.with(|outline| {
+			let outline = outline.as_ref()?;
+			let points = outline
+				.iter()
+				.map(|p| CanvasPt(p.as_dvec2()).to_screen(&view_transform).0)
+				.map(|p| format!("{},{}", p.x, p.y))
+				.join(" ");
+			Some(points)
+		})
+	};
+
+	view! {
+		<svg class="SelectionOutline">
+			{move || points().map(|points| view! { <polygon points=points></polygon> })}
+		</svg>
+	}
+}
@@ -0,0 +1,29 @@
+use crate::components::*;
+use crate::engine::SelectionTool;
+use leptos::prelude::*;
+
+/// Chooses whether dragging on the canvas paints or selects.
+#[component]
+pub fn SelectionSetting(tool: RwSignal<Option<SelectionTool>>) -> impl IntoView {
+	let name = |tool: Option<SelectionTool>| tool.map_or("Paint".to_string(), |t| t.to_string());
+	let tools = || std::iter::once(None).chain(SelectionTool::ALL.map(Some));
+
+	let options = tools()
+		.map(|t| view! { <option value=name(t)>{name(t)}</option> })
+		.collect_view();
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if let Some(t) = tools().find(|&t| name(t) == value) {
+			tool.set(t);
+		}
+	};
+
+	view! {
+		<BrushSetting name="Tool">
+			<select on:change=change prop:value=move || name(tool.get())>
+				{options}
+			</select>
+			<span class="DocumentSettingNote">"Painting is confined to the selection. T transforms it, and Escape clears it."</span>
+		</BrushSetting>
+	}
+}
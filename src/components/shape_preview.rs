@@ -0,0 +1,40 @@
+use crate::coordinates::CanvasPt;
+use crate::view_transform::ViewTransform;
+use glam::Vec2;
+use itertools::Itertools;
+use leptos::prelude::*;
+
+/// Draws a shape being dragged out over the canvas, as wide as the brush which will stroke it.
+#[component]
+pub fn ShapePreview(
+	// In canvas coordinates.
+	#[prop(into)] outline: Signal<Option<Vec<Vec2>>>,
+	// The width of the stroke, in canvas units.
+	#[prop(into)] width: Signal<f64>,
+	#[prop(into)] view_transform: Signal<ViewTransform>,
+) -> impl IntoView {
+	let points = move || {
+		let view_transform = view_transform.get();
+		outline.with(|outline| {
+			let outline = outline.as_ref()?;
+			let points = outline
+				.iter()
+				.map(|p| CanvasPt(p.as_dvec2()).to_screen(&view_transform).0)
+				.map(|p| format!("{},{}", p.x, p.y))
+				.join(" ");
+			Some(points)
+		})
+	};
+	let stroke_width = move || format!("{}px", width.get() * view_transform.get().zoom);
+
+	view! {
+		<svg class="ShapePreview">
+			{move || {
+				points()
+					.map(|points| {
+						view! { <polyline points=points style:stroke-width=stroke_width></polyline> }
+					})
+			}}
+		</svg>
+	}
+}
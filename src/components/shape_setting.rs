@@ -0,0 +1,51 @@
+use crate::components::*;
+use crate::engine::ShapeTool;
+use leptos::prelude::*;
+
+const PEN: &str = "Pen";
+
+/// Chooses whether dragging on the canvas paints freehand or draws a shape, or whether clicking
+/// places the anchors of a path with the pen.
+#[component]
+pub fn ShapeSetting(tool: RwSignal<Option<ShapeTool>>, pen: RwSignal<bool>) -> impl IntoView {
+	let name = |tool: Option<ShapeTool>| tool.map_or("Freehand".to_string(), |t| t.to_string());
+	let tools = || std::iter::once(None).chain(ShapeTool::ALL.map(Some));
+
+	let options = tools()
+		.map(|t| view! { <option value=name(t)>{name(t)}</option> })
+		.collect_view();
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if value == PEN {
+			tool.set(None);
+			pen.set(true);
+		} else if let Some(t) = tools().find(|&t| name(t) == value) {
+			tool.set(t);
+			pen.set(false);
+		}
+	};
+	let value = move || {
+		if pen.get() {
+			PEN.to_string()
+		} else {
+			name(tool.get())
+		}
+	};
+	let note = move || {
+		if pen.get() {
+			"Click to place anchors. Enter strokes the path, Backspace removes the last anchor, and Escape discards it."
+		} else {
+			"Shapes are drawn with the brush once the drag ends."
+		}
+	};
+
+	view! {
+		<BrushSetting name="Shape">
+			<select on:change=change prop:value=value>
+				{options}
+				<option value=PEN>{PEN}</option>
+			</select>
+			<span class="DocumentSettingNote">{note}</span>
+		</BrushSetting>
+	}
+}
@@ -0,0 +1,119 @@
+use crate::components::*;
+use crate::util::{Shortcut, ShortcutRegistry};
+use leptos::prelude::*;
+use leptos::wasm_bindgen::JsCast;
+use leptos::web_sys;
+
+/// What keyboard shortcuts can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum ShortcutAction {
+	#[display("Smaller brush")]
+	DecreaseBrushSize,
+	#[display("Larger brush")]
+	IncreaseBrushSize,
+	#[display("Less opaque brush")]
+	DecreaseBrushOpacity,
+	#[display("More opaque brush")]
+	IncreaseBrushOpacity,
+}
+
+impl ShortcutAction {
+	pub const ALL: [Self; 4] = [
+		Self::DecreaseBrushSize,
+		Self::IncreaseBrushSize,
+		Self::DecreaseBrushOpacity,
+		Self::IncreaseBrushOpacity,
+	];
+
+	fn default_shortcut(self) -> &'static str {
+		match self {
+			Self::DecreaseBrushSize => "[",
+			Self::IncreaseBrushSize => "]",
+			// Shift+[ and Shift+] on most layouts.
+			Self::DecreaseBrushOpacity => "{",
+			Self::IncreaseBrushOpacity => "}",
+		}
+	}
+
+	/// The registry with each action's default shortcut.
+	pub fn default_shortcuts() -> ShortcutRegistry<Self> {
+		let mut registry = ShortcutRegistry::default();
+		for action in Self::ALL {
+			registry.bind(action.default_shortcut().parse().unwrap(), action);
+		}
+		registry
+	}
+}
+
+/// The shortcut pressed in `e`.
+pub fn event_shortcut(e: &leptos::ev::KeyboardEvent) -> Shortcut {
+	Shortcut::new(
+		&e.key(),
+		e.ctrl_key() || e.meta_key(),
+		e.alt_key(),
+		e.shift_key(),
+	)
+}
+
+/// Whether `e` is typing into a text field, where keys shouldn't trigger shortcuts.
+pub fn is_typing(e: &leptos::ev::KeyboardEvent) -> bool {
+	e.target()
+		.and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+		.is_some_and(|target| matches!(target.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"))
+}
+
+/// Lists each action's shortcuts. Clicking one and pressing a key makes that key its shortcut, and
+/// Escape cancels.
+#[component]
+pub fn ShortcutSettings(shortcuts: RwSignal<ShortcutRegistry<ShortcutAction>>) -> impl IntoView {
+	let capturing = RwSignal::new(None::<ShortcutAction>);
+
+	let rows = ShortcutAction::ALL
+		.into_iter()
+		.map(|action| {
+			let label = move || match capturing.get() == Some(action) {
+				true => "Press a key...".to_string(),
+				false => shortcuts.with(|shortcuts| {
+					let bound = shortcuts
+						.shortcuts(action)
+						.map(|s| s.to_string())
+						.collect::<Vec<_>>();
+					match bound.is_empty() {
+						true => "None".to_string(),
+						false => bound.join(", "),
+					}
+				}),
+			};
+			let keydown = move |e: leptos::ev::KeyboardEvent| {
+				if capturing.get_untracked() != Some(action) {
+					return;
+				}
+				// The page's own shortcuts mustn't see the key being bound.
+				e.stop_propagation();
+				e.prevent_default();
+				let shortcut = event_shortcut(&e);
+				if shortcut.is_modifier() {
+					return;
+				}
+				if e.key() != "Escape" {
+					shortcuts.update(|shortcuts| shortcuts.rebind(action, shortcut));
+				}
+				capturing.set(None);
+			};
+			view! {
+				<BrushSetting name=action.to_string()>
+					<button
+						class="ShortcutButton"
+						class:capturing=move || capturing.get() == Some(action)
+						on:click=move |_| capturing.set(Some(action))
+						on:blur=move |_| capturing.set(None)
+						on:keydown=keydown
+					>
+						{label}
+					</button>
+				</BrushSetting>
+			}
+		})
+		.collect_view();
+	view! { {rows} }
+}
@@ -0,0 +1,61 @@
+use crate::util::input_interpolate::InputPoint;
+use crate::util::StrokeTrace;
+use glam::{vec2, Vec2};
+use itertools::Itertools;
+use leptos::prelude::*;
+
+/// The size of the pressure graph, in pixels.
+const GRAPH_WIDTH: f32 = 240.0;
+const GRAPH_HEIGHT: f32 = 80.0;
+
+fn polyline_points<'a>(
+	points: impl Iterator<Item = &'a InputPoint>,
+	position: impl Fn(&InputPoint) -> Vec2,
+) -> String {
+	points
+		.map(|p| {
+			let p = position(p);
+			format!("{},{}", p.x, p.y)
+		})
+		.join(" ")
+}
+
+/// Graphs the pointer's recent points against the stabilized stroke, for tuning smoothing and
+/// diagnosing jittery tablets. Positions are drawn where they are on the canvas, and pressure over
+/// time in the corner.
+#[component]
+pub fn StrokeGraph(#[prop(into)] trace: Signal<StrokeTrace>) -> impl IntoView {
+	let screen_position = |p: &InputPoint| vec2(p.x, p.y);
+	let graph_position = |trace: &StrokeTrace| {
+		let duration = trace.duration();
+		let start_time = trace.end_time().unwrap_or_default() - duration;
+		move |p: &InputPoint| {
+			vec2(
+				GRAPH_WIDTH * (p.t - start_time) / duration,
+				GRAPH_HEIGHT * (1.0 - p.pressure),
+			)
+		}
+	};
+
+	let raw_positions = move || trace.with(|t| polyline_points(t.raw(), screen_position));
+	let fitted_positions = move || trace.with(|t| polyline_points(t.fitted(), screen_position));
+	let raw_pressures = move || trace.with(|t| polyline_points(t.raw(), graph_position(t)));
+	let fitted_pressures = move || trace.with(|t| polyline_points(t.fitted(), graph_position(t)));
+
+	view! {
+		<svg class="StrokeGraph">
+			<polyline class="raw" points=raw_positions></polyline>
+			<polyline class="fitted" points=fitted_positions></polyline>
+		</svg>
+		<svg class="StrokeGraphPressure" viewBox=format!("0 0 {GRAPH_WIDTH} {GRAPH_HEIGHT}")>
+			<polyline class="raw" points=raw_pressures></polyline>
+			<polyline class="fitted" points=fitted_pressures></polyline>
+			<text class="raw" x="4" y="12">
+				"Raw"
+			</text>
+			<text class="fitted" x="4" y="24">
+				"Fitted"
+			</text>
+		</svg>
+	}
+}
@@ -0,0 +1,28 @@
+use crate::components::*;
+use crate::engine::SymmetryKind;
+use leptos::prelude::*;
+
+#[component]
+pub fn SymmetrySetting(symmetry: RwSignal<SymmetryKind>) -> impl IntoView {
+	let options = SymmetryKind::ALL
+		.into_iter()
+		.map(|s| view! { <option value=s.to_string()>{s.to_string()}</option> })
+		.collect_view();
+	let change = move |e: leptos::ev::Event| {
+		let value = event_target_value(&e);
+		if let Some(s) = SymmetryKind::ALL
+			.into_iter()
+			.find(|s| s.to_string() == value)
+		{
+			symmetry.set(s);
+		}
+	};
+
+	view! {
+		<BrushSetting name="Symmetry">
+			<select on:change=change prop:value=move || symmetry.get().to_string()>
+				{options}
+			</select>
+		</BrushSetting>
+	}
+}
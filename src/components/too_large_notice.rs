@@ -0,0 +1,25 @@
+use crate::engine::PoolExhausted;
+use leptos::prelude::*;
+
+/// Tells the user that an edit needed more charts than the GPU can hold, and how to carry on, until
+/// it is dismissed.
+#[component]
+pub fn TooLargeNotice(error: RwSignal<Option<PoolExhausted>>) -> impl IntoView {
+	move || {
+		error.get().map(|_| {
+			view! {
+				<div class="TooLargeNotice" role="alert">
+					<p>
+						"This document is too large for this GPU, so part of the last edit wasn't drawn."
+					</p>
+					<p>
+						"Painting where the canvas is already painted still works. To go on past its "
+						"edges, export the document, or the part of it you need, and continue in a new "
+						"one."
+					</p>
+					<button on:click=move |_| error.set(None)>"Dismiss"</button>
+				</div>
+			}
+		})
+	}
+}
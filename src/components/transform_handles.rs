@@ -0,0 +1,79 @@
+use crate::coordinates::CanvasPt;
+use crate::engine::TransformHandle;
+use crate::view_transform::ViewTransform;
+use glam::DVec2;
+use itertools::Itertools;
+use leptos::prelude::*;
+
+/// How close to a handle, in screen pixels, the pointer grabs it.
+const HANDLE_RADIUS: f64 = 8.0;
+
+/// How far the rotation handle stands out from the middle of the top edge, in screen pixels.
+const ROTATE_HANDLE_OFFSET: f64 = 24.0;
+
+/// Where the rotation handle is for the bounds with `corners`, in the order of
+/// [`crate::engine::AABox::corners`], in screen pixels.
+fn rotate_handle(corners: &[DVec2; 4]) -> DVec2 {
+	let center = 0.25 * corners.iter().sum::<DVec2>();
+	let top = 0.5 * (corners[0] + corners[3]);
+	top + ROTATE_HANDLE_OFFSET * (top - center).normalize_or_zero()
+}
+
+/// The handle of the bounds with `corners`, in screen pixels, which pressing at `point` grabs.
+/// Anywhere away from the handles moves the selection.
+pub fn transform_handle_at(corners: &[DVec2; 4], point: DVec2) -> TransformHandle {
+	if point.distance(rotate_handle(corners)) <= HANDLE_RADIUS {
+		return TransformHandle::Rotate;
+	}
+	corners
+		.iter()
+		.position(|corner| point.distance(*corner) <= HANDLE_RADIUS)
+		.map_or(TransformHandle::Move, TransformHandle::Scale)
+}
+
+/// Draws the bounds of a floating selection over the canvas, with handles at the corners for
+/// scaling and one above the top edge for rotating.
+#[component]
+pub fn TransformHandles(
+	// The corners of the bounds in canvas coordinates, or `None` while nothing is floating.
+	#[prop(into)] corners: Signal<Option<[DVec2; 4]>>,
+	#[prop(into)] view_transform: Signal<ViewTransform>,
+) -> impl IntoView {
+	let screen_corners = move || {
+		let view_transform = view_transform.get();
+		corners
+			.get()
+			.map(|corners| corners.map(|p| CanvasPt(p).to_screen(&view_transform).0))
+	};
+
+	view! {
+		<svg class="TransformHandles">
+			{move || {
+				screen_corners()
+					.map(|corners| {
+						let outline = corners.iter().map(|p| format!("{},{}", p.x, p.y)).join(" ");
+						let top = 0.5 * (corners[0] + corners[3]);
+						let rotate = rotate_handle(&corners);
+						let handles = corners
+							.map(|corner| corner - 0.5 * HANDLE_RADIUS)
+							.map(|corner| {
+								view! {
+									<rect
+										x=corner.x
+										y=corner.y
+										width=HANDLE_RADIUS
+										height=HANDLE_RADIUS
+									></rect>
+								}
+							});
+						view! {
+							<polygon points=outline></polygon>
+							<line x1=top.x y1=top.y x2=rotate.x y2=rotate.y></line>
+							<circle cx=rotate.x cy=rotate.y r={0.5 * HANDLE_RADIUS}></circle>
+							{handles.into_iter().collect_view()}
+						}
+					})
+			}}
+		</svg>
+	}
+}
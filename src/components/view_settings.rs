@@ -0,0 +1,72 @@
+use crate::components::*;
+use crate::view_transform::ViewTransform;
+use leptos::prelude::*;
+
+/// A change to the view a [`Canvas`] makes on request, animating to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewCommand {
+	/// Returns to the canvas origin at 100%, unrotated.
+	Reset,
+	/// Frames everything drawn, keeping the rotation.
+	FitContent,
+	/// Frames the selection, keeping the rotation.
+	FitSelection,
+	/// Returns to a bookmarked view. The canvas keeps its own zoom limits.
+	GoTo(ViewTransform),
+}
+
+/// Buttons moving the view of the canvas, which it does once `command` is set, and the active
+/// document's bookmarked views.
+#[component]
+pub fn ViewSettings(command: RwSignal<Option<ViewCommand>>, documents: Documents) -> impl IntoView {
+	let bookmarks = move || documents.active().map(|document| document.view_bookmarks);
+	let add_bookmark = move |_| {
+		if let Some(document) = documents.active() {
+			let view = document.view_transform.get_untracked();
+			document
+				.view_bookmarks
+				.update(|bookmarks| bookmarks.push(view));
+		}
+	};
+	let bookmark_buttons = move || {
+		let bookmarks = bookmarks()?;
+		let buttons = bookmarks
+			.get()
+			.into_iter()
+			.enumerate()
+			.map(|(index, view)| {
+				let remove = move |_| {
+					bookmarks.update(|bookmarks| {
+						if index < bookmarks.len() {
+							bookmarks.remove(index);
+						}
+					})
+				};
+				view! {
+					<span class="ViewBookmark">
+						<button on:click=move |_| command.set(Some(ViewCommand::GoTo(view)))>
+							{format!("View {}", index + 1)}
+						</button>
+						<button title="Remove bookmark" on:click=remove>
+							"\u{d7}"
+						</button>
+					</span>
+				}
+			})
+			.collect_view();
+		Some(buttons)
+	};
+
+	view! {
+		<button on:click=move |_| command.set(Some(ViewCommand::Reset))>"Reset view"</button>
+		<button on:click=move |_| command.set(Some(ViewCommand::FitContent))>"Fit content"</button>
+		<button on:click=move |_| command.set(Some(ViewCommand::FitSelection))>
+			"Zoom to selection"
+		</button>
+		<button on:click=add_bookmark>"Bookmark view"</button>
+		{bookmark_buttons}
+		<span class="DocumentSettingNote">
+			"0 also resets the view. The view is restored on reload, but bookmarks aren't."
+		</span>
+	}
+}
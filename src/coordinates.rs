@@ -0,0 +1,144 @@
+//! Points in each of the coordinate spaces positions pass through on their way from an input event
+//! to a chart's texture.
+//!
+//! Each space is its own type, and converting between them is always explicit, so that a screen
+//! position can't be drawn to the canvas without the view transform, nor a canvas position sampled
+//! from a chart without its key. The spaces are:
+//!
+//! - [`ScreenPx`]: pixels of the canvas element, from its top-left corner with y down.
+//! - [`CanvasPt`]: the unbounded canvas, which the view pans, zooms, and rotates onto the screen.
+//! - [`ChartUv`]: texture coordinates within one chart of the atlas.
+//!
+//! Shaders also use clip space, which runs from -1 to 1 with y up. Only [`ScreenPx::to_clip`]
+//! converts to it, since it is the one place that flip belongs.
+
+use crate::engine::{ChartKey, CHART_SCALE, CHART_SIZE};
+use crate::view_transform::ViewTransform;
+use glam::*;
+
+/// A position on screen, in CSS pixels from the top-left corner of the canvas element.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScreenPx(pub DVec2);
+
+/// A position on the canvas, in canvas units. The canvas has y down, like the screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CanvasPt(pub DVec2);
+
+/// A position within a chart, from `(0, 0)` at its top-left corner to `(1, 1)` at its bottom-right.
+/// Only meaningful along with the chart's key.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChartUv(pub Vec2);
+
+impl ScreenPx {
+	pub fn new(x: f64, y: f64) -> Self {
+		Self(dvec2(x, y))
+	}
+
+	pub fn to_canvas(self, view: &ViewTransform) -> CanvasPt {
+		CanvasPt(view.screen_to_canvas_point(self.0))
+	}
+
+	/// The clip space position of this point on a screen of `screen_size` pixels. Clip space has y
+	/// up, so this is where the flip from screen coordinates happens.
+	pub fn to_clip(self, screen_size: DVec2) -> Vec2 {
+		(dvec2(2.0, -2.0) * (self.0 / screen_size - 0.5)).as_vec2()
+	}
+
+	pub fn as_vec2(self) -> Vec2 {
+		self.0.as_vec2()
+	}
+}
+
+impl CanvasPt {
+	pub fn new(x: f64, y: f64) -> Self {
+		Self(dvec2(x, y))
+	}
+
+	pub fn to_screen(self, view: &ViewTransform) -> ScreenPx {
+		ScreenPx(view.canvas_to_screen_point(self.0))
+	}
+
+	/// The key of the chart containing this point.
+	pub fn chart(self) -> ChartKey {
+		let key = (self.0 / CHART_SCALE as f64).floor().as_ivec2();
+		ChartKey(key.x, key.y)
+	}
+
+	/// This point relative to the chart at `key`. It is only within the chart if `key` is
+	/// [`Self::chart`].
+	pub fn to_chart_uv(self, key: ChartKey) -> ChartUv {
+		let origin = CanvasPt::chart_origin(key).0;
+		ChartUv(((self.0 - origin) / CHART_SCALE as f64).as_vec2())
+	}
+
+	/// The top-left corner of the chart at `key`.
+	pub fn chart_origin(key: ChartKey) -> Self {
+		Self(dvec2(key.0 as f64, key.1 as f64) * CHART_SCALE as f64)
+	}
+
+	/// The `f32` position the engine draws with. This loses precision far from the origin, so prefer
+	/// converting differences from a nearby point.
+	pub fn as_vec2(self) -> Vec2 {
+		self.0.as_vec2()
+	}
+}
+
+impl ChartUv {
+	pub fn to_canvas(self, key: ChartKey) -> CanvasPt {
+		CanvasPt(CanvasPt::chart_origin(key).0 + self.0.as_dvec2() * CHART_SCALE as f64)
+	}
+
+	/// The position in texels of the chart's texture.
+	pub fn to_texel(self) -> Vec2 {
+		self.0 * CHART_SIZE as f32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test::assert_near;
+
+	#[test]
+	fn screen_and_canvas_round_trip() {
+		let mut view = ViewTransform::IDENTITY;
+		view.pan(dvec2(30.0, -20.0));
+		view.zoom_about(dvec2(5.0, 7.0), 3.0);
+		view.rotate_about(dvec2(-2.0, 11.0), 0.7);
+
+		let screen = ScreenPx::new(123.0, 45.0);
+		let canvas = screen.to_canvas(&view);
+		assert_eq!(canvas.0, view.screen_to_canvas_point(screen.0));
+		assert_near!(canvas.to_screen(&view).0, screen.0, 1e-9);
+	}
+
+	#[test]
+	fn screen_to_clip_flips_y() {
+		let size = dvec2(200.0, 100.0);
+		assert_eq!(ScreenPx::new(0.0, 0.0).to_clip(size), vec2(-1.0, 1.0));
+		assert_eq!(ScreenPx::new(200.0, 100.0).to_clip(size), vec2(1.0, -1.0));
+		assert_eq!(ScreenPx::new(100.0, 50.0).to_clip(size), Vec2::ZERO);
+	}
+
+	#[test]
+	fn canvas_and_chart_round_trip() {
+		let s = CHART_SCALE as f64;
+		let point = CanvasPt::new(-0.25 * s, 2.5 * s);
+		let key = point.chart();
+		assert_eq!(key, ChartKey(-1, 2));
+		assert_eq!(key, ChartKey::find_containing(point.as_vec2()));
+
+		let uv = point.to_chart_uv(key);
+		assert_near!(uv.0, vec2(0.75, 0.5), 1e-6);
+		assert_near!(uv.to_texel(), vec2(0.75, 0.5) * CHART_SIZE as f32, 1e-3);
+		assert_near!(uv.to_canvas(key).0, point.0, 1e-9);
+
+		// The chart's own transform agrees.
+		let chart_to_canvas = key.chart_to_canvas();
+		assert_near!(
+			chart_to_canvas.transform_point2(uv.0),
+			point.as_vec2(),
+			1e-4
+		);
+	}
+}
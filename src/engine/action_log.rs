@@ -0,0 +1,350 @@
+use super::versioned::{self, Versioned, VersionedError};
+use super::{
+	Airbrush, Atlas, BrushMode, History, InputPoint, PoolExhausted, Symmetry, DEFAULT_BRUSH_SPACING,
+};
+use crate::coordinates::ScreenPx;
+use crate::util::input_interpolate::{self, Stabilizer, StrokeEnd};
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::collections::VecDeque;
+
+// The log is for reproducing bugs in stroke smoothing and in drawing across charts, which depend on
+// the pointer's path and the brush but not on what was already painted. It holds no pixels, so it
+// can be attached to a bug report without sharing the painting.
+
+/// The version of action logs written by this build. Older versions are read, newer ones are not.
+pub const ACTION_LOG_VERSION: u32 = 1;
+
+/// The most actions an [`ActionLog`] keeps. Older ones are dropped as new ones are recorded.
+pub const MAX_LOGGED_ACTIONS: usize = 100;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ActionLogError {
+	#[error("malformed action log: {0}")]
+	Malformed(String),
+	#[error("unsupported action log version {0}")]
+	UnsupportedVersion(u32),
+}
+
+impl From<VersionedError> for ActionLogError {
+	fn from(err: VersionedError) -> Self {
+		match err {
+			VersionedError::Malformed(err) => Self::Malformed(err),
+			VersionedError::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+		}
+	}
+}
+
+/// A point as the pointer reported it.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LoggedPoint {
+	/// In seconds.
+	pub t: f32,
+	/// In screen pixels.
+	pub x: f32,
+	pub y: f32,
+	pub pressure: f32,
+	/// The stylus tilt relative to the screen. See [`InputPoint::tilt`].
+	pub tilt: Vec2,
+}
+
+impl LoggedPoint {
+	pub fn new(point: input_interpolate::InputPoint, tilt: Vec2) -> Self {
+		Self {
+			t: point.t,
+			x: point.x,
+			y: point.y,
+			pressure: point.pressure,
+			tilt,
+		}
+	}
+
+	fn stroke_point(&self) -> input_interpolate::InputPoint {
+		input_interpolate::InputPoint {
+			t: self.t,
+			x: self.x,
+			y: self.y,
+			pressure: self.pressure,
+		}
+	}
+}
+
+/// A stroke as the pointer drew it, with the view and brush it was drawn with.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LoggedStroke {
+	pub view: ViewTransform,
+	pub color: Vec3,
+	pub size: f32,
+	pub opacity: f32,
+	pub rate: f32,
+	pub smoothing: f32,
+	/// See [`Stabilizer::with_max_interval`]. Logs from before this was configurable used the
+	/// default.
+	#[serde(default = "default_smoothing_interval")]
+	pub smoothing_interval: f32,
+	/// See [`Stabilizer::with_stroke_end`]. Logs from before this was configurable ended with a
+	/// straight line.
+	#[serde(default = "default_stroke_end")]
+	pub stroke_end: StrokeEnd,
+	pub mode: BrushMode,
+	/// See [`Airbrush::set_spacing`]. Logs from before this was configurable used the default.
+	#[serde(default = "default_spacing")]
+	pub spacing: f32,
+	/// See [`Airbrush::set_scatter`]. Logs from before this was configurable didn't scatter.
+	#[serde(default)]
+	pub scatter: f32,
+	/// See [`Airbrush::set_grain`]. Logs from before this was configurable had no grain.
+	#[serde(default)]
+	pub grain: f32,
+	/// See [`Airbrush::set_pickup`]. Logs from before this was configurable couldn't mix, so it
+	/// didn't matter.
+	#[serde(default)]
+	pub pickup: f32,
+	pub symmetry: Symmetry,
+	/// The airbrush's seed, so replays spray the same stamps.
+	pub seed: u64,
+	pub points: Vec<LoggedPoint>,
+}
+
+impl LoggedStroke {
+	/// Converts a point of the stabilized stroke to the canvas, as the canvas does when drawing.
+	fn input_point(&self, point: input_interpolate::InputPoint, tilt: Vec2) -> InputPoint {
+		let position = ScreenPx::new(point.x as f64, point.y as f64);
+		InputPoint {
+			position: position.to_canvas(&self.view).0,
+			pressure: point.pressure,
+			color: self.color,
+			size: self.size,
+			opacity: self.opacity,
+			rate: self.rate,
+			tilt: Vec2::from_angle(-self.view.rotation as f32).rotate(tilt),
+		}
+	}
+
+	fn replay(
+		&self,
+		queue: &wgpu::Queue,
+		atlas: &mut Atlas,
+		airbrush: &mut Airbrush,
+		history: &mut History,
+	) -> Result<(), PoolExhausted> {
+		let mut result = Ok(());
+		history.begin_edit();
+		airbrush.set_symmetry(self.symmetry);
+		airbrush.set_mode(self.mode);
+		airbrush.set_spacing(self.spacing);
+		airbrush.set_scatter(self.scatter);
+		airbrush.set_grain(self.grain);
+		airbrush.set_pickup(self.pickup);
+		airbrush.set_seed(Some(self.seed));
+		airbrush.start();
+
+		let mut stabilizer = Stabilizer::new(self.smoothing)
+			.with_max_interval(self.smoothing_interval)
+			.with_stroke_end(self.stroke_end);
+		let pushed = self
+			.points
+			.iter()
+			.map(|point| (stabilizer.push(point.stroke_point()), point.tilt))
+			.collect::<Vec<_>>();
+		let last_tilt = self.points.last().map_or(Vec2::ZERO, |point| point.tilt);
+		let finished = (stabilizer.finish(), last_tilt);
+
+		for (points, tilt) in pushed.into_iter().chain([finished]) {
+			for point in points {
+				if let Some(drawable) = airbrush.drag(queue, self.input_point(point, tilt)) {
+					history.record(atlas, drawable.get_chart_keys());
+					result = result.and(atlas.draw(&drawable));
+				}
+			}
+		}
+
+		airbrush.stop();
+		history.end_edit();
+		result
+	}
+}
+
+fn default_smoothing_interval() -> f32 {
+	input_interpolate::DEFAULT_STABILIZER_INTERVAL
+}
+
+fn default_stroke_end() -> StrokeEnd {
+	StrokeEnd::Linear
+}
+
+fn default_spacing() -> f32 {
+	DEFAULT_BRUSH_SPACING
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LoggedAction {
+	Stroke(LoggedStroke),
+	Undo,
+	Redo,
+}
+
+/// The recent strokes, undos, and redos on a canvas. Other edits, such as imported images, are not
+/// logged, since they would carry pixels.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ActionLog {
+	actions: VecDeque<LoggedAction>,
+}
+
+impl ActionLog {
+	pub fn record(&mut self, action: LoggedAction) {
+		if self.actions.len() == MAX_LOGGED_ACTIONS {
+			self.actions.pop_front();
+		}
+		self.actions.push_back(action);
+	}
+
+	pub fn actions(&self) -> impl Iterator<Item = &LoggedAction> {
+		self.actions.iter()
+	}
+
+	pub fn encode(&self) -> String {
+		let versioned = Versioned::new(ACTION_LOG_VERSION, self);
+		serde_json::to_string_pretty(&versioned).expect("action logs should serialize")
+	}
+
+	/// Decodes an action log. The version is checked first, so logs from newer builds are reported
+	/// as such rather than as malformed.
+	pub fn decode(encoded: &str) -> Result<Self, ActionLogError> {
+		Ok(versioned::decode(encoded, ACTION_LOG_VERSION)?)
+	}
+
+	/// Replays the log to `atlas` without any user input, as the canvas drew it. Actions from before
+	/// the oldest one kept are lost, so this should start from an empty atlas and history. If the
+	/// atlas runs out of room for charts, the rest of the log is still replayed to those it has.
+	pub fn replay(
+		&self,
+		queue: &wgpu::Queue,
+		atlas: &mut Atlas,
+		airbrush: &mut Airbrush,
+		history: &mut History,
+	) -> Result<(), PoolExhausted> {
+		let mut result = Ok(());
+		for action in &self.actions {
+			match action {
+				LoggedAction::Stroke(stroke) => {
+					result = result.and(stroke.replay(queue, atlas, airbrush, history));
+				}
+				LoggedAction::Undo => {
+					history.undo(atlas);
+				}
+				LoggedAction::Redo => {
+					history.redo(atlas);
+				}
+			}
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{CanvasFormat, ChartKey, SymmetryKind};
+	use crate::render::Resources;
+	use crate::test;
+	use std::assert_matches::assert_matches;
+
+	/// A stroke from chart `(0, 0)` into `(1, 0)`, drawn zoomed out so the screen points are half as
+	/// far apart as the canvas points.
+	fn stroke() -> LoggedStroke {
+		let mut view = ViewTransform::default();
+		view.zoom_about(DVec2::ZERO, 0.5);
+		LoggedStroke {
+			view,
+			color: vec3(0.6, 0.1, -0.05),
+			size: 8.0,
+			opacity: 1.0,
+			rate: 25.0,
+			smoothing: 0.5,
+			smoothing_interval: 0.05,
+			stroke_end: StrokeEnd::default(),
+			mode: BrushMode::Paint,
+			spacing: DEFAULT_BRUSH_SPACING,
+			scatter: 0.5,
+			grain: 0.5,
+			pickup: 0.5,
+			symmetry: Symmetry {
+				kind: SymmetryKind::None,
+				center: Vec2::ZERO,
+			},
+			seed: 7,
+			points: (0..=10)
+				.map(|i| LoggedPoint {
+					t: 0.01 * i as f32,
+					x: 100.0 + 5.0 * i as f32,
+					y: 50.0,
+					pressure: 0.5,
+					tilt: Vec2::ZERO,
+				})
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut log = ActionLog::default();
+		log.record(LoggedAction::Stroke(stroke()));
+		log.record(LoggedAction::Undo);
+		log.record(LoggedAction::Redo);
+		assert_eq!(ActionLog::decode(&log.encode()), Ok(log));
+	}
+
+	#[test]
+	fn rejects_newer_versions() {
+		let encoded = format!(
+			r#"{{"version": {}, "actions": []}}"#,
+			ACTION_LOG_VERSION + 1
+		);
+		assert_matches!(
+			ActionLog::decode(&encoded),
+			Err(ActionLogError::UnsupportedVersion(_))
+		);
+		assert_matches!(ActionLog::decode("{}"), Err(ActionLogError::Malformed(_)));
+	}
+
+	#[test]
+	fn keeps_recent_actions() {
+		let mut log = ActionLog::default();
+		log.record(LoggedAction::Stroke(stroke()));
+		for _ in 0..MAX_LOGGED_ACTIONS {
+			log.record(LoggedAction::Undo);
+		}
+		assert_eq!(log.actions().count(), MAX_LOGGED_ACTIONS);
+		assert!(log.actions().all(|action| *action == LoggedAction::Undo));
+	}
+
+	#[test]
+	fn replay() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Resources::new(device);
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut airbrush = Airbrush::new(device, queue, &resources, format);
+		let mut history = History::default();
+
+		let mut log = ActionLog::default();
+		log.record(LoggedAction::Stroke(stroke()));
+		log.replay(queue, &mut atlas, &mut airbrush, &mut history)?;
+
+		// The stroke runs from canvas (200, 100) to (300, 100).
+		assert!(atlas.get_chart(&ChartKey(0, 0)).is_some());
+		assert!(atlas.get_chart(&ChartKey(1, 0)).is_some());
+		let sample = |atlas: &Atlas, point| pollster::block_on(atlas.sample(point));
+		assert!(sample(&atlas, vec2(250.5, 100.5))?.w > 0.0);
+		assert!(history.can_undo());
+
+		// Undoing the stroke clears it again.
+		let mut log = ActionLog::default();
+		log.record(LoggedAction::Undo);
+		log.replay(queue, &mut atlas, &mut airbrush, &mut history)?;
+		assert_eq!(sample(&atlas, vec2(250.5, 100.5))?.w, 0.0);
+		Ok(())
+	}
+}
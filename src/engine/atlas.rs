@@ -1,12 +1,18 @@
-use super::tile::{self, TextureLayerDescriptor};
-use super::Extent2d;
+use super::tile::{self, PoolExhausted, TextureLayerDescriptor};
+use super::{
+	AirbrushDrawable, CanvasFormat, DocumentBounds, Extent2d, ImportedImage, ProjectFile,
+	SavedChart, Selection,
+};
+use crate::render::{texture, Resources};
 use crate::shaders::TileData;
 use crate::WgpuContext;
 use glam::*;
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AABox {
 	min: Vec2,
 	max: Vec2,
@@ -25,6 +31,19 @@ impl AABox {
 		self.min.x > self.max.x && self.min.y > self.max.y
 	}
 
+	pub fn min(&self) -> Vec2 {
+		self.min
+	}
+
+	pub fn max(&self) -> Vec2 {
+		self.max
+	}
+
+	/// The smallest box containing both this one and `other`.
+	pub fn union(self, other: Self) -> Self {
+		Self::new(self.min.min(other.min), self.max.max(other.max))
+	}
+
 	pub fn expanded_to_contain(self, point: Vec2) -> Self {
 		Self::new(self.min.min(point), self.max.max(point))
 	}
@@ -53,6 +72,15 @@ impl AABox {
 pub const CHART_SIZE: u32 = 256;
 pub const CHART_SCALE: f32 = CHART_SIZE as f32;
 
+/// How much GPU memory an atlas' charts may occupy before the least recently used are evicted. This
+/// mostly affects charts only kept for undo.
+pub const CHART_MEMORY_BUDGET: u64 = 512 << 20;
+
+/// The most GPU memory an atlas' charts may occupy at once. Eviction keeps them within the budget
+/// between edits, but an edit covering more than this fails to allocate charts rather than exhaust
+/// the GPU. It can be changed with [`tile::Pool::set_max_layers`].
+pub const CHART_MEMORY_LIMIT: u64 = 2 << 30;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ChartKey(pub i32, pub i32);
 
@@ -91,6 +119,21 @@ impl ChartKey {
 		Affine2::from_mat2_translation(Mat2::from_diagonal(scale), translation)
 	}
 
+	/// The region of the canvas the chart covers.
+	pub fn canvas_bounds(&self) -> AABox {
+		let (scale, translation) = self.chart_to_canvas_scale_and_translation();
+		AABox::new(translation, translation + scale)
+	}
+
+	/// The data for a tile storing this chart.
+	pub fn tile_data(&self) -> TileData {
+		TileData {
+			chart_to_canvas_scale: Vec2::splat(CHART_SCALE),
+			chart_to_canvas_translation: Vec2::ZERO,
+			chart_offset: ivec2(self.0, self.1),
+		}
+	}
+
 	pub fn chart_to_canvas_mat4(&self) -> Mat4 {
 		let (scale, translation) = self.chart_to_canvas_scale_and_translation();
 		Mat4::from_scale_rotation_translation(
@@ -119,13 +162,22 @@ impl Chart {
 
 #[derive(Clone)]
 pub struct Atlas {
+	format: CanvasFormat,
 	tile_pool: tile::Pool,
 	charts: HashMap<ChartKey, Arc<Chart>>,
+	// Charts changed since the last call to `take_dirty`.
+	dirty: HashSet<ChartKey>,
+	// The smallest and largest keys of charts changed since the last call to
+	// `take_changed_region`, including restored ones.
+	changed: Option<(ChartKey, ChartKey)>,
+	selection: Selection,
+	// What the document is cropped to, if anything.
+	bounds: Option<DocumentBounds>,
 	// usage_bind_group: Arc<BindGroup0>,
 }
 
 impl Atlas {
-	pub fn new(context: Arc<WgpuContext>, format: wgpu::TextureFormat) -> Self {
+	pub fn new(context: Arc<WgpuContext>, format: CanvasFormat) -> Self {
 		// let device = context.device();
 		// let chart_sampler = &device.create_sampler(&wgpu::SamplerDescriptor {
 		// 	address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -139,53 +191,715 @@ impl Atlas {
 		// let usage_bind_group =
 		// 	BindGroup0::from_bindings(device, BindGroupLayout0 { chart_sampler }).into();
 
-		Atlas {
-			tile_pool: tile::Pool::new(
-				context,
-				TextureLayerDescriptor {
-					size: Extent2d {
-						width: CHART_SIZE,
-						height: CHART_SIZE,
-					},
-					format,
-					..Default::default()
+		let selection = Selection::new(context.clone());
+		let tile_pool = tile::Pool::new(
+			context,
+			TextureLayerDescriptor {
+				size: Extent2d {
+					width: CHART_SIZE,
+					height: CHART_SIZE,
 				},
-			),
+				format: format.texture_format(),
+				..Default::default()
+			},
+		);
+		tile_pool.set_memory_budget(Some(CHART_MEMORY_BUDGET));
+		tile_pool.set_max_layers(Some((CHART_MEMORY_LIMIT / chart_bytes(format)) as u32));
+
+		Atlas {
+			format,
+			tile_pool,
 			charts: HashMap::new(),
+			dirty: HashSet::new(),
+			changed: None,
+			selection,
+			bounds: None,
 			// usage_bind_group,
 		}
 	}
 
+	pub fn format(&self) -> CanvasFormat {
+		self.format
+	}
+
+	/// The memory each chart's texture occupies, in bytes.
+	pub fn chart_bytes(&self) -> u64 {
+		chart_bytes(self.format)
+	}
+
 	pub fn buffer_layout(&self) -> wgpu::VertexBufferLayout<'static> {
 		self.tile_pool.buffer_layout()
 	}
 
+	/// The pool holding the charts' textures. It is shared with clones of the atlas, and its memory
+	/// is released once they, and any charts taken from them, are dropped.
+	pub fn tile_pool(&self) -> &tile::Pool {
+		&self.tile_pool
+	}
+
 	pub fn charts(&self) -> impl Iterator<Item = Arc<Chart>> + '_ {
 		self.charts.values().cloned()
 	}
 
+	pub fn charts_by_key(&self) -> impl Iterator<Item = (ChartKey, Arc<Chart>)> + '_ {
+		self.charts.iter().map(|(&key, chart)| (key, chart.clone()))
+	}
+
+	/// The charts with keys from `min` to `max` along each axis, inclusive.
+	pub fn charts_between(
+		&self,
+		min: ChartKey,
+		max: ChartKey,
+	) -> impl Iterator<Item = Arc<Chart>> + '_ {
+		self
+			.charts
+			.iter()
+			.filter(move |(key, _)| {
+				(min.0..=max.0).contains(&key.0) && (min.1..=max.1).contains(&key.1)
+			})
+			.map(|(_, chart)| chart.clone())
+	}
+
+	/// Uploads up to `limit` evicted charts intersecting `region` again, returning how many were
+	/// uploaded. This spreads the work of bringing charts into view over time.
+	pub fn prefetch(&self, region: AABox, limit: usize) -> usize {
+		let evicted = ChartKey::find_intersecting(region)
+			.filter_map(|key| self.charts.get(&key))
+			.filter(|chart| chart.tile().is_evicted())
+			.take(limit)
+			.collect_vec();
+		for chart in &evicted {
+			chart.tile().prefetch();
+		}
+		evicted.len()
+	}
+
+	/// The smallest and largest chart keys along each axis, or `None` if there are no charts.
+	pub fn chart_bounds(&self) -> Option<(ChartKey, ChartKey)> {
+		let keys = self.charts.keys();
+		let min = keys.clone().map(|k| ivec2(k.0, k.1)).reduce(IVec2::min)?;
+		let max = keys.map(|k| ivec2(k.0, k.1)).reduce(IVec2::max)?;
+		Some((ChartKey(min.x, min.y), ChartKey(max.x, max.y)))
+	}
+
 	pub fn get_chart(&self, key: &ChartKey) -> Option<Arc<Chart>> {
 		self.charts.get(key).cloned()
 	}
 
+	/// The chart at `key` for drawing to, allocated if there is none. Panics if the tile pool has no
+	/// room for it, so where that is possible, use [`Self::try_get_chart_mut`].
 	pub fn get_chart_mut(&mut self, key: ChartKey) -> &mut Chart {
-		let chart = self.charts.entry(key).or_insert_with(|| {
-			let tile = self.tile_pool.allocate_tile();
-			let (chart_to_canvas_scale, chart_to_canvas_translation) =
-				key.chart_to_canvas_scale_and_translation();
-			let tile_data = TileData {
-				chart_to_canvas_scale,
-				chart_to_canvas_translation,
+		self.try_get_chart_mut(key).unwrap()
+	}
+
+	/// The chart at `key` for drawing to, or an error if there is none and the tile pool has no room
+	/// for another.
+	pub fn try_get_chart_mut(&mut self, key: ChartKey) -> Result<&mut Chart, PoolExhausted> {
+		if !self.charts.contains_key(&key) {
+			let tile = self.tile_pool.try_allocate_tile()?;
+			tile.set_data(&key.tile_data());
+
+			tile.fill_texture(&self.format.clear_pixel());
+			self.charts.insert(key, Chart::new(tile).into());
+		}
+		self.mark_changed(key);
+		let chart = self.charts.get_mut(&key).unwrap();
+		// TODO: When this clones, we need to put that back in the atlas.
+		Ok(Arc::make_mut(chart))
+	}
+
+	/// Replaces the chart at `key`, returning the previous one. `None` removes the chart.
+	pub fn replace_chart(&mut self, key: ChartKey, chart: Option<Arc<Chart>>) -> Option<Arc<Chart>> {
+		self.mark_changed(key);
+		match chart {
+			Some(chart) => self.charts.insert(key, chart),
+			None => self.charts.remove(&key),
+		}
+	}
+
+	/// Removes every chart, returning the atlas to a blank canvas.
+	pub fn clear(&mut self) {
+		let keys = self.charts.drain().map(|(key, _)| key).collect_vec();
+		for key in keys {
+			self.mark_changed(key);
+		}
+	}
+
+	fn mark_changed(&mut self, key: ChartKey) {
+		self.dirty.insert(key);
+		self.expand_changed_region(key);
+	}
+
+	fn expand_changed_region(&mut self, key: ChartKey) {
+		self.changed = Some(match self.changed {
+			Some((min, max)) => (
+				ChartKey(min.0.min(key.0), min.1.min(key.1)),
+				ChartKey(max.0.max(key.0), max.1.max(key.1)),
+			),
+			None => (key, key),
+		});
+	}
+
+	/// Composites `image` over the canvas with its top-left corner at `origin`, allocating charts as
+	/// needed. If the tile pool runs out of room, the charts which could be drawn to still are.
+	pub fn draw_image(
+		&mut self,
+		resources: &Resources,
+		image: &ImportedImage,
+		origin: IVec2,
+	) -> Result<(), PoolExhausted> {
+		let mut exhausted = None;
+		let context = self.tile_pool.context();
+		let (device, queue) = (context.device(), context.queue());
+
+		// Each chart's share of the image is uploaded here in turn, then drawn over the chart.
+		let staging = texture()
+			.label("Atlas::draw_image")
+			.width(CHART_SIZE)
+			.height(CHART_SIZE)
+			.format(self.format.texture_format())
+			.usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+			.create(device);
+
+		for key in image.chart_keys(origin) {
+			let pixels = self.format.encode_pixels(&image.chart_pixels(origin, key));
+			queue.write_texture(
+				staging.as_image_copy(),
+				&pixels,
+				wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: Some(pixels.len() as u32 / CHART_SIZE),
+					rows_per_image: None,
+				},
+				staging.size(),
+			);
+
+			let chart = match self.try_get_chart_mut(key) {
+				Ok(chart) => chart,
+				Err(error) => {
+					exhausted = Some(error);
+					continue;
+				}
 			};
-			tile.set_data(&tile_data);
+			let _write = chart.tile().begin_write();
+			let mut encoder =
+				context
+					.profiler()
+					.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+						label: Some("Atlas::draw_image"),
+					});
+			chart
+				.tile()
+				.copy_from_texture()
+				.resources(resources)
+				.encoder(&mut *encoder)
+				.source(&staging)
+				// This must match how strokes are drawn.
+				.blend(wgpu::BlendState::ALPHA_BLENDING)
+				.call();
+			// The staging texture is rewritten for the next chart, so this can't be batched.
+			encoder.submit(queue);
+		}
+		exhausted.map_or(Ok(()), Err)
+	}
+
+	/// Returns the keys of charts which have been modified, added, or removed since the last call.
+	pub fn take_dirty(&mut self) -> HashSet<ChartKey> {
+		std::mem::take(&mut self.dirty)
+	}
+
+	/// Returns the smallest and largest keys along each axis of the charts which have been modified,
+	/// added, removed, or restored since the last call, or `None` if there are none. Unlike
+	/// [`Self::take_dirty`], this is for redrawing rather than saving, so it is only a bound.
+	pub fn take_changed_region(&mut self) -> Option<(ChartKey, ChartKey)> {
+		self.changed.take()
+	}
+
+	/// Marks charts as modified, e.g. to retry saving them.
+	pub fn mark_dirty(&mut self, keys: impl IntoIterator<Item = ChartKey>) {
+		self.dirty.extend(keys)
+	}
+
+	/// Reads back the chart at `key` for saving, or `None` if there is no such chart. The chart's
+	/// current contents are captured immediately, so later edits don't affect the result.
+	pub fn save_chart(
+		&self,
+		key: ChartKey,
+	) -> Option<impl Future<Output = anyhow::Result<SavedChart>>> {
+		let chart = self.get_chart(&key)?;
+		let format = self.format;
+		let texture_data = chart.tile().get_texture_data();
+		Some(async move {
+			Ok(SavedChart {
+				key,
+				format,
+				tile_data: key.tile_data(),
+				texture_data: texture_data.await?,
+			})
+		})
+	}
+
+	/// Reads back every chart, as with [`Atlas::save_chart`], for saving the whole document along
+	/// with its bounds. The atlas has no metadata, so the project's is left empty.
+	pub fn save_project(&self) -> impl Future<Output = anyhow::Result<ProjectFile>> {
+		let format = self.format;
+		let bounds = self.bounds;
+		let saves = self
+			.charts
+			.keys()
+			.filter_map(|&key| self.save_chart(key))
+			.collect_vec();
+		async move {
+			Ok(ProjectFile {
+				format,
+				charts: futures::future::try_join_all(saves).await?,
+				metadata: Default::default(),
+				bounds,
+			})
+		}
+	}
+
+	/// The bounding box, in canvas coordinates, of the charts with anything drawn on them, or `None`
+	/// if there are none. Charts are allocated wherever strokes might reach, so they're read back to
+	/// skip those which are still transparent.
+	pub fn content_bounds(&self) -> impl Future<Output = anyhow::Result<Option<AABox>>> {
+		let format = self.format;
+		let reads = self
+			.charts
+			.iter()
+			.map(|(&key, chart)| {
+				let texture_data = chart.tile().get_texture_data();
+				async move { anyhow::Ok((key, texture_data.await?)) }
+			})
+			.collect_vec();
+		async move {
+			let charts = futures::future::try_join_all(reads).await?;
+			Ok(charts
+				.into_iter()
+				.filter(|(_, texture_data)| {
+					format
+						.decode_pixels(texture_data)
+						.iter()
+						.any(|pixel| pixel.w > 0.0)
+				})
+				.map(|(key, _)| key.canvas_bounds())
+				.reduce(AABox::union))
+		}
+	}
 
-			let zero = half::f16::from_f32(0f32);
-			tile.fill_texture(bytemuck::cast_slice(&[zero, zero, zero, zero]));
-			Chart::new(tile).into()
+	/// Reads back the color of the canvas at `point`, as Oklab with alpha. The canvas is transparent
+	/// wherever there is no chart.
+	pub fn sample(&self, point: Vec2) -> impl Future<Output = anyhow::Result<Vec4>> {
+		let key = ChartKey::find_containing(point);
+		let format = self.format;
+		let texel = (point - key.chart_to_canvas().translation)
+			.floor()
+			.as_uvec2()
+			.min(UVec2::splat(CHART_SIZE - 1));
+		let texture_data = self.get_chart(&key).map(|chart| {
+			let size = Extent2d {
+				width: 1,
+				height: 1,
+			};
+			chart.tile().get_texture_region_data(texel, size)
 		});
-		// TODO: When this clones, we need to put that back in the atlas.
-		Arc::make_mut(chart)
+		async move {
+			let Some(texture_data) = texture_data else {
+				return Ok(Vec4::ZERO);
+			};
+			Ok(format.decode_pixels(&texture_data.await?)[0])
+		}
+	}
+
+	/// Replaces a chart with one previously saved, converting it to this atlas' format. Restored
+	/// charts are not considered modified. Fails, leaving the atlas as it was, if there is no room
+	/// for another chart.
+	pub fn restore_chart(&mut self, saved: SavedChart) -> Result<(), PoolExhausted> {
+		let saved = saved.converted_to(self.format);
+		let tile = self.tile_pool.try_allocate_tile()?;
+		tile.set_data(&saved.tile_data);
+		tile.write_texture(&saved.texture_data);
+		self.expand_changed_region(saved.key);
+		self.charts.insert(saved.key, Chart::new(tile).into());
+		Ok(())
+	}
+
+	/// The region the document is cropped to, or `None` if it is unbounded. Charts outside of it
+	/// are kept, but aren't shown or exported.
+	pub fn bounds(&self) -> Option<DocumentBounds> {
+		self.bounds
+	}
+
+	pub fn set_bounds(&mut self, bounds: Option<DocumentBounds>) {
+		self.bounds = bounds;
+	}
+
+	/// The part of the canvas [`Atlas::draw`] paints.
+	pub fn selection(&self) -> &Selection {
+		&self.selection
+	}
+
+	pub fn selection_mut(&mut self) -> &mut Selection {
+		&mut self.selection
+	}
+
+	/// Draws `drawable` into each selected chart it covers, allocating charts as needed. If the tile
+	/// pool runs out of room, the charts which could be drawn to still are.
+	pub fn draw(&mut self, drawable: &AirbrushDrawable) -> Result<(), PoolExhausted> {
+		let context = self.tile_pool.context();
+		let mut encoder =
+			context
+				.profiler()
+				.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+					label: Some("Atlas::draw"),
+				});
+
+		// Readbacks of the charts drawn to wait until the draws are submitted.
+		let mut writes = Vec::new();
+		let mut exhausted = None;
+		// Find the minimal set of tiles to write to. Every segment of the batch is drawn with a single
+		// submission.
+		for (index, chart_key) in drawable.get_chart_keys().enumerate() {
+			let Some(mask_bind_group) = self.selection.bind_group(chart_key) else {
+				continue;
+			};
+			let chart = match self.try_get_chart_mut(chart_key) {
+				Ok(chart) => chart,
+				Err(error) => {
+					exhausted = Some(error);
+					continue;
+				}
+			};
+			writes.push(chart.tile().begin_write());
+			let view = chart.tile().texture_view();
+			let chart_bind_group = chart.tile().write_bind_group();
+
+			for pass in drawable.passes(index) {
+				drawable.prepare_pass(&mut encoder, chart.tile(), &pass);
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					label: Some("Drawing Pass"),
+					color_attachments: &[
+						// This is what @location(0) in the fragment shader targets
+						Some(wgpu::RenderPassColorAttachment {
+							view,
+							resolve_target: None,
+							ops: wgpu::Operations {
+								load: wgpu::LoadOp::Load,
+								store: wgpu::StoreOp::Store,
+							},
+						}),
+					],
+					..Default::default()
+				});
+				chart_bind_group.set(&mut render_pass);
+				mask_bind_group.set(&mut render_pass);
+				drawable.draw(&mut render_pass, &pass);
+			}
+		}
+		drawable.submit_uploads(context.queue());
+		encoder.submit(context.queue());
+		drop(writes);
+		exhausted.map_or(Ok(()), Err)
 	}
 }
 
+fn chart_bytes(format: CanvasFormat) -> u64 {
+	(CHART_SIZE * CHART_SIZE * format.texture_format().block_copy_size(None).unwrap()) as u64
+}
+
 // TODO: Test with wgpu-test (https://github.com/gfx-rs/wgpu/tree/v0.20.0/tests)
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test;
+
+	#[test]
+	fn new_charts_are_cleared() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		for format in CanvasFormat::ALL {
+			if !format.is_supported(context.adapter(), context.device().features()) {
+				continue;
+			}
+			let mut atlas = Atlas::new((*context).clone(), format);
+			let chart = atlas.get_chart_mut(ChartKey(-3, 7));
+			let data = pollster::block_on(chart.tile().get_texture_data())?;
+			let expected = format
+				.clear_pixel()
+				.repeat((CHART_SIZE * CHART_SIZE) as usize);
+			assert!(data == expected, "{format:?}");
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn dirty_charts() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::default());
+		atlas.get_chart_mut(ChartKey(0, 0));
+		atlas.get_chart_mut(ChartKey(1, 0));
+		assert_eq!(
+			atlas.take_dirty(),
+			HashSet::from([ChartKey(0, 0), ChartKey(1, 0)])
+		);
+		assert!(atlas.take_dirty().is_empty());
+
+		atlas.replace_chart(ChartKey(1, 0), None);
+		assert_eq!(atlas.take_dirty(), HashSet::from([ChartKey(1, 0)]));
+
+		atlas.clear();
+		assert_eq!(atlas.take_dirty(), HashSet::from([ChartKey(0, 0)]));
+		Ok(())
+	}
+
+	#[test]
+	fn changed_region() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::default());
+		assert_eq!(atlas.take_changed_region(), None);
+		atlas.get_chart_mut(ChartKey(-1, 2));
+		atlas.get_chart_mut(ChartKey(3, 0));
+		assert_eq!(
+			atlas.take_changed_region(),
+			Some((ChartKey(-1, 0), ChartKey(3, 2)))
+		);
+		assert_eq!(atlas.take_changed_region(), None);
+
+		// Saving keeps its own record of changes.
+		assert_eq!(atlas.take_dirty().len(), 2);
+		atlas.replace_chart(ChartKey(3, 0), None);
+		assert_eq!(
+			atlas.take_changed_region(),
+			Some((ChartKey(3, 0), ChartKey(3, 0)))
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn save_and_restore() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let key = ChartKey(4, -1);
+		let pixel = [10, 140, 100, 200];
+
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		atlas.get_chart_mut(key).tile().fill_texture(&pixel);
+		assert!(atlas.save_chart(ChartKey(0, 0)).is_none());
+		let saved = pollster::block_on(atlas.save_chart(key).unwrap())?;
+		let saved = SavedChart::decode(&saved.encode())?;
+
+		for format in CanvasFormat::ALL {
+			if !format.is_supported(context.adapter(), context.device().features()) {
+				continue;
+			}
+			let mut restored = Atlas::new((*context).clone(), format);
+			restored.restore_chart(saved.clone())?;
+			assert!(restored.take_dirty().is_empty());
+			let chart = restored.get_chart(&key).unwrap();
+			let data = pollster::block_on(chart.tile().get_texture_data())?;
+			let expected = CanvasFormat::Rgba8Unorm.decode_pixels(&pixel)[0];
+			for actual in format.decode_pixels(&data) {
+				assert!(actual.abs_diff_eq(expected, 1e-3), "{format:?}: {actual}");
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn content_bounds() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		assert_eq!(pollster::block_on(atlas.content_bounds())?, None);
+
+		// Charts which are allocated but transparent don't count.
+		atlas.get_chart_mut(ChartKey(5, 5));
+		assert_eq!(pollster::block_on(atlas.content_bounds())?, None);
+
+		let pixel = format.encode_pixels(&[vec4(0.5, 0.0, 0.0, 0.25)]);
+		for key in [ChartKey(-1, 0), ChartKey(1, 2)] {
+			atlas.get_chart_mut(key).tile().fill_texture(&pixel);
+		}
+		assert_eq!(
+			pollster::block_on(atlas.content_bounds())?,
+			Some(AABox::new(vec2(-256.0, 0.0), vec2(512.0, 768.0)))
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn sample() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let key = ChartKey(-1, 2);
+		let color = vec4(0.7, -0.1, 0.05, 0.5);
+		let texel = uvec2(3, CHART_SIZE - 1);
+
+		for format in CanvasFormat::ALL {
+			if !format.is_supported(context.adapter(), context.device().features()) {
+				continue;
+			}
+			let mut atlas = Atlas::new((*context).clone(), format);
+			let mut data = format
+				.clear_pixel()
+				.repeat((CHART_SIZE * CHART_SIZE) as usize);
+			let pixel = format.encode_pixels(&[color]);
+			let offset = (texel.y * CHART_SIZE + texel.x) as usize * pixel.len();
+			data[offset..offset + pixel.len()].copy_from_slice(&pixel);
+			atlas.get_chart_mut(key).tile().write_texture(&data);
+
+			let origin = key.chart_to_canvas().translation;
+			let sample = |point| pollster::block_on(atlas.sample(point));
+			let actual = sample(origin + texel.as_vec2() + 0.5)?;
+			assert!(actual.abs_diff_eq(color, 1e-2), "{format:?}: {actual}");
+			assert_eq!(sample(origin + 0.5)?, Vec4::ZERO, "{format:?}");
+			// There is no chart here.
+			assert_eq!(sample(vec2(0.5, 0.5))?, Vec4::ZERO, "{format:?}");
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn prefetch() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		for x in 0..3 {
+			atlas.get_chart_mut(ChartKey(x, 0));
+		}
+		let layer_bytes = 4 * (CHART_SIZE * CHART_SIZE) as u64;
+		atlas.tile_pool().set_memory_budget(Some(layer_bytes));
+		assert_eq!(pollster::block_on(atlas.tile_pool().evict())?, 2);
+
+		// Only evicted charts in the region count, and no more than the limit at a time.
+		let first_two = AABox::new(Vec2::splat(1.0), vec2(1.5 * CHART_SCALE, 1.0));
+		let everything = AABox::new(Vec2::splat(-1e4), Vec2::splat(1e4));
+		assert_eq!(atlas.prefetch(first_two, 1), 1);
+		assert_eq!(atlas.prefetch(everything, 5), 1);
+		assert_eq!(atlas.prefetch(everything, 5), 0);
+		assert!(atlas.charts().all(|chart| !chart.tile().is_evicted()));
+		Ok(())
+	}
+
+	#[test]
+	fn draw_confined_to_selection() -> anyhow::Result<()> {
+		use crate::engine::{Airbrush, InputPoint, SelectionShape};
+		let context = test::WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut airbrush = Airbrush::new(context.device(), context.queue(), &resources, format);
+
+		// Only the left half of the first chart is selected, so the stroke stops halfway across it
+		// and doesn't reach the next chart.
+		let half = CHART_SCALE / 2.0;
+		let left_half = SelectionShape::rectangle(Vec2::ZERO, vec2(half, CHART_SCALE));
+		atlas.selection_mut().select(left_half);
+		let point = |x: f32| InputPoint {
+			position: dvec2(x as f64, half as f64),
+			pressure: 1.0,
+			color: Vec3::ONE,
+			size: 8.0,
+			opacity: 1.0,
+			rate: 10.0,
+			tilt: Vec2::ZERO,
+		};
+		airbrush.draw_stroke(
+			context.queue(),
+			&mut atlas,
+			(0..=12).map(|i| point(20.0 + 25.0 * i as f32)),
+		)?;
+		assert!(atlas.get_chart(&ChartKey(1, 0)).is_none());
+
+		let chart = atlas.get_chart(&ChartKey(0, 0)).unwrap();
+		let pixels = format.decode_pixels(&pollster::block_on(chart.tile().get_texture_data())?);
+		let alpha = |x: f32| pixels[(half as u32 * CHART_SIZE + x as u32) as usize].w;
+		assert!(alpha(0.5 * half) > 0.1, "{}", alpha(0.5 * half));
+		assert_eq!(alpha(1.5 * half), 0.0);
+
+		atlas.selection_mut().clear();
+		assert!(atlas.selection().shape().is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn draw_without_room_for_charts() -> anyhow::Result<()> {
+		use crate::engine::{Airbrush, InputPoint};
+		let context = test::WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut airbrush = Airbrush::new(context.device(), context.queue(), &resources, format);
+		atlas.tile_pool().set_max_layers(Some(1));
+		atlas.get_chart_mut(ChartKey(0, 0));
+		atlas.take_dirty();
+
+		// The stroke crosses into a second chart, which there is no room for.
+		let point = |x: f64| InputPoint {
+			position: dvec2(x, 100.0),
+			pressure: 1.0,
+			color: Vec3::ONE,
+			size: 8.0,
+			opacity: 1.0,
+			rate: 10.0,
+			tilt: Vec2::ZERO,
+		};
+		let drawn = airbrush.draw_stroke(
+			context.queue(),
+			&mut atlas,
+			(0..=12).map(|i| point(200.0 + 10.0 * i as f64)),
+		);
+		assert_eq!(drawn, Err(PoolExhausted { max_layers: 1 }));
+		assert!(atlas.get_chart(&ChartKey(1, 0)).is_none());
+		assert_eq!(atlas.take_dirty(), HashSet::from([ChartKey(0, 0)]));
+		assert!(pollster::block_on(atlas.sample(vec2(220.5, 100.5)))?.w > 0.0);
+		Ok(())
+	}
+
+	#[test]
+	fn draw_image() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		// Opaque white over a transparent pixel, straddling two charts.
+		let image = ImportedImage::from_texture_data(
+			&[255, 255, 255, 255, 0, 0, 0, 0],
+			uvec2(2, 1),
+			wgpu::TextureFormat::Rgba8Unorm,
+		)?;
+		let origin = ivec2(CHART_SIZE as i32 - 1, 0);
+
+		for format in CanvasFormat::ALL {
+			if !format.is_supported(context.adapter(), context.device().features()) {
+				continue;
+			}
+			let mut atlas = Atlas::new((*context).clone(), format);
+			let background = format.encode_pixels(&[vec4(0.5, 0.1, 0.0, 1.0)]);
+			atlas
+				.get_chart_mut(ChartKey(1, 0))
+				.tile()
+				.fill_texture(&background);
+			atlas.draw_image(&resources, &image, origin)?;
+			assert_eq!(
+				atlas.take_dirty(),
+				HashSet::from([ChartKey(0, 0), ChartKey(1, 0)])
+			);
+
+			let data = |key| -> anyhow::Result<Vec<Vec4>> {
+				let chart = atlas.get_chart(&key).unwrap();
+				Ok(format.decode_pixels(&pollster::block_on(chart.tile().get_texture_data())?))
+			};
+			let left = data(ChartKey(0, 0))?;
+			assert!(
+				left[CHART_SIZE as usize - 1].abs_diff_eq(vec4(1.0, 0.0, 0.0, 1.0), 1e-2),
+				"{format:?}"
+			);
+			assert_eq!(left[0], Vec4::ZERO);
+			// Transparent pixels leave the canvas untouched.
+			let right = data(ChartKey(1, 0))?;
+			let background = format.decode_pixels(&background)[0];
+			assert!(
+				right.iter().all(|p| p.abs_diff_eq(background, 1e-3)),
+				"{format:?}"
+			);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,74 @@
+use super::ChartKey;
+use glam::*;
+
+/// A finite rectangle of the otherwise unbounded canvas, in canvas units. When a document has
+/// bounds, only what is inside them is shown and exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct DocumentBounds {
+	pub origin: IVec2,
+	pub size: UVec2,
+}
+
+impl DocumentBounds {
+	/// The bounds with opposite corners `a` and `b`, rounded to whole canvas units, or `None` if
+	/// they would be empty.
+	pub fn spanning(a: DVec2, b: DVec2) -> Option<Self> {
+		let min = a.min(b).round().as_ivec2();
+		let max = a.max(b).round().as_ivec2();
+		let size = (max - min).max(IVec2::ZERO).as_uvec2();
+		(size.min_element() > 0).then_some(Self { origin: min, size })
+	}
+
+	/// The top-left corner.
+	pub fn min(&self) -> IVec2 {
+		self.origin
+	}
+
+	/// The bottom-right corner, just outside the bounds.
+	pub fn max(&self) -> IVec2 {
+		self.origin + self.size.as_ivec2()
+	}
+
+	pub fn contains(&self, point: DVec2) -> bool {
+		point.cmpge(self.min().as_dvec2()).all() && point.cmplt(self.max().as_dvec2()).all()
+	}
+
+	/// The smallest and largest keys along each axis of the charts overlapping the bounds.
+	pub fn charts(&self) -> (ChartKey, ChartKey) {
+		let min = ChartKey::find_containing(self.min().as_vec2());
+		let max = ChartKey::find_containing((self.max() - 1).as_vec2());
+		(min, max)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::CHART_SCALE;
+
+	#[test]
+	fn spans_corners() {
+		let bounds = DocumentBounds::spanning(dvec2(100.4, -20.0), dvec2(-10.6, 30.2)).unwrap();
+		assert_eq!(bounds.origin, ivec2(-11, -20));
+		assert_eq!(bounds.size, uvec2(111, 50));
+		assert!(bounds.contains(dvec2(-11.0, -20.0)));
+		assert!(!bounds.contains(dvec2(100.0, 0.0)));
+
+		// Bounds with no area are no bounds at all.
+		assert_eq!(
+			DocumentBounds::spanning(dvec2(5.0, 0.0), dvec2(5.2, 40.0)),
+			None
+		);
+	}
+
+	#[test]
+	fn finds_overlapping_charts() {
+		let s = CHART_SCALE as i32;
+		let bounds = DocumentBounds {
+			origin: ivec2(-s, 10),
+			size: uvec2(2 * s as u32, s as u32),
+		};
+		// The right edge falls exactly between charts, so the chart past it is excluded.
+		assert_eq!(bounds.charts(), (ChartKey(-1, 0), ChartKey(0, 1)));
+	}
+}
@@ -0,0 +1,188 @@
+use super::DocumentBounds;
+use crate::render::{error_scope, BindingBuffer, CapturedCommand, FrameCapturer, Resources};
+use crate::shaders::{self, bounds::Bounds};
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::sync::Arc;
+
+/// How much the canvas outside of the document's bounds is darkened.
+pub const OUTSIDE_BOUNDS_OPACITY: f32 = 0.4;
+
+/// Draws the border of a document's [`DocumentBounds`] over whatever has already been drawn,
+/// darkening what's outside of them.
+pub struct BoundsRenderer {
+	resources: Arc<Resources>,
+	bounds_buffer: BindingBuffer<Bounds>,
+	bind_group: shaders::bounds::BindGroup0,
+}
+
+impl BoundsRenderer {
+	pub fn new(device: &wgpu::Device, resources: Arc<Resources>) -> Self {
+		let bounds_buffer = BindingBuffer::new_sized()
+			.label("BoundsRenderer::bounds_buffer")
+			.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+			.create(device);
+		let bind_group = error_scope(device, "BoundsRenderer::bind_group", || {
+			resources
+				.bounds
+				.pipeline_layout()
+				.get()
+				.bind_group_layouts()
+				.0
+				.bind_group()
+				.bounds(bounds_buffer.as_entire_buffer_binding())
+				.create()
+		});
+		Self {
+			resources,
+			bounds_buffer,
+			bind_group,
+		}
+	}
+
+	/// Creates a pipeline drawing to `target_format`. Passes with a depth/stencil attachment need a
+	/// matching `depth_stencil`.
+	pub fn create_pipeline(
+		&self,
+		target_format: wgpu::TextureFormat,
+		sample_count: u32,
+		depth_stencil: Option<wgpu::DepthStencilState>,
+	) -> wgpu::RenderPipeline {
+		self
+			.resources
+			.bounds
+			.pipeline_layout()
+			.get()
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(shaders::bounds::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: target_format,
+					blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.multisample(wgpu::MultisampleState {
+				count: sample_count,
+				..Default::default()
+			})
+			.maybe_depth_stencil(depth_stencil)
+			.maybe_cache(self.resources.pipeline_cache())
+			.get()
+	}
+
+	/// Computes the shader bounds for `bounds` seen through `view_transform`, darkening what's
+	/// outside of them by `outside_opacity`.
+	pub fn bounds(
+		view_transform: &ViewTransform,
+		bounds: DocumentBounds,
+		outside_opacity: f32,
+	) -> Bounds {
+		// Working relative to the bounds keeps this precise far from the canvas origin.
+		let origin = bounds.min().as_dvec2();
+		Bounds {
+			screen_to_bounds: view_transform
+				.canvas_to_screen_matrix_relative_to(origin)
+				.inverse(),
+			size: bounds.size.as_vec2(),
+			zoom: view_transform.zoom as f32,
+			outside_opacity,
+		}
+	}
+
+	/// Writes the bounds. This must happen before submitting any passes from [`Self::draw`].
+	pub fn write_bounds(&self, queue: &wgpu::Queue, bounds: &Bounds) {
+		self.bounds_buffer.write(queue, bounds);
+	}
+
+	/// Draws the bounds last written, recording what it issues to `capture`.
+	pub fn draw(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		capture: &FrameCapturer,
+	) {
+		render_pass.set_pipeline(pipeline);
+		capture.record(|| CapturedCommand::SetPipeline {
+			pipeline: "bounds".to_string(),
+		});
+		self.bind_group.set(render_pass);
+		capture.record(|| CapturedCommand::SetBindGroup {
+			index: 0,
+			bind_group: "bounds".to_string(),
+		});
+		render_pass.draw(0..4, 0..1);
+		capture.record(|| CapturedCommand::Draw {
+			vertices: 0..4,
+			instances: 0..1,
+			layers: Vec::new(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{render, test};
+
+	#[test]
+	fn borders_bounds() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let renderer = BoundsRenderer::new(device, Arc::new(Resources::new(device)));
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format, 1, None);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+			.create(device);
+
+		// At 2x zoom, bounds 10 units wide from the origin span the left 20 pixels of the screen.
+		let mut view_transform = ViewTransform::default();
+		view_transform.zoom_about(DVec2::ZERO, 2.0);
+		let bounds = DocumentBounds {
+			origin: IVec2::ZERO,
+			size: uvec2(10, 100),
+		};
+		let bounds = BoundsRenderer::bounds(&view_transform, bounds, 0.5);
+		renderer.write_bounds(context.queue(), &bounds);
+
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &target.create_view(&Default::default()),
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			renderer.draw(&mut render_pass, &pipeline, context.frame_capture());
+		}
+		context.queue().submit([encoder.finish()]);
+
+		let data = pollster::block_on(context.get_texture_layer_data(&target, 0))?;
+		let texel = |x: usize, y: usize| {
+			let offset = 4 * (y * 64 + x);
+			&data[offset..offset + 4]
+		};
+		// Clear inside, light just inside the edge, and darkened outside.
+		assert_eq!(texel(10, 24), [0, 0, 0, 0]);
+		let light = texel(19, 24);
+		assert!(light.iter().all(|&c| c > 200), "{light:?}");
+		let outside = texel(40, 24);
+		assert!(
+			outside[..3] == [0, 0, 0] && outside[3].abs_diff(128) <= 1,
+			"{outside:?}"
+		);
+		Ok(())
+	}
+}
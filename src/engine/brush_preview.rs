@@ -0,0 +1,100 @@
+use super::InputPoint;
+use glam::*;
+use std::f32::consts::PI;
+
+/// An S-curve stroke used to preview brush settings.
+#[derive(Clone)]
+pub struct PreviewStroke {
+	pub points: Vec<InputPoint>,
+	/// The stroke, including its full width, lies within `[0, size]`.
+	pub size: Vec2,
+}
+
+impl PreviewStroke {
+	/// The minimum length of the stroke so that small brushes still show their shape along a curve.
+	const MIN_LENGTH: f32 = 96.0;
+
+	/// Builds a stroke with the settings of `template` and `samples` input points. Pressure tapers
+	/// toward both ends, so the preview also shows how the brush responds to pressure.
+	pub fn new(template: InputPoint, samples: usize) -> Self {
+		assert!(samples >= 2);
+		let margin = template.size;
+		let length = (12.0 * template.size).max(Self::MIN_LENGTH);
+		let amplitude = length / 6.0;
+		let scale = 1.0 / (samples - 1) as f32;
+		let points = (0..samples)
+			.map(|i| {
+				let t = scale * i as f32;
+				InputPoint {
+					position: dvec2(
+						(margin + length * t) as f64,
+						(margin + amplitude * (1.0 - (2.0 * PI * t).sin())) as f64,
+					),
+					pressure: 0.25 + 0.75 * (PI * t).sin(),
+					..template
+				}
+			})
+			.collect();
+		Self {
+			points,
+			size: vec2(length, 2.0 * amplitude) + 2.0 * margin,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn template(size: f32) -> InputPoint {
+		InputPoint {
+			position: DVec2::ZERO,
+			pressure: 1.0,
+			color: Vec3::ONE,
+			size,
+			opacity: 1.0,
+			rate: 25.0,
+			tilt: Vec2::ZERO,
+		}
+	}
+
+	#[test]
+	fn stroke_is_within_bounds() {
+		for size in [1.0, 16.0, 1024.0] {
+			let stroke = PreviewStroke::new(template(size), 64);
+			assert_eq!(stroke.points.len(), 64);
+			for point in &stroke.points {
+				let radius = point.size * point.pressure;
+				let position = point.position.as_vec2();
+				assert!(position.cmpge(Vec2::splat(radius - 1e-3)).all());
+				assert!(position.cmple(stroke.size - radius + 1e-3).all());
+			}
+		}
+	}
+
+	#[test]
+	fn pressure_tapers() {
+		let stroke = PreviewStroke::new(template(16.0), 65);
+		let pressures: Vec<_> = stroke.points.iter().map(|p| p.pressure).collect();
+		approx::assert_abs_diff_eq!(pressures[0], 0.25, epsilon = 1e-6);
+		approx::assert_abs_diff_eq!(pressures[32], 1.0, epsilon = 1e-6);
+		approx::assert_abs_diff_eq!(pressures[64], 0.25, epsilon = 1e-6);
+	}
+
+	#[test]
+	fn stroke_keeps_brush_settings() {
+		let template = InputPoint {
+			color: vec3(0.5, 0.1, -0.1),
+			opacity: 0.5,
+			rate: 10.0,
+			..template(8.0)
+		};
+		let stroke = PreviewStroke::new(template, 8);
+		for point in &stroke.points {
+			assert_eq!(point.color, template.color);
+			assert_eq!(point.size, template.size);
+			assert_eq!(point.opacity, template.opacity);
+			assert_eq!(point.rate, template.rate);
+		}
+	}
+}
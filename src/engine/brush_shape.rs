@@ -0,0 +1,213 @@
+use super::{embedded_shapes, BUILT_IN_SHAPES};
+use crate::util::ImageExt;
+use std::sync::Arc;
+use thiserror::Error;
+use zune_core::bytestream::ZCursor;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_image::image::Image;
+
+/// The name of the shape brushes start with.
+pub const DEFAULT_BRUSH_SHAPE: &str = "00507";
+
+/// The name of the built-in shape with a hard edge, as for inking.
+pub const ROUND_BRUSH_SHAPE: &str = "round";
+
+/// The width and height of [`ROUND_BRUSH_SHAPE`].
+const ROUND_BRUSH_SHAPE_SIZE: u32 = 64;
+
+/// The largest width or height of an imported shape. The shape texture holds a copy of the shape
+/// for each of [`super::OPACITY_LEVELS`], so this bounds its memory.
+pub const MAX_BRUSH_SHAPE_SIZE: u32 = 512;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BrushShapeError {
+	#[error("couldn't decode brush shape: {0}")]
+	Decode(String),
+
+	#[error("brush shape is empty")]
+	Empty,
+
+	#[error("brush shape is {width}x{height}, but may be at most {max}x{max}", max = MAX_BRUSH_SHAPE_SIZE)]
+	TooLarge { width: u32, height: u32 },
+}
+
+/// The density of paint the airbrush sprays across its footprint, from 0 to 1, in rows from the
+/// top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrushShape {
+	pub width: u32,
+	pub height: u32,
+	pub values: Vec<f32>,
+}
+
+impl BrushShape {
+	/// Decodes a shape from an image, such as the contents of a PNG file. The image is converted to
+	/// grayscale, and white sprays the most paint. Transparency is ignored.
+	pub fn decode(encoded: &[u8]) -> Result<Self, BrushShapeError> {
+		let mut image = Image::read(ZCursor::new(encoded), DecoderOptions::default())
+			.map_err(|err| BrushShapeError::Decode(format!("{err:?}")))?;
+		image
+			.convert_color(ColorSpace::Luma)
+			.map_err(|err| BrushShapeError::Decode(format!("{err:?}")))?;
+		let (width, height) = image.dimensions();
+		Self::new(
+			width as u32,
+			height as u32,
+			image.convert_to_f32_subpixels(),
+		)
+	}
+
+	/// Checks that `values` has `width` by `height` values, and that those are small enough to
+	/// upload. Values are clamped to [0, 1].
+	pub fn new(width: u32, height: u32, values: Vec<f32>) -> Result<Self, BrushShapeError> {
+		if width == 0 || height == 0 {
+			return Err(BrushShapeError::Empty);
+		}
+		if width > MAX_BRUSH_SHAPE_SIZE || height > MAX_BRUSH_SHAPE_SIZE {
+			return Err(BrushShapeError::TooLarge { width, height });
+		}
+		if values.len() != (width * height) as usize {
+			return Err(BrushShapeError::Decode(format!(
+				"expected {} values but found {}",
+				width * height,
+				values.len()
+			)));
+		}
+		let values = values.into_iter().map(|v| v.clamp(0.0, 1.0)).collect();
+		Ok(Self {
+			width,
+			height,
+			values,
+		})
+	}
+
+	/// A disc of full density filling `size` texels, with an edge a texel wide.
+	pub fn round(size: u32) -> Self {
+		let radius = 0.5 * size as f32;
+		let values = (0..size * size)
+			.map(|i| {
+				let texel = glam::uvec2(i % size, i / size).as_vec2() + 0.5;
+				let distance = (texel - radius).length();
+				(radius - distance).clamp(0.0, 1.0)
+			})
+			.collect();
+		Self {
+			width: size,
+			height: size,
+			values,
+		}
+	}
+
+	/// The built-in shape named `name`, which is one of [`super::BUILT_IN_SHAPES`].
+	pub fn built_in(name: &str) -> Option<Self> {
+		match name {
+			DEFAULT_BRUSH_SHAPE => Some(embedded_shapes::get_shape_00507()),
+			ROUND_BRUSH_SHAPE => Some(Self::round(ROUND_BRUSH_SHAPE_SIZE)),
+			_ => None,
+		}
+	}
+}
+
+/// The shapes brushes can use, by name. It starts with the built-in shapes, and users may add
+/// their own.
+#[derive(Debug, Clone)]
+pub struct BrushShapes {
+	shapes: Vec<(String, Arc<BrushShape>)>,
+}
+
+impl Default for BrushShapes {
+	fn default() -> Self {
+		let shapes = BUILT_IN_SHAPES
+			.into_iter()
+			.filter_map(|name| Some((name.to_string(), Arc::new(BrushShape::built_in(name)?))))
+			.collect();
+		Self { shapes }
+	}
+}
+
+impl BrushShapes {
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.shapes.iter().map(|(name, _)| name.as_str())
+	}
+
+	pub fn get(&self, name: &str) -> Option<Arc<BrushShape>> {
+		self
+			.shapes
+			.iter()
+			.find(|(n, _)| n == name)
+			.map(|(_, shape)| shape.clone())
+	}
+
+	/// Adds a shape, replacing any of the same name, including built-in ones.
+	pub fn insert(&mut self, name: impl Into<String>, shape: BrushShape) {
+		let name = name.into();
+		let shape = Arc::new(shape);
+		match self.shapes.iter_mut().find(|(n, _)| *n == name) {
+			Some((_, existing)) => *existing = shape,
+			None => self.shapes.push((name, shape)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use zune_image::codecs::ImageFormat;
+
+	fn encode(width: usize, height: usize, luma: &[u8]) -> Vec<u8> {
+		let image = Image::from_u8(luma, width, height, ColorSpace::Luma);
+		image.write_to_vec(ImageFormat::PNG).unwrap()
+	}
+
+	#[test]
+	fn decodes_grayscale() {
+		let shape = BrushShape::decode(&encode(2, 1, &[0, 255])).unwrap();
+		assert_eq!((shape.width, shape.height), (2, 1));
+		assert_eq!(shape.values, [0.0, 1.0]);
+	}
+
+	#[test]
+	fn rejects_bad_shapes() {
+		assert!(matches!(
+			BrushShape::decode(b"not an image"),
+			Err(BrushShapeError::Decode(_))
+		));
+		let size = MAX_BRUSH_SHAPE_SIZE as usize + 1;
+		assert_eq!(
+			BrushShape::decode(&encode(size, 1, &vec![0; size])),
+			Err(BrushShapeError::TooLarge {
+				width: size as u32,
+				height: 1
+			})
+		);
+		assert_eq!(BrushShape::new(0, 4, vec![]), Err(BrushShapeError::Empty));
+	}
+
+	#[test]
+	fn round() {
+		let shape = BrushShape::round(8);
+		let value = |x: u32, y: u32| shape.values[(y * 8 + x) as usize];
+		assert_eq!(value(4, 4), 1.0);
+		assert_eq!(value(0, 0), 0.0);
+		assert!(value(0, 4) > 0.0 && value(0, 4) < 1.0);
+	}
+
+	#[test]
+	fn registry() {
+		let mut shapes = BrushShapes::default();
+		for name in BUILT_IN_SHAPES {
+			assert!(shapes.get(name).is_some(), "{name}");
+		}
+
+		let dot = BrushShape::new(1, 1, vec![1.0]).unwrap();
+		shapes.insert("dot", dot.clone());
+		shapes.insert("dot", dot.clone());
+		assert_eq!(
+			shapes.names().collect::<Vec<_>>(),
+			[DEFAULT_BRUSH_SHAPE, ROUND_BRUSH_SHAPE, "dot"]
+		);
+		assert_eq!(shapes.get("dot").as_deref(), Some(&dot));
+		assert!(shapes.get("missing").is_none());
+	}
+}
@@ -0,0 +1,201 @@
+use glam::Vec4;
+use half::f16;
+
+/// The texture format charts are stored in. This is a per-document setting trading memory for
+/// precision.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	Default,
+	serde::Serialize,
+	serde::Deserialize,
+	derive_more::Display,
+)]
+pub enum CanvasFormat {
+	/// Half the memory of the default, at the cost of visible banding in smooth gradients.
+	#[display("8-bit")]
+	Rgba8Unorm,
+	#[default]
+	#[display("16-bit float")]
+	Rgba16Float,
+	/// For HDR workflows. Requires float32 filtering and blending support.
+	#[display("32-bit float")]
+	Rgba32Float,
+}
+
+impl CanvasFormat {
+	pub const ALL: [Self; 3] = [Self::Rgba8Unorm, Self::Rgba16Float, Self::Rgba32Float];
+
+	/// A stable identifier for serialized documents.
+	pub fn index(self) -> u32 {
+		Self::ALL.iter().position(|&f| f == self).unwrap() as u32
+	}
+
+	pub fn from_index(index: u32) -> Option<Self> {
+		Self::ALL.get(index as usize).copied()
+	}
+
+	pub fn texture_format(self) -> wgpu::TextureFormat {
+		match self {
+			Self::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+			Self::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+			Self::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+		}
+	}
+
+	/// Device features that must be enabled to use this format.
+	pub fn required_features(self) -> wgpu::Features {
+		match self {
+			Self::Rgba32Float => wgpu::Features::FLOAT32_FILTERABLE,
+			_ => wgpu::Features::empty(),
+		}
+	}
+
+	/// Whether charts in this format can be drawn to, blended, and sampled with filtering.
+	pub fn is_supported(self, adapter: &wgpu::Adapter, device_features: wgpu::Features) -> bool {
+		use wgpu::TextureFormatFeatureFlags as Flags;
+		use wgpu::TextureUsages as Usages;
+		let features = adapter.get_texture_format_features(self.texture_format());
+		device_features.contains(self.required_features())
+			&& features.allowed_usages.contains(
+				Usages::RENDER_ATTACHMENT
+					| Usages::TEXTURE_BINDING
+					| Usages::COPY_SRC
+					| Usages::COPY_DST,
+			) && features
+			.flags
+			.contains(Flags::FILTERABLE | Flags::BLENDABLE)
+	}
+
+	/// The offset added to the Oklab `a` and `b` channels when they are stored. Unsigned normalized
+	/// formats can't represent negative values, so they store `a` and `b` offset to the middle of
+	/// the range. This must match `chart_ab_offset` in `lib/chart_color.wgsl`.
+	pub fn chart_ab_offset(self) -> f32 {
+		match self {
+			Self::Rgba8Unorm => 128.0 / 255.0,
+			Self::Rgba16Float | Self::Rgba32Float => 0.0,
+		}
+	}
+
+	/// The encoding of a single fully-transparent black pixel, used to clear new charts.
+	pub fn clear_pixel(self) -> Vec<u8> {
+		match self {
+			Self::Rgba8Unorm => vec![0, 128, 128, 0],
+			Self::Rgba16Float => bytemuck::cast_slice(&[f16::ZERO; 4]).to_vec(),
+			Self::Rgba32Float => bytemuck::cast_slice(&[0f32; 4]).to_vec(),
+		}
+	}
+
+	/// Decodes tightly packed texture data into Oklab colors with alpha.
+	pub fn decode_pixels(self, data: &[u8]) -> Vec<Vec4> {
+		let offset = Vec4::new(0.0, self.chart_ab_offset(), self.chart_ab_offset(), 0.0);
+		let raw: Vec<Vec4> = match self {
+			Self::Rgba8Unorm => data
+				.chunks_exact(4)
+				.map(|c| Vec4::from_array([c[0], c[1], c[2], c[3]].map(|c| c as f32 / 255.0)))
+				.collect(),
+			Self::Rgba16Float => bytemuck::pod_collect_to_vec::<_, [f16; 4]>(data)
+				.into_iter()
+				.map(|c| Vec4::from_array(c.map(f16::to_f32)))
+				.collect(),
+			Self::Rgba32Float => bytemuck::pod_collect_to_vec::<_, [f32; 4]>(data)
+				.into_iter()
+				.map(Vec4::from_array)
+				.collect(),
+		};
+		raw.into_iter().map(|p| p - offset).collect()
+	}
+
+	/// Encodes Oklab colors with alpha as tightly packed texture data, the inverse of
+	/// [`Self::decode_pixels`] up to precision.
+	pub fn encode_pixels(self, pixels: &[Vec4]) -> Vec<u8> {
+		let offset = Vec4::new(0.0, self.chart_ab_offset(), self.chart_ab_offset(), 0.0);
+		let raw = pixels.iter().map(|&p| p + offset);
+		match self {
+			Self::Rgba8Unorm => raw
+				.flat_map(|p| {
+					(p.clamp(Vec4::ZERO, Vec4::ONE) * 255.0)
+						.round()
+						.to_array()
+						.map(|c| c as u8)
+				})
+				.collect(),
+			Self::Rgba16Float => {
+				let raw: Vec<[f16; 4]> = raw.map(|p| p.to_array().map(f16::from_f32)).collect();
+				bytemuck::cast_slice(&raw).to_vec()
+			}
+			Self::Rgba32Float => {
+				let raw: Vec<[f32; 4]> = raw.map(|p| p.to_array()).collect();
+				bytemuck::cast_slice(&raw).to_vec()
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clear_pixel_size() {
+		for format in CanvasFormat::ALL {
+			assert_eq!(
+				Some(format.clear_pixel().len() as u32),
+				format.texture_format().block_copy_size(None),
+				"{format:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn clear_pixel_decodes_to_zero() {
+		let clear = CanvasFormat::Rgba8Unorm.clear_pixel();
+		let offset = CanvasFormat::Rgba8Unorm.chart_ab_offset();
+		let decoded: Vec<f32> = clear.iter().map(|&c| c as f32 / 255.0).collect();
+		assert_eq!(decoded[0], 0.0);
+		assert_eq!(decoded[1] - offset, 0.0);
+		assert_eq!(decoded[2] - offset, 0.0);
+		assert_eq!(decoded[3], 0.0);
+
+		for format in [CanvasFormat::Rgba16Float, CanvasFormat::Rgba32Float] {
+			assert_eq!(format.chart_ab_offset(), 0.0);
+			assert!(format.clear_pixel().iter().all(|&b| b == 0));
+		}
+	}
+
+	#[test]
+	fn index_round_trip() {
+		for format in CanvasFormat::ALL {
+			assert_eq!(CanvasFormat::from_index(format.index()), Some(format));
+		}
+		assert_eq!(CanvasFormat::from_index(3), None);
+	}
+
+	#[test]
+	fn clear_pixel_decodes_to_transparent() {
+		for format in CanvasFormat::ALL {
+			assert_eq!(
+				format.decode_pixels(&format.clear_pixel()),
+				[Vec4::ZERO],
+				"{format:?}"
+			);
+			assert_eq!(
+				format.encode_pixels(&[Vec4::ZERO]),
+				format.clear_pixel(),
+				"{format:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn serialization_round_trip() {
+		for format in CanvasFormat::ALL {
+			let json = serde_json::to_string(&format).unwrap();
+			assert_eq!(serde_json::from_str::<CanvasFormat>(&json).unwrap(), format);
+		}
+	}
+}
@@ -0,0 +1,820 @@
+use super::{
+	draw_tiles, Atlas, CanvasFormat, ChartKey, DocumentBounds, FloatingSelection, Tile, CHART_SCALE,
+	CHART_SIZE,
+};
+use crate::coordinates::{CanvasPt, ScreenPx};
+use crate::render::{
+	error_scope, BindingBuffer, CapturedCommand, FrameCapturer, FrameRing, Resources,
+};
+use crate::shaders::{self, canvas::CanvasView};
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::sync::{Arc, Mutex};
+
+fn create_canvas_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+	error_scope(device, "canvas_renderer::create_canvas_sampler", || {
+		device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Nearest,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		})
+	})
+}
+
+/// The format of the canvas pass's depth/stencil attachment, when it has one. Nothing tests or writes
+/// depth yet, but the stencil can clip the composite to a selection or a layer's mask.
+pub const CANVAS_DEPTH_STENCIL_FORMAT: wgpu::TextureFormat =
+	wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// A depth/stencil state for drawing to a pass with a depth/stencil attachment of `format` as if it
+/// had none. Every fragment passes, and neither depth nor stencil is written.
+pub fn passthrough_depth_stencil(format: wgpu::TextureFormat) -> wgpu::DepthStencilState {
+	wgpu::DepthStencilState {
+		format,
+		depth_write_enabled: false,
+		depth_compare: wgpu::CompareFunction::Always,
+		stencil: wgpu::StencilState::default(),
+		bias: wgpu::DepthBiasState::default(),
+	}
+}
+
+/// How large chart texels must be on screen, in pixels, for the pixel grid to start fading in. It is
+/// fully shown at twice this.
+pub const PIXEL_GRID_MIN_TEXEL_SIZE: f32 = 8.0;
+
+/// How opaque the pixel grid is once fully shown.
+pub const PIXEL_GRID_OPACITY: f32 = 0.4;
+
+/// The range of colors the canvas is shown in, which determines the format of its surface.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceColorSpace {
+	/// sRGB, with 8 bits per channel.
+	#[default]
+	Srgb,
+	/// Extended sRGB, with half-precision floats per channel, which shows colors outside of sRGB on
+	/// displays that can. Falls back to [`Self::Srgb`] where unsupported.
+	Extended,
+}
+
+impl SurfaceColorSpace {
+	/// Chooses a surface format from `formats`, ordered by the surface's preference. Formats which
+	/// the canvas shader encodes are preferred over sRGB formats, since those blend differently than
+	/// exports do. Falls back to the surface's preferred format.
+	pub fn choose_format(self, formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+		use wgpu::TextureFormat::*;
+		let find = |candidates: &[wgpu::TextureFormat]| {
+			formats
+				.iter()
+				.copied()
+				.find(|format| candidates.contains(format))
+		};
+		let extended = match self {
+			Self::Extended => find(&[Rgba16Float]),
+			Self::Srgb => None,
+		};
+		extended
+			.or_else(|| find(&[Bgra8Unorm, Rgba8Unorm]))
+			.or_else(|| find(&[Bgra8UnormSrgb, Rgba8UnormSrgb]))
+			.or_else(|| formats.first().copied())
+	}
+}
+
+/// Draws the charts of an [`Atlas`] to a render target through a [`ViewTransform`].
+pub struct CanvasRenderer {
+	resources: Arc<Resources>,
+	format: CanvasFormat,
+	// The view is written every frame, so each frame in flight has its own buffer.
+	views: FrameRing<(BindingBuffer<CanvasView>, shaders::canvas::BindGroup0)>,
+	// The charts the last view written shows, or `None` to draw every chart.
+	visible_charts: Mutex<Option<(ChartKey, ChartKey)>>,
+}
+
+impl CanvasRenderer {
+	pub fn new(device: &wgpu::Device, resources: Arc<Resources>, format: CanvasFormat) -> Self {
+		let sampler = create_canvas_sampler(device);
+		let views = FrameRing::new(|_| {
+			let view_buffer = BindingBuffer::init(&CanvasView {
+				canvas_to_view: Mat4::ZERO,
+				chart_origin: IVec2::ZERO,
+				clip_min: Vec2::MIN,
+				clip_max: Vec2::MAX,
+				texel_size: 1.0,
+				grid_opacity: 0.0,
+			})
+			.label("CanvasRenderer::view_buffer")
+			.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+			.create(device);
+			let bind_group = error_scope(device, "CanvasRenderer::bind_group", || {
+				resources
+					.canvas
+					.pipeline_layout()
+					.get()
+					.bind_group_layouts()
+					.0
+					.bind_group()
+					.chart_sampler(&sampler)
+					.view(view_buffer.as_entire_buffer_binding())
+					.create()
+			});
+			(view_buffer, bind_group)
+		});
+		Self {
+			resources,
+			format,
+			views,
+			visible_charts: Mutex::new(None),
+		}
+	}
+
+	/// Creates a pipeline drawing to `target_format`. sRGB formats are written linear colors, since
+	/// they encode colors themselves, and others are written encoded colors. Passes with a
+	/// depth/stencil attachment need a matching `depth_stencil`, such as
+	/// [`passthrough_depth_stencil`].
+	pub fn create_pipeline(
+		&self,
+		target_format: wgpu::TextureFormat,
+		sample_count: u32,
+		depth_stencil: Option<wgpu::DepthStencilState>,
+	) -> wgpu::RenderPipeline {
+		let linear_output = target_format.is_srgb();
+		self.create_pipeline_with_output(target_format, sample_count, depth_stencil, linear_output)
+	}
+
+	/// Like [`Self::create_pipeline`], but always writes linear colors, for float textures that are
+	/// filtered and encoded later.
+	pub fn create_linear_pipeline(
+		&self,
+		target_format: wgpu::TextureFormat,
+	) -> wgpu::RenderPipeline {
+		self.create_pipeline_with_output(target_format, 1, None, true)
+	}
+
+	fn create_pipeline_with_output(
+		&self,
+		target_format: wgpu::TextureFormat,
+		sample_count: u32,
+		depth_stencil: Option<wgpu::DepthStencilState>,
+		linear_output: bool,
+	) -> wgpu::RenderPipeline {
+		self
+			.resources
+			.canvas
+			.pipeline_layout()
+			.get()
+			.vs_main_pipeline(wgpu::VertexStepMode::Instance)
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(shaders::canvas::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: target_format,
+					// Charts are drawn over the background, which may itself be transparent.
+					blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.multisample(wgpu::MultisampleState {
+				count: sample_count,
+				..Default::default()
+			})
+			.maybe_depth_stencil(depth_stencil)
+			.overrides(shaders::canvas::OverrideConstants {
+				chart_ab_offset: Some(self.format.chart_ab_offset()),
+				linear_output: Some(linear_output),
+			})
+			.maybe_cache(self.resources.pipeline_cache())
+			.get()
+	}
+
+	/// Computes the shader view for a screen of `screen_size` pixels. It isn't clipped, as for an
+	/// unbounded document.
+	///
+	/// The view is relative to the chart under the center of the screen. This keeps the `f32` values
+	/// in the vertex path small no matter how far the view is from the canvas origin.
+	pub fn canvas_view(view_transform: &ViewTransform, screen_size: DVec2) -> CanvasView {
+		// This is the mapping from normalized device coordinates to framebuffer coordinates.
+		// Equivalently, it transforms `@builtin(position)` from the vertex to the fragment shader.
+		let scale = 0.5 * screen_size.as_vec2();
+		let view_to_screen = Mat4::from_scale(scale.extend(1.0))
+			* Mat4::from_translation(vec3(1.0, 1.0, 0.0))
+			* Mat4::from_scale(vec3(1.0, -1.0, 1.0));
+		let screen_to_view = view_to_screen.inverse();
+
+		let screen_center = 0.5 * screen_size;
+		let chart = ScreenPx(screen_center).to_canvas(view_transform).chart();
+		let chart_origin = ivec2(chart.0, chart.1);
+		let origin = CanvasPt::chart_origin(chart).0;
+		CanvasView {
+			canvas_to_view: screen_to_view
+				* view_transform.canvas_to_screen_matrix_relative_to(origin),
+			chart_origin,
+			clip_min: Vec2::MIN,
+			clip_max: Vec2::MAX,
+			// Each chart texel covers a canvas unit.
+			texel_size: view_transform.zoom as f32 * CHART_SCALE / CHART_SIZE as f32,
+			grid_opacity: 0.0,
+		}
+	}
+
+	/// `canvas_view`, with a grid between texels once they are large enough on screen to tell apart.
+	pub fn pixel_grid_view(canvas_view: &CanvasView) -> CanvasView {
+		let fade_in = canvas_view.texel_size / PIXEL_GRID_MIN_TEXEL_SIZE - 1.0;
+		CanvasView {
+			grid_opacity: PIXEL_GRID_OPACITY * fade_in.clamp(0.0, 1.0),
+			..canvas_view.clone()
+		}
+	}
+
+	/// `canvas_view`, clipped to `bounds`. Charts outside of them aren't drawn at all.
+	pub fn bounded_view(canvas_view: &CanvasView, bounds: DocumentBounds) -> CanvasView {
+		let origin = canvas_view.chart_origin * CHART_SIZE as i32;
+		CanvasView {
+			canvas_to_view: canvas_view.canvas_to_view,
+			chart_origin: canvas_view.chart_origin,
+			clip_min: (bounds.min() - origin).as_vec2(),
+			clip_max: (bounds.max() - origin).as_vec2(),
+			..canvas_view.clone()
+		}
+	}
+
+	/// The view for drawing charts placed through `transform`, in canvas coordinates, such as those
+	/// of a [`FloatingSelection`], through `canvas_view`. It isn't clipped, since the clip would
+	/// apply before the transform.
+	pub fn transformed_view(canvas_view: &CanvasView, transform: DAffine2) -> CanvasView {
+		// Like the view, the transform is taken relative to the view's chart.
+		let chart_origin = canvas_view.chart_origin;
+		let origin = CanvasPt::chart_origin(ChartKey(chart_origin.x, chart_origin.y)).0;
+		let relative =
+			DAffine2::from_translation(-origin) * transform * DAffine2::from_translation(origin);
+		let (linear, translation) = (relative.matrix2.as_mat2(), relative.translation.as_vec2());
+		let relative = Mat4::from_cols(
+			linear.x_axis.extend(0.0).extend(0.0),
+			linear.y_axis.extend(0.0).extend(0.0),
+			Vec4::Z,
+			translation.extend(0.0).extend(1.0),
+		);
+		// Nor is it gridded, since its texels needn't line up with the canvas's.
+		CanvasView {
+			canvas_to_view: canvas_view.canvas_to_view * relative,
+			chart_origin,
+			clip_min: Vec2::MIN,
+			clip_max: Vec2::MAX,
+			texel_size: canvas_view.texel_size,
+			grid_opacity: 0.0,
+		}
+	}
+
+	/// The smallest and largest keys along each axis of the charts `canvas_view` shows any of within
+	/// its clip, or `None` if the view is degenerate, as it is for an empty screen.
+	pub fn visible_charts(canvas_view: &CanvasView) -> Option<(ChartKey, ChartKey)> {
+		// Working relative to the view's chart keeps this precise far from the origin.
+		let view_to_canvas = canvas_view.canvas_to_view.inverse();
+		let corners = [
+			vec2(-1.0, -1.0),
+			vec2(1.0, -1.0),
+			vec2(1.0, 1.0),
+			vec2(-1.0, 1.0),
+		]
+		.map(|corner| view_to_canvas.project_point3(corner.extend(0.0)).truncate());
+		if !corners.iter().all(|corner| corner.is_finite()) {
+			return None;
+		}
+		let key = |point: Vec2| {
+			let key = ChartKey::find_containing(point);
+			ChartKey(
+				key.0 + canvas_view.chart_origin.x,
+				key.1 + canvas_view.chart_origin.y,
+			)
+		};
+		// Charts outside of the clip aren't shown either.
+		let min = corners
+			.into_iter()
+			.reduce(Vec2::min)?
+			.max(canvas_view.clip_min);
+		let max = corners
+			.into_iter()
+			.reduce(Vec2::max)?
+			.min(canvas_view.clip_max);
+		Some((key(min), key(max)))
+	}
+
+	/// Writes the view for a new frame. This must happen before submitting any passes from
+	/// [`Self::draw`], which draw with the view last written.
+	pub fn write_view(&self, queue: &wgpu::Queue, canvas_view: &CanvasView) {
+		let (view_buffer, _) = self.views.advance();
+		view_buffer.write(queue, canvas_view);
+		*self.visible_charts.lock().unwrap() = Self::visible_charts(canvas_view);
+	}
+
+	pub fn draw(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		atlas: &Atlas,
+	) {
+		// The canvas is unbounded, so only the charts in view, and within the clip, are drawn.
+		let charts: Vec<_> = match *self.visible_charts.lock().unwrap() {
+			Some((min, max)) => atlas.charts_between(min, max).collect(),
+			None => atlas.charts().collect(),
+		};
+		let tiles: Vec<_> = charts.iter().map(|c| c.tile()).collect();
+		let context = atlas.tile_pool().context();
+		self.draw_charts(render_pass, pipeline, context.frame_capture(), &tiles);
+	}
+
+	/// Draws the lifted charts of `floating`. The view last written should be transformed to place
+	/// them, as by [`Self::transformed_view`], so this renderer needs its own view.
+	pub fn draw_floating(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		floating: &FloatingSelection,
+		capture: &FrameCapturer,
+	) {
+		let visible = *self.visible_charts.lock().unwrap();
+		let tiles: Vec<_> = floating
+			.pieces()
+			.filter(|(key, _)| match visible {
+				Some((min, max)) => {
+					(min.0..=max.0).contains(&key.0) && (min.1..=max.1).contains(&key.1)
+				}
+				None => true,
+			})
+			.map(|(_, tile)| tile)
+			.collect();
+		self.draw_charts(render_pass, pipeline, capture, &tiles);
+	}
+
+	fn draw_charts(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		capture: &FrameCapturer,
+		tiles: &[&Tile],
+	) {
+		render_pass.set_pipeline(pipeline);
+		capture.record(|| CapturedCommand::SetPipeline {
+			pipeline: "canvas".to_string(),
+		});
+		let (_, bind_group) = self.views.current();
+		bind_group.set(render_pass);
+		capture.record(|| CapturedCommand::SetBindGroup {
+			index: 0,
+			bind_group: "canvas view".to_string(),
+		});
+		draw_tiles(render_pass, 0..4, tiles);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::CHART_SCALE;
+	use crate::{render, test};
+
+	#[test]
+	fn viewing_never_allocates_charts() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		atlas.get_chart_mut(ChartKey(0, 0));
+
+		let renderer = CanvasRenderer::new(device, Arc::new(Resources::new(device)), format);
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format, 1, None);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+			.create(device)
+			.create_view(&Default::default());
+		let screen_size = dvec2(64.0, 48.0);
+
+		let mut view_transform = ViewTransform::default();
+		for _ in 0..8 {
+			view_transform.pan(dvec2(300.0, -200.0));
+			view_transform.zoom_about(0.5 * screen_size, 0.5);
+			let canvas_view = CanvasRenderer::canvas_view(&view_transform, screen_size);
+			renderer.write_view(context.queue(), &canvas_view);
+
+			let mut encoder = device.create_command_encoder(&Default::default());
+			{
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+						view: &target,
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+							store: wgpu::StoreOp::Store,
+						},
+					})],
+					..Default::default()
+				});
+				renderer.draw(&mut render_pass, &pipeline, &atlas);
+			}
+			context.queue().submit([encoder.finish()]);
+			assert_eq!(atlas.charts().count(), 1);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn captures_a_frame() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		// The second chart is allocated in the pool's second block.
+		atlas.get_chart_mut(ChartKey(0, 0));
+		atlas.get_chart_mut(ChartKey(1, 0));
+
+		let renderer = CanvasRenderer::new(device, Arc::new(Resources::new(device)), format);
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format, 1, None);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+			.create(device)
+			.create_view(&Default::default());
+		let screen_size = dvec2(2.0 * CHART_SCALE as f64, CHART_SCALE as f64);
+		let canvas_view = CanvasRenderer::canvas_view(&ViewTransform::default(), screen_size);
+		renderer.write_view(context.queue(), &canvas_view);
+
+		let capture = context.frame_capture();
+		capture.capture_next_frame();
+		capture.begin_frame();
+		capture.begin_pass("Render Pass");
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &target,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			renderer.draw(&mut render_pass, &pipeline, &atlas);
+		}
+		context.queue().submit([encoder.finish()]);
+		let frame = capture.end_frame().unwrap();
+
+		assert_eq!(frame.passes.len(), 1);
+		let commands = &frame.passes[0].commands;
+		assert_eq!(
+			commands[0],
+			CapturedCommand::SetPipeline {
+				pipeline: "canvas".to_string()
+			}
+		);
+		let draws = commands
+			.iter()
+			.filter_map(|command| match command {
+				CapturedCommand::Draw {
+					vertices, layers, ..
+				} => Some((vertices.clone(), layers.clone())),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(draws, [(0..4, vec![0]), (0..4, vec![0])]);
+		assert_eq!(frame.draw_count(), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn transformed_view_places_charts() {
+		let s = CHART_SCALE as f64;
+		let mut view_transform = ViewTransform::default();
+		view_transform.pan(dvec2(-40.0 * s, 3.5 * s));
+		let canvas_view = CanvasRenderer::canvas_view(&view_transform, dvec2(2.0 * s, s));
+		let transform = DAffine2::from_translation(dvec2(40.0 * s, -3.0 * s))
+			* DAffine2::from_angle(0.5)
+			* DAffine2::from_scale(dvec2(2.0, 0.5));
+		let transformed = CanvasRenderer::transformed_view(&canvas_view, transform);
+
+		// Both views work relative to the chart at the center of the screen.
+		let origin = CanvasPt::chart_origin(ChartKey(
+			canvas_view.chart_origin.x,
+			canvas_view.chart_origin.y,
+		))
+		.0;
+		let relative = |p: DVec2| (p - origin).as_vec2().extend(0.0);
+		for point in [dvec2(10.0, 20.0), dvec2(-3.0 * s, 0.5 * s)] {
+			let placed = transform.transform_point2(point);
+			let expected = canvas_view.canvas_to_view.project_point3(relative(placed));
+			let actual = transformed.canvas_to_view.project_point3(relative(point));
+			assert!(actual.abs_diff_eq(expected, 1e-4), "{actual} != {expected}");
+		}
+	}
+
+	#[test]
+	fn finds_visible_charts() {
+		let s = CHART_SCALE as f64;
+		let screen_size = dvec2(2.0 * s, s);
+		let visible = |view_transform: &ViewTransform| {
+			CanvasRenderer::visible_charts(&CanvasRenderer::canvas_view(view_transform, screen_size))
+		};
+
+		// The screen shows canvas `[-1.5, 0.5] x [2.25, 3.25]` charts.
+		let mut view_transform = ViewTransform::default();
+		view_transform.pan(dvec2(1.5 * s, -2.25 * s));
+		assert_eq!(
+			visible(&view_transform),
+			Some((ChartKey(-2, 2), ChartKey(0, 3)))
+		);
+
+		// Far from the origin, zoomed out and rotated, the screen covers more charts.
+		view_transform.pan(dvec2(-1e4 * s, 0.0));
+		view_transform.zoom_about(0.5 * screen_size, 0.5);
+		let (min, max) = visible(&view_transform).unwrap();
+		assert_eq!((min.0, max.0), (9997, 10001));
+		view_transform.rotate_about(0.5 * screen_size, std::f64::consts::FRAC_PI_2);
+		let (rotated_min, rotated_max) = visible(&view_transform).unwrap();
+		assert!(rotated_max.0 - rotated_min.0 < max.0 - min.0);
+		assert!(rotated_max.1 - rotated_min.1 > max.1 - min.1);
+
+		// There is no telling what a degenerate view shows.
+		assert_eq!(
+			CanvasRenderer::visible_charts(&CanvasRenderer::canvas_view(&view_transform, DVec2::ZERO)),
+			None
+		);
+	}
+
+	#[test]
+	fn pixel_grid_fades_in_with_zoom() {
+		let grid_opacity = |zoom: f64| {
+			let mut view_transform = ViewTransform::default();
+			view_transform.set_zoom_about(DVec2::ZERO, zoom);
+			let canvas_view = CanvasRenderer::canvas_view(&view_transform, dvec2(64.0, 48.0));
+			assert_eq!(canvas_view.grid_opacity, 0.0);
+			CanvasRenderer::pixel_grid_view(&canvas_view).grid_opacity
+		};
+		assert_eq!(grid_opacity(4.0), 0.0);
+		let fading = grid_opacity(12.0);
+		assert!(fading > 0.0 && fading < PIXEL_GRID_OPACITY, "{fading}");
+		assert_eq!(grid_opacity(32.0), PIXEL_GRID_OPACITY);
+	}
+
+	#[test]
+	fn bounded_view_culls_charts() {
+		let s = CHART_SCALE as f64;
+		let screen_size = dvec2(2.0 * s, s);
+		// The screen shows canvas `[-1.5, 0.5] x [2.25, 3.25]` charts, far from the canvas origin.
+		let mut view_transform = ViewTransform::default();
+		view_transform.pan(dvec2(1.5 * s, -2.25 * s));
+		view_transform.pan(dvec2(-1e4 * s, 0.0));
+		let canvas_view = CanvasRenderer::canvas_view(&view_transform, screen_size);
+		let (min, max) = CanvasRenderer::visible_charts(&canvas_view).unwrap();
+
+		// Bounds within the second visible chart from the left.
+		let origin = ivec2((min.0 + 1) * CHART_SIZE as i32 + 10, 2 * CHART_SIZE as i32);
+		let bounds = DocumentBounds {
+			origin,
+			size: uvec2(100, 2 * CHART_SIZE),
+		};
+		let bounded = CanvasRenderer::bounded_view(&canvas_view, bounds);
+		assert_eq!(
+			CanvasRenderer::visible_charts(&bounded),
+			Some((ChartKey(min.0 + 1, min.1), ChartKey(min.0 + 1, max.1)))
+		);
+		// The clip is relative to the same chart as the view.
+		let chart_origin = canvas_view.chart_origin * CHART_SIZE as i32;
+		assert_eq!(bounded.clip_min, (origin - chart_origin).as_vec2());
+	}
+
+	#[test]
+	fn composites_premultiplied() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		// Half transparent white.
+		let pixel = format.encode_pixels(&[vec4(1.0, 0.0, 0.0, 0.5)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+
+		let renderer = CanvasRenderer::new(device, Arc::new(Resources::new(device)), format);
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format, 1, None);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+			.create(device);
+		let screen_size = dvec2(64.0, 48.0);
+
+		// Only the right half of the screen shows the chart.
+		let mut view_transform = ViewTransform::default();
+		view_transform.pan(dvec2(32.0, 0.0));
+		let canvas_view = CanvasRenderer::canvas_view(&view_transform, screen_size);
+		renderer.write_view(context.queue(), &canvas_view);
+
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &target.create_view(&Default::default()),
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			renderer.draw(&mut render_pass, &pipeline, &atlas);
+		}
+		context.queue().submit([encoder.finish()]);
+
+		let data = pollster::block_on(context.get_texture_layer_data(&target, 0))?;
+		let texel = |x: usize, y: usize| {
+			let offset = 4 * (y * 64 + x);
+			&data[offset..offset + 4]
+		};
+		assert_eq!(texel(8, 24), [0, 0, 0, 0]);
+		for c in texel(48, 24) {
+			assert!(c.abs_diff(128) <= 1, "{:?}", texel(48, 24));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn draws_the_same_with_a_depth_stencil_attachment() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let pixel = format.encode_pixels(&[vec4(0.6, 0.0, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+		let renderer = CanvasRenderer::new(device, Arc::new(Resources::new(device)), format);
+		let mut view_transform = ViewTransform::default();
+		view_transform.pan(dvec2(8.0, 0.0));
+		let canvas_view = CanvasRenderer::canvas_view(&view_transform, dvec2(16.0, 16.0));
+		renderer.write_view(context.queue(), &canvas_view);
+
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let render = |depth_stencil: bool| -> anyhow::Result<Vec<u8>> {
+			let pipeline = renderer.create_pipeline(
+				target_format,
+				1,
+				depth_stencil.then(|| passthrough_depth_stencil(CANVAS_DEPTH_STENCIL_FORMAT)),
+			);
+			let target = render::texture()
+				.width(16)
+				.height(16)
+				.format(target_format)
+				.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+				.create(device);
+			let depth_stencil_view = depth_stencil.then(|| {
+				render::texture()
+					.width(16)
+					.height(16)
+					.format(CANVAS_DEPTH_STENCIL_FORMAT)
+					.usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+					.create(device)
+					.create_view(&Default::default())
+			});
+			let mut encoder = device.create_command_encoder(&Default::default());
+			{
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+						view: &target.create_view(&Default::default()),
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+							store: wgpu::StoreOp::Store,
+						},
+					})],
+					depth_stencil_attachment: depth_stencil_view.as_ref().map(|view| {
+						wgpu::RenderPassDepthStencilAttachment {
+							view,
+							depth_ops: Some(wgpu::Operations {
+								load: wgpu::LoadOp::Clear(0.0),
+								store: wgpu::StoreOp::Discard,
+							}),
+							stencil_ops: Some(wgpu::Operations {
+								load: wgpu::LoadOp::Clear(1),
+								store: wgpu::StoreOp::Discard,
+							}),
+						}
+					}),
+					..Default::default()
+				});
+				renderer.draw(&mut render_pass, &pipeline, &atlas);
+			}
+			context.queue().submit([encoder.finish()]);
+			Ok(pollster::block_on(
+				context.get_texture_layer_data(&target, 0),
+			)?)
+		};
+
+		// The attachment is cleared to values no depth or stencil test would pass, so this only
+		// matches if nothing is tested.
+		let without = render(false)?;
+		assert!(without.chunks_exact(4).any(|texel| texel[3] == 255));
+		assert_eq!(render(true)?, without);
+		Ok(())
+	}
+
+	#[test]
+	fn srgb_targets_show_the_same_colors() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		// Opaque mid gray.
+		let pixel = format.encode_pixels(&[vec4(0.6, 0.0, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+		let renderer = CanvasRenderer::new(device, Arc::new(Resources::new(device)), format);
+		let mut view_transform = ViewTransform::default();
+		view_transform.pan(dvec2(-8.0, -8.0));
+		let canvas_view = CanvasRenderer::canvas_view(&view_transform, dvec2(16.0, 16.0));
+		renderer.write_view(context.queue(), &canvas_view);
+
+		let render = |target_format: wgpu::TextureFormat| -> anyhow::Result<Vec<u8>> {
+			let pipeline = renderer.create_pipeline(target_format, 1, None);
+			let target = render::texture()
+				.width(16)
+				.height(16)
+				.format(target_format)
+				.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+				.create(device);
+			let mut encoder = device.create_command_encoder(&Default::default());
+			{
+				let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+					color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+						view: &target.create_view(&Default::default()),
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+							store: wgpu::StoreOp::Store,
+						},
+					})],
+					..Default::default()
+				});
+				renderer.draw(&mut render_pass, &pipeline, &atlas);
+			}
+			context.queue().submit([encoder.finish()]);
+			let data = pollster::block_on(context.get_texture_layer_data(&target, 0))?;
+			let offset = 4 * (12 * 16 + 12);
+			Ok(data[offset..offset + 4].to_vec())
+		};
+
+		let encoded = render(wgpu::TextureFormat::Rgba8Unorm)?;
+		let srgb = render(wgpu::TextureFormat::Rgba8UnormSrgb)?;
+		assert_eq!(encoded[3], 255);
+		for (a, b) in encoded.iter().zip(&srgb) {
+			assert!(a.abs_diff(*b) <= 1, "{encoded:?} != {srgb:?}");
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn chooses_surface_format() {
+		use wgpu::TextureFormat::*;
+		let formats = [Bgra8UnormSrgb, Rgba16Float, Bgra8Unorm];
+		assert_eq!(
+			SurfaceColorSpace::Srgb.choose_format(&formats),
+			Some(Bgra8Unorm)
+		);
+		assert_eq!(
+			SurfaceColorSpace::Extended.choose_format(&formats),
+			Some(Rgba16Float)
+		);
+		// Without an extended format, or one the shader encodes, fall back.
+		assert_eq!(
+			SurfaceColorSpace::Extended.choose_format(&[Rgba8UnormSrgb, Bgra8Unorm]),
+			Some(Bgra8Unorm)
+		);
+		assert_eq!(
+			SurfaceColorSpace::Srgb.choose_format(&[Rgb10a2Unorm, Rgba8UnormSrgb]),
+			Some(Rgba8UnormSrgb)
+		);
+		assert_eq!(
+			SurfaceColorSpace::Srgb.choose_format(&[Rgb10a2Unorm]),
+			Some(Rgb10a2Unorm)
+		);
+		assert_eq!(SurfaceColorSpace::Srgb.choose_format(&[]), None);
+	}
+}
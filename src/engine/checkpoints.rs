@@ -0,0 +1,364 @@
+use super::{Airbrush, Atlas, Chart, ChartKey, PoolExhausted, VectorDrawing, VectorEdit};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+// The canvas `History` undoes recent edits by swapping charts back, which is quick but keeps every
+// chart each edit touched, so it only goes back so far. Beyond it, the document is restored from a
+// checkpoint of every chart and the strokes since are replayed from the vector drawing. Checkpoints
+// share charts with the atlas until they are drawn over, so they cost little at first, but hold
+// more of their own as edits pass. Frequent checkpoints keep replays short, and thinning them out
+// keeps their memory in check.
+
+/// How often [`Checkpoints`] are taken, and how much memory they may hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointPolicy {
+	/// The edits between checkpoints, which is the most restoring replays while within the budget.
+	pub interval: usize,
+	/// The most memory, in bytes, that charts held only by checkpoints may occupy. Past it,
+	/// checkpoints are dropped, lengthening replays.
+	pub memory_budget: u64,
+}
+
+impl Default for CheckpointPolicy {
+	fn default() -> Self {
+		Self {
+			interval: 25,
+			memory_budget: 256 << 20,
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum RestoreError {
+	/// Edits with no vector form, such as imported images, can only be restored from a checkpoint
+	/// taken after them.
+	#[error("edit {0} can't be replayed")]
+	NotReplayable(usize),
+	#[error(transparent)]
+	TooLarge(#[from] PoolExhausted),
+}
+
+struct Checkpoint {
+	/// How many of the drawing's edits the checkpoint was taken after.
+	edits: usize,
+	charts: HashMap<ChartKey, Arc<Chart>>,
+	/// Whether the edits before it can't be replayed, so it is never dropped.
+	pinned: bool,
+}
+
+/// Checkpoints of an [`Atlas`] along its [`VectorDrawing`], from which any of the drawing's states
+/// can be restored by replaying the strokes after the latest checkpoint before it.
+pub struct Checkpoints {
+	// In order of `edits`, starting with the blank atlas before any edits.
+	checkpoints: Vec<Checkpoint>,
+	policy: CheckpointPolicy,
+}
+
+impl Default for Checkpoints {
+	fn default() -> Self {
+		Self::new(CheckpointPolicy::default())
+	}
+}
+
+impl Checkpoints {
+	/// Creates checkpoints for a blank document.
+	pub fn new(policy: CheckpointPolicy) -> Self {
+		Self {
+			checkpoints: vec![Checkpoint {
+				edits: 0,
+				charts: HashMap::new(),
+				pinned: true,
+			}],
+			policy,
+		}
+	}
+
+	/// Notes that an edit was just recorded in `drawing`, which `atlas` now shows. Checkpoints after
+	/// edits which were undone are forgotten, and one is taken if it has been long enough since the
+	/// last, or the edit can't be replayed. Older checkpoints are then dropped while they hold more
+	/// memory than the policy allows.
+	pub fn record(&mut self, atlas: &Atlas, drawing: &VectorDrawing) {
+		let edits = drawing.edits().count();
+		self
+			.checkpoints
+			.retain(|checkpoint| checkpoint.edits < edits);
+		let replayable = matches!(drawing.edits().last(), Some(VectorEdit::Stroke(_)));
+		let since = edits - self.checkpoints.last().map_or(0, |c| c.edits);
+		if !replayable || since >= self.policy.interval {
+			self.checkpoints.push(Checkpoint {
+				edits,
+				charts: atlas.charts_by_key().collect(),
+				pinned: !replayable,
+			});
+		}
+		self.thin(atlas, edits);
+	}
+
+	/// The memory, in bytes, of the charts held by checkpoints but no longer by `atlas`.
+	pub fn memory(&self, atlas: &Atlas) -> u64 {
+		let held: HashSet<_> = self
+			.checkpoints
+			.iter()
+			.flat_map(|checkpoint| self.own_charts(atlas, checkpoint))
+			.collect();
+		held.len() as u64 * atlas.chart_bytes()
+	}
+
+	/// How many edits restoring the first `edits` of the drawing would replay.
+	pub fn replay_len(&self, edits: usize) -> usize {
+		edits - self.latest_before(edits).edits
+	}
+
+	/// Restores `atlas` to show `drawing`'s edits, from the latest checkpoint before them, replaying
+	/// the strokes since with `airbrush`. Nothing is changed if any of those edits can't be replayed.
+	/// Returns how many strokes were replayed. If the atlas runs out of room for charts, every stroke
+	/// is still drawn to those it has.
+	pub fn restore(
+		&self,
+		queue: &wgpu::Queue,
+		atlas: &mut Atlas,
+		airbrush: &mut Airbrush,
+		drawing: &VectorDrawing,
+	) -> Result<usize, RestoreError> {
+		let edits = drawing.edits().count();
+		let checkpoint = self.latest_before(edits);
+		let strokes = drawing
+			.edits()
+			.enumerate()
+			.skip(checkpoint.edits)
+			.map(|(index, edit)| match edit {
+				VectorEdit::Stroke(stroke) => Ok(stroke),
+				VectorEdit::Raster => Err(RestoreError::NotReplayable(index)),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		atlas.clear();
+		for (&key, chart) in &checkpoint.charts {
+			atlas.replace_chart(key, Some(chart.clone()));
+		}
+		let mut result = Ok(());
+		for stroke in &strokes {
+			result = result.and(stroke.replay(queue, atlas, airbrush, 1.0));
+		}
+		airbrush.stop();
+		result?;
+		Ok(strokes.len())
+	}
+
+	fn latest_before(&self, edits: usize) -> &Checkpoint {
+		self
+			.checkpoints
+			.iter()
+			.rev()
+			.find(|checkpoint| checkpoint.edits <= edits)
+			.expect("the first checkpoint is before every edit")
+	}
+
+	/// The charts `checkpoint` holds which `atlas` doesn't.
+	fn own_charts<'a>(
+		&self,
+		atlas: &'a Atlas,
+		checkpoint: &'a Checkpoint,
+	) -> impl Iterator<Item = *const Chart> + 'a {
+		checkpoint
+			.charts
+			.iter()
+			.filter(|(key, chart)| {
+				atlas
+					.get_chart(key)
+					.map_or(true, |current| !Arc::ptr_eq(&current, chart))
+			})
+			.map(|(_, chart)| Arc::as_ptr(chart))
+	}
+
+	/// Drops checkpoints until they fit the memory budget. Each dropped checkpoint merges the replays
+	/// either side of it, so of those whose charts no other checkpoint shares, the one leaving the
+	/// shortest replay goes first. This spreads the remaining checkpoints out evenly.
+	fn thin(&mut self, atlas: &Atlas, edits: usize) {
+		while self.memory(atlas) > self.policy.memory_budget {
+			let held_elsewhere = |index: usize| -> HashSet<*const Chart> {
+				self
+					.checkpoints
+					.iter()
+					.enumerate()
+					.filter(|&(other, _)| other != index)
+					.flat_map(|(_, checkpoint)| checkpoint.charts.values().map(Arc::as_ptr))
+					.chain(atlas.charts().map(|chart| Arc::as_ptr(&chart)))
+					.collect()
+			};
+			let dropped = (1..self.checkpoints.len())
+				.filter(|&index| !self.checkpoints[index].pinned)
+				.filter(|&index| {
+					let elsewhere = held_elsewhere(index);
+					self.checkpoints[index]
+						.charts
+						.values()
+						.any(|chart| !elsewhere.contains(&Arc::as_ptr(chart)))
+				})
+				.min_by_key(|&index| {
+					let next = self.checkpoints.get(index + 1).map_or(edits, |c| c.edits);
+					next - self.checkpoints[index - 1].edits
+				});
+			let Some(dropped) = dropped else {
+				return;
+			};
+			self.checkpoints.remove(dropped);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{
+		BrushMode, CanvasFormat, Symmetry, VectorSegment, VectorStroke, DEFAULT_BRUSH_SPACING,
+	};
+	use crate::render::Resources;
+	use crate::test;
+	use glam::*;
+
+	/// A straight stroke along `y` from canvas `x = 50` to `x = 150`, in chart (0, 0).
+	fn stroke(y: f32) -> VectorStroke {
+		VectorStroke {
+			color: vec3(0.6, 0.1, -0.05),
+			size: 4.0,
+			opacity: 1.0,
+			rate: 25.0,
+			mode: BrushMode::Paint,
+			spacing: DEFAULT_BRUSH_SPACING,
+			scatter: 0.0,
+			grain: 0.0,
+			pickup: 0.0,
+			symmetry: Symmetry::default(),
+			seed: 7,
+			segments: vec![VectorSegment {
+				points: [50.0, 80.0, 120.0, 150.0].map(|x| vec3(x, y, 1.0)),
+				tilt: Vec2::ZERO,
+			}],
+		}
+	}
+
+	fn stroke_y(index: usize) -> f32 {
+		20.0 + 20.0 * index as f32
+	}
+
+	fn painted(atlas: &Atlas, y: f32) -> anyhow::Result<bool> {
+		Ok(pollster::block_on(atlas.sample(vec2(100.5, y + 0.5)))?.w > 0.0)
+	}
+
+	#[test]
+	fn restores_by_replaying_from_a_checkpoint() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Resources::new(device);
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut airbrush = Airbrush::new(device, queue, &resources, format);
+		// Every stroke draws over the same chart, so each checkpoint holds a copy of its own.
+		let mut checkpoints = Checkpoints::new(CheckpointPolicy {
+			interval: 2,
+			memory_budget: 2 * atlas.chart_bytes(),
+		});
+		let mut drawing = VectorDrawing::default();
+		for index in 0..9 {
+			let stroke = stroke(stroke_y(index));
+			stroke.replay(queue, &mut atlas, &mut airbrush, 1.0)?;
+			airbrush.stop();
+			drawing.record(VectorEdit::Stroke(stroke));
+			checkpoints.record(&atlas, &drawing);
+			assert!(checkpoints.memory(&atlas) <= 2 * atlas.chart_bytes());
+		}
+		// Taken after edits 2, 4, 6, and 8, and thinned to the two most spread out.
+		let taken = |checkpoints: &Checkpoints| {
+			checkpoints
+				.checkpoints
+				.iter()
+				.map(|checkpoint| checkpoint.edits)
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(taken(&checkpoints), [0, 4, 8]);
+		assert_eq!(checkpoints.replay_len(9), 1);
+		assert_eq!(checkpoints.replay_len(7), 3);
+
+		for _ in 0..3 {
+			drawing.undo();
+		}
+		assert_eq!(
+			checkpoints.restore(queue, &mut atlas, &mut airbrush, &drawing)?,
+			2
+		);
+		for index in 0..9 {
+			assert_eq!(
+				painted(&atlas, stroke_y(index))?,
+				index < 6,
+				"stroke {index}"
+			);
+		}
+
+		// Redoing restores from the same checkpoint.
+		drawing.redo();
+		assert_eq!(
+			checkpoints.restore(queue, &mut atlas, &mut airbrush, &drawing)?,
+			3
+		);
+		assert!(painted(&atlas, stroke_y(6))?);
+
+		// A new edit forgets the checkpoint after the one undone.
+		drawing.record(VectorEdit::Raster);
+		checkpoints.record(&atlas, &drawing);
+		assert_eq!(taken(&checkpoints), [0, 4, 8]);
+		assert!(checkpoints.checkpoints[2].pinned);
+		Ok(())
+	}
+
+	#[test]
+	fn edits_which_cant_be_replayed_are_checkpointed() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Resources::new(device);
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut airbrush = Airbrush::new(device, queue, &resources, format);
+		let mut checkpoints = Checkpoints::new(CheckpointPolicy {
+			interval: 10,
+			memory_budget: 0,
+		});
+		let mut drawing = VectorDrawing::default();
+
+		// An imported image, standing in for which a chart is filled.
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&[0, 0, 0, 255]);
+		drawing.record(VectorEdit::Raster);
+		checkpoints.record(&atlas, &drawing);
+		let stroke = stroke(stroke_y(0));
+		stroke.replay(queue, &mut atlas, &mut airbrush, 1.0)?;
+		airbrush.stop();
+		drawing.record(VectorEdit::Stroke(stroke));
+		checkpoints.record(&atlas, &drawing);
+
+		// The image's checkpoint is kept despite the budget.
+		assert_eq!(checkpoints.replay_len(2), 1);
+		drawing.undo();
+		assert_eq!(
+			checkpoints.restore(queue, &mut atlas, &mut airbrush, &drawing)?,
+			0
+		);
+		let sample = |point| pollster::block_on(atlas.sample(point));
+		assert_eq!(
+			sample(vec2(100.5, stroke_y(0) + 0.5))?,
+			sample(vec2(200.5, 200.5))?
+		);
+
+		// Without a checkpoint after it, the image can't be restored.
+		let unrecorded = Checkpoints::default();
+		assert_eq!(
+			unrecorded
+				.restore(queue, &mut atlas, &mut airbrush, &drawing)
+				.unwrap_err(),
+			RestoreError::NotReplayable(0)
+		);
+		Ok(())
+	}
+}
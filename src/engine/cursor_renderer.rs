@@ -0,0 +1,184 @@
+use crate::render::{error_scope, BindingBuffer, CapturedCommand, FrameCapturer, Resources};
+use crate::shaders::{self, cursor::Cursor};
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::sync::Arc;
+
+/// Draws an outline of the brush at the cursor, over whatever has already been drawn.
+pub struct CursorRenderer {
+	resources: Arc<Resources>,
+	cursor_buffer: BindingBuffer<Cursor>,
+	bind_group: shaders::cursor::BindGroup0,
+}
+
+impl CursorRenderer {
+	pub fn new(device: &wgpu::Device, resources: Arc<Resources>) -> Self {
+		let cursor_buffer = BindingBuffer::new_sized()
+			.label("CursorRenderer::cursor_buffer")
+			.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+			.create(device);
+		let bind_group = error_scope(device, "CursorRenderer::bind_group", || {
+			resources
+				.cursor
+				.pipeline_layout()
+				.get()
+				.bind_group_layouts()
+				.0
+				.bind_group()
+				.cursor(cursor_buffer.as_entire_buffer_binding())
+				.create()
+		});
+		Self {
+			resources,
+			cursor_buffer,
+			bind_group,
+		}
+	}
+
+	/// Creates a pipeline drawing to `target_format`. Passes with a depth/stencil attachment need a
+	/// matching `depth_stencil`.
+	pub fn create_pipeline(
+		&self,
+		target_format: wgpu::TextureFormat,
+		sample_count: u32,
+		depth_stencil: Option<wgpu::DepthStencilState>,
+	) -> wgpu::RenderPipeline {
+		self
+			.resources
+			.cursor
+			.pipeline_layout()
+			.get()
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(shaders::cursor::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: target_format,
+					blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.multisample(wgpu::MultisampleState {
+				count: sample_count,
+				..Default::default()
+			})
+			.maybe_depth_stencil(depth_stencil)
+			.maybe_cache(self.resources.pipeline_cache())
+			.get()
+	}
+
+	/// Computes the shader cursor for a brush of `radius`, in canvas units, at `position` on a
+	/// screen of `screen_size`, both in pixels. The outline scales with the view's zoom.
+	pub fn cursor(
+		view_transform: &ViewTransform,
+		screen_size: DVec2,
+		position: DVec2,
+		radius: f64,
+		opacity: f32,
+	) -> Cursor {
+		Cursor {
+			center: position.as_vec2(),
+			screen_size: screen_size.as_vec2(),
+			radius: (radius * view_transform.zoom) as f32,
+			opacity,
+		}
+	}
+
+	/// Writes the cursor. This must happen before submitting any passes from [`Self::draw`].
+	pub fn write_cursor(&self, queue: &wgpu::Queue, cursor: &Cursor) {
+		self.cursor_buffer.write(queue, cursor);
+	}
+
+	/// Draws the cursor last written, recording what it issues to `capture`.
+	pub fn draw(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		capture: &FrameCapturer,
+	) {
+		render_pass.set_pipeline(pipeline);
+		capture.record(|| CapturedCommand::SetPipeline {
+			pipeline: "cursor".to_string(),
+		});
+		self.bind_group.set(render_pass);
+		capture.record(|| CapturedCommand::SetBindGroup {
+			index: 0,
+			bind_group: "cursor".to_string(),
+		});
+		render_pass.draw(0..4, 0..1);
+		capture.record(|| CapturedCommand::Draw {
+			vertices: 0..4,
+			instances: 0..1,
+			layers: Vec::new(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{render, test};
+
+	#[test]
+	fn outlines_brush() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let renderer = CursorRenderer::new(device, Arc::new(Resources::new(device)));
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format, 1, None);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+			.create(device);
+
+		// A brush of radius 5 at 2x zoom is outlined 10 pixels from the cursor.
+		let mut view_transform = ViewTransform::default();
+		view_transform.zoom_about(DVec2::ZERO, 2.0);
+		let cursor = CursorRenderer::cursor(
+			&view_transform,
+			dvec2(64.0, 48.0),
+			dvec2(32.0, 24.0),
+			5.0,
+			1.0,
+		);
+		renderer.write_cursor(context.queue(), &cursor);
+
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &target.create_view(&Default::default()),
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			renderer.draw(&mut render_pass, &pipeline, context.frame_capture());
+		}
+		context.queue().submit([encoder.finish()]);
+
+		let data = pollster::block_on(context.get_texture_layer_data(&target, 0))?;
+		let texel = |x: usize, y: usize| {
+			let offset = 4 * (y * 64 + x);
+			&data[offset..offset + 4]
+		};
+		// Light just inside the edge, dark just outside it, and clear elsewhere.
+		assert_eq!(texel(32, 24), [0, 0, 0, 0]);
+		let light = texel(41, 24);
+		assert!(light.iter().all(|&c| c > 200), "{light:?}");
+		let dark = texel(42, 24);
+		assert!(
+			dark[..3].iter().all(|&c| c < 50) && dark[3] > 200,
+			"{dark:?}"
+		);
+		assert_eq!(texel(50, 24), [0, 0, 0, 0]);
+		Ok(())
+	}
+}
@@ -2,18 +2,13 @@ use std::sync::OnceLock;
 
 // Long term, we probably don't want to embed the large assets.
 
+use super::BrushShape;
 use crate::util::*;
 use zune_core::colorspace::ColorSpace;
 use zune_image::image::*;
 
 static RAW_00507_PNG: &[u8] = include_bytes!("../../public/assets/shapes/00507.png");
 
-pub struct Shape {
-	pub width: u32,
-	pub height: u32,
-	pub values: Vec<f32>,
-}
-
 pub fn get_image_00507() -> &'static Image {
 	static IMAGE: OnceLock<Image> = OnceLock::new();
 	IMAGE.get_or_init(|| {
@@ -24,12 +19,11 @@ pub fn get_image_00507() -> &'static Image {
 	})
 }
 
-// TODO: Remove this.
-pub fn get_shape_00507() -> Shape {
+pub fn get_shape_00507() -> BrushShape {
 	let mut image = get_image_00507().clone();
 	let (width, height) = image.dimensions();
 
-	Shape {
+	BrushShape {
 		width: width as u32,
 		height: height as u32,
 		values: image.convert_to_f32_subpixels(),
@@ -0,0 +1,142 @@
+//! Paints through the same engine API the canvas uses, headlessly, and checks the result across
+//! charts.
+
+use crate::coordinates::CanvasPt;
+use crate::engine::*;
+use crate::render::Resources;
+use crate::test;
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// A stroke that crosses from chart `(0, 0)` into `(1, 0)` and then `(1, 1)`, passing close to, but
+/// not over, the corner of `(0, 1)`.
+fn stroke() -> Vec<InputPoint> {
+	let point = InputPoint {
+		position: DVec2::ZERO,
+		pressure: 1.0,
+		color: vec3(0.6, 0.1, -0.05),
+		size: 8.0,
+		opacity: 1.0,
+		rate: 25.0,
+		tilt: Vec2::ZERO,
+	};
+	[
+		(dvec2(200.0, 200.0), 0.2),
+		(dvec2(240.0, 230.0), 0.6),
+		(dvec2(280.0, 250.0), 1.0),
+		(dvec2(300.0, 280.0), 0.4),
+	]
+	.into_iter()
+	.map(|(position, pressure)| InputPoint {
+		position,
+		pressure,
+		..point
+	})
+	.collect()
+}
+
+/// Paints [`stroke`], moved from chart `(0, 0)` to `origin`, into a new atlas, with a fixed seed.
+fn paint(context: &test::WgpuTestContext, origin: ChartKey) -> anyhow::Result<(Resources, Atlas)> {
+	let (device, queue) = (context.device(), context.queue());
+	let resources = Resources::new(device);
+	let format = CanvasFormat::Rgba8Unorm;
+	let mut atlas = Atlas::new((**context).clone(), format);
+	let mut airbrush = Airbrush::new(device, queue, &resources, format);
+	airbrush.set_seed(Some(0));
+	let points = stroke().into_iter().map(|point| InputPoint {
+		position: CanvasPt::chart_origin(origin).0 + point.position,
+		..point
+	});
+	airbrush.draw_stroke(queue, &mut atlas, points)?;
+	Ok((resources, atlas))
+}
+
+#[test]
+fn paint_stroke_across_charts() -> anyhow::Result<()> {
+	let context = test::WgpuTestContext::new()?;
+	let (_, mut atlas) = paint(&context, ChartKey(0, 0))?;
+
+	// Only charts the brush touched are allocated.
+	let expected = HashSet::from([ChartKey(0, 0), ChartKey(1, 0), ChartKey(1, 1)]);
+	assert_eq!(atlas.take_dirty(), expected);
+	assert_eq!(atlas.charts().count(), expected.len());
+	assert!(atlas.get_chart(&ChartKey(0, 1)).is_none());
+
+	// The stroke is continuous where it crosses from one chart to the next.
+	let sample = |point| pollster::block_on(atlas.sample(point));
+	for (before, after) in [
+		(vec2(255.5, 238.5), vec2(256.5, 238.5)),
+		(vec2(284.5, 255.5), vec2(284.5, 256.5)),
+	] {
+		let (before, after) = (sample(before)?, sample(after)?);
+		assert!(before.w > 0.0, "{before}");
+		assert!(before.abs_diff_eq(after, 0.1), "{before} != {after}");
+	}
+	assert_eq!(sample(vec2(100.0, 100.0))?, Vec4::ZERO);
+	Ok(())
+}
+
+#[test]
+fn paint_far_from_origin() -> anyhow::Result<()> {
+	let context = test::WgpuTestContext::new()?;
+	let (_, mut near) = paint(&context, ChartKey(0, 0))?;
+	// Billions of units from the origin, where neighboring `f32` positions are 512 units apart.
+	let origin = ChartKey(1 << 24, -(1 << 24));
+	let (_, mut far) = paint(&context, origin)?;
+
+	// The stroke covers the same charts, with the same paint, as it does near the origin.
+	let moved = |key: ChartKey| ChartKey(key.0 + origin.0, key.1 + origin.1);
+	let keys = near.take_dirty();
+	assert_eq!(far.take_dirty(), keys.iter().copied().map(moved).collect());
+	let texels = |atlas: &Atlas, key| {
+		let tile = atlas.get_chart(&key).unwrap().tile();
+		pollster::block_on(tile.get_texture_data())
+	};
+	for key in keys {
+		assert_eq!(texels(&near, key)?, texels(&far, moved(key))?, "{key:?}");
+	}
+	Ok(())
+}
+
+#[test]
+#[ignore = "test/output/engine/end_to_end/stroke.png has yet to be rendered and committed"]
+fn stitch_stroke_across_charts() -> anyhow::Result<()> {
+	let context = test::WgpuTestContext::new()?;
+	let (device, queue) = (context.device(), context.queue());
+	let (resources, atlas) = paint(&context, ChartKey(0, 0))?;
+	let format = CanvasFormat::Rgba8Unorm;
+
+	// Stitch the charts together as the canvas would, showing canvas `[128, 384]` in each axis.
+	let renderer = CanvasRenderer::new(device, Arc::new(resources), format);
+	let texture_format = wgpu::TextureFormat::Rgba8Unorm;
+	let pipeline = renderer.create_pipeline(texture_format, 1, None);
+	let options = test::GoldenOptions {
+		texture_format,
+		..Default::default()
+	};
+	let screen_size = dvec2(options.width as f64, options.height as f64);
+	let mut view_transform = ViewTransform::default();
+	view_transform.pan(dvec2(-128.0, -128.0));
+	view_transform.zoom_about(DVec2::ZERO, 0.5);
+	renderer.write_view(
+		queue,
+		&CanvasRenderer::canvas_view(&view_transform, screen_size),
+	);
+
+	context.render_golden_commands("engine/end_to_end/stroke", options, |view, encoder| {
+		let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+				view: &view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+					store: wgpu::StoreOp::Store,
+				},
+			})],
+			..Default::default()
+		});
+		renderer.draw(&mut render_pass, &pipeline, &atlas);
+	})
+}
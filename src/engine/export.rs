@@ -0,0 +1,864 @@
+use super::{Atlas, CanvasRenderer, CHART_SIZE};
+use crate::render::*;
+use crate::shaders::{copy_transform, dither, resample};
+use crate::util::{self, ImageExt};
+use crate::view_transform::ViewTransform;
+use crate::WgpuContext;
+use bon::builder;
+use glam::*;
+use std::future::Future;
+use std::sync::Arc;
+use thiserror::Error;
+use zune_image::codecs::ImageFormat;
+use zune_image::image::Image;
+
+pub const MIN_EXPORT_SCALE: f64 = 0.25;
+pub const MAX_EXPORT_SCALE: f64 = 4.0;
+
+/// Dithered exports have this many bits per channel unless chosen otherwise. Exports are 8-bit
+/// anyway, so this only dithers away the banding of rounding the canvas to them.
+pub const DEFAULT_DITHER_BITS: u32 = 8;
+pub const MIN_DITHER_BITS: u32 = 1;
+pub const MAX_DITHER_BITS: u32 = 8;
+
+/// The resolution exports are printed at unless the document specifies otherwise.
+pub const DEFAULT_DPI: f64 = 300.0;
+pub const MIN_DPI: f64 = 1.0;
+pub const MAX_DPI: f64 = 4800.0;
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// The bytes every PNG starts with, before its chunks.
+pub(super) const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The largest dimension of document thumbnails.
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Thumbnails are rendered at this many times their final resolution before being filtered down.
+const THUMBNAIL_SUPERSAMPLING: f64 = 4.0;
+
+/// The format of exported textures. Exports are encoded for display, so this is 8-bit sRGB.
+pub const EXPORT_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// The format the canvas is rendered to before resampling. It holds premultiplied linear colors,
+/// which are only encoded once copied to [`EXPORT_TEXTURE_FORMAT`].
+const INTERMEDIATE_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	Default,
+	serde::Serialize,
+	serde::Deserialize,
+	derive_more::Display,
+)]
+pub enum Resampling {
+	/// Fast, but aliases when downscaling by more than half.
+	#[default]
+	Bilinear,
+	/// Sharper and alias-free, at the cost of a compute pass.
+	Lanczos,
+}
+
+impl Resampling {
+	pub const ALL: [Self; 2] = [Self::Bilinear, Self::Lanczos];
+
+	/// Whether `device` can resample this way. Lanczos resampling writes a storage texture, which
+	/// WebGL2 lacks. Where it is unsupported, exports fall back to bilinear resampling.
+	pub fn is_supported(self, device: &wgpu::Device) -> bool {
+		match self {
+			Self::Bilinear => true,
+			Self::Lanczos => device.limits().max_storage_textures_per_shader_stage > 0,
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+	#[error("export scale {0} is outside of [{MIN_EXPORT_SCALE}, {MAX_EXPORT_SCALE}]")]
+	ScaleOutOfRange(f64),
+
+	#[error("dither bit depth {0} is outside of [{MIN_DITHER_BITS}, {MAX_DITHER_BITS}]")]
+	DitherBitsOutOfRange(u32),
+
+	#[error("nothing to export")]
+	Empty,
+
+	#[error("{width}x{height} exceeds the maximum texture size of {max}")]
+	TooLarge { width: u32, height: u32, max: u32 },
+}
+
+/// Whether `device` can dither exports. Dithering writes a storage texture, which WebGL2 lacks.
+/// Where it is unsupported, exports are rounded instead.
+pub fn is_dithering_supported(device: &wgpu::Device) -> bool {
+	device.limits().max_storage_textures_per_shader_stage > 0
+}
+
+/// The size of an export of an image of `size` pixels at `scale`.
+pub fn export_size(size: UVec2, scale: f64) -> UVec2 {
+	(size.as_dvec2() * scale).round().as_uvec2().max(UVec2::ONE)
+}
+
+/// The physical size in inches of an image of `size` pixels printed at `dpi`.
+pub fn print_size(size: UVec2, dpi: f64) -> DVec2 {
+	size.as_dvec2() / dpi
+}
+
+/// The origin and size in pixels of the region of `atlas` exported: its bounds if it has any, and
+/// otherwise the region covered by charts, or `None` if there are no charts.
+pub fn export_region(atlas: &Atlas) -> Option<(IVec2, UVec2)> {
+	if let Some(bounds) = atlas.bounds() {
+		return Some((bounds.origin, bounds.size));
+	}
+	let (min_key, max_key) = atlas.chart_bounds()?;
+	let (min, max) = (ivec2(min_key.0, min_key.1), ivec2(max_key.0, max_key.1));
+	Some((
+		min * CHART_SIZE as i32,
+		(max - min + 1).as_uvec2() * CHART_SIZE,
+	))
+}
+
+/// Renders the region of `atlas` given by [`export_region`] at `scale` times its resolution.
+///
+/// With `dither_bits`, the result is quantized to that many bits per channel with blue noise
+/// dithering rather than rounded, so that smooth gradients don't band.
+///
+/// The result has format [`EXPORT_TEXTURE_FORMAT`].
+#[builder(finish_fn = generate)]
+pub fn export(
+	#[builder(start_fn)] atlas: &Atlas,
+	#[builder(finish_fn)] device: &wgpu::Device,
+	#[builder(finish_fn)] queue: &wgpu::Queue,
+	#[builder(finish_fn)] resources: &Arc<Resources>,
+	#[builder(default = 1.0)] scale: f64,
+	#[builder(default)] resampling: Resampling,
+	dither_bits: Option<u32>,
+	#[builder(default = wgpu::TextureUsages::COPY_SRC)] usage: wgpu::TextureUsages,
+) -> Result<wgpu::Texture, ExportError> {
+	use ExportError::*;
+	if !(MIN_EXPORT_SCALE..=MAX_EXPORT_SCALE).contains(&scale) {
+		Err(ScaleOutOfRange(scale))?;
+	}
+	if let Some(bits) = dither_bits {
+		if !(MIN_DITHER_BITS..=MAX_DITHER_BITS).contains(&bits) {
+			Err(DitherBitsOutOfRange(bits))?;
+		}
+	}
+	let (origin, size) = export_region(atlas).ok_or(Empty)?;
+	let export_size = export_size(size, scale);
+
+	let max = device.limits().max_texture_dimension_2d;
+	let largest = size.max(export_size);
+	if largest.max_element() > max {
+		Err(TooLarge {
+			width: largest.x,
+			height: largest.y,
+			max,
+		})?;
+	}
+
+	let source = render_atlas(atlas, device, queue, resources, origin, 1.0, size);
+	let source = match resampling {
+		Resampling::Bilinear => source,
+		Resampling::Lanczos if export_size == size || !resampling.is_supported(device) => source,
+		Resampling::Lanczos => lanczos_resample(&source, device, queue, resources, export_size),
+	};
+
+	let destination = texture()
+		.label("export::destination")
+		.width(export_size.x)
+		.height(export_size.y)
+		.format(EXPORT_TEXTURE_FORMAT)
+		.usage(usage | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST)
+		.create(device);
+	match dither_bits {
+		Some(bits) if is_dithering_supported(device) => {
+			dither_to(&source, &destination, bits, device, queue, resources)
+		}
+		_ => copy_to(&source, &destination, device, queue, resources),
+	}
+	Ok(destination)
+}
+
+/// Renders `atlas` at `zoom` times its native resolution into a texture of `size` pixels, with the
+/// canvas position `origin` at the top-left.
+fn render_atlas(
+	atlas: &Atlas,
+	device: &wgpu::Device,
+	queue: &wgpu::Queue,
+	resources: &Arc<Resources>,
+	origin: IVec2,
+	zoom: f64,
+	size: UVec2,
+) -> wgpu::Texture {
+	let destination = texture()
+		.label("export::render_atlas")
+		.width(size.x)
+		.height(size.y)
+		.format(INTERMEDIATE_TEXTURE_FORMAT)
+		.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+		.create(device);
+	let renderer = CanvasRenderer::new(device, resources.clone(), atlas.format());
+	let (destination_view, pipeline) = error_scope(device, "export::render_atlas", || {
+		(
+			destination.create_view(&Default::default()),
+			renderer.create_linear_pipeline(INTERMEDIATE_TEXTURE_FORMAT),
+		)
+	});
+	let view_transform = ViewTransform {
+		translation: -origin.as_dvec2() * zoom,
+		zoom,
+		..ViewTransform::IDENTITY
+	};
+	renderer.write_view(
+		queue,
+		&CanvasRenderer::canvas_view(&view_transform, size.as_dvec2()),
+	);
+
+	let mut command_encoder = device.create_command_encoder(&Default::default());
+	{
+		let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("export::render_atlas"),
+			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+				view: &destination_view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+					store: wgpu::StoreOp::Store,
+				},
+			})],
+			..Default::default()
+		});
+		renderer.draw(&mut render_pass, &pipeline, atlas);
+	}
+	queue.submit([command_encoder.finish()]);
+	destination
+}
+
+/// Renders a preview of `atlas` no larger than `max_size` pixels in either dimension, or `None` if
+/// the atlas is empty.
+///
+/// The result has format [`EXPORT_TEXTURE_FORMAT`].
+#[builder(finish_fn = generate)]
+pub fn thumbnail(
+	#[builder(start_fn)] atlas: &Atlas,
+	#[builder(finish_fn)] device: &wgpu::Device,
+	#[builder(finish_fn)] queue: &wgpu::Queue,
+	#[builder(finish_fn)] resources: &Arc<Resources>,
+	#[builder(default = THUMBNAIL_SIZE)] max_size: u32,
+	#[builder(default = wgpu::TextureUsages::COPY_SRC)] usage: wgpu::TextureUsages,
+) -> Option<wgpu::Texture> {
+	let (origin, size) = export_region(atlas)?;
+	let scale = (max_size as f64 / size.max_element() as f64).min(1.0);
+	let thumbnail_size = export_size(size, scale);
+
+	// Rendering directly at the thumbnail's resolution would alias, and rendering at full resolution
+	// could be arbitrarily expensive.
+	let zoom = (scale * THUMBNAIL_SUPERSAMPLING).min(1.0);
+	let source_size = export_size(size, zoom);
+	let source = render_atlas(atlas, device, queue, resources, origin, zoom, source_size);
+	let source = if source_size == thumbnail_size || !Resampling::Lanczos.is_supported(device) {
+		source
+	} else {
+		lanczos_resample(&source, device, queue, resources, thumbnail_size)
+	};
+
+	let destination = texture()
+		.label("export::thumbnail")
+		.width(thumbnail_size.x)
+		.height(thumbnail_size.y)
+		.format(EXPORT_TEXTURE_FORMAT)
+		.usage(usage | wgpu::TextureUsages::RENDER_ATTACHMENT)
+		.create(device);
+	copy_to(&source, &destination, device, queue, resources);
+	Some(destination)
+}
+
+fn lanczos_resample(
+	source: &wgpu::Texture,
+	device: &wgpu::Device,
+	queue: &wgpu::Queue,
+	resources: &Resources,
+	size: UVec2,
+) -> wgpu::Texture {
+	use resample::*;
+
+	let destination = texture()
+		.label("export::lanczos_resample")
+		.width(size.x)
+		.height(size.y)
+		// This must match the format in the the shader.
+		.format(wgpu::TextureFormat::Rgba16Float)
+		.usage(wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING)
+		.create(device);
+
+	let (pipeline, bind_group) = error_scope(device, "export::lanczos_resample", || {
+		let pipeline_layout = resources
+			.resample
+			.pipeline_layout()
+			.source_filterable(false)
+			.get();
+		let pipeline = pipeline_layout
+			.lanczos_resample_pipeline()
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let source_view = source.create_view(&Default::default());
+		let destination_view = destination.create_view(&Default::default());
+		let bind_group = pipeline_layout
+			.bind_group_layouts()
+			.0
+			.bind_group()
+			.source(&source_view)
+			.destination(&destination_view)
+			.create();
+		(pipeline, bind_group)
+	});
+
+	let mut command_encoder = device.create_command_encoder(&Default::default());
+	{
+		let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+			label: Some("export::lanczos_resample"),
+			..Default::default()
+		});
+		pass.set_pipeline(&pipeline);
+		bind_group.set_compute(&mut pass);
+		pass.dispatch_workgroups(
+			size.x.div_ceil(WORKGROUP_WIDTH),
+			size.y.div_ceil(WORKGROUP_HEIGHT),
+			1,
+		);
+	}
+	queue.submit([command_encoder.finish()]);
+	destination
+}
+
+/// Copies the premultiplied `source` to `destination` with straight alpha, resampling bilinearly if
+/// their sizes differ.
+fn copy_to(
+	source: &wgpu::Texture,
+	destination: &wgpu::Texture,
+	device: &wgpu::Device,
+	queue: &wgpu::Queue,
+	resources: &Resources,
+) {
+	let (pipeline, destination_view, bind_group) = error_scope(device, "export::copy_to", || {
+		let pipeline_layout = resources
+			.copy_transform
+			.pipeline_layout()
+			.source_texture_filterable(true)
+			.source_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+			.get();
+		let pipeline = pipeline_layout
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(copy_transform::FragmentEntry::fs_unpremultiply {
+				targets: [Some(wgpu::ColorTargetState {
+					format: destination.format(),
+					blend: Some(wgpu::BlendState::REPLACE),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+		let transform_buffer = BindingBuffer::init_sized(&copy_transform::Transform {
+			linear: Mat2::IDENTITY,
+			translation: Vec2::ZERO,
+		})
+		.create(device);
+		let source_view = source.create_view(&Default::default());
+		let destination_view = destination.create_view(&Default::default());
+		let bind_group = pipeline_layout
+			.bind_group_layouts()
+			.0
+			.bind_group()
+			.transform(transform_buffer.as_entire_buffer_binding())
+			.source_texture(&source_view)
+			.source_sampler(&sampler)
+			.create();
+		(pipeline, destination_view, bind_group)
+	});
+
+	let mut command_encoder = device.create_command_encoder(&Default::default());
+	{
+		let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("export::copy_to"),
+			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+				view: &destination_view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+					store: wgpu::StoreOp::Store,
+				},
+			})],
+			..Default::default()
+		});
+		render_pass.set_pipeline(&pipeline);
+		bind_group.set(&mut render_pass);
+		render_pass.draw(0..4, 0..1);
+	}
+	queue.submit([command_encoder.finish()]);
+}
+
+/// Like [`copy_to`], but quantizes to `bits` per channel with blue noise dithering. `destination`
+/// must be 8-bit sRGB.
+fn dither_to(
+	source: &wgpu::Texture,
+	destination: &wgpu::Texture,
+	bits: u32,
+	device: &wgpu::Device,
+	queue: &wgpu::Queue,
+	resources: &Resources,
+) {
+	use dither::*;
+
+	let size = uvec2(destination.width(), destination.height());
+	// Storage textures can't be sRGB, so the shader encodes colors itself into a texture which can
+	// be copied to the destination as is.
+	let dithered = texture()
+		.label("export::dither_to")
+		.width(size.x)
+		.height(size.y)
+		// This must match the format in the shader.
+		.format(wgpu::TextureFormat::Rgba8Unorm)
+		.usage(wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC)
+		.create(device);
+	let blue_noise = texture()
+		.label("export::blue_noise")
+		.width(util::BLUE_NOISE_SIZE)
+		.height(util::BLUE_NOISE_SIZE)
+		.format(wgpu::TextureFormat::R32Float)
+		.usage(wgpu::TextureUsages::TEXTURE_BINDING)
+		.with_data((queue, bytemuck::cast_slice(util::blue_noise())))
+		.create(device);
+
+	let (pipeline, bind_group) = error_scope(device, "export::dither_to", || {
+		let pipeline_layout = resources
+			.dither
+			.pipeline_layout()
+			.source_filterable(true)
+			.source_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+			.blue_noise_filterable(false)
+			.get();
+		let pipeline = pipeline_layout
+			.dither_pipeline()
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			address_mode_u: wgpu::AddressMode::ClampToEdge,
+			address_mode_v: wgpu::AddressMode::ClampToEdge,
+			address_mode_w: wgpu::AddressMode::ClampToEdge,
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			mipmap_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
+		let quantization_buffer = BindingBuffer::init_sized(&Quantization {
+			levels: ((1 << bits) - 1) as f32,
+		})
+		.create(device);
+		let source_view = source.create_view(&Default::default());
+		let blue_noise_view = blue_noise.create_view(&Default::default());
+		let dithered_view = dithered.create_view(&Default::default());
+		let bind_group = pipeline_layout
+			.bind_group_layouts()
+			.0
+			.bind_group()
+			.quantization(quantization_buffer.as_entire_buffer_binding())
+			.source(&source_view)
+			.source_sampler(&sampler)
+			.blue_noise(&blue_noise_view)
+			.destination(&dithered_view)
+			.create();
+		(pipeline, bind_group)
+	});
+
+	let mut command_encoder = device.create_command_encoder(&Default::default());
+	{
+		let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+			label: Some("export::dither_to"),
+			..Default::default()
+		});
+		pass.set_pipeline(&pipeline);
+		bind_group.set_compute(&mut pass);
+		pass.dispatch_workgroups(
+			size.x.div_ceil(WORKGROUP_WIDTH),
+			size.y.div_ceil(WORKGROUP_HEIGHT),
+			1,
+		);
+	}
+	// The formats differ only in whether they're sRGB, so the encoded bytes copy over unchanged.
+	command_encoder.copy_texture_to_texture(
+		dithered.as_image_copy(),
+		destination.as_image_copy(),
+		dithered.size(),
+	);
+	queue.submit([command_encoder.finish()]);
+}
+
+/// Encodes an exported texture as a PNG, recording that it should be printed at `dpi` if given.
+pub fn encode_png(
+	context: &WgpuContext,
+	texture: &wgpu::Texture,
+	dpi: Option<f64>,
+) -> impl Future<Output = anyhow::Result<Vec<u8>>> {
+	let data = context.get_texture_layer_data(texture, 0);
+	let (width, height, format) = (texture.width(), texture.height(), texture.format());
+	async move {
+		let image = Image::from_texture_data(&data.await?, width, height, format)?;
+		let png = image.write_to_vec(ImageFormat::PNG)?;
+		Ok(match dpi {
+			Some(dpi) => set_png_dpi(&png, dpi)?,
+			None => png,
+		})
+	}
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("malformed PNG")]
+pub struct MalformedPngError;
+
+/// Replaces any physical pixel dimensions (`pHYs`) chunk in `png` with one for `dpi`.
+pub fn set_png_dpi(png: &[u8], dpi: f64) -> Result<Vec<u8>, MalformedPngError> {
+	let chunks = png.strip_prefix(PNG_SIGNATURE).ok_or(MalformedPngError)?;
+
+	let pixels_per_meter = (dpi / METERS_PER_INCH).round() as u32;
+	let mut phys = Vec::with_capacity(9);
+	phys.extend(pixels_per_meter.to_be_bytes());
+	phys.extend(pixels_per_meter.to_be_bytes());
+	// The unit is the meter.
+	phys.push(1);
+
+	let mut result = PNG_SIGNATURE.to_vec();
+	let mut rest = chunks;
+	while !rest.is_empty() {
+		let (chunk_type, chunk) = split_png_chunk(&mut rest).ok_or(MalformedPngError)?;
+		if chunk_type == *b"pHYs" {
+			continue;
+		}
+		result.extend(chunk);
+		// The chunk must precede the image data, so put it right after the header.
+		if chunk_type == *b"IHDR" {
+			write_png_chunk(&mut result, *b"pHYs", &phys);
+		}
+	}
+	Ok(result)
+}
+
+/// Splits the first chunk off of `chunks`, returning its type and all of its bytes.
+pub(super) fn split_png_chunk<'a>(chunks: &mut &'a [u8]) -> Option<([u8; 4], &'a [u8])> {
+	let length = u32::from_be_bytes(chunks.get(0..4)?.try_into().unwrap()) as usize;
+	let chunk_type = chunks.get(4..8)?.try_into().unwrap();
+	// Length, type, data, and CRC.
+	let (chunk, rest) = chunks.split_at_checked(12 + length)?;
+	*chunks = rest;
+	Some((chunk_type, chunk))
+}
+
+pub(super) fn write_png_chunk(png: &mut Vec<u8>, chunk_type: [u8; 4], data: &[u8]) {
+	png.extend((data.len() as u32).to_be_bytes());
+	let start = png.len();
+	png.extend(chunk_type);
+	png.extend(data);
+	let crc = crc32(&png[start..]);
+	png.extend(crc.to_be_bytes());
+}
+
+/// The CRC-32 used by PNG.
+fn crc32(data: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in data {
+		crc ^= byte as u32;
+		for _ in 0..8 {
+			crc = (crc >> 1) ^ (0xedb88320 & (crc & 1).wrapping_neg());
+		}
+	}
+	!crc
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{CanvasFormat, ChartKey, DocumentBounds};
+	use crate::test;
+	use std::assert_matches::assert_matches;
+
+	#[test]
+	fn export_sizes() {
+		let size = uvec2(512, 256);
+		assert_eq!(export_size(size, 1.0), size);
+		assert_eq!(export_size(size, 0.25), uvec2(128, 64));
+		assert_eq!(export_size(size, 4.0), uvec2(2048, 1024));
+		assert_eq!(export_size(uvec2(3, 1), 0.25), uvec2(1, 1));
+	}
+
+	#[test]
+	fn print_sizes() {
+		assert_eq!(print_size(uvec2(600, 300), 300.0), dvec2(2.0, 1.0));
+		assert_eq!(print_size(uvec2(72, 36), 72.0), dvec2(1.0, 0.5));
+	}
+
+	#[test]
+	fn png_crc() {
+		assert_eq!(crc32(b"IEND"), 0xae426082);
+	}
+
+	#[test]
+	fn png_dpi() {
+		let ihdr = [0u8; 13];
+		let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+		write_png_chunk(&mut png, *b"IHDR", &ihdr);
+		write_png_chunk(&mut png, *b"pHYs", &[0; 9]);
+		write_png_chunk(&mut png, *b"IEND", &[]);
+
+		let with_dpi = set_png_dpi(&png, 72.0).unwrap();
+		let mut expected = b"\x89PNG\r\n\x1a\n".to_vec();
+		write_png_chunk(&mut expected, *b"IHDR", &ihdr);
+		// 72 DPI is 2835 pixels per meter.
+		expected.extend(9u32.to_be_bytes());
+		expected.extend(b"pHYs\0\0\x0b\x13\0\0\x0b\x13\x01");
+		expected.extend(0x009a9c18u32.to_be_bytes());
+		write_png_chunk(&mut expected, *b"IEND", &[]);
+		assert_eq!(with_dpi, expected);
+
+		assert_eq!(set_png_dpi(b"GIF89a", 72.0), Err(MalformedPngError));
+		assert_eq!(
+			set_png_dpi(&png[..png.len() - 1], 72.0),
+			Err(MalformedPngError)
+		);
+	}
+
+	#[test]
+	fn export_errors() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+
+		assert_matches!(
+			export(&atlas).generate(device, queue, &resources),
+			Err(ExportError::Empty)
+		);
+		atlas.get_chart_mut(ChartKey(0, 0));
+		for scale in [0.1, 8.0] {
+			assert_matches!(
+				export(&atlas)
+					.scale(scale)
+					.generate(device, queue, &resources),
+				Err(ExportError::ScaleOutOfRange(_))
+			);
+		}
+		for bits in [0, 16] {
+			assert_matches!(
+				export(&atlas)
+					.dither_bits(bits)
+					.generate(device, queue, &resources),
+				Err(ExportError::DitherBitsOutOfRange(_))
+			);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn export_at_scale() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba16Float);
+		atlas.get_chart_mut(ChartKey(-1, 2));
+		atlas.get_chart_mut(ChartKey(1, 2));
+
+		for resampling in Resampling::ALL {
+			for scale in [0.25, 1.0, 2.0] {
+				let texture = export(&atlas)
+					.scale(scale)
+					.resampling(resampling)
+					.generate(device, queue, &resources)?;
+				assert_eq!(texture.format(), EXPORT_TEXTURE_FORMAT);
+				assert_eq!(
+					uvec2(texture.width(), texture.height()),
+					export_size(uvec2(3, 1) * CHART_SIZE, scale),
+					"{resampling:?} at {scale}"
+				);
+				// Empty charts export as transparent.
+				let data = pollster::block_on(context.get_texture_layer_data(&texture, 0))?;
+				assert!(data.chunks_exact(4).all(|pixel| pixel[3] == 0));
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn export_bounds() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		let format = CanvasFormat::Rgba16Float;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let pixel = format.encode_pixels(&[vec4(1.0, 0.0, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+		atlas.get_chart_mut(ChartKey(4, 4));
+
+		// Bounds straddling the edge of the painted chart export exactly that region, regardless of
+		// the charts outside.
+		atlas.set_bounds(Some(DocumentBounds {
+			origin: ivec2(-20, 100),
+			size: uvec2(40, 30),
+		}));
+		assert_eq!(
+			export_region(&atlas),
+			Some((ivec2(-20, 100), uvec2(40, 30)))
+		);
+		let texture = export(&atlas).generate(device, queue, &resources)?;
+		assert_eq!(uvec2(texture.width(), texture.height()), uvec2(40, 30));
+		let data = pollster::block_on(context.get_texture_layer_data(&texture, 0))?;
+		let alpha = |x: usize, y: usize| data[4 * (y * 40 + x) + 3];
+		assert_eq!(alpha(10, 15), 0);
+		assert_eq!(alpha(30, 15), 255);
+		Ok(())
+	}
+
+	#[test]
+	fn export_straight_alpha() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		let format = CanvasFormat::Rgba16Float;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		// Half transparent white, which the canvas renders premultiplied.
+		let pixel = format.encode_pixels(&[vec4(1.0, 0.0, 0.0, 0.5)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+
+		let texture = export(&atlas).generate(device, queue, &resources)?;
+		let data = pollster::block_on(context.get_texture_layer_data(&texture, 0))?;
+		for pixel in data.chunks_exact(4) {
+			assert!(pixel[..3].iter().all(|&c| c >= 254), "{pixel:?}");
+			assert!(pixel[3].abs_diff(128) <= 1, "{pixel:?}");
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn export_mid_gray() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		let format = CanvasFormat::Rgba16Float;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		// Colors between black and white show whether they're encoded exactly once.
+		let lightness = util::rgb_to_oklab(Vec3::splat(128.0 / 255.0)).x;
+		let pixel = format.encode_pixels(&[vec4(lightness, 0.0, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+
+		let is_mid_gray = |pixel: &[u8]| pixel[..3].iter().all(|&c| c.abs_diff(128) <= 1);
+		let texture = export(&atlas).generate(device, queue, &resources)?;
+		let data = pollster::block_on(context.get_texture_layer_data(&texture, 0))?;
+		for pixel in data.chunks_exact(4) {
+			assert!(is_mid_gray(pixel), "{pixel:?}");
+			assert_eq!(pixel[3], 255, "{pixel:?}");
+		}
+
+		let texture = thumbnail(&atlas)
+			.generate(device, queue, &resources)
+			.unwrap();
+		let data = pollster::block_on(context.get_texture_layer_data(&texture, 0))?;
+		for pixel in data.chunks_exact(4) {
+			assert!(is_mid_gray(pixel), "{pixel:?}");
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn export_dithered() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		if !is_dithering_supported(device) {
+			return Ok(());
+		}
+		let format = CanvasFormat::Rgba16Float;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		// The gray halfway between black and white in sRGB, which is between the levels of 2 bits.
+		let lightness = util::rgb_to_oklab(Vec3::splat(0.5)).x;
+		let pixel = format.encode_pixels(&[vec4(lightness, 0.0, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+
+		let texture = export(&atlas)
+			.dither_bits(2)
+			.generate(device, queue, &resources)?;
+		assert_eq!(texture.format(), EXPORT_TEXTURE_FORMAT);
+		let data = pollster::block_on(context.get_texture_layer_data(&texture, 0))?;
+		// Each texel rounds to one of the neighboring levels, a third and two thirds, but on average
+		// they match the source.
+		let mut counts = [0; 2];
+		for pixel in data.chunks_exact(4) {
+			assert_eq!(pixel[3], 255, "{pixel:?}");
+			assert!(pixel[..3].iter().all(|&c| c == pixel[0]), "{pixel:?}");
+			match pixel[0] {
+				85 => counts[0] += 1,
+				170 => counts[1] += 1,
+				_ => panic!("{pixel:?}"),
+			}
+		}
+		let mean = (85 * counts[0] + 170 * counts[1]) as f64 / (counts[0] + counts[1]) as f64;
+		assert!((mean - 127.5).abs() < 2.0, "{mean}");
+		Ok(())
+	}
+
+	#[test]
+	fn thumbnail_fits() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Arc::new(Resources::new(device));
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba16Float);
+		assert!(thumbnail(&atlas)
+			.generate(device, queue, &resources)
+			.is_none());
+
+		atlas.get_chart_mut(ChartKey(-1, 2));
+		atlas.get_chart_mut(ChartKey(1, 2));
+		let texture = thumbnail(&atlas)
+			.generate(device, queue, &resources)
+			.unwrap();
+		assert_eq!(texture.format(), EXPORT_TEXTURE_FORMAT);
+		assert_eq!(uvec2(texture.width(), texture.height()), uvec2(256, 85));
+
+		// Small documents aren't enlarged.
+		let texture = thumbnail(&atlas)
+			.max_size(1024)
+			.generate(device, queue, &resources)
+			.unwrap();
+		assert_eq!(uvec2(texture.width(), texture.height()), uvec2(768, 256));
+		Ok(())
+	}
+}
@@ -0,0 +1,489 @@
+use super::tile::{PoolExhausted, Tile};
+use super::{AABox, Atlas, Chart, ChartKey, History, SelectionShape, CHART_SIZE};
+use crate::coordinates::CanvasPt;
+use crate::render::{texture, Resources};
+use glam::*;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Keeps the color of each texel and multiplies its alpha by the mask's.
+const KEEP_MASKED: wgpu::BlendState = wgpu::BlendState {
+	color: wgpu::BlendComponent {
+		src_factor: wgpu::BlendFactor::Zero,
+		dst_factor: wgpu::BlendFactor::One,
+		operation: wgpu::BlendOperation::Add,
+	},
+	alpha: wgpu::BlendComponent {
+		src_factor: wgpu::BlendFactor::Zero,
+		dst_factor: wgpu::BlendFactor::SrcAlpha,
+		operation: wgpu::BlendOperation::Add,
+	},
+};
+
+/// Keeps the color of each texel and multiplies its alpha by the inverse of the mask's.
+const REMOVE_MASKED: wgpu::BlendState = wgpu::BlendState {
+	alpha: wgpu::BlendComponent {
+		src_factor: wgpu::BlendFactor::Zero,
+		dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+		operation: wgpu::BlendOperation::Add,
+	},
+	..KEEP_MASKED
+};
+
+/// Where a floating selection is placed relative to where it was lifted from. It is scaled along
+/// its own axes and rotated about its pivot, then moved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatingTransform {
+	pub translation: DVec2,
+	pub scale: DVec2,
+	/// In radians, clockwise on screen.
+	pub rotation: f64,
+}
+
+impl Default for FloatingTransform {
+	fn default() -> Self {
+		Self {
+			translation: DVec2::ZERO,
+			scale: DVec2::ONE,
+			rotation: 0.0,
+		}
+	}
+}
+
+/// What dragging a floating selection changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformHandle {
+	Move,
+	/// Scales so that the corner with this index in [`FloatingSelection::corners`] follows the
+	/// pointer.
+	Scale(usize),
+	Rotate,
+}
+
+impl FloatingTransform {
+	/// The mapping from where the selection was lifted from to where it is placed, in canvas
+	/// coordinates, for a selection pivoting about `pivot`.
+	pub fn to_affine(&self, pivot: DVec2) -> DAffine2 {
+		DAffine2::from_translation(pivot + self.translation)
+			* DAffine2::from_angle(self.rotation)
+			* DAffine2::from_scale(self.scale)
+			* DAffine2::from_translation(-pivot)
+	}
+
+	/// This transform after dragging `handle` from `from` to `to`, both in canvas coordinates, for
+	/// a selection pivoting about `pivot`.
+	pub fn dragged(self, pivot: DVec2, handle: TransformHandle, from: DVec2, to: DVec2) -> Self {
+		let center = pivot + self.translation;
+		match handle {
+			TransformHandle::Move => Self {
+				translation: self.translation + to - from,
+				..self
+			},
+			TransformHandle::Rotate => {
+				let (from, to) = (from - center, to - center);
+				if from == DVec2::ZERO || to == DVec2::ZERO {
+					return self;
+				}
+				Self {
+					rotation: self.rotation + from.angle_between(to),
+					..self
+				}
+			}
+			TransformHandle::Scale(_) => {
+				// Scaling is along the selection's own axes, so the drag is measured along them too.
+				let unrotate = DVec2::from_angle(-self.rotation);
+				let (from, to) = (unrotate.rotate(from - center), unrotate.rotate(to - center));
+				let ratio = DVec2::select(from.abs().cmpgt(DVec2::splat(1e-6)), to / from, DVec2::ONE);
+				Self {
+					scale: self.scale * ratio,
+					..self
+				}
+			}
+		}
+	}
+}
+
+/// A selected region lifted off of the canvas to be moved, scaled, or rotated before it is stamped
+/// back. Each chart the selection covers has a scratch tile holding its selected texels in place,
+/// and the canvas keeps the rest. Until it is committed or cancelled, the floating selection is
+/// drawn over the canvas through its transform.
+pub struct FloatingSelection {
+	shape: SelectionShape,
+	pivot: DVec2,
+	pieces: Vec<(ChartKey, Tile)>,
+	// The charts as they were before lifting, which cancelling puts back.
+	originals: HashMap<ChartKey, Arc<Chart>>,
+	pub transform: FloatingTransform,
+}
+
+impl FloatingSelection {
+	/// Lifts the selected part of `atlas` off of it, or returns `None` if nothing is selected or
+	/// there is nothing drawn under the selection. The scratch tiles come from the atlas' pool, so
+	/// this fails, leaving the atlas unchanged, if it has no room for them.
+	pub fn lift(resources: &Resources, atlas: &mut Atlas) -> Result<Option<Self>, PoolExhausted> {
+		let Some(shape) = atlas.selection().shape().cloned() else {
+			return Ok(None);
+		};
+		let context = atlas.tile_pool().context();
+		let (device, queue) = (context.device(), context.queue());
+
+		// Each chart's mask is uploaded here in turn, with its coverage in alpha.
+		let mask = texture()
+			.label("FloatingSelection::lift")
+			.width(CHART_SIZE)
+			.height(CHART_SIZE)
+			.format(wgpu::TextureFormat::Rgba8Unorm)
+			.usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+			.create(device);
+
+		let mut floating = Self {
+			pivot: aabox_center(&shape.outline()),
+			shape,
+			pieces: Vec::new(),
+			originals: HashMap::new(),
+			transform: FloatingTransform::default(),
+		};
+		for key in floating.shape.chart_keys() {
+			let Some(original) = atlas.get_chart(&key) else {
+				continue;
+			};
+			let tile = match atlas.tile_pool().try_allocate_tile() {
+				Ok(tile) => tile,
+				Err(error) => {
+					floating.cancel(atlas);
+					return Err(error);
+				}
+			};
+			tile.set_data(&key.tile_data());
+			let pixels = floating
+				.shape
+				.rasterize(key)
+				.into_iter()
+				.flat_map(|coverage| [u8::MAX, u8::MAX, u8::MAX, coverage])
+				.collect_vec();
+			queue.write_texture(
+				mask.as_image_copy(),
+				&pixels,
+				wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: Some(4 * CHART_SIZE),
+					rows_per_image: None,
+				},
+				mask.size(),
+			);
+
+			// The original is kept, so the atlas gets a copy of the chart to cut the selection out of.
+			let chart = atlas.get_chart_mut(key);
+			let _writes = (tile.begin_write(), chart.tile().begin_write());
+			let mut encoder =
+				context
+					.profiler()
+					.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+						label: Some("FloatingSelection::lift"),
+					});
+			tile
+				.blit_from()
+				.resources(resources)
+				.encoder(&mut *encoder)
+				.source(original.tile())
+				.call();
+			tile
+				.copy_from_texture()
+				.resources(resources)
+				.encoder(&mut *encoder)
+				.source(&mask)
+				.blend(KEEP_MASKED)
+				.call();
+			chart
+				.tile()
+				.copy_from_texture()
+				.resources(resources)
+				.encoder(&mut *encoder)
+				.source(&mask)
+				.blend(REMOVE_MASKED)
+				.call();
+			// The mask is rewritten for the next chart, so this can't be batched.
+			encoder.submit(queue);
+
+			floating.pieces.push((key, tile));
+			floating.originals.insert(key, original);
+		}
+		Ok((!floating.pieces.is_empty()).then_some(floating))
+	}
+
+	/// The selection as it was lifted.
+	pub fn shape(&self) -> &SelectionShape {
+		&self.shape
+	}
+
+	/// The point the selection scales and rotates about, the center of its bounds as lifted.
+	pub fn pivot(&self) -> DVec2 {
+		self.pivot
+	}
+
+	/// The mapping from where the selection was lifted from to where it is placed.
+	pub fn affine(&self) -> DAffine2 {
+		self.transform.to_affine(self.pivot)
+	}
+
+	/// The selection as it is placed.
+	pub fn placed_shape(&self) -> SelectionShape {
+		if self.transform == FloatingTransform::default() {
+			return self.shape.clone();
+		}
+		let affine = self.affine();
+		SelectionShape::Lasso(
+			self
+				.shape
+				.outline()
+				.into_iter()
+				.map(|p| affine.transform_point2(p.as_dvec2()).as_vec2())
+				.collect(),
+		)
+	}
+
+	/// The corners of the selection's bounds as placed, in the order of [`AABox::corners`].
+	pub fn corners(&self) -> [DVec2; 4] {
+		let affine = self.affine();
+		AABox::containing(self.shape.outline().into_iter())
+			.corners()
+			.map(|corner| affine.transform_point2(corner.as_dvec2()))
+	}
+
+	/// The scratch tiles, each holding the lifted texels of the chart at its key. Drawn through
+	/// [`Self::affine`], they show the selection where it is placed.
+	pub fn pieces(&self) -> impl Iterator<Item = (ChartKey, &Tile)> {
+		self.pieces.iter().map(|(key, tile)| (*key, tile))
+	}
+
+	/// Puts the lifted texels back where they were.
+	pub fn cancel(self, atlas: &mut Atlas) {
+		for (key, original) in self.originals {
+			atlas.replace_chart(key, Some(original));
+		}
+	}
+
+	/// Composites the lifted texels over the canvas where they are placed, allocating charts as
+	/// needed, and records the whole move as one edit in `history`. If the tile pool runs out of
+	/// room, the charts which could be drawn to still are.
+	pub fn commit(
+		self,
+		resources: &Resources,
+		atlas: &mut Atlas,
+		history: &mut History,
+	) -> Result<(), PoolExhausted> {
+		let affine = self.affine();
+		let placed = chart_range(self.corners());
+		let stamps = self
+			.pieces
+			.iter()
+			.flat_map(|(source, tile)| {
+				let to_canvas = affine * DAffine2::from_translation(CanvasPt::chart_origin(*source).0);
+				let chart = AABox::new(Vec2::ZERO, Vec2::splat(CHART_SIZE as f32)).corners();
+				let covered = chart_range(chart.map(|p| to_canvas.transform_point2(p.as_dvec2())));
+				// Only where the selection lands is drawn to.
+				let (min, max) = (covered.0.max(placed.0), covered.1.min(placed.1));
+				(min.x..=max.x)
+					.cartesian_product(min.y..=max.y)
+					.map(move |(x, y)| (ChartKey(x, y), tile, to_canvas))
+			})
+			.collect_vec();
+
+		// The edit starts from the charts as they were before lifting.
+		let lifted = self
+			.originals
+			.into_iter()
+			.map(|(key, original)| (key, atlas.replace_chart(key, Some(original))))
+			.collect_vec();
+		history.begin_edit();
+		history.record(
+			atlas,
+			lifted
+				.iter()
+				.map(|(key, _)| *key)
+				.chain(stamps.iter().map(|(key, ..)| *key)),
+		);
+		for (key, chart) in lifted {
+			atlas.replace_chart(key, chart);
+		}
+
+		let mut exhausted = None;
+		let context = atlas.tile_pool().context();
+		for (key, source, to_canvas) in stamps {
+			let chart = match atlas.try_get_chart_mut(key) {
+				Ok(chart) => chart,
+				Err(error) => {
+					exhausted = Some(error);
+					continue;
+				}
+			};
+			let to_texels = DAffine2::from_translation(-CanvasPt::chart_origin(key).0) * to_canvas;
+			let _write = chart.tile().begin_write();
+			let mut encoder =
+				context
+					.profiler()
+					.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+						label: Some("FloatingSelection::commit"),
+					});
+			chart
+				.tile()
+				.blit_from()
+				.resources(resources)
+				.encoder(&mut *encoder)
+				.source(source)
+				.transform(Affine2::from_mat2_translation(
+					to_texels.matrix2.as_mat2(),
+					to_texels.translation.as_vec2(),
+				))
+				// This must match how strokes are drawn.
+				.blend(wgpu::BlendState::ALPHA_BLENDING)
+				.call();
+			encoder.submit(context.queue());
+		}
+		history.end_edit();
+		exhausted.map_or(Ok(()), Err)
+	}
+}
+
+fn aabox_center(points: &[Vec2]) -> DVec2 {
+	let min = points.iter().copied().reduce(Vec2::min).unwrap_or_default();
+	let max = points.iter().copied().reduce(Vec2::max).unwrap_or_default();
+	0.5 * (min + max).as_dvec2()
+}
+
+/// The smallest and largest keys along each axis of the charts overlapping the bounds of `corners`.
+/// The bounds are half-open, so a shape ending on a chart's edge doesn't reach into the next.
+fn chart_range(corners: [DVec2; 4]) -> (IVec2, IVec2) {
+	let min = corners.into_iter().reduce(DVec2::min).unwrap() / CHART_SIZE as f64;
+	let max = corners.into_iter().reduce(DVec2::max).unwrap() / CHART_SIZE as f64;
+	let min = min.floor().as_ivec2();
+	(min, (max.ceil().as_ivec2() - 1).max(min))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::CanvasFormat;
+	use crate::test;
+	use std::f64::consts::FRAC_PI_2;
+
+	#[test]
+	fn dragging_handles() {
+		let pivot = dvec2(10.0, 10.0);
+		let moved = FloatingTransform::default().dragged(
+			pivot,
+			TransformHandle::Move,
+			DVec2::ZERO,
+			dvec2(5.0, -2.0),
+		);
+		assert_eq!(moved.translation, dvec2(5.0, -2.0));
+
+		// A quarter turn about the moved pivot.
+		let rotated = moved.dragged(
+			pivot,
+			TransformHandle::Rotate,
+			dvec2(25.0, 8.0),
+			dvec2(15.0, 18.0),
+		);
+		assert!((rotated.rotation - FRAC_PI_2).abs() < 1e-9);
+		let affine = rotated.to_affine(pivot);
+		assert!(affine
+			.transform_point2(dvec2(20.0, 10.0))
+			.abs_diff_eq(dvec2(15.0, 18.0), 1e-9));
+
+		// Scaling follows the selection's own axes, which are now turned.
+		let scaled = rotated.dragged(
+			pivot,
+			TransformHandle::Scale(2),
+			dvec2(15.0, 18.0),
+			dvec2(15.0, 28.0),
+		);
+		assert!(scaled.scale.abs_diff_eq(dvec2(2.0, 1.0), 1e-9));
+		assert!(scaled
+			.to_affine(pivot)
+			.transform_point2(dvec2(20.0, 10.0))
+			.abs_diff_eq(dvec2(15.0, 28.0), 1e-9));
+	}
+
+	#[test]
+	fn moves_the_selection() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut history = History::default();
+		let paint = format.encode_pixels(&[vec4(0.8, 0.1, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&paint);
+		let data = |atlas: &Atlas, key| -> anyhow::Result<Vec<Vec4>> {
+			let chart = atlas.get_chart(&key).unwrap();
+			Ok(format.decode_pixels(&pollster::block_on(chart.tile().get_texture_data())?))
+		};
+		let texel = |pixels: &[Vec4], x: u32, y: u32| pixels[(y * CHART_SIZE + x) as usize];
+
+		// Nothing is lifted without a selection.
+		assert!(FloatingSelection::lift(&resources, &mut atlas)?.is_none());
+
+		atlas.selection_mut().select(SelectionShape::rectangle(
+			vec2(16.0, 16.0),
+			vec2(48.0, 48.0),
+		));
+		let mut floating = FloatingSelection::lift(&resources, &mut atlas)?.unwrap();
+		assert_eq!(floating.pieces().count(), 1);
+		let lifted = data(&atlas, ChartKey(0, 0))?;
+		assert_eq!(texel(&lifted, 20, 20).w, 0.0);
+		assert_eq!(texel(&lifted, 10, 10).w, 1.0);
+
+		// Moved by whole texels, across into the next chart.
+		floating.transform.translation = dvec2(CHART_SIZE as f64 - 32.0, 0.0);
+		floating.commit(&resources, &mut atlas, &mut history)?;
+		let left = data(&atlas, ChartKey(0, 0))?;
+		let right = data(&atlas, ChartKey(1, 0))?;
+		assert_eq!(texel(&left, 20, 20).w, 0.0);
+		assert!(texel(&left, CHART_SIZE - 10, 20).abs_diff_eq(vec4(0.8, 0.1, 0.0, 1.0), 1e-2));
+		assert!(texel(&right, 10, 20).abs_diff_eq(vec4(0.8, 0.1, 0.0, 1.0), 1e-2));
+		assert_eq!(texel(&right, 20, 20).w, 0.0);
+		assert!(atlas.get_chart(&ChartKey(0, 1)).is_none());
+
+		// The move is a single edit, undone as one.
+		assert!(history.undo(&mut atlas));
+		let restored = data(&atlas, ChartKey(0, 0))?;
+		assert_eq!(texel(&restored, 20, 20).w, 1.0);
+		assert!(atlas.get_chart(&ChartKey(1, 0)).is_none());
+		assert!(!history.can_undo());
+		Ok(())
+	}
+
+	#[test]
+	fn cancelling_puts_the_selection_back() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let paint = format.encode_pixels(&[vec4(0.8, 0.1, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&paint);
+		let original = atlas.get_chart(&ChartKey(0, 0)).unwrap();
+
+		atlas.selection_mut().select(SelectionShape::rectangle(
+			vec2(16.0, 16.0),
+			vec2(48.0, 48.0),
+		));
+		let floating = FloatingSelection::lift(&resources, &mut atlas)?.unwrap();
+		assert!(!Arc::ptr_eq(
+			&atlas.get_chart(&ChartKey(0, 0)).unwrap(),
+			&original
+		));
+		floating.cancel(&mut atlas);
+		assert!(Arc::ptr_eq(
+			&atlas.get_chart(&ChartKey(0, 0)).unwrap(),
+			&original
+		));
+		Ok(())
+	}
+}
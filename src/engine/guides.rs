@@ -0,0 +1,189 @@
+use glam::*;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_6, PI};
+
+/// The angle between neighboring guide lines radiating from a vanishing point, in radians.
+pub const GUIDE_RAY_SPACING: f32 = PI / 36.0;
+
+/// How many parallel guide lines are drawn across the distance from the guides' origin to their
+/// vanishing points.
+const GRID_DIVISIONS: f32 = 8.0;
+
+/// Guides drawn over the canvas for drawing in perspective, or an isometric grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum GuideKind {
+	/// Lines radiating from a vanishing point at the origin, and horizontal and vertical lines.
+	#[display("1-point perspective")]
+	OnePoint,
+	/// Lines radiating from vanishing points to either side of the origin, and vertical lines.
+	#[display("2-point perspective")]
+	TwoPoint,
+	/// Lines radiating from vanishing points to either side of the origin and one below it.
+	#[display("3-point perspective")]
+	ThreePoint,
+	/// Vertical lines and lines 30° either side of horizontal.
+	#[display("Isometric")]
+	Isometric,
+}
+
+impl GuideKind {
+	pub const ALL: [Self; 4] = [
+		Self::OnePoint,
+		Self::TwoPoint,
+		Self::ThreePoint,
+		Self::Isometric,
+	];
+}
+
+/// Guides placed on the canvas, in canvas coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guides {
+	pub kind: GuideKind,
+	pub origin: Vec2,
+	/// How far the vanishing points are from the origin, in canvas units. Parallel lines are spaced
+	/// a fraction of this apart.
+	pub scale: f32,
+}
+
+impl Guides {
+	/// The points guide lines radiate from.
+	pub fn vanishing_points(&self) -> Vec<Vec2> {
+		let (origin, scale) = (self.origin, self.scale);
+		match self.kind {
+			GuideKind::OnePoint => vec![origin],
+			GuideKind::TwoPoint => vec![origin - scale * Vec2::X, origin + scale * Vec2::X],
+			GuideKind::ThreePoint => vec![
+				origin - scale * Vec2::X,
+				origin + scale * Vec2::X,
+				origin + scale * Vec2::Y,
+			],
+			GuideKind::Isometric => Vec::new(),
+		}
+	}
+
+	/// The angles of the families of evenly spaced parallel guide lines, in radians.
+	pub fn parallel_angles(&self) -> Vec<f32> {
+		match self.kind {
+			GuideKind::OnePoint => vec![0.0, FRAC_PI_2],
+			GuideKind::TwoPoint => vec![FRAC_PI_2],
+			GuideKind::ThreePoint => Vec::new(),
+			GuideKind::Isometric => vec![FRAC_PI_2, FRAC_PI_6, PI - FRAC_PI_6],
+		}
+	}
+
+	/// The distance between neighboring parallel guide lines, in canvas units.
+	pub fn parallel_spacing(&self) -> f32 {
+		self.scale / GRID_DIVISIONS
+	}
+
+	/// The directions of the guide lines through `point`. Any line through a vanishing point is a
+	/// guide line, not just those drawn.
+	pub fn directions_through(&self, point: Vec2) -> Vec<Vec2> {
+		let radiating = self
+			.vanishing_points()
+			.into_iter()
+			.filter_map(|vanishing_point| (vanishing_point - point).try_normalize());
+		let parallel = self.parallel_angles().into_iter().map(Vec2::from_angle);
+		radiating.chain(parallel).collect()
+	}
+}
+
+/// Snaps the points of a stroke onto a guide line through where it starts. Which line is chosen
+/// once the stroke leaves the start, as whichever is nearest, and the stroke follows it from then
+/// on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuideSnap {
+	start: Vec2,
+	// How far the stroke goes before a line is chosen, in canvas units.
+	commit_distance: f32,
+	direction: Option<Vec2>,
+}
+
+impl GuideSnap {
+	pub fn new(start: Vec2, commit_distance: f32) -> Self {
+		Self {
+			start,
+			commit_distance,
+			direction: None,
+		}
+	}
+
+	/// Projects `point` onto the guide line the stroke follows. Until one is chosen, the stroke
+	/// stays where it started.
+	pub fn snap(&mut self, guides: &Guides, point: Vec2) -> Vec2 {
+		let offset = point - self.start;
+		let direction = match self.direction {
+			Some(direction) => direction,
+			None if offset.length() < self.commit_distance => return self.start,
+			None => {
+				let nearest = guides
+					.directions_through(self.start)
+					.into_iter()
+					.min_by(|a, b| {
+						let distance = |d: &Vec2| offset.perp_dot(*d).abs();
+						distance(a).total_cmp(&distance(b))
+					});
+				let Some(nearest) = nearest else {
+					return point;
+				};
+				*self.direction.insert(nearest)
+			}
+		};
+		self.start + offset.dot(direction) * direction
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn guides(kind: GuideKind) -> Guides {
+		Guides {
+			kind,
+			origin: vec2(100.0, 50.0),
+			scale: 400.0,
+		}
+	}
+
+	#[test]
+	fn lines_radiate_from_vanishing_points() {
+		let guides = guides(GuideKind::TwoPoint);
+		let directions = guides.directions_through(vec2(100.0, 150.0));
+		assert_eq!(directions.len(), 3);
+		// Toward either vanishing point, and vertical.
+		assert!(directions[0].abs_diff_eq(vec2(-400.0, -100.0).normalize(), 1e-6));
+		assert!(directions[1].abs_diff_eq(vec2(400.0, -100.0).normalize(), 1e-6));
+		assert!(directions[2].abs_diff_eq(Vec2::Y, 1e-6));
+
+		// There's no line through a vanishing point to itself.
+		assert_eq!(guides.directions_through(vec2(500.0, 50.0)).len(), 2);
+		assert_eq!(guides.parallel_spacing(), 50.0);
+	}
+
+	#[test]
+	fn snaps_to_the_nearest_line() {
+		let guides = guides(GuideKind::Isometric);
+		let start = vec2(10.0, 10.0);
+		let mut snap = GuideSnap::new(start, 5.0);
+		// Too close to the start to choose a line.
+		assert_eq!(snap.snap(&guides, vec2(12.0, 11.0)), start);
+
+		// Heading nearly 30° below horizontal chooses that line, and stays on it.
+		let along = Vec2::from_angle(FRAC_PI_6);
+		let snapped = snap.snap(&guides, start + 20.0 * Vec2::from_angle(0.6));
+		assert!((snapped - start).perp_dot(along).abs() < 1e-4, "{snapped}");
+		let snapped = snap.snap(&guides, start + vec2(0.0, 30.0));
+		assert!((snapped - start).perp_dot(along).abs() < 1e-4, "{snapped}");
+		assert!(snapped.distance(start) > 1.0);
+	}
+
+	#[test]
+	fn snapped_strokes_head_toward_vanishing_points() {
+		let guides = guides(GuideKind::OnePoint);
+		let start = vec2(300.0, 250.0);
+		let mut snap = GuideSnap::new(start, 1.0);
+		// Roughly toward the vanishing point, so exactly toward it.
+		let snapped = snap.snap(&guides, vec2(215.0, 160.0));
+		let toward = (guides.origin - start).normalize();
+		assert!((snapped - start).perp_dot(toward).abs() < 1e-3, "{snapped}");
+	}
+}
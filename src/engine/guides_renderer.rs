@@ -0,0 +1,182 @@
+use super::Guides;
+use super::GUIDE_RAY_SPACING;
+use crate::render::{error_scope, BindingBuffer, CapturedCommand, FrameCapturer, Resources};
+use crate::shaders::{self, guides::GuideOverlay};
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::sync::Arc;
+
+/// How opaque guide lines are drawn.
+pub const GUIDE_OPACITY: f32 = 0.5;
+
+/// Draws [`Guides`] over whatever has already been drawn. They are drawn in a pass of their own,
+/// directly to the resolved target, so they are neither multisampled nor depth tested.
+pub struct GuidesRenderer {
+	resources: Arc<Resources>,
+	guides_buffer: BindingBuffer<GuideOverlay>,
+	bind_group: shaders::guides::BindGroup0,
+}
+
+impl GuidesRenderer {
+	pub fn new(device: &wgpu::Device, resources: Arc<Resources>) -> Self {
+		let guides_buffer = BindingBuffer::new_sized()
+			.label("GuidesRenderer::guides_buffer")
+			.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+			.create(device);
+		let bind_group = error_scope(device, "GuidesRenderer::bind_group", || {
+			resources
+				.guides
+				.pipeline_layout()
+				.get()
+				.bind_group_layouts()
+				.0
+				.bind_group()
+				.guides(guides_buffer.as_entire_buffer_binding())
+				.create()
+		});
+		Self {
+			resources,
+			guides_buffer,
+			bind_group,
+		}
+	}
+
+	/// Creates a pipeline drawing to `target_format`.
+	pub fn create_pipeline(&self, target_format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+		self
+			.resources
+			.guides
+			.pipeline_layout()
+			.get()
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(shaders::guides::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: target_format,
+					blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.maybe_cache(self.resources.pipeline_cache())
+			.get()
+	}
+
+	/// Computes the shader overlay for `guides` seen through `view_transform`.
+	pub fn overlay(view_transform: &ViewTransform, guides: &Guides, opacity: f32) -> GuideOverlay {
+		// Working relative to the guides keeps this precise far from the canvas origin.
+		let origin = guides.origin;
+		let mut vanishing_points = [Vec2::ZERO; 3];
+		let placed = guides.vanishing_points();
+		for (to, from) in vanishing_points.iter_mut().zip(&placed) {
+			*to = *from - origin;
+		}
+		let mut parallel_angles = [0.0; 4];
+		let angles = guides.parallel_angles();
+		parallel_angles[..angles.len()].copy_from_slice(&angles);
+		GuideOverlay {
+			screen_to_guides: view_transform
+				.canvas_to_screen_matrix_relative_to(origin.as_dvec2())
+				.inverse(),
+			vanishing_point0: vanishing_points[0],
+			vanishing_point1: vanishing_points[1],
+			vanishing_point2: vanishing_points[2],
+			vanishing_point_count: placed.len() as u32,
+			ray_spacing: GUIDE_RAY_SPACING,
+			parallel_angles: Vec4::from_array(parallel_angles),
+			parallel_count: angles.len() as u32,
+			parallel_spacing: guides.parallel_spacing(),
+			zoom: view_transform.zoom as f32,
+			opacity,
+		}
+	}
+
+	/// Writes the overlay. This must happen before submitting any passes from [`Self::draw`].
+	pub fn write_overlay(&self, queue: &wgpu::Queue, overlay: &GuideOverlay) {
+		self.guides_buffer.write(queue, overlay);
+	}
+
+	/// Draws the overlay last written, recording what it issues to `capture`.
+	pub fn draw(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		capture: &FrameCapturer,
+	) {
+		render_pass.set_pipeline(pipeline);
+		capture.record(|| CapturedCommand::SetPipeline {
+			pipeline: "guides".to_string(),
+		});
+		self.bind_group.set(render_pass);
+		capture.record(|| CapturedCommand::SetBindGroup {
+			index: 0,
+			bind_group: "guides".to_string(),
+		});
+		render_pass.draw(0..4, 0..1);
+		capture.record(|| CapturedCommand::Draw {
+			vertices: 0..4,
+			instances: 0..1,
+			layers: Vec::new(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::GuideKind;
+	use crate::{render, test};
+
+	#[test]
+	fn draws_guide_lines() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let renderer = GuidesRenderer::new(device, Arc::new(Resources::new(device)));
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+			.create(device);
+
+		// Horizontal and vertical lines every 8 pixels, through their centers, and lines radiating
+		// from the middle of the screen.
+		let guides = Guides {
+			kind: GuideKind::OnePoint,
+			origin: vec2(32.5, 24.5),
+			scale: 64.0,
+		};
+		let overlay = GuidesRenderer::overlay(&ViewTransform::default(), &guides, 1.0);
+		renderer.write_overlay(context.queue(), &overlay);
+
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &target.create_view(&Default::default()),
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			renderer.draw(&mut render_pass, &pipeline, context.frame_capture());
+		}
+		context.queue().submit([encoder.finish()]);
+
+		let data = pollster::block_on(context.get_texture_layer_data(&target, 0))?;
+		let alpha = |x: usize, y: usize| data[4 * (y * 64 + x) + 3];
+		// On a vertical line, and on the horizon far enough from the vanishing point to show.
+		assert!(alpha(8, 5) > 200, "{}", alpha(8, 5));
+		assert!(alpha(61, 24) > 200, "{}", alpha(61, 24));
+		// Between lines.
+		assert_eq!(alpha(12, 2), 0);
+		Ok(())
+	}
+}
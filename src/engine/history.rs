@@ -0,0 +1,249 @@
+use super::{Atlas, Chart, ChartKey};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The charts an edit touched, as they were on one side of the edit. `None` means the chart didn't
+/// exist.
+#[derive(Default)]
+struct Edit {
+	charts: HashMap<ChartKey, Option<Arc<Chart>>>,
+}
+
+impl Edit {
+	/// Swaps the recorded charts into `atlas`, returning the edit which reverses this one.
+	fn apply(self, atlas: &mut Atlas) -> Self {
+		let charts = self
+			.charts
+			.into_iter()
+			.map(|(key, chart)| (key, atlas.replace_chart(key, chart)))
+			.collect();
+		Self { charts }
+	}
+}
+
+/// Undo and redo for edits to an [`Atlas`].
+///
+/// Before an edit writes to a chart, the history keeps a reference to it. Charts are copy-on-write,
+/// so this is cheap until the chart is actually modified, at which point the atlas gets a copy and
+/// the history keeps the original.
+pub struct History {
+	undo_stack: Vec<Edit>,
+	redo_stack: Vec<Edit>,
+	pending: Option<Edit>,
+	max_len: usize,
+}
+
+impl Default for History {
+	fn default() -> Self {
+		Self::new(Self::DEFAULT_MAX_LEN)
+	}
+}
+
+impl History {
+	pub const DEFAULT_MAX_LEN: usize = 100;
+
+	/// Creates an empty history which remembers at most `max_len` edits.
+	pub fn new(max_len: usize) -> Self {
+		Self {
+			undo_stack: Vec::new(),
+			redo_stack: Vec::new(),
+			pending: None,
+			max_len,
+		}
+	}
+
+	pub fn can_undo(&self) -> bool {
+		self.pending.is_none() && !self.undo_stack.is_empty()
+	}
+
+	pub fn can_redo(&self) -> bool {
+		self.pending.is_none() && !self.redo_stack.is_empty()
+	}
+
+	/// Whether an edit has begun and not yet ended.
+	pub fn is_editing(&self) -> bool {
+		self.pending.is_some()
+	}
+
+	/// Forgets every edit, for when the atlas was changed some other way, such as restoring it from
+	/// [`Checkpoints`](super::Checkpoints).
+	pub fn clear(&mut self) {
+		self.undo_stack.clear();
+		self.redo_stack.clear();
+		self.pending = None;
+	}
+
+	/// Starts an edit, such as a stroke. Any edit already in progress is ended first.
+	pub fn begin_edit(&mut self) {
+		self.end_edit();
+		self.pending = Some(Edit::default());
+	}
+
+	/// Records the current state of the charts at `keys`, which are about to be written to. Only the
+	/// first record of each chart in an edit is kept.
+	pub fn record(&mut self, atlas: &Atlas, keys: impl IntoIterator<Item = ChartKey>) {
+		let Some(edit) = &mut self.pending else {
+			return;
+		};
+		for key in keys {
+			edit
+				.charts
+				.entry(key)
+				.or_insert_with(|| atlas.get_chart(&key));
+		}
+	}
+
+	/// Ends the current edit. Edits which didn't touch any charts aren't kept.
+	pub fn end_edit(&mut self) {
+		let Some(edit) = self.pending.take() else {
+			return;
+		};
+		if edit.charts.is_empty() {
+			return;
+		}
+		self.redo_stack.clear();
+		self.undo_stack.push(edit);
+		if self.undo_stack.len() > self.max_len {
+			self.undo_stack.remove(0);
+		}
+	}
+
+	/// Reverts the most recent edit. Returns whether anything changed.
+	pub fn undo(&mut self, atlas: &mut Atlas) -> bool {
+		if !self.can_undo() {
+			return false;
+		}
+		let edit = self.undo_stack.pop().unwrap();
+		self.redo_stack.push(edit.apply(atlas));
+		true
+	}
+
+	/// Reapplies the most recently undone edit. Returns whether anything changed.
+	pub fn redo(&mut self, atlas: &mut Atlas) -> bool {
+		if !self.can_redo() {
+			return false;
+		}
+		let edit = self.redo_stack.pop().unwrap();
+		self.undo_stack.push(edit.apply(atlas));
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{CanvasFormat, CHART_SIZE};
+	use crate::test;
+
+	fn paint(history: &mut History, atlas: &mut Atlas, key: ChartKey, pixel: &[u8]) {
+		history.record(atlas, [key]);
+		atlas.get_chart_mut(key).tile().fill_texture(pixel);
+	}
+
+	fn chart_data(atlas: &Atlas, key: ChartKey) -> anyhow::Result<Option<Vec<u8>>> {
+		let Some(chart) = atlas.get_chart(&key) else {
+			return Ok(None);
+		};
+		Ok(Some(pollster::block_on(chart.tile().get_texture_data())?))
+	}
+
+	fn filled(pixel: &[u8]) -> Option<Vec<u8>> {
+		Some(pixel.repeat((CHART_SIZE * CHART_SIZE) as usize))
+	}
+
+	#[test]
+	fn history_keeps_charts_alive() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		let mut history = History::default();
+		history.begin_edit();
+		paint(&mut history, &mut atlas, ChartKey(0, 0), &[255, 0, 0, 255]);
+		history.end_edit();
+		history.begin_edit();
+		paint(&mut history, &mut atlas, ChartKey(0, 0), &[0, 0, 255, 255]);
+		history.end_edit();
+		// The edited chart is a copy, so the original is still held for undo.
+		assert_eq!(atlas.tile_pool().stats().tiles, 2);
+
+		let pool = atlas.tile_pool().downgrade();
+		drop(atlas);
+		assert_eq!(pool.stats().map(|stats| stats.tiles), Some(1));
+		drop(history);
+		assert_eq!(pool.stats(), None);
+		Ok(())
+	}
+
+	#[test]
+	fn undo_and_redo() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		let mut history = History::default();
+		let (a, b) = (ChartKey(0, 0), ChartKey(1, -1));
+		let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+
+		history.begin_edit();
+		paint(&mut history, &mut atlas, a, &red);
+		history.end_edit();
+
+		history.begin_edit();
+		paint(&mut history, &mut atlas, a, &blue);
+		paint(&mut history, &mut atlas, b, &blue);
+		history.end_edit();
+
+		assert!(history.undo(&mut atlas));
+		assert_eq!(chart_data(&atlas, a)?, filled(&red));
+		assert_eq!(chart_data(&atlas, b)?, None);
+
+		assert!(history.undo(&mut atlas));
+		assert_eq!(chart_data(&atlas, a)?, None);
+		assert!(!history.undo(&mut atlas));
+
+		assert!(history.redo(&mut atlas));
+		assert!(history.redo(&mut atlas));
+		assert_eq!(chart_data(&atlas, a)?, filled(&blue));
+		assert_eq!(chart_data(&atlas, b)?, filled(&blue));
+		assert!(!history.redo(&mut atlas));
+		Ok(())
+	}
+
+	#[test]
+	fn new_edit_clears_redo() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		let mut history = History::default();
+		let key = ChartKey(0, 0);
+
+		history.begin_edit();
+		paint(&mut history, &mut atlas, key, &[255; 4]);
+		history.end_edit();
+		assert!(history.undo(&mut atlas));
+		assert!(history.can_redo());
+
+		history.begin_edit();
+		paint(&mut history, &mut atlas, key, &[0, 255, 0, 255]);
+		history.end_edit();
+		assert!(!history.can_redo());
+		Ok(())
+	}
+
+	#[test]
+	fn empty_edits_are_dropped() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		let mut history = History::new(2);
+
+		history.begin_edit();
+		history.end_edit();
+		assert!(!history.can_undo());
+
+		for _ in 0..3 {
+			history.begin_edit();
+			paint(&mut history, &mut atlas, ChartKey(0, 0), &[255; 4]);
+			history.end_edit();
+		}
+		assert!(history.undo(&mut atlas));
+		assert!(history.undo(&mut atlas));
+		assert!(!history.undo(&mut atlas));
+		Ok(())
+	}
+}
@@ -0,0 +1,179 @@
+use super::{AABox, ChartKey, PoolExhausted, CHART_SIZE};
+use crate::util::{rgb_to_oklab, ImageExt};
+use glam::*;
+use half::f16;
+use thiserror::Error;
+use zune_core::bytestream::ZCursor;
+use zune_core::options::DecoderOptions;
+use zune_image::image::Image;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+	#[error("couldn't decode image: {0}")]
+	Decode(String),
+
+	#[error("unsupported texture format {0:?}")]
+	UnsupportedTextureFormat(wgpu::TextureFormat),
+
+	#[error("image is too large for this GPU: {0}")]
+	TooLarge(#[from] PoolExhausted),
+}
+
+/// An image converted to the canvas' color space, ready to be drawn into an atlas.
+#[derive(Debug, Clone)]
+pub struct ImportedImage {
+	size: UVec2,
+	// Oklab with straight alpha, in rows from the top.
+	pixels: Vec<Vec4>,
+}
+
+impl ImportedImage {
+	/// Decodes an encoded image, such as the contents of a PNG or JPEG file.
+	pub fn decode(encoded: &[u8]) -> Result<Self, ImportError> {
+		let image = Image::read(ZCursor::new(encoded), DecoderOptions::default())
+			.map_err(|err| ImportError::Decode(format!("{err:?}")))?;
+		let (data, width, height, format) = image.into_texture_data();
+		Self::from_texture_data(&data, uvec2(width, height), format)
+	}
+
+	/// Converts sRGB-encoded texture data.
+	pub fn from_texture_data(
+		data: &[u8],
+		size: UVec2,
+		format: wgpu::TextureFormat,
+	) -> Result<Self, ImportError> {
+		use wgpu::TextureFormat::*;
+		let rgba: Vec<Vec4> = match format.remove_srgb_suffix() {
+			R8Unorm => data
+				.iter()
+				.map(|&l| Vec3::splat(l as f32 / 255.0).extend(1.0))
+				.collect(),
+			Rgba8Unorm => data
+				.chunks_exact(4)
+				.map(|c| Vec4::from_array([c[0], c[1], c[2], c[3]].map(|c| c as f32 / 255.0)))
+				.collect(),
+			R16Unorm => bytemuck::pod_collect_to_vec::<_, u16>(data)
+				.into_iter()
+				.map(|l| Vec3::splat(l as f32 / 65535.0).extend(1.0))
+				.collect(),
+			Rgba16Unorm => bytemuck::pod_collect_to_vec::<_, [u16; 4]>(data)
+				.into_iter()
+				.map(|c| Vec4::from_array(c.map(|c| c as f32 / 65535.0)))
+				.collect(),
+			Rgba16Float => bytemuck::pod_collect_to_vec::<_, [f16; 4]>(data)
+				.into_iter()
+				.map(|c| Vec4::from_array(c.map(f16::to_f32)))
+				.collect(),
+			Rgba32Float => bytemuck::pod_collect_to_vec::<_, [f32; 4]>(data)
+				.into_iter()
+				.map(Vec4::from_array)
+				.collect(),
+			_ => Err(ImportError::UnsupportedTextureFormat(format))?,
+		};
+		assert_eq!(rgba.len(), (size.x * size.y) as usize);
+
+		let pixels = rgba
+			.into_iter()
+			.map(|p| {
+				let alpha = p.w.clamp(0.0, 1.0);
+				if alpha > 0.0 {
+					rgb_to_oklab(p.xyz().clamp(Vec3::ZERO, Vec3::ONE)).extend(alpha)
+				} else {
+					Vec4::ZERO
+				}
+			})
+			.collect();
+		Ok(Self { size, pixels })
+	}
+
+	pub fn size(&self) -> UVec2 {
+		self.size
+	}
+
+	/// Multiplies the image's alpha by `opacity`.
+	pub fn fade(&mut self, opacity: f32) {
+		let opacity = opacity.clamp(0.0, 1.0);
+		for pixel in &mut self.pixels {
+			pixel.w *= opacity;
+		}
+	}
+
+	/// The charts covered by the image with its top-left corner at `origin`.
+	pub fn chart_keys(&self, origin: IVec2) -> impl Iterator<Item = ChartKey> {
+		let min = origin.as_vec2();
+		// The bounds are half-open, so pull the far corner back inside.
+		let max = (origin + self.size.as_ivec2() - 1).as_vec2();
+		ChartKey::find_intersecting(AABox::new(min, max))
+	}
+
+	/// The image's pixels within the chart at `key`, with its top-left corner at `origin`. Pixels
+	/// outside of the image are transparent.
+	pub fn chart_pixels(&self, origin: IVec2, key: ChartKey) -> Vec<Vec4> {
+		let chart_origin = ivec2(key.0, key.1) * CHART_SIZE as i32;
+		let mut pixels = vec![Vec4::ZERO; (CHART_SIZE * CHART_SIZE) as usize];
+		for y in 0..CHART_SIZE {
+			for x in 0..CHART_SIZE {
+				let p = chart_origin + uvec2(x, y).as_ivec2() - origin;
+				if p.cmpge(IVec2::ZERO).all() && p.as_uvec2().cmplt(self.size).all() {
+					let p = p.as_uvec2();
+					pixels[(y * CHART_SIZE + x) as usize] =
+						self.pixels[(p.y * self.size.x + p.x) as usize];
+				}
+			}
+		}
+		pixels
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn checker() -> ImportedImage {
+		// Opaque white, transparent, opaque black, half-transparent red.
+		let data = [255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 255, 255, 0, 0, 128];
+		ImportedImage::from_texture_data(&data, uvec2(2, 2), wgpu::TextureFormat::Rgba8Unorm).unwrap()
+	}
+
+	#[test]
+	fn converts_to_oklab() {
+		let image = checker();
+		assert!(image.pixels[0].abs_diff_eq(vec4(1.0, 0.0, 0.0, 1.0), 1e-3));
+		assert_eq!(image.pixels[1], Vec4::ZERO);
+		assert!(image.pixels[2].abs_diff_eq(vec4(0.0, 0.0, 0.0, 1.0), 1e-3));
+		assert!(image.pixels[3].x > 0.5 && image.pixels[3].y > 0.1);
+		assert!((image.pixels[3].w - 128.0 / 255.0).abs() < 1e-6);
+
+		let gray =
+			ImportedImage::from_texture_data(&[255], uvec2(1, 1), wgpu::TextureFormat::R8Unorm)
+				.unwrap();
+		assert!(gray.pixels[0].abs_diff_eq(vec4(1.0, 0.0, 0.0, 1.0), 1e-3));
+	}
+
+	#[test]
+	fn charts_straddled() {
+		let image = checker();
+		let origin = IVec2::splat(CHART_SIZE as i32 - 1);
+		let mut keys: Vec<_> = image.chart_keys(origin).collect();
+		keys.sort();
+		assert_eq!(
+			keys,
+			[
+				ChartKey(0, 0),
+				ChartKey(0, 1),
+				ChartKey(1, 0),
+				ChartKey(1, 1)
+			]
+		);
+		assert_eq!(image.chart_keys(IVec2::ZERO).count(), 1);
+
+		let last = (CHART_SIZE * CHART_SIZE - 1) as usize;
+		let pixels = image.chart_pixels(origin, ChartKey(0, 0));
+		assert_eq!(pixels[last], image.pixels[0]);
+		assert_eq!(pixels.iter().filter(|&&p| p != Vec4::ZERO).count(), 1);
+		let pixels = image.chart_pixels(origin, ChartKey(1, 1));
+		assert_eq!(pixels[0], image.pixels[3]);
+		let pixels = image.chart_pixels(origin, ChartKey(0, 1));
+		assert_eq!(pixels[(CHART_SIZE - 1) as usize], image.pixels[2]);
+	}
+}
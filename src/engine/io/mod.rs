@@ -0,0 +1,6 @@
+//! Reading and writing files shared with other painting apps.
+
+mod zip;
+
+mod ora;
+pub use ora::*;
@@ -0,0 +1,528 @@
+use super::zip::{ZipError, ZipReader, ZipWriter};
+use crate::engine::{
+	export, export_region, Atlas, ExportError, ImportError, ImportedImage, EXPORT_TEXTURE_FORMAT,
+	THUMBNAIL_SIZE,
+};
+use crate::render::Resources;
+use crate::util::ImageExt;
+use crate::WgpuContext;
+use glam::*;
+use std::future::Future;
+use std::sync::Arc;
+use zune_image::codecs::ImageFormat;
+use zune_image::image::Image;
+
+// OpenRaster is the layered format shared by Krita, GIMP, and MyPaint: a ZIP archive of a PNG per
+// layer, an XML stack describing how they are arranged, and a flattened copy with a thumbnail for
+// viewers which don't understand layers.
+
+/// The extension of OpenRaster files.
+pub const ORA_FILE_EXTENSION: &str = "ora";
+
+/// The MIME type of OpenRaster files, which is also the archive's first entry.
+pub const ORA_MIME_TYPE: &str = "image/openraster";
+
+const MIME_TYPE_PATH: &str = "mimetype";
+const STACK_PATH: &str = "stack.xml";
+const MERGED_IMAGE_PATH: &str = "mergedimage.png";
+const THUMBNAIL_PATH: &str = "Thumbnails/thumbnail.png";
+
+/// The version of the OpenRaster specification written.
+const ORA_VERSION: &str = "0.0.6";
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum OraError {
+	#[error(transparent)]
+	Zip(#[from] ZipError),
+	#[error("not an OpenRaster file")]
+	NotOpenRaster,
+	#[error("OpenRaster file is missing {0}")]
+	MissingEntry(String),
+	#[error("malformed OpenRaster stack: {0}")]
+	MalformedStack(String),
+}
+
+/// A layer of an OpenRaster file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OraLayer {
+	pub name: String,
+	/// Where the layer's top-left corner is in the image, in pixels.
+	pub origin: IVec2,
+	pub opacity: f32,
+	pub visible: bool,
+	/// The layer's pixels, as a PNG.
+	pub png: Vec<u8>,
+}
+
+/// The contents of an OpenRaster file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OraFile {
+	pub size: UVec2,
+	/// From the top of the stack to the bottom. Groups are flattened into their layers.
+	pub layers: Vec<OraLayer>,
+	/// The layers composited, as a PNG. Empty if a decoded file left it out.
+	pub merged_png: Vec<u8>,
+	/// The composited layers no larger than 256 pixels either way, as a PNG. Empty if a decoded
+	/// file left it out.
+	pub thumbnail_png: Vec<u8>,
+}
+
+impl OraFile {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut zip = ZipWriter::default();
+		// The MIME type must come first and be stored, so the file can be identified by its prefix.
+		zip.add(MIME_TYPE_PATH, ORA_MIME_TYPE.as_bytes(), false);
+		let paths: Vec<_> = (0..self.layers.len())
+			.map(|index| format!("data/layer{index}.png"))
+			.collect();
+		zip.add(STACK_PATH, write_stack(self, &paths).as_bytes(), true);
+		// PNGs are already compressed.
+		for (layer, path) in self.layers.iter().zip(&paths) {
+			zip.add(path, &layer.png, false);
+		}
+		zip.add(MERGED_IMAGE_PATH, &self.merged_png, false);
+		zip.add(THUMBNAIL_PATH, &self.thumbnail_png, false);
+		zip.finish()
+	}
+
+	pub fn decode(encoded: &[u8]) -> Result<Self, OraError> {
+		let zip = ZipReader::new(encoded)?;
+		let read = |path: &str| {
+			zip.read(path)
+				.ok_or_else(|| OraError::MissingEntry(path.into()))?
+				.map_err(OraError::from)
+		};
+		if read(MIME_TYPE_PATH).ok().as_deref() != Some(ORA_MIME_TYPE.as_bytes()) {
+			return Err(OraError::NotOpenRaster);
+		}
+		let stack = String::from_utf8(read(STACK_PATH)?)
+			.map_err(|err| OraError::MalformedStack(err.to_string()))?;
+		let (size, stack_layers) = parse_stack(&stack)?;
+		let layers = stack_layers
+			.into_iter()
+			.map(|layer| {
+				Ok(OraLayer {
+					png: read(&layer.src)?,
+					name: layer.name,
+					origin: layer.origin,
+					opacity: layer.opacity,
+					visible: layer.visible,
+				})
+			})
+			.collect::<Result<_, OraError>>()?;
+		let optional = |path: &str| zip.read(path).transpose().map(Option::unwrap_or_default);
+		Ok(Self {
+			size,
+			layers,
+			merged_png: optional(MERGED_IMAGE_PATH)?,
+			thumbnail_png: optional(THUMBNAIL_PATH)?,
+		})
+	}
+
+	/// Composites the visible layers onto `atlas`, bottom first, with the image's top-left corner at
+	/// the canvas origin.
+	pub fn draw_to(&self, atlas: &mut Atlas, resources: &Resources) -> Result<(), ImportError> {
+		let layers = self
+			.layers
+			.iter()
+			.rev()
+			.filter(|layer| layer.visible && layer.opacity > 0.0)
+			.map(|layer| {
+				let mut image = ImportedImage::decode(&layer.png)?;
+				image.fade(layer.opacity);
+				Ok((image, layer.origin))
+			})
+			.collect::<Result<Vec<_>, ImportError>>()?;
+		// Nothing is drawn unless every layer decodes.
+		for (image, origin) in layers {
+			atlas.draw_image(resources, &image, origin)?;
+		}
+		Ok(())
+	}
+}
+
+/// Renders each of `layers`, named and listed from the top of the stack down, for an OpenRaster
+/// file. The image covers every layer's charts, and layers without any are left out.
+pub fn export_ora(
+	context: &Arc<WgpuContext>,
+	resources: &Arc<Resources>,
+	layers: &[(&str, &Atlas)],
+) -> Result<impl Future<Output = anyhow::Result<OraFile>>, ExportError> {
+	let (device, queue) = (context.device(), context.queue());
+	let regions: Vec<_> = layers
+		.iter()
+		.filter_map(|&(name, atlas)| Some((name, atlas, export_region(atlas)?)))
+		.collect();
+	let min = regions
+		.iter()
+		.map(|(_, _, (origin, _))| *origin)
+		.reduce(IVec2::min)
+		.ok_or(ExportError::Empty)?;
+	let max = regions
+		.iter()
+		.map(|(_, _, (origin, size))| *origin + size.as_ivec2())
+		.reduce(IVec2::max)
+		.ok_or(ExportError::Empty)?;
+	let size = (max - min).as_uvec2();
+
+	let mut rendered = Vec::with_capacity(regions.len());
+	for (name, atlas, (origin, layer_size)) in regions {
+		let texture = export(atlas).generate(device, queue, resources)?;
+		let data = context.get_texture_layer_data(&texture, 0);
+		rendered.push((name.to_string(), origin - min, layer_size, data));
+	}
+
+	Ok(async move {
+		let mut layers = Vec::with_capacity(rendered.len());
+		let mut pixels = Vec::with_capacity(rendered.len());
+		for (name, origin, layer_size, data) in rendered {
+			let data = data.await?;
+			layers.push(OraLayer {
+				name,
+				origin,
+				opacity: 1.0,
+				visible: true,
+				png: encode_png(&data, layer_size)?,
+			});
+			pixels.push((origin, layer_size, data));
+		}
+
+		let merged = composite(size, pixels.iter().rev());
+		let thumbnail_size = (size.as_vec2() * (THUMBNAIL_SIZE as f32 / size.max_element() as f32))
+			.min(size.as_vec2())
+			.round()
+			.as_uvec2()
+			.max(UVec2::ONE);
+		let thumbnail = downscale(&merged, size, thumbnail_size);
+		Ok(OraFile {
+			size,
+			layers,
+			merged_png: encode_png(&merged, size)?,
+			thumbnail_png: encode_png(&thumbnail, thumbnail_size)?,
+		})
+	})
+}
+
+fn encode_png(data: &[u8], size: UVec2) -> anyhow::Result<Vec<u8>> {
+	let image = Image::from_texture_data(data, size.x, size.y, EXPORT_TEXTURE_FORMAT)?;
+	Ok(image.write_to_vec(ImageFormat::PNG)?)
+}
+
+/// Composites 8-bit images with straight alpha, bottom first, into one of `size` pixels, as
+/// OpenRaster's default `svg:src-over` does.
+fn composite<'a>(
+	size: UVec2,
+	layers: impl IntoIterator<Item = &'a (IVec2, UVec2, Vec<u8>)>,
+) -> Vec<u8> {
+	let mut merged = vec![Vec4::ZERO; (size.x * size.y) as usize];
+	for (origin, layer_size, data) in layers {
+		for (index, texel) in data.chunks_exact(4).enumerate() {
+			let position =
+				*origin + uvec2(index as u32 % layer_size.x, index as u32 / layer_size.x).as_ivec2();
+			if position.cmplt(IVec2::ZERO).any() || position.as_uvec2().cmpge(size).any() {
+				continue;
+			}
+			let source =
+				Vec4::from_array([texel[0], texel[1], texel[2], texel[3]].map(|c| c as f32 / 255.0));
+			let destination = &mut merged[(position.y as u32 * size.x + position.x as u32) as usize];
+			// Composite premultiplied, then divide the alpha back out below.
+			let source = (source.xyz() * source.w).extend(source.w);
+			*destination = source + *destination * (1.0 - source.w);
+		}
+	}
+	merged.into_iter().flat_map(unpremultiplied_texel).collect()
+}
+
+/// Shrinks an 8-bit image with straight alpha from `size` to `new_size` pixels, averaging the
+/// texels covering each new one.
+fn downscale(data: &[u8], size: UVec2, new_size: UVec2) -> Vec<u8> {
+	let mut sums = vec![(Vec4::ZERO, 0.0); (new_size.x * new_size.y) as usize];
+	for (index, texel) in data.chunks_exact(4).enumerate() {
+		let position = uvec2(index as u32 % size.x, index as u32 / size.x);
+		let target = (position * new_size / size).min(new_size - 1);
+		let texel =
+			Vec4::from_array([texel[0], texel[1], texel[2], texel[3]].map(|c| c as f32 / 255.0));
+		let (sum, count) = &mut sums[(target.y * new_size.x + target.x) as usize];
+		*sum += (texel.xyz() * texel.w).extend(texel.w);
+		*count += 1.0;
+	}
+	sums
+		.into_iter()
+		.flat_map(|(sum, count)| unpremultiplied_texel(sum / f32::max(count, 1.0)))
+		.collect()
+}
+
+fn unpremultiplied_texel(premultiplied: Vec4) -> [u8; 4] {
+	let alpha = premultiplied.w;
+	let color = match alpha > 0.0 {
+		true => premultiplied.xyz() / alpha,
+		false => Vec3::ZERO,
+	};
+	color
+		.extend(alpha)
+		.to_array()
+		.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn escape_xml(text: &str) -> String {
+	text
+		.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+		.replace('\'', "&apos;")
+}
+
+fn unescape_xml(text: &str) -> String {
+	text
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&apos;", "'")
+		.replace("&amp;", "&")
+}
+
+fn write_stack(file: &OraFile, paths: &[String]) -> String {
+	let mut stack = format!(
+		"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<image version=\"{ORA_VERSION}\" w=\"{}\" h=\"{}\">\n<stack>\n",
+		file.size.x, file.size.y
+	);
+	for (layer, path) in file.layers.iter().zip(paths) {
+		stack += &format!(
+			"<layer name=\"{}\" src=\"{path}\" x=\"{}\" y=\"{}\" opacity=\"{}\" visibility=\"{}\"/>\n",
+			escape_xml(&layer.name),
+			layer.origin.x,
+			layer.origin.y,
+			layer.opacity,
+			if layer.visible { "visible" } else { "hidden" },
+		);
+	}
+	stack += "</stack>\n</image>\n";
+	stack
+}
+
+/// A layer as the stack describes it.
+#[derive(Debug, PartialEq)]
+struct StackLayer {
+	name: String,
+	src: String,
+	origin: IVec2,
+	opacity: f32,
+	visible: bool,
+}
+
+/// The elements of `xml` which open or close themselves, with their names and attributes.
+/// Closing tags, comments, and declarations are skipped. This is far from a full XML parser, but
+/// stacks only use elements and attributes.
+fn xml_elements(
+	xml: &str,
+) -> impl Iterator<Item = Result<(&str, Vec<(&str, String)>), OraError>> + '_ {
+	xml.split('<').skip(1).filter_map(|tag| {
+		let malformed = || OraError::MalformedStack(format!("unterminated tag <{tag}"));
+		let Some((tag, _)) = tag.split_once('>') else {
+			return Some(Err(malformed()));
+		};
+		if tag.starts_with(['/', '?', '!']) {
+			return None;
+		}
+		let tag = tag.trim_end_matches('/').trim();
+		let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+		let mut attributes = Vec::new();
+		loop {
+			rest = rest.trim_start();
+			if rest.is_empty() {
+				break;
+			}
+			let Some((key, value)) = rest.split_once('=') else {
+				return Some(Err(malformed()));
+			};
+			let value = value.trim_start();
+			let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+				return Some(Err(malformed()));
+			};
+			let Some((value, after)) = value[1..].split_once(quote) else {
+				return Some(Err(malformed()));
+			};
+			attributes.push((key.trim(), unescape_xml(value)));
+			rest = after;
+		}
+		Some(Ok((name, attributes)))
+	})
+}
+
+fn parse_stack(xml: &str) -> Result<(UVec2, Vec<StackLayer>), OraError> {
+	let malformed = |message: String| OraError::MalformedStack(message);
+	let mut size = None;
+	let mut layers = Vec::new();
+	for element in xml_elements(xml) {
+		let (name, attributes) = element?;
+		let attribute = |key: &str| {
+			attributes
+				.iter()
+				.find(|(k, _)| *k == key)
+				.map(|(_, value)| value.as_str())
+		};
+		let number = |key: &str, default: f32| match attribute(key) {
+			Some(value) => value
+				.trim()
+				.parse::<f32>()
+				.map_err(|_| malformed(format!("{key}=\"{value}\" isn't a number"))),
+			None => Ok(default),
+		};
+		match name {
+			"image" => {
+				let (w, h) = (number("w", -1.0)?, number("h", -1.0)?);
+				if w < 1.0 || h < 1.0 {
+					return Err(malformed("the image has no size".into()));
+				}
+				size = Some(vec2(w, h).as_uvec2());
+			}
+			"layer" => {
+				let Some(src) = attribute("src") else {
+					return Err(malformed("a layer has no source".into()));
+				};
+				layers.push(StackLayer {
+					name: attribute("name").unwrap_or_default().into(),
+					src: src.into(),
+					origin: vec2(number("x", 0.0)?, number("y", 0.0)?).as_ivec2(),
+					opacity: number("opacity", 1.0)?.clamp(0.0, 1.0),
+					visible: attribute("visibility") != Some("hidden"),
+				});
+			}
+			_ => {}
+		}
+	}
+	let size = size.ok_or_else(|| malformed("there is no image element".into()))?;
+	Ok((size, layers))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{CanvasFormat, ChartKey};
+	use crate::test;
+	use std::assert_matches::assert_matches;
+
+	/// A PNG of a single opaque color.
+	fn solid_png(size: UVec2, color: [u8; 4]) -> Vec<u8> {
+		let data = color.repeat((size.x * size.y) as usize);
+		encode_png(&data, size).unwrap()
+	}
+
+	fn file() -> OraFile {
+		OraFile {
+			size: uvec2(4, 3),
+			layers: vec![
+				OraLayer {
+					name: "Ink & \"lines\"".into(),
+					origin: ivec2(1, 1),
+					opacity: 0.5,
+					visible: true,
+					png: solid_png(uvec2(2, 2), [0, 0, 0, 255]),
+				},
+				OraLayer {
+					name: "Background".into(),
+					origin: IVec2::ZERO,
+					opacity: 1.0,
+					visible: false,
+					png: solid_png(uvec2(4, 3), [255, 255, 255, 255]),
+				},
+			],
+			merged_png: solid_png(uvec2(4, 3), [128, 128, 128, 255]),
+			thumbnail_png: solid_png(uvec2(4, 3), [128, 128, 128, 255]),
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let file = file();
+		let encoded = file.encode();
+		assert!(encoded[30..].starts_with(b"mimetypeimage/openraster"));
+		assert_eq!(OraFile::decode(&encoded), Ok(file));
+	}
+
+	#[test]
+	fn rejects_other_archives() {
+		let mut zip = ZipWriter::default();
+		zip.add(MIME_TYPE_PATH, b"application/zip", false);
+		assert_eq!(OraFile::decode(&zip.finish()), Err(OraError::NotOpenRaster));
+		assert_matches!(OraFile::decode(b"PK"), Err(OraError::Zip(_)));
+	}
+
+	#[test]
+	fn parses_stacks_from_other_apps() {
+		let stack = r#"<?xml version='1.0' encoding='UTF-8'?>
+			<!-- Krita nests groups, which are flattened. -->
+			<image h="64" w="32" xres="72" yres="72" version="0.0.1">
+				<stack name="root">
+					<stack name="Group" x="0" y="0">
+						<layer name='Top' src='data/top.png' x='-3' y='5.0' />
+					</stack>
+					<layer src="data/bottom.png" visibility="hidden" opacity="0.25" composite-op="svg:multiply"/>
+				</stack>
+			</image>"#;
+		let (size, layers) = parse_stack(stack).unwrap();
+		assert_eq!(size, uvec2(32, 64));
+		assert_eq!(
+			layers,
+			[
+				StackLayer {
+					name: "Top".into(),
+					src: "data/top.png".into(),
+					origin: ivec2(-3, 5),
+					opacity: 1.0,
+					visible: true,
+				},
+				StackLayer {
+					name: "".into(),
+					src: "data/bottom.png".into(),
+					origin: IVec2::ZERO,
+					opacity: 0.25,
+					visible: false,
+				},
+			]
+		);
+
+		assert_matches!(
+			parse_stack("<stack></stack>"),
+			Err(OraError::MalformedStack(_))
+		);
+		assert_matches!(
+			parse_stack(r#"<image w="1" h="1"><layer x="1"/></image>"#),
+			Err(OraError::MalformedStack(_))
+		);
+	}
+
+	#[test]
+	fn composites_layers_bottom_up() {
+		let red = (
+			IVec2::ZERO,
+			uvec2(2, 1),
+			[255, 0, 0, 255, 255, 0, 0, 255].to_vec(),
+		);
+		let blue = (ivec2(1, 0), uvec2(1, 1), [0, 0, 255, 128].to_vec());
+		let merged = composite(uvec2(3, 1), [&red, &blue]);
+		assert_eq!(merged[0..4], [255, 0, 0, 255]);
+		assert_eq!(merged[4..8], [127, 0, 128, 255]);
+		assert_eq!(merged[8..12], [0, 0, 0, 0]);
+
+		let shrunk = downscale(&merged, uvec2(3, 1), uvec2(1, 1));
+		assert_eq!(shrunk[3], 170);
+	}
+
+	#[test]
+	fn draws_visible_layers() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		file().draw_to(&mut atlas, &resources)?;
+
+		// Only the half-transparent black layer is visible.
+		assert!(atlas.get_chart(&ChartKey(0, 0)).is_some());
+		let sample = |point| pollster::block_on(atlas.sample(point));
+		let ink = sample(vec2(1.5, 1.5))?;
+		assert!((ink.w - 0.5).abs() < 0.01 && ink.x < 0.01);
+		assert_eq!(sample(vec2(0.5, 0.5))?.w, 0.0);
+		Ok(())
+	}
+}
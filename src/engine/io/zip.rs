@@ -0,0 +1,314 @@
+// Just enough of the ZIP format for OpenRaster files: entries are stored or deflated, there is a
+// single disk, and nothing exceeds the 4 GiB limits which would call for ZIP64.
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// Version 2.0, the first with deflate.
+const VERSION: u16 = 20;
+/// Marks names as UTF-8.
+const UTF8_FLAG: u16 = 1 << 11;
+/// 1980-01-01 00:00, the earliest time ZIP can represent. Entries aren't timestamped.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// How hard to work at compressing entries, from 0 to 10.
+const COMPRESSION_LEVEL: u8 = 6;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ZipError {
+	#[error("not a ZIP archive")]
+	NotAZip,
+	#[error("ZIP archive is truncated")]
+	Truncated,
+	#[error("unsupported ZIP compression method {0}")]
+	UnsupportedMethod(u16),
+	#[error("ZIP entry {0} is corrupt")]
+	Corrupt(String),
+}
+
+const fn crc32_table() -> [u32; 256] {
+	let mut table = [0; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = match crc & 1 {
+				0 => crc >> 1,
+				_ => (crc >> 1) ^ 0xedb88320,
+			};
+			bit += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+fn crc32(data: &[u8]) -> u32 {
+	!data.iter().fold(!0, |crc, &byte| {
+		CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+	})
+}
+
+/// Where an entry's data is, as recorded in the central directory.
+struct CentralEntry {
+	name: String,
+	method: u16,
+	crc: u32,
+	compressed_size: u32,
+	size: u32,
+	offset: u32,
+}
+
+/// Builds a ZIP archive in memory.
+#[derive(Default)]
+pub struct ZipWriter {
+	data: Vec<u8>,
+	entries: Vec<CentralEntry>,
+}
+
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+	data.extend(value.to_le_bytes());
+}
+
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+	data.extend(value.to_le_bytes());
+}
+
+impl ZipWriter {
+	/// Adds an entry, deflating it if `compress` is set. Entries are written in the order they are
+	/// added.
+	pub fn add(&mut self, name: &str, contents: &[u8], compress: bool) {
+		let (method, compressed) = match compress {
+			true => (
+				DEFLATED,
+				miniz_oxide::deflate::compress_to_vec(contents, COMPRESSION_LEVEL),
+			),
+			false => (STORED, contents.to_vec()),
+		};
+		let entry = CentralEntry {
+			name: name.into(),
+			method,
+			crc: crc32(contents),
+			compressed_size: compressed.len() as u32,
+			size: contents.len() as u32,
+			offset: self.data.len() as u32,
+		};
+
+		let data = &mut self.data;
+		push_u32(data, LOCAL_HEADER_SIGNATURE);
+		push_u16(data, VERSION);
+		push_u16(data, UTF8_FLAG);
+		push_u16(data, method);
+		push_u16(data, DOS_TIME);
+		push_u16(data, DOS_DATE);
+		push_u32(data, entry.crc);
+		push_u32(data, entry.compressed_size);
+		push_u32(data, entry.size);
+		push_u16(data, name.len() as u16);
+		push_u16(data, 0);
+		data.extend(name.as_bytes());
+		data.extend(compressed);
+		self.entries.push(entry);
+	}
+
+	/// Writes the central directory, completing the archive.
+	pub fn finish(self) -> Vec<u8> {
+		let Self { mut data, entries } = self;
+		let directory_offset = data.len() as u32;
+		for entry in &entries {
+			push_u32(&mut data, CENTRAL_HEADER_SIGNATURE);
+			push_u16(&mut data, VERSION);
+			push_u16(&mut data, VERSION);
+			push_u16(&mut data, UTF8_FLAG);
+			push_u16(&mut data, entry.method);
+			push_u16(&mut data, DOS_TIME);
+			push_u16(&mut data, DOS_DATE);
+			push_u32(&mut data, entry.crc);
+			push_u32(&mut data, entry.compressed_size);
+			push_u32(&mut data, entry.size);
+			push_u16(&mut data, entry.name.len() as u16);
+			// Extra field, comment, and starting disk.
+			push_u16(&mut data, 0);
+			push_u16(&mut data, 0);
+			push_u16(&mut data, 0);
+			// Internal and external attributes.
+			push_u16(&mut data, 0);
+			push_u32(&mut data, 0);
+			push_u32(&mut data, entry.offset);
+			data.extend(entry.name.as_bytes());
+		}
+		let directory_size = data.len() as u32 - directory_offset;
+
+		push_u32(&mut data, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+		// This disk and the disk with the directory.
+		push_u16(&mut data, 0);
+		push_u16(&mut data, 0);
+		push_u16(&mut data, entries.len() as u16);
+		push_u16(&mut data, entries.len() as u16);
+		push_u32(&mut data, directory_size);
+		push_u32(&mut data, directory_offset);
+		// Comment length.
+		push_u16(&mut data, 0);
+		data
+	}
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, ZipError> {
+	let bytes = data.get(offset..offset + 2).ok_or(ZipError::Truncated)?;
+	Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, ZipError> {
+	let bytes = data.get(offset..offset + 4).ok_or(ZipError::Truncated)?;
+	Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads entries from a ZIP archive in memory.
+pub struct ZipReader<'a> {
+	data: &'a [u8],
+	entries: Vec<CentralEntry>,
+}
+
+impl<'a> ZipReader<'a> {
+	pub fn new(data: &'a [u8]) -> Result<Self, ZipError> {
+		// The end of central directory record is followed only by a comment of at most 64 KiB.
+		let search_start = data
+			.len()
+			.saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE + u16::MAX as usize);
+		let end = (search_start..=data.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+			.rev()
+			.find(|&offset| read_u32(data, offset) == Ok(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+			.ok_or(ZipError::NotAZip)?;
+		let count = read_u16(data, end + 10)?;
+		let mut offset = read_u32(data, end + 16)? as usize;
+
+		let mut entries = Vec::with_capacity(count as usize);
+		for _ in 0..count {
+			if read_u32(data, offset)? != CENTRAL_HEADER_SIGNATURE {
+				return Err(ZipError::NotAZip);
+			}
+			let name_length = read_u16(data, offset + 28)? as usize;
+			let extra_length = read_u16(data, offset + 30)? as usize;
+			let comment_length = read_u16(data, offset + 32)? as usize;
+			let name_start = offset + CENTRAL_HEADER_SIZE;
+			let name = data
+				.get(name_start..name_start + name_length)
+				.ok_or(ZipError::Truncated)?;
+			entries.push(CentralEntry {
+				name: String::from_utf8_lossy(name).into_owned(),
+				method: read_u16(data, offset + 10)?,
+				crc: read_u32(data, offset + 16)?,
+				compressed_size: read_u32(data, offset + 20)?,
+				size: read_u32(data, offset + 24)?,
+				offset: read_u32(data, offset + 42)?,
+			});
+			offset = name_start + name_length + extra_length + comment_length;
+		}
+		Ok(Self { data, entries })
+	}
+
+	/// The names of the entries, in the order they were written.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.entries.iter().map(|entry| entry.name.as_str())
+	}
+
+	/// The contents of the entry named `name`, or `None` if there is no such entry.
+	pub fn read(&self, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+		let entry = self.entries.iter().find(|entry| entry.name == name)?;
+		Some(self.read_entry(entry))
+	}
+
+	fn read_entry(&self, entry: &CentralEntry) -> Result<Vec<u8>, ZipError> {
+		let data = self.data;
+		let offset = entry.offset as usize;
+		if read_u32(data, offset)? != LOCAL_HEADER_SIGNATURE {
+			return Err(ZipError::Corrupt(entry.name.clone()));
+		}
+		// The local header's sizes may be deferred to a data descriptor, so the central directory's
+		// are used instead.
+		let name_length = read_u16(data, offset + 26)? as usize;
+		let extra_length = read_u16(data, offset + 28)? as usize;
+		let start = offset + LOCAL_HEADER_SIZE + name_length + extra_length;
+		let compressed = data
+			.get(start..start + entry.compressed_size as usize)
+			.ok_or(ZipError::Truncated)?;
+		let corrupt = || ZipError::Corrupt(entry.name.clone());
+		let contents = match entry.method {
+			STORED => compressed.to_vec(),
+			DEFLATED => {
+				miniz_oxide::inflate::decompress_to_vec_with_limit(compressed, entry.size as usize)
+					.map_err(|_| corrupt())?
+			}
+			method => return Err(ZipError::UnsupportedMethod(method)),
+		};
+		if contents.len() != entry.size as usize || crc32(&contents) != entry.crc {
+			return Err(corrupt());
+		}
+		Ok(contents)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn computes_crc32() {
+		assert_eq!(crc32(b""), 0);
+		assert_eq!(crc32(b"123456789"), 0xcbf43926);
+	}
+
+	#[test]
+	fn round_trip() {
+		let text = "stored and deflated ".repeat(100);
+		let mut writer = ZipWriter::default();
+		writer.add("mimetype", b"image/openraster", false);
+		writer.add("data/text.txt", text.as_bytes(), true);
+		writer.add("empty", b"", true);
+		let archive = writer.finish();
+
+		let reader = ZipReader::new(&archive).unwrap();
+		assert_eq!(
+			reader.names().collect::<Vec<_>>(),
+			["mimetype", "data/text.txt", "empty"]
+		);
+		assert_eq!(
+			reader.read("mimetype"),
+			Some(Ok(b"image/openraster".to_vec()))
+		);
+		assert_eq!(reader.read("data/text.txt"), Some(Ok(text.into_bytes())));
+		assert_eq!(reader.read("empty"), Some(Ok(Vec::new())));
+		assert_eq!(reader.read("missing"), None);
+
+		// The mimetype is stored uncompressed right after the first header, as OpenRaster requires.
+		assert_eq!(&archive[38..54], b"image/openraster");
+	}
+
+	#[test]
+	fn rejects_corruption() {
+		assert_eq!(ZipReader::new(b"not a zip").err(), Some(ZipError::NotAZip));
+
+		let mut writer = ZipWriter::default();
+		writer.add("entry", b"contents", false);
+		let mut archive = writer.finish();
+		// Flip a bit of the contents, which follow the 30-byte header and 5-byte name.
+		archive[35] ^= 1;
+		let reader = ZipReader::new(&archive).unwrap();
+		assert_eq!(
+			reader.read("entry"),
+			Some(Err(ZipError::Corrupt("entry".into())))
+		);
+	}
+}
@@ -0,0 +1,63 @@
+/// A color for telling documents apart at a glance, like the labels of a file manager.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	serde::Serialize,
+	serde::Deserialize,
+	derive_more::Display,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorTag {
+	#[display("Red")]
+	Red,
+	#[display("Orange")]
+	Orange,
+	#[display("Yellow")]
+	Yellow,
+	#[display("Green")]
+	Green,
+	#[display("Blue")]
+	Blue,
+	#[display("Purple")]
+	Purple,
+	#[display("Gray")]
+	Gray,
+}
+
+impl ColorTag {
+	pub const ALL: [Self; 7] = [
+		Self::Red,
+		Self::Orange,
+		Self::Yellow,
+		Self::Green,
+		Self::Blue,
+		Self::Purple,
+		Self::Gray,
+	];
+
+	/// The color shown for the tag, as CSS.
+	pub fn css_color(self) -> &'static str {
+		match self {
+			Self::Red => "#e5484d",
+			Self::Orange => "#f76b15",
+			Self::Yellow => "#ffc53d",
+			Self::Green => "#30a46c",
+			Self::Blue => "#0090ff",
+			Self::Purple => "#8e4ec6",
+			Self::Gray => "#8b8d98",
+		}
+	}
+}
+
+/// Organizational details of a document which don't affect how it looks. They are saved with the
+/// project, and unknown fields are ignored so that older versions can still open newer projects.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DocumentMetadata {
+	pub color_tag: Option<ColorTag>,
+	pub notes: String,
+}
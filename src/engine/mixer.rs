@@ -0,0 +1,202 @@
+use crate::engine::{CanvasFormat, Tile, CHART_SIZE, MAX_BATCHED_SEGMENTS};
+use crate::render::{self, BindingBuffer, Resources};
+use crate::shaders::mixer::*;
+use glam::Vec3;
+use std::sync::Arc;
+
+/// Draws the airbrush's strips by mixing its color with the paint already under each texel, and
+/// laying the mixture back down.
+///
+/// As with [`super::Smudge`], a chart can't be read while it is drawn to, so each chart is first
+/// copied to a source texture and then drawn from it. There is a source for each chart a batch of
+/// segments covers, created as needed and reused by later batches. Each segment of a batch has its
+/// own action.
+pub struct Mixer {
+	device: Arc<wgpu::Device>,
+	pipeline: wgpu::RenderPipeline,
+	actions: Vec<(BindingBuffer<MixerAction>, BindGroup0)>,
+	source_layout: BindGroupLayout3,
+	format: CanvasFormat,
+	sources: Vec<(wgpu::Texture, BindGroup3)>,
+}
+
+impl Mixer {
+	/// Creates the mixer for a brush of `shape_texture`, sampled with `shape_sampler`.
+	pub fn new(
+		device: &Arc<wgpu::Device>,
+		resources: &Resources,
+		format: CanvasFormat,
+		shape_texture: &wgpu::TextureView,
+		shape_sampler: &wgpu::Sampler,
+	) -> Self {
+		let pipeline_layout = resources
+			.mixer
+			.pipeline_layout()
+			.shape_texture_filterable(true)
+			.shape_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+			.selection_mask_filterable(false)
+			.mixer_source_filterable(false)
+			.get();
+		let pipeline = pipeline_layout
+			.vs_main_pipeline(wgpu::VertexStepMode::Vertex)
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: format.texture_format(),
+					// The shader mixes in what was already there itself.
+					blend: Some(wgpu::BlendState::REPLACE),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.overrides(OverrideConstants {
+				chart_ab_offset: Some(format.chart_ab_offset()),
+			})
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let actions = (0..MAX_BATCHED_SEGMENTS)
+			.map(|_| {
+				let action_buffer = BindingBuffer::new_sized()
+					.label("mixer")
+					.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+					.create(device);
+				let bind_group = render::error_scope(device, "Mixer::actions", || {
+					create_bind_group(resources, &action_buffer, shape_texture, shape_sampler)
+				});
+				(action_buffer, bind_group)
+			})
+			.collect();
+
+		Self {
+			device: device.clone(),
+			pipeline,
+			actions,
+			source_layout: render::error_scope(device, "Mixer::source_layout", || {
+				BindGroupLayout3::new(device.clone())
+			}),
+			format,
+			sources: Vec::new(),
+		}
+	}
+
+	/// Replaces the shape the brush mixes with, as [`super::Airbrush::set_shape`] does for paint.
+	pub fn set_shape(
+		&mut self,
+		resources: &Resources,
+		shape_texture: &wgpu::TextureView,
+		shape_sampler: &wgpu::Sampler,
+	) {
+		for (action_buffer, bind_group) in &mut self.actions {
+			*bind_group = create_bind_group(resources, action_buffer, shape_texture, shape_sampler);
+		}
+	}
+
+	/// Prepares to draw the `slot`th segment of a batch, mixing `color`, in Oklab, with the paint
+	/// under it by `pickup`.
+	pub fn begin_segment(&mut self, queue: &wgpu::Queue, slot: usize, color: Vec3, pickup: f32) {
+		self.actions[slot]
+			.0
+			.write(queue, MixerAction { color, pickup });
+	}
+
+	/// Makes sure there is a source for each of the `chart_count` charts a batch is drawn to.
+	pub fn reserve_sources(&mut self, chart_count: usize) {
+		while self.sources.len() < chart_count {
+			let texture = render::texture()
+				.label("Mixer::source")
+				.width(CHART_SIZE)
+				.height(CHART_SIZE)
+				.format(self.format.texture_format())
+				.usage(wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING)
+				.create(&self.device);
+			let bind_group = render::error_scope(&self.device, "Mixer::source", || {
+				let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+				self.source_layout.bind_group().mixer_source(&view).create()
+			});
+			self.sources.push((texture, bind_group));
+		}
+	}
+
+	/// Records a copy of `tile`, the `index`th chart the batch is drawn to, for [`Self::set`] to read.
+	/// Each segment must copy the chart again, after the previous segment has drawn to it.
+	pub fn copy_source(&self, encoder: &mut wgpu::CommandEncoder, tile: &Tile, index: usize) {
+		tile.copy_texture_to(encoder, &self.sources[index].0);
+	}
+
+	/// Sets the pipeline and bind groups other than the chart's and the selection's to draw the
+	/// `slot`th segment to the `index`th chart.
+	pub fn set(&self, render_pass: &mut wgpu::RenderPass<'_>, slot: usize, index: usize) {
+		render_pass.set_pipeline(&self.pipeline);
+		self.actions[slot].1.set(render_pass);
+		self.sources[index].1.set(render_pass);
+	}
+}
+
+fn create_bind_group(
+	resources: &Resources,
+	action_buffer: &BindingBuffer<MixerAction>,
+	shape_texture: &wgpu::TextureView,
+	shape_sampler: &wgpu::Sampler,
+) -> BindGroup0 {
+	// This must match the layout the pipeline was created with in `Mixer::new`.
+	resources
+		.mixer
+		.pipeline_layout()
+		.shape_texture_filterable(true)
+		.shape_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+		.selection_mask_filterable(false)
+		.mixer_source_filterable(false)
+		.get()
+		.bind_group_layouts()
+		.0
+		.bind_group()
+		.action(action_buffer.as_entire_buffer_binding())
+		.shape_texture(shape_texture)
+		.shape_sampler(shape_sampler)
+		.create()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{Airbrush, Atlas, BrushMode};
+	use crate::test;
+	use glam::*;
+
+	#[test]
+	fn mixer_blends_with_paint_under_it() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Resources::new(device);
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let color = vec4(0.6, 0.1, -0.05, 1.0);
+		test::paint_left_half(&mut atlas, color);
+
+		// Mix across the edge of the paint in a single segment, picking up half of it. Each segment
+		// mixes again, so overlapping ones would approach the brush's color.
+		let brush_color = vec3(0.3, -0.1, 0.1);
+		let mut airbrush = Airbrush::new(device, queue, &resources, format);
+		airbrush.set_mode(BrushMode::Mix);
+		airbrush.set_pickup(0.5);
+		let points = test::segment_across_paint_edge(test::input_point(16.0, brush_color));
+		airbrush.draw_stroke(queue, &mut atlas, points)?;
+
+		let sample = |point| pollster::block_on(atlas.sample(point));
+		// Over the paint, the color moves towards the brush's, but no further than halfway.
+		let mixed = sample(vec2(112.5, 128.5))?;
+		assert!(mixed.xyz().distance(color.xyz()) > 0.05, "{mixed}");
+		assert!(mixed.xyz().distance(brush_color) > 0.15, "{mixed}");
+		// There is nothing to pick up from bare canvas, so the brush lays down its own color.
+		let laid = sample(vec2(144.5, 128.5))?;
+		assert!(laid.w > 0.1, "{laid}");
+		assert!(laid.xyz().abs_diff_eq(brush_color, 0.02), "{laid}");
+		// Nothing changes away from the stroke.
+		let untouched = sample(vec2(100.5, 200.5))?;
+		assert!(untouched.abs_diff_eq(color, 1e-2), "{untouched}");
+		Ok(())
+	}
+}
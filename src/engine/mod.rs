@@ -1,17 +1,114 @@
+//! The painting engine: an unbounded canvas stored as an [`Atlas`] of fixed-size charts, the brushes
+//! that paint to it, and its history, persistence, and import and export.
+
 // mod stroke;
 // pub use stroke::*;
 
+mod action_log;
+pub use action_log::*;
+
 mod atlas;
 pub use atlas::*;
 
+mod bounds;
+pub use bounds::*;
+
+mod canvas_format;
+pub use canvas_format::*;
+
+mod canvas_renderer;
+pub use canvas_renderer::*;
+
+mod cursor_renderer;
+pub use cursor_renderer::*;
+
+mod stroke_tip_renderer;
+pub use stroke_tip_renderer::*;
+
+mod bounds_renderer;
+pub use bounds_renderer::*;
+
+mod guides_renderer;
+pub use guides_renderer::*;
+
+mod export;
+pub use export::*;
+
+mod history;
+pub use history::*;
+
+mod checkpoints;
+pub use checkpoints::*;
+
+mod floating;
+pub use floating::*;
+
+mod import;
+pub use import::*;
+
+mod persistence;
+pub use persistence::*;
+
+mod metadata;
+pub use metadata::*;
+
+mod presets;
+pub use presets::*;
+
 mod tile;
 pub use tile::*;
 
 mod airbrush;
 pub use airbrush::*;
 
+mod brush_shape;
+pub use brush_shape::*;
+
+mod symmetry;
+pub use symmetry::*;
+
+mod selection;
+pub use selection::*;
+
+mod shape_tool;
+pub use shape_tool::*;
+
+mod guides;
+pub use guides::*;
+
+mod pen_path;
+pub use pen_path::*;
+
+mod smudge;
+pub use smudge::*;
+
+mod mixer;
+pub use mixer::*;
+
+mod brush_preview;
+pub use brush_preview::*;
+
 pub mod raster;
 
+pub mod io;
+
+mod vector_drawing;
+pub use vector_drawing::*;
+
+mod pick;
+pub use pick::*;
+
+mod timelapse;
+pub use timelapse::*;
+
+mod workload;
+pub use workload::*;
+
 mod embedded_shapes;
 
 mod process_shape;
+
+mod versioned;
+
+#[cfg(test)]
+mod end_to_end;
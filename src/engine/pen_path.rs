@@ -0,0 +1,172 @@
+use super::VectorSegment;
+use crate::util::clothoid::Clothoid;
+use glam::*;
+
+/// How close an anchor may be to the one before it, in canvas units. Closer anchors are dropped,
+/// as there would be no curve to fit between them.
+const MIN_ANCHOR_SPACING: f32 = 1.0;
+
+/// The tolerance the clothoid between each pair of anchors is fit to.
+const FIT_TOLERANCE: f32 = 1e-4;
+
+/// The most the direction of the path turns between consecutive samples of it, in radians.
+const SAMPLE_TURN: f32 = 0.05;
+
+/// A path placed anchor by anchor with the pen tool. It passes smoothly through each anchor, along
+/// clothoids fit between them, and is stroked with the brush once finished.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PenPath {
+	anchors: Vec<Vec2>,
+}
+
+impl PenPath {
+	/// The anchors placed so far, in canvas coordinates.
+	pub fn anchors(&self) -> &[Vec2] {
+		&self.anchors
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.anchors.is_empty()
+	}
+
+	/// Places an anchor at the end of the path. Returns whether it was placed, which it isn't if it
+	/// is too close to the last.
+	pub fn push(&mut self, anchor: Vec2) -> bool {
+		if let Some(last) = self.anchors.last() {
+			if last.distance(anchor) < MIN_ANCHOR_SPACING {
+				return false;
+			}
+		}
+		self.anchors.push(anchor);
+		true
+	}
+
+	/// Removes the last anchor placed.
+	pub fn pop(&mut self) -> Option<Vec2> {
+		self.anchors.pop()
+	}
+
+	/// The direction of the path through each anchor, as an angle. Within the path, it is parallel
+	/// to the chord between the neighboring anchors. At either end, it mirrors the direction at the
+	/// neighboring anchor about the chord to it, so that the first and last spans bend as little
+	/// as the rest allow.
+	fn tangents(&self) -> Vec<f32> {
+		let anchors = &self.anchors;
+		let n = anchors.len();
+		if n < 2 {
+			return vec![0.0; n];
+		}
+		let chord = |i: usize| (anchors[i + 1] - anchors[i]).to_angle();
+		if n == 2 {
+			return vec![chord(0); 2];
+		}
+		let inner: Vec<f32> = (1..n - 1)
+			.map(|i| (anchors[i + 1] - anchors[i - 1]).to_angle())
+			.collect();
+		let first = 2.0 * chord(0) - inner[0];
+		let last = 2.0 * chord(n - 2) - inner[inner.len() - 1];
+		std::iter::once(first)
+			.chain(inner)
+			.chain(std::iter::once(last))
+			.collect()
+	}
+
+	/// The path as segments through canvas coordinates with a constant `pressure`, or `None` if
+	/// it has fewer than two anchors to draw between.
+	pub fn segments(&self, pressure: f32) -> Option<Vec<VectorSegment>> {
+		if self.anchors.len() < 2 {
+			return None;
+		}
+		let tangents = self.tangents();
+		let mut segments = Vec::new();
+		for (anchors, tangents) in self.anchors.windows(2).zip(tangents.windows(2)) {
+			let clothoid = Clothoid::fit_g1(
+				anchors[0],
+				tangents[0],
+				anchors[1],
+				tangents[1],
+				FIT_TOLERANCE,
+			);
+			let samples = clothoid.sample(SAMPLE_TURN);
+			// Between samples, the clothoid is closely followed by the cubic with the same ends and
+			// directions.
+			segments.extend(samples.windows(2).map(|pair| {
+				let (start, end) = (pair[0].position(), pair[1].position());
+				let handle = start.distance(end) / 3.0;
+				let points = [
+					start,
+					start + handle * Vec2::from_angle(pair[0].theta()),
+					end - handle * Vec2::from_angle(pair[1].theta()),
+					end,
+				];
+				VectorSegment {
+					points: points.map(|p| p.extend(pressure)),
+					tilt: Vec2::ZERO,
+				}
+			}));
+			// The fit ends within its tolerance of the anchor, but the next span starts exactly on it.
+			if let Some(last) = segments.last_mut() {
+				last.points[3] = anchors[1].extend(pressure);
+			}
+		}
+		Some(segments)
+	}
+
+	/// The path as a polyline through canvas coordinates, for previewing it, continued to `hover`
+	/// as though it were the next anchor. `None` if there is nothing to draw.
+	pub fn outline(&self, hover: Option<Vec2>) -> Option<Vec<Vec2>> {
+		let mut path = self.clone();
+		if let Some(hover) = hover {
+			path.push(hover);
+		}
+		let segments = path.segments(1.0)?;
+		let first = segments.first()?.points[0].xy();
+		let rest = segments.iter().map(|segment| segment.points[3].xy());
+		Some(std::iter::once(first).chain(rest).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn passes_through_anchors() {
+		let mut path = PenPath::default();
+		assert!(path.push(vec2(0.0, 0.0)));
+		assert!(!path.push(vec2(0.5, 0.0)));
+		assert_eq!(path.segments(1.0), None);
+		assert!(path.push(vec2(100.0, 50.0)));
+		assert!(path.push(vec2(200.0, 0.0)));
+		assert!(path.push(vec2(300.0, 80.0)));
+
+		let segments = path.segments(0.5).unwrap();
+		for (a, b) in segments.iter().zip(segments.iter().skip(1)) {
+			assert_eq!(a.points[3], b.points[0]);
+		}
+		assert!(segments.iter().all(|s| s.points.iter().all(|p| p.z == 0.5)));
+		// Each anchor is the end of some segment.
+		for anchor in &path.anchors()[1..] {
+			assert!(
+				segments.iter().any(|s| s.points[3].xy() == *anchor),
+				"{anchor}"
+			);
+		}
+		assert_eq!(segments[0].points[0].xy(), path.anchors()[0]);
+
+		assert_eq!(path.pop(), Some(vec2(300.0, 80.0)));
+		assert_eq!(path.anchors().len(), 3);
+	}
+
+	#[test]
+	fn straight_paths_stay_straight() {
+		let mut path = PenPath::default();
+		path.push(vec2(0.0, 0.0));
+		let outline = path.outline(Some(vec2(100.0, 100.0))).unwrap();
+		assert!(outline.len() >= 2);
+		for p in outline {
+			assert!((p.x - p.y).abs() < 1e-2, "{p}");
+		}
+		assert_eq!(path.outline(None), None);
+	}
+}
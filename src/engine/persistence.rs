@@ -0,0 +1,413 @@
+use super::{CanvasFormat, ChartKey, DocumentBounds, DocumentMetadata, CHART_SIZE};
+use crate::shaders::TileData;
+use glam::*;
+
+/// Identifies an encoded [`SavedChart`].
+const MAGIC: [u8; 4] = *b"STKC";
+const VERSION: u32 = 1;
+
+/// Identifies an encoded [`ProjectFile`].
+const PROJECT_MAGIC: [u8; 4] = *b"STKP";
+/// Version 2 added the document's metadata after the charts, and version 3 its bounds after that.
+const PROJECT_VERSION: u32 = 3;
+
+/// The extension of files holding an encoded [`ProjectFile`].
+pub const PROJECT_FILE_EXTENSION: &str = "stark";
+
+/// How hard to work at compressing texture data, from 0 to 10. Most charts are largely transparent,
+/// so even fast settings compress them well.
+const COMPRESSION_LEVEL: u8 = 6;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum PersistenceError {
+	#[error("saved chart is truncated")]
+	Truncated,
+	#[error("not a saved chart")]
+	NotAChart,
+	#[error("not a project file")]
+	NotAProject,
+	#[error("unsupported saved chart version {0}")]
+	UnsupportedVersion(u32),
+	#[error("unknown canvas format {0}")]
+	UnknownFormat(u32),
+	#[error("saved chart texture data is corrupt")]
+	Corrupt,
+	#[error("project metadata is malformed: {0}")]
+	MalformedMetadata(String),
+	#[error("project bounds are malformed: {0}")]
+	MalformedBounds(String),
+}
+
+/// The fixed-size prefix of an encoded chart, followed by the compressed texture data.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Header {
+	magic: [u8; 4],
+	version: u32,
+	format: u32,
+	key: [i32; 2],
+	chart_to_canvas_scale: [f32; 2],
+	chart_to_canvas_translation: [f32; 2],
+	chart_offset: [i32; 2],
+}
+
+/// A chart's contents in a form that can be stored outside of the GPU.
+#[derive(Debug, Clone)]
+pub struct SavedChart {
+	pub key: ChartKey,
+	pub format: CanvasFormat,
+	pub tile_data: TileData,
+	/// The texture, tightly packed in `format`.
+	pub texture_data: Vec<u8>,
+}
+
+impl SavedChart {
+	pub fn encode(&self) -> Vec<u8> {
+		let header = Header {
+			magic: MAGIC,
+			version: VERSION,
+			format: self.format.index(),
+			key: [self.key.0, self.key.1],
+			chart_to_canvas_scale: self.tile_data.chart_to_canvas_scale.into(),
+			chart_to_canvas_translation: self.tile_data.chart_to_canvas_translation.into(),
+			chart_offset: self.tile_data.chart_offset.into(),
+		};
+		let mut encoded = bytemuck::bytes_of(&header).to_vec();
+		encoded.extend(miniz_oxide::deflate::compress_to_vec(
+			&self.texture_data,
+			COMPRESSION_LEVEL,
+		));
+		encoded
+	}
+
+	pub fn decode(encoded: &[u8]) -> Result<Self, PersistenceError> {
+		let header_size = std::mem::size_of::<Header>();
+		if encoded.len() < header_size {
+			return Err(PersistenceError::Truncated);
+		}
+		let (header, compressed) = encoded.split_at(header_size);
+		let header: Header = bytemuck::pod_read_unaligned(header);
+		if header.magic != MAGIC {
+			return Err(PersistenceError::NotAChart);
+		}
+		if header.version != VERSION {
+			return Err(PersistenceError::UnsupportedVersion(header.version));
+		}
+		let format = CanvasFormat::from_index(header.format)
+			.ok_or(PersistenceError::UnknownFormat(header.format))?;
+
+		let expected_len = texture_data_len(format);
+		let texture_data =
+			miniz_oxide::inflate::decompress_to_vec_with_limit(compressed, expected_len)
+				.map_err(|_| PersistenceError::Corrupt)?;
+		if texture_data.len() != expected_len {
+			return Err(PersistenceError::Corrupt);
+		}
+
+		Ok(Self {
+			key: ChartKey(header.key[0], header.key[1]),
+			format,
+			tile_data: TileData {
+				chart_to_canvas_scale: header.chart_to_canvas_scale.into(),
+				chart_to_canvas_translation: header.chart_to_canvas_translation.into(),
+				chart_offset: header.chart_offset.into(),
+			},
+			texture_data,
+		})
+	}
+
+	/// Re-encodes the texture data in `format`.
+	pub fn converted_to(self, format: CanvasFormat) -> Self {
+		if format == self.format {
+			return self;
+		}
+		let pixels = self.format.decode_pixels(&self.texture_data);
+		Self {
+			format,
+			texture_data: format.encode_pixels(&pixels),
+			..self
+		}
+	}
+}
+
+/// The fixed-size prefix of an encoded project, followed by each chart's length and encoding, then
+/// the length of the metadata and the metadata as JSON, then likewise the bounds.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ProjectHeader {
+	magic: [u8; 4],
+	version: u32,
+	format: u32,
+	chart_count: u32,
+}
+
+/// A whole document in a single file, for opening it elsewhere.
+#[derive(Debug, Clone)]
+pub struct ProjectFile {
+	pub format: CanvasFormat,
+	pub charts: Vec<SavedChart>,
+	pub metadata: DocumentMetadata,
+	/// What the document is cropped to, or `None` if it is unbounded.
+	pub bounds: Option<DocumentBounds>,
+}
+
+impl ProjectFile {
+	pub fn encode(&self) -> Vec<u8> {
+		let header = ProjectHeader {
+			magic: PROJECT_MAGIC,
+			version: PROJECT_VERSION,
+			format: self.format.index(),
+			chart_count: self.charts.len() as u32,
+		};
+		let mut encoded = bytemuck::bytes_of(&header).to_vec();
+		for chart in &self.charts {
+			let chart = chart.encode();
+			encoded.extend_from_slice(bytemuck::bytes_of(&(chart.len() as u32)));
+			encoded.extend(chart);
+		}
+		for json in [
+			serde_json::to_vec(&self.metadata).unwrap(),
+			serde_json::to_vec(&self.bounds).unwrap(),
+		] {
+			encoded.extend_from_slice(bytemuck::bytes_of(&(json.len() as u32)));
+			encoded.extend(json);
+		}
+		encoded
+	}
+
+	/// Decodes a project. Charts saved in another format are converted to the project's.
+	pub fn decode(encoded: &[u8]) -> Result<Self, PersistenceError> {
+		let (header, mut rest) = split_pod::<ProjectHeader>(encoded)?;
+		if header.magic != PROJECT_MAGIC {
+			return Err(PersistenceError::NotAProject);
+		}
+		if !(1..=PROJECT_VERSION).contains(&header.version) {
+			return Err(PersistenceError::UnsupportedVersion(header.version));
+		}
+		let format = CanvasFormat::from_index(header.format)
+			.ok_or(PersistenceError::UnknownFormat(header.format))?;
+
+		let mut charts = Vec::new();
+		for _ in 0..header.chart_count {
+			let (len, after_len) = split_pod::<u32>(rest)?;
+			if after_len.len() < len as usize {
+				return Err(PersistenceError::Truncated);
+			}
+			let (chart, after_chart) = after_len.split_at(len as usize);
+			charts.push(SavedChart::decode(chart)?.converted_to(format));
+			rest = after_chart;
+		}
+
+		let metadata = if header.version < 2 {
+			DocumentMetadata::default()
+		} else {
+			let (metadata, after_metadata) = split_prefixed(rest)?;
+			rest = after_metadata;
+			serde_json::from_slice(metadata)
+				.map_err(|err| PersistenceError::MalformedMetadata(err.to_string()))?
+		};
+		let bounds = if header.version < 3 {
+			None
+		} else {
+			let (bounds, _) = split_prefixed(rest)?;
+			serde_json::from_slice(bounds)
+				.map_err(|err| PersistenceError::MalformedBounds(err.to_string()))?
+		};
+		Ok(Self {
+			format,
+			charts,
+			metadata,
+			bounds,
+		})
+	}
+}
+
+/// Reads a `T` from the start of `encoded`, returning it and the bytes following it.
+fn split_pod<T: bytemuck::Pod>(encoded: &[u8]) -> Result<(T, &[u8]), PersistenceError> {
+	let size = std::mem::size_of::<T>();
+	if encoded.len() < size {
+		return Err(PersistenceError::Truncated);
+	}
+	let (value, rest) = encoded.split_at(size);
+	Ok((bytemuck::pod_read_unaligned(value), rest))
+}
+
+/// Reads a length followed by that many bytes from the start of `encoded`, returning the bytes and
+/// those following them.
+fn split_prefixed(encoded: &[u8]) -> Result<(&[u8], &[u8]), PersistenceError> {
+	let (len, rest) = split_pod::<u32>(encoded)?;
+	if rest.len() < len as usize {
+		return Err(PersistenceError::Truncated);
+	}
+	Ok(rest.split_at(len as usize))
+}
+
+/// The name under which the chart at `key` is stored.
+pub fn storage_key(key: ChartKey) -> String {
+	format!("{},{}", key.0, key.1)
+}
+
+fn texture_data_len(format: CanvasFormat) -> usize {
+	format.clear_pixel().len() * (CHART_SIZE * CHART_SIZE) as usize
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::ColorTag;
+
+	fn test_chart(format: CanvasFormat) -> SavedChart {
+		let key = ChartKey(-2, 5);
+		let pixels: Vec<Vec4> = (0..CHART_SIZE * CHART_SIZE)
+			.map(|i| {
+				let t = (i % CHART_SIZE) as f32 / CHART_SIZE as f32;
+				vec4(t, 0.25 * t - 0.1, -0.2 * t, t * t)
+			})
+			.collect();
+		SavedChart {
+			key,
+			format,
+			tile_data: key.tile_data(),
+			texture_data: format.encode_pixels(&pixels),
+		}
+	}
+
+	#[test]
+	fn encode_round_trip() {
+		for format in CanvasFormat::ALL {
+			let chart = test_chart(format);
+			let encoded = chart.encode();
+			assert!(encoded.len() < chart.texture_data.len(), "{format:?}");
+
+			let decoded = SavedChart::decode(&encoded).unwrap();
+			assert_eq!(decoded.key, chart.key);
+			assert_eq!(decoded.format, format);
+			assert_eq!(decoded.tile_data.chart_offset, chart.tile_data.chart_offset);
+			assert_eq!(
+				decoded.tile_data.chart_to_canvas_scale,
+				chart.tile_data.chart_to_canvas_scale
+			);
+			assert!(decoded.texture_data == chart.texture_data, "{format:?}");
+		}
+	}
+
+	#[test]
+	fn decode_errors() {
+		let encoded = test_chart(CanvasFormat::Rgba8Unorm).encode();
+		assert_eq!(
+			SavedChart::decode(&encoded[..8]).unwrap_err(),
+			PersistenceError::Truncated
+		);
+
+		let mut not_a_chart = encoded.clone();
+		not_a_chart[0] = b'X';
+		assert_eq!(
+			SavedChart::decode(&not_a_chart).unwrap_err(),
+			PersistenceError::NotAChart
+		);
+
+		let mut future_version = encoded.clone();
+		future_version[4..8].copy_from_slice(&2u32.to_ne_bytes());
+		assert_eq!(
+			SavedChart::decode(&future_version).unwrap_err(),
+			PersistenceError::UnsupportedVersion(2)
+		);
+
+		let mut unknown_format = encoded.clone();
+		unknown_format[8..12].copy_from_slice(&7u32.to_ne_bytes());
+		assert_eq!(
+			SavedChart::decode(&unknown_format).unwrap_err(),
+			PersistenceError::UnknownFormat(7)
+		);
+
+		let truncated_data = &encoded[..encoded.len() - 16];
+		assert_eq!(
+			SavedChart::decode(truncated_data).unwrap_err(),
+			PersistenceError::Corrupt
+		);
+	}
+
+	#[test]
+	fn project_round_trip() {
+		let project = ProjectFile {
+			format: CanvasFormat::Rgba16Float,
+			charts: vec![
+				test_chart(CanvasFormat::Rgba16Float),
+				SavedChart {
+					key: ChartKey(3, 0),
+					..test_chart(CanvasFormat::Rgba8Unorm)
+				},
+			],
+			metadata: DocumentMetadata {
+				color_tag: Some(ColorTag::Green),
+				notes: "Finish the sky.".into(),
+			},
+			bounds: Some(DocumentBounds {
+				origin: ivec2(-300, 40),
+				size: uvec2(1920, 1080),
+			}),
+		};
+		let encoded = project.encode();
+		let decoded = ProjectFile::decode(&encoded).unwrap();
+		assert_eq!(decoded.format, project.format);
+		assert_eq!(decoded.metadata, project.metadata);
+		assert_eq!(decoded.bounds, project.bounds);
+		assert_eq!(
+			decoded.charts.iter().map(|c| c.key).collect::<Vec<_>>(),
+			[ChartKey(-2, 5), ChartKey(3, 0)]
+		);
+		// Every chart is converted to the project's format.
+		assert!(decoded
+			.charts
+			.iter()
+			.all(|c| c.format == CanvasFormat::Rgba16Float));
+
+		for len in [8, 20, encoded.len() - 1] {
+			assert_eq!(
+				ProjectFile::decode(&encoded[..len]).unwrap_err(),
+				PersistenceError::Truncated
+			);
+		}
+		// Projects from before bounds were saved are unbounded.
+		let bounds_len = serde_json::to_vec(&project.bounds).unwrap().len() + 4;
+		let mut version_2 = encoded[..encoded.len() - bounds_len].to_vec();
+		version_2[4..8].copy_from_slice(&2u32.to_ne_bytes());
+		let decoded = ProjectFile::decode(&version_2).unwrap();
+		assert_eq!(decoded.metadata, project.metadata);
+		assert_eq!(decoded.bounds, None);
+
+		// Projects from before metadata was saved have none.
+		let metadata_len = serde_json::to_vec(&project.metadata).unwrap().len() + 4;
+		let mut version_1 = version_2[..version_2.len() - metadata_len].to_vec();
+		version_1[4..8].copy_from_slice(&1u32.to_ne_bytes());
+		let decoded = ProjectFile::decode(&version_1).unwrap();
+		assert_eq!(decoded.charts.len(), 2);
+		assert_eq!(decoded.metadata, DocumentMetadata::default());
+
+		// Charts aren't projects, even though both start with a magic number.
+		assert_eq!(
+			ProjectFile::decode(&test_chart(CanvasFormat::Rgba8Unorm).encode()).unwrap_err(),
+			PersistenceError::NotAProject
+		);
+	}
+
+	#[test]
+	fn convert_between_formats() {
+		let chart = test_chart(CanvasFormat::Rgba32Float);
+		let expected = CanvasFormat::Rgba32Float.decode_pixels(&chart.texture_data);
+		for format in CanvasFormat::ALL {
+			let converted = chart.clone().converted_to(format);
+			assert_eq!(converted.format, format);
+			assert_eq!(converted.texture_data.len(), texture_data_len(format));
+			let tolerance = match format {
+				CanvasFormat::Rgba8Unorm => 0.5 / 255.0 + 1e-6,
+				CanvasFormat::Rgba16Float => 1e-3,
+				CanvasFormat::Rgba32Float => 0.0,
+			};
+			let actual = format.decode_pixels(&converted.texture_data);
+			for (a, e) in actual.iter().zip(&expected) {
+				assert!(a.abs_diff_eq(*e, tolerance), "{format:?}: {a} != {e}");
+			}
+		}
+	}
+}
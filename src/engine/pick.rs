@@ -0,0 +1,96 @@
+use super::{Atlas, ChartKey, StrokeId, TileLayer, VectorDrawing};
+use crate::coordinates::{CanvasPt, ScreenPx};
+use crate::view_transform::ViewTransform;
+
+// Tools and overlays acting on whatever is under the pointer, such as the eyedropper and selection,
+// find it here rather than each mapping the pointer through the view and on to a chart themselves.
+
+/// What is under a point on screen, from [`pick`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickResult {
+	/// The point of the canvas under the screen point.
+	pub point: CanvasPt,
+	/// The chart containing the point, whether or not anything has been drawn to it.
+	pub chart: ChartKey,
+	/// Where the chart is held in the atlas' tile pool, if it has been drawn to and is resident.
+	pub layer: Option<TileLayer>,
+	/// The latest stroke drawn over the point, if the strokes were given.
+	pub stroke: Option<StrokeId>,
+}
+
+/// Finds what is under `screen_point` on a canvas showing `atlas` through `view`. Strokes are found
+/// in `vector_drawing`, which takes time proportional to its length, so callers which don't need
+/// them should pass `None`.
+pub fn pick(
+	view: &ViewTransform,
+	atlas: &Atlas,
+	vector_drawing: Option<&VectorDrawing>,
+	screen_point: ScreenPx,
+) -> PickResult {
+	let point = screen_point.to_canvas(view);
+	let chart = point.chart();
+	PickResult {
+		point,
+		chart,
+		layer: atlas
+			.get_chart(&chart)
+			.and_then(|chart| chart.tile().layer()),
+		stroke: vector_drawing.and_then(|drawing| drawing.stroke_at(point.as_vec2())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{
+		BrushMode, CanvasFormat, Symmetry, VectorEdit, VectorSegment, VectorStroke,
+		DEFAULT_BRUSH_SPACING,
+	};
+	use crate::test;
+	use glam::*;
+
+	#[test]
+	fn picks_through_the_view() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		atlas.get_chart_mut(ChartKey(1, 0));
+		let mut drawing = VectorDrawing::default();
+		drawing.record(VectorEdit::Stroke(VectorStroke {
+			color: Vec3::ONE,
+			size: 8.0,
+			opacity: 1.0,
+			rate: 1.0,
+			mode: BrushMode::Paint,
+			spacing: DEFAULT_BRUSH_SPACING,
+			scatter: 0.0,
+			grain: 0.0,
+			pickup: 0.0,
+			symmetry: Symmetry::default(),
+			seed: 0,
+			segments: vec![VectorSegment {
+				points: [300.0, 330.0, 370.0, 400.0].map(|x| vec3(x, 100.0, 1.0)),
+				tilt: Vec2::ZERO,
+			}],
+		}));
+
+		// Zoomed out, so screen points are twice as far apart on the canvas.
+		let mut view = ViewTransform::default();
+		view.zoom_about(DVec2::ZERO, 0.5);
+		let picked = pick(&view, &atlas, Some(&drawing), ScreenPx::new(175.0, 50.0));
+		assert_eq!(picked.point, CanvasPt::new(350.0, 100.0));
+		assert_eq!(picked.chart, ChartKey(1, 0));
+		assert!(picked.layer.is_some());
+		assert_eq!(picked.stroke, Some(StrokeId(0)));
+
+		// Strokes are only found when asked for.
+		let picked = pick(&view, &atlas, None, ScreenPx::new(175.0, 50.0));
+		assert_eq!(picked.stroke, None);
+
+		// Charts which haven't been drawn to have no layer.
+		let picked = pick(&view, &atlas, Some(&drawing), ScreenPx::new(-10.0, 50.0));
+		assert_eq!(picked.chart, ChartKey(-1, 0));
+		assert_eq!(picked.layer, None);
+		assert_eq!(picked.stroke, None);
+		Ok(())
+	}
+}
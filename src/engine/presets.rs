@@ -0,0 +1,406 @@
+use super::versioned::{self, Versioned, VersionedError};
+use super::{
+	BrushMode, StampProfile, DEFAULT_BRUSH_PICKUP, DEFAULT_BRUSH_SHAPE, DEFAULT_BRUSH_SPACING,
+	MIN_STAMP_SPREAD, ROUND_BRUSH_SHAPE,
+};
+use glam::*;
+use std::ops::RangeInclusive;
+
+/// The version of preset files written by this build. Older versions are read, newer ones are not.
+pub const PRESETS_VERSION: u32 = 1;
+
+/// The brush shapes presets may refer to. Presets only refer to built-in shapes, so shared presets
+/// never depend on files the recipient doesn't have.
+pub const BUILT_IN_SHAPES: [&str; 2] = [DEFAULT_BRUSH_SHAPE, ROUND_BRUSH_SHAPE];
+
+pub const PRESET_SIZE_RANGE: RangeInclusive<f64> = 1.0..=1024.0;
+pub const PRESET_OPACITY_RANGE: RangeInclusive<f64> = 0.0..=2.0;
+pub const PRESET_RATE_RANGE: RangeInclusive<f64> = 0.0..=100.0;
+pub const PRESET_SMOOTHING_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+pub const PRESET_SPACING_RANGE: RangeInclusive<f64> = 0.01..=1.0;
+pub const PRESET_SCATTER_RANGE: RangeInclusive<f64> = 0.0..=2.0;
+pub const PRESET_GRAIN_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+pub const PRESET_PICKUP_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+pub const PRESET_HARDNESS_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+pub const PRESET_SPREAD_RANGE: RangeInclusive<f64> = MIN_STAMP_SPREAD as f64..=1.0;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum PresetError {
+	#[error("malformed preset file: {0}")]
+	Malformed(String),
+	#[error("unsupported preset file version {0}")]
+	UnsupportedVersion(u32),
+	#[error("presets and palettes must be named")]
+	Unnamed,
+	#[error("unknown brush shape {0:?}")]
+	UnknownShape(String),
+	#[error("{name} {value} of {preset:?} is outside of [{min}, {max}]")]
+	OutOfRange {
+		preset: String,
+		name: &'static str,
+		value: f64,
+		min: f64,
+		max: f64,
+	},
+	#[error("{color} in {palette:?} is not an Oklab color")]
+	InvalidColor { palette: String, color: Vec3 },
+}
+
+impl From<VersionedError> for PresetError {
+	fn from(err: VersionedError) -> Self {
+		match err {
+			VersionedError::Malformed(err) => Self::Malformed(err),
+			VersionedError::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+		}
+	}
+}
+
+/// Named brush settings.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BrushPreset {
+	pub name: String,
+	/// One of [`BUILT_IN_SHAPES`].
+	pub shape: String,
+	/// The brush diameter, in canvas pixels.
+	pub size: f64,
+	pub opacity: f64,
+	pub rate: f64,
+	pub smoothing: f64,
+	/// See [`super::Airbrush::set_spacing`]. Presets from before this was configurable used the
+	/// default.
+	#[serde(default = "default_spacing")]
+	pub spacing: f64,
+	/// See [`super::Airbrush::set_scatter`]. Presets from before this was configurable didn't
+	/// scatter.
+	#[serde(default)]
+	pub scatter: f64,
+	/// See [`super::Airbrush::set_grain`]. Presets from before this was configurable had no grain.
+	#[serde(default)]
+	pub grain: f64,
+	/// See [`super::StampProfile`]. Presets from before these were configurable sprayed their shape
+	/// as it is.
+	#[serde(default = "default_hardness")]
+	pub hardness: f64,
+	#[serde(default = "default_spread")]
+	pub spread: f64,
+	/// Presets from before this was configurable painted.
+	#[serde(default)]
+	pub mode: BrushMode,
+	/// See [`super::Airbrush::set_pickup`]. Presets from before this was configurable couldn't mix,
+	/// so they use the default.
+	#[serde(default = "default_pickup")]
+	pub pickup: f64,
+}
+
+fn default_spacing() -> f64 {
+	DEFAULT_BRUSH_SPACING as f64
+}
+
+fn default_pickup() -> f64 {
+	DEFAULT_BRUSH_PICKUP as f64
+}
+
+fn default_hardness() -> f64 {
+	StampProfile::default().hardness as f64
+}
+
+fn default_spread() -> f64 {
+	StampProfile::default().spread as f64
+}
+
+/// The presets every user starts with.
+pub fn built_in_brush_presets() -> Vec<BrushPreset> {
+	let airbrush = BrushPreset {
+		name: "Airbrush".into(),
+		shape: DEFAULT_BRUSH_SHAPE.into(),
+		size: 256.0,
+		opacity: 1.0,
+		rate: 25.0,
+		smoothing: 0.0,
+		spacing: default_spacing(),
+		scatter: 0.0,
+		grain: 0.0,
+		hardness: default_hardness(),
+		spread: default_spread(),
+		mode: BrushMode::Paint,
+		pickup: default_pickup(),
+	};
+	vec![
+		BrushPreset {
+			name: "Wash".into(),
+			size: 1024.0,
+			opacity: 0.3,
+			rate: 10.0,
+			smoothing: 0.2,
+			..airbrush.clone()
+		},
+		BrushPreset {
+			name: "Ink".into(),
+			shape: ROUND_BRUSH_SHAPE.into(),
+			size: 16.0,
+			opacity: 2.0,
+			rate: 100.0,
+			smoothing: 0.5,
+			spacing: 0.02,
+			..airbrush.clone()
+		},
+		BrushPreset {
+			name: "Pencil".into(),
+			shape: ROUND_BRUSH_SHAPE.into(),
+			size: 4.0,
+			opacity: 0.8,
+			rate: 50.0,
+			smoothing: 0.1,
+			..airbrush.clone()
+		},
+		BrushPreset {
+			name: "Smudge".into(),
+			size: 144.0,
+			mode: BrushMode::Smudge,
+			..airbrush.clone()
+		},
+		BrushPreset {
+			name: "Wet Mix".into(),
+			shape: ROUND_BRUSH_SHAPE.into(),
+			size: 64.0,
+			opacity: 0.6,
+			rate: 50.0,
+			smoothing: 0.2,
+			mode: BrushMode::Mix,
+			pickup: 0.6,
+			..airbrush.clone()
+		},
+		airbrush,
+	]
+}
+
+impl BrushPreset {
+	pub fn stamp_profile(&self) -> StampProfile {
+		StampProfile {
+			hardness: self.hardness as f32,
+			spread: self.spread as f32,
+		}
+	}
+
+	pub fn validate(&self) -> Result<(), PresetError> {
+		if self.name.trim().is_empty() {
+			return Err(PresetError::Unnamed);
+		}
+		if !BUILT_IN_SHAPES.contains(&self.shape.as_str()) {
+			return Err(PresetError::UnknownShape(self.shape.clone()));
+		}
+		for (name, value, range) in [
+			("size", self.size, PRESET_SIZE_RANGE),
+			("opacity", self.opacity, PRESET_OPACITY_RANGE),
+			("rate", self.rate, PRESET_RATE_RANGE),
+			("smoothing", self.smoothing, PRESET_SMOOTHING_RANGE),
+			("spacing", self.spacing, PRESET_SPACING_RANGE),
+			("scatter", self.scatter, PRESET_SCATTER_RANGE),
+			("grain", self.grain, PRESET_GRAIN_RANGE),
+			("pickup", self.pickup, PRESET_PICKUP_RANGE),
+			("hardness", self.hardness, PRESET_HARDNESS_RANGE),
+			("spread", self.spread, PRESET_SPREAD_RANGE),
+		] {
+			// This also rejects NaN.
+			if !range.contains(&value) {
+				return Err(PresetError::OutOfRange {
+					preset: self.name.clone(),
+					name,
+					value,
+					min: *range.start(),
+					max: *range.end(),
+				});
+			}
+		}
+		Ok(())
+	}
+}
+
+/// A named list of colors, in Oklab.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Palette {
+	pub name: String,
+	pub colors: Vec<Vec3>,
+}
+
+impl Palette {
+	pub fn validate(&self) -> Result<(), PresetError> {
+		if self.name.trim().is_empty() {
+			return Err(PresetError::Unnamed);
+		}
+		// Oklab lightness is between 0 and 1, and `a` and `b` are well within [-1, 1] for any color
+		// we can display.
+		let valid = |c: Vec3| (0.0..=1.0).contains(&c.x) && c.yz().abs().max_element() <= 1.0;
+		if let Some(&color) = self.colors.iter().find(|&&c| !valid(c)) {
+			return Err(PresetError::InvalidColor {
+				palette: self.name.clone(),
+				color,
+			});
+		}
+		Ok(())
+	}
+}
+
+/// The contents of a file for sharing brush presets and palettes. Either may be omitted.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PresetFile {
+	#[serde(default)]
+	pub brushes: Vec<BrushPreset>,
+	#[serde(default)]
+	pub palettes: Vec<Palette>,
+}
+
+impl PresetFile {
+	pub fn encode(&self) -> String {
+		let versioned = Versioned::new(PRESETS_VERSION, self);
+		serde_json::to_string_pretty(&versioned).expect("presets should serialize")
+	}
+
+	/// Decodes and validates a preset file. The version is checked first, so files from newer
+	/// builds are reported as such rather than as malformed.
+	pub fn decode(encoded: &str) -> Result<Self, PresetError> {
+		let file: Self = versioned::decode(encoded, PRESETS_VERSION)?;
+		file.brushes.iter().try_for_each(BrushPreset::validate)?;
+		file.palettes.iter().try_for_each(Palette::validate)?;
+		Ok(file)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::assert_matches::assert_matches;
+
+	fn example() -> PresetFile {
+		PresetFile {
+			brushes: vec![BrushPreset {
+				name: "Soft".into(),
+				shape: "00507".into(),
+				size: 256.0,
+				opacity: 0.5,
+				rate: 25.0,
+				smoothing: 0.2,
+				spacing: 0.1,
+				scatter: 0.5,
+				grain: 0.25,
+				hardness: 0.8,
+				spread: 0.6,
+				mode: BrushMode::Smudge,
+				pickup: 0.3,
+			}],
+			palettes: vec![Palette {
+				name: "Grays".into(),
+				colors: vec![Vec3::ZERO, vec3(0.5, 0.0, 0.0), vec3(0.9, -0.1, 0.05)],
+			}],
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let file = example();
+		let encoded = file.encode();
+		assert!(encoded.starts_with("{\n  \"version\": 1,"));
+		assert_eq!(PresetFile::decode(&encoded), Ok(file));
+	}
+
+	#[test]
+	fn sections_are_optional() {
+		assert_eq!(
+			PresetFile::decode(r#"{"version": 1}"#),
+			Ok(PresetFile::default())
+		);
+		let palettes_only = PresetFile {
+			brushes: Vec::new(),
+			..example()
+		};
+		assert_eq!(
+			PresetFile::decode(&palettes_only.encode()),
+			Ok(palettes_only)
+		);
+	}
+
+	#[test]
+	fn built_in_presets_are_valid() {
+		let presets = built_in_brush_presets();
+		assert!(presets.len() > 1);
+		for preset in &presets {
+			assert_eq!(preset.validate(), Ok(()));
+		}
+		let mut names: Vec<_> = presets.iter().map(|p| &p.name).collect();
+		names.sort();
+		names.dedup();
+		assert_eq!(names.len(), presets.len());
+	}
+
+	#[test]
+	fn older_presets_use_defaults() {
+		let encoded = r#"{"version": 1, "brushes": [{"name": "Old", "shape": "00507", "size": 4.0,
+			"opacity": 1.0, "rate": 25.0, "smoothing": 0.0}]}"#;
+		let brush = &PresetFile::decode(encoded).unwrap().brushes[0];
+		assert_eq!(brush.spacing, default_spacing());
+		assert_eq!(brush.scatter, 0.0);
+		assert_eq!(brush.grain, 0.0);
+		assert_eq!(brush.stamp_profile(), StampProfile::default());
+		assert_eq!(brush.mode, BrushMode::Paint);
+		assert_eq!(brush.pickup, default_pickup());
+	}
+
+	#[test]
+	fn versions() {
+		assert_matches!(
+			PresetFile::decode(r#"{"brushes": []}"#),
+			Err(PresetError::Malformed(_))
+		);
+		assert_eq!(
+			PresetFile::decode(r#"{"version": 2, "brushes": "from the future"}"#),
+			Err(PresetError::UnsupportedVersion(2))
+		);
+		assert_eq!(
+			PresetFile::decode(r#"{"version": 0}"#),
+			Err(PresetError::UnsupportedVersion(0))
+		);
+	}
+
+	#[test]
+	fn validation() {
+		let decode = |edit: fn(&mut PresetFile)| {
+			let mut file = example();
+			edit(&mut file);
+			PresetFile::decode(&file.encode())
+		};
+		assert_eq!(
+			decode(|f| f.brushes[0].name = " ".into()),
+			Err(PresetError::Unnamed)
+		);
+		assert_eq!(
+			decode(|f| f.brushes[0].shape = "missing".into()),
+			Err(PresetError::UnknownShape("missing".into()))
+		);
+		assert_matches!(
+			decode(|f| f.brushes[0].opacity = 3.0),
+			Err(PresetError::OutOfRange {
+				name: "opacity",
+				..
+			})
+		);
+		assert_matches!(
+			decode(|f| f.brushes[0].size = 0.0),
+			Err(PresetError::OutOfRange { name: "size", .. })
+		);
+		assert_matches!(
+			decode(|f| f.brushes[0].scatter = -1.0),
+			Err(PresetError::OutOfRange {
+				name: "scatter",
+				..
+			})
+		);
+		assert_matches!(
+			decode(|f| f.palettes[0].colors.push(vec3(1.5, 0.0, 0.0))),
+			Err(PresetError::InvalidColor { .. })
+		);
+		assert_eq!(
+			decode(|f| f.palettes[0].name.clear()),
+			Err(PresetError::Unnamed)
+		);
+	}
+}
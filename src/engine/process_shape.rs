@@ -1,9 +1,7 @@
 use core::f32;
 
 use crate::render::*;
-use crate::shaders::{
-	copy_transform, depth_to_layers, horizontal_scan, layers_to_depth, log_transform,
-};
+use crate::shaders::{copy_transform, scan, transfer};
 use bon::builder;
 use glam::*;
 use thiserror::Error;
@@ -47,104 +45,161 @@ pub fn rotations(
 		.usage(usage | wgpu::TextureUsages::RENDER_ATTACHMENT)
 		.create(device);
 
-	let copy_transform_shader = &resources.copy_transform;
-	let copy_transform_pipeline_layout = copy_transform_shader
-		.pipeline_layout()
-		.source_texture_filterable(true)
-		.source_sampler_filtering(wgpu::SamplerBindingType::Filtering)
-		.get();
-	let copy_transform_pipeline = copy_transform_pipeline_layout
-		.vs_main_pipeline()
-		.primitive(wgpu::PrimitiveState {
-			topology: wgpu::PrimitiveTopology::TriangleStrip,
-			..Default::default()
-		})
-		.fragment(copy_transform::FragmentEntry::fs_main {
-			targets: [Some(wgpu::ColorTargetState {
-				format,
-				blend: Some(wgpu::BlendState::REPLACE),
-				write_mask: wgpu::ColorWrites::ALL,
-			})],
-		})
-		.get();
-
-	let source_view = source.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("generate_rotations::source_view"),
-		base_array_layer: layer_index,
-		array_layer_count: Some(1),
-		..Default::default()
-	});
-
-	let source_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-		address_mode_u: wgpu::AddressMode::ClampToEdge,
-		address_mode_v: wgpu::AddressMode::ClampToEdge,
-		address_mode_w: wgpu::AddressMode::ClampToEdge,
-		mag_filter: wgpu::FilterMode::Linear,
-		min_filter: wgpu::FilterMode::Linear,
-		mipmap_filter: wgpu::FilterMode::Linear,
-		..Default::default()
-	});
-
-	let mut command_encoder = device.create_command_encoder(&Default::default());
-	let rotation_step = f32::consts::TAU / rotations as f32;
-	for rotation in 0..rotations {
-		let destination_view = output_texture.create_view(&wgpu::TextureViewDescriptor {
-			label: Some("generate_rotations::destination_view"),
-			base_array_layer: rotation,
+	error_scope(device, "rotations", || {
+		let copy_transform_shader = &resources.copy_transform;
+		let copy_transform_pipeline_layout = copy_transform_shader
+			.pipeline_layout()
+			.source_texture_filterable(true)
+			.source_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+			.get();
+		let copy_transform_pipeline = copy_transform_pipeline_layout
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(copy_transform::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format,
+					blend: Some(wgpu::BlendState::REPLACE),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let source_view = source.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("generate_rotations::source_view"),
+			base_array_layer: layer_index,
 			array_layer_count: Some(1),
-			dimension: Some(wgpu::TextureViewDimension::D2),
 			..Default::default()
 		});
 
-		let transform_buffer = BindingBuffer::init_sized(&Mat2::from_scale_angle(
-			scale,
-			rotation_step * rotation as f32,
-		))
-		.create(device);
+		let mut command_encoder = device.create_command_encoder(&Default::default());
+		let rotation_step = f32::consts::TAU / rotations as f32;
+		for rotation in 0..rotations {
+			let destination_view = output_texture.create_view(&wgpu::TextureViewDescriptor {
+				label: Some("generate_rotations::destination_view"),
+				base_array_layer: rotation,
+				array_layer_count: Some(1),
+				dimension: Some(wgpu::TextureViewDimension::D2),
+				..Default::default()
+			});
+
+			let linear = Mat2::from_scale_angle(scale, rotation_step * rotation as f32);
+			// Regenerating rotations of the same source reuses the bind groups, and their transforms.
+			let bindings = vec![
+				BindingKey::texture_layer(source, layer_index),
+				BindingKey::contents(&linear.to_cols_array()),
+				BindingKey::contents(&[
+					wgpu::FilterMode::Linear as u8,
+					wgpu::FilterMode::Linear as u8,
+				]),
+			];
+			let bind_group =
+				resources
+					.copy_transform_bind_groups
+					.get_or_create(true, bindings, || {
+						error_scope(device, "generate_rotations", || {
+							let transform_buffer = BindingBuffer::init_sized(&copy_transform::Transform {
+								linear,
+								translation: Vec2::ZERO,
+							})
+							.create(device);
+							let source_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+								address_mode_u: wgpu::AddressMode::ClampToEdge,
+								address_mode_v: wgpu::AddressMode::ClampToEdge,
+								address_mode_w: wgpu::AddressMode::ClampToEdge,
+								mag_filter: wgpu::FilterMode::Linear,
+								min_filter: wgpu::FilterMode::Linear,
+								mipmap_filter: wgpu::FilterMode::Linear,
+								..Default::default()
+							});
+							let bind_group = copy_transform_pipeline_layout
+								.bind_group_layouts()
+								.0
+								.bind_group()
+								.transform(transform_buffer.as_entire_buffer_binding())
+								.source_texture(&source_view)
+								.source_sampler(&source_sampler)
+								.create();
+							(transform_buffer, source_sampler, bind_group)
+						})
+					});
+
+			let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &destination_view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			render_pass.set_pipeline(&copy_transform_pipeline);
+			bind_group.2.set(&mut render_pass);
+			render_pass.draw(0..4, 0..1);
+		}
+		queue.submit([command_encoder.finish()]);
+	});
 
-		let bind_group = copy_transform_pipeline_layout
-			.bind_group_layouts()
-			.0
-			.bind_group()
-			.transform(transform_buffer.as_entire_buffer_binding())
-			.source_texture(&source_view)
-			.source_sampler(&source_sampler)
-			.create();
+	Ok(output_texture)
+}
 
-		let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-				view: &destination_view,
-				resolve_target: None,
-				ops: wgpu::Operations {
-					load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-					store: wgpu::StoreOp::Store,
-				},
-			})],
-			..Default::default()
-		});
-		render_pass.set_pipeline(&copy_transform_pipeline);
-		bind_group.set(&mut render_pass);
-		render_pass.draw(0..4, 0..1);
+/// How [`transfer`] maps each texel of a shape, after clamping it to `[0, 1]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TransferFunction {
+	#[default]
+	Linear,
+	/// `-ln(1 - x)`, which turns coverage into optical depth.
+	Log,
+	/// `x^gamma`.
+	Gamma(f32),
+}
+
+impl TransferFunction {
+	fn override_constants(self) -> transfer::OverrideConstants {
+		// These must match the constants in `transfer.wgsl`.
+		let (transfer_function, gamma) = match self {
+			Self::Linear => (0, None),
+			Self::Log => (1, None),
+			Self::Gamma(gamma) => (2, Some(gamma)),
+		};
+		transfer::OverrideConstants {
+			transfer_function: Some(transfer_function),
+			gamma,
+		}
 	}
-	queue.submit([command_encoder.finish()]);
+}
 
-	Ok(output_texture)
+/// The direction in which [`scan`] accumulates a shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanDirection {
+	/// Across each row.
+	#[default]
+	Horizontal,
+	/// Down each column.
+	Vertical,
 }
 
+/// Applies a [`TransferFunction`] to the first channel of `source`.
 #[builder(finish_fn = generate)]
-pub fn log_transform(
+pub fn transfer(
 	#[builder(start_fn)] source: &wgpu::Texture,
 	#[builder(finish_fn)] device: &wgpu::Device,
 	#[builder(finish_fn)] queue: &wgpu::Queue,
 	#[builder(finish_fn)] resources: &Resources,
+	#[builder(default)] function: TransferFunction,
 	#[builder(default)] layer_index: u32,
 	#[builder(default = wgpu::TextureUsages::all())] usage: wgpu::TextureUsages,
 	#[builder(default = &[])] view_formats: &[wgpu::TextureFormat],
 ) -> wgpu::Texture {
-	use log_transform::*;
+	use transfer::*;
 
 	let destination = texture()
-		.label("log_transform::destination")
+		.label("transfer::destination")
 		.width(source.width())
 		.height(source.height())
 		// This must match the format in the the shader.
@@ -153,61 +208,69 @@ pub fn log_transform(
 		.usage(usage | wgpu::TextureUsages::STORAGE_BINDING)
 		.create(device);
 
-	let shader = &resources.log_transform;
-	let pipeline_layout = shader.pipeline_layout().source_filterable(false).get();
-	let pipeline = pipeline_layout.log_transform_pipeline().get();
-
-	let source_view = source.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("log_transform::source"),
-		base_array_layer: layer_index,
-		array_layer_count: Some(1),
-		dimension: Some(wgpu::TextureViewDimension::D2),
-		..Default::default()
-	});
-
-	let destination_view = destination.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("log_transform::destination"),
-		..Default::default()
-	});
+	error_scope(device, "transfer", || {
+		let shader = &resources.transfer;
+		let pipeline_layout = shader.pipeline_layout().source_filterable(false).get();
+		let pipeline = pipeline_layout
+			.transfer_pipeline()
+			.overrides(function.override_constants())
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let source_view = source.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("transfer::source"),
+			base_array_layer: layer_index,
+			array_layer_count: Some(1),
+			dimension: Some(wgpu::TextureViewDimension::D2),
+			..Default::default()
+		});
 
-	let bind_group = pipeline_layout
-		.bind_group_layouts()
-		.0
-		.bind_group()
-		.source(&source_view)
-		.destination(&destination_view)
-		.create();
-
-	let mut command_encoder = device.create_command_encoder(&Default::default());
-	{
-		let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-			label: Some("log_transform"),
+		let destination_view = destination.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("transfer::destination"),
 			..Default::default()
 		});
-		let x_workgroups = (source.width() + WORKGROUP_WIDTH - 1) / WORKGROUP_WIDTH;
-		let y_workgroups = (source.height() + WORKGROUP_HEIGHT - 1) / WORKGROUP_HEIGHT;
-		pass.set_pipeline(&pipeline);
-		bind_group.set_compute(&mut pass);
-		pass.dispatch_workgroups(x_workgroups, y_workgroups, 1);
-	}
-	queue.submit([command_encoder.finish()]);
+
+		let bind_group = pipeline_layout
+			.bind_group_layouts()
+			.0
+			.bind_group()
+			.source(&source_view)
+			.destination(&destination_view)
+			.create();
+
+		let mut command_encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+				label: Some("transfer"),
+				..Default::default()
+			});
+			let x_workgroups = (source.width() + WORKGROUP_WIDTH - 1) / WORKGROUP_WIDTH;
+			let y_workgroups = (source.height() + WORKGROUP_HEIGHT - 1) / WORKGROUP_HEIGHT;
+			pass.set_pipeline(&pipeline);
+			bind_group.set_compute(&mut pass);
+			pass.dispatch_workgroups(x_workgroups, y_workgroups, 1);
+		}
+		queue.submit([command_encoder.finish()]);
+	});
 
 	destination
 }
 
+/// Computes the running mean of each layer of `source` along `direction`.
 #[builder(finish_fn = generate)]
-pub fn horizontal_scan(
+pub fn scan(
 	#[builder(start_fn)] source: &wgpu::Texture,
 	#[builder(finish_fn)] device: &wgpu::Device,
 	#[builder(finish_fn)] queue: &wgpu::Queue,
 	#[builder(finish_fn)] resources: &Resources,
+	#[builder(default)] direction: ScanDirection,
 	#[builder(default = wgpu::TextureUsages::all())] usage: wgpu::TextureUsages,
 	#[builder(default = &[])] view_formats: &[wgpu::TextureFormat],
 ) -> wgpu::Texture {
-	use horizontal_scan::*;
+	use scan::*;
 
 	let destination = texture()
-		.label("generate_rotations::destination")
+		.label("scan::destination")
 		.width(source.width())
 		.height(source.height())
 		.array_layers(source.depth_or_array_layers())
@@ -217,44 +280,163 @@ pub fn horizontal_scan(
 		.usage(usage | wgpu::TextureUsages::STORAGE_BINDING)
 		.create(device);
 
-	let shader = &resources.horizontal_scan;
-	let pipeline_layout = shader.pipeline_layout().source_filterable(false).get();
-	let pipeline = pipeline_layout.horizontal_scan_pipeline().get();
+	error_scope(device, "scan", || {
+		let shader = &resources.scan;
+		let pipeline_layout = shader.pipeline_layout().source_filterable(false).get();
+		let vertical = direction == ScanDirection::Vertical;
+		let pipeline = pipeline_layout
+			.scan_pipeline()
+			.overrides(OverrideConstants {
+				vertical: Some(vertical),
+			})
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let source_view = source.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("scan::source"),
+			dimension: Some(wgpu::TextureViewDimension::D2Array),
+			..Default::default()
+		});
 
-	let source_view = source.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("horizontal_scan::source"),
-		dimension: Some(wgpu::TextureViewDimension::D2Array),
-		..Default::default()
-	});
+		let destination_view = destination.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("scan::destination"),
+			dimension: Some(wgpu::TextureViewDimension::D2Array),
+			..Default::default()
+		});
 
-	let destination_view = destination.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("horizontal_scan::destination"),
-		dimension: Some(wgpu::TextureViewDimension::D2Array),
-		..Default::default()
+		let bind_group = pipeline_layout
+			.bind_group_layouts()
+			.0
+			.bind_group()
+			.source(&source_view)
+			.destination(&destination_view)
+			.create();
+
+		let mut command_encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+				label: Some("scan"),
+				..Default::default()
+			});
+			// Each invocation scans one row or column.
+			let lines = if vertical {
+				source.width()
+			} else {
+				source.height()
+			};
+			let num_workgroups = (lines + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+			pass.set_pipeline(&pipeline);
+			bind_group.set_compute(&mut pass);
+			pass.dispatch_workgroups(num_workgroups, source.depth_or_array_layers(), 1);
+		}
+		queue.submit([command_encoder.finish()]);
 	});
 
-	let bind_group = pipeline_layout
-		.bind_group_layouts()
-		.0
-		.bind_group()
-		.source(&source_view)
-		.destination(&destination_view)
-		.create();
-
-	let mut command_encoder = device.create_command_encoder(&Default::default());
-	{
-		let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-			label: Some("horizontal_scan"),
+	destination
+}
+
+/// A format [`layers_to_depth`] and [`depth_to_layers`] can write. Storage texture formats are fixed
+/// in WGSL, so each has its own shader variant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VolumeFormat {
+	#[default]
+	R32Float,
+	Rgba16Float,
+	Rgba32Float,
+}
+
+impl VolumeFormat {
+	pub fn texture_format(self) -> wgpu::TextureFormat {
+		match self {
+			Self::R32Float => wgpu::TextureFormat::R32Float,
+			Self::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+			Self::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+		}
+	}
+
+	/// Checks that `source` has the expected dimension and that its channels all fit in this format.
+	fn validate_source(
+		self,
+		source: &wgpu::Texture,
+		dimension: wgpu::TextureDimension,
+	) -> Result<(), ConvertVolumeError> {
+		use ConvertVolumeError::*;
+		if source.dimension() != dimension {
+			Err(WrongTextureDimension(source.dimension()))?;
+		}
+		let source_format = source.format();
+		if !matches!(
+			source_format.sample_type(None, None),
+			Some(wgpu::TextureSampleType::Float { .. })
+		) {
+			Err(UnsupportedSourceFormat(source_format))?;
+		}
+		let format = self.texture_format();
+		if source_format.components() > format.components() {
+			Err(TooFewChannels {
+				source_format,
+				format,
+			})?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ConvertVolumeError {
+	#[error("wrong texture dimension: {0:?}")]
+	WrongTextureDimension(wgpu::TextureDimension),
+	#[error("{0:?} can't be read as floating point")]
+	UnsupportedSourceFormat(wgpu::TextureFormat),
+	#[error("{format:?} doesn't have enough channels for {source_format:?}")]
+	TooFewChannels {
+		source_format: wgpu::TextureFormat,
+		format: wgpu::TextureFormat,
+	},
+}
+
+/// Records a pass running `$shader`, one of the variants of a volume conversion, over `size`
+/// texels. The variants differ only in their types, so this can't be a function.
+macro_rules! encode_volume_conversion {
+	(
+		$encoder:expr,
+		$resources:expr,
+		$shader:ident::$pipeline:ident,
+		$source_view:expr,
+		$destination_view:expr,
+		$size:expr
+	) => {{
+		use crate::shaders::$shader::*;
+		let pipeline_layout = $resources
+			.$shader
+			.pipeline_layout()
+			.source_filterable(false)
+			.get();
+		let pipeline = pipeline_layout
+			.$pipeline()
+			.maybe_cache($resources.pipeline_cache())
+			.get();
+		let bind_group = pipeline_layout
+			.bind_group_layouts()
+			.0
+			.bind_group()
+			.source($source_view)
+			.destination($destination_view)
+			.create();
+
+		let mut pass = $encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+			label: Some(stringify!($shader)),
 			..Default::default()
 		});
-		let num_workgroups = (source.height() + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+		let (width, height, depth) = $size;
 		pass.set_pipeline(&pipeline);
 		bind_group.set_compute(&mut pass);
-		pass.dispatch_workgroups(num_workgroups, source.depth_or_array_layers(), 1);
-	}
-	queue.submit([command_encoder.finish()]);
-
-	destination
+		pass.dispatch_workgroups(
+			width.div_ceil(WORKGROUP_WIDTH),
+			height.div_ceil(WORKGROUP_HEIGHT),
+			depth,
+		);
+	}};
 }
 
 #[builder(finish_fn = convert)]
@@ -263,60 +445,70 @@ pub fn layers_to_depth(
 	#[builder(finish_fn)] device: &wgpu::Device,
 	#[builder(finish_fn)] queue: &wgpu::Queue,
 	#[builder(finish_fn)] resources: &Resources,
+	#[builder(default)] format: VolumeFormat,
 	#[builder(default = wgpu::TextureUsages::all())] usage: wgpu::TextureUsages,
 	#[builder(default = &[])] view_formats: &[wgpu::TextureFormat],
-) -> wgpu::Texture {
-	use layers_to_depth::*;
+) -> Result<wgpu::Texture, ConvertVolumeError> {
+	format.validate_source(source, wgpu::TextureDimension::D2)?;
+	let size = (
+		source.width(),
+		source.height(),
+		source.depth_or_array_layers(),
+	);
 
 	let destination = texture()
 		.label("layers_to_depth::destination")
-		.width(source.width())
-		.height(source.height())
-		.depth(source.depth_or_array_layers())
-		// This must match the format in the the shader.
-		.format(wgpu::TextureFormat::R32Float)
+		.width(size.0)
+		.height(size.1)
+		.depth(size.2)
+		.format(format.texture_format())
 		.view_formats(view_formats)
 		.usage(usage | wgpu::TextureUsages::STORAGE_BINDING)
 		.create(device);
 
-	let shader = &resources.layers_to_depth;
-	let pipeline_layout = shader.pipeline_layout().source_filterable(false).get();
-	let pipeline = pipeline_layout.layers_to_depth_pipeline().get();
-
-	let source_view = source.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("layers_to_depth::source"),
-		dimension: Some(wgpu::TextureViewDimension::D2Array),
-		..Default::default()
-	});
-
-	let destination_view = destination.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("layers_to_depth::destination"),
-		..Default::default()
-	});
+	error_scope(device, "layers_to_depth", || {
+		let source_view = &source.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("layers_to_depth::source"),
+			dimension: Some(wgpu::TextureViewDimension::D2Array),
+			..Default::default()
+		});
 
-	let bind_group = pipeline_layout
-		.bind_group_layouts()
-		.0
-		.bind_group()
-		.source(&source_view)
-		.destination(&destination_view)
-		.create();
-
-	let mut command_encoder = device.create_command_encoder(&Default::default());
-	{
-		let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-			label: Some("layers_to_depth"),
+		let destination_view = &destination.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("layers_to_depth::destination"),
 			..Default::default()
 		});
-		let x_workgroups = (source.width() + WORKGROUP_WIDTH - 1) / WORKGROUP_WIDTH;
-		let y_workgroups = (source.height() + WORKGROUP_HEIGHT - 1) / WORKGROUP_HEIGHT;
-		pass.set_pipeline(&pipeline);
-		bind_group.set_compute(&mut pass);
-		pass.dispatch_workgroups(x_workgroups, y_workgroups, 1);
-	}
-	queue.submit([command_encoder.finish()]);
 
-	destination
+		let mut command_encoder = device.create_command_encoder(&Default::default());
+		match format {
+			VolumeFormat::R32Float => encode_volume_conversion!(
+				command_encoder,
+				resources,
+				layers_to_depth_r32float::layers_to_depth_pipeline,
+				source_view,
+				destination_view,
+				size
+			),
+			VolumeFormat::Rgba16Float => encode_volume_conversion!(
+				command_encoder,
+				resources,
+				layers_to_depth_rgba16float::layers_to_depth_pipeline,
+				source_view,
+				destination_view,
+				size
+			),
+			VolumeFormat::Rgba32Float => encode_volume_conversion!(
+				command_encoder,
+				resources,
+				layers_to_depth_rgba32float::layers_to_depth_pipeline,
+				source_view,
+				destination_view,
+				size
+			),
+		}
+		queue.submit([command_encoder.finish()]);
+	});
+
+	Ok(destination)
 }
 
 #[builder(finish_fn = convert)]
@@ -325,61 +517,71 @@ pub fn depth_to_layers(
 	#[builder(finish_fn)] device: &wgpu::Device,
 	#[builder(finish_fn)] queue: &wgpu::Queue,
 	#[builder(finish_fn)] resources: &Resources,
+	#[builder(default)] format: VolumeFormat,
 	#[builder(default = wgpu::TextureUsages::all())] usage: wgpu::TextureUsages,
 	#[builder(default = &[])] view_formats: &[wgpu::TextureFormat],
-) -> wgpu::Texture {
-	use depth_to_layers::*;
+) -> Result<wgpu::Texture, ConvertVolumeError> {
+	format.validate_source(source, wgpu::TextureDimension::D3)?;
+	let size = (
+		source.width(),
+		source.height(),
+		source.depth_or_array_layers(),
+	);
 
 	let destination = texture()
 		.label("depth_to_layers::destination")
-		.width(source.width())
-		.height(source.height())
-		.array_layers(source.depth_or_array_layers())
-		// This must match the format in the the shader.
-		.format(wgpu::TextureFormat::R32Float)
+		.width(size.0)
+		.height(size.1)
+		.array_layers(size.2)
+		.format(format.texture_format())
 		.view_formats(view_formats)
 		.usage(usage | wgpu::TextureUsages::STORAGE_BINDING)
 		.create(device);
 
-	let shader = &resources.depth_to_layers;
-	let pipeline_layout = shader.pipeline_layout().source_filterable(false).get();
-	let pipeline = pipeline_layout.depth_to_layers_pipeline().get();
-
-	let source_view = source.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("depth_to_layers::source"),
-		dimension: Some(wgpu::TextureViewDimension::D3),
-		..Default::default()
-	});
-
-	let destination_view = destination.create_view(&wgpu::TextureViewDescriptor {
-		label: Some("depth_to_layers::destination"),
-		dimension: Some(wgpu::TextureViewDimension::D2Array),
-		..Default::default()
-	});
+	error_scope(device, "depth_to_layers", || {
+		let source_view = &source.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("depth_to_layers::source"),
+			dimension: Some(wgpu::TextureViewDimension::D3),
+			..Default::default()
+		});
 
-	let bind_group = pipeline_layout
-		.bind_group_layouts()
-		.0
-		.bind_group()
-		.source(&source_view)
-		.destination(&destination_view)
-		.create();
-
-	let mut command_encoder = device.create_command_encoder(&Default::default());
-	{
-		let mut pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-			label: Some("depth_to_layers"),
+		let destination_view = &destination.create_view(&wgpu::TextureViewDescriptor {
+			label: Some("depth_to_layers::destination"),
+			dimension: Some(wgpu::TextureViewDimension::D2Array),
 			..Default::default()
 		});
-		let x_workgroups = (source.width() + WORKGROUP_WIDTH - 1) / WORKGROUP_WIDTH;
-		let y_workgroups = (source.height() + WORKGROUP_HEIGHT - 1) / WORKGROUP_HEIGHT;
-		pass.set_pipeline(&pipeline);
-		bind_group.set_compute(&mut pass);
-		pass.dispatch_workgroups(x_workgroups, y_workgroups, 1);
-	}
-	queue.submit([command_encoder.finish()]);
 
-	destination
+		let mut command_encoder = device.create_command_encoder(&Default::default());
+		match format {
+			VolumeFormat::R32Float => encode_volume_conversion!(
+				command_encoder,
+				resources,
+				depth_to_layers_r32float::depth_to_layers_pipeline,
+				source_view,
+				destination_view,
+				size
+			),
+			VolumeFormat::Rgba16Float => encode_volume_conversion!(
+				command_encoder,
+				resources,
+				depth_to_layers_rgba16float::depth_to_layers_pipeline,
+				source_view,
+				destination_view,
+				size
+			),
+			VolumeFormat::Rgba32Float => encode_volume_conversion!(
+				command_encoder,
+				resources,
+				depth_to_layers_rgba32float::depth_to_layers_pipeline,
+				source_view,
+				destination_view,
+				size
+			),
+		}
+		queue.submit([command_encoder.finish()]);
+	});
+
+	Ok(destination)
 }
 
 #[cfg(test)]
@@ -408,6 +610,23 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn regenerated_rotations_reuse_bind_groups() -> anyhow::Result<()> {
+		let context = WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let source = context.create_image_texture("test/input/cs-gray-7f7f7f.png")?;
+		let generate = || {
+			rotations(4)
+				.source(&source)
+				.generate(context.device(), context.queue(), &resources)
+		};
+		generate()?;
+		assert_eq!(resources.copy_transform_bind_groups.len(), 4);
+		generate()?;
+		assert_eq!(resources.copy_transform_bind_groups.len(), 4);
+		Ok(())
+	}
+
 	#[test]
 	fn test_rotations_texture_format() -> anyhow::Result<()> {
 		let context = WgpuTestContext::new()?;
@@ -434,8 +653,11 @@ mod tests {
 		let resources = Resources::new(context.device());
 		let source = context.create_image_texture("test/input/cs-gray-7f7f7f.png")?;
 
-		let destination =
-			log_transform(&source).generate(&context.device(), context.queue(), &resources);
+		let destination = transfer(&source).function(TransferFunction::Log).generate(
+			&context.device(),
+			context.queue(),
+			&resources,
+		);
 
 		context.golden_texture(
 			"engine/process_shape/log_transform",
@@ -446,14 +668,32 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_gamma_transform() -> anyhow::Result<()> {
+		let context = WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let source = context.create_image_texture("test/input/cs-gray-7f7f7f.png")?;
+
+		let destination = transfer(&source)
+			.function(TransferFunction::Gamma(2.2))
+			.generate(&context.device(), context.queue(), &resources);
+
+		context.golden_texture(
+			"engine/process_shape/gamma_transform",
+			GoldenOptions::default(),
+			&destination,
+			0,
+		)?;
+		Ok(())
+	}
+
 	#[test]
 	fn test_horizontal_scan() -> anyhow::Result<()> {
 		let context = WgpuTestContext::new()?;
 		let resources = Resources::new(context.device());
 		let source = context.create_image_texture("test/input/cs-gray-7f7f7f.png")?;
 
-		let destination =
-			horizontal_scan(&source).generate(&context.device(), context.queue(), &resources);
+		let destination = scan(&source).generate(&context.device(), context.queue(), &resources);
 
 		context.golden_texture(
 			"engine/process_shape/horizontal_scan",
@@ -464,6 +704,27 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_vertical_scan() -> anyhow::Result<()> {
+		let context = WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let source = context.create_image_texture("test/input/cs-gray-7f7f7f.png")?;
+
+		let destination = scan(&source).direction(ScanDirection::Vertical).generate(
+			&context.device(),
+			context.queue(),
+			&resources,
+		);
+
+		context.golden_texture(
+			"engine/process_shape/vertical_scan",
+			GoldenOptions::default(),
+			&destination,
+			0,
+		)?;
+		Ok(())
+	}
+
 	#[test]
 	fn layers_to_depth_to_layers() -> anyhow::Result<()> {
 		let context = WgpuTestContext::new()?;
@@ -472,11 +733,11 @@ mod tests {
 
 		let depth = layers_to_depth(&source)
 			.usage(wgpu::TextureUsages::TEXTURE_BINDING)
-			.convert(&context.device(), context.queue(), &resources);
+			.convert(&context.device(), context.queue(), &resources)?;
 
 		let destination = depth_to_layers(&depth)
 			.usage(wgpu::TextureUsages::COPY_SRC)
-			.convert(&context.device(), context.queue(), &resources);
+			.convert(&context.device(), context.queue(), &resources)?;
 
 		context.golden_texture(
 			"engine/process_shape/layers_to_depth_to_layers",
@@ -486,4 +747,51 @@ mod tests {
 		)?;
 		Ok(())
 	}
+
+	#[test]
+	fn color_layers_to_depth_to_layers() -> anyhow::Result<()> {
+		let context = WgpuTestContext::new()?;
+		let resources = Resources::new(context.device());
+		let layers = [[255u8, 0, 0, 255], [0, 51, 255, 102]];
+		let source = texture()
+			.width(2)
+			.height(2)
+			.array_layers(2)
+			.format(wgpu::TextureFormat::Rgba8Unorm)
+			.usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+			.with_data((
+				context.queue(),
+				&layers.map(|pixel| pixel.repeat(4)).concat(),
+			))
+			.create(context.device());
+
+		// A single channel can't hold the source's colors.
+		assert!(matches!(
+			layers_to_depth(&source).convert(&context.device(), context.queue(), &resources),
+			Err(ConvertVolumeError::TooFewChannels { .. })
+		));
+
+		let format = VolumeFormat::Rgba16Float;
+		let depth = layers_to_depth(&source)
+			.format(format)
+			.usage(wgpu::TextureUsages::TEXTURE_BINDING)
+			.convert(&context.device(), context.queue(), &resources)?;
+		assert_eq!(depth.depth_or_array_layers(), 2);
+		let destination = depth_to_layers(&depth)
+			.format(format)
+			.usage(wgpu::TextureUsages::COPY_SRC)
+			.convert(&context.device(), context.queue(), &resources)?;
+
+		for (layer_index, pixel) in layers.iter().enumerate() {
+			let data =
+				pollster::block_on(context.get_texture_layer_data(&destination, layer_index as u32))?;
+			let expected = pixel.map(|c| c as f32 / 255.0);
+			for actual in bytemuck::pod_collect_to_vec::<u8, [half::f16; 4]>(&data) {
+				for (a, e) in actual.iter().zip(expected) {
+					assert!((a.to_f32() - e).abs() < 1e-3, "{actual:?} != {expected:?}");
+				}
+			}
+		}
+		Ok(())
+	}
 }
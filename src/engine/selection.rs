@@ -0,0 +1,333 @@
+use super::{AABox, ChartKey, CHART_SIZE};
+use crate::render;
+use crate::shaders::airbrush;
+use crate::WgpuContext;
+use glam::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+/// How many times each row of a mask is sampled to antialias the selection's edges. Coverage along
+/// rows is computed exactly.
+const MASK_ROW_SUBSAMPLES: u32 = 4;
+
+/// The outline of a selection, in canvas coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionShape {
+	Rectangle {
+		min: Vec2,
+		max: Vec2,
+	},
+	/// A freehand outline, implicitly closed. Where it crosses itself, regions are selected by the
+	/// even-odd rule.
+	Lasso(Vec<Vec2>),
+}
+
+impl SelectionShape {
+	/// The rectangle with opposite corners `a` and `b`.
+	pub fn rectangle(a: Vec2, b: Vec2) -> Self {
+		Self::Rectangle {
+			min: a.min(b),
+			max: a.max(b),
+		}
+	}
+
+	/// The outline as a closed polygon.
+	pub fn outline(&self) -> Vec<Vec2> {
+		match self {
+			Self::Rectangle { min, max } => AABox::new(*min, *max).corners().to_vec(),
+			Self::Lasso(points) => points.clone(),
+		}
+	}
+
+	/// The smallest box containing the shape.
+	pub fn bounds(&self) -> AABox {
+		AABox::containing(self.outline().into_iter())
+	}
+
+	/// The charts containing any part of the shape.
+	pub fn chart_keys(&self) -> Vec<ChartKey> {
+		let outline = self.outline();
+		if outline.len() < 3 {
+			return Vec::new();
+		}
+		ChartKey::find_intersecting(AABox::containing(outline.into_iter())).collect()
+	}
+
+	/// The fraction of each texel of the chart at `key` covered by the shape, row by row.
+	pub fn rasterize(&self, key: ChartKey) -> Vec<u8> {
+		let size = CHART_SIZE as usize;
+		let mut coverage = vec![0.0f32; size * size];
+		// Work in texels, so texel `(x, y)` spans `[x, x + 1] x [y, y + 1]`.
+		let chart_to_canvas = key.chart_to_canvas();
+		let canvas_to_texel =
+			Affine2::from_scale(Vec2::splat(CHART_SIZE as f32)) * chart_to_canvas.inverse();
+		let outline = self
+			.outline()
+			.into_iter()
+			.map(|p| canvas_to_texel.transform_point2(p))
+			.collect::<Vec<_>>();
+		if outline.len() < 3 {
+			return vec![0; size * size];
+		}
+
+		let weight = 1.0 / MASK_ROW_SUBSAMPLES as f32;
+		let mut crossings = Vec::new();
+		for (y, row) in coverage.chunks_exact_mut(size).enumerate() {
+			for sample in 0..MASK_ROW_SUBSAMPLES {
+				let sample_y = y as f32 + (sample as f32 + 0.5) * weight;
+				crossings.clear();
+				let edges = outline.iter().zip(outline.iter().cycle().skip(1));
+				for (&a, &b) in edges {
+					if (a.y <= sample_y) != (b.y <= sample_y) {
+						let t = (sample_y - a.y) / (b.y - a.y);
+						crossings.push(a.x + t * (b.x - a.x));
+					}
+				}
+				crossings.sort_by(f32::total_cmp);
+				for span in crossings.chunks_exact(2) {
+					add_span(row, span[0], span[1], weight);
+				}
+			}
+		}
+		coverage
+			.into_iter()
+			.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+			.collect()
+	}
+}
+
+/// How dragging on the canvas selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum SelectionTool {
+	/// Selects the rectangle spanned by the drag.
+	Rectangle,
+	/// Selects the region enclosed by the drag's path.
+	Lasso,
+}
+
+impl SelectionTool {
+	pub const ALL: [Self; 2] = [Self::Rectangle, Self::Lasso];
+
+	/// The shape selected by dragging through `points`, in canvas coordinates, or `None` if the drag
+	/// encloses nothing, as with a click.
+	pub fn shape(self, points: &[Vec2]) -> Option<SelectionShape> {
+		let shape = match self {
+			Self::Rectangle => SelectionShape::rectangle(*points.first()?, *points.last()?),
+			Self::Lasso => SelectionShape::Lasso(points.to_vec()),
+		};
+		let outline = shape.outline();
+		let doubled_area: f32 = outline
+			.iter()
+			.zip(outline.iter().cycle().skip(1))
+			.map(|(a, b)| a.perp_dot(*b))
+			.sum();
+		(doubled_area.abs() >= 2.0).then_some(shape)
+	}
+}
+
+/// Adds `weight` times the overlap of `[x0, x1]` with each texel of `row`.
+fn add_span(row: &mut [f32], x0: f32, x1: f32, weight: f32) {
+	let end = row.len() as f32;
+	let (x0, x1) = (x0.clamp(0.0, end), x1.clamp(0.0, end));
+	if x1 <= x0 {
+		return;
+	}
+	let (i0, i1) = (x0.floor() as usize, x1.floor() as usize);
+	if i0 == i1 {
+		row[i0] += (x1 - x0) * weight;
+		return;
+	}
+	row[i0] += (i0 as f32 + 1.0 - x0) * weight;
+	for texel in &mut row[i0 + 1..i1] {
+		*texel += weight;
+	}
+	if let Some(texel) = row.get_mut(i1) {
+		*texel += (x1 - i1 as f32) * weight;
+	}
+}
+
+/// Confines painting to part of the canvas. Each chart the selection touches has a mask of how much
+/// of each texel is selected, and painting leaves other charts untouched. Without a selection,
+/// everything is selected.
+#[derive(Clone)]
+pub struct Selection {
+	context: Arc<WgpuContext>,
+	layout: airbrush::BindGroupLayout2,
+	shape: Option<SelectionShape>,
+	masks: HashMap<ChartKey, Arc<airbrush::BindGroup2>>,
+	everything: Arc<airbrush::BindGroup2>,
+}
+
+impl Selection {
+	pub fn new(context: Arc<WgpuContext>) -> Self {
+		let layout = airbrush::BindGroupLayout2::new(context.device().clone());
+		let everything = vec![u8::MAX; (CHART_SIZE * CHART_SIZE) as usize];
+		let everything = create_mask_bind_group(&context, &layout, &everything).into();
+		Self {
+			context,
+			layout,
+			shape: None,
+			masks: HashMap::new(),
+			everything,
+		}
+	}
+
+	pub fn shape(&self) -> Option<&SelectionShape> {
+		self.shape.as_ref()
+	}
+
+	/// The bounding box of the selection, or `None` if everything is selected.
+	pub fn bounds(&self) -> Option<AABox> {
+		self.shape.as_ref().map(SelectionShape::bounds)
+	}
+
+	/// Replaces the selection with `shape`.
+	pub fn select(&mut self, shape: SelectionShape) {
+		self.masks = shape
+			.chart_keys()
+			.into_iter()
+			.map(|key| {
+				let mask = shape.rasterize(key);
+				let bind_group = create_mask_bind_group(&self.context, &self.layout, &mask);
+				(key, bind_group.into())
+			})
+			.collect();
+		self.shape = Some(shape);
+	}
+
+	/// Selects everything.
+	pub fn clear(&mut self) {
+		self.shape = None;
+		self.masks.clear();
+	}
+
+	/// The mask to apply when painting the chart at `key`, or `None` if none of it is selected.
+	pub fn bind_group(&self, key: ChartKey) -> Option<Arc<airbrush::BindGroup2>> {
+		match self.shape {
+			None => Some(self.everything.clone()),
+			Some(_) => self.masks.get(&key).cloned(),
+		}
+	}
+}
+
+fn create_mask_bind_group(
+	context: &WgpuContext,
+	layout: &airbrush::BindGroupLayout2,
+	mask: &[u8],
+) -> airbrush::BindGroup2 {
+	render::error_scope(context.device(), "selection_mask", || {
+		let texture = context.device().create_texture_with_data(
+			context.queue(),
+			&wgpu::TextureDescriptor {
+				label: Some("selection_mask"),
+				size: wgpu::Extent3d {
+					width: CHART_SIZE,
+					height: CHART_SIZE,
+					depth_or_array_layers: 1,
+				},
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: wgpu::TextureDimension::D2,
+				format: wgpu::TextureFormat::R8Unorm,
+				usage: wgpu::TextureUsages::TEXTURE_BINDING,
+				view_formats: &[],
+			},
+			wgpu::util::TextureDataOrder::LayerMajor,
+			mask,
+		);
+		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		layout.bind_group().selection_mask(&view).create()
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn texel(mask: &[u8], x: usize, y: usize) -> u8 {
+		mask[y * CHART_SIZE as usize + x]
+	}
+
+	#[test]
+	fn rasterize_rectangle() {
+		let shape = SelectionShape::rectangle(vec2(10.5, 20.0), vec2(2.0, 4.0));
+		assert_eq!(shape.chart_keys(), [ChartKey(0, 0)]);
+		let mask = shape.rasterize(ChartKey(0, 0));
+		assert_eq!(texel(&mask, 5, 10), 255);
+		assert_eq!(texel(&mask, 1, 10), 0);
+		assert_eq!(texel(&mask, 5, 3), 0);
+		assert_eq!(texel(&mask, 5, 20), 0);
+		// Partially covered texels are partially selected.
+		assert_eq!(texel(&mask, 10, 10), 128);
+		assert_eq!(texel(&mask, 11, 10), 0);
+
+		assert!(shape.rasterize(ChartKey(1, 0)).iter().all(|&t| t == 0));
+	}
+
+	#[test]
+	fn rasterize_lasso() {
+		// A triangle spanning two charts, listed in either winding.
+		let points = vec![vec2(200.0, 0.0), vec2(300.0, 0.0), vec2(200.0, 100.0)];
+		for shape in [
+			SelectionShape::Lasso(points.clone()),
+			SelectionShape::Lasso(points.into_iter().rev().collect()),
+		] {
+			assert_eq!(shape.chart_keys(), [ChartKey(0, 0), ChartKey(1, 0)]);
+			let area: f32 = shape
+				.chart_keys()
+				.into_iter()
+				.flat_map(|key| shape.rasterize(key))
+				.map(|t| t as f32 / 255.0)
+				.sum();
+			assert!((area - 5000.0).abs() < 10.0, "{area}");
+			assert_eq!(texel(&shape.rasterize(ChartKey(0, 0)), 210, 10), 255);
+			assert_eq!(texel(&shape.rasterize(ChartKey(1, 0)), 20, 10), 255);
+			assert_eq!(texel(&shape.rasterize(ChartKey(1, 0)), 40, 90), 0);
+		}
+
+		// Too few points to enclose anything.
+		let line = SelectionShape::Lasso(vec![Vec2::ZERO, Vec2::ONE]);
+		assert!(line.chart_keys().is_empty());
+	}
+
+	#[test]
+	fn bounds() {
+		let rectangle = SelectionShape::rectangle(vec2(10.5, 20.0), vec2(2.0, 4.0));
+		assert_eq!(
+			rectangle.bounds(),
+			AABox::new(vec2(2.0, 4.0), vec2(10.5, 20.0))
+		);
+		let lasso = SelectionShape::Lasso(vec![
+			vec2(200.0, 0.0),
+			vec2(300.0, -5.0),
+			vec2(250.0, 100.0),
+		]);
+		assert_eq!(
+			lasso.bounds(),
+			AABox::new(vec2(200.0, -5.0), vec2(300.0, 100.0))
+		);
+	}
+
+	#[test]
+	fn tools() {
+		let points = [vec2(10.0, 10.0), vec2(20.0, 10.0), vec2(15.0, 30.0)];
+		assert_eq!(
+			SelectionTool::Rectangle.shape(&points),
+			Some(SelectionShape::Rectangle {
+				min: vec2(10.0, 10.0),
+				max: vec2(15.0, 30.0),
+			})
+		);
+		assert_eq!(
+			SelectionTool::Lasso.shape(&points),
+			Some(SelectionShape::Lasso(points.to_vec()))
+		);
+		// Clicks and straight lines select nothing.
+		for tool in SelectionTool::ALL {
+			assert_eq!(tool.shape(&[]), None);
+			assert_eq!(tool.shape(&points[..1]), None);
+			assert_eq!(tool.shape(&[Vec2::ZERO, Vec2::X, 2.0 * Vec2::X]), None);
+		}
+	}
+}
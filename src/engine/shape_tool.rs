@@ -0,0 +1,128 @@
+use super::VectorSegment;
+use glam::*;
+
+/// How far the inner control points of a cubic Bezier quarter of a circle are from its ends, as a
+/// fraction of the radius. This keeps the curve within a fraction of a percent of the circle.
+const QUARTER_CIRCLE_HANDLE: f32 = 0.552_284_8;
+
+/// How many points each segment of a shape's outline is sampled at for previewing it.
+const OUTLINE_SAMPLES: usize = 16;
+
+/// A shape drawn by dragging across the canvas, which is stroked with the brush once the drag ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum ShapeTool {
+	/// A straight line from where the drag starts to where it ends.
+	Line,
+	/// The outline of the rectangle spanned by the drag.
+	Rectangle,
+	/// The outline of the ellipse inscribed in the rectangle spanned by the drag.
+	Ellipse,
+}
+
+/// The center of the canvas pixel containing `point`, where shapes snapping to pixels are placed so
+/// that lines along them are centered on pixels.
+pub fn pixel_center(point: Vec2) -> Vec2 {
+	point.floor() + 0.5
+}
+
+impl ShapeTool {
+	pub const ALL: [Self; 3] = [Self::Line, Self::Rectangle, Self::Ellipse];
+
+	/// The path of the shape dragged from `from` to `to`, in canvas coordinates, with a constant
+	/// `pressure`, or `None` if the drag spans nothing to draw, as with a click.
+	pub fn segments(self, from: Vec2, to: Vec2, pressure: f32) -> Option<Vec<VectorSegment>> {
+		if from.distance(to) < 1.0 {
+			return None;
+		}
+		let segments = match self {
+			Self::Line => vec![VectorSegment::line(from, to, pressure)],
+			Self::Rectangle => {
+				let corners = [from, vec2(to.x, from.y), to, vec2(from.x, to.y)];
+				(0..4)
+					.map(|i| VectorSegment::line(corners[i], corners[(i + 1) % 4], pressure))
+					.collect()
+			}
+			Self::Ellipse => {
+				let center = 0.5 * (from + to);
+				let radii = 0.5 * (to - from).abs();
+				let axes = [Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y].map(|axis| radii * axis);
+				(0..4)
+					.map(|i| {
+						let (a, b) = (axes[i], axes[(i + 1) % 4]);
+						let points = [
+							a,
+							a + QUARTER_CIRCLE_HANDLE * b,
+							b + QUARTER_CIRCLE_HANDLE * a,
+							b,
+						];
+						VectorSegment {
+							points: points.map(|p| (center + p).extend(pressure)),
+							tilt: Vec2::ZERO,
+						}
+					})
+					.collect()
+			}
+		};
+		Some(segments)
+	}
+
+	/// The shape dragged from `from` to `to` as a polyline through canvas coordinates, for
+	/// previewing it, or `None` if there is nothing to draw. Closed shapes end where they start.
+	pub fn outline(self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+		let segments = self.segments(from, to, 1.0)?;
+		let first = segments.first()?.points[0].xy();
+		let rest = segments.iter().flat_map(|segment| {
+			(1..=OUTLINE_SAMPLES).map(|i| segment.evaluate(i as f32 / OUTLINE_SAMPLES as f32).xy())
+		});
+		Some(std::iter::once(first).chain(rest).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shapes_follow_the_drag() {
+		let (from, to) = (vec2(10.0, 20.0), vec2(50.0, -20.0));
+		for tool in ShapeTool::ALL {
+			let segments = tool.segments(from, to, 0.75).unwrap();
+			// Each segment starts where the last ends, and every point has the pressure.
+			for (a, b) in segments.iter().zip(segments.iter().skip(1)) {
+				assert_eq!(a.points[3], b.points[0], "{tool}");
+			}
+			assert!(segments
+				.iter()
+				.all(|s| s.points.iter().all(|p| p.z == 0.75)));
+
+			let outline = tool.outline(from, to).unwrap();
+			let (start, end) = (outline[0], *outline.last().unwrap());
+			match tool {
+				ShapeTool::Line => {
+					assert_eq!(start, from);
+					assert!(end.abs_diff_eq(to, 1e-4));
+				}
+				_ => assert!(start.abs_diff_eq(end, 1e-4), "{tool}"),
+			}
+		}
+		assert_eq!(ShapeTool::Rectangle.segments(from, from, 1.0), None);
+	}
+
+	#[test]
+	fn snaps_to_pixel_centers() {
+		assert_eq!(pixel_center(vec2(3.2, -0.4)), vec2(3.5, -0.5));
+		assert_eq!(pixel_center(vec2(-7.0, 12.99)), vec2(-6.5, 12.5));
+	}
+
+	#[test]
+	fn ellipses_fit_the_drag() {
+		let outline = ShapeTool::Ellipse
+			.outline(vec2(-100.0, 0.0), vec2(100.0, 50.0))
+			.unwrap();
+		// Every point of the outline lies on the ellipse within a fraction of a percent.
+		for p in outline {
+			let normalized = (p - vec2(0.0, 25.0)) / vec2(100.0, 25.0);
+			assert!((normalized.length() - 1.0).abs() < 1e-3, "{p}");
+		}
+	}
+}
@@ -0,0 +1,234 @@
+use crate::engine::{CanvasFormat, Tile, CHART_SCALE, CHART_SIZE, MAX_BATCHED_SEGMENTS};
+use crate::render::{self, BindingBuffer, Resources};
+use crate::shaders::smudge::*;
+use glam::Vec2;
+use std::sync::Arc;
+
+/// What a brush does to the canvas under it.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	Default,
+	serde::Serialize,
+	serde::Deserialize,
+	derive_more::Display,
+)]
+pub enum BrushMode {
+	/// Sprays paint.
+	#[default]
+	Paint,
+	/// Drags the paint already on the canvas along the stroke.
+	Smudge,
+	/// Mixes the brush's color with the paint already on the canvas. See
+	/// [`super::Airbrush::set_pickup`].
+	Mix,
+	/// Removes paint, as much as painting would lay down.
+	Erase,
+}
+
+impl BrushMode {
+	pub const ALL: [Self; 4] = [Self::Paint, Self::Smudge, Self::Mix, Self::Erase];
+
+	/// Whether the mode draws from the paint already under the brush, so each chart must be copied
+	/// before each segment draws to it.
+	pub fn reads_canvas(self) -> bool {
+		matches!(self, Self::Smudge | Self::Mix)
+	}
+}
+
+/// Draws the airbrush's strips by pulling each texel under them from where the brush just was.
+///
+/// A chart can't be read while it is drawn to, so each chart is first copied to a source texture
+/// and then drawn from it. There is a source for each chart a batch of segments covers, created as
+/// needed and reused by later batches. Each segment of a batch has its own action.
+pub struct Smudge {
+	device: Arc<wgpu::Device>,
+	pipeline: wgpu::RenderPipeline,
+	actions: Vec<(BindingBuffer<SmudgeAction>, BindGroup0)>,
+	source_layout: BindGroupLayout3,
+	format: CanvasFormat,
+	sources: Vec<(wgpu::Texture, BindGroup3)>,
+}
+
+impl Smudge {
+	/// Creates the smudge for a brush of `shape_texture`, sampled with `shape_sampler`.
+	pub fn new(
+		device: &Arc<wgpu::Device>,
+		resources: &Resources,
+		format: CanvasFormat,
+		shape_texture: &wgpu::TextureView,
+		shape_sampler: &wgpu::Sampler,
+	) -> Self {
+		let pipeline_layout = resources
+			.smudge
+			.pipeline_layout()
+			.shape_texture_filterable(true)
+			.shape_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+			.selection_mask_filterable(false)
+			.smudge_source_filterable(false)
+			.get();
+		let pipeline = pipeline_layout
+			.vs_main_pipeline(wgpu::VertexStepMode::Vertex)
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: format.texture_format(),
+					// The shader mixes in what was already there itself.
+					blend: Some(wgpu::BlendState::REPLACE),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		let actions = (0..MAX_BATCHED_SEGMENTS)
+			.map(|_| {
+				let action_buffer = BindingBuffer::new_sized()
+					.label("smudge")
+					.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+					.create(device);
+				let bind_group = render::error_scope(device, "Smudge::actions", || {
+					create_bind_group(resources, &action_buffer, shape_texture, shape_sampler)
+				});
+				(action_buffer, bind_group)
+			})
+			.collect();
+
+		Self {
+			device: device.clone(),
+			pipeline,
+			actions,
+			source_layout: render::error_scope(device, "Smudge::source_layout", || {
+				BindGroupLayout3::new(device.clone())
+			}),
+			format,
+			sources: Vec::new(),
+		}
+	}
+
+	/// Replaces the shape the brush smudges with, as [`super::Airbrush::set_shape`] does for paint.
+	pub fn set_shape(
+		&mut self,
+		resources: &Resources,
+		shape_texture: &wgpu::TextureView,
+		shape_sampler: &wgpu::Sampler,
+	) {
+		for (action_buffer, bind_group) in &mut self.actions {
+			*bind_group = create_bind_group(resources, action_buffer, shape_texture, shape_sampler);
+		}
+	}
+
+	/// Prepares to draw the `slot`th segment of a batch, which moved the brush by `offset`, in canvas
+	/// coordinates.
+	pub fn begin_segment(&mut self, queue: &wgpu::Queue, slot: usize, offset: Vec2) {
+		let offset = offset * (CHART_SIZE as f32 / CHART_SCALE);
+		self.actions[slot].0.write(queue, SmudgeAction { offset });
+	}
+
+	/// Makes sure there is a source for each of the `chart_count` charts a batch is drawn to.
+	pub fn reserve_sources(&mut self, chart_count: usize) {
+		while self.sources.len() < chart_count {
+			let texture = render::texture()
+				.label("Smudge::source")
+				.width(CHART_SIZE)
+				.height(CHART_SIZE)
+				.format(self.format.texture_format())
+				.usage(wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING)
+				.create(&self.device);
+			let bind_group = render::error_scope(&self.device, "Smudge::source", || {
+				let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+				self
+					.source_layout
+					.bind_group()
+					.smudge_source(&view)
+					.create()
+			});
+			self.sources.push((texture, bind_group));
+		}
+	}
+
+	/// Records a copy of `tile`, the `index`th chart the batch is drawn to, for [`Self::set`] to read.
+	/// Each segment must copy the chart again, after the previous segment has drawn to it.
+	pub fn copy_source(&self, encoder: &mut wgpu::CommandEncoder, tile: &Tile, index: usize) {
+		tile.copy_texture_to(encoder, &self.sources[index].0);
+	}
+
+	/// Sets the pipeline and bind groups other than the chart's and the selection's to draw the
+	/// `slot`th segment to the `index`th chart.
+	pub fn set(&self, render_pass: &mut wgpu::RenderPass<'_>, slot: usize, index: usize) {
+		render_pass.set_pipeline(&self.pipeline);
+		self.actions[slot].1.set(render_pass);
+		self.sources[index].1.set(render_pass);
+	}
+}
+
+fn create_bind_group(
+	resources: &Resources,
+	action_buffer: &BindingBuffer<SmudgeAction>,
+	shape_texture: &wgpu::TextureView,
+	shape_sampler: &wgpu::Sampler,
+) -> BindGroup0 {
+	// This must match the layout the pipeline was created with in `Smudge::new`.
+	resources
+		.smudge
+		.pipeline_layout()
+		.shape_texture_filterable(true)
+		.shape_sampler_filtering(wgpu::SamplerBindingType::Filtering)
+		.selection_mask_filterable(false)
+		.smudge_source_filterable(false)
+		.get()
+		.bind_group_layouts()
+		.0
+		.bind_group()
+		.action(action_buffer.as_entire_buffer_binding())
+		.shape_texture(shape_texture)
+		.shape_sampler(shape_sampler)
+		.create()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{Airbrush, Atlas, InputPoint};
+	use crate::test;
+	use glam::*;
+
+	#[test]
+	fn smudge_drags_paint() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Resources::new(device);
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let color = vec4(0.6, 0.1, -0.05, 1.0);
+		test::paint_left_half(&mut atlas, color);
+
+		// Smudge rightwards across the edge of the paint.
+		let mut airbrush = Airbrush::new(device, queue, &resources, format);
+		airbrush.set_mode(BrushMode::Smudge);
+		let point = test::input_point(16.0, Vec3::ZERO);
+		let points = (0..=8).map(|i| InputPoint {
+			position: dvec2(100.0 + 6.0 * i as f64, 128.0),
+			..point
+		});
+		airbrush.draw_stroke(queue, &mut atlas, points)?;
+
+		let sample = |point| pollster::block_on(atlas.sample(point));
+		// Paint is dragged into the empty half along the stroke, keeping its color.
+		let dragged = sample(vec2(130.5, 128.5))?;
+		assert!(dragged.w > 0.1, "{dragged}");
+		assert!(dragged.xyz().abs_diff_eq(color.xyz(), 0.1), "{dragged}");
+		// Nothing changes away from the stroke.
+		assert_eq!(sample(vec2(130.5, 200.5))?.w, 0.0);
+		let untouched = sample(vec2(100.5, 200.5))?;
+		assert!(untouched.abs_diff_eq(color, 1e-2), "{untouched}");
+		Ok(())
+	}
+}
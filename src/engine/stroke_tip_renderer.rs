@@ -0,0 +1,204 @@
+use crate::render::{error_scope, BindingBuffer, CapturedCommand, FrameCapturer, Resources};
+use crate::shaders::{self, stroke_tip::StrokeTip};
+use crate::util::input_interpolate::InputPoint;
+use crate::view_transform::ViewTransform;
+use glam::*;
+use std::sync::Arc;
+
+/// Draws a provisional tip on the stroke being drawn, from where it has been drawn to where the
+/// pointer is predicted to be, over whatever has already been drawn. Nothing is kept, so each frame
+/// corrects the last one's prediction once the pointer's real points are drawn.
+pub struct StrokeTipRenderer {
+	resources: Arc<Resources>,
+	tip_buffer: BindingBuffer<StrokeTip>,
+	bind_group: shaders::stroke_tip::BindGroup0,
+}
+
+impl StrokeTipRenderer {
+	pub fn new(device: &wgpu::Device, resources: Arc<Resources>) -> Self {
+		let tip_buffer = BindingBuffer::new_sized()
+			.label("StrokeTipRenderer::tip_buffer")
+			.usage(wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST)
+			.create(device);
+		let bind_group = error_scope(device, "StrokeTipRenderer::bind_group", || {
+			resources
+				.stroke_tip
+				.pipeline_layout()
+				.get()
+				.bind_group_layouts()
+				.0
+				.bind_group()
+				.tip(tip_buffer.as_entire_buffer_binding())
+				.create()
+		});
+		Self {
+			resources,
+			tip_buffer,
+			bind_group,
+		}
+	}
+
+	/// Creates a pipeline drawing to `target_format`. sRGB targets are written linear colors, since
+	/// they encode colors themselves, and others are written encoded colors. Passes with a
+	/// depth/stencil attachment need a matching `depth_stencil`.
+	pub fn create_pipeline(
+		&self,
+		target_format: wgpu::TextureFormat,
+		sample_count: u32,
+		depth_stencil: Option<wgpu::DepthStencilState>,
+	) -> wgpu::RenderPipeline {
+		self
+			.resources
+			.stroke_tip
+			.pipeline_layout()
+			.get()
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(shaders::stroke_tip::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: target_format,
+					blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.multisample(wgpu::MultisampleState {
+				count: sample_count,
+				..Default::default()
+			})
+			.maybe_depth_stencil(depth_stencil)
+			.overrides(shaders::stroke_tip::OverrideConstants {
+				linear_output: Some(target_format.is_srgb()),
+			})
+			.maybe_cache(self.resources.pipeline_cache())
+			.get()
+	}
+
+	/// Computes the shader tip from `start`, where the stroke has been drawn to, to `end`, where the
+	/// pointer is predicted to be, both in screen pixels on a screen of `screen_size`. The brush is
+	/// `radius` at full pressure, in canvas units, and scales with the view's zoom. `color` is in
+	/// Oklab.
+	pub fn tip(
+		view_transform: &ViewTransform,
+		screen_size: DVec2,
+		start: InputPoint,
+		end: InputPoint,
+		radius: f64,
+		color: Vec3,
+		opacity: f32,
+	) -> StrokeTip {
+		let radius = (radius * view_transform.zoom) as f32;
+		StrokeTip {
+			start: vec2(start.x, start.y),
+			end: vec2(end.x, end.y),
+			screen_size: screen_size.as_vec2(),
+			radii: radius * vec2(start.pressure, end.pressure),
+			color,
+			opacity,
+		}
+	}
+
+	/// Writes the tip. This must happen before submitting any passes from [`Self::draw`].
+	pub fn write_tip(&self, queue: &wgpu::Queue, tip: &StrokeTip) {
+		self.tip_buffer.write(queue, tip);
+	}
+
+	/// Draws the tip last written, recording what it issues to `capture`.
+	pub fn draw(
+		&self,
+		render_pass: &mut wgpu::RenderPass<'_>,
+		pipeline: &wgpu::RenderPipeline,
+		capture: &FrameCapturer,
+	) {
+		render_pass.set_pipeline(pipeline);
+		capture.record(|| CapturedCommand::SetPipeline {
+			pipeline: "stroke tip".to_string(),
+		});
+		self.bind_group.set(render_pass);
+		capture.record(|| CapturedCommand::SetBindGroup {
+			index: 0,
+			bind_group: "stroke tip".to_string(),
+		});
+		render_pass.draw(0..4, 0..1);
+		capture.record(|| CapturedCommand::Draw {
+			vertices: 0..4,
+			instances: 0..1,
+			layers: Vec::new(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{render, test};
+
+	#[test]
+	fn fills_from_stroke_to_prediction() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let renderer = StrokeTipRenderer::new(device, Arc::new(Resources::new(device)));
+		let target_format = wgpu::TextureFormat::Rgba8Unorm;
+		let pipeline = renderer.create_pipeline(target_format, 1, None);
+		let target = render::texture()
+			.width(64)
+			.height(48)
+			.format(target_format)
+			.usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+			.create(device);
+
+		// A white brush of radius 3 at 2x zoom, 6 pixels wide at full pressure and 3 at half.
+		let mut view_transform = ViewTransform::default();
+		view_transform.zoom_about(DVec2::ZERO, 2.0);
+		let point = |x: f32, pressure: f32| InputPoint {
+			t: 0.0,
+			x,
+			y: 24.0,
+			pressure,
+		};
+		let tip = StrokeTipRenderer::tip(
+			&view_transform,
+			dvec2(64.0, 48.0),
+			point(16.0, 1.0),
+			point(40.0, 0.5),
+			3.0,
+			vec3(1.0, 0.0, 0.0),
+			1.0,
+		);
+		renderer.write_tip(context.queue(), &tip);
+
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &target.create_view(&Default::default()),
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+			renderer.draw(&mut render_pass, &pipeline, context.frame_capture());
+		}
+		context.queue().submit([encoder.finish()]);
+
+		let data = pollster::block_on(context.get_texture_layer_data(&target, 0))?;
+		let texel = |x: usize, y: usize| {
+			let offset = 4 * (y * 64 + x);
+			&data[offset..offset + 4]
+		};
+		// Filled along the way, tapering towards the prediction, and clear past either end.
+		for (x, y) in [(16, 24), (28, 24), (28, 27), (40, 24), (11, 24)] {
+			let filled = texel(x, y);
+			assert!(filled.iter().all(|&c| c > 240), "({x}, {y}): {filled:?}");
+		}
+		for (x, y) in [(9, 24), (45, 24), (40, 28), (28, 32)] {
+			assert_eq!(texel(x, y), [0, 0, 0, 0], "({x}, {y})");
+		}
+		Ok(())
+	}
+}
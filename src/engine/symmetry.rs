@@ -0,0 +1,154 @@
+use glam::*;
+
+/// The most copies a stroke can be repeated into, including itself.
+pub const MAX_SYMMETRY_COPIES: u32 = 16;
+
+/// How strokes are repeated as they are drawn.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	Default,
+	serde::Serialize,
+	serde::Deserialize,
+	derive_more::Display,
+)]
+pub enum SymmetryKind {
+	#[default]
+	#[display("None")]
+	None,
+	/// Mirrored left to right, across a vertical axis.
+	#[display("Vertical")]
+	Vertical,
+	/// Mirrored top to bottom, across a horizontal axis.
+	#[display("Horizontal")]
+	Horizontal,
+	/// Rotated into this many evenly spaced copies.
+	#[display("Radial {_0}")]
+	Radial(u32),
+}
+
+impl SymmetryKind {
+	pub const ALL: [Self; 7] = [
+		Self::None,
+		Self::Vertical,
+		Self::Horizontal,
+		Self::Radial(3),
+		Self::Radial(4),
+		Self::Radial(6),
+		Self::Radial(8),
+	];
+}
+
+/// Repeats strokes about a center, in canvas coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Symmetry {
+	pub kind: SymmetryKind,
+	pub center: Vec2,
+}
+
+impl Symmetry {
+	/// The transforms from a stroke to each of its copies, starting with the identity. There are at
+	/// most [`MAX_SYMMETRY_COPIES`].
+	pub fn transforms(&self) -> Vec<Affine2> {
+		self
+			.transforms_f64()
+			.iter()
+			.map(|t| Affine2::from_mat2_translation(t.matrix2.as_mat2(), t.translation.as_vec2()))
+			.collect()
+	}
+
+	/// Like [`Self::transforms`], but in `f64`, so copies of strokes far from the center land as
+	/// precisely as the strokes themselves.
+	pub fn transforms_f64(&self) -> Vec<DAffine2> {
+		let center = self.center.as_dvec2();
+		let about_center = |transform: DAffine2| {
+			DAffine2::from_translation(center) * transform * DAffine2::from_translation(-center)
+		};
+		match self.kind {
+			SymmetryKind::None => vec![DAffine2::IDENTITY],
+			SymmetryKind::Vertical => vec![
+				DAffine2::IDENTITY,
+				about_center(DAffine2::from_scale(dvec2(-1.0, 1.0))),
+			],
+			SymmetryKind::Horizontal => vec![
+				DAffine2::IDENTITY,
+				about_center(DAffine2::from_scale(dvec2(1.0, -1.0))),
+			],
+			SymmetryKind::Radial(folds) => {
+				let folds = folds.clamp(1, MAX_SYMMETRY_COPIES);
+				let angle = std::f64::consts::TAU / folds as f64;
+				(0..folds)
+					.map(|i| about_center(DAffine2::from_angle(i as f64 * angle)))
+					.collect()
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_images(symmetry: Symmetry, point: Vec2, expected: &[Vec2]) {
+		let images: Vec<_> = symmetry
+			.transforms()
+			.iter()
+			.map(|t| t.transform_point2(point))
+			.collect();
+		assert_eq!(images.len(), expected.len(), "{images:?}");
+		for (image, expected) in images.iter().zip(expected) {
+			assert!(image.abs_diff_eq(*expected, 1e-4), "{images:?}");
+		}
+	}
+
+	#[test]
+	fn mirrors() {
+		let center = vec2(100.0, -20.0);
+		let point = vec2(130.0, 10.0);
+		assert_images(Symmetry::default(), point, &[point]);
+		assert_images(
+			Symmetry {
+				kind: SymmetryKind::Vertical,
+				center,
+			},
+			point,
+			&[point, vec2(70.0, 10.0)],
+		);
+		assert_images(
+			Symmetry {
+				kind: SymmetryKind::Horizontal,
+				center,
+			},
+			point,
+			&[point, vec2(130.0, -50.0)],
+		);
+	}
+
+	#[test]
+	fn radial() {
+		let symmetry = Symmetry {
+			kind: SymmetryKind::Radial(4),
+			center: vec2(10.0, 10.0),
+		};
+		assert_images(
+			symmetry,
+			vec2(20.0, 10.0),
+			&[
+				vec2(20.0, 10.0),
+				vec2(10.0, 20.0),
+				vec2(0.0, 10.0),
+				vec2(10.0, 0.0),
+			],
+		);
+
+		let many = Symmetry {
+			kind: SymmetryKind::Radial(1000),
+			..symmetry
+		};
+		assert_eq!(many.transforms().len(), MAX_SYMMETRY_COPIES as usize);
+	}
+}
@@ -1,50 +1,17 @@
-use crate::render::BindingBuffer;
+use crate::render::{
+	self, BindingBuffer, BindingKey, CapturedCommand, GrowableBindingBuffer, Resources,
+};
 use crate::{
-	shaders::tile_read as read, shaders::tile_write as write, shaders::TileData, util::QueueExt,
-	WgpuContext,
+	shaders::copy_transform, shaders::tile_read as read, shaders::tile_write as write,
+	shaders::TileData, util::QueueExt, WgpuContext,
 };
 use bon::bon;
-use encase::ShaderSize;
 use encase::ShaderType;
+use glam::{uvec2, vec2, Affine2, IVec2, Mat2, UVec2, Vec2};
 use std::future::Future;
-use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-use wgpu::{util::DeviceExt, BufferAddress, Extent3d};
-
-struct StableVec<T> {
-	vec: Mutex<Vec<Pin<Box<T>>>>,
-}
-
-impl<T> Default for StableVec<T> {
-	fn default() -> Self {
-		Self {
-			vec: Default::default(),
-		}
-	}
-}
-
-impl<T> StableVec<T> {
-	pub fn push(&self, value: T) -> usize {
-		let mut vec = self.vec.lock().unwrap();
-		let index = vec.len();
-		vec.push(Box::pin(value));
-		index
-	}
-
-	pub fn len(&self) -> usize {
-		self.vec.lock().unwrap().len()
-	}
-}
-
-impl<T> std::ops::Index<usize> for StableVec<T> {
-	type Output = T;
-	fn index(&self, index: usize) -> &Self::Output {
-		let vec = self.vec.lock().unwrap();
-		let r: &T = &vec[index];
-		// SAFETY: `r` remains pinned for the lifetime of `self`.
-		unsafe { std::mem::transmute(r) }
-	}
-}
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+use wgpu::{util::DeviceExt, Extent3d};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Extent2d {
@@ -115,6 +82,14 @@ struct Index {
 	layer_index: u32,
 }
 
+/// Where a resident tile's texture is held: a layer of one of its pool's texture arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, derive_more::Display)]
+#[display("{block}:{layer}")]
+pub struct TileLayer {
+	pub block: usize,
+	pub layer: u32,
+}
+
 #[derive(Default)]
 struct FreeList {
 	indices: Mutex<Vec<Index>>,
@@ -128,15 +103,51 @@ impl FreeList {
 	fn release(&self, index: Index) {
 		self.indices.lock().unwrap().push(index);
 	}
+
+	fn len(&self) -> usize {
+		self.indices.lock().unwrap().len()
+	}
+}
+
+/// The error when a [`Pool`] needs another layer for a new tile but already holds as many as it
+/// may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("all {max_layers} layers of the tile pool are in use")]
+pub struct PoolExhausted {
+	pub max_layers: u32,
+}
+
+/// How hard to work at compressing evicted tiles. Eviction happens while drawing, so this favors
+/// speed.
+const EVICTION_COMPRESSION_LEVEL: u8 = 1;
+
+/// A snapshot of the GPU memory held by a [`Pool`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+	/// The number of texture arrays allocated.
+	pub blocks: usize,
+	/// The number of layers across all blocks, whether or not they hold a tile.
+	pub layers: u32,
+	/// The number of layers currently holding a tile. Evicted tiles don't count.
+	pub tiles: u32,
+	/// The size of all block textures.
+	pub texture_bytes: u64,
 }
 
 struct PoolInternal {
 	context: Arc<WgpuContext>,
-	blocks: StableVec<Block>,
+	/// Append-only, so references to blocks remain valid without holding a lock while drawing.
+	blocks: boxcar::Vec<Block>,
 	free_list: FreeList,
 	texture_layer_descriptor: TextureLayerDescriptor,
 	read_bind_group_layout: read::BindGroupLayout,
 	write_bind_group_layout: write::BindGroupLayout,
+	/// Advanced whenever a tile is used, so the least recently used tiles can be evicted first.
+	clock: AtomicU64,
+	memory_budget: Mutex<Option<u64>>,
+	/// Limits how many layers the blocks may hold in all. Held while adding a block, so blocks are
+	/// added one at a time.
+	max_layers: Mutex<Option<u32>>,
 }
 
 impl PoolInternal {
@@ -148,33 +159,257 @@ impl PoolInternal {
 		self.free_list.release(index)
 	}
 
-	pub fn allocate_tile(self: Arc<Self>) -> Tile {
-		let index = self.allocate_index();
-		Tile::new(self.clone(), index)
+	fn tick(&self) -> u64 {
+		self.clock.fetch_add(1, Ordering::Relaxed)
+	}
+
+	fn layer_bytes(&self) -> u64 {
+		let descriptor = &self.texture_layer_descriptor;
+		descriptor.size.width as u64
+			* descriptor.size.height as u64
+			* descriptor.format.block_copy_size(None).unwrap_or(0) as u64
 	}
 
+	/// Records which tile occupies a layer, so it can be found for eviction.
+	fn set_owner(&self, index: Index, state: &Arc<Mutex<TileState>>) {
+		let layer = &self.get_block(index.block_index).layers[index.layer_index as usize];
+		*layer.owner.lock().unwrap() = Arc::downgrade(state);
+	}
+
+	/// Overwrites the texture of a layer with tightly packed `texture_data`.
+	fn write_layer_texture(&self, index: Index, texture_data: &[u8]) {
+		let descriptor = &self.texture_layer_descriptor;
+		let size = descriptor.size;
+		let bytes_per_row = descriptor.format.block_copy_size(None).unwrap() * size.width;
+		self.context.queue().write_texture(
+			wgpu::ImageCopyTexture {
+				texture: &self.get_block(index.block_index).texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d {
+					z: index.layer_index,
+					..Default::default()
+				},
+				aspect: wgpu::TextureAspect::All,
+			},
+			texture_data,
+			wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: Some(bytes_per_row),
+				rows_per_image: None,
+			},
+			size.with_array_layers(1),
+		);
+	}
+
+	fn write_layer_data(&self, index: Index, data: &TileData) {
+		// Should the buffer grow, the bind groups of the block notice its generation changed.
+		self
+			.get_block(index.block_index)
+			.data_buffer
+			.write()
+			.unwrap()
+			.write_slice(
+				self.context.device(),
+				self.context.queue(),
+				index.layer_index as u64,
+				std::slice::from_ref(data),
+			);
+	}
+
+	/// The bind group reading `block`, rebuilt if its data buffer has grown since it was created.
+	fn read_bind_group(&self, block: &Block) -> Arc<read::BindGroup> {
+		let data_buffer = block.data_buffer.read().unwrap();
+		let mut read_bind_group = block.read_bind_group.lock().unwrap();
+		let (generation, bind_group) = &*read_bind_group;
+		if *generation == data_buffer.generation() {
+			return bind_group.clone();
+		}
+		let bind_group =
+			Arc::new(self.create_read_bind_group(&block.read_texture_view, &data_buffer));
+		*read_bind_group = (data_buffer.generation(), bind_group.clone());
+		bind_group
+	}
+
+	fn create_read_bind_group(
+		&self,
+		read_texture_view: &wgpu::TextureView,
+		data_buffer: &BindingBuffer<[TileData]>,
+	) -> read::BindGroup {
+		let device = self.context.device();
+		render::error_scope(device, "tile::Block::read_bind_group", || {
+			self
+				.read_bind_group_layout
+				.bind_group()
+				.tile_texture(read_texture_view)
+				.tile_data(data_buffer.as_entire_buffer_binding())
+				.create()
+		})
+	}
+
+	fn stats(&self) -> PoolStats {
+		let layer_bytes = self.layer_bytes();
+		let (blocks, layers) = self
+			.blocks
+			.iter()
+			.fold((0, 0), |(blocks, layers), (_, block)| {
+				(blocks + 1, layers + block.texture.depth_or_array_layers())
+			});
+		PoolStats {
+			blocks,
+			layers,
+			tiles: layers - self.free_list.len() as u32,
+			texture_bytes: layers as u64 * layer_bytes,
+		}
+	}
+
+	pub fn try_allocate_tile(self: Arc<Self>) -> Result<Tile, PoolExhausted> {
+		let index = self.try_allocate_index()?;
+		Ok(Tile::new(self.clone(), index))
+	}
+
+	/// Moves the least recently used tiles out of GPU memory until the rest fit in the budget,
+	/// returning how many were evicted. Their contents are read back and compressed, so this
+	/// finishes asynchronously. Tiles used in the meantime are left alone.
+	fn evict(self: &Arc<Self>) -> impl Future<Output = anyhow::Result<usize>> {
+		use itertools::Itertools;
+
+		let layer_bytes = self.layer_bytes().max(1);
+		let budget = self
+			.memory_budget
+			.lock()
+			.unwrap()
+			.map_or(u64::MAX, |b| b / layer_bytes);
+		let excess = (self.stats().tiles as u64).saturating_sub(budget) as usize;
+
+		let mut candidates = Vec::new();
+		if excess > 0 {
+			for (block_index, block) in self.blocks.iter() {
+				for (layer_index, layer) in block.layers.iter().enumerate() {
+					let Some(state) = layer.owner.lock().unwrap().upgrade() else {
+						continue;
+					};
+					let index = Index {
+						block_index,
+						layer_index: layer_index as u32,
+					};
+					let (last_used, version) = {
+						let state = state.lock().unwrap();
+						// A tile being written to would be read back without the write.
+						if !state.is_resident_at(index) || state.pending_writes > 0 {
+							continue;
+						}
+						(state.last_used, state.version)
+					};
+					candidates.push((last_used, version, index, state));
+				}
+			}
+		}
+
+		let readbacks = candidates
+			.into_iter()
+			.sorted_by_key(|(last_used, ..)| *last_used)
+			.take(excess)
+			.map(|(last_used, version, index, state)| {
+				let texture_data = self.context.get_texture_layer_data(
+					&self.get_block(index.block_index).texture,
+					index.layer_index,
+				);
+				async move { (last_used, version, index, state, texture_data.await) }
+			})
+			.collect_vec();
+
+		let pool = self.clone();
+		async move {
+			let mut evicted = 0;
+			for (last_used, version, index, state, texture_data) in
+				futures::future::join_all(readbacks).await
+			{
+				let texture_data = texture_data?;
+				let mut state = state.lock().unwrap();
+				// A tile written to since it was read back would lose the write, and one used since is no
+				// longer the least recently used.
+				if state.version != version
+					|| state.pending_writes > 0
+					|| state.last_used != last_used
+					|| !state.is_resident_at(index)
+				{
+					continue;
+				}
+				state.residency = Residency::Evicted(
+					miniz_oxide::deflate::compress_to_vec(&texture_data, EVICTION_COMPRESSION_LEVEL)
+						.into(),
+				);
+				pool.release_index(index);
+				evicted += 1;
+			}
+			Ok(evicted)
+		}
+	}
+
+	/// Finds a free layer for a new tile, adding a block if there are none, unless that would take
+	/// the pool past its layer limit.
+	fn try_allocate_index(&self) -> Result<Index, PoolExhausted> {
+		self.allocate_index_within(true)
+	}
+
+	/// Finds a free layer for a tile which already has contents, such as an evicted tile being
+	/// uploaded again or a copy of a tile. These may go past the layer limit, since failing would lose
+	/// the contents, and eviction brings the pool back under it.
 	fn allocate_index(&self) -> Index {
+		self
+			.allocate_index_within(false)
+			.expect("unlimited allocation should succeed")
+	}
+
+	fn allocate_index_within(&self, limited: bool) -> Result<Index, PoolExhausted> {
 		if let Some(index) = self.free_list.try_allocate() {
-			return index;
+			return Ok(index);
 		}
 
-		// TODO: If this were actually multi-threaded, this would be subtly suboptimal because we
-		// could add another block between this call to `len()` and pushing the new block.
-		let block_index = self.blocks.len();
-		let block_size = 1 << (block_index as u32).min(u32::BITS - 1);
-		let block_size = block_size.min(self.context.device().limits().max_texture_array_layers);
+		let max_layers = self.max_layers.lock().unwrap();
+		// Another block may have been added while waiting for the lock.
+		if let Some(index) = self.free_list.try_allocate() {
+			return Ok(index);
+		}
+		let block_index = self.blocks.count();
+		let mut block_size = 1 << (block_index as u32).min(u32::BITS - 1);
+		block_size = block_size.min(self.context.device().limits().max_texture_array_layers);
+		if let (true, Some(max_layers)) = (limited, *max_layers) {
+			block_size = block_size.min(max_layers.saturating_sub(self.stats().layers));
+			if block_size == 0 {
+				return Err(PoolExhausted { max_layers });
+			}
+		}
+
+		let block_index = self.blocks.push(self.create_block(block_size));
+		for layer_index in 1..block_size {
+			self.free_list.release(Index {
+				block_index,
+				layer_index,
+			})
+		}
+		Ok(Index {
+			block_index,
+			layer_index: 0,
+		})
+	}
+
+	fn create_block(&self, block_size: u32) -> Block {
 		assert!(block_size > 0);
 
 		let device = self.context.device();
-		let texture =
-			device.create_texture(&self.texture_layer_descriptor.with_array_layers(block_size));
-
-		let read_texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
-			dimension: Some(wgpu::TextureViewDimension::D2Array),
-			..Default::default()
-		});
+		let (texture, read_texture_view) =
+			render::error_scope(device, "tile::Block::texture", || {
+				let texture =
+					device.create_texture(&self.texture_layer_descriptor.with_array_layers(block_size));
+				let read_texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+					dimension: Some(wgpu::TextureViewDimension::D2Array),
+					..Default::default()
+				});
+				(texture, read_texture_view)
+			});
 
-		let data_buffer = BindingBuffer::<[TileData]>::with_capacity(block_size as u64)
+		let data_buffer = GrowableBindingBuffer::<[TileData]>::with_capacity(block_size as u64)
 			.label("tile::Block::data_buffer")
 			.usage(
 				// When drawing batches, this is bound to storage.
@@ -186,30 +421,14 @@ impl PoolInternal {
 			)
 			.create(device);
 
-		let read_bind_group = self
-			.read_bind_group_layout
-			.bind_group()
-			.tile_texture(&read_texture_view)
-			.tile_data(data_buffer.as_entire_buffer_binding())
-			.create();
+		let read_bind_group = self.create_read_bind_group(&read_texture_view, &data_buffer);
 
-		let block = Block {
+		Block {
 			texture,
+			read_bind_group: Mutex::new((data_buffer.generation(), Arc::new(read_bind_group))),
 			read_texture_view,
-			data_buffer,
-			read_bind_group,
-		};
-		self.blocks.push(block);
-
-		for layer_index in 1..block_size {
-			self.free_list.release(Index {
-				block_index,
-				layer_index,
-			})
-		}
-		Index {
-			block_index,
-			layer_index: 0,
+			data_buffer: RwLock::new(data_buffer),
+			layers: (0..block_size).map(|_| Default::default()).collect(),
 		}
 	}
 }
@@ -225,12 +444,18 @@ impl Pool {
 	}
 
 	pub fn new(context: Arc<WgpuContext>, texture_layer_descriptor: TextureLayerDescriptor) -> Self {
-		let read_bind_group_layout = read::BindGroupLayout::new(
-			context.device().clone(),
-			// TODO: Should this be false given that filtering won't be consistent at tile boudnaries?
-			true,
-		);
-		let write_bind_group_layout = write::BindGroupLayout::new(context.device().clone());
+		let device = context.device();
+		let (read_bind_group_layout, write_bind_group_layout) =
+			render::error_scope(device, "tile::Pool::bind_group_layouts", || {
+				(
+					read::BindGroupLayout::new(
+						device.clone(),
+						// TODO: Should this be false given that filtering won't be consistent at tile boudnaries?
+						true,
+					),
+					write::BindGroupLayout::new(device.clone()),
+				)
+			});
 		Pool {
 			internal: PoolInternal {
 				context,
@@ -239,6 +464,9 @@ impl Pool {
 				texture_layer_descriptor,
 				read_bind_group_layout,
 				write_bind_group_layout,
+				clock: Default::default(),
+				memory_budget: Default::default(),
+				max_layers: Default::default(),
 			}
 			.into(),
 		}
@@ -248,60 +476,210 @@ impl Pool {
 		read::InstanceInput::vertex_buffer_layout(wgpu::VertexStepMode::Instance)
 	}
 
+	/// Allocates a new tile. Panics if the pool is at its layer limit, so where that is possible,
+	/// use [`Self::try_allocate_tile`].
 	pub fn allocate_tile(&self) -> Tile {
-		self.internal.clone().allocate_tile()
+		self.try_allocate_tile().unwrap()
+	}
+
+	/// Allocates a new tile, or fails if the pool is at its layer limit and every layer is in use.
+	pub fn try_allocate_tile(&self) -> Result<Tile, PoolExhausted> {
+		self.internal.clone().try_allocate_tile()
+	}
+
+	pub fn stats(&self) -> PoolStats {
+		self.internal.stats()
+	}
+
+	/// Sets how much GPU memory tiles may occupy before [`Self::evict`] moves some of them out.
+	/// `None`, the default, means there is no limit.
+	pub fn set_memory_budget(&self, bytes: Option<u64>) {
+		*self.internal.memory_budget.lock().unwrap() = bytes;
+	}
+
+	/// Limits how many layers the pool may hold, across all of its blocks, so a document which
+	/// outgrows the GPU fails to allocate new tiles rather than the device. `None`, the default, means
+	/// there is no limit beyond the device's. Existing blocks are kept even if they exceed it.
+	pub fn set_max_layers(&self, max_layers: Option<u32>) {
+		*self.internal.max_layers.lock().unwrap() = max_layers;
+	}
+
+	pub fn max_layers(&self) -> Option<u32> {
+		*self.internal.max_layers.lock().unwrap()
+	}
+
+	/// Evicts the least recently used tiles until the rest fit in the memory budget, returning how
+	/// many were evicted. Evicted tiles are uploaded again when next used.
+	pub fn evict(&self) -> impl Future<Output = anyhow::Result<usize>> {
+		self.internal.evict()
+	}
+
+	/// A handle that doesn't keep the pool alive. The pool's memory is released once it and all of
+	/// its tiles are dropped.
+	pub fn downgrade(&self) -> WeakPool {
+		WeakPool {
+			internal: Arc::downgrade(&self.internal),
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct WeakPool {
+	internal: Weak<PoolInternal>,
+}
+
+impl WeakPool {
+	/// The pool's stats, or `None` if its memory has been released.
+	pub fn stats(&self) -> Option<PoolStats> {
+		self.internal.upgrade().map(|internal| internal.stats())
+	}
+}
+
+/// Where a tile's contents are kept.
+enum Residency {
+	/// In a layer of the pool.
+	Resident(Index),
+	/// Compressed in CPU memory, leaving the layer free for other tiles.
+	Evicted(Arc<[u8]>),
+}
+
+struct TileState {
+	residency: Residency,
+	/// The last data set, kept so it can be restored along with an evicted texture.
+	data: Option<TileData>,
+	/// When the tile was last used, according to the pool's clock.
+	last_used: u64,
+	/// Advanced by each write, so a readback can tell whether the tile changed after it.
+	version: u64,
+	/// The number of [`TileWrite`]s alive, whose writes may not have been submitted yet.
+	pending_writes: usize,
+	/// Readbacks waiting for the pending writes to be submitted.
+	write_waiters: Vec<futures::channel::oneshot::Sender<()>>,
+}
+
+impl TileState {
+	fn is_resident_at(&self, index: Index) -> bool {
+		matches!(self.residency, Residency::Resident(i) if i == index)
+	}
+}
+
+/// An intent to write to a tile, from recording the commands which write to it until they are
+/// submitted. Readbacks of the tile started in the meantime wait until this is dropped, so that they
+/// are submitted after the write rather than reading what was there before it. Background work, such
+/// as eviction, leaves the tile alone while it is being written.
+#[must_use = "the write is over as soon as this is dropped"]
+pub struct TileWrite {
+	state: Arc<Mutex<TileState>>,
+}
+
+impl Drop for TileWrite {
+	fn drop(&mut self) {
+		let waiters = {
+			let mut state = self.state.lock().unwrap();
+			state.version += 1;
+			state.pending_writes -= 1;
+			if state.pending_writes > 0 {
+				return;
+			}
+			std::mem::take(&mut state.write_waiters)
+		};
+		for waiter in waiters {
+			let _ = waiter.send(());
+		}
 	}
 }
 
 pub struct Tile {
 	pool: Arc<PoolInternal>,
-	index: Index,
-	write_bind_group: write::BindGroup,
-	texture_view: wgpu::TextureView,
-	layer_index_buffer: BindingBuffer<u32>,
+	state: Arc<Mutex<TileState>>,
 }
 
 #[bon]
 impl Tile {
 	pub fn new(pool: Arc<PoolInternal>, index: Index) -> Self {
-		let layer_index = index.layer_index;
-		let block = pool.get_block(index.block_index);
-		let texture_descriptor = &pool.texture_layer_descriptor;
+		let state = Arc::new(Mutex::new(TileState {
+			residency: Residency::Resident(index),
+			data: None,
+			last_used: pool.tick(),
+			version: 0,
+			pending_writes: 0,
+			write_waiters: Vec::new(),
+		}));
+		pool.set_owner(index, &state);
+		Self { pool, state }
+	}
 
-		let layer_index_buffer = BindingBuffer::init_sized(&layer_index)
-			.label("Tile::layer_index_buffer")
-			.usage(wgpu::BufferUsages::UNIFORM)
-			.create(&pool.context.device());
+	/// The layer holding this tile. If the tile was evicted, it is uploaded to a layer again.
+	fn index(&self) -> Index {
+		Self::resident_index(&self.pool, &self.state)
+	}
 
-		let write_bind_group = pool
-			.write_bind_group_layout
-			.bind_group()
-			.tile_data(block.data_buffer.as_entire_buffer_binding())
-			.layer_index(layer_index_buffer.as_entire_buffer_binding())
-			.create();
+	fn resident_index(pool: &Arc<PoolInternal>, state_lock: &Arc<Mutex<TileState>>) -> Index {
+		let mut state = state_lock.lock().unwrap();
+		state.last_used = pool.tick();
+		let texture_data = match &state.residency {
+			Residency::Resident(index) => return *index,
+			Residency::Evicted(compressed) => miniz_oxide::inflate::decompress_to_vec(compressed)
+				.expect("evicted tiles should decompress"),
+		};
 
-		let texture_view = block.texture.create_view(&wgpu::TextureViewDescriptor {
-			label: Some("Tile::view"),
-			format: Some(texture_descriptor.format),
-			dimension: Some(wgpu::TextureViewDimension::D2),
-			aspect: wgpu::TextureAspect::All,
-			base_mip_level: 0,
-			mip_level_count: Some(texture_descriptor.mip_level_count),
-			base_array_layer: index.layer_index,
-			array_layer_count: Some(1),
-		});
+		let index = pool.allocate_index();
+		pool.set_owner(index, state_lock);
+		pool.write_layer_texture(index, &texture_data);
+		if let Some(data) = &state.data {
+			pool.write_layer_data(index, data);
+		}
+		state.residency = Residency::Resident(index);
+		index
+	}
 
-		Self {
-			pool,
-			index,
-			write_bind_group,
-			texture_view,
-			layer_index_buffer,
+	/// The layer holding this tile, or `None` if it is evicted. Unlike drawing it, this doesn't
+	/// upload an evicted tile or count as using it.
+	pub fn layer(&self) -> Option<TileLayer> {
+		match &self.state.lock().unwrap().residency {
+			Residency::Resident(index) => Some(TileLayer {
+				block: index.block_index,
+				layer: index.layer_index,
+			}),
+			Residency::Evicted(_) => None,
+		}
+	}
+
+	/// Declares that commands writing to this tile are about to be recorded. The returned
+	/// [`TileWrite`] must be kept until they are submitted.
+	pub fn begin_write(&self) -> TileWrite {
+		self.state.lock().unwrap().pending_writes += 1;
+		TileWrite {
+			state: self.state.clone(),
 		}
 	}
 
+	/// Resolves once every write pending when this is called has been submitted, or `None` if there
+	/// are none.
+	fn pending_writes_submitted(&self) -> Option<impl Future<Output = ()>> {
+		let mut state = self.state.lock().unwrap();
+		if state.pending_writes == 0 {
+			return None;
+		}
+		let (sender, receiver) = futures::channel::oneshot::channel();
+		state.write_waiters.push(sender);
+		Some(async move {
+			let _ = receiver.await;
+		})
+	}
+
+	/// Whether this tile's contents are out of GPU memory, so using it will upload them again.
+	pub fn is_evicted(&self) -> bool {
+		matches!(self.state.lock().unwrap().residency, Residency::Evicted(_))
+	}
+
+	/// Uploads this tile again if it was evicted, so that using it soon doesn't have to.
+	pub fn prefetch(&self) {
+		self.index();
+	}
+
 	fn get_block(&self) -> &Block {
-		self.pool.get_block(self.index.block_index)
+		self.pool.get_block(self.index().block_index)
 	}
 
 	#[builder]
@@ -312,37 +690,95 @@ impl Tile {
 		#[builder(default)] base_mip_level: u32,
 		mip_level_count: Option<u32>,
 	) -> wgpu::TextureView {
-		let block = self.get_block();
-		block.texture.create_view(&wgpu::TextureViewDescriptor {
-			label,
-			format,
-			dimension: Some(wgpu::TextureViewDimension::D2),
-			aspect: wgpu::TextureAspect::All,
-			base_mip_level,
-			mip_level_count,
-			base_array_layer: self.index.layer_index,
-			array_layer_count: Some(1),
+		let index = self.index();
+		let block = self.pool.get_block(index.block_index);
+		let device = self.pool.context.device();
+		render::error_scope(device, label.unwrap_or("Tile::create_texture_view"), || {
+			block.texture.create_view(&wgpu::TextureViewDescriptor {
+				label,
+				format,
+				dimension: Some(wgpu::TextureViewDimension::D2),
+				aspect: wgpu::TextureAspect::All,
+				base_mip_level,
+				mip_level_count,
+				base_array_layer: index.layer_index,
+				array_layer_count: Some(1),
+			})
 		})
 	}
 
-	pub fn write_bind_group(&self) -> &write::BindGroup {
-		&self.write_bind_group
+	fn get_layer(&self) -> (Index, &Layer) {
+		let index = self.index();
+		let block = self.pool.get_block(index.block_index);
+		(index, &block.layers[index.layer_index as usize])
 	}
 
-	pub fn texture_view(&self) -> &wgpu::TextureView {
-		&self.texture_view
+	/// The bind group for drawing to this tile. It is created on first use and shared by every tile
+	/// which occupies the same layer, until the block's data buffer grows. Hold a
+	/// [`Self::begin_write`] until the draw is submitted.
+	pub fn write_bind_group(&self) -> Arc<write::BindGroup> {
+		let pool = &self.pool;
+		let device = pool.context.device();
+		let (index, layer) = self.get_layer();
+		let layer_index_buffer = layer.layer_index_buffer.get_or_init(|| {
+			BindingBuffer::init_sized(&index.layer_index)
+				.label("Tile::layer_index_buffer")
+				.usage(wgpu::BufferUsages::UNIFORM)
+				.create(device)
+		});
+		let data_buffer = pool
+			.get_block(index.block_index)
+			.data_buffer
+			.read()
+			.unwrap();
+		let mut write_bind_group = layer.write_bind_group.lock().unwrap();
+		if let Some((generation, bind_group)) = &*write_bind_group {
+			if *generation == data_buffer.generation() {
+				return bind_group.clone();
+			}
+		}
+		let bind_group = render::error_scope(device, "Tile::write_bind_group", || {
+			pool
+				.write_bind_group_layout
+				.bind_group()
+				.tile_data(data_buffer.as_entire_buffer_binding())
+				.layer_index(layer_index_buffer.as_entire_buffer_binding())
+				.create()
+		});
+		let bind_group = Arc::new(bind_group);
+		*write_bind_group = Some((data_buffer.generation(), bind_group.clone()));
+		bind_group
 	}
 
-	fn get_buffer_offset(&self) -> BufferAddress {
-		BindingBuffer::<[TileData]>::raw_offset(self.index.layer_index as u64)
+	/// The view of this tile's texture layer. Like [`Self::write_bind_group`], it is created on first
+	/// use and shared by every tile which occupies the same layer.
+	pub fn texture_view(&self) -> &wgpu::TextureView {
+		let texture_descriptor = &self.pool.texture_layer_descriptor;
+		let (index, layer) = self.get_layer();
+		layer.texture_view.get_or_init(|| {
+			let block = self.pool.get_block(index.block_index);
+			render::error_scope(self.pool.context.device(), "Tile::view", || {
+				block.texture.create_view(&wgpu::TextureViewDescriptor {
+					label: Some("Tile::view"),
+					format: Some(texture_descriptor.format),
+					dimension: Some(wgpu::TextureViewDimension::D2),
+					aspect: wgpu::TextureAspect::All,
+					base_mip_level: 0,
+					mip_level_count: Some(texture_descriptor.mip_level_count),
+					base_array_layer: index.layer_index,
+					array_layer_count: Some(1),
+				})
+			})
+		})
 	}
 
 	fn get_copy_texture(&self) -> wgpu::ImageCopyTexture<'_> {
+		let index = self.index();
 		wgpu::ImageCopyTexture {
-			texture: &self.get_block().texture,
+			texture: &self.pool.get_block(index.block_index).texture,
 			mip_level: 0,
 			origin: wgpu::Origin3d {
-				z: self.index.layer_index,
+				z: index.layer_index,
 				..Default::default()
 			},
 			aspect: wgpu::TextureAspect::All,
@@ -353,36 +789,328 @@ impl Tile {
 		&self.pool.context
 	}
 
-	fn device(&self) -> &wgpu::Device {
-		&self.context().device()
-	}
-
 	fn queue(&self) -> &wgpu::Queue {
 		&self.context().queue()
 	}
 
 	pub fn set_data(&self, data: &TileData) {
 		tracing::trace!(?data, "Tile::set_data");
-		self.get_block().data_buffer.write_slice(
-			self.queue(),
-			self.index.layer_index as u64,
-			std::slice::from_ref(data),
-		)
+		let _write = self.begin_write();
+		let index = self.index();
+		self.state.lock().unwrap().data = Some(data.clone());
+		self.pool.write_layer_data(index, data)
 	}
 
 	pub fn fill_texture(&self, pixel_data: &[u8]) {
+		let _write = self.begin_write();
+		let index = self.index();
 		self.queue().fill_texture_layer(
-			&self.get_block().texture,
+			&self.pool.get_block(index.block_index).texture,
 			pixel_data,
-			self.index.layer_index,
+			index.layer_index,
+		);
+	}
+
+	/// Overwrites the texture with tightly packed `texture_data`, as returned by
+	/// [`Self::get_texture_data`].
+	pub fn write_texture(&self, texture_data: &[u8]) {
+		let _write = self.begin_write();
+		self.pool.write_layer_texture(self.index(), texture_data);
+	}
+
+	pub fn get_texture_data(&self) -> impl Future<Output = anyhow::Result<Vec<u8>>> {
+		self.get_texture_region_data(UVec2::ZERO, self.pool.texture_layer_descriptor.size)
+	}
+
+	/// Reads back the texels of this tile's texture in the rectangle from `origin` of `size`, tightly
+	/// packed in rows. The contents are captured immediately, unless a [`TileWrite`] is pending, in
+	/// which case they are captured once its write is submitted.
+	pub fn get_texture_region_data(
+		&self,
+		origin: glam::UVec2,
+		size: Extent2d,
+	) -> impl Future<Output = anyhow::Result<Vec<u8>>> {
+		use futures::future::Either;
+		let read = move |pool: &PoolInternal, index: Index| {
+			pool.context.get_texture_region_data(
+				&pool.get_block(index.block_index).texture,
+				wgpu::Origin3d {
+					x: origin.x,
+					y: origin.y,
+					z: index.layer_index,
+				},
+				Extent3d {
+					width: size.width,
+					height: size.height,
+					depth_or_array_layers: 1,
+				},
+			)
+		};
+		match self.pending_writes_submitted() {
+			None => Either::Left(read(&self.pool, self.index())),
+			Some(submitted) => {
+				let pool = self.pool.clone();
+				let state = self.state.clone();
+				Either::Right(async move {
+					submitted.await;
+					// Otherwise, its layer may already hold another tile.
+					anyhow::ensure!(
+						Arc::strong_count(&state) > 1,
+						"tile was dropped before it could be read back"
+					);
+					read(&pool, Self::resident_index(&pool, &state)).await
+				})
+			}
+		}
+	}
+
+	/// Records a copy of this tile's texture into `destination`, a single layer of the same size and
+	/// format.
+	pub fn copy_texture_to(&self, encoder: &mut wgpu::CommandEncoder, destination: &wgpu::Texture) {
+		let index = self.index();
+		let size = self.pool.texture_layer_descriptor.size;
+		encoder.copy_texture_to_texture(
+			wgpu::ImageCopyTexture {
+				texture: &self.pool.get_block(index.block_index).texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d {
+					x: 0,
+					y: 0,
+					z: index.layer_index,
+				},
+				aspect: wgpu::TextureAspect::All,
+			},
+			destination.as_image_copy(),
+			size.with_array_layers(1),
 		);
 	}
 
+	/// Records a draw of the texels of `source` in the rectangle from `origin` of `size` into this
+	/// tile, with their top-left corner at `destination`. Unlike a texture copy, `source` may have any
+	/// format which can be sampled as floating point, and is converted to the tile's format. Only its
+	/// first layer and mip level are read. Hold a [`Self::begin_write`] until `encoder` is submitted.
+	#[builder]
+	pub fn copy_from_texture(
+		&self,
+		resources: &Resources,
+		encoder: &mut wgpu::CommandEncoder,
+		source: &wgpu::Texture,
+		#[builder(default)] origin: UVec2,
+		/// Defaults to the rest of `source` from `origin`.
+		size: Option<Extent2d>,
+		#[builder(default)] destination: IVec2,
+		#[builder(default = wgpu::BlendState::REPLACE)] blend: wgpu::BlendState,
+	) {
+		let size = size.unwrap_or(Extent2d {
+			width: source.width() - origin.x,
+			height: source.height() - origin.y,
+		});
+		let source_view =
+			render::error_scope(self.context().device(), "Tile::copy_from_texture", || {
+				source.create_view(&wgpu::TextureViewDescriptor {
+					label: Some("Tile::copy_from_texture"),
+					dimension: Some(wgpu::TextureViewDimension::D2),
+					mip_level_count: Some(1),
+					array_layer_count: Some(1),
+					..Default::default()
+				})
+			});
+		// The whole source is drawn, and the scissor keeps what is outside of the region.
+		let offset = destination - origin.as_ivec2();
+		let scissor = (
+			destination,
+			destination + uvec2(size.width, size.height).as_ivec2(),
+		);
+		self.draw_transformed(
+			resources,
+			encoder,
+			(
+				&source_view,
+				// Textures are copied from once, as when importing, so their bind groups aren't cached,
+				// which would keep them alive.
+				None,
+				source.format(),
+				uvec2(source.width(), source.height()),
+			),
+			Affine2::from_translation(offset.as_vec2()),
+			Some(scissor),
+			blend,
+		);
+	}
+
+	/// Records a draw of `source`, another tile, into this tile. `transform` maps texels of `source`
+	/// to texels of this tile, and is sampled bilinearly unless it only translates by whole texels.
+	/// The tiles may belong to pools with different formats, and texels of this tile which the
+	/// transformed `source` doesn't cover are left unchanged. Hold a [`Self::begin_write`] until
+	/// `encoder` is submitted.
+	#[builder]
+	pub fn blit_from(
+		&self,
+		resources: &Resources,
+		encoder: &mut wgpu::CommandEncoder,
+		source: &Tile,
+		#[builder(default)] transform: Affine2,
+		#[builder(default = wgpu::BlendState::REPLACE)] blend: wgpu::BlendState,
+	) {
+		assert!(
+			!Arc::ptr_eq(&self.state, &source.state),
+			"a tile can't be drawn into itself"
+		);
+		let source_descriptor = &source.pool.texture_layer_descriptor;
+		let source_size = uvec2(source_descriptor.size.width, source_descriptor.size.height);
+		self.draw_transformed(
+			resources,
+			encoder,
+			(
+				source.texture_view(),
+				Some(BindingKey::texture_view(source.texture_view())),
+				source_descriptor.format,
+				source_size,
+			),
+			transform,
+			None,
+			blend,
+		);
+	}
+
+	/// Draws `source`, a view with the key its bind groups are cached by if any, and its format and
+	/// size, through `transform` from its texels to this tile's, optionally clipped to the rectangle
+	/// of texels from the first corner of `scissor` to the second.
+	fn draw_transformed(
+		&self,
+		resources: &Resources,
+		encoder: &mut wgpu::CommandEncoder,
+		(source_view, source_key, source_format, source_size): (
+			&wgpu::TextureView,
+			Option<BindingKey>,
+			wgpu::TextureFormat,
+			UVec2,
+		),
+		transform: Affine2,
+		scissor: Option<(IVec2, IVec2)>,
+		blend: wgpu::BlendState,
+	) {
+		let device = self.context().device();
+		let descriptor = &self.pool.texture_layer_descriptor;
+		let size = uvec2(descriptor.size.width, descriptor.size.height);
+
+		let Some(wgpu::TextureSampleType::Float { filterable }) =
+			source_format.sample_type(None, Some(device.features()))
+		else {
+			panic!("{source_format:?} can't be read as floating point");
+		};
+		// Whole texel translations copy texels exactly, so they don't need filtering.
+		let exact =
+			transform.matrix2 == Mat2::IDENTITY && transform.translation.fract() == Vec2::ZERO;
+		let filter = match filterable && !exact {
+			true => wgpu::FilterMode::Linear,
+			false => wgpu::FilterMode::Nearest,
+		};
+
+		let pipeline_layout = resources
+			.copy_transform
+			.pipeline_layout()
+			.source_texture_filterable(filterable)
+			.source_sampler_filtering(match filterable {
+				true => wgpu::SamplerBindingType::Filtering,
+				false => wgpu::SamplerBindingType::NonFiltering,
+			})
+			.get();
+		let pipeline = pipeline_layout
+			.vs_main_pipeline()
+			.primitive(wgpu::PrimitiveState {
+				topology: wgpu::PrimitiveTopology::TriangleStrip,
+				..Default::default()
+			})
+			.fragment(copy_transform::FragmentEntry::fs_main {
+				targets: [Some(wgpu::ColorTargetState {
+					format: descriptor.format,
+					blend: Some(blend),
+					write_mask: wgpu::ColorWrites::ALL,
+				})],
+			})
+			.maybe_cache(resources.pipeline_cache())
+			.get();
+
+		// The shader draws the source across clip space, so the transform is taken between clip spaces.
+		let clip_to_texels = |size: UVec2| {
+			let size = size.as_vec2();
+			Affine2::from_mat2_translation(Mat2::from_diagonal(vec2(0.5, -0.5) * size), 0.5 * size)
+		};
+		let transform = clip_to_texels(size).inverse() * transform * clip_to_texels(source_size);
+		let create_bind_group = || {
+			render::error_scope(device, "Tile::draw_transformed", || {
+				let transform_buffer = BindingBuffer::init_sized(&copy_transform::Transform {
+					linear: transform.matrix2,
+					translation: transform.translation,
+				})
+				.label("Tile::draw_transformed")
+				.create(device);
+				let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+					mag_filter: filter,
+					min_filter: filter,
+					..Default::default()
+				});
+				let bind_group = pipeline_layout
+					.bind_group_layouts()
+					.0
+					.bind_group()
+					.transform(transform_buffer.as_entire_buffer_binding())
+					.source_texture(source_view)
+					.source_sampler(&sampler)
+					.create();
+				(transform_buffer, sampler, bind_group)
+			})
+		};
+		// Tiles are often drawn from the same tile through the same transform, such as when copying,
+		// so those bind groups are cached.
+		let bind_group = match source_key {
+			Some(source_key) => {
+				let bindings = vec![
+					source_key,
+					BindingKey::contents(&transform.to_cols_array()),
+					BindingKey::contents(&[filter as u8, wgpu::FilterMode::Nearest as u8]),
+				];
+				resources.copy_transform_bind_groups.get_or_create(
+					filterable,
+					bindings,
+					create_bind_group,
+				)
+			}
+			None => Arc::new(create_bind_group()),
+		};
+
+		let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("Tile::draw_transformed"),
+			color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+				view: self.texture_view(),
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Load,
+					store: wgpu::StoreOp::Store,
+				},
+			})],
+			..Default::default()
+		});
+		if let Some((min, max)) = scissor {
+			let min = min.clamp(IVec2::ZERO, size.as_ivec2()).as_uvec2();
+			let max = max.clamp(IVec2::ZERO, size.as_ivec2()).as_uvec2();
+			if min.cmpge(max).any() {
+				return;
+			}
+			render_pass.set_scissor_rect(min.x, min.y, max.x - min.x, max.y - min.y);
+		}
+		render_pass.set_pipeline(&pipeline);
+		bind_group.2.set(&mut render_pass);
+		render_pass.draw(0..4, 0..1);
+	}
+
 	pub fn encode_texture_as_url(&self) -> impl Future<Output = anyhow::Result<String>> {
+		let index = self.index();
 		crate::debug::encode_texture_layer_as_url(
 			self.context(),
-			&self.get_block().texture,
-			self.index.layer_index,
+			&self.pool.get_block(index.block_index).texture,
+			index.layer_index,
 		)
 	}
 }
@@ -390,9 +1118,34 @@ impl Tile {
 impl Clone for Tile {
 	fn clone(&self) -> Self {
 		let pool = &self.pool;
-		let source_block = self.get_block();
+		{
+			// An evicted tile can share its compressed contents without touching the GPU.
+			let state = self.state.lock().unwrap();
+			if let Residency::Evicted(compressed) = &state.residency {
+				return Tile {
+					pool: pool.clone(),
+					state: Arc::new(Mutex::new(TileState {
+						residency: Residency::Evicted(compressed.clone()),
+						data: state.data.clone(),
+						last_used: state.last_used,
+						version: 0,
+						pending_writes: 0,
+						write_waiters: Vec::new(),
+					})),
+				};
+			}
+		}
+
 		let destination = pool.clone().allocate_tile();
-		let destination_block = destination.get_block();
+		let data = self.state.lock().unwrap().data.clone();
+		if let Some(data) = &data {
+			// The data is kept on the CPU too, and the two tiles may share a block's buffer, which
+			// can't be copied to itself.
+			pool.write_layer_data(destination.index(), data);
+		}
+		destination.state.lock().unwrap().data = data;
+		let source_texture = self.get_copy_texture();
+		let destination_texture = destination.get_copy_texture();
 
 		let context = &*pool.context;
 		let queue = context.queue();
@@ -401,16 +1154,9 @@ impl Clone for Tile {
 			.create_command_encoder(&wgpu::CommandEncoderDescriptor {
 				label: Some("Tile::clone"),
 			});
-		encoder.copy_buffer_to_buffer(
-			&source_block.data_buffer,
-			self.get_buffer_offset(),
-			&destination_block.data_buffer,
-			destination.get_buffer_offset(),
-			TileData::SHADER_SIZE.get(),
-		);
 		encoder.copy_texture_to_texture(
-			self.get_copy_texture(),
-			destination.get_copy_texture(),
+			source_texture,
+			destination_texture,
 			pool.texture_layer_descriptor.size.with_array_layers(1),
 		);
 		queue.submit([encoder.finish()]);
@@ -420,15 +1166,41 @@ impl Clone for Tile {
 
 impl Drop for Tile {
 	fn drop(&mut self) {
-		self.pool.release_index(self.index);
+		if let Residency::Resident(index) = self.state.lock().unwrap().residency {
+			self.pool.release_index(index);
+		}
 	}
 }
 
 struct Block {
 	texture: wgpu::Texture,
 	read_texture_view: wgpu::TextureView,
-	data_buffer: BindingBuffer<[TileData]>,
-	read_bind_group: read::BindGroup,
+	/// Locked so it can grow while blocks are shared.
+	data_buffer: RwLock<GrowableBindingBuffer<[TileData]>>,
+	/// Tagged with the generation of `data_buffer` it binds.
+	read_bind_group: Mutex<(u64, Arc<read::BindGroup>)>,
+	layers: Vec<Layer>,
+}
+
+impl Drop for Block {
+	fn drop(&mut self) {
+		// On the web, dropping a handle leaves the memory to the garbage collector, which may not run
+		// for some time. Destroying releases it immediately.
+		self.texture.destroy();
+		self.data_buffer.get_mut().unwrap().destroy();
+	}
+}
+
+/// Per-layer resources, created lazily because most tiles are never drawn to directly. They outlive
+/// any one tile so that reallocating a layer doesn't recreate them.
+#[derive(Default)]
+struct Layer {
+	texture_view: OnceLock<wgpu::TextureView>,
+	layer_index_buffer: OnceLock<BindingBuffer<u32>>,
+	/// Tagged with the generation of the block's data buffer it binds.
+	write_bind_group: Mutex<Option<(u64, Arc<write::BindGroup>)>>,
+	/// The tile most recently allocated to this layer, which may have since moved or been dropped.
+	owner: Mutex<Weak<Mutex<TileState>>>,
 }
 
 fn draw_tile_internal(
@@ -445,21 +1217,31 @@ fn draw_tile_internal(
 		.chunk_by(|i| i.block_index)
 		.into_iter()
 	{
+		let capture = pool.context.frame_capture();
 		let block = pool.get_block(block_index);
-		block.read_bind_group.set(render_pass);
+		pool.read_bind_group(block).set(render_pass);
+		capture.record(|| CapturedCommand::SetBindGroup {
+			index: 1,
+			bind_group: format!("tile block {block_index}"),
+		});
 
 		let layer_indices = block_tile_indices.map(|i| i.layer_index).collect_vec();
+		let device = pool.context.device();
 		let instance_input_buffer =
-			pool
-				.context
-				.device()
-				.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			render::error_scope(device, "draw_tile::instance_input_buffer", || {
+				device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 					label: Some("draw_tile::instance_input_buffer"),
 					usage: wgpu::BufferUsages::VERTEX,
 					contents: bytemuck::cast_slice(&layer_indices),
-				});
+				})
+			});
 		render_pass.set_vertex_buffer(0, instance_input_buffer.slice(..));
 		render_pass.draw(vertices.clone(), 0..(layer_indices.len() as u32));
+		capture.record(|| CapturedCommand::Draw {
+			vertices: vertices.clone(),
+			instances: 0..(layer_indices.len() as u32),
+			layers: layer_indices,
+		});
 	}
 }
 
@@ -473,7 +1255,7 @@ pub fn draw_tiles(
 		render_pass,
 		vertices,
 		&first.pool,
-		tiles.iter().map(|t| t.index),
+		tiles.iter().map(|t| t.index()),
 	);
 }
 
@@ -507,18 +1289,21 @@ mod tests {
 		tiles[0].set_data(&TileData {
 			chart_to_canvas_scale: Vec2::ONE,
 			chart_to_canvas_translation: Vec2::ZERO,
+			chart_offset: IVec2::ZERO,
 		});
 		tiles[0].fill_texture(bytemuck::cast_slice(&[192u8, 64u8, 0u8, 128u8]));
 
 		tiles[1].set_data(&TileData {
 			chart_to_canvas_scale: Vec2::ONE,
 			chart_to_canvas_translation: vec2(-1f32, 0f32),
+			chart_offset: IVec2::ZERO,
 		});
 		tiles[1].fill_texture(bytemuck::cast_slice(&[128u8, 0u8, 64u8, 192u8]));
 
 		tiles[2].set_data(&TileData {
 			chart_to_canvas_scale: Vec2::ONE,
 			chart_to_canvas_translation: vec2(0f32, -1f32),
+			chart_offset: IVec2::ZERO,
 		});
 		tiles[2].fill_texture(bytemuck::cast_slice(&[0u8, 64u8, 128u8, 255u8]));
 
@@ -575,4 +1360,301 @@ mod tests {
 			},
 		)
 	}
+
+	#[test]
+	fn layer_resources_are_lazy_and_reused() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let pool = Pool::new(context.clone(), Default::default());
+
+		let tile = pool.allocate_tile();
+		assert!(tile.get_layer().1.texture_view.get().is_none());
+		assert!(tile
+			.get_layer()
+			.1
+			.write_bind_group
+			.lock()
+			.unwrap()
+			.is_none());
+
+		let texture_view: *const wgpu::TextureView = tile.texture_view();
+		let write_bind_group = tile.write_bind_group();
+		let index = tile.index();
+		drop(tile);
+
+		// The free list returns the most recently released layer.
+		let tile = pool.allocate_tile();
+		assert_eq!(tile.index(), index);
+		assert!(std::ptr::eq(tile.texture_view(), texture_view));
+		assert!(Arc::ptr_eq(&tile.write_bind_group(), &write_bind_group));
+		Ok(())
+	}
+
+	#[test]
+	fn memory_released_with_last_tile() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let texture_layer_descriptor = TextureLayerDescriptor {
+			size: Extent2d {
+				width: 4,
+				height: 4,
+			},
+			..Default::default()
+		};
+		let pool = Pool::new(context.clone(), texture_layer_descriptor);
+		assert_eq!(pool.stats(), PoolStats::default());
+
+		let mut tiles = vec![pool.allocate_tile(), pool.allocate_tile()];
+		assert_eq!(
+			pool.stats(),
+			PoolStats {
+				blocks: 2,
+				layers: 3,
+				tiles: 2,
+				texture_bytes: 3 * 4 * 4 * 4,
+			}
+		);
+
+		// Released layers are kept for reuse while the pool is alive.
+		tiles.pop();
+		let stats = pool.stats();
+		assert_eq!((stats.layers, stats.tiles), (3, 1));
+
+		// Tiles keep the pool alive.
+		let weak = pool.downgrade();
+		drop(pool);
+		assert_eq!(weak.stats().map(|stats| stats.tiles), Some(1));
+		drop(tiles);
+		assert_eq!(weak.stats(), None);
+		Ok(())
+	}
+
+	#[test]
+	fn new_tiles_are_limited_by_max_layers() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let texture_layer_descriptor = TextureLayerDescriptor {
+			size: Extent2d {
+				width: 4,
+				height: 4,
+			},
+			..Default::default()
+		};
+		let pool = Pool::new(context.clone(), texture_layer_descriptor);
+		pool.set_max_layers(Some(4));
+
+		// The third block is cut short to fit.
+		let mut tiles = (0..4)
+			.map(|_| pool.try_allocate_tile())
+			.collect::<Result<Vec<_>, _>>()?;
+		assert_eq!((pool.stats().blocks, pool.stats().layers), (3, 4));
+		assert_eq!(
+			pool.try_allocate_tile().err(),
+			Some(PoolExhausted { max_layers: 4 })
+		);
+
+		// Released layers can be reused.
+		tiles.pop();
+		tiles.push(pool.try_allocate_tile()?);
+
+		// Copies keep their contents even past the limit.
+		tiles.push(tiles[0].clone());
+		assert_eq!(pool.stats().tiles, 5);
+		Ok(())
+	}
+
+	#[test]
+	fn evicted_tiles_are_restored() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let texture_layer_descriptor = TextureLayerDescriptor {
+			size: Extent2d {
+				width: 4,
+				height: 4,
+			},
+			..Default::default()
+		};
+		let layer_bytes = 4 * 4 * 4;
+		let pool = Pool::new(context.clone(), texture_layer_descriptor);
+		pool.set_memory_budget(Some(2 * layer_bytes));
+
+		let tiles = (0..3u8)
+			.map(|i| {
+				let tile = pool.allocate_tile();
+				tile.fill_texture(&[i, 2 * i, 3 * i, 255]);
+				tile
+			})
+			.collect_vec();
+		let data = TileData {
+			chart_to_canvas_scale: Vec2::ONE,
+			chart_to_canvas_translation: Vec2::ZERO,
+			chart_offset: ivec2(1, 2),
+		};
+		tiles[0].set_data(&data);
+		// Use the first tile again, leaving the second least recently used.
+		let _ = tiles[2].index();
+		let _ = tiles[0].index();
+
+		assert_eq!(pollster::block_on(pool.evict())?, 1);
+		assert_eq!(pool.stats().tiles, 2);
+		assert!(matches!(
+			tiles[1].state.lock().unwrap().residency,
+			Residency::Evicted(_)
+		));
+		// Copies of an evicted tile stay evicted.
+		let copy = tiles[1].clone();
+		assert_eq!(pool.stats().tiles, 2);
+
+		// Prefetching a tile, or using it, uploads it again.
+		assert!(tiles[1].is_evicted());
+		tiles[1].prefetch();
+		assert!(!tiles[1].is_evicted());
+		assert!(copy.is_evicted());
+		let expected = [1, 2, 3, 255].repeat(16);
+		assert_eq!(pollster::block_on(tiles[1].get_texture_data())?, expected);
+		assert_eq!(pollster::block_on(copy.get_texture_data())?, expected);
+		assert_eq!(pool.stats().tiles, 4);
+
+		// Within budget, nothing is evicted.
+		pool.set_memory_budget(None);
+		assert_eq!(pollster::block_on(pool.evict())?, 0);
+		Ok(())
+	}
+
+	#[test]
+	fn readbacks_wait_for_pending_writes() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let texture_layer_descriptor = TextureLayerDescriptor {
+			size: Extent2d {
+				width: 4,
+				height: 4,
+			},
+			..Default::default()
+		};
+		let pool = Pool::new(context.clone(), texture_layer_descriptor);
+		let tile = pool.allocate_tile();
+		tile.fill_texture(&[1, 2, 3, 255]);
+
+		// Without a pending write, the current contents are read.
+		let before = tile.get_texture_data();
+		let write = tile.begin_write();
+		let after = tile.get_texture_data();
+		tile.fill_texture(&[4, 5, 6, 255]);
+		drop(write);
+		assert_eq!(pollster::block_on(before)?, [1, 2, 3, 255].repeat(16));
+		assert_eq!(pollster::block_on(after)?, [4, 5, 6, 255].repeat(16));
+
+		// The readback fails rather than reading a layer the tile no longer holds.
+		let write = tile.begin_write();
+		let dropped = tile.get_texture_data();
+		drop(tile);
+		drop(write);
+		assert!(pollster::block_on(dropped).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn eviction_spares_tiles_written_meanwhile() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let texture_layer_descriptor = TextureLayerDescriptor {
+			size: Extent2d {
+				width: 4,
+				height: 4,
+			},
+			..Default::default()
+		};
+		let pool = Pool::new(context.clone(), texture_layer_descriptor);
+		pool.set_memory_budget(Some(0));
+		let tiles = [pool.allocate_tile(), pool.allocate_tile()];
+
+		// Tiles being written aren't read back, and those written during the readback are kept.
+		let write = tiles[0].begin_write();
+		let eviction = pool.evict();
+		drop(tiles[1].begin_write());
+		drop(write);
+		assert_eq!(pollster::block_on(eviction)?, 0);
+		assert_eq!(pool.stats().tiles, 2);
+
+		assert_eq!(pollster::block_on(pool.evict())?, 2);
+		Ok(())
+	}
+
+	#[test]
+	fn copy_from_texture_and_blit() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let resources = render::Resources::new(device);
+		let texture_layer_descriptor = TextureLayerDescriptor {
+			size: Extent2d {
+				width: 4,
+				height: 4,
+			},
+			..Default::default()
+		};
+		let pool = Pool::new(context.clone(), texture_layer_descriptor);
+		let texel = |x: u8, y: u8| [60 * x, 60 * y, 0, 255];
+
+		let source = render::texture()
+			.width(4)
+			.height(4)
+			.format(wgpu::TextureFormat::Rgba8Unorm)
+			.usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+			.create(device);
+		let source_data = (0..4)
+			.flat_map(|y| (0..4).flat_map(move |x| texel(x, y)))
+			.collect_vec();
+		context.queue().write_texture(
+			source.as_image_copy(),
+			&source_data,
+			wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: Some(4 * 4),
+				rows_per_image: None,
+			},
+			source.size(),
+		);
+
+		let [copy, blit] = [(); 2].map(|_| {
+			let tile = pool.allocate_tile();
+			tile.fill_texture(&[0; 4]);
+			tile
+		});
+		let mut encoder = device.create_command_encoder(&Default::default());
+		copy
+			.copy_from_texture()
+			.resources(&resources)
+			.encoder(&mut encoder)
+			.source(&source)
+			.origin(uvec2(1, 1))
+			.size(Extent2d {
+				width: 2,
+				height: 2,
+			})
+			.destination(ivec2(0, 2))
+			.call();
+		blit
+			.blit_from()
+			.resources(&resources)
+			.encoder(&mut encoder)
+			.source(&copy)
+			.transform(Affine2::from_translation(vec2(1.0, -2.0)))
+			.call();
+		context.queue().submit([encoder.finish()]);
+
+		let texels = |tile: &Tile| -> anyhow::Result<Vec<[u8; 4]>> {
+			let data = pollster::block_on(tile.get_texture_data())?;
+			Ok(data
+				.chunks(4)
+				.map(|texel| texel.try_into().unwrap())
+				.collect())
+		};
+		let copied = texels(&copy)?;
+		assert_eq!(copied[4 * 2], texel(1, 1));
+		assert_eq!(copied[4 * 3 + 1], texel(2, 2));
+		assert_eq!(copied[4 * 2 + 2], [0; 4]);
+		assert_eq!(copied[0], [0; 4]);
+
+		// The copied region moves up and to the right, leaving the rest unchanged.
+		let blitted = texels(&blit)?;
+		assert_eq!(blitted[1], texel(1, 1));
+		assert_eq!(blitted[4 + 2], texel(2, 2));
+		assert_eq!(blitted[4 * 3], [0; 4]);
+		Ok(())
+	}
 }
@@ -0,0 +1,388 @@
+use super::export::{split_png_chunk, write_png_chunk, PNG_SIGNATURE};
+use super::{encode_png, thumbnail, Atlas, MalformedPngError, EXPORT_TEXTURE_FORMAT};
+use crate::render::{texture, Resources};
+use crate::WgpuContext;
+use glam::*;
+use std::future::Future;
+use std::sync::Arc;
+use thiserror::Error;
+
+// Frames are kept as PNGs, which are small for a painting in progress, and only assembled into an
+// animated PNG when the timelapse is downloaded. Browsers play animated PNGs like any other image,
+// and encoding one needs nothing beyond the PNG encoder exports already use.
+
+/// The width and height of timelapse frames. The painting is scaled to fit, so the frames stay the
+/// same size however it grows.
+pub const TIMELAPSE_FRAME_SIZE: u32 = 512;
+
+/// Once this many frames are recorded, every other frame is dropped and frames are taken half as
+/// often, so that a long session fits in a bounded amount of memory.
+pub const MAX_TIMELAPSE_FRAMES: usize = 600;
+
+/// How long each frame of a timelapse is shown, in milliseconds.
+pub const DEFAULT_TIMELAPSE_FRAME_DELAY: u16 = 100;
+
+/// The last frame, showing the finished painting, is held at least this long before looping.
+const FINAL_FRAME_DELAY: u16 = 2000;
+
+pub const TIMELAPSE_MIME_TYPE: &str = "image/apng";
+
+/// When a [`TimelapseRecorder`] takes a frame. Frames are only taken as strokes end, since the
+/// painting doesn't change otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelapseTrigger {
+	/// When at least this many milliseconds have passed since the last frame.
+	Interval(f64),
+	/// After every this many strokes.
+	Strokes(u32),
+}
+
+impl Default for TimelapseTrigger {
+	fn default() -> Self {
+		Self::Interval(2000.0)
+	}
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TimelapseError {
+	#[error("no frames have been recorded")]
+	Empty,
+	#[error("frames differ in size or format")]
+	MismatchedFrames,
+	#[error(transparent)]
+	MalformedPng(#[from] MalformedPngError),
+}
+
+/// Collects frames of a painting session as it progresses, to play back as a timelapse.
+#[derive(Debug, Clone)]
+pub struct TimelapseRecorder {
+	trigger: TimelapseTrigger,
+	/// How many times longer than the trigger asks to wait between frames. This doubles each time
+	/// the frames are thinned out.
+	stride: u32,
+	frames: Vec<Vec<u8>>,
+	last_frame_time: Option<f64>,
+	strokes_since_frame: u32,
+}
+
+impl Default for TimelapseRecorder {
+	fn default() -> Self {
+		Self::new(Default::default())
+	}
+}
+
+impl TimelapseRecorder {
+	pub fn new(trigger: TimelapseTrigger) -> Self {
+		Self {
+			trigger,
+			stride: 1,
+			frames: Vec::new(),
+			last_frame_time: None,
+			strokes_since_frame: 0,
+		}
+	}
+
+	pub fn frame_count(&self) -> usize {
+		self.frames.len()
+	}
+
+	/// Notes that a stroke ended at `now`, in milliseconds, and returns whether to capture a frame
+	/// with [`capture_timelapse_frame`].
+	pub fn stroke_finished(&mut self, now: f64) -> bool {
+		self.strokes_since_frame += 1;
+		let due = match (self.trigger, self.last_frame_time) {
+			(_, None) => true,
+			(TimelapseTrigger::Interval(interval), Some(last)) => {
+				now - last >= interval * self.stride as f64
+			}
+			(TimelapseTrigger::Strokes(strokes), Some(_)) => {
+				self.strokes_since_frame >= strokes.max(1) * self.stride
+			}
+		};
+		if due {
+			self.last_frame_time = Some(now);
+			self.strokes_since_frame = 0;
+		}
+		due
+	}
+
+	/// Adds a frame captured by [`capture_timelapse_frame`].
+	pub fn push_frame(&mut self, png: Vec<u8>) {
+		self.frames.push(png);
+		if self.frames.len() >= MAX_TIMELAPSE_FRAMES {
+			// Count from the end, so the latest frame is kept.
+			let len = self.frames.len();
+			let mut index = 0;
+			self.frames.retain(|_| {
+				index += 1;
+				(len - index) % 2 == 0
+			});
+			self.stride *= 2;
+		}
+	}
+
+	/// Encodes the frames as an animated PNG, showing each for `frame_delay` milliseconds.
+	pub fn encode(&self, frame_delay: u16) -> Result<Vec<u8>, TimelapseError> {
+		encode_apng(&self.frames, frame_delay)
+	}
+}
+
+/// Renders `atlas` fit within a square frame of [`TIMELAPSE_FRAME_SIZE`] pixels and encodes it as a
+/// PNG for [`TimelapseRecorder::push_frame`], or returns `None` if the atlas is empty.
+pub fn capture_timelapse_frame(
+	context: &WgpuContext,
+	resources: &Arc<Resources>,
+	atlas: &Atlas,
+) -> Option<impl Future<Output = anyhow::Result<Vec<u8>>>> {
+	let (device, queue) = (context.device(), context.queue());
+	let painting = thumbnail(atlas)
+		.max_size(TIMELAPSE_FRAME_SIZE)
+		.generate(device, queue, resources)?;
+
+	// New textures are transparent, so only the painting needs to be copied, centered in the frame.
+	let frame = texture()
+		.label("capture_timelapse_frame")
+		.width(TIMELAPSE_FRAME_SIZE)
+		.height(TIMELAPSE_FRAME_SIZE)
+		.format(EXPORT_TEXTURE_FORMAT)
+		.usage(wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC)
+		.create(device);
+	let offset =
+		(UVec2::splat(TIMELAPSE_FRAME_SIZE) - uvec2(painting.width(), painting.height())) / 2;
+	let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+		label: Some("capture_timelapse_frame"),
+	});
+	encoder.copy_texture_to_texture(
+		painting.as_image_copy(),
+		wgpu::ImageCopyTexture {
+			texture: &frame,
+			mip_level: 0,
+			origin: wgpu::Origin3d {
+				x: offset.x,
+				y: offset.y,
+				z: 0,
+			},
+			aspect: wgpu::TextureAspect::All,
+		},
+		painting.size(),
+	);
+	queue.submit([encoder.finish()]);
+	Some(encode_png(context, &frame, None))
+}
+
+/// Assembles `frames`, PNGs of the same size and format, into an animated PNG which shows each for
+/// `frame_delay` milliseconds and loops. Viewers which don't support animation show the first frame.
+pub fn encode_apng(frames: &[Vec<u8>], frame_delay: u16) -> Result<Vec<u8>, TimelapseError> {
+	if frames.is_empty() {
+		Err(TimelapseError::Empty)?;
+	}
+
+	let mut apng = PNG_SIGNATURE.to_vec();
+	let mut header = Vec::new();
+	// Frame control and frame data chunks share one sequence.
+	let mut sequence = 0u32;
+	for (index, frame) in frames.iter().enumerate() {
+		let delay = match index + 1 == frames.len() {
+			true => frame_delay.max(FINAL_FRAME_DELAY),
+			false => frame_delay,
+		};
+		let mut chunks = frame.strip_prefix(PNG_SIGNATURE).ok_or(MalformedPngError)?;
+		let mut has_image_data = false;
+		while !chunks.is_empty() {
+			let (chunk_type, chunk) = split_png_chunk(&mut chunks).ok_or(MalformedPngError)?;
+			// Without the length and type before it and the CRC after it.
+			let data = &chunk[8..chunk.len() - 4];
+			match &chunk_type {
+				b"IHDR" if index == 0 => {
+					header = data.to_vec();
+					apng.extend(chunk);
+					let mut animation_control = (frames.len() as u32).to_be_bytes().to_vec();
+					// Loop forever.
+					animation_control.extend(0u32.to_be_bytes());
+					write_png_chunk(&mut apng, *b"acTL", &animation_control);
+				}
+				b"IHDR" if data != header => Err(TimelapseError::MismatchedFrames)?,
+				b"IDAT" => {
+					if header.len() < 8 {
+						Err(MalformedPngError)?;
+					}
+					if !has_image_data {
+						let frame_control = frame_control(sequence, &header, delay);
+						write_png_chunk(&mut apng, *b"fcTL", &frame_control);
+						sequence += 1;
+						has_image_data = true;
+					}
+					// The first frame is also the default image, so its data is left as is.
+					if index == 0 {
+						apng.extend(chunk);
+					} else {
+						let mut frame_data = sequence.to_be_bytes().to_vec();
+						frame_data.extend(data);
+						write_png_chunk(&mut apng, *b"fdAT", &frame_data);
+						sequence += 1;
+					}
+				}
+				b"IEND" => break,
+				// Ancillary chunks, such as the color space, apply to every frame, so only the first
+				// frame's are kept, in their place before the image data.
+				_ if index == 0 && !has_image_data => apng.extend(chunk),
+				_ => {}
+			}
+		}
+		if !has_image_data {
+			Err(MalformedPngError)?;
+		}
+	}
+	write_png_chunk(&mut apng, *b"IEND", &[]);
+	Ok(apng)
+}
+
+/// The data of a frame control chunk for a frame covering the whole image, described by `header`,
+/// and replacing the previous frame.
+fn frame_control(sequence: u32, header: &[u8], delay: u16) -> Vec<u8> {
+	let mut data = sequence.to_be_bytes().to_vec();
+	// The width and height start the header.
+	data.extend(&header[0..8]);
+	// The offset.
+	data.extend([0; 8]);
+	// The delay is a fraction of a second.
+	data.extend(delay.to_be_bytes());
+	data.extend(1000u16.to_be_bytes());
+	// Neither dispose of nor blend with the previous frame.
+	data.extend([0, 0]);
+	data
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{CanvasFormat, ChartKey};
+	use crate::test;
+
+	/// A PNG with the given chunks, which is only well-formed enough to be split into chunks.
+	fn png(chunks: &[([u8; 4], &[u8])]) -> Vec<u8> {
+		let mut png = PNG_SIGNATURE.to_vec();
+		for &(chunk_type, data) in chunks {
+			write_png_chunk(&mut png, chunk_type, data);
+		}
+		png
+	}
+
+	fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+		let mut rest = png.strip_prefix(PNG_SIGNATURE).unwrap();
+		let mut chunks = Vec::new();
+		while !rest.is_empty() {
+			let (chunk_type, chunk) = split_png_chunk(&mut rest).unwrap();
+			chunks.push((chunk_type, chunk[8..chunk.len() - 4].to_vec()));
+		}
+		chunks
+	}
+
+	const HEADER: [u8; 13] = [0, 0, 0, 2, 0, 0, 0, 3, 8, 6, 0, 0, 0];
+
+	#[test]
+	fn assembles_frames() {
+		let frames = [b"first", b"secnd"].map(|data| {
+			png(&[
+				(*b"IHDR", &HEADER),
+				(*b"sRGB", &[0]),
+				(*b"IDAT", data),
+				(*b"IEND", &[]),
+			])
+		});
+		let apng = encode_apng(&frames, 50).unwrap();
+		let chunks = chunks(&apng);
+		let types = chunks.iter().map(|(t, _)| t).collect::<Vec<_>>();
+		assert_eq!(
+			types,
+			[b"IHDR", b"acTL", b"sRGB", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"IEND"]
+		);
+		assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 0]);
+		assert_eq!(chunks[3].1, frame_control(0, &HEADER, 50));
+		assert_eq!(chunks[4].1, b"first");
+		assert_eq!(chunks[5].1, frame_control(1, &HEADER, FINAL_FRAME_DELAY));
+		assert_eq!(chunks[6].1, [&[0, 0, 0, 2], &b"secnd"[..]].concat());
+	}
+
+	#[test]
+	fn rejects_mismatched_frames() {
+		let mut other_header = HEADER;
+		other_header[3] = 4;
+		let frames = [HEADER, other_header]
+			.map(|header| png(&[(*b"IHDR", &header), (*b"IDAT", b"data"), (*b"IEND", &[])]));
+		assert_eq!(
+			encode_apng(&frames, 50),
+			Err(TimelapseError::MismatchedFrames)
+		);
+		assert_eq!(encode_apng(&[], 50), Err(TimelapseError::Empty));
+		assert_eq!(
+			encode_apng(&[png(&[(*b"IHDR", &HEADER)])], 50),
+			Err(MalformedPngError.into())
+		);
+	}
+
+	#[test]
+	fn triggers_frames() {
+		let mut recorder = TimelapseRecorder::new(TimelapseTrigger::Interval(1000.0));
+		assert!(recorder.stroke_finished(0.0));
+		assert!(!recorder.stroke_finished(500.0));
+		assert!(recorder.stroke_finished(1000.0));
+		assert!(!recorder.stroke_finished(1999.0));
+
+		let mut recorder = TimelapseRecorder::new(TimelapseTrigger::Strokes(3));
+		let frames = (0..7)
+			.map(|i| recorder.stroke_finished(i as f64))
+			.collect::<Vec<_>>();
+		assert_eq!(frames, [true, false, false, true, false, false, true]);
+	}
+
+	#[test]
+	fn thins_frames_when_full() {
+		let mut recorder = TimelapseRecorder::new(TimelapseTrigger::Strokes(1));
+		for i in 0..MAX_TIMELAPSE_FRAMES {
+			recorder.push_frame(i.to_be_bytes().to_vec());
+		}
+		assert_eq!(recorder.frame_count(), MAX_TIMELAPSE_FRAMES / 2);
+		assert_eq!(
+			recorder.frames.last().unwrap(),
+			&(MAX_TIMELAPSE_FRAMES - 1).to_be_bytes()
+		);
+
+		// Frames are now taken half as often.
+		assert!(recorder.stroke_finished(0.0));
+		assert!(!recorder.stroke_finished(1.0));
+		assert!(recorder.stroke_finished(2.0));
+	}
+
+	#[test]
+	fn captured_frames_have_a_fixed_size() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let resources = Arc::new(Resources::new(context.device()));
+		let mut atlas = Atlas::new((*context).clone(), CanvasFormat::Rgba8Unorm);
+		assert!(capture_timelapse_frame(&context, &resources, &atlas).is_none());
+
+		let pixel = atlas.format().encode_pixels(&[vec4(1.0, 0.0, 0.0, 1.0)]);
+		atlas
+			.get_chart_mut(ChartKey(0, 0))
+			.tile()
+			.fill_texture(&pixel);
+		let frames = (0..2)
+			.map(|_| {
+				pollster::block_on(capture_timelapse_frame(&context, &resources, &atlas).unwrap())
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+		let size = TIMELAPSE_FRAME_SIZE.to_be_bytes();
+		assert_eq!(chunks(&frames[0])[0].1[0..8], [size, size].concat());
+
+		let mut recorder = TimelapseRecorder::default();
+		frames
+			.into_iter()
+			.for_each(|frame| recorder.push_frame(frame));
+		let apng = recorder.encode(DEFAULT_TIMELAPSE_FRAME_DELAY)?;
+		assert_eq!(
+			chunks(&apng).iter().filter(|(t, _)| t == b"fcTL").count(),
+			2
+		);
+		Ok(())
+	}
+}
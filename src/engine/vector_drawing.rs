@@ -0,0 +1,456 @@
+use super::versioned::{self, Versioned, VersionedError};
+use super::{Airbrush, Atlas, BrushMode, InputPoint, PoolExhausted, Symmetry};
+use crate::coordinates::ScreenPx;
+use crate::util::input_interpolate::Bezier;
+use crate::view_transform::ViewTransform;
+use glam::*;
+use itertools::Itertools;
+
+// Strokes are kept as the curves the stabilizer fit to the pointer's path, in canvas units, rather
+// than as the points sampled from them for drawing. Unlike the atlas, they can be drawn again at
+// any resolution, and unlike the action log, they are kept for the whole document.
+
+/// The version of vector drawings written by this build. Older versions are read, newer ones are
+/// not.
+pub const VECTOR_DRAWING_VERSION: u32 = 1;
+
+/// The most canvas pixels between the points a segment is sampled at when it is replayed.
+const REPLAY_SAMPLE_SPACING: f32 = 2.0;
+
+/// The most points a segment is sampled at when it is replayed, however long it is.
+const MAX_REPLAY_SAMPLES: usize = 256;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum VectorDrawingError {
+	#[error("malformed vector drawing: {0}")]
+	Malformed(String),
+	#[error("unsupported vector drawing version {0}")]
+	UnsupportedVersion(u32),
+}
+
+impl From<VersionedError> for VectorDrawingError {
+	fn from(err: VersionedError) -> Self {
+		match err {
+			VersionedError::Malformed(err) => Self::Malformed(err),
+			VersionedError::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+		}
+	}
+}
+
+/// A cubic Bezier segment of a stroke's path. Its control points are canvas positions with the
+/// pressure as the third coordinate.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct VectorSegment {
+	pub points: [Vec3; 4],
+	/// The stylus tilt relative to the canvas when the segment was fit. See [`InputPoint::tilt`].
+	pub tilt: Vec2,
+}
+
+impl VectorSegment {
+	/// Maps a segment of the stabilized stroke, in screen pixels, onto the canvas. The view is a
+	/// similarity, so mapping the control points maps the curve exactly.
+	pub fn from_screen(segment: Bezier<Vec3>, view: &ViewTransform, tilt: Vec2) -> Self {
+		let segment = segment.map(|p| {
+			let position = ScreenPx(p.xy().as_dvec2()).to_canvas(view).as_vec2();
+			position.extend(p.z)
+		});
+		Self {
+			points: segment.control_points(),
+			tilt: Vec2::from_angle(-view.rotation as f32).rotate(tilt),
+		}
+	}
+
+	/// A straight segment from `start` to `end`, in canvas coordinates, with a constant `pressure`.
+	pub fn line(start: Vec2, end: Vec2, pressure: f32) -> Self {
+		let points = [
+			start,
+			start.lerp(end, 1.0 / 3.0),
+			start.lerp(end, 2.0 / 3.0),
+			end,
+		];
+		Self {
+			points: points.map(|p| p.extend(pressure)),
+			tilt: Vec2::ZERO,
+		}
+	}
+
+	/// The point a fraction `s` of the way along the segment's parameter.
+	pub fn evaluate(&self, s: f32) -> Vec3 {
+		let [p0, p1, p2, p3] = self.points;
+		let (q0, q1, q2) = (p0.lerp(p1, s), p1.lerp(p2, s), p2.lerp(p3, s));
+		q0.lerp(q1, s).lerp(q1.lerp(q2, s), s)
+	}
+
+	/// The length of the control polygon, which is never shorter than the curve.
+	fn polygon_length(&self) -> f32 {
+		self
+			.points
+			.windows(2)
+			.map(|w| w[0].xy().distance(w[1].xy()))
+			.sum()
+	}
+
+	/// Whether a brush of `size` passes over `point` along the segment, sampled as it is replayed.
+	fn covers(&self, point: Vec2, size: f32) -> bool {
+		let count = (self.polygon_length() / REPLAY_SAMPLE_SPACING)
+			.ceil()
+			.clamp(1.0, MAX_REPLAY_SAMPLES as f32) as usize;
+		(0..=count)
+			.map(|i| self.evaluate(i as f32 / count as f32))
+			.tuple_windows()
+			.any(|(a, b)| {
+				let d = b.xy() - a.xy();
+				let t = ((point - a.xy()).dot(d) / d.length_squared()).clamp(0.0, 1.0);
+				let nearest = a.lerp(b, if t.is_nan() { 0.0 } else { t });
+				point.distance(nearest.xy()) <= size * nearest.z.clamp(0.0, 1.0)
+			})
+	}
+}
+
+/// A stroke's path along with the brush it was drawn with.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct VectorStroke {
+	pub color: Vec3,
+	/// In canvas pixels.
+	pub size: f32,
+	pub opacity: f32,
+	pub rate: f32,
+	pub mode: BrushMode,
+	pub spacing: f32,
+	pub scatter: f32,
+	/// Strokes from before this was configurable had no grain.
+	#[serde(default)]
+	pub grain: f32,
+	/// Strokes from before this was configurable couldn't mix, so it didn't matter.
+	#[serde(default)]
+	pub pickup: f32,
+	pub symmetry: Symmetry,
+	/// The airbrush's seed, so replays spray the same stamps.
+	pub seed: u64,
+	pub segments: Vec<VectorSegment>,
+}
+
+impl VectorStroke {
+	/// Whether the brush passed over `point`, a canvas position, in any of its symmetric copies.
+	/// Tilt and scatter aren't accounted for.
+	pub fn covers(&self, point: Vec2) -> bool {
+		self.symmetry.transforms().iter().any(|transform| {
+			// Symmetries preserve distances, so the point is mapped back to the stroke's own path.
+			let point = transform.inverse().transform_point2(point);
+			self
+				.segments
+				.iter()
+				.any(|segment| segment.covers(point, self.size))
+		})
+	}
+
+	/// Draws the stroke to `atlas`, with the canvas scaled by `scale`.
+	pub(super) fn replay(
+		&self,
+		queue: &wgpu::Queue,
+		atlas: &mut Atlas,
+		airbrush: &mut Airbrush,
+		scale: f32,
+	) -> Result<(), PoolExhausted> {
+		let mut result = Ok(());
+		self.start(airbrush, scale);
+		for input in self.input_points(scale) {
+			if let Some(drawable) = airbrush.drag(queue, input) {
+				result = result.and(atlas.draw(&drawable));
+			}
+		}
+		airbrush.stop();
+		result
+	}
+
+	/// Sets `airbrush` up with the stroke's brush, with the canvas scaled by `scale`, and starts a
+	/// stroke. The stroke is drawn by dragging the airbrush through [`Self::input_points`].
+	pub fn start(&self, airbrush: &mut Airbrush, scale: f32) {
+		airbrush.set_symmetry(Symmetry {
+			center: scale * self.symmetry.center,
+			..self.symmetry
+		});
+		airbrush.set_mode(self.mode);
+		airbrush.set_spacing(self.spacing);
+		airbrush.set_scatter(self.scatter);
+		airbrush.set_grain(self.grain);
+		airbrush.set_pickup(self.pickup);
+		airbrush.set_seed(Some(self.seed));
+		airbrush.start();
+	}
+
+	/// The points the stroke is drawn through, sampled along its path, with the canvas scaled by
+	/// `scale`.
+	pub fn input_points(&self, scale: f32) -> impl Iterator<Item = InputPoint> + '_ {
+		self
+			.segments
+			.iter()
+			.enumerate()
+			.flat_map(move |(index, segment)| {
+				let count = (scale * segment.polygon_length() / REPLAY_SAMPLE_SPACING)
+					.ceil()
+					.clamp(1.0, MAX_REPLAY_SAMPLES as f32) as usize;
+				// The start of each segment is the end of the last.
+				let first = if index == 0 { 0 } else { 1 };
+				(first..=count).map(move |i| {
+					let point = segment.evaluate(i as f32 / count as f32);
+					InputPoint {
+						position: (scale * point.xy()).as_dvec2(),
+						pressure: point.z.clamp(0.0, 1.0),
+						color: self.color,
+						size: scale * self.size,
+						opacity: self.opacity,
+						rate: self.rate,
+						tilt: segment.tilt,
+					}
+				})
+			})
+	}
+}
+
+/// A stroke of a [`VectorDrawing`], by its position among the drawing's edits. Undoing the stroke
+/// invalidates it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, derive_more::Display)]
+#[display("#{_0}")]
+pub struct StrokeId(pub usize);
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum VectorEdit {
+	Stroke(VectorStroke),
+	/// An edit with no vector form, such as an imported image. It is kept so undo and redo line up
+	/// with the canvas history, but isn't replayed.
+	Raster,
+}
+
+/// Every edit to a document which hasn't been undone, in order, with strokes as vectors. It follows
+/// the canvas [`History`](super::History), so each edit recorded there should be recorded here, and
+/// each successful undo or redo there repeated here.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct VectorDrawing {
+	edits: Vec<VectorEdit>,
+	#[serde(skip)]
+	undone: Vec<VectorEdit>,
+}
+
+impl VectorDrawing {
+	/// Records an edit, which can no longer be redone past.
+	pub fn record(&mut self, edit: VectorEdit) {
+		self.edits.push(edit);
+		self.undone.clear();
+	}
+
+	pub fn undo(&mut self) -> bool {
+		let Some(edit) = self.edits.pop() else {
+			return false;
+		};
+		self.undone.push(edit);
+		true
+	}
+
+	pub fn redo(&mut self) -> bool {
+		let Some(edit) = self.undone.pop() else {
+			return false;
+		};
+		self.edits.push(edit);
+		true
+	}
+
+	pub fn edits(&self) -> impl Iterator<Item = &VectorEdit> {
+		self.edits.iter()
+	}
+
+	pub fn stroke(&self, id: StrokeId) -> Option<&VectorStroke> {
+		match self.edits.get(id.0)? {
+			VectorEdit::Stroke(stroke) => Some(stroke),
+			VectorEdit::Raster => None,
+		}
+	}
+
+	/// The latest stroke which passed over `point`, a canvas position. See [`VectorStroke::covers`].
+	pub fn stroke_at(&self, point: Vec2) -> Option<StrokeId> {
+		self
+			.edits
+			.iter()
+			.enumerate()
+			.rev()
+			.find_map(|(index, edit)| match edit {
+				VectorEdit::Stroke(stroke) if stroke.covers(point) => Some(StrokeId(index)),
+				_ => None,
+			})
+	}
+
+	/// Whether every edit is a stroke, so that replaying draws the whole document.
+	pub fn is_complete(&self) -> bool {
+		self
+			.edits
+			.iter()
+			.all(|edit| matches!(edit, VectorEdit::Stroke(_)))
+	}
+
+	pub fn encode(&self) -> String {
+		let versioned = Versioned::new(VECTOR_DRAWING_VERSION, self);
+		serde_json::to_string(&versioned).expect("vector drawings should serialize")
+	}
+
+	/// Decodes a vector drawing. Edits which had been undone aren't saved, so can't be redone.
+	pub fn decode(encoded: &str) -> Result<Self, VectorDrawingError> {
+		Ok(versioned::decode(encoded, VECTOR_DRAWING_VERSION)?)
+	}
+
+	/// Draws every stroke to `atlas`, with the canvas scaled by `scale`, so a scale of 2 draws the
+	/// drawing at twice the resolution it was painted at. This should start from an empty atlas. If
+	/// the atlas runs out of room for charts, every stroke is still drawn to those it has.
+	pub fn replay(
+		&self,
+		queue: &wgpu::Queue,
+		atlas: &mut Atlas,
+		airbrush: &mut Airbrush,
+		scale: f32,
+	) -> Result<(), PoolExhausted> {
+		let mut result = Ok(());
+		for edit in &self.edits {
+			if let VectorEdit::Stroke(stroke) = edit {
+				result = result.and(stroke.replay(queue, atlas, airbrush, scale));
+			}
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{CanvasFormat, ChartKey, SymmetryKind, DEFAULT_BRUSH_SPACING};
+	use crate::render::Resources;
+	use crate::test;
+	use std::assert_matches::assert_matches;
+
+	/// A straight stroke along `y = 100` from canvas `x = 200` to `x = 300`, in one segment.
+	fn stroke() -> VectorStroke {
+		VectorStroke {
+			color: vec3(0.6, 0.1, -0.05),
+			size: 8.0,
+			opacity: 1.0,
+			rate: 25.0,
+			mode: BrushMode::Paint,
+			spacing: DEFAULT_BRUSH_SPACING,
+			scatter: 0.0,
+			grain: 0.0,
+			pickup: 0.0,
+			symmetry: Symmetry {
+				kind: SymmetryKind::None,
+				center: Vec2::ZERO,
+			},
+			seed: 7,
+			segments: vec![VectorSegment {
+				points: [200.0, 230.0, 270.0, 300.0].map(|x| vec3(x, 100.0, 0.5)),
+				tilt: Vec2::ZERO,
+			}],
+		}
+	}
+
+	#[test]
+	fn maps_segments_onto_the_canvas() {
+		let mut view = ViewTransform::default();
+		view.zoom_about(DVec2::ZERO, 0.5);
+		let segment = Bezier::linear(0.0, vec3(100.0, 50.0, 0.5), 1.0, vec3(130.0, 50.0, 1.0));
+		let segment = VectorSegment::from_screen(segment, &view, Vec2::ZERO);
+		assert_eq!(segment.points[0], vec3(200.0, 100.0, 0.5));
+		assert_eq!(segment.points[3], vec3(260.0, 100.0, 1.0));
+		assert!(segment
+			.evaluate(0.5)
+			.abs_diff_eq(vec3(230.0, 100.0, 0.75), 1e-4));
+	}
+
+	#[test]
+	fn undo_and_redo() {
+		let mut drawing = VectorDrawing::default();
+		assert!(!drawing.undo());
+		drawing.record(VectorEdit::Stroke(stroke()));
+		drawing.record(VectorEdit::Raster);
+		assert!(!drawing.is_complete());
+		assert!(drawing.undo());
+		assert!(drawing.is_complete());
+		assert!(drawing.redo());
+		assert!(!drawing.redo());
+		assert_eq!(drawing.edits().count(), 2);
+
+		// A new edit can't be redone past.
+		drawing.undo();
+		drawing.record(VectorEdit::Stroke(stroke()));
+		assert!(!drawing.redo());
+		assert!(drawing.is_complete());
+	}
+
+	#[test]
+	fn finds_the_latest_stroke_at_a_point() {
+		let mut drawing = VectorDrawing::default();
+		drawing.record(VectorEdit::Stroke(stroke()));
+		drawing.record(VectorEdit::Raster);
+		// The brush reaches half its size from the path, at half pressure.
+		assert_eq!(drawing.stroke_at(vec2(250.0, 103.0)), Some(StrokeId(0)));
+		assert_eq!(drawing.stroke_at(vec2(250.0, 105.0)), None);
+		assert_eq!(drawing.stroke_at(vec2(190.0, 100.0)), None);
+
+		let mirrored = VectorStroke {
+			symmetry: Symmetry {
+				kind: SymmetryKind::Horizontal,
+				center: Vec2::ZERO,
+			},
+			..stroke()
+		};
+		drawing.record(VectorEdit::Stroke(mirrored));
+		assert_eq!(drawing.stroke_at(vec2(250.0, 100.0)), Some(StrokeId(2)));
+		assert_eq!(drawing.stroke_at(vec2(250.0, -100.0)), Some(StrokeId(2)));
+		assert!(drawing.stroke(StrokeId(2)).is_some());
+		assert!(drawing.stroke(StrokeId(1)).is_none());
+
+		drawing.undo();
+		assert_eq!(drawing.stroke_at(vec2(250.0, 100.0)), Some(StrokeId(0)));
+		assert_eq!(drawing.stroke_at(vec2(250.0, -100.0)), None);
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut drawing = VectorDrawing::default();
+		drawing.record(VectorEdit::Stroke(stroke()));
+		drawing.record(VectorEdit::Raster);
+		assert_eq!(VectorDrawing::decode(&drawing.encode()), Ok(drawing));
+
+		let encoded = format!(
+			r#"{{"version": {}, "edits": []}}"#,
+			VECTOR_DRAWING_VERSION + 1
+		);
+		assert_matches!(
+			VectorDrawing::decode(&encoded),
+			Err(VectorDrawingError::UnsupportedVersion(_))
+		);
+		assert_matches!(
+			VectorDrawing::decode("{}"),
+			Err(VectorDrawingError::Malformed(_))
+		);
+	}
+
+	#[test]
+	fn replays_at_a_larger_scale() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let resources = Resources::new(device);
+		let format = CanvasFormat::Rgba8Unorm;
+		let mut atlas = Atlas::new((*context).clone(), format);
+		let mut airbrush = Airbrush::new(device, queue, &resources, format);
+
+		let mut drawing = VectorDrawing::default();
+		drawing.record(VectorEdit::Stroke(stroke()));
+		drawing.replay(queue, &mut atlas, &mut airbrush, 2.0)?;
+
+		// At twice the scale, the stroke runs from (400, 200) to (600, 200), and is twice as wide.
+		assert!(atlas.get_chart(&ChartKey(1, 0)).is_some());
+		assert!(atlas.get_chart(&ChartKey(2, 0)).is_some());
+		let sample = |point| pollster::block_on(atlas.sample(point));
+		assert!(sample(vec2(500.5, 200.5))?.w > 0.0);
+		assert!(sample(vec2(500.5, 205.5))?.w > 0.0);
+		assert_eq!(sample(vec2(250.5, 100.5))?.w, 0.0);
+		Ok(())
+	}
+}
@@ -0,0 +1,81 @@
+//! JSON files which begin with the version they were written in, so files from newer builds can be
+//! reported as such rather than as malformed.
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum VersionedError {
+	#[error("{0}")]
+	Malformed(String),
+	#[error("unsupported version {0}")]
+	UnsupportedVersion(u32),
+}
+
+/// What is actually written, so the version comes first. The contents' fields follow it.
+#[derive(serde::Serialize)]
+pub struct Versioned<'a, T> {
+	version: u32,
+	#[serde(flatten)]
+	contents: &'a T,
+}
+
+impl<'a, T: serde::Serialize> Versioned<'a, T> {
+	pub fn new(version: u32, contents: &'a T) -> Self {
+		Self { version, contents }
+	}
+}
+
+#[derive(serde::Deserialize)]
+struct Version {
+	version: u32,
+}
+
+/// Decodes what was written by [`Versioned`]. The version is checked first, and must be between 1
+/// and `latest`.
+pub fn decode<T: serde::de::DeserializeOwned>(
+	encoded: &str,
+	latest: u32,
+) -> Result<T, VersionedError> {
+	let malformed = |err: serde_json::Error| VersionedError::Malformed(err.to_string());
+	let Version { version } = serde_json::from_str(encoded).map_err(malformed)?;
+	if version == 0 || version > latest {
+		return Err(VersionedError::UnsupportedVersion(version));
+	}
+	serde_json::from_str(encoded).map_err(malformed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::assert_matches::assert_matches;
+
+	#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+	struct Contents {
+		value: i32,
+	}
+
+	#[test]
+	fn version_comes_first() {
+		let encoded = serde_json::to_string(&Versioned::new(3, &Contents { value: 7 })).unwrap();
+		assert_eq!(encoded, r#"{"version":3,"value":7}"#);
+		assert_eq!(decode(&encoded, 3), Ok(Contents { value: 7 }));
+	}
+
+	#[test]
+	fn rejects_unsupported_versions() {
+		for version in [0, 4] {
+			let encoded = format!(r#"{{"version":{version},"value":7}}"#);
+			assert_eq!(
+				decode::<Contents>(&encoded, 3),
+				Err(VersionedError::UnsupportedVersion(version))
+			);
+		}
+		// Contents from newer builds may not be understood at all, but are still reported by version.
+		assert_eq!(
+			decode::<Contents>(r#"{"version":4}"#, 3),
+			Err(VersionedError::UnsupportedVersion(4))
+		);
+		assert_matches!(
+			decode::<Contents>(r#"{"value":7}"#, 3),
+			Err(VersionedError::Malformed(_))
+		);
+	}
+}
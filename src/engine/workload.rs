@@ -0,0 +1,119 @@
+// Dragging a huge soft brush quickly can queue more fragments in one frame than a weak GPU shades
+// in hundreds of milliseconds. Rather than stall, the canvas draws what fits in each frame, carries
+// the rest of the stroke over to later frames, and thins the stroke out while it is behind.
+
+/// How long a frame lasts, in milliseconds.
+const FRAME_DURATION: f64 = 1000.0 / 60.0;
+
+/// The widest [`WorkloadBudget::spacing`] degrades to, as a fraction of the brush diameter.
+const MAX_DEGRADED_SPACING: f32 = 0.5;
+
+/// Fragments shaded per frame on each kind of adapter. These are conservative, since the airbrush
+/// shader is far from the cheapest.
+const DISCRETE_GPU_FRAGMENTS: f64 = 32e6;
+const INTEGRATED_GPU_FRAGMENTS: f64 = 8e6;
+const CPU_FRAGMENTS: f64 = 1e6;
+
+/// Limits how many fragments are drawn per frame, and tracks how far behind drawing has fallen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkloadBudget {
+	fragments_per_frame: f64,
+	// When the current frame started, in milliseconds.
+	frame_start: Option<f64>,
+	spent: f64,
+	// The number of consecutive frames that ended with work left over.
+	behind: u32,
+}
+
+impl WorkloadBudget {
+	pub fn new(fragments_per_frame: f64) -> Self {
+		Self {
+			fragments_per_frame,
+			frame_start: None,
+			spent: 0.0,
+			behind: 0,
+		}
+	}
+
+	/// A budget suited to the kind of adapter described by `info`.
+	pub fn for_adapter(info: &wgpu::AdapterInfo) -> Self {
+		Self::new(match info.device_type {
+			wgpu::DeviceType::DiscreteGpu => DISCRETE_GPU_FRAGMENTS,
+			wgpu::DeviceType::Cpu => CPU_FRAGMENTS,
+			_ => INTEGRATED_GPU_FRAGMENTS,
+		})
+	}
+
+	/// Starts a new frame if the last one started at least a frame before `now`, in milliseconds.
+	pub fn update(&mut self, now: f64) {
+		if self
+			.frame_start
+			.is_none_or(|start| now - start >= FRAME_DURATION)
+		{
+			self.frame_start = Some(now);
+			self.spent = 0.0;
+		}
+	}
+
+	pub fn spend(&mut self, fragments: f64) {
+		self.spent += fragments;
+	}
+
+	/// Whether the current frame has no budget left. Something is always drawn in a fresh frame,
+	/// however expensive, so that drawing can't stop altogether.
+	pub fn is_exhausted(&self) -> bool {
+		self.spent >= self.fragments_per_frame
+	}
+
+	/// Records whether a frame ended with work left over for the next.
+	pub fn end_frame(&mut self, work_left: bool) {
+		self.behind = match work_left {
+			true => self.behind + 1,
+			false => 0,
+		};
+	}
+
+	/// The spacing to draw with instead of `spacing`. It doubles for each frame drawing has been
+	/// behind, so fewer segments are drawn until it catches up.
+	pub fn spacing(&self, spacing: f32) -> f32 {
+		let degraded = spacing * 2f32.powi(self.behind.min(16) as i32);
+		degraded.min(MAX_DEGRADED_SPACING).max(spacing)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn budget_resets_each_frame() {
+		let mut budget = WorkloadBudget::new(100.0);
+		budget.update(0.0);
+		budget.spend(60.0);
+		assert!(!budget.is_exhausted());
+		budget.spend(60.0);
+		assert!(budget.is_exhausted());
+
+		// Still the same frame.
+		budget.update(10.0);
+		assert!(budget.is_exhausted());
+		budget.update(20.0);
+		assert!(!budget.is_exhausted());
+	}
+
+	#[test]
+	fn spacing_degrades_while_behind() {
+		let mut budget = WorkloadBudget::new(100.0);
+		assert_eq!(budget.spacing(0.05), 0.05);
+		budget.end_frame(true);
+		assert_eq!(budget.spacing(0.05), 0.1);
+		for _ in 0..8 {
+			budget.end_frame(true);
+		}
+		assert_eq!(budget.spacing(0.05), MAX_DEGRADED_SPACING);
+		// Wide spacing is never narrowed.
+		assert_eq!(budget.spacing(0.8), 0.8);
+		budget.end_frame(false);
+		assert_eq!(budget.spacing(0.05), 0.05);
+	}
+}
@@ -1,3 +1,5 @@
+//! Geometric primitives.
+
 use glam::Vec2;
 
 pub struct AABox {
@@ -4,14 +4,36 @@
 #![feature(maybe_uninit_array_assume_init)]
 #![cfg_attr(test, feature(assert_matches))]
 
-pub(crate) mod util;
+//! A tile-atlas painting engine and the web app built on it.
+//!
+//! The engine paints to an unbounded canvas split into fixed-size charts, each its own texture,
+//! allocated only where something is painted. It depends only on `wgpu`, so it can be embedded in
+//! other projects by disabling default features, which removes the Leptos app:
+//!
+//! ```toml
+//! stark = { git = "https://github.com/cbbowen/stark", default-features = false }
+//! ```
+//!
+//! - [`engine`] holds the canvas ([`engine::Atlas`]), brushes, history, and import and export.
+//! - [`render`] holds the `wgpu` helpers and shared [`render::Resources`] the engine draws with.
+//! - [`util`] holds color conversions, input smoothing, and other helpers.
+//! - [`view_transform`] maps between canvas and screen coordinates.
+//! - [`coordinates`] has types for points in each coordinate space, to keep conversions explicit.
+//!
+//! [`WgpuContext`] creates a device to start from.
 
+pub mod util;
+
+pub mod coordinates;
+#[cfg(feature = "ui")]
 mod components;
-mod engine;
-mod geom;
+pub mod engine;
+pub mod geom;
+#[cfg(feature = "ui")]
 mod pages;
-mod render;
+pub mod render;
 pub mod shaders;
+pub mod view_transform;
 
 mod wgpu_context;
 pub use wgpu_context::*;
@@ -21,11 +43,17 @@ pub mod debug;
 #[cfg(test)]
 pub mod test;
 
+#[cfg(feature = "ui")]
 use leptos::prelude::*;
+#[cfg(feature = "ui")]
 use leptos_meta::*;
+#[cfg(feature = "ui")]
 use leptos_router::components::*;
+#[cfg(feature = "ui")]
 use leptos_router::*;
 
+/// The web app.
+#[cfg(feature = "ui")]
 #[component]
 pub fn App() -> impl IntoView {
 	provide_meta_context();
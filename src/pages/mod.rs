@@ -1,6 +1,6 @@
 use crate::components::*;
 use crate::*;
-use leptos::children::Children;
+use leptos::children::ChildrenFn;
 use leptos::prelude::*;
 use leptos_meta::*;
 use leptos_router::components::A;
@@ -8,79 +8,520 @@ use std::sync::Arc;
 use util::create_derived;
 
 #[component]
-pub fn ShaderModulesProvider(children: Children) -> impl IntoView {
+pub fn ShaderModulesProvider(children: ChildrenFn) -> impl IntoView {
 	let context: Arc<WgpuContext> = use_context().unwrap();
-	let resources = Arc::new(render::Resources::new(context.device()));
+	// Pipelines compiled in earlier sessions are loaded first, where the backend can reuse them.
+	let resources = LocalResource::new({
+		let context = context.clone();
+		move || {
+			let context = context.clone();
+			async move {
+				let data = load_pipeline_cache(&context).await;
+				Arc::new(render::Resources::with_pipeline_cache_data(
+					context.device(),
+					data.as_deref(),
+				))
+			}
+		}
+	});
+
+	// Save whatever was compiled as the page goes away, for the next session.
+	let pagehide_handle = window_event_listener(leptos::ev::pagehide, move |_| {
+		let Some(resources) = resources.get_untracked() else {
+			return;
+		};
+		let context = context.clone();
+		leptos::task::spawn_local(async move {
+			save_pipeline_cache(&context, &resources).await;
+		});
+	});
+	on_cleanup(move || pagehide_handle.remove());
 
 	use leptos::context::Provider;
-	view! { <Provider value=resources>{children()}</Provider> }
+	view! {
+		<Suspense>
+			{move || {
+				let children = children.clone();
+				Suspend::new(async move {
+					let resources = resources.await;
+					view! { <Provider value=resources>{children()}</Provider> }
+				})
+			}}
+		</Suspense>
+	}
 }
 
+/// The range of the brush size slider. The brush size is the square of its value.
+const MIN_INPUT_BRUSH_SIZE: f64 = 1.0;
+const MAX_INPUT_BRUSH_SIZE: f64 = 32.0;
+
+/// The range of the brush opacity slider.
+const MIN_BRUSH_OPACITY: f64 = 0.0;
+const MAX_BRUSH_OPACITY: f64 = 2.0;
+
+/// How many presses of a shortcut double the brush size or opacity. Stepping logarithmically makes
+/// each press a similar change in how the brush looks, whether it is small or large.
+const SHORTCUT_STEPS_PER_DOUBLING: f64 = 4.0;
+
+/// The least opacity stepped to from the keyboard, since zero can't be doubled.
+const MIN_STEPPED_BRUSH_OPACITY: f64 = 0.05;
+
 #[component]
 pub fn Home() -> impl IntoView {
-	let brush_color = RwSignal::new(glam::Vec3::new(0.5, 0.0, 0.0));
-	let input_brush_size = RwSignal::new(16.0);
-	let brush_opacity = RwSignal::new(1.0);
-	let brush_rate = RwSignal::new(25.0);
+	let saved_brush = load_brush_settings();
+	let brush_color = RwSignal::new(saved_brush.color);
+	let input_brush_size = RwSignal::new(saved_brush.input_size);
+	let brush_opacity = RwSignal::new(saved_brush.opacity);
+	let brush_rate = RwSignal::new(saved_brush.rate);
+	let smoothing = RwSignal::new(saved_brush.smoothing);
+	let smoothing_interval = RwSignal::new(saved_brush.smoothing_interval);
+	let dpi = RwSignal::new(engine::DEFAULT_DPI);
+	let atlas = RwSignal::new_local(None);
+	let documents = Documents::default();
+	let brush_presets = RwSignal::new(Vec::new());
+	let palettes = RwSignal::new(Vec::new());
+	let symmetry = RwSignal::new(engine::SymmetryKind::None);
+	let brush_mode = RwSignal::new(saved_brush.mode);
+	let brush_shapes = RwSignal::new(engine::BrushShapes::default());
+	let brush_shape_name = RwSignal::new(saved_brush.shape);
+	let brush_spacing = RwSignal::new(saved_brush.spacing);
+	let brush_scatter = RwSignal::new(saved_brush.scatter);
+	let brush_grain = RwSignal::new(saved_brush.grain);
+	let brush_pickup = RwSignal::new(saved_brush.pickup);
+	let brush_hardness = RwSignal::new(saved_brush.hardness);
+	let brush_spread = RwSignal::new(saved_brush.spread);
+	let selection_tool = RwSignal::new(None);
+	let crop_tool = RwSignal::new(false);
+	let shape_tool = RwSignal::new(None);
+	let pen_tool = RwSignal::new(false);
+	let guides = RwSignal::new(None);
+	let snap_to_guides = RwSignal::new(false);
+	let pixel_grid = RwSignal::new(false);
+	let snap_to_pixels = RwSignal::new(false);
+	let low_latency = RwSignal::new(false);
+	let extended_range = RwSignal::new(false);
+	let stroke_graph = RwSignal::new(false);
+	let gpu_profiler = RwSignal::new(false);
+	let latency_hud = RwSignal::new(false);
+	let pick_hud = RwSignal::new(false);
+	let predict_pointer = RwSignal::new(false);
+	let capture_frame = RwSignal::new(false);
+	let captured_frame = RwSignal::new(None::<render::FrameCapture>);
+	let shortcuts = RwSignal::new(ShortcutAction::default_shortcuts());
+	let brush_readout = RwSignal::new(None::<String>);
+	let view_command = RwSignal::new(None::<ViewCommand>);
+	let adapter_choice = leptos_router::hooks::use_query_map()
+		.with_untracked(|query| {
+			query
+				.get("gpu")
+				.and_then(|gpu| AdapterChoice::from_query(&gpu))
+		})
+		.unwrap_or_default();
 
 	let brush_size = create_derived(move || {
 		let input_brush_size = input_brush_size.get();
 		input_brush_size * input_brush_size
 	});
+	let brush_shape =
+		create_derived(move || brush_shapes.with(|shapes| shapes.get(&brush_shape_name.get())));
+	let stamp_profile = create_derived(move || engine::StampProfile {
+		hardness: brush_hardness.get() as f32,
+		spread: brush_spread.get() as f32,
+	});
+	// The slider is in milliseconds.
+	let smoothing_interval_seconds = create_derived(move || smoothing_interval.get() / 1000.0);
+
+	// Announce what otherwise only shows, for screen readers. Colors are named after the nearest CSS
+	// color, once the picker settles.
+	let announcer = Announcer::default();
+	provide_context(announcer);
+	announcer.announce_changes(
+		move || selection_tool.get(),
+		|tool: Option<engine::SelectionTool>| match tool {
+			Some(tool) => format!("{tool} selection tool"),
+			None => "Paint tool".to_string(),
+		},
+		250.0,
+	);
+	announcer.announce_changes(
+		move || brush_mode.get(),
+		|mode| format!("{mode} mode"),
+		250.0,
+	);
+	announcer.announce_changes(
+		move || brush_color.get(),
+		|color| format!("Color {}", util::nearest_css_color_name(color)),
+		500.0,
+	);
+
+	// Step the brush size and opacity with shortcuts, [, ], {, and } by default. The canvas outlines
+	// the brush and briefly reads out the new value.
+	let keydown = move |e: leptos::ev::KeyboardEvent| {
+		if is_typing(&e) {
+			return;
+		}
+		let shortcut = event_shortcut(&e);
+		let Some(action) = shortcuts.with_untracked(|shortcuts| shortcuts.action(&shortcut)) else {
+			return;
+		};
+		let readout = match action {
+			ShortcutAction::DecreaseBrushSize | ShortcutAction::IncreaseBrushSize => {
+				let steps = match action {
+					ShortcutAction::DecreaseBrushSize => -1.0,
+					_ => 1.0,
+				};
+				// The slider's value is the square root of the size, so it takes twice the steps.
+				input_brush_size.update(|size| {
+					*size = util::step_logarithmically(
+						*size,
+						steps,
+						2.0 * SHORTCUT_STEPS_PER_DOUBLING,
+						MIN_INPUT_BRUSH_SIZE,
+						MAX_INPUT_BRUSH_SIZE,
+					)
+				});
+				let size = input_brush_size.get_untracked();
+				format!("Size {:.0}", size * size)
+			}
+			ShortcutAction::DecreaseBrushOpacity | ShortcutAction::IncreaseBrushOpacity => {
+				let steps = match action {
+					ShortcutAction::DecreaseBrushOpacity => -1.0,
+					_ => 1.0,
+				};
+				brush_opacity.update(|opacity| {
+					*opacity = util::step_logarithmically(
+						*opacity,
+						steps,
+						SHORTCUT_STEPS_PER_DOUBLING,
+						MIN_STEPPED_BRUSH_OPACITY,
+						MAX_BRUSH_OPACITY,
+					)
+				});
+				format!("Opacity {:.0}%", brush_opacity.get_untracked() * 100.0)
+			}
+		};
+		brush_readout.set(Some(readout));
+		e.prevent_default();
+	};
+	let keydown_handle = window_event_listener(leptos::ev::keydown, keydown);
+	on_cleanup(move || keydown_handle.remove());
+
+	// The brush and the autosaved document's view are saved as the page goes away, to pick up where
+	// they were left next time.
+	let pagehide_handle = window_event_listener(leptos::ev::pagehide, move |_| {
+		save_brush_settings(&BrushSettings {
+			color: brush_color.get_untracked(),
+			input_size: input_brush_size.get_untracked(),
+			opacity: brush_opacity.get_untracked(),
+			rate: brush_rate.get_untracked(),
+			smoothing: smoothing.get_untracked(),
+			smoothing_interval: smoothing_interval.get_untracked(),
+			mode: brush_mode.get_untracked(),
+			shape: brush_shape_name.get_untracked(),
+			spacing: brush_spacing.get_untracked(),
+			scatter: brush_scatter.get_untracked(),
+			grain: brush_grain.get_untracked(),
+			pickup: brush_pickup.get_untracked(),
+			hardness: brush_hardness.get_untracked(),
+			spread: brush_spread.get_untracked(),
+		});
+		if let Some(document) = documents.autosaved() {
+			save_view(&document.view_transform.get_untracked());
+		}
+	});
+	on_cleanup(move || pagehide_handle.remove());
 
 	view! {
 		<Title text="Home"/>
-		<KeyboardStateProvider>
-			<RenderContextProvider initializing_fallback=|| {
+		<Announcements announcer=announcer/>
+		<GpuErrorToasts/>
+		<RenderContextProvider
+			initializing_fallback=|| {
 				view! { <fallback::Initializing></fallback::Initializing> }
-			}>
-				<ShaderModulesProvider>
+			}
+			power_preference=adapter_choice.power_preference
+			force_fallback_adapter=adapter_choice.force_fallback_adapter
+			backends=adapter_choice.backends
+		>
+			<ShaderModulesProvider>
 
-					<Canvas
-						brush_color=brush_color
-						brush_size=brush_size
-						brush_opacity=brush_opacity
-						brush_rate=brush_rate
-					/>
+				// The canvas is recreated whenever a different document is shown, or the surface
+				// needs different options. Without WebGPU, charts can't be painted at all.
+				{move || {
+					let context: Arc<WgpuContext> = use_context().unwrap();
+					if context.is_compatibility_mode() {
+						return view! { <fallback::Unsupported feature="Painting"/> }.into_any();
+					}
+					let low_latency = low_latency.get();
+					let color_space = match extended_range.get() {
+						true => engine::SurfaceColorSpace::Extended,
+						false => engine::SurfaceColorSpace::Srgb,
+					};
+					documents
+						.active()
+						.map(|document| {
+							let context: Arc<WgpuContext> = use_context().unwrap();
+							let resources: Arc<render::Resources> = use_context().unwrap();
+							let open_file = move |
+								(kind, file): (DroppedFileKind, leptos::web_sys::File)|
+							{
+								leptos::task::spawn_local(open_dropped_file(
+									kind,
+									file,
+									context.clone(),
+									resources.clone(),
+									documents,
+									brush_presets,
+									palettes,
+								));
+							};
+							view! {
+								<Canvas
+									brush_color=brush_color
+									brush_size=brush_size
+									brush_opacity=brush_opacity
+									brush_rate=brush_rate
+									smoothing=smoothing
+									smoothing_interval=smoothing_interval_seconds
+									symmetry=symmetry
+									brush_mode=brush_mode
+									brush_shape=brush_shape
+									stamp_profile=stamp_profile
+									brush_spacing=brush_spacing
+									brush_scatter=brush_scatter
+									brush_grain=brush_grain
+									brush_pickup=brush_pickup
+									selection_tool=selection_tool
+									crop_tool=crop_tool
+									shape_tool=shape_tool
+									pen_tool=pen_tool
+									guides=guides
+									snap_to_guides=snap_to_guides
+									pixel_grid=pixel_grid
+									snap_to_pixels=snap_to_pixels
+									document=document
+									atlas_handle=atlas
+									open_file=open_file
+									low_latency=low_latency
+									color_space=color_space
+									stroke_graph=stroke_graph
+									gpu_profiler=gpu_profiler
+									latency_hud=latency_hud
+									pick_hud=pick_hud
+									predict_pointer=predict_pointer
+									capture_frame=capture_frame
+									captured_frame=captured_frame
+									brush_readout=brush_readout
+									view_command=view_command
+								/>
+							}
+						})
+						.into_any()
+				}}
 
-					<div class="SidePanels">
+				<DocumentTabs documents=documents/>
 
-						<Panel title="Color">
-							<ColorPicker color=brush_color/>
-						</Panel>
+				<div class="SidePanels">
 
-						<Panel title="Brush">
-							<BrushSetting name="Size">
-								<thaw::Slider
-									value=input_brush_size
-									min=1.0
-									max=32.0
-									step=1.0
-								></thaw::Slider>
-							</BrushSetting>
-							<BrushSetting name="Opacity">
+					<Panel title="Color">
+						<ColorPicker color=brush_color/>
+						<HarmonySwatches color=brush_color/>
+						<Palettes palettes=palettes color=brush_color/>
+					</Panel>
+
+					<Panel title="Brush">
+						{move || {
+							let context: Arc<WgpuContext> = use_context().unwrap();
+							if context.is_compatibility_mode() {
+								return view! { <fallback::Unsupported feature="The brush preview"/> }
+									.into_any();
+							}
+							let canvas_format = documents
+								.active()
+								.map(|document| document.format())
+								.unwrap_or_default();
+							view! {
+								<BrushPreview
+									brush_color=brush_color
+									brush_size=brush_size
+									brush_opacity=brush_opacity
+									brush_rate=brush_rate
+									brush_shape=brush_shape
+									stamp_profile=stamp_profile
+									brush_spacing=brush_spacing
+									brush_scatter=brush_scatter
+									brush_grain=brush_grain
+									canvas_format=canvas_format
+								/>
+							}
+								.into_any()
+						}}
+						<BrushSetting name="Size">
+							<thaw::Slider
+								value=input_brush_size
+								min=MIN_INPUT_BRUSH_SIZE
+								max=MAX_INPUT_BRUSH_SIZE
+								step=1.0
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Opacity">
+							<thaw::Slider
+								value=brush_opacity
+								min=MIN_BRUSH_OPACITY
+								max=MAX_BRUSH_OPACITY
+								step=0.05
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Rate">
+							<thaw::Slider
+								value=brush_rate
+								min=0.0
+								max=100.0
+								step=5.0
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Smoothing">
+							<thaw::Slider
+								value=smoothing
+								min=0.0
+								max=1.0
+								step=0.05
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Smoothing interval">
+							<thaw::Slider
+								value=smoothing_interval
+								min=20.0
+								max=250.0
+								step=10.0
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Spacing">
+							<thaw::Slider
+								value=brush_spacing
+								min=*engine::PRESET_SPACING_RANGE.start()
+								max=*engine::PRESET_SPACING_RANGE.end()
+								step=0.01
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Scatter">
+							<thaw::Slider
+								value=brush_scatter
+								min=*engine::PRESET_SCATTER_RANGE.start()
+								max=*engine::PRESET_SCATTER_RANGE.end()
+								step=0.05
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Grain">
+							<thaw::Slider
+								value=brush_grain
+								min=*engine::PRESET_GRAIN_RANGE.start()
+								max=*engine::PRESET_GRAIN_RANGE.end()
+								step=0.05
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Hardness">
+							<thaw::Slider
+								value=brush_hardness
+								min=*engine::PRESET_HARDNESS_RANGE.start()
+								max=*engine::PRESET_HARDNESS_RANGE.end()
+								step=0.05
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushSetting name="Spread">
+							<thaw::Slider
+								value=brush_spread
+								min=*engine::PRESET_SPREAD_RANGE.start()
+								max=*engine::PRESET_SPREAD_RANGE.end()
+								step=0.05
+							></thaw::Slider>
+						</BrushSetting>
+						<BrushShapeSetting shapes=brush_shapes shape=brush_shape_name/>
+						<BrushModeSetting mode=brush_mode/>
+						<Show when=move || brush_mode.get() == engine::BrushMode::Mix>
+							<BrushSetting name="Pickup">
 								<thaw::Slider
-									value=brush_opacity
-									min=0.0
-									max=2.0
+									value=brush_pickup
+									min=*engine::PRESET_PICKUP_RANGE.start()
+									max=*engine::PRESET_PICKUP_RANGE.end()
 									step=0.05
 								></thaw::Slider>
 							</BrushSetting>
-							<BrushSetting name="Rate">
-								<thaw::Slider
-									value=brush_rate
-									min=0.0
-									max=100.0
-									step=5.0
-								></thaw::Slider>
-							</BrushSetting>
-						</Panel>
+						</Show>
+						<SymmetrySetting symmetry=symmetry/>
+						<BrushPresets
+							presets=brush_presets
+							input_brush_size=input_brush_size
+							brush_opacity=brush_opacity
+							brush_rate=brush_rate
+							smoothing=smoothing
+							brush_shape=brush_shape_name
+							brush_spacing=brush_spacing
+							brush_scatter=brush_scatter
+							brush_grain=brush_grain
+							brush_hardness=brush_hardness
+							brush_spread=brush_spread
+							brush_mode=brush_mode
+							brush_pickup=brush_pickup
+						/>
+					</Panel>
+
+					<Panel title="Select">
+						<SelectionSetting tool=selection_tool/>
+					</Panel>
+
+					<Panel title="Shape">
+						<ShapeSetting tool=shape_tool pen=pen_tool/>
+					</Panel>
+
+					<Panel title="Guides">
+						<GuideSetting
+							guides=guides
+							snap=snap_to_guides
+							pixel_grid=pixel_grid
+							snap_to_pixels=snap_to_pixels
+						/>
+					</Panel>
+
+					<Panel title="View">
+						<ViewSettings command=view_command documents=documents/>
+					</Panel>
+
+					<Panel title="Document">
+						<DocumentSettings documents=documents dpi=dpi crop_tool=crop_tool/>
+					</Panel>
+
+					<Panel title="Export">
+						<ExportSettings atlas=atlas dpi=dpi documents=documents/>
+					</Panel>
+
+					<Panel title="Experimental">
+						<ExperimentalSettings
+							low_latency=low_latency
+							extended_range=extended_range
+							stroke_graph=stroke_graph
+							gpu_profiler=gpu_profiler
+							latency_hud=latency_hud
+							pick_hud=pick_hud
+							predict_pointer=predict_pointer
+							capture_frame=capture_frame
+							captured_frame=captured_frame
+							documents=documents
+						/>
+					</Panel>
+
+					<Panel title="Shortcuts">
+						<ShortcutSettings shortcuts=shortcuts/>
+					</Panel>
+
+					<Panel title="Diagnostics">
+						<Diagnostics choice=adapter_choice/>
+					</Panel>
 
-					</div>
+				</div>
 
-				</ShaderModulesProvider>
-			</RenderContextProvider>
-		</KeyboardStateProvider>
+			</ShaderModulesProvider>
+		</RenderContextProvider>
 	}
 }
 
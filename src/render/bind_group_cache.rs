@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// Identifies something a bind group binds, so that bind groups of the same resources are found
+/// again. Resources are identified by their ids, which aren't reused while cached bind groups keep
+/// them alive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BindingKey {
+	Buffer {
+		id: wgpu::Id<wgpu::Buffer>,
+		offset: wgpu::BufferAddress,
+		size: Option<wgpu::BufferSize>,
+	},
+	/// A layer of a texture, for bind groups which create their own view of it.
+	TextureLayer {
+		id: wgpu::Id<wgpu::Texture>,
+		layer: u32,
+	},
+	TextureView(wgpu::Id<wgpu::TextureView>),
+	Sampler(wgpu::Id<wgpu::Sampler>),
+	/// The contents of a resource the cached value owns, such as a uniform buffer, compared bitwise.
+	Contents(Vec<u8>),
+}
+
+impl BindingKey {
+	pub fn buffer(binding: &wgpu::BufferBinding) -> Self {
+		Self::Buffer {
+			id: binding.buffer.global_id(),
+			offset: binding.offset,
+			size: binding.size,
+		}
+	}
+
+	pub fn texture_layer(texture: &wgpu::Texture, layer: u32) -> Self {
+		Self::TextureLayer {
+			id: texture.global_id(),
+			layer,
+		}
+	}
+
+	pub fn texture_view(view: &wgpu::TextureView) -> Self {
+		Self::TextureView(view.global_id())
+	}
+
+	pub fn sampler(sampler: &wgpu::Sampler) -> Self {
+		Self::Sampler(sampler.global_id())
+	}
+
+	pub fn contents<T: bytemuck::NoUninit>(values: &[T]) -> Self {
+		Self::Contents(bytemuck::cast_slice(values).to_vec())
+	}
+}
+
+struct CacheState<L, T> {
+	clock: u64,
+	// Each entry, with when it was last used.
+	entries: HashMap<(L, Vec<BindingKey>), (u64, Arc<T>)>,
+}
+
+/// Bind groups, along with any resources created for them, keyed by their layout and what they
+/// bind. The layout is told apart by whatever determines it, of type `L`, since bind groups of
+/// different layouts can bind the same resources.
+///
+/// Cached bind groups keep what they bind alive, so only the `capacity` most recently used are
+/// kept.
+pub struct BindGroupCache<L, T> {
+	capacity: usize,
+	state: Mutex<CacheState<L, T>>,
+}
+
+impl<L: Clone + Eq + Hash, T> BindGroupCache<L, T> {
+	pub fn new(capacity: usize) -> Self {
+		assert!(capacity > 0);
+		Self {
+			capacity,
+			state: Mutex::new(CacheState {
+				clock: 0,
+				entries: HashMap::new(),
+			}),
+		}
+	}
+
+	/// Returns the bind group of `layout` binding `bindings`, calling `create` to create it if it
+	/// isn't cached.
+	pub fn get_or_create(
+		&self,
+		layout: L,
+		bindings: Vec<BindingKey>,
+		create: impl FnOnce() -> T,
+	) -> Arc<T> {
+		let mut state = self.state.lock().unwrap();
+		state.clock += 1;
+		let clock = state.clock;
+		let key = (layout, bindings);
+		if let Some((last_used, value)) = state.entries.get_mut(&key) {
+			*last_used = clock;
+			return value.clone();
+		}
+
+		if state.entries.len() >= self.capacity {
+			let least_recent = state
+				.entries
+				.iter()
+				.min_by_key(|(_, (last_used, _))| *last_used)
+				.map(|(key, _)| key.clone());
+			if let Some(least_recent) = least_recent {
+				state.entries.remove(&least_recent);
+			}
+		}
+		let value = Arc::new(create());
+		state.entries.insert(key, (clock, value.clone()));
+		value
+	}
+
+	/// How many bind groups are cached.
+	pub fn len(&self) -> usize {
+		self.state.lock().unwrap().entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reuses_and_evicts_least_recently_used() {
+		let cache = BindGroupCache::new(2);
+		let created = std::cell::Cell::new(0);
+		let get = |layout: u32, contents: u32| {
+			*cache.get_or_create(layout, vec![BindingKey::contents(&[contents])], || {
+				created.set(created.get() + 1);
+				(layout, contents)
+			})
+		};
+
+		assert_eq!(get(0, 1), (0, 1));
+		assert_eq!(get(0, 1), (0, 1));
+		assert_eq!(created.get(), 1);
+		// The same bindings with another layout are another bind group.
+		assert_eq!(get(1, 1), (1, 1));
+		assert_eq!(created.get(), 2);
+
+		// Using the first again leaves the second least recently used, so it's evicted.
+		get(0, 1);
+		get(0, 2);
+		assert_eq!(cache.len(), 2);
+		assert_eq!(created.get(), 3);
+		get(0, 1);
+		assert_eq!(created.get(), 3);
+		get(1, 1);
+		assert_eq!(created.get(), 4);
+	}
+}
@@ -0,0 +1,135 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// A validation error raised by the device, with the label of what raised it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuError {
+	pub label: String,
+	pub message: String,
+}
+
+type Listener = Arc<dyn Fn(&GpuError) + Send + Sync>;
+
+struct Listeners {
+	next_id: u64,
+	listeners: Vec<(u64, Listener)>,
+}
+
+static LISTENERS: Mutex<Listeners> = Mutex::new(Listeners {
+	next_id: 0,
+	listeners: Vec::new(),
+});
+
+/// Keeps a listener from [`subscribe_gpu_errors`] subscribed until dropped.
+#[must_use]
+pub struct GpuErrorSubscription(u64);
+
+impl Drop for GpuErrorSubscription {
+	fn drop(&mut self) {
+		let mut listeners = LISTENERS.lock().unwrap();
+		listeners.listeners.retain(|(id, _)| *id != self.0);
+	}
+}
+
+/// Calls `listener` with every error reported from now on, such as to show them in the UI.
+pub fn subscribe_gpu_errors(
+	listener: impl Fn(&GpuError) + Send + Sync + 'static,
+) -> GpuErrorSubscription {
+	let mut listeners = LISTENERS.lock().unwrap();
+	let id = listeners.next_id;
+	listeners.next_id += 1;
+	listeners.listeners.push((id, Arc::new(listener)));
+	GpuErrorSubscription(id)
+}
+
+/// Logs `error`, raised by what's labelled `label`, and tells any listeners.
+pub fn report_gpu_error(label: &str, error: &wgpu::Error) {
+	tracing::error!(label, %error, "GPU error");
+	let error = GpuError {
+		label: label.to_string(),
+		message: error.to_string(),
+	};
+	// Listeners are called without the lock held, so they may subscribe or report errors themselves.
+	let listeners: Vec<_> = LISTENERS
+		.lock()
+		.unwrap()
+		.listeners
+		.iter()
+		.map(|(_, listener)| listener.clone())
+		.collect();
+	for listener in listeners {
+		listener(&error);
+	}
+}
+
+/// Calls `create`, reporting any validation error it raises under `label` rather than leaving it to
+/// the device's uncaptured error handler, which panics natively and only logs to the console on the
+/// web.
+pub fn error_scope<T>(device: &wgpu::Device, label: &str, create: impl FnOnce() -> T) -> T {
+	device.push_error_scope(wgpu::ErrorFilter::Validation);
+	let value = create();
+	let label = label.to_string();
+	when_popped(device.pop_error_scope(), move |error| {
+		report_gpu_error(&label, &error)
+	});
+	value
+}
+
+/// Calls `report` with the error of a popped scope, if there is one, once it's known.
+fn when_popped(
+	popped: impl Future<Output = Option<wgpu::Error>> + 'static,
+	report: impl FnOnce(wgpu::Error) + 'static,
+) {
+	// On the web, the browser answers once it has validated everything in the scope.
+	#[cfg(all(target_arch = "wasm32", feature = "ui"))]
+	wasm_bindgen_futures::spawn_local(async move {
+		if let Some(error) = popped.await {
+			report(error);
+		}
+	});
+	// Natively, validation happens as each call is made, so the error is known right away.
+	#[cfg(not(all(target_arch = "wasm32", feature = "ui")))]
+	if let Some(Some(error)) = futures::FutureExt::now_or_never(popped) {
+		report(error);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test;
+
+	#[test]
+	fn reports_validation_errors() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let device = context.device();
+		let reported = Arc::new(Mutex::new(Vec::new()));
+		let _subscription = {
+			let reported = reported.clone();
+			subscribe_gpu_errors(move |error| reported.lock().unwrap().push(error.clone()))
+		};
+
+		let size = error_scope(device, "mappable buffer", || {
+			// A buffer can't be mapped for both reading and writing.
+			device
+				.create_buffer(&wgpu::BufferDescriptor {
+					label: None,
+					size: 4,
+					usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::MAP_WRITE,
+					mapped_at_creation: false,
+				})
+				.size()
+		});
+		assert_eq!(size, 4);
+
+		// Other tests may report errors concurrently.
+		let reported = reported.lock().unwrap();
+		assert!(
+			reported
+				.iter()
+				.any(|error| error.label == "mappable buffer"),
+			"{reported:?}"
+		);
+		Ok(())
+	}
+}
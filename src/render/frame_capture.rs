@@ -0,0 +1,212 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::Mutex;
+
+/// A command recorded by a [`FrameCapturer`]. Pipelines and bind groups are named by whatever set
+/// them, since `wgpu` doesn't keep their labels.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum CapturedCommand {
+	SetPipeline {
+		pipeline: String,
+	},
+	SetBindGroup {
+		index: u32,
+		bind_group: String,
+	},
+	/// A draw, with the texture array layers it reads as instances if it draws tiles.
+	Draw {
+		vertices: Range<u32>,
+		instances: Range<u32>,
+		layers: Vec<u32>,
+	},
+}
+
+/// The commands recorded in a pass, in order.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CapturedPass {
+	pub label: String,
+	pub commands: Vec<CapturedCommand>,
+}
+
+/// The commands issued for one frame, from [`FrameCapturer`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct FrameCapture {
+	pub passes: Vec<CapturedPass>,
+}
+
+impl FrameCapture {
+	/// The number of draws across every pass.
+	pub fn draw_count(&self) -> usize {
+		self
+			.passes
+			.iter()
+			.flat_map(|pass| &pass.commands)
+			.filter(|command| matches!(command, CapturedCommand::Draw { .. }))
+			.count()
+	}
+
+	/// The capture as JSON, for attaching to bug reports.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+}
+
+impl fmt::Display for FrameCapture {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for pass in &self.passes {
+			writeln!(f, "{}", pass.label)?;
+			for command in &pass.commands {
+				match command {
+					CapturedCommand::SetPipeline { pipeline } => writeln!(f, "  pipeline {pipeline}")?,
+					CapturedCommand::SetBindGroup { index, bind_group } => {
+						writeln!(f, "  bind group {index}: {bind_group}")?
+					}
+					CapturedCommand::Draw {
+						vertices,
+						instances,
+						layers,
+					} => {
+						write!(
+							f,
+							"  draw {} vertices × {} instances",
+							vertices.len(),
+							instances.len()
+						)?;
+						if !layers.is_empty() {
+							write!(f, ", layers {layers:?}")?;
+						}
+						writeln!(f)?;
+					}
+				}
+			}
+		}
+		write!(f, "{} draws", self.draw_count())
+	}
+}
+
+#[derive(Debug, Default)]
+enum CaptureState {
+	#[default]
+	Idle,
+	Armed,
+	Recording(FrameCapture),
+}
+
+/// Records which pipelines, bind groups, and draws a frame issues, for debugging where native
+/// capture tools don't attach, as in browsers. Whatever draws calls [`Self::record`] as it goes,
+/// which does nothing unless a capture was asked for, and the frame is bracketed by
+/// [`Self::begin_frame`] and [`Self::end_frame`].
+#[derive(Debug, Default)]
+pub struct FrameCapturer {
+	state: Mutex<CaptureState>,
+}
+
+impl FrameCapturer {
+	/// Asks for the next frame to be recorded.
+	pub fn capture_next_frame(&self) {
+		let mut state = self.state.lock().unwrap();
+		if let CaptureState::Idle = *state {
+			*state = CaptureState::Armed;
+		}
+	}
+
+	/// Starts recording, if a capture was asked for.
+	pub fn begin_frame(&self) {
+		let mut state = self.state.lock().unwrap();
+		if let CaptureState::Armed = *state {
+			*state = CaptureState::Recording(FrameCapture::default());
+		}
+	}
+
+	/// Stops recording, returning what was recorded, or `None` if nothing was.
+	pub fn end_frame(&self) -> Option<FrameCapture> {
+		let mut state = self.state.lock().unwrap();
+		match std::mem::take(&mut *state) {
+			CaptureState::Recording(capture) => Some(capture),
+			other => {
+				*state = other;
+				None
+			}
+		}
+	}
+
+	/// Starts a pass labelled `label`. Commands recorded outside of any pass go in an unlabelled one.
+	pub fn begin_pass(&self, label: &str) {
+		if let CaptureState::Recording(capture) = &mut *self.state.lock().unwrap() {
+			capture.passes.push(CapturedPass {
+				label: label.to_string(),
+				commands: Vec::new(),
+			});
+		}
+	}
+
+	/// Records the command `command` returns, if recording. It isn't called otherwise, so describing
+	/// the command costs nothing outside of captures.
+	pub fn record(&self, command: impl FnOnce() -> CapturedCommand) {
+		let CaptureState::Recording(capture) = &mut *self.state.lock().unwrap() else {
+			return;
+		};
+		if capture.passes.is_empty() {
+			capture.passes.push(CapturedPass {
+				label: "unlabelled".to_string(),
+				commands: Vec::new(),
+			});
+		}
+		capture.passes.last_mut().unwrap().commands.push(command());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn captures_only_the_frame_asked_for() {
+		let capturer = FrameCapturer::default();
+		let draw = || CapturedCommand::Draw {
+			vertices: 0..4,
+			instances: 0..2,
+			layers: vec![3, 5],
+		};
+
+		// Nothing is recorded until a capture is asked for.
+		capturer.begin_frame();
+		capturer.record(|| unreachable!());
+		assert_eq!(capturer.end_frame(), None);
+
+		capturer.capture_next_frame();
+		capturer.record(|| unreachable!());
+		capturer.begin_frame();
+		capturer.begin_pass("Render Pass");
+		capturer.record(|| CapturedCommand::SetPipeline {
+			pipeline: "canvas".to_string(),
+		});
+		capturer.record(draw);
+		let capture = capturer.end_frame().unwrap();
+		assert_eq!(
+			capture,
+			FrameCapture {
+				passes: vec![CapturedPass {
+					label: "Render Pass".to_string(),
+					commands: vec![
+						CapturedCommand::SetPipeline {
+							pipeline: "canvas".to_string()
+						},
+						draw(),
+					],
+				}],
+			}
+		);
+		assert_eq!(capture.draw_count(), 1);
+		assert_eq!(
+			capture.to_string(),
+			"Render Pass\n  pipeline canvas\n  draw 4 vertices × 2 instances, layers [3, 5]\n1 draws"
+		);
+
+		// Later frames aren't recorded.
+		capturer.begin_frame();
+		capturer.record(|| unreachable!());
+		assert_eq!(capturer.end_frame(), None);
+	}
+}
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How many frames may be queued before the GPU finishes with the oldest of them.
+pub const FRAMES_IN_FLIGHT: usize = 3;
+
+/// A copy of some per-frame resource, such as a uniform buffer and its bind group, for each of
+/// [`FRAMES_IN_FLIGHT`]. Each frame writes to the next copy, so it never waits for the GPU to finish
+/// reading the copy of an earlier frame.
+pub struct FrameRing<T> {
+	items: Vec<T>,
+	current: AtomicUsize,
+}
+
+impl<T> FrameRing<T> {
+	/// Creates each copy with `create`, which is given its index.
+	pub fn new(create: impl FnMut(usize) -> T) -> Self {
+		Self {
+			items: (0..FRAMES_IN_FLIGHT).map(create).collect(),
+			current: AtomicUsize::new(0),
+		}
+	}
+
+	/// Moves on to the next frame's copy, and returns it.
+	pub fn advance(&self) -> &T {
+		let index = (self.current.load(Ordering::Relaxed) + 1) % self.items.len();
+		self.current.store(index, Ordering::Relaxed);
+		&self.items[index]
+	}
+
+	/// The current frame's copy.
+	pub fn current(&self) -> &T {
+		&self.items[self.current.load(Ordering::Relaxed)]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cycles_through_copies() {
+		let ring = FrameRing::new(|index| index);
+		assert_eq!(*ring.current(), 0);
+		let advanced: Vec<_> = (0..FRAMES_IN_FLIGHT + 1).map(|_| *ring.advance()).collect();
+		assert_eq!(advanced, [1, 2, 0, 1]);
+		assert_eq!(*ring.current(), 1);
+	}
+}
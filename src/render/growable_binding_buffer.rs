@@ -0,0 +1,149 @@
+use super::BindingBuffer;
+use bon::bon;
+use std::borrow::Borrow;
+use std::ops::Deref;
+
+/// A [`BindingBuffer`] of an array which grows when written past its end. Growing replaces the
+/// underlying buffer, so anything bound to it must be recreated, which [`Self::generation`] tells.
+pub struct GrowableBindingBuffer<T: ?Sized> {
+	buffer: BindingBuffer<T>,
+	capacity: u64,
+	label: Option<String>,
+	usage: wgpu::BufferUsages,
+	generation: u64,
+}
+
+impl<T: ?Sized> Deref for GrowableBindingBuffer<T> {
+	type Target = BindingBuffer<T>;
+	fn deref(&self) -> &Self::Target {
+		&self.buffer
+	}
+}
+
+#[bon]
+impl<T: ?Sized + encase::CalculateSizeFor> GrowableBindingBuffer<T> {
+	/// Builds an uninitialized buffer with room for `capacity` elements.
+	#[builder(finish_fn = create)]
+	pub fn with_capacity(
+		#[builder(start_fn)] capacity: u64,
+		#[builder(finish_fn)] device: &wgpu::Device,
+		label: Option<&str>,
+		usage: Option<wgpu::BufferUsages>,
+	) -> Self {
+		// Growing copies from the old buffer to the new one.
+		let usage = usage.unwrap_or(BindingBuffer::<T>::default_usages())
+			| wgpu::BufferUsages::COPY_SRC
+			| wgpu::BufferUsages::COPY_DST;
+		Self {
+			buffer: Self::create_buffer(device, label, usage, capacity),
+			capacity,
+			label: label.map(str::to_string),
+			usage,
+			generation: 0,
+		}
+	}
+
+	fn create_buffer(
+		device: &wgpu::Device,
+		label: Option<&str>,
+		usage: wgpu::BufferUsages,
+		capacity: u64,
+	) -> BindingBuffer<T> {
+		BindingBuffer::with_capacity(capacity)
+			.maybe_label(label)
+			.usage(usage)
+			.create(device)
+	}
+
+	/// How many elements fit before the buffer grows.
+	pub fn capacity(&self) -> u64 {
+		self.capacity
+	}
+
+	/// Counts how many times the buffer has grown. Whenever this changes, bind groups and bindings
+	/// of the buffer from before are stale.
+	pub fn generation(&self) -> u64 {
+		self.generation
+	}
+}
+
+impl<T: ?Sized + encase::ShaderType + encase::CalculateSizeFor + encase::internal::WriteInto>
+	GrowableBindingBuffer<T>
+{
+	/// Writes `values` starting at element `offset`, first growing the buffer if they don't fit.
+	/// Returns whether it grew.
+	///
+	/// The old contents are copied by a command submitted right away, so writes already queued to
+	/// the old buffer are kept, and this write, queued after, isn't overwritten by the copy. Writes to
+	/// the old buffer by commands not yet submitted are lost.
+	pub fn write_slice(
+		&mut self,
+		device: &wgpu::Device,
+		queue: &wgpu::Queue,
+		offset: u64,
+		values: impl Borrow<T>,
+	) -> bool {
+		let values = values.borrow();
+		let end = BindingBuffer::<T>::raw_offset(offset) + T::size(values).get();
+		let grew = end > self.buffer.size();
+		if grew {
+			let mut capacity = self.capacity.max(1);
+			while T::calculate_size_for(capacity).get() < end {
+				capacity *= 2;
+			}
+			let buffer = Self::create_buffer(device, self.label.as_deref(), self.usage, capacity);
+			let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+				label: Some("GrowableBindingBuffer::write_slice"),
+			});
+			encoder.copy_buffer_to_buffer(&self.buffer, 0, &buffer, 0, self.buffer.size());
+			queue.submit([encoder.finish()]);
+			self.buffer = buffer;
+			self.capacity = capacity;
+			self.generation += 1;
+		}
+		self.buffer.write_slice(queue, offset, values);
+		grew
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test;
+	use std::sync::Arc;
+
+	#[test]
+	fn grows_keeping_contents() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let mut buffer = GrowableBindingBuffer::<[u32]>::with_capacity(2)
+			.label("grows_keeping_contents")
+			.usage(wgpu::BufferUsages::STORAGE)
+			.create(device);
+
+		assert!(!buffer.write_slice(device, queue, 0, [1u32, 2].as_slice()));
+		assert_eq!(buffer.generation(), 0);
+		assert!(buffer.write_slice(device, queue, 2, [3u32, 4, 5].as_slice()));
+		assert_eq!(buffer.generation(), 1);
+		assert_eq!(buffer.capacity(), 8);
+
+		let size = 5 * 4;
+		let readback = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("grows_keeping_contents::readback"),
+			size,
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		let mut encoder = device.create_command_encoder(&Default::default());
+		encoder.copy_buffer_to_buffer(&buffer, 0, &readback, 0, size);
+		queue.submit([encoder.finish()]);
+
+		let data = pollster::block_on(context.get_buffer_data(Arc::new(readback)))?;
+		let values: Vec<u32> = data
+			.chunks_exact(4)
+			.map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+			.collect();
+		assert_eq!(values, [1, 2, 3, 4, 5]);
+		Ok(())
+	}
+}
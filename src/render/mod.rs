@@ -1,9 +1,28 @@
+//! Helpers for creating `wgpu` textures and buffers, and the [`Resources`] shared by everything that
+//! draws.
+
+mod bind_group_cache;
+mod error_scope;
+mod frame_capture;
+mod frame_ring;
+mod growable_binding_buffer;
+mod profiler;
+mod render_graph;
 mod resources;
+mod upload;
 use std::{borrow::Borrow, mem::MaybeUninit, ops::Deref};
 
+pub use bind_group_cache::*;
 use bon::{bon, builder};
+pub use error_scope::*;
+pub use frame_capture::*;
+pub use frame_ring::*;
+pub use growable_binding_buffer::*;
+pub use profiler::*;
+pub use render_graph::*;
 pub use resources::*;
 use thiserror::Error;
+pub use upload::*;
 use wgpu::util::DeviceExt;
 
 #[derive(Debug, Error)]
@@ -48,6 +67,31 @@ fn to_extent_and_dimension(
 	))
 }
 
+/// Calls `create` to create a pipeline and waits until it is ready, returning any validation error
+/// rather than raising it on the device. The error is also reported under `label`, as by
+/// [`error_scope`], as soon as it's raised, so callers with nothing more to do about it can simply
+/// drop it.
+///
+/// `wgpu` has no asynchronous pipeline creation, but on the web, the browser compiles the shaders in
+/// its GPU process and only answers the error scope once it is done. Awaiting that keeps the UI
+/// responsive instead of stalling on the first draw that uses the pipeline. Natively, pipelines are
+/// compiled before `create` returns, so this resolves right away.
+pub async fn create_pipeline_async<P>(
+	device: &wgpu::Device,
+	label: &str,
+	create: impl FnOnce() -> P,
+) -> Result<P, wgpu::Error> {
+	device.push_error_scope(wgpu::ErrorFilter::Validation);
+	let pipeline = create();
+	match device.pop_error_scope().await {
+		Some(error) => {
+			report_gpu_error(label, &error);
+			Err(error)
+		}
+		None => Ok(pipeline),
+	}
+}
+
 #[builder(finish_fn = create)]
 pub fn texture(
 	#[builder(finish_fn)] device: &wgpu::Device,
@@ -74,11 +118,13 @@ pub fn texture(
 		format,
 		view_formats,
 	};
-	if let Some((queue, data)) = with_data {
-		device.create_texture_with_data(queue, &descriptor, Default::default(), data)
-	} else {
-		device.create_texture(&descriptor)
-	}
+	error_scope(device, label.unwrap_or("texture"), || {
+		if let Some((queue, data)) = with_data {
+			device.create_texture_with_data(queue, &descriptor, Default::default(), data)
+		} else {
+			device.create_texture(&descriptor)
+		}
+	})
 }
 
 /// Thin wrapper around a `wgpu::Buffer` that stores the a type `T` in a format suitable for binding
@@ -135,21 +181,35 @@ impl<T: ?Sized + encase::ShaderType + encase::internal::WriteInto> BindingBuffer
 		usage: Option<wgpu::BufferUsages>,
 	) -> Self {
 		let usage = usage.unwrap_or(Self::default_usages());
-		let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-			label,
-			contents: Self::value_to_data(value).borrow(),
-			usage,
+		let buffer = error_scope(device, label.unwrap_or("BindingBuffer"), || {
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label,
+				contents: Self::value_to_data(value).borrow(),
+				usage,
+			})
 		});
 		Self::from_buffer(buffer)
 	}
 
+	/// Encodes `values` straight into upload memory, at `offset` bytes into the buffer.
+	fn upload_at(&self, queue: &wgpu::Queue, offset: u64, values: &T) {
+		upload(queue, &self.buffer, offset, T::size(values).get(), |data| {
+			encase::StorageBuffer::new(data).write(values).unwrap()
+		})
+	}
+
 	/// Writes the given `value` to the buffer.
 	pub fn write(&self, queue: &wgpu::Queue, value: impl Borrow<T>) {
-		queue.write_buffer(
-			&self.buffer,
-			0,
-			Self::value_to_data(value.borrow()).borrow(),
-		)
+		self.upload_at(queue, 0, value.borrow())
+	}
+
+	/// Like [`Self::write`], but through `uploader`, whose copies must be submitted before the
+	/// buffer is read.
+	pub fn stage(&self, uploader: &mut Uploader, value: impl Borrow<T>) {
+		let value = value.borrow();
+		uploader.upload(&self.buffer, 0, T::size(value).get(), |data| {
+			encase::StorageBuffer::new(data).write(value).unwrap()
+		})
 	}
 }
 
@@ -178,11 +238,13 @@ where
 		#[builder(default)] mapped_at_creation: bool,
 	) -> Self {
 		let usage = usage.unwrap_or(Self::default_usages());
-		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label,
-			size: T::SHADER_SIZE.get(),
-			usage,
-			mapped_at_creation,
+		let buffer = error_scope(device, label.unwrap_or("BindingBuffer"), || {
+			device.create_buffer(&wgpu::BufferDescriptor {
+				label,
+				size: T::SHADER_SIZE.get(),
+				usage,
+				mapped_at_creation,
+			})
 		});
 		Self::from_buffer(buffer)
 	}
@@ -198,22 +260,20 @@ where
 		usage: Option<wgpu::BufferUsages>,
 	) -> Self {
 		let usage = usage.unwrap_or(Self::default_usages());
-		let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-			label,
-			contents: Self::sized_value_to_data(value).borrow(),
-			usage,
+		let buffer = error_scope(device, label.unwrap_or("BindingBuffer"), || {
+			device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label,
+				contents: Self::sized_value_to_data(value).borrow(),
+				usage,
+			})
 		});
 		Self::from_buffer(buffer)
 	}
 
 	/// Writes the given `value` to the buffer. The value must implement `encase::ShaderSize`.
-	/// Functionally, this is equivalent to `write` but avoids an extra allocation.
+	/// This is equivalent to `write`, since neither allocates.
 	pub fn write_sized(&self, queue: &wgpu::Queue, value: impl Borrow<T>) {
-		queue.write_buffer(
-			&self.buffer,
-			0,
-			Self::sized_value_to_data(value.borrow()).borrow(),
-		)
+		self.upload_at(queue, 0, value.borrow())
 	}
 }
 
@@ -230,11 +290,13 @@ impl<T: ?Sized + encase::CalculateSizeFor> BindingBuffer<T> {
 		#[builder(default)] mapped_at_creation: bool,
 	) -> Self {
 		let usage = usage.unwrap_or(Self::default_usages());
-		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-			label,
-			size: T::calculate_size_for(capacity).get(),
-			usage,
-			mapped_at_creation,
+		let buffer = error_scope(device, label.unwrap_or("BindingBuffer"), || {
+			device.create_buffer(&wgpu::BufferDescriptor {
+				label,
+				size: T::calculate_size_for(capacity).get(),
+				usage,
+				mapped_at_creation,
+			})
 		});
 		Self::from_buffer(buffer)
 	}
@@ -271,10 +333,6 @@ impl<T: ?Sized + encase::ShaderType + encase::CalculateSizeFor + encase::interna
 	}
 
 	pub fn write_slice(&self, queue: &wgpu::Queue, offset: u64, values: impl Borrow<T>) {
-		queue.write_buffer(
-			&self.buffer,
-			Self::raw_offset(offset),
-			Self::value_to_data(values.borrow()).borrow(),
-		)
+		self.upload_at(queue, Self::raw_offset(offset), values.borrow())
 	}
 }
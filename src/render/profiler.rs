@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The most passes timed in one encoder. Later passes go untimed.
+const MAX_PASSES_PER_ENCODER: u32 = 32;
+
+/// How much each measurement moves a pass' average towards it.
+const DURATION_SMOOTHING: f64 = 0.1;
+
+/// The GPU time taken by passes with some label, in milliseconds. Passes with the same label in
+/// one encoder, such as one per chart drawn, are counted together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassTiming {
+	pub average: f64,
+	pub latest: f64,
+	pub count: u64,
+}
+
+/// Timings by pass label.
+#[derive(Debug, Clone, Default)]
+pub struct PassTimings(BTreeMap<String, PassTiming>);
+
+impl PassTimings {
+	/// Records that passes labelled `label` took `duration` milliseconds.
+	pub fn record(&mut self, label: &str, duration: f64) {
+		match self.0.get_mut(label) {
+			Some(timing) => {
+				timing.average += DURATION_SMOOTHING * (duration - timing.average);
+				timing.latest = duration;
+				timing.count += 1;
+			}
+			None => {
+				self.0.insert(
+					label.to_string(),
+					PassTiming {
+						average: duration,
+						latest: duration,
+						count: 1,
+					},
+				);
+			}
+		}
+	}
+
+	/// Each label and its timing, in order of label.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &PassTiming)> {
+		self
+			.0
+			.iter()
+			.map(|(label, timing)| (label.as_str(), timing))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+/// The query set of an encoder and the buffers its timestamps are read back through. They are
+/// reused once read back.
+#[derive(Debug)]
+struct QueryFrame {
+	query_set: wgpu::QuerySet,
+	resolve_buffer: wgpu::Buffer,
+	readback_buffer: Arc<wgpu::Buffer>,
+}
+
+impl QueryFrame {
+	fn new(device: &wgpu::Device) -> Self {
+		let count = MAX_PASSES_PER_ENCODER * 2;
+		let size = count as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+		Self {
+			query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+				label: Some("GpuProfiler"),
+				ty: wgpu::QueryType::Timestamp,
+				count,
+			}),
+			resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+				label: Some("GpuProfiler::resolve"),
+				size,
+				usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+				mapped_at_creation: false,
+			}),
+			readback_buffer: Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+				label: Some("GpuProfiler::readback"),
+				size,
+				usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+				mapped_at_creation: false,
+			})),
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+	timings: PassTimings,
+	free: Vec<QueryFrame>,
+}
+
+/// Times passes on the GPU with timestamp queries, for finding which are slow. Encoders created
+/// through it write a timestamp at the start and end of each pass, and once they have run, the
+/// durations are added to [`Self::timings`].
+///
+/// Profiling is off until [enabled](Self::set_enabled), and is unavailable without
+/// [`wgpu::Features::TIMESTAMP_QUERY`], in which case encoders are created untimed.
+#[derive(Debug)]
+pub struct GpuProfiler {
+	device: Arc<wgpu::Device>,
+	// Nanoseconds per timestamp tick.
+	period: f32,
+	enabled: AtomicBool,
+	shared: Arc<Mutex<Shared>>,
+}
+
+impl GpuProfiler {
+	pub fn new(device: Arc<wgpu::Device>, queue: &wgpu::Queue) -> Self {
+		Self {
+			device,
+			period: queue.get_timestamp_period(),
+			enabled: AtomicBool::new(false),
+			shared: Default::default(),
+		}
+	}
+
+	pub fn is_supported(&self) -> bool {
+		self
+			.device
+			.features()
+			.contains(wgpu::Features::TIMESTAMP_QUERY)
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.enabled.load(Ordering::Relaxed)
+	}
+
+	/// Starts or stops timing passes. Starting again forgets earlier timings.
+	pub fn set_enabled(&self, enabled: bool) {
+		if enabled && !self.enabled.swap(true, Ordering::Relaxed) {
+			self.shared.lock().unwrap().timings = Default::default();
+		}
+		self.enabled.store(enabled, Ordering::Relaxed);
+	}
+
+	/// The timings of every pass run since profiling was enabled.
+	pub fn timings(&self) -> PassTimings {
+		self.shared.lock().unwrap().timings.clone()
+	}
+
+	/// Creates an encoder whose passes are timed while profiling is enabled. It must be submitted
+	/// with [`ProfiledEncoder::submit`] for the timings to be read back.
+	pub fn create_command_encoder(
+		&self,
+		descriptor: &wgpu::CommandEncoderDescriptor,
+	) -> ProfiledEncoder {
+		let queries = (self.is_enabled() && self.is_supported()).then(|| {
+			let frame = self.shared.lock().unwrap().free.pop();
+			Queries {
+				frame: frame.unwrap_or_else(|| QueryFrame::new(&self.device)),
+				labels: Vec::new(),
+				period: self.period,
+				shared: self.shared.clone(),
+			}
+		});
+		ProfiledEncoder {
+			encoder: self.device.create_command_encoder(descriptor),
+			queries,
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Queries {
+	frame: QueryFrame,
+	// The label of each timed pass, whose timestamps are at twice its index and the one after.
+	labels: Vec<String>,
+	period: f32,
+	shared: Arc<Mutex<Shared>>,
+}
+
+impl Queries {
+	/// Allocates the timestamps of a pass labelled `label`, or returns `None` if there are none left.
+	fn timestamp_indices(&mut self, label: Option<&str>) -> Option<(u32, u32)> {
+		let index = self.labels.len() as u32;
+		if index >= MAX_PASSES_PER_ENCODER {
+			return None;
+		}
+		self.labels.push(label.unwrap_or("unlabelled").to_string());
+		Some((index * 2, index * 2 + 1))
+	}
+
+	/// Reads back the timestamps once the encoder has run, and records them.
+	fn read_back(self) {
+		let Self {
+			frame,
+			labels,
+			period,
+			shared,
+		} = self;
+		if labels.is_empty() {
+			shared.lock().unwrap().free.push(frame);
+			return;
+		}
+		let size = labels.len() as wgpu::BufferAddress * 2 * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+		let readback_buffer = frame.readback_buffer.clone();
+		readback_buffer
+			.slice(..size)
+			.map_async(wgpu::MapMode::Read, move |result| {
+				let mut durations = BTreeMap::<String, f64>::new();
+				if result.is_ok() {
+					let data = frame.readback_buffer.slice(..size).get_mapped_range();
+					let timestamps = data
+						.chunks_exact(wgpu::QUERY_SIZE as usize)
+						.map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+						.collect::<Vec<_>>();
+					for (label, pass) in labels.into_iter().zip(timestamps.chunks_exact(2)) {
+						let ticks = pass[1].saturating_sub(pass[0]);
+						*durations.entry(label).or_default() += ticks as f64 * period as f64 * 1e-6;
+					}
+				}
+				frame.readback_buffer.unmap();
+
+				let mut shared = shared.lock().unwrap();
+				for (label, duration) in durations {
+					shared.timings.record(&label, duration);
+				}
+				shared.free.push(frame);
+			});
+	}
+}
+
+/// A command encoder from [`GpuProfiler::create_command_encoder`]. Passes begun through it are
+/// timed, and it otherwise acts as the encoder it wraps.
+#[derive(Debug)]
+pub struct ProfiledEncoder {
+	encoder: wgpu::CommandEncoder,
+	queries: Option<Queries>,
+}
+
+impl ProfiledEncoder {
+	/// Begins a render pass, timed under its label. Any timestamp writes of `descriptor` are replaced.
+	pub fn begin_render_pass(
+		&mut self,
+		descriptor: &wgpu::RenderPassDescriptor<'_>,
+	) -> wgpu::RenderPass<'_> {
+		let queries = self.queries.as_mut();
+		let indices = queries.and_then(|queries| queries.timestamp_indices(descriptor.label));
+		let timestamp_writes = indices.map(|(beginning, end)| wgpu::RenderPassTimestampWrites {
+			query_set: &self.queries.as_ref().unwrap().frame.query_set,
+			beginning_of_pass_write_index: Some(beginning),
+			end_of_pass_write_index: Some(end),
+		});
+		self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			timestamp_writes,
+			..descriptor.clone()
+		})
+	}
+
+	/// Begins a compute pass, timed under its label. Any timestamp writes of `descriptor` are
+	/// replaced.
+	pub fn begin_compute_pass(
+		&mut self,
+		descriptor: &wgpu::ComputePassDescriptor<'_>,
+	) -> wgpu::ComputePass<'_> {
+		let queries = self.queries.as_mut();
+		let indices = queries.and_then(|queries| queries.timestamp_indices(descriptor.label));
+		let timestamp_writes = indices.map(|(beginning, end)| wgpu::ComputePassTimestampWrites {
+			query_set: &self.queries.as_ref().unwrap().frame.query_set,
+			beginning_of_pass_write_index: Some(beginning),
+			end_of_pass_write_index: Some(end),
+		});
+		self
+			.encoder
+			.begin_compute_pass(&wgpu::ComputePassDescriptor {
+				timestamp_writes,
+				..descriptor.clone()
+			})
+	}
+
+	/// Finishes the encoder and submits it to `queue`, then reads back how long its passes took.
+	pub fn submit(self, queue: &wgpu::Queue) -> wgpu::SubmissionIndex {
+		let Self {
+			mut encoder,
+			queries,
+		} = self;
+		let Some(queries) = queries else {
+			return queue.submit([encoder.finish()]);
+		};
+		let count = queries.labels.len() as u32 * 2;
+		if count > 0 {
+			let frame = &queries.frame;
+			encoder.resolve_query_set(&frame.query_set, 0..count, &frame.resolve_buffer, 0);
+			encoder.copy_buffer_to_buffer(
+				&frame.resolve_buffer,
+				0,
+				&frame.readback_buffer,
+				0,
+				count as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress,
+			);
+		}
+		let index = queue.submit([encoder.finish()]);
+		queries.read_back();
+		index
+	}
+}
+
+impl Deref for ProfiledEncoder {
+	type Target = wgpu::CommandEncoder;
+
+	fn deref(&self) -> &Self::Target {
+		&self.encoder
+	}
+}
+
+impl DerefMut for ProfiledEncoder {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.encoder
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test;
+
+	#[test]
+	fn pass_timings() {
+		let mut timings = PassTimings::default();
+		assert!(timings.is_empty());
+		timings.record("b", 2.0);
+		timings.record("a", 1.0);
+		timings.record("b", 12.0);
+		let timings = timings.iter().collect::<Vec<_>>();
+		assert_eq!(timings[0].0, "a");
+		assert_eq!(
+			*timings[0].1,
+			PassTiming {
+				average: 1.0,
+				latest: 1.0,
+				count: 1
+			}
+		);
+		assert_eq!(timings[1].0, "b");
+		approx::assert_abs_diff_eq!(timings[1].1.average, 3.0, epsilon = 1e-9);
+		assert_eq!(timings[1].1.latest, 12.0);
+		assert_eq!(timings[1].1.count, 2);
+	}
+
+	#[test]
+	fn times_passes() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let profiler = context.profiler();
+		if !profiler.is_supported() {
+			return Ok(());
+		}
+		profiler.set_enabled(true);
+		let texture = context.device().create_texture(&wgpu::TextureDescriptor {
+			label: None,
+			size: wgpu::Extent3d {
+				width: 64,
+				height: 64,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::Rgba8Unorm,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+			view_formats: &[],
+		});
+		let view = texture.create_view(&Default::default());
+		let mut encoder = profiler.create_command_encoder(&Default::default());
+		for _ in 0..2 {
+			encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("clear"),
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view: &view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+						store: wgpu::StoreOp::Store,
+					},
+				})],
+				..Default::default()
+			});
+		}
+		encoder.submit(context.queue());
+		context.device().poll(wgpu::Maintain::wait());
+
+		// Both passes share a label, so they are counted once.
+		let timings = profiler.timings();
+		let timings = timings.iter().collect::<Vec<_>>();
+		assert_eq!(timings.len(), 1);
+		assert_eq!(timings[0].0, "clear");
+		assert_eq!(timings[0].1.count, 1);
+		assert!(timings[0].1.latest >= 0.0);
+		profiler.set_enabled(false);
+		Ok(())
+	}
+}
@@ -0,0 +1,146 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RenderGraphError {
+	#[error("passes {0:?} depend on each other")]
+	Cycle(Vec<&'static str>),
+}
+
+struct GraphPass<'a, E> {
+	label: &'static str,
+	reads: Vec<&'static str>,
+	writes: Vec<&'static str>,
+	encode: Box<dyn FnOnce(&mut E) + 'a>,
+}
+
+/// Passes of a frame, each declaring the resources, such as textures, it reads and writes by name.
+/// They're encoded in an order where every pass reading a resource comes after every pass writing
+/// it. Passes writing the same resource, including those which also read it, as by loading a
+/// target, run in the order they were added, as do passes which don't otherwise depend on each
+/// other.
+///
+/// Passes are encoded into an encoder of type `E`, such as a [`super::ProfiledEncoder`] or a
+/// [`wgpu::CommandEncoder`].
+pub struct RenderGraph<'a, E> {
+	passes: Vec<GraphPass<'a, E>>,
+}
+
+impl<E> Default for RenderGraph<'_, E> {
+	fn default() -> Self {
+		Self { passes: Vec::new() }
+	}
+}
+
+impl<'a, E> RenderGraph<'a, E> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a pass labelled `label`, reading `reads` and writing `writes`, which `encode` encodes.
+	pub fn add_pass(
+		&mut self,
+		label: &'static str,
+		reads: impl IntoIterator<Item = &'static str>,
+		writes: impl IntoIterator<Item = &'static str>,
+		encode: impl FnOnce(&mut E) + 'a,
+	) {
+		self.passes.push(GraphPass {
+			label,
+			reads: reads.into_iter().collect(),
+			writes: writes.into_iter().collect(),
+			encode: Box::new(encode),
+		});
+	}
+
+	/// The indices of the passes, in the order they're encoded.
+	fn order(&self) -> Result<Vec<usize>, RenderGraphError> {
+		let count = self.passes.len();
+		// Each pass' dependents, and how many passes it depends on.
+		let mut dependents = vec![Vec::new(); count];
+		let mut dependencies = vec![0; count];
+		for (i, pass) in self.passes.iter().enumerate() {
+			let writes =
+				|j: usize, resource: &str| self.passes[j].writes.iter().any(|w| *w == resource);
+			for (j, other) in self.passes.iter().enumerate() {
+				let depends = pass.reads.iter().any(|r| writes(j, r) && !writes(i, r))
+					|| (j < i && pass.writes.iter().any(|r| other.writes.contains(r)));
+				if j != i && depends {
+					dependents[j].push(i);
+					dependencies[i] += 1;
+				}
+			}
+		}
+
+		let mut order = Vec::with_capacity(count);
+		let mut ready: Vec<usize> = (0..count).filter(|&i| dependencies[i] == 0).collect();
+		// The earliest added pass that's ready runs first.
+		while let Some(&i) = ready.iter().min() {
+			ready.retain(|&r| r != i);
+			order.push(i);
+			for &dependent in &dependents[i] {
+				dependencies[dependent] -= 1;
+				if dependencies[dependent] == 0 {
+					ready.push(dependent);
+				}
+			}
+		}
+		if order.len() < count {
+			let cycle = (0..count)
+				.filter(|&i| dependencies[i] > 0)
+				.map(|i| self.passes[i].label)
+				.collect();
+			return Err(RenderGraphError::Cycle(cycle));
+		}
+		Ok(order)
+	}
+
+	/// The labels of the passes, in the order they're encoded.
+	pub fn labels(&self) -> Result<Vec<&'static str>, RenderGraphError> {
+		Ok(self
+			.order()?
+			.into_iter()
+			.map(|i| self.passes[i].label)
+			.collect())
+	}
+
+	/// Encodes the passes into `encoder`. Nothing is encoded if they can't be ordered.
+	pub fn encode(self, encoder: &mut E) -> Result<(), RenderGraphError> {
+		let order = self.order()?;
+		let mut passes = self.passes.into_iter().map(Some).collect::<Vec<_>>();
+		for i in order {
+			let pass = passes[i].take().unwrap();
+			(pass.encode)(encoder);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn readers_follow_writers() {
+		let mut graph = RenderGraph::<Vec<&str>>::new();
+		graph.add_pass("present", ["frame"], [], |e| e.push("present"));
+		graph.add_pass("canvas", ["tiles"], ["frame"], |e| e.push("canvas"));
+		// The canvas and overlay both write the frame, so they keep the order they were added in.
+		graph.add_pass("overlay", ["frame"], ["frame"], |e| e.push("overlay"));
+		graph.add_pass("tiles", [], ["tiles"], |e| e.push("tiles"));
+		let expected = ["tiles", "canvas", "overlay", "present"];
+		assert_eq!(graph.labels(), Ok(expected.to_vec()));
+
+		let mut encoded = Vec::new();
+		graph.encode(&mut encoded).unwrap();
+		assert_eq!(encoded, expected);
+	}
+
+	#[test]
+	fn cycles_are_errors() {
+		let mut graph = RenderGraph::<()>::new();
+		graph.add_pass("independent", [], ["c"], |_| {});
+		graph.add_pass("a", ["b"], ["a"], |_| {});
+		graph.add_pass("b", ["a"], ["b"], |_| {});
+		assert_eq!(graph.labels(), Err(RenderGraphError::Cycle(vec!["a", "b"])));
+	}
+}
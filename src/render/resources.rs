@@ -1,32 +1,105 @@
 use std::sync::Arc;
 
+use super::{error_scope, BindGroupCache, BindingBuffer};
 use crate::shaders::*;
 
+/// How many [`copy_transform`] bind groups are cached.
+const COPY_TRANSFORM_BIND_GROUP_CAPACITY: usize = 64;
+
+/// A bind group of [`copy_transform`], with the transform buffer and sampler it binds.
+pub type CopyTransformBindGroup = (
+	BindingBuffer<copy_transform::Transform>,
+	wgpu::Sampler,
+	copy_transform::BindGroup0,
+);
+
 /// Resources that only need to be loaded once for a given device.
 pub struct Resources {
 	pub canvas: canvas::Shader,
 	pub airbrush: airbrush::Shader,
+	pub smudge: smudge::Shader,
+	pub mixer: mixer::Shader,
 	pub color_picker: color_picker::Shader,
+	pub cursor: cursor::Shader,
+	pub stroke_tip: stroke_tip::Shader,
+	pub bounds: bounds::Shader,
+	pub guides: guides::Shader,
 	pub copy_transform: copy_transform::Shader,
+	/// Keyed by whether the source texture is filterable, which determines the layout.
+	pub copy_transform_bind_groups: BindGroupCache<bool, CopyTransformBindGroup>,
+
+	pub depth_to_layers_r32float: depth_to_layers_r32float::Shader,
+	pub depth_to_layers_rgba16float: depth_to_layers_rgba16float::Shader,
+	pub depth_to_layers_rgba32float: depth_to_layers_rgba32float::Shader,
+	pub layers_to_depth_r32float: layers_to_depth_r32float::Shader,
+	pub layers_to_depth_rgba16float: layers_to_depth_rgba16float::Shader,
+	pub layers_to_depth_rgba32float: layers_to_depth_rgba32float::Shader,
+	pub transfer: transfer::Shader,
+	pub scan: scan::Shader,
+	pub resample: resample::Shader,
+	pub dither: dither::Shader,
 
-	pub depth_to_layers: depth_to_layers::Shader,
-	pub layers_to_depth: layers_to_depth::Shader,
-	pub log_transform: log_transform::Shader,
-	pub horizontal_scan: horizontal_scan::Shader,
+	pipeline_cache: Option<wgpu::PipelineCache>,
 }
 
 impl Resources {
 	pub fn new(device: &Arc<wgpu::Device>) -> Self {
-		Resources {
+		Self::with_pipeline_cache_data(device, None)
+	}
+
+	/// Creates the resources with a pipeline cache, if the device supports one, starting from `data`
+	/// previously returned by [`Self::pipeline_cache_data`]. The data must be from an adapter with
+	/// the same [`wgpu::util::pipeline_cache_key`], but is otherwise validated, and ignored if stale.
+	pub fn with_pipeline_cache_data(device: &Arc<wgpu::Device>, data: Option<&[u8]>) -> Self {
+		let pipeline_cache = device
+			.features()
+			.contains(wgpu::Features::PIPELINE_CACHE)
+			.then(|| {
+				// SAFETY: The data is only reused on adapters with the same cache key, as required.
+				unsafe {
+					device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+						label: Some("Resources::pipeline_cache"),
+						data,
+						fallback: true,
+					})
+				}
+			});
+		error_scope(device, "Resources", || Resources {
 			canvas: canvas::Shader::new(device.clone()),
 			airbrush: airbrush::Shader::new(device.clone()),
+			smudge: smudge::Shader::new(device.clone()),
+			mixer: mixer::Shader::new(device.clone()),
 			color_picker: color_picker::Shader::new(device.clone()),
+			cursor: cursor::Shader::new(device.clone()),
+			stroke_tip: stroke_tip::Shader::new(device.clone()),
+			bounds: bounds::Shader::new(device.clone()),
+			guides: guides::Shader::new(device.clone()),
 			copy_transform: copy_transform::Shader::new(device.clone()),
+			copy_transform_bind_groups: BindGroupCache::new(COPY_TRANSFORM_BIND_GROUP_CAPACITY),
+
+			depth_to_layers_r32float: depth_to_layers_r32float::Shader::new(device.clone()),
+			depth_to_layers_rgba16float: depth_to_layers_rgba16float::Shader::new(device.clone()),
+			depth_to_layers_rgba32float: depth_to_layers_rgba32float::Shader::new(device.clone()),
+			layers_to_depth_r32float: layers_to_depth_r32float::Shader::new(device.clone()),
+			layers_to_depth_rgba16float: layers_to_depth_rgba16float::Shader::new(device.clone()),
+			layers_to_depth_rgba32float: layers_to_depth_rgba32float::Shader::new(device.clone()),
+			transfer: transfer::Shader::new(device.clone()),
+			scan: scan::Shader::new(device.clone()),
+			resample: resample::Shader::new(device.clone()),
+			dither: dither::Shader::new(device.clone()),
+
+			pipeline_cache,
+		})
+	}
+
+	/// The cache every pipeline should be created with, so compiled shaders can be reused by later
+	/// sessions. This is `None` where the backend doesn't support one, including on the web.
+	pub fn pipeline_cache(&self) -> Option<&wgpu::PipelineCache> {
+		self.pipeline_cache.as_ref()
+	}
 
-			depth_to_layers: depth_to_layers::Shader::new(device.clone()),
-			layers_to_depth: layers_to_depth::Shader::new(device.clone()),
-			log_transform: log_transform::Shader::new(device.clone()),
-			horizontal_scan: horizontal_scan::Shader::new(device.clone()),
-		}
+	/// The contents of the pipeline cache, to save for [`Self::with_pipeline_cache_data`].
+	pub fn pipeline_cache_data(&self) -> Option<Vec<u8>> {
+		self.pipeline_cache.as_ref()?.get_data()
 	}
 }
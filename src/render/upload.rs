@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Counts the bytes written to buffers through [`upload`] and [`Uploader`], by frame.
+#[derive(Debug, Default)]
+pub struct UploadMetrics {
+	frame_bytes: AtomicU64,
+	last_frame_bytes: AtomicU64,
+	total_bytes: AtomicU64,
+}
+
+impl UploadMetrics {
+	fn record(&self, bytes: u64) {
+		self.frame_bytes.fetch_add(bytes, Ordering::Relaxed);
+		self.total_bytes.fetch_add(bytes, Ordering::Relaxed);
+	}
+
+	/// Ends the current frame, returning how many bytes were uploaded during it.
+	pub fn end_frame(&self) -> u64 {
+		let bytes = self.frame_bytes.swap(0, Ordering::Relaxed);
+		self.last_frame_bytes.store(bytes, Ordering::Relaxed);
+		bytes
+	}
+
+	/// How many bytes were uploaded during the last frame ended.
+	pub fn last_frame_bytes(&self) -> u64 {
+		self.last_frame_bytes.load(Ordering::Relaxed)
+	}
+
+	/// How many bytes have been uploaded in all.
+	pub fn total_bytes(&self) -> u64 {
+		self.total_bytes.load(Ordering::Relaxed)
+	}
+}
+
+static METRICS: UploadMetrics = UploadMetrics {
+	frame_bytes: AtomicU64::new(0),
+	last_frame_bytes: AtomicU64::new(0),
+	total_bytes: AtomicU64::new(0),
+};
+
+/// The metrics of every upload. Frames are ended by whatever presents them.
+pub fn upload_metrics() -> &'static UploadMetrics {
+	&METRICS
+}
+
+/// Writes `size` bytes to `buffer` at `offset`, filled in by `write`. They're written straight to
+/// staging memory the queue sets aside for this write, rather than to an allocation copied from.
+/// Uploads made for every input event should go through an [`Uploader`] instead, which recycles
+/// its staging memory.
+pub fn upload(
+	queue: &wgpu::Queue,
+	buffer: &wgpu::Buffer,
+	offset: wgpu::BufferAddress,
+	size: u64,
+	write: impl FnOnce(&mut [u8]),
+) {
+	let Some(size) = wgpu::BufferSize::new(size) else {
+		return;
+	};
+	// This only fails if the write is out of bounds, which is reported to the device as an error.
+	if let Some(mut view) = queue.write_buffer_with(buffer, offset, size) {
+		write(&mut view);
+		METRICS.record(size.get());
+	}
+}
+
+/// Uploads through a ring of staging buffers, which are mapped again for writing once the GPU has
+/// copied out of them. This way, uploads made as often as every input event reuse the same memory
+/// rather than allocating more.
+///
+/// The copies are recorded to a command buffer of their own, from [`Self::finish`], which must be
+/// submitted before anything reads the buffers written. [`Self::recall`] recycles the staging
+/// buffers of copies already submitted.
+pub struct Uploader {
+	device: Arc<wgpu::Device>,
+	belt: wgpu::util::StagingBelt,
+	encoder: Option<wgpu::CommandEncoder>,
+}
+
+impl Uploader {
+	/// `chunk_size` is the size of each staging buffer, which should fit the uploads made between
+	/// submissions. Larger uploads get staging buffers of their own size.
+	pub fn new(device: &Arc<wgpu::Device>, chunk_size: u64) -> Self {
+		Self {
+			device: device.clone(),
+			belt: wgpu::util::StagingBelt::new(chunk_size),
+			encoder: None,
+		}
+	}
+
+	/// Like [`upload`], but staged in the ring. `offset` and `size` must be multiples of
+	/// [`wgpu::COPY_BUFFER_ALIGNMENT`].
+	pub fn upload(
+		&mut self,
+		buffer: &wgpu::Buffer,
+		offset: wgpu::BufferAddress,
+		size: u64,
+		write: impl FnOnce(&mut [u8]),
+	) {
+		let Some(size) = wgpu::BufferSize::new(size) else {
+			return;
+		};
+		let encoder = self.encoder.get_or_insert_with(|| {
+			self
+				.device
+				.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+					label: Some("Uploader"),
+				})
+		});
+		let mut view = self
+			.belt
+			.write_buffer(encoder, buffer, offset, size, &self.device);
+		write(&mut view);
+		METRICS.record(size.get());
+	}
+
+	/// The copies of the uploads since the last call, if there were any.
+	pub fn finish(&mut self) -> Option<wgpu::CommandBuffer> {
+		let encoder = self.encoder.take()?;
+		self.belt.finish();
+		Some(encoder.finish())
+	}
+
+	/// Recycles the staging buffers of every [`Self::finish`]ed command buffer, which must all have
+	/// been submitted or dropped.
+	pub fn recall(&mut self) {
+		self.belt.recall();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test;
+
+	#[test]
+	fn uploads_and_counts_bytes() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("uploads_and_counts_bytes"),
+			size: 8,
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		// Other tests upload concurrently, so this can only tell the count went up by at least as
+		// much.
+		let before = upload_metrics().total_bytes();
+		upload(queue, &buffer, 4, 4, |data| {
+			data.copy_from_slice(&[1, 2, 3, 4])
+		});
+		upload(queue, &buffer, 0, 0, |_| unreachable!());
+		assert!(upload_metrics().total_bytes() - before >= 4);
+		queue.submit([]);
+
+		let data = pollster::block_on(context.get_buffer_data(Arc::new(buffer)))?;
+		assert_eq!(data, [0, 0, 0, 0, 1, 2, 3, 4]);
+		Ok(())
+	}
+
+	#[test]
+	fn uploader_reuses_staging_buffers() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let (device, queue) = (context.device(), context.queue());
+		let mut uploader = Uploader::new(device, 8);
+		assert!(uploader.finish().is_none());
+		// Reading back polls the device, so each upload after the first has a recalled staging
+		// buffer to reuse. Read back buffers stay mapped, so each upload gets its own.
+		for value in [1, 2, 3] {
+			let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+				label: Some("uploader_reuses_staging_buffers"),
+				size: 8,
+				usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+				mapped_at_creation: false,
+			}));
+			uploader.upload(&buffer, 0, 8, |data| data.fill(value));
+			queue.submit(uploader.finish());
+			uploader.recall();
+			let data = pollster::block_on(context.get_buffer_data(buffer.clone()))?;
+			assert_eq!(data, [value; 8]);
+		}
+		Ok(())
+	}
+}
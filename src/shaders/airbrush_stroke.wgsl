@@ -0,0 +1,60 @@
+// The vertex stage shared by brushes which draw along the airbrush's triangle strips. Including
+// shaders must declare `shape_texture` and `shape_sampler` and include `tile_write.wgsl`.
+
+struct VertexInput {
+	@builtin(vertex_index) vertex_index: u32,
+	@location(0) position: vec2<f32>,
+	@location(1) u_bounds: vec2<f32>,
+	@location(2) opacity: f32,
+	@location(3) rate: f32,
+	@location(4) width: f32,
+	// The atlas chart whose minimum corner `position` is relative to, so strokes far from the canvas
+	// origin keep their precision. Tiles not scaled like the atlas' charts are only drawn relative to
+	// the chart at the origin.
+	@location(5) chart_origin: vec2<i32>,
+};
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) @interpolate(perspective) u_bounds: vec2<f32>,
+	@location(1) @interpolate(perspective) vw: vec2<f32>,
+	@location(2) @interpolate(perspective) rate: f32,
+
+	// Only used for debugging.
+	@location(3) @interpolate(flat) face_index: f32,
+};
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+	 let canvas_position = in.position;
+	 let layer_tile_data = tile_data[layer_index];
+	 let chart_position = (canvas_position - layer_tile_data.chart_to_canvas_translation) / layer_tile_data.chart_to_canvas_scale - vec2<f32>(layer_tile_data.chart_offset - in.chart_origin);
+    out.position = vec4(vec2(2.0, -2.0) * (chart_position - 0.5), 0.0, 1.0) / in.width;
+    out.u_bounds = in.u_bounds;
+    out.vw = vec2(f32(in.vertex_index & 1), in.opacity);
+	 out.rate = in.rate;
+	 out.face_index = f32(in.vertex_index);
+    return out;
+}
+
+// The W coordinate of the shape texture for `opacity`. Each level is sampled at its texel center, so
+// opacities between levels are interpolated. This must invert `level_opacity` in `airbrush.rs`.
+fn opacity_coordinate(opacity: f32) -> f32 {
+	let levels = f32(textureDimensions(shape_texture).z);
+	let t = 1.0 - sqrt(1.0 - clamp(opacity, 0.0, 1.0));
+	return (t * (levels - 1.0) + 0.5) / levels;
+}
+
+// The log of how much of the canvas under a fragment shows through the stroke.
+fn stroke_log_transmission(in: VertexOutput) -> f32 {
+    let vw = vec2(in.vw.x, opacity_coordinate(in.vw.y));
+    return in.rate * (textureSample(shape_texture, shape_sampler, vec3(in.u_bounds.y, vw)).x -
+	                   textureSample(shape_texture, shape_sampler, vec3(in.u_bounds.x, vw)).x);
+}
+
+fn expm1(x: f32) -> f32 {
+    return exp(x) - 1;
+}
@@ -21,7 +21,8 @@ fn vs_main(
 	let y = 0.5 * f32(vertex_index & 2u);
 	let layer_index = instance_in.layer_index;
 	let layer_tile_data = tile_data[layer_index];
-	out.position = vec4(layer_tile_data.chart_to_canvas_scale * vec2(x, y) + layer_tile_data.chart_to_canvas_translation, 0.0, 1.0);
+	let chart_position = vec2(x, y) + vec2<f32>(layer_tile_data.chart_offset);
+	out.position = vec4(layer_tile_data.chart_to_canvas_scale * chart_position + layer_tile_data.chart_to_canvas_translation, 0.0, 1.0);
 	out.tex_coords = vec2<f32>(x, y);
 	out.layer_index = layer_index;
 	return out;
@@ -0,0 +1,58 @@
+struct Bounds {
+	// Maps screen pixels, with the origin at the top left, to canvas coordinates relative to the
+	// top-left corner of the bounds.
+	screen_to_bounds: mat4x4<f32>,
+	// The size of the bounds, in canvas units.
+	size: vec2<f32>,
+	// Screen pixels per canvas unit.
+	zoom: f32,
+	// How much to darken what's outside of the bounds, from 0 to 1.
+	outside_opacity: f32,
+};
+@group(0) @binding(0)
+var<uniform> bounds: Bounds;
+
+// The width of each of the two lines making up the border, in screen pixels.
+const LINE_WIDTH: f32 = 1.0;
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+};
+
+// The whole screen is covered, since the bounds may extend beyond it in any direction.
+@vertex
+fn vs_main(
+	@builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+	let x = f32(vertex_index & 1u);
+	let y = 0.5 * f32(vertex_index & 2u);
+
+	var out: VertexOutput;
+	out.position = vec4(2.0 * vec2(x, y) - 1.0, 0.0, 1.0);
+	return out;
+}
+
+// The signed distance from `p` to the edge of a box from the origin to `size`, negative inside.
+fn box_distance(p: vec2<f32>, size: vec2<f32>) -> f32 {
+	let d = max(-p, p - size);
+	return length(max(d, vec2(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+// The antialiased coverage of a line of `LINE_WIDTH` centered on `offset`, at `distance`.
+fn line(distance: f32, offset: f32) -> f32 {
+	return clamp(0.5 * LINE_WIDTH + 0.5 - abs(distance - offset), 0.0, 1.0);
+}
+
+// The border is a light line just inside the edge over a dark line just outside it, like the brush
+// cursor, so it stays visible over any paint. The output is premultiplied.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	let p = (bounds.screen_to_bounds * vec4(in.position.xy, 0.0, 1.0)).xy;
+	let distance = bounds.zoom * box_distance(p, bounds.size);
+	let light = line(distance, -0.5 * LINE_WIDTH);
+	let dark = line(distance, 0.5 * LINE_WIDTH);
+	let outside = bounds.outside_opacity * clamp(distance + 0.5, 0.0, 1.0);
+	let shade = max(dark, outside);
+	let alpha = light + (1.0 - light) * shade;
+	return vec4(vec3(light), alpha);
+}
@@ -1,15 +1,38 @@
-include!("oklab.wgsl") {}
+include!("lib/oklab.wgsl") { namespace: oklab }
+include!("lib/chart_color.wgsl") {}
 
 @group(0) @binding(0)
 var chart_sampler: sampler;
+
+struct CanvasView {
+	// Maps canvas coordinates, relative to the corner of the `chart_origin` chart, to view coordinates.
+	canvas_to_view: mat4x4<f32>,
+	chart_origin: vec2<i32>,
+	// Only canvas coordinates from `clip_min` up to `clip_max`, relative to the same corner, are drawn.
+	clip_min: vec2<f32>,
+	clip_max: vec2<f32>,
+	// The size of a chart texel on screen, in pixels.
+	texel_size: f32,
+	// How opaque the lines between texels are drawn, or 0 to draw none.
+	grid_opacity: f32,
+};
 @group(0) @binding(1)
-var<uniform> canvas_to_view: mat4x4<f32>;
+var<uniform> view: CanvasView;
 
 include!("tile_read.wgsl") {}
 
+// The premultiplied color of the lines between texels.
+const GRID_COLOR: vec4<f32> = vec4(0.5, 0.5, 0.5, 1.0);
+
+// Whether the target encodes colors itself, as sRGB formats do, so the output must be linear. This
+// must match `CanvasRenderer::create_pipeline`.
+override linear_output: bool = false;
+
 struct VertexOutput {
 	@location(0) chart_position: vec2<f32>,
 	@location(1) @interpolate(flat) layer_index: u32,
+	// Relative to the corner of the `chart_origin` chart.
+	@location(2) canvas_position: vec2<f32>,
 	@builtin(position) view_position: vec4<f32>,
 };
 
@@ -23,18 +46,40 @@ fn vs_main(
 	let chart_position = vec2<f32>(x, y);
 
 	let layer_tile_data = tile_data[instance.layer_index];
-	let canvas_position = layer_tile_data.chart_to_canvas_scale * chart_position + layer_tile_data.chart_to_canvas_translation;
-	let view_position = canvas_to_view * vec4(canvas_position, 0.0, 1.0);
+	let chart_offset = vec2<f32>(layer_tile_data.chart_offset - view.chart_origin);
+	let canvas_position = layer_tile_data.chart_to_canvas_scale * (chart_position + chart_offset) + layer_tile_data.chart_to_canvas_translation;
+	let view_position = view.canvas_to_view * vec4(canvas_position, 0.0, 1.0);
 
 	var out: VertexOutput;
 	out.layer_index = instance.layer_index;
 	out.chart_position = chart_position;
+	out.canvas_position = canvas_position;
 	out.view_position = view_position;
 	return out;
 }
 
+// The output is premultiplied, so the canvas composites correctly over whatever is behind it, be it
+// a cleared background or the page. It isn't clamped, so extended-range targets show colors outside
+// of sRGB.
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-	let oklab = textureSample(tile_texture, chart_sampler, in.chart_position, in.layer_index);
-	return vec4(oklab_to_rgb(oklab.xyz), oklab.w);
+	if any(in.canvas_position < view.clip_min) || any(in.canvas_position >= view.clip_max) {
+		return vec4(0.0);
+	}
+	let stored = textureSample(tile_texture, chart_sampler, in.chart_position, in.layer_index);
+	let alpha = clamp(stored.w, 0.0, 1.0);
+	let linear = oklab::oklab_to_linear_srgb(decode_chart_color(stored.xyz));
+	var rgb = oklab::linear_srgb_to_rgb(linear);
+	if linear_output {
+		rgb = linear;
+	}
+	let color = vec4(rgb * alpha, alpha);
+	if view.grid_opacity <= 0.0 {
+		return color;
+	}
+	// A line a pixel wide along the edges of each texel.
+	let texel = in.chart_position * vec2<f32>(textureDimensions(tile_texture));
+	let edge = view.texel_size * abs(texel - round(texel));
+	let grid = view.grid_opacity * clamp(1.0 - min(edge.x, edge.y), 0.0, 1.0);
+	return mix(color, GRID_COLOR, grid);
 }
@@ -1,5 +1,5 @@
-include!("oklab.wgsl") {}
-include!("dither.wgsl") {}
+include!("lib/oklab.wgsl") { namespace: oklab }
+include!("lib/dither.wgsl") { namespace: dither }
 
 @group(0) @binding(0)
 var<uniform> lightness: f32;
@@ -28,12 +28,27 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 	let srgb = constrained_oklab_to_linear_srgb(lightness, in.tex_coords);
-	// let srgb = oklab_to_linear_srgb(vec3(lightness, in.tex_coords));
+	// let srgb = oklab::oklab_to_linear_srgb(vec3(lightness, in.tex_coords));
 	// if !valid_color(srgb) {
 	// 	return vec4(0.0);
 	// }
-	let rgb = linear_srgb_to_rgb(srgb) + dither3(in.clip_position.xy) / 128.0;
-	return vec4(rgb, 1.0);
+	let rgb = oklab::linear_srgb_to_rgb(srgb) + dither::dither3(in.clip_position.xy) / 128.0;
+
+	// Outline the edge of the sRGB gamut, past which colors are scaled back into it, in whichever of
+	// black or white stands out against the lightness.
+	let excess = gamut_excess(oklab::oklab_to_linear_srgb(vec3(lightness, in.tex_coords)));
+	let edge = clamp(GAMUT_EDGE_WIDTH - abs(excess) / fwidth(excess), 0.0, 1.0);
+	let edge_color = vec3(select(0.0, 1.0, lightness < 0.5));
+	return vec4(mix(rgb, edge_color, edge), 1.0);
+}
+
+// The width of the line along the edge of the gamut, in pixels.
+const GAMUT_EDGE_WIDTH: f32 = 1.5;
+
+// How far the furthest channel of `rgb` is outside of [0, 1], or negative if all are inside.
+fn gamut_excess(rgb: vec3<f32>) -> f32 {
+	let excess = max(-rgb, rgb - 1.0);
+	return max(excess.x, max(excess.y, excess.z));
 }
 
 fn valid_color(rgb: vec3<f32>) -> bool {
@@ -53,13 +68,13 @@ override proof: bool = true;
 // Scales chroma to produce a valid sRGB color.
 fn constrained_oklab_to_linear_srgb(L: f32, ab: vec2<f32>) -> vec3<f32> {
 	var s = 0.5;
-	var r = oklab_to_linear_srgb(vec3(L, s * ab));
+	var r = oklab::oklab_to_linear_srgb(vec3(L, s * ab));
 	var step_size = 0.5;
 
 	for (var i = 0; i < 8; i = i + 1) {
 		step_size = step_size * 0.5;
 		s = s + step(valid_color(r), step_size);
-		r = oklab_to_linear_srgb(vec3(L, s * ab));
+		r = oklab::oklab_to_linear_srgb(vec3(L, s * ab));
 	}
 
 	// Final step with the same step size. This allows us to reach 1.0.
@@ -68,5 +83,5 @@ fn constrained_oklab_to_linear_srgb(L: f32, ab: vec2<f32>) -> vec3<f32> {
 	if proof {
 		proof_factor = pow(1.0 - s, 0.25);
 	}
-	return mix(oklab_to_linear_srgb(vec3(L, s * ab)), 0.25 * vec3(1.0 - L), proof_factor);
+	return mix(oklab::oklab_to_linear_srgb(vec3(L, s * ab)), 0.25 * vec3(1.0 - L), proof_factor);
 }
@@ -1,6 +1,11 @@
 
+// Maps the source, which spans clip space, to its place in the destination, also in clip space.
+struct Transform {
+	linear: mat2x2<f32>,
+	translation: vec2<f32>,
+};
 @group(0) @binding(0)
-var<uniform> transform: mat2x2<f32>;
+var<uniform> transform: Transform;
 @group(0) @binding(1)
 var source_texture: texture_2d<f32>;
 @group(0) @binding(2)
@@ -20,7 +25,7 @@ fn vs_main(
 	let source_position = vec2(x, y);
 
 	let centered_position = vec2(2.0, -2.0) * (source_position - 0.5);
-	let destination_position = transform * centered_position;
+	let destination_position = transform.linear * centered_position + transform.translation;
 
 	var out: VertexOutput;
 	out.destination_position = vec4(destination_position, 0.0, 1.0);
@@ -31,4 +36,15 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 	 return textureSample(source_texture, source_sampler, in.source_position);
+}
+
+// Like `fs_main`, but converts a premultiplied source to straight alpha. Sampling happens first, so
+// filtering is still done on premultiplied colors.
+@fragment
+fn fs_unpremultiply(in: VertexOutput) -> @location(0) vec4<f32> {
+	let color = textureSample(source_texture, source_sampler, in.source_position);
+	if color.a <= 0.0 {
+		return vec4(0.0);
+	}
+	return vec4(color.rgb / color.a, color.a);
 }
\ No newline at end of file
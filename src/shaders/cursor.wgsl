@@ -0,0 +1,54 @@
+struct Cursor {
+	// The center of the cursor and the size of the screen, in screen pixels with the origin at the top
+	// left.
+	center: vec2<f32>,
+	screen_size: vec2<f32>,
+	radius: f32,
+	opacity: f32,
+};
+@group(0) @binding(0)
+var<uniform> cursor: Cursor;
+
+// How far the quad extends beyond the radius, in screen pixels, to fit the outline.
+const MARGIN: f32 = 3.0;
+
+// The width of each of the two rings making up the outline, in screen pixels.
+const RING_WIDTH: f32 = 1.5;
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	// Relative to the center of the cursor, in screen pixels.
+	@location(0) offset: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+	@builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+	let x = f32(vertex_index & 1u);
+	let y = 0.5 * f32(vertex_index & 2u);
+	let offset = (2.0 * vec2(x, y) - 1.0) * (cursor.radius + MARGIN);
+	let screen_position = cursor.center + offset;
+
+	var out: VertexOutput;
+	out.position = vec4(vec2(2.0, -2.0) * (screen_position / cursor.screen_size - 0.5), 0.0, 1.0);
+	out.offset = offset;
+	return out;
+}
+
+// The antialiased coverage of a ring of `RING_WIDTH` centered on `radius`, at `distance` from its
+// center.
+fn ring(distance: f32, radius: f32) -> f32 {
+	return clamp(0.5 * RING_WIDTH + 0.5 - abs(distance - radius), 0.0, 1.0);
+}
+
+// The outline is a light ring just inside the brush's edge over a dark ring just outside it, so it
+// stays visible over any paint. The output is premultiplied.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	let distance = length(in.offset);
+	let light = ring(distance, cursor.radius - 0.5 * RING_WIDTH);
+	let dark = ring(distance, cursor.radius + 0.5 * RING_WIDTH);
+	let alpha = light + (1.0 - light) * dark;
+	return cursor.opacity * vec4(vec3(light), alpha);
+}
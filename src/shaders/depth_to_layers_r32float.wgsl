@@ -0,0 +1,5 @@
+include!("depth_to_layers.wgsl") {}
+
+@group(0) @binding(1)
+// Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats.
+var destination: texture_storage_2d_array<r32float, write>;
@@ -1,36 +1,58 @@
-const DITHER1_A = vec2(63.6574, -41.2570);
-const DITHER1_B = vec3(-57.4096, 62.2291, 10448.7115);
+include!("lib/oklab.wgsl") { namespace: oklab }
 
-const DITHER2_A = mat2x2(
-	76.7223, -38.3171,
-    93.1957, 114.9507);
-const DITHER2_B = mat3x2(
-	96.5832, 18.5166, 17033.1114,
-    61.2907, -114.9439, 62222.1600);
+struct Quantization {
+	// The largest value of a channel, `2^bits - 1`.
+	levels: f32,
+};
+@group(0) @binding(0)
+var<uniform> quantization: Quantization;
+@group(0) @binding(1)
+var source: texture_2d<f32>;
+@group(0) @binding(2)
+var source_sampler: sampler;
+@group(0) @binding(3)
+// A tile of thresholds in [0, 1), repeated across the destination.
+var blue_noise: texture_2d<f32>;
+@group(0) @binding(4)
+// Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats, none of which are sRGB, so
+// colors are encoded here instead.
+var destination: texture_storage_2d<rgba8unorm, write>;
 
-const DITHER3_A = mat2x3(
-	6.3841, 91.6403,
-    75.5221, 17.0909,
-    -97.6091, 20.9826);
-const DITHER3_B = mat3x3(
-	-66.3084, -73.0280, 50941.4172,
-    -113.8825, 26.8574, 13387.8386,
-    118.5056, 13.7056, 20853.5495);
+const WORKGROUP_WIDTH: u32 = 16;
+const WORKGROUP_HEIGHT: u32 = 16;
 
-fn dither1(co: vec2<f32>) -> f32 {
-    let a = sin(dot(DITHER1_A, co));
-    let b = dot(DITHER1_B, vec3(co, 1.0));
-    return fract(a * b);
-}
+// Converts the premultiplied, linear `source` to straight alpha in sRGB and quantizes it to the
+// destination's size and `quantization.levels`. Each value rounds up with the probability of its
+// fraction, so the average over an area matches the source instead of banding.
+@compute
+@workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT, 1)
+fn dither(
+    @builtin(global_invocation_id)
+    gid: vec3<u32>,
+) {
+	let destination_dimensions = textureDimensions(destination);
+	if gid.x >= destination_dimensions.x || gid.y >= destination_dimensions.y {
+		return;
+	}
 
-fn dither2(co: vec2<f32>) -> vec2<f32> {
-    let a = sin(DITHER2_A * co);
-    let b = DITHER2_B * vec3(co, 1.0);
-    return fract(a * b);
-}
+	// Sampling at the destination's texel centers resamples bilinearly if the sizes differ. As with
+	// `copy_transform::fs_unpremultiply`, filtering is done on premultiplied colors.
+	let position = (vec2<f32>(gid.xy) + 0.5) / vec2<f32>(destination_dimensions);
+	let color = textureSampleLevel(source, source_sampler, position, 0.0);
+	var encoded = vec4(0.0);
+	if color.a > 0.0 {
+		let straight = clamp(color.rgb / color.a, vec3(0.0), vec3(1.0));
+		encoded = vec4(oklab::linear_srgb_to_rgb(straight), clamp(color.a, 0.0, 1.0));
+	}
+
+	let noise_dimensions = textureDimensions(blue_noise);
+	let color_threshold = textureLoad(blue_noise, gid.xy % noise_dimensions, 0).r;
+	// Alpha is dithered by the noise shifted half a tile, so it doesn't round together with color.
+	let alpha_position = (gid.xy + noise_dimensions / 2) % noise_dimensions;
+	let alpha_threshold = textureLoad(blue_noise, alpha_position, 0).r;
+	let threshold = vec4(vec3(color_threshold), alpha_threshold);
 
-fn dither3(co: vec2<f32>) -> vec3<f32> {
-    let a = sin(DITHER3_A * co);
-    let b = DITHER3_B * vec3(co, 1.0);
-    return fract(a * b);
+	let levels = quantization.levels;
+	let quantized = min(floor(encoded * levels + threshold), vec4(levels)) / levels;
+	textureStore(destination, gid.xy, quantized);
 }
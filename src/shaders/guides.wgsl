@@ -0,0 +1,90 @@
+struct GuideOverlay {
+	// Maps screen pixels, with the origin at the top left, to canvas coordinates relative to the
+	// guides' origin.
+	screen_to_guides: mat4x4<f32>,
+	// The points lines radiate from, relative to the guides' origin. Only the first
+	// `vanishing_point_count` are drawn.
+	vanishing_point0: vec2<f32>,
+	vanishing_point1: vec2<f32>,
+	vanishing_point2: vec2<f32>,
+	vanishing_point_count: u32,
+	// The angle between neighboring lines radiating from each vanishing point, in radians.
+	ray_spacing: f32,
+	// The angles of the families of parallel lines, in radians. Only the first `parallel_count` are
+	// drawn.
+	parallel_angles: vec4<f32>,
+	parallel_count: u32,
+	// The distance between neighboring parallel lines, in canvas units.
+	parallel_spacing: f32,
+	// Screen pixels per canvas unit.
+	zoom: f32,
+	opacity: f32,
+};
+@group(0) @binding(0)
+var<uniform> guides: GuideOverlay;
+
+// The width of each line, in screen pixels.
+const LINE_WIDTH: f32 = 1.0;
+// How far from a vanishing point its lines fade in, in screen pixels, since they crowd together
+// toward it.
+const RAY_FADE: f32 = 48.0;
+const COLOR: vec3<f32> = vec3(0.3, 0.6, 1.0);
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+};
+
+// The whole screen is covered, since the guides extend beyond it in every direction.
+@vertex
+fn vs_main(
+	@builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+	let x = f32(vertex_index & 1u);
+	let y = 0.5 * f32(vertex_index & 2u);
+
+	var out: VertexOutput;
+	out.position = vec4(2.0 * vec2(x, y) - 1.0, 0.0, 1.0);
+	return out;
+}
+
+// The antialiased coverage of a line of `LINE_WIDTH` at `distance` in screen pixels.
+fn line(distance: f32) -> f32 {
+	return clamp(0.5 * LINE_WIDTH + 0.5 - distance, 0.0, 1.0);
+}
+
+// The coverage of the lines radiating from `vanishing_point` at `p`.
+fn rays(p: vec2<f32>, vanishing_point: vec2<f32>) -> f32 {
+	let d = p - vanishing_point;
+	let radius = guides.zoom * length(d);
+	let angle = atan2(d.y, d.x);
+	let nearest = round(angle / guides.ray_spacing) * guides.ray_spacing;
+	let fade = clamp(radius / RAY_FADE, 0.0, 1.0);
+	return fade * line(radius * abs(sin(angle - nearest)));
+}
+
+// The coverage of the family of parallel lines at `angle` at `p`.
+fn parallels(p: vec2<f32>, angle: f32) -> f32 {
+	let normal = vec2(-sin(angle), cos(angle));
+	let offset = dot(p, normal) / guides.parallel_spacing;
+	return line(guides.zoom * guides.parallel_spacing * abs(offset - round(offset)));
+}
+
+// The output is premultiplied.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	let p = (guides.screen_to_guides * vec4(in.position.xy, 0.0, 1.0)).xy;
+	var vanishing_points = array(
+		guides.vanishing_point0,
+		guides.vanishing_point1,
+		guides.vanishing_point2,
+	);
+	var coverage = 0.0;
+	for (var i = 0u; i < guides.vanishing_point_count; i++) {
+		coverage = max(coverage, rays(p, vanishing_points[i]));
+	}
+	for (var i = 0u; i < guides.parallel_count; i++) {
+		coverage = max(coverage, parallels(p, guides.parallel_angles[i]));
+	}
+	let alpha = guides.opacity * coverage;
+	return vec4(COLOR * alpha, alpha);
+}
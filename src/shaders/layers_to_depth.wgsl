@@ -1,9 +1,7 @@
 @group(0) @binding(0)
 var source: texture_2d_array<f32>;
 
-@group(0) @binding(1)
-// Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats.
-var destination: texture_storage_3d<r32float, write>;
+// The destination is declared by each variant, since storage texture formats are fixed.
 
 const WORKGROUP_WIDTH: u32 = 16;
 const WORKGROUP_HEIGHT: u32 = 16;
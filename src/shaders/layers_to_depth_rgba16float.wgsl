@@ -0,0 +1,5 @@
+include!("layers_to_depth.wgsl") {}
+
+@group(0) @binding(1)
+// Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats.
+var destination: texture_storage_3d<rgba16float, write>;
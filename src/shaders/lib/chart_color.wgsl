@@ -0,0 +1,11 @@
+// Unsigned normalized chart formats can't represent the negative `a` and `b` components of Oklab,
+// so those formats store them offset by `chart_ab_offset`. See `CanvasFormat::chart_ab_offset`.
+override chart_ab_offset: f32 = 0.0;
+
+fn encode_chart_color(oklab: vec3<f32>) -> vec3<f32> {
+	return oklab + vec3(0.0, chart_ab_offset, chart_ab_offset);
+}
+
+fn decode_chart_color(stored: vec3<f32>) -> vec3<f32> {
+	return stored - vec3(0.0, chart_ab_offset, chart_ab_offset);
+}
@@ -0,0 +1,22 @@
+// Procedural noise, which needs no textures and is the same wherever it is evaluated.
+
+// The 2D PCG hash from "Hash Functions for GPU Rendering" (Jarzynski and Olano, 2020).
+fn pcg2d(input: vec2<u32>) -> vec2<u32> {
+	var v = input * 1664525u + 1013904223u;
+	v.x += v.y * 1664525u;
+	v.y += v.x * 1664525u;
+	v ^= v >> vec2(16u);
+	v.x += v.y * 1664525u;
+	v.y += v.x * 1664525u;
+	v ^= v >> vec2(16u);
+	return v;
+}
+
+// Uniform white noise from 0 to 1, constant over each unit cell of `position` and independent for
+// each `seed`.
+fn white(position: vec2<f32>, seed: vec2<f32>) -> f32 {
+	let cell = vec2<u32>(vec2<i32>(floor(position)));
+	let hash = pcg2d(cell ^ bitcast<vec2<u32>>(seed));
+	// The top 24 bits are exactly representable.
+	return f32(hash.x >> 8u) / 16777216.0;
+}
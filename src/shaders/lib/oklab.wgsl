@@ -18,9 +18,10 @@ fn linear_srgb_to_rgb(srgb: vec3<f32>) -> vec3<f32> {
 	return vec3(srgb_gamma(srgb.x), srgb_gamma(srgb.y), srgb_gamma(srgb.z));
 }
 
+// Negative values, as extended-range targets accept, are encoded symmetrically.
 fn srgb_gamma(x: f32) -> f32 {
-	if x >= 0.0031308 {
-		return 1.055 * pow(x, 1 / 2.4) - 0.055;
+	if abs(x) >= 0.0031308 {
+		return sign(x) * (1.055 * pow(abs(x), 1 / 2.4) - 0.055);
 	 }
 	 return 12.92 * x;
 }
@@ -0,0 +1,43 @@
+include!("lib/chart_color.wgsl") {}
+
+struct MixerAction {
+	color: vec3<f32>,
+	// How much the brush's color takes on the paint under it, from 0 to 1. See `Airbrush::set_pickup`.
+	pickup: f32,
+};
+@group(0) @binding(0)
+var<uniform> action: MixerAction;
+@group(0) @binding(1)
+var shape_texture: texture_3d<f32>;
+@group(0) @binding(2)
+var shape_sampler: sampler;
+
+include!("tile_write.wgsl") {}
+
+// How much of each texel of the chart being drawn is selected. See `Selection` in `selection.rs`.
+@group(2) @binding(0)
+var selection_mask: texture_2d<f32>;
+
+// A copy of the chart being drawn, from before this segment, since a texture can't be read while it
+// is drawn to. See `Mixer` in `mixer.rs`.
+@group(3) @binding(0)
+var mixer_source: texture_2d<f32>;
+
+include!("airbrush_stroke.wgsl") {}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	let selected = textureLoad(selection_mask, vec2<u32>(in.position.xy), 0).x;
+	let strength = clamp(-expm1(stroke_log_transmission(in)) * selected, 0.0, 1.0);
+
+	// The brush picks up paint in proportion to how much is there, so it keeps its own color over
+	// bare canvas. Chart colors are offset Oklab, so they can be mixed as they're stored.
+	let here = textureLoad(mixer_source, vec2<i32>(in.position.xy), 0);
+	let paint = mix(encode_chart_color(action.color), here.xyz, action.pickup * here.w);
+
+	// The mixed paint is laid over what was there, with colors weighted by alpha as when smudging.
+	let alpha = mix(here.w, 1.0, strength);
+	let weighted = mix(here.xyz * here.w, paint, strength);
+	let color = select(here.xyz, weighted / alpha, alpha > 0.0);
+	return vec4(color, alpha);
+}
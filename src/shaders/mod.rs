@@ -1,6 +1,6 @@
 use wgsl_to_wgpu_macro::shader;
 
-// `cargo expand --lib shaders::horizontal_scan`
+// `cargo expand --lib shaders::scan`
 
 pub mod atlas {
 	super::shader!(mod "atlas.wgsl" in "src/shaders");
@@ -13,13 +13,26 @@ pub mod atlas {
 shader!(pub mod "canvas.wgsl" in "src/shaders");
 shader!(pub mod "copy_transform.wgsl" in "src/shaders");
 shader!(pub mod "color_picker.wgsl" in "src/shaders");
+shader!(pub mod "cursor.wgsl" in "src/shaders");
+shader!(pub mod "stroke_tip.wgsl" in "src/shaders");
+shader!(pub mod "bounds.wgsl" in "src/shaders");
+shader!(pub mod "guides.wgsl" in "src/shaders");
 
 shader!(pub mod "airbrush.wgsl" in "src/shaders");
+shader!(pub mod "smudge.wgsl" in "src/shaders");
+shader!(pub mod "mixer.wgsl" in "src/shaders");
 
-shader!(pub mod "depth_to_layers.wgsl" in "src/shaders");
-shader!(pub mod "layers_to_depth.wgsl" in "src/shaders");
-shader!(pub mod "log_transform.wgsl" in "src/shaders" where filterable: false);
-shader!(pub mod "horizontal_scan.wgsl" in "src/shaders" where filterable: false);
+// Storage texture formats are fixed in WGSL, so these have a variant for each destination format.
+shader!(pub mod "depth_to_layers_r32float.wgsl" in "src/shaders");
+shader!(pub mod "depth_to_layers_rgba16float.wgsl" in "src/shaders");
+shader!(pub mod "depth_to_layers_rgba32float.wgsl" in "src/shaders");
+shader!(pub mod "layers_to_depth_r32float.wgsl" in "src/shaders");
+shader!(pub mod "layers_to_depth_rgba16float.wgsl" in "src/shaders");
+shader!(pub mod "layers_to_depth_rgba32float.wgsl" in "src/shaders");
+shader!(pub mod "transfer.wgsl" in "src/shaders" where filterable: false);
+shader!(pub mod "scan.wgsl" in "src/shaders" where filterable: false);
+shader!(pub mod "resample.wgsl" in "src/shaders" where filterable: false);
+shader!(pub mod "dither.wgsl" in "src/shaders");
 
 // Expose parts of the tile read/write templates.
 pub use tile_read::TileData;
@@ -35,3 +48,132 @@ pub mod tile_write {
 	pub type BindGroupLayout = tile_write_internal::BindGroupLayout1;
 	pub type BindGroup = tile_write_internal::BindGroup1;
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::test;
+	use crate::util::rgb_to_oklab;
+	use glam::*;
+
+	fn wgsl_vec3(v: Vec3) -> String {
+		format!("vec3({:?}, {:?}, {:?})", v.x, v.y, v.z)
+	}
+
+	#[test]
+	fn assertions_report_failures() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		context.assert_shader(
+			&["fn run() { expect(0u, true); expect(1u, 1.0 < 2.0); }"],
+			2,
+		)?;
+		let message = context
+			.assert_shader(&["fn run() { expect(0u, true); expect(1u, false); }"], 3)
+			.unwrap_err()
+			.to_string();
+		assert!(
+			message.contains("[1] failed and [2] weren't reached"),
+			"{message}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn oklab_matches_the_cpu() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		// In gamut, where the CPU's gamma agrees with the shader's.
+		let colors = [
+			vec3(0.0, 0.0, 0.0),
+			vec3(1.0, 1.0, 1.0),
+			vec3(0.002, 0.5, 0.003),
+			vec3(1.0, 0.0, 0.0),
+			vec3(0.1, 0.2, 0.9),
+			vec3(0.8, 0.6, 0.2),
+		];
+		let run = colors
+			.iter()
+			.enumerate()
+			.map(|(index, &rgb)| {
+				format!(
+					"expect({index}u, all(abs(oklab_to_rgb({}) - {}) < vec3(1e-4)));",
+					wgsl_vec3(rgb_to_oklab(rgb)),
+					wgsl_vec3(rgb),
+				)
+			})
+			.collect::<String>();
+		context.assert_shader(
+			&[
+				include_str!("lib/oklab.wgsl"),
+				&format!("fn run() {{ {run} }}"),
+			],
+			colors.len() as u32,
+		)
+	}
+
+	#[test]
+	fn srgb_gamma_is_odd() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		// Extended-range targets are written negative values, which are encoded symmetrically.
+		let run = r#"
+			fn run() {
+				var x = array(0.001, 0.0031308, 0.2, 1.0, 1.5);
+				for (var i = 0u; i < 5u; i++) {
+					expect(i, srgb_gamma(-x[i]) == -srgb_gamma(x[i]));
+				}
+				expect(5u, srgb_gamma(0.0) == 0.0);
+				expect(6u, abs(srgb_gamma(1.0) - 1.0) < 1e-6);
+			}
+		"#;
+		context.assert_shader(&[include_str!("lib/oklab.wgsl"), run], 7)
+	}
+
+	#[test]
+	fn dither_is_in_the_unit_interval() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let run = r#"
+			fn run() {
+				var in_range = array(true, true, true);
+				for (var x = -64.0; x < 64.0; x += 1.0) {
+					for (var y = -64.0; y < 64.0; y += 1.0) {
+						let co = vec2(x, y) + 0.5;
+						let d1 = dither1(co);
+						let d2 = dither2(co);
+						let d3 = dither3(co);
+						in_range[0] &= (d1 >= 0.0 && d1 < 1.0);
+						in_range[1] &= (all(d2 >= vec2(0.0)) && all(d2 < vec2(1.0)));
+						in_range[2] &= (all(d3 >= vec3(0.0)) && all(d3 < vec3(1.0)));
+					}
+				}
+				for (var i = 0u; i < 3u; i++) {
+					expect(i, in_range[i]);
+				}
+			}
+		"#;
+		context.assert_shader(&[include_str!("lib/dither.wgsl"), run], 3)
+	}
+
+	#[test]
+	fn white_noise_is_uniform() -> anyhow::Result<()> {
+		let context = test::WgpuTestContext::new()?;
+		let run = r#"
+			fn run() {
+				var in_range = true;
+				var sum = 0.0;
+				var changed_with_seed = 0;
+				for (var x = -32.0; x < 32.0; x += 1.0) {
+					for (var y = -32.0; y < 32.0; y += 1.0) {
+						let n = white(vec2(x, y) + 0.5, vec2(0.25, 7.0));
+						in_range &= (n >= 0.0 && n < 1.0);
+						sum += n;
+						changed_with_seed += i32(n != white(vec2(x, y), vec2(0.5, 7.0)));
+					}
+				}
+				expect(0u, in_range);
+				expect(1u, abs(sum / 4096.0 - 0.5) < 0.02);
+				expect(2u, changed_with_seed > 4000);
+				// Constant within each cell.
+				expect(3u, white(vec2(3.1, -2.9), vec2(1.0)) == white(vec2(3.9, -2.1), vec2(1.0)));
+			}
+		"#;
+		context.assert_shader(&[include_str!("lib/noise.wgsl"), run], 4)
+	}
+}
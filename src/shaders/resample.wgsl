@@ -0,0 +1,72 @@
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+// Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats.
+var destination: texture_storage_2d<rgba16float, write>;
+
+const WORKGROUP_WIDTH: u32 = 16;
+const WORKGROUP_HEIGHT: u32 = 16;
+
+// The number of lobes on each side of the Lanczos kernel.
+const LOBES: f32 = 3.0;
+const PI: f32 = 3.14159265358979;
+
+fn sinc(x: f32) -> f32 {
+	if abs(x) < 1e-5 {
+		return 1.0;
+	}
+	let y = PI * x;
+	return sin(y) / y;
+}
+
+fn lanczos(x: f32) -> f32 {
+	if abs(x) >= LOBES {
+		return 0.0;
+	}
+	return sinc(x) * sinc(x / LOBES);
+}
+
+// Resamples `source` to the size of `destination` with a Lanczos kernel. Colors are filtered
+// premultiplied so that transparent texels don't bleed into their neighbors.
+@compute
+@workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT, 1)
+fn lanczos_resample(
+    @builtin(global_invocation_id)
+    gid: vec3<u32>,
+) {
+	let destination_dimensions = textureDimensions(destination);
+	if gid.x >= destination_dimensions.x || gid.y >= destination_dimensions.y {
+		return;
+	}
+	let source_dimensions = vec2<i32>(textureDimensions(source));
+
+	let scale = vec2<f32>(source_dimensions) / vec2<f32>(destination_dimensions);
+	// When downsampling, the kernel widens to remove frequencies the destination can't represent.
+	let kernel_scale = max(scale, vec2(1.0));
+	let center = (vec2<f32>(gid.xy) + 0.5) * scale;
+	let radius = LOBES * kernel_scale;
+	let lower = max(vec2<i32>(floor(center - radius)), vec2(0));
+	let upper = min(vec2<i32>(ceil(center + radius)), source_dimensions - 1);
+
+	var sum = vec4(0.0);
+	var weight_sum = 0.0;
+	for (var y = lower.y; y <= upper.y; y++) {
+		let y_weight = lanczos((f32(y) + 0.5 - center.y) / kernel_scale.y);
+		for (var x = lower.x; x <= upper.x; x++) {
+			let weight = y_weight * lanczos((f32(x) + 0.5 - center.x) / kernel_scale.x);
+			let texel = textureLoad(source, vec2(x, y), 0);
+			sum += weight * vec4(texel.rgb * texel.a, texel.a);
+			weight_sum += weight;
+		}
+	}
+	sum /= weight_sum;
+
+	// The kernel's negative lobes can ring past the valid range.
+	let alpha = clamp(sum.a, 0.0, 1.0);
+	var color = vec3(0.0);
+	if sum.a > 0.0 {
+		color = max(sum.rgb / sum.a, vec3(0.0));
+	}
+	textureStore(destination, gid.xy, vec4(color, alpha));
+}
@@ -5,27 +5,37 @@ var source: texture_2d_array<f32>;
 // Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats.
 var destination: texture_storage_2d_array<r32float, write>;
 
+// Whether to scan down each column rather than across each row.
+override vertical: bool = false;
+
 const WORKGROUP_SIZE: u32 = 256;
 
+// The coordinates of the `i`th texel along `line`.
+fn texel(line: u32, i: u32) -> vec2<u32> {
+	return select(vec2(i, line), vec2(line, i), vertical);
+}
+
 @compute
 @workgroup_size(WORKGROUP_SIZE, 1, 1)
-fn horizontal_scan(
+fn scan(
     @builtin(global_invocation_id)
     gid: vec3<u32>,
 ) {
-	let y = gid.x;
+	let line = gid.x;
 	let z = gid.y;
 	let texture_dimensions = textureDimensions(source);
-	if y >= texture_dimensions.y {
+	let line_count = select(texture_dimensions.y, texture_dimensions.x, vertical);
+	let line_length = select(texture_dimensions.x, texture_dimensions.y, vertical);
+	if line >= line_count {
 		return;
 	}
 	if z >= textureNumLayers(source) {
 		return;
 	}
-	let scale = 1 / f32(texture_dimensions.x);
+	let scale = 1 / f32(line_length);
 	var value: vec4<f32> = vec4(0, 0, 0, 0);
-	for (var x: u32 = 0; x < texture_dimensions.x; x++) {
-		let xy = vec2(x, y);
+	for (var i: u32 = 0; i < line_length; i++) {
+		let xy = texel(line, i);
 		value += textureLoad(source, xy, z, 0);
 		textureStore(destination, xy, z, scale * value);
 	}
@@ -0,0 +1,43 @@
+struct SmudgeAction {
+	// How far the brush moved since the previous segment, in chart texels. Each texel under the
+	// brush is pulled from this far behind it.
+	offset: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> action: SmudgeAction;
+@group(0) @binding(1)
+var shape_texture: texture_3d<f32>;
+@group(0) @binding(2)
+var shape_sampler: sampler;
+
+include!("tile_write.wgsl") {}
+
+// How much of each texel of the chart being drawn is selected. See `Selection` in `selection.rs`.
+@group(2) @binding(0)
+var selection_mask: texture_2d<f32>;
+
+// A copy of the chart being drawn, from before this segment, since a texture can't be read while it
+// is drawn to. See `Smudge` in `smudge.rs`.
+@group(3) @binding(0)
+var smudge_source: texture_2d<f32>;
+
+include!("airbrush_stroke.wgsl") {}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	let selected = textureLoad(selection_mask, vec2<u32>(in.position.xy), 0).x;
+	let strength = clamp(-expm1(stroke_log_transmission(in)) * selected, 0.0, 1.0);
+
+	// Paint isn't dragged across chart edges. Texels pulled from beyond an edge take the nearest one
+	// inside the chart instead.
+	let last_texel = vec2<i32>(textureDimensions(smudge_source)) - 1;
+	let here = textureLoad(smudge_source, vec2<i32>(in.position.xy), 0);
+	let behind_texel = clamp(vec2<i32>(floor(in.position.xy - action.offset)), vec2(0), last_texel);
+	let behind = textureLoad(smudge_source, behind_texel, 0);
+
+	// Colors are weighted by alpha, so dragging transparent regions thins paint without tinting it.
+	let alpha = mix(here.w, behind.w, strength);
+	let weighted = mix(here.xyz * here.w, behind.xyz * behind.w, strength);
+	let color = select(here.xyz, weighted / alpha, alpha > 0.0);
+	return vec4(color, alpha);
+}
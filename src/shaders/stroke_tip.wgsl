@@ -0,0 +1,64 @@
+include!("lib/oklab.wgsl") { namespace: oklab }
+
+struct StrokeTip {
+	// Where the drawn stroke ends and where the pointer is predicted to be, and the size of the
+	// screen, in screen pixels with the origin at the top left.
+	start: vec2<f32>,
+	end: vec2<f32>,
+	screen_size: vec2<f32>,
+	// The brush's radius at the start and end, in screen pixels.
+	radii: vec2<f32>,
+	// The brush color, in Oklab.
+	color: vec3<f32>,
+	opacity: f32,
+};
+@group(0) @binding(0)
+var<uniform> tip: StrokeTip;
+
+// Whether the target encodes colors itself, as sRGB formats do, so the output must be linear. This
+// must match `StrokeTipRenderer::create_pipeline`.
+override linear_output: bool = false;
+
+// How far the quad extends beyond the brush, in screen pixels, to fit the antialiased edge.
+const MARGIN: f32 = 1.0;
+
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	// In screen pixels.
+	@location(0) screen_position: vec2<f32>,
+};
+
+// The quad bounds both ends of the tip.
+@vertex
+fn vs_main(
+	@builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+	let x = f32(vertex_index & 1u);
+	let y = 0.5 * f32(vertex_index & 2u);
+	let lower = min(tip.start - tip.radii.x, tip.end - tip.radii.y) - MARGIN;
+	let upper = max(tip.start + tip.radii.x, tip.end + tip.radii.y) + MARGIN;
+	let screen_position = mix(lower, upper, vec2(x, y));
+
+	var out: VertexOutput;
+	out.position = vec4(vec2(2.0, -2.0) * (screen_position / tip.screen_size - 0.5), 0.0, 1.0);
+	out.screen_position = screen_position;
+	return out;
+}
+
+// The tip is a capsule from the start to the end, its radius tapering between them, filled with
+// the brush color. The output is premultiplied.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	let along = tip.end - tip.start;
+	let offset = in.screen_position - tip.start;
+	let h = clamp(dot(offset, along) / max(dot(along, along), 1e-6), 0.0, 1.0);
+	let distance = length(offset - h * along) - mix(tip.radii.x, tip.radii.y, h);
+	let alpha = tip.opacity * clamp(0.5 - distance, 0.0, 1.0);
+
+	let linear = oklab::oklab_to_linear_srgb(tip.color);
+	var rgb = oklab::linear_srgb_to_rgb(linear);
+	if linear_output {
+		rgb = linear;
+	}
+	return vec4(rgb * alpha, alpha);
+}
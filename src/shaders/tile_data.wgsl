@@ -1,6 +1,10 @@
 struct TileData {
 	// This inconvenient to invert. Alternatively, we could have separate read and write data.
 	// chart_to_canvas: mat4x4<f32>,
+	// A chart position `p` maps to `chart_to_canvas_scale * (p + chart_offset) + chart_to_canvas_translation`
+	// on the canvas. Keeping the integer offset separate lets renderers subtract a nearby origin
+	// exactly, so tiles far from the canvas origin don't lose precision in `f32`.
 	chart_to_canvas_scale: vec2<f32>,
 	chart_to_canvas_translation: vec2<f32>,
+	chart_offset: vec2<i32>,
 };
@@ -0,0 +1,49 @@
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+// Must be one of https://www.w3.org/TR/WGSL/#storage-texel-formats.
+var destination: texture_storage_2d<r32float, write>;
+
+// These must match `TransferFunction` in `process_shape.rs`.
+const TRANSFER_LINEAR: u32 = 0;
+const TRANSFER_LOG: u32 = 1;
+const TRANSFER_GAMMA: u32 = 2;
+
+override transfer_function: u32 = TRANSFER_LINEAR;
+// Only used by `TRANSFER_GAMMA`.
+override gamma: f32 = 1.0;
+
+const WORKGROUP_WIDTH: u32 = 16;
+const WORKGROUP_HEIGHT: u32 = 16;
+
+fn apply_transfer(input: f32) -> f32 {
+	let x = clamp(input, 0.0, 1.0);
+	switch transfer_function {
+		case TRANSFER_LOG: {
+			// Ideally, we would use `ln_1p(-x)` here.
+			return -log(1 - x);
+		}
+		case TRANSFER_GAMMA: {
+			return pow(x, gamma);
+		}
+		default: {
+			return x;
+		}
+	}
+}
+
+@compute
+@workgroup_size(WORKGROUP_WIDTH, WORKGROUP_HEIGHT, 1)
+fn transfer(
+    @builtin(global_invocation_id)
+    gid: vec3<u32>,
+) {
+	let texture_dimensions = textureDimensions(source);
+	if gid.x >= texture_dimensions.x || gid.y >= texture_dimensions.y {
+		return;
+	}
+
+	let input = textureLoad(source, gid.xy, 0).x;
+	textureStore(destination, gid.xy, vec4(apply_transfer(input), 0, 0, 1));
+}
@@ -21,6 +21,21 @@ impl Deref for WgpuTestContext {
 	}
 }
 
+/// Declarations for the shaders [`WgpuTestContext::assert_shader`] runs. Each expectation's entry
+/// is 0 until `expect` is reached, then 1 if it failed or 2 if it passed.
+const SHADER_ASSERTION_PRELUDE: &str = r#"
+@group(0) @binding(0) var<storage, read_write> expectations: array<u32>;
+
+fn expect(index: u32, condition: bool) {
+	expectations[index] = select(1u, 2u, condition);
+}
+
+@compute @workgroup_size(1)
+fn main() {
+	run();
+}
+"#;
+
 pub struct GoldenOptions {
 	pub texture_format: wgpu::TextureFormat,
 	pub width: u32,
@@ -43,7 +58,7 @@ impl Default for GoldenOptions {
 
 impl WgpuTestContext {
 	pub fn new() -> Result<Self, WgpuContextError> {
-		let context = pollster::block_on(WgpuContext::new())?;
+		let context = pollster::block_on(WgpuContext::builder().create())?;
 		let device = context.device();
 
 		let copy_transform = shaders::copy_transform::Shader::new(device.clone());
@@ -88,10 +103,12 @@ impl WgpuTestContext {
 			..Default::default()
 		});
 
-		let transform_buffer =
-			render::BindingBuffer::init_sized(&glam::Mat2::IDENTITY).create(device);
-
 		use shaders::copy_transform::*;
+		let transform_buffer = render::BindingBuffer::init_sized(&Transform {
+			linear: glam::Mat2::IDENTITY,
+			translation: glam::Vec2::ZERO,
+		})
+		.create(device);
 		let pipeline_layout = Shader::new(device.clone()).pipeline_layout().get();
 		let pipeline = pipeline_layout
 			.vs_main_pipeline()
@@ -132,6 +149,79 @@ impl WgpuTestContext {
 		self.queue().submit([command_encoder.finish()]);
 	}
 
+	/// Tests WGSL directly, rather than through what it renders. `sources` are concatenated, typically
+	/// the helpers under test followed by a `fn run()` which calls `expect(index, condition)` for each
+	/// of `count` expectations. `run` is called once from a compute shader, and this fails unless
+	/// every expectation was reached and passed.
+	pub fn assert_shader(&self, sources: &[&str], count: u32) -> anyhow::Result<()> {
+		let device = self.device();
+		let source = [SHADER_ASSERTION_PRELUDE].iter().chain(sources).join("\n");
+		let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+			label: Some("assert_shader"),
+			source: wgpu::ShaderSource::Wgsl(source.into()),
+		});
+		let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+			label: Some("assert_shader"),
+			layout: None,
+			module: &module,
+			entry_point: "main",
+			compilation_options: Default::default(),
+			cache: None,
+		});
+
+		let size = (count.max(1) * 4) as wgpu::BufferAddress;
+		// Buffers start zeroed, so every expectation starts unreached.
+		let expectations = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("assert_shader::expectations"),
+			size,
+			usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+			mapped_at_creation: false,
+		});
+		let readback = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("assert_shader::readback"),
+			size,
+			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("assert_shader"),
+			layout: &pipeline.get_bind_group_layout(0),
+			entries: &[wgpu::BindGroupEntry {
+				binding: 0,
+				resource: expectations.as_entire_binding(),
+			}],
+		});
+
+		let mut encoder = device.create_command_encoder(&Default::default());
+		{
+			let mut pass = encoder.begin_compute_pass(&Default::default());
+			pass.set_pipeline(&pipeline);
+			pass.set_bind_group(0, &bind_group, &[]);
+			pass.dispatch_workgroups(1, 1, 1);
+		}
+		encoder.copy_buffer_to_buffer(&expectations, 0, &readback, 0, size);
+		self.queue().submit([encoder.finish()]);
+
+		let data = pollster::block_on(self.get_buffer_data(Arc::new(readback)))?;
+		let results = data
+			.chunks_exact(4)
+			.take(count as usize)
+			.map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+			.collect_vec();
+		let indices = |value: u32| {
+			results
+				.iter()
+				.positions(move |&result| result == value)
+				.collect_vec()
+		};
+		let (unreached, failed) = (indices(0), indices(1));
+		anyhow::ensure!(
+			unreached.is_empty() && failed.is_empty(),
+			"expectations {failed:?} failed and {unreached:?} weren't reached"
+		);
+		Ok(())
+	}
+
 	pub fn render_golden_commands(
 		&self,
 		name: &str,
@@ -190,10 +280,12 @@ impl WgpuTestContext {
 			std::fs::create_dir_all(parent)?;
 		}
 
+		// A missing golden is written for review, but fails the test so that it isn't mistaken for a
+		// comparison. It passes once committed.
 		if let Ok(mut file) = std::fs::File::create_new(&path) {
 			let data = image.write_to_vec(ImageFormat::PNG)?;
-			file.write_all(&data);
-			return Ok(());
+			file.write_all(&data)?;
+			anyhow::bail!("golden {} was missing and has been written", path.display());
 		}
 
 		let mut golden = Image::open(&path)?;
@@ -214,3 +306,50 @@ impl WgpuTestContext {
 		Ok(())
 	}
 }
+
+/// Paints the left half of chart `(0, 0)` with `color`, as Oklab with alpha, and clears the right
+/// half, so that strokes across the middle show what they do to paint and to bare canvas.
+pub fn paint_left_half(atlas: &mut engine::Atlas, color: glam::Vec4) {
+	let format = atlas.format();
+	let half = engine::CHART_SIZE as usize / 2;
+	let row = [
+		format.encode_pixels(&[color]).repeat(half),
+		format.clear_pixel().repeat(half),
+	]
+	.concat();
+	atlas
+		.get_chart_mut(engine::ChartKey(0, 0))
+		.tile()
+		.write_texture(&row.repeat(engine::CHART_SIZE as usize));
+}
+
+/// A point of a stroke at full pressure and opacity, at the canvas origin.
+pub fn input_point(size: f32, color: glam::Vec3) -> engine::InputPoint {
+	engine::InputPoint {
+		position: glam::DVec2::ZERO,
+		pressure: 1.0,
+		color,
+		size,
+		opacity: 1.0,
+		rate: 25.0,
+		tilt: glam::Vec2::ZERO,
+	}
+}
+
+/// A single segment along the middle row of chart `(0, 0)`, crossing the edge of the paint left
+/// by [`paint_left_half`].
+pub fn segment_across_paint_edge(point: engine::InputPoint) -> [engine::InputPoint; 2] {
+	[104.0, 152.0].map(|x| engine::InputPoint {
+		position: glam::dvec2(x, 128.0),
+		..point
+	})
+}
+
+/// Asserts that `a` and `b`, such as `glam` vectors, are within `epsilon` of each other.
+macro_rules! assert_near {
+	($a:expr, $b:expr, $epsilon:expr) => {{
+		let (a, b) = ($a, $b);
+		assert!(a.abs_diff_eq(b, $epsilon), "{a} != {b}");
+	}};
+}
+pub(crate) use assert_near;
@@ -0,0 +1,171 @@
+//! Blue noise, which dithers without the clumps of white noise or the visible pattern of an ordered
+//! dither.
+
+use std::sync::OnceLock;
+
+/// The width and height of the tile of thresholds [`blue_noise`] returns.
+pub const BLUE_NOISE_SIZE: u32 = 64;
+
+/// The standard deviation, in texels, of the Gaussian measuring how clustered texels are.
+const SIGMA: f32 = 1.5;
+
+/// The fraction of texels set in the pattern the thresholds are built from.
+const INITIAL_DENSITY: f32 = 0.1;
+
+/// A tileable square of [`BLUE_NOISE_SIZE`] by [`BLUE_NOISE_SIZE`] thresholds in row-major order.
+/// Each of the `n` texels has a different threshold `(i + 0.5) / n`, arranged so that the texels
+/// below any threshold are evenly spread.
+pub fn blue_noise() -> &'static [f32] {
+	static NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+	NOISE.get_or_init(|| generate(BLUE_NOISE_SIZE as usize))
+}
+
+/// Generates a `size` by `size` tile of thresholds with Ulichney's void-and-cluster method.
+fn generate(size: usize) -> Vec<f32> {
+	let n = size * size;
+	let mut pattern = Pattern::new(size);
+	let mut rng = fastrand::Rng::with_seed(0);
+	let initial = ((n as f32 * INITIAL_DENSITY) as usize).max(1);
+	while pattern.count < initial {
+		let i = rng.usize(..n);
+		if !pattern.set[i] {
+			pattern.toggle(i);
+		}
+	}
+
+	// Spread the initial texels out by moving the most clustered to the largest void, until that
+	// would put it back where it was.
+	for _ in 0..n {
+		let cluster = pattern.tightest_cluster();
+		pattern.toggle(cluster);
+		let void = pattern.largest_void();
+		pattern.toggle(void);
+		if void == cluster {
+			break;
+		}
+	}
+
+	let mut ranks = vec![0; n];
+	// The initial texels rank lowest, the most clustered last.
+	let mut removing = pattern.clone();
+	for rank in (0..pattern.count).rev() {
+		let cluster = removing.tightest_cluster();
+		removing.toggle(cluster);
+		ranks[cluster] = rank;
+	}
+	// The rest rank in the order they fill the largest remaining void.
+	for rank in pattern.count..n {
+		let void = pattern.largest_void();
+		pattern.toggle(void);
+		ranks[void] = rank;
+	}
+	ranks
+		.into_iter()
+		.map(|rank| (rank as f32 + 0.5) / n as f32)
+		.collect()
+}
+
+/// A binary pattern on a torus, with the energy of each texel: the sum of a Gaussian of its
+/// distance to each set texel.
+#[derive(Clone)]
+struct Pattern {
+	size: usize,
+	set: Vec<bool>,
+	count: usize,
+	energy: Vec<f32>,
+	/// The Gaussian of each offset, wrapping around the edges.
+	kernel: Vec<f32>,
+}
+
+impl Pattern {
+	fn new(size: usize) -> Self {
+		let distance = |d: usize| d.min(size - d) as f32;
+		let kernel = (0..size * size)
+			.map(|i| {
+				let (x, y) = (distance(i % size), distance(i / size));
+				(-(x * x + y * y) / (2.0 * SIGMA * SIGMA)).exp()
+			})
+			.collect();
+		Self {
+			size,
+			set: vec![false; size * size],
+			count: 0,
+			energy: vec![0.0; size * size],
+			kernel,
+		}
+	}
+
+	fn toggle(&mut self, i: usize) {
+		let size = self.size;
+		let sign = if self.set[i] { -1.0 } else { 1.0 };
+		self.set[i] = !self.set[i];
+		if self.set[i] {
+			self.count += 1;
+		} else {
+			self.count -= 1;
+		}
+		let (x0, y0) = (i % size, i / size);
+		for (j, energy) in self.energy.iter_mut().enumerate() {
+			let dx = (j % size + size - x0) % size;
+			let dy = (j / size + size - y0) % size;
+			*energy += sign * self.kernel[dy * size + dx];
+		}
+	}
+
+	/// The set texel with the most energy.
+	fn tightest_cluster(&self) -> usize {
+		self.extreme(true, |a, b| a > b)
+	}
+
+	/// The unset texel with the least energy.
+	fn largest_void(&self) -> usize {
+		self.extreme(false, |a, b| a < b)
+	}
+
+	fn extreme(&self, set: bool, better: impl Fn(f32, f32) -> bool) -> usize {
+		let mut best = None;
+		for (i, &energy) in self.energy.iter().enumerate() {
+			if self.set[i] == set && best.is_none_or(|b: usize| better(energy, self.energy[b])) {
+				best = Some(i);
+			}
+		}
+		best.unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SIZE: usize = 16;
+
+	#[test]
+	fn thresholds_are_a_permutation() {
+		let mut thresholds = generate(SIZE);
+		thresholds.sort_by(f32::total_cmp);
+		let n = SIZE * SIZE;
+		for (i, threshold) in thresholds.into_iter().enumerate() {
+			assert_eq!(threshold, (i as f32 + 0.5) / n as f32);
+		}
+	}
+
+	#[test]
+	fn neighbors_differ() {
+		let thresholds = generate(SIZE);
+		let at = |x: usize, y: usize| thresholds[(y % SIZE) * SIZE + x % SIZE];
+		let mean_difference = |dx: usize, dy: usize| {
+			let mut sum = 0.0;
+			for y in 0..SIZE {
+				for x in 0..SIZE {
+					sum += (at(x, y) - at(x + dx, y + dy)).abs();
+				}
+			}
+			sum / (SIZE * SIZE) as f32
+		};
+		// Neighbors in white noise differ by a third on average. Blue noise lacks low frequencies, so
+		// its neighbors differ by more.
+		let (horizontal, vertical) = (mean_difference(1, 0), mean_difference(0, 1));
+		assert!(horizontal > 0.38, "{horizontal}");
+		assert!(vertical > 0.38, "{vertical}");
+	}
+}
@@ -15,6 +15,16 @@ pub struct ClothoidState {
 	curvature: f32,
 }
 
+impl ClothoidState {
+	pub fn position(&self) -> Vec2 {
+		self.position
+	}
+
+	pub fn theta(&self) -> f32 {
+		self.theta
+	}
+}
+
 impl Debug for ClothoidState {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		write!(
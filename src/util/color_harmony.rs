@@ -0,0 +1,76 @@
+use super::{clamp_to_srgb_gamut, oklab_to_oklch, oklch_to_oklab};
+use glam::*;
+use std::f32::consts::{PI, TAU};
+
+/// How far apart in hue analogous colors are, in radians.
+const ANALOGOUS_SPACING: f32 = PI / 6.0;
+
+/// A rule choosing colors that go with a given one. Hues are rotated in Oklch, so companions keep
+/// the lightness and chroma of the color as far as the sRGB gamut allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum ColorHarmony {
+	/// The opposite hue.
+	#[display("Complementary")]
+	Complementary,
+	/// The hues a third of the way around in either direction.
+	#[display("Triadic")]
+	Triadic,
+	/// The neighboring hues in either direction.
+	#[display("Analogous")]
+	Analogous,
+}
+
+impl ColorHarmony {
+	pub const ALL: [Self; 3] = [Self::Complementary, Self::Triadic, Self::Analogous];
+
+	/// How far each companion's hue is rotated from the color's, in radians.
+	fn rotations(self) -> &'static [f32] {
+		match self {
+			Self::Complementary => &[PI],
+			Self::Triadic => &[TAU / 3.0, -TAU / 3.0],
+			Self::Analogous => &[-ANALOGOUS_SPACING, ANALOGOUS_SPACING],
+		}
+	}
+
+	/// The colors going with `lab`, in Oklab, within the sRGB gamut. Grays have no hue, so their
+	/// companions are themselves.
+	pub fn companions(self, lab: Vec3) -> Vec<Vec3> {
+		let lch = oklab_to_oklch(lab);
+		self
+			.rotations()
+			.iter()
+			.map(|rotation| clamp_to_srgb_gamut(oklch_to_oklab(lch + vec3(0.0, 0.0, *rotation))))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::{in_srgb_gamut, rgb_to_oklab};
+
+	#[test]
+	fn companions_rotate_hue() {
+		let lab = rgb_to_oklab(vec3(0.8, 0.4, 0.2));
+		let lch = oklab_to_oklch(lab);
+		for harmony in ColorHarmony::ALL {
+			let companions = harmony.companions(lab);
+			assert_eq!(companions.len(), harmony.rotations().len());
+			for (companion, rotation) in companions.into_iter().zip(harmony.rotations()) {
+				assert!(in_srgb_gamut(companion), "{harmony}: {companion}");
+				let companion = oklab_to_oklch(companion);
+				assert!((companion.x - lch.x).abs() < 1e-3, "{harmony}: {companion}");
+				let turned = (companion.z - lch.z - rotation).rem_euclid(TAU);
+				assert!(turned.min(TAU - turned) < 1e-3, "{harmony}: {companion}");
+			}
+		}
+	}
+
+	#[test]
+	fn grays_are_their_own_companions() {
+		let gray = rgb_to_oklab(Vec3::splat(0.5));
+		for companion in ColorHarmony::Triadic.companions(gray) {
+			assert!(companion.abs_diff_eq(gray, 1e-5), "{companion}");
+		}
+	}
+}
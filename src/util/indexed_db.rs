@@ -0,0 +1,117 @@
+use leptos::prelude::window;
+use leptos::wasm_bindgen;
+use leptos::web_sys;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+use super::{JsError, ResultExt};
+
+/// An IndexedDB database whose object stores map string keys to byte arrays.
+#[derive(Clone)]
+pub struct ByteDatabase(web_sys::IdbDatabase);
+
+impl ByteDatabase {
+	/// Opens the database `name`, creating it and any of `store_names` which don't exist. `version`
+	/// must be increased whenever `store_names` changes.
+	pub async fn open(name: &str, version: u32, store_names: &[&str]) -> Result<Self, JsError> {
+		let factory = window()
+			.indexed_db()?
+			.ok_or_else(|| JsValue::from_str("IndexedDB is unavailable"))?;
+		let request = factory.open_with_u32(name, version)?;
+
+		let on_upgrade_needed = {
+			let request = request.clone();
+			let store_names: Vec<String> = store_names.iter().map(|&s| s.to_owned()).collect();
+			Closure::<dyn FnMut()>::new(move || {
+				let Ok(database) = request.result() else {
+					return;
+				};
+				let database: web_sys::IdbDatabase = database.unchecked_into();
+				for store_name in &store_names {
+					if !database.object_store_names().contains(store_name) {
+						database.create_object_store(store_name).ok_or_log();
+					}
+				}
+			})
+		};
+		request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+		let database = wait_for(&request).await?.unchecked_into();
+		request.set_onupgradeneeded(None);
+		Ok(Self(database))
+	}
+
+	pub fn store(&self, name: &str) -> ByteStore {
+		ByteStore {
+			database: self.0.clone(),
+			store_name: name.to_owned(),
+		}
+	}
+}
+
+/// A single object store in a [`ByteDatabase`].
+pub struct ByteStore {
+	database: web_sys::IdbDatabase,
+	store_name: String,
+}
+
+impl ByteStore {
+	fn object_store(
+		&self,
+		mode: web_sys::IdbTransactionMode,
+	) -> Result<web_sys::IdbObjectStore, JsError> {
+		let transaction = self
+			.database
+			.transaction_with_str_and_mode(&self.store_name, mode)?;
+		Ok(transaction.object_store(&self.store_name)?)
+	}
+
+	pub async fn put(&self, key: &str, value: &[u8]) -> Result<(), JsError> {
+		let store = self.object_store(web_sys::IdbTransactionMode::Readwrite)?;
+		let value = js_sys::Uint8Array::from(value);
+		wait_for(&store.put_with_key(&value, &JsValue::from_str(key))?).await?;
+		Ok(())
+	}
+
+	/// The value of `key`, if there is one.
+	pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, JsError> {
+		let store = self.object_store(web_sys::IdbTransactionMode::Readonly)?;
+		let value = wait_for(&store.get(&JsValue::from_str(key))?).await?;
+		Ok(value
+			.dyn_into::<js_sys::Uint8Array>()
+			.ok()
+			.map(|value| value.to_vec()))
+	}
+
+	pub async fn delete(&self, key: &str) -> Result<(), JsError> {
+		let store = self.object_store(web_sys::IdbTransactionMode::Readwrite)?;
+		wait_for(&store.delete(&JsValue::from_str(key))?).await?;
+		Ok(())
+	}
+
+	pub async fn clear(&self) -> Result<(), JsError> {
+		let store = self.object_store(web_sys::IdbTransactionMode::Readwrite)?;
+		wait_for(&store.clear()?).await?;
+		Ok(())
+	}
+
+	/// Every value in the store, in key order.
+	pub async fn get_all(&self) -> Result<Vec<Vec<u8>>, JsError> {
+		let store = self.object_store(web_sys::IdbTransactionMode::Readonly)?;
+		let values: js_sys::Array = wait_for(&store.get_all()?).await?.unchecked_into();
+		Ok(values
+			.iter()
+			.filter_map(|value| value.dyn_into::<js_sys::Uint8Array>().ok())
+			.map(|value| value.to_vec())
+			.collect())
+	}
+}
+
+/// Waits for `request` to complete, returning its result.
+async fn wait_for(request: &web_sys::IdbRequest) -> Result<JsValue, JsValue> {
+	let promise = js_sys::Promise::new(&mut |resolve, reject| {
+		request.set_onsuccess(Some(&resolve));
+		request.set_onerror(Some(&reject));
+	});
+	wasm_bindgen_futures::JsFuture::from(promise).await?;
+	request.result()
+}
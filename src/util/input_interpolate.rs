@@ -65,6 +65,30 @@ impl<Y: VectorSpace> Bezier<Y> {
 		}
 	}
 
+	/// The curve's four control points. The curve starts at the first and ends at the last.
+	pub fn control_points(&self) -> [Y; 4] {
+		self.p
+	}
+
+	/// The times the curve starts and ends at.
+	pub fn time_range(&self) -> std::ops::RangeInclusive<f32> {
+		self.t0..=self.t1
+	}
+
+	/// The curve's value at `t`, which must be within [`Self::time_range`].
+	pub fn value_at(&self, t: f32) -> Y {
+		self.evaluate(t).y
+	}
+
+	/// Applies `f` to each control point, which transforms the curve exactly when `f` is affine.
+	pub fn map<Z>(self, f: impl Fn(Y) -> Z) -> Bezier<Z> {
+		Bezier {
+			t0: self.t0,
+			t1: self.t1,
+			p: self.p.map(f),
+		}
+	}
+
 	pub fn evaluate(&self, t: f32) -> BezierPoint<Y> {
 		debug_assert!(t >= self.t0);
 		debug_assert!(t <= self.t1);
@@ -168,22 +192,170 @@ pub struct InputPoint {
 	pub pressure: f32,
 }
 
-#[derive(Default, Debug, Clone)]
+/// Pressure is fit in units of this many per full pressure, since the interpolators fit to within
+/// half a unit.
+const PRESSURE_RESOLUTION: f32 = 256.0;
+
+/// How a stroke ends, between the end of the last segment fit and the last point added, which the
+/// pointer usually reaches before there are enough points after it to fit another.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum StrokeEnd {
+	/// A straight line, which turns sharply where it meets the curve when the pointer lifts mid-flick.
+	Linear,
+	/// Continues along the curve's tangent, its speed decaying to `retained` times the speed it
+	/// started with, so that the stroke carries on smoothly and still ends exactly on the last point.
+	Decaying { retained: f32 },
+}
+
+impl Default for StrokeEnd {
+	fn default() -> Self {
+		Self::Decaying { retained: 0.0 }
+	}
+}
+
+impl StrokeEnd {
+	fn segment(
+		self,
+		start: BezierPoint<glam::Vec3>,
+		end: BezierPoint<glam::Vec3>,
+	) -> Bezier<glam::Vec3> {
+		match self {
+			Self::Linear => Bezier::linear(start.t, start.y, end.t, end.y),
+			Self::Decaying { retained } => {
+				let mut handle = start.dy_dt * ((end.t - start.t) / 3.0);
+				// The tangent's speed is from before the pointer lifted, and can be far more than the
+				// distance left to cover, so the handles are kept within half of it rather than looping
+				// past the end and back.
+				let (length, chord) = (
+					handle.truncate().length(),
+					(end.y - start.y).truncate().length(),
+				);
+				if length > 0.5 * chord {
+					handle *= 0.5 * chord / length;
+				}
+				Bezier {
+					t0: start.t,
+					t1: end.t,
+					p: [
+						start.y,
+						start.y + handle,
+						end.y - handle * retained.clamp(0.0, 1.0),
+						end.y,
+					],
+				}
+			}
+		}
+	}
+}
+
+/// How quickly the estimate of the time between input points follows changes in the input rate,
+/// as the weight given to each new interval.
+const INPUT_SPACING_RESPONSE: f32 = 0.2;
+
+#[derive(Debug, Clone)]
 pub struct InputSplineBuilder<I> {
 	interpolator: I,
+	min_interval: f32,
+	// The number of input points each segment should span, if the interval adapts to the input rate.
+	points_per_segment: Option<f32>,
+	max_queued_points: usize,
+	// A running average of the time between input points, in seconds.
+	input_spacing: Option<f32>,
+	last_input_time: Option<f32>,
+	// The last point added, whether or not it was queued to be fit.
+	last_input: Option<InputPoint>,
+	stroke_end: StrokeEnd,
 	input_points: std::collections::VecDeque<InputPoint>,
 	output_points: Vec<BezierPoint<glam::Vec3>>,
 }
 
-impl<I: Interpolator> InputSplineBuilder<I> {
+impl<I: Default> Default for InputSplineBuilder<I> {
+	fn default() -> Self {
+		Self::new(I::default())
+	}
+}
+
+impl<I> InputSplineBuilder<I> {
+	/// The default for [`Self::with_min_interval`], in seconds.
+	pub const DEFAULT_MIN_INTERVAL: f32 = 0.125;
+
+	/// The default for [`Self::with_max_queued_points`].
+	pub const DEFAULT_MAX_QUEUED_POINTS: usize = 16;
+
+	/// The fewest queued points [`Self::with_max_queued_points`] allows, since a first segment is fit
+	/// to four.
+	const MIN_QUEUED_POINTS: usize = 4;
+
 	pub fn new(interpolator: I) -> Self {
 		Self {
 			interpolator,
+			min_interval: Self::DEFAULT_MIN_INTERVAL,
+			points_per_segment: None,
+			max_queued_points: Self::DEFAULT_MAX_QUEUED_POINTS,
+			input_spacing: None,
+			last_input_time: None,
+			last_input: None,
+			stroke_end: StrokeEnd::default(),
 			input_points: Default::default(),
 			output_points: Vec::new(),
 		}
 	}
 
+	/// Ignores points arriving less than `min_interval` after the end of the last segment. Longer
+	/// intervals give smoother but less responsive curves.
+	pub fn with_min_interval(self, min_interval: f32) -> Self {
+		Self {
+			min_interval,
+			..self
+		}
+	}
+
+	/// Shortens the interval to span only about `points_per_segment` input points when they arrive
+	/// quickly, so fast pointers aren't held back as long as slow ones. The interval never exceeds
+	/// the one given to [`Self::with_min_interval`].
+	pub fn with_adaptive_interval(self, points_per_segment: f32) -> Self {
+		Self {
+			points_per_segment: Some(points_per_segment),
+			..self
+		}
+	}
+
+	/// Drops the oldest points which haven't been fit once more than `max_queued_points` are
+	/// waiting, so input the interpolator can't fit doesn't accumulate.
+	pub fn with_max_queued_points(self, max_queued_points: usize) -> Self {
+		Self {
+			max_queued_points: max_queued_points.max(Self::MIN_QUEUED_POINTS),
+			..self
+		}
+	}
+
+	/// Sets how [`Self::finish`] reaches the last point added.
+	pub fn with_stroke_end(self, stroke_end: StrokeEnd) -> Self {
+		Self { stroke_end, ..self }
+	}
+
+	/// The interval points are currently ignored for after the end of each segment, in seconds.
+	pub fn interval(&self) -> f32 {
+		match (self.points_per_segment, self.input_spacing) {
+			(Some(points), Some(spacing)) => self.min_interval.min(points * spacing),
+			_ => self.min_interval,
+		}
+	}
+
+	fn record_input_time(&mut self, t: f32) {
+		if let Some(dt) = self.last_input_time.map(|last| t - last) {
+			if dt > 0.0 {
+				self.input_spacing = Some(match self.input_spacing {
+					Some(spacing) => spacing + (dt - spacing) * INPUT_SPACING_RESPONSE,
+					None => dt,
+				});
+			}
+		}
+		self.last_input_time = Some(t);
+	}
+}
+
+impl<I: Interpolator> InputSplineBuilder<I> {
 	fn x_points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
 		self.input_points.iter().map(|p| (p.t, p.x))
 	}
@@ -193,21 +365,26 @@ impl<I: Interpolator> InputSplineBuilder<I> {
 	}
 
 	fn z_points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
-		self.input_points.iter().map(|p| (p.t, p.pressure))
+		self
+			.input_points
+			.iter()
+			.map(|p| (p.t, PRESSURE_RESOLUTION * p.pressure))
 	}
 
 	pub fn add_point(&mut self, point: InputPoint) -> Option<Bezier<glam::Vec3>> {
+		self.record_input_time(point.t);
+		self.last_input = Some(point);
 		let last_point: Option<BezierPoint<glam::Vec3>> = self.output_points.last().cloned();
-		const MIN_INTERPOLATION_INTERVAL: f32 = 0.125;
 		if let Some(last_point) = last_point {
-			if point.t < last_point.t + MIN_INTERPOLATION_INTERVAL {
+			if point.t < last_point.t + self.interval() {
 				return None;
 			}
 		}
 
-		const PRESSURE_RESOLUTION: f32 = 256.0;
-
 		self.input_points.push_back(point);
+		while self.input_points.len() > self.max_queued_points {
+			self.input_points.pop_front();
+		}
 		let x_bezier = self.interpolator.fit(
 			last_point.map(|p| BezierPoint {
 				t: p.t,
@@ -247,19 +424,249 @@ impl<I: Interpolator> InputSplineBuilder<I> {
 			t0,
 			t1,
 			p: [
-				glam::vec3(x_bezier.p[0], y_bezier.p[0], z_bezier.p[0] / PRESSURE_RESOLUTION),
-				glam::vec3(x_bezier.p[1], y_bezier.p[1], z_bezier.p[1] / PRESSURE_RESOLUTION),
-				glam::vec3(x_bezier.p[2], y_bezier.p[2], z_bezier.p[2] / PRESSURE_RESOLUTION),
-				glam::vec3(x_bezier.p[3], y_bezier.p[3], z_bezier.p[3] / PRESSURE_RESOLUTION),
+				glam::vec3(
+					x_bezier.p[0],
+					y_bezier.p[0],
+					z_bezier.p[0] / PRESSURE_RESOLUTION,
+				),
+				glam::vec3(
+					x_bezier.p[1],
+					y_bezier.p[1],
+					z_bezier.p[1] / PRESSURE_RESOLUTION,
+				),
+				glam::vec3(
+					x_bezier.p[2],
+					y_bezier.p[2],
+					z_bezier.p[2] / PRESSURE_RESOLUTION,
+				),
+				glam::vec3(
+					x_bezier.p[3],
+					y_bezier.p[3],
+					z_bezier.p[3] / PRESSURE_RESOLUTION,
+				),
 			],
 		};
 		self.output_points.push(bezier.evaluate_end());
 		Some(bezier)
 	}
 
+	/// Returns a final segment from the end of the last one to the last point added, including one
+	/// which arrived too soon after the last segment to be fit. There are no later points to
+	/// constrain it, so its shape is set by [`Self::with_stroke_end`].
 	pub fn finish(self) -> Option<Bezier<glam::Vec3>> {
-		// TODO: Implement this.
-		None
+		let point = |p: &InputPoint| BezierPoint {
+			t: p.t,
+			y: glam::vec3(p.x, p.y, p.pressure),
+			dy_dt: glam::Vec3::ZERO,
+		};
+		let end = point(self.last_input.as_ref()?);
+		let start = match self.output_points.last() {
+			Some(start) => *start,
+			None => point(self.input_points.front()?),
+		};
+		(end.t > start.t).then(|| self.stroke_end.segment(start, end))
+	}
+}
+
+/// The most a fully stabilized stroke may stray from the pointer, in pixels.
+const MAX_STABILIZER_TOLERANCE: f32 = 8.0;
+
+/// The longest a fully stabilized stroke waits between segments by default, in seconds.
+pub const DEFAULT_STABILIZER_INTERVAL: f32 = 0.1;
+
+/// About how many pointer points each smoothed segment spans, when they arrive quickly enough that
+/// this is less than the stabilizer's interval.
+const STABILIZER_POINTS_PER_SEGMENT: f32 = 6.0;
+
+/// The most pixels between consecutive samples of a smoothed segment.
+const STABILIZER_SAMPLE_SPACING: f32 = 2.0;
+
+/// The farthest [`Stabilizer::extrapolate`] predicts the pointer will go, in pixels, since the
+/// tangent at the end of a sudden flick can overshoot wildly.
+pub const MAX_PREDICTION_DISTANCE: f32 = 48.0;
+
+/// Smooths a stroke by fitting curves to the pointer's path, trading responsiveness for steadier
+/// lines. Points go in as the pointer reports them and come out, possibly delayed, along the curve.
+#[derive(Debug, Clone)]
+pub struct Stabilizer {
+	strength: f32,
+	// Positions are divided by this before fitting, since the interpolator fits within half a unit.
+	scale: f32,
+	builder: Option<InputSplineBuilder<CubicInterpolator>>,
+	last_output: Option<InputPoint>,
+	last_input: Option<InputPoint>,
+	// The segments the stroke's points were sampled from, in pixels, until they are taken.
+	segments: Vec<Bezier<glam::Vec3>>,
+	// The end of the latest segment, in pixels, whose tangent predictions follow.
+	end: Option<BezierPoint<glam::Vec3>>,
+}
+
+impl Stabilizer {
+	/// Creates a stabilizer for a new stroke. A `strength` of 0 passes points through unchanged, and
+	/// 1 is the smoothest.
+	pub fn new(strength: f32) -> Self {
+		let strength = strength.clamp(0.0, 1.0);
+		let builder = (strength > 0.0).then(|| {
+			InputSplineBuilder::new(CubicInterpolator)
+				.with_min_interval(strength * DEFAULT_STABILIZER_INTERVAL)
+				.with_adaptive_interval(STABILIZER_POINTS_PER_SEGMENT)
+		});
+		Self {
+			strength,
+			scale: (2.0 * strength * MAX_STABILIZER_TOLERANCE).max(1.0),
+			builder,
+			last_output: None,
+			last_input: None,
+			segments: Vec::new(),
+			end: None,
+		}
+	}
+
+	/// Sets the longest a fully stabilized stroke waits between segments, in seconds, in place of
+	/// [`DEFAULT_STABILIZER_INTERVAL`]. Weaker stabilizers wait proportionally less, and all of them
+	/// wait less when the pointer reports points quickly.
+	pub fn with_max_interval(self, max_interval: f32) -> Self {
+		let min_interval = self.strength * max_interval.max(0.0);
+		Self {
+			builder: self
+				.builder
+				.map(|builder| builder.with_min_interval(min_interval)),
+			..self
+		}
+	}
+
+	/// Sets how the smoothed stroke reaches the pointer's last point when it is finished. See
+	/// [`StrokeEnd`].
+	pub fn with_stroke_end(self, stroke_end: StrokeEnd) -> Self {
+		Self {
+			builder: self
+				.builder
+				.map(|builder| builder.with_stroke_end(stroke_end)),
+			..self
+		}
+	}
+
+	/// Adds a point from the pointer, returning the points of the smoothed stroke which are now
+	/// known. Positions are in pixels and times in seconds.
+	pub fn push(&mut self, point: InputPoint) -> Vec<InputPoint> {
+		self.last_input = Some(point);
+		let Some(builder) = &mut self.builder else {
+			self.record_line_to(point);
+			return self.output([point]);
+		};
+		let scaled = InputPoint {
+			x: point.x / self.scale,
+			y: point.y / self.scale,
+			..point
+		};
+		match builder.add_point(scaled) {
+			Some(segment) => self.sample(segment),
+			None => Vec::new(),
+		}
+	}
+
+	/// Ends the stroke, returning the remaining points so that it reaches the pointer.
+	pub fn finish(&mut self) -> Vec<InputPoint> {
+		let mut points = match self.builder.take().and_then(InputSplineBuilder::finish) {
+			Some(segment) => self.sample(segment),
+			None => Vec::new(),
+		};
+		// End exactly on the pointer. The final segment already does, up to rounding, unless the
+		// stroke wasn't smoothed.
+		if let Some(last_input) = self.last_input.take() {
+			match points.last_mut() {
+				Some(last) if last.t >= last_input.t => *last = last_input,
+				_ if self.last_output.map_or(true, |p| p.t < last_input.t) => {
+					self.record_line_to(last_input);
+					points.push(last_input);
+				}
+				_ => {}
+			}
+			self.last_output = Some(last_input);
+		}
+		points
+	}
+
+	/// Takes the segments of the smoothed stroke which are known so far, in pixels with the pressure
+	/// as the third coordinate. Together they describe the stroke independently of how densely it
+	/// was sampled. Unsmoothed strokes are straight lines between the pointer's points.
+	pub fn take_segments(&mut self) -> Vec<Bezier<glam::Vec3>> {
+		std::mem::take(&mut self.segments)
+	}
+
+	/// The last point of the smoothed stroke returned so far, which is as far as it has been drawn.
+	pub fn last_output(&self) -> Option<InputPoint> {
+		self.last_output
+	}
+
+	/// Predicts where the pointer will be `horizon` seconds after its last point, by carrying on from
+	/// it along the tangent at the end of the smoothed stroke. The pressure is held. Returns `None`
+	/// until there is a segment to take the tangent from.
+	pub fn extrapolate(&self, horizon: f32) -> Option<InputPoint> {
+		let last_input = self.last_input?;
+		let step = self.end?.dy_dt.truncate() * horizon;
+		if !step.is_finite() {
+			return None;
+		}
+		let step = step.clamp_length_max(MAX_PREDICTION_DISTANCE);
+		Some(InputPoint {
+			t: last_input.t + horizon,
+			x: last_input.x + step.x,
+			y: last_input.y + step.y,
+			..last_input
+		})
+	}
+
+	fn record_line_to(&mut self, point: InputPoint) {
+		if let Some(last) = self.last_output {
+			let segment = Bezier::linear(
+				last.t,
+				glam::vec3(last.x, last.y, last.pressure),
+				point.t,
+				glam::vec3(point.x, point.y, point.pressure),
+			);
+			// Points reported together have no speed between them, so they keep the last tangent.
+			if point.t > last.t {
+				self.end = Some(segment.evaluate_end());
+			}
+			self.segments.push(segment);
+		}
+	}
+
+	fn sample(&mut self, segment: Bezier<glam::Vec3>) -> Vec<InputPoint> {
+		let scale = glam::vec3(self.scale, self.scale, 1.0);
+		let scaled = segment.map(|p| p * scale);
+		self.end = Some(scaled.evaluate_end());
+		self.segments.push(scaled);
+		let point = |t: f32| {
+			let y = segment.evaluate(t).y;
+			InputPoint {
+				t,
+				x: y.x * self.scale,
+				y: y.y * self.scale,
+				pressure: y.z.clamp(0.0, 1.0),
+			}
+		};
+		let (start, end) = (point(segment.t0), point(segment.t1));
+		let chord = glam::vec2(end.x - start.x, end.y - start.y).length();
+		let count = (chord / STABILIZER_SAMPLE_SPACING).ceil().clamp(1.0, 64.0) as usize;
+		// The start of each segment is the end of the last.
+		let first = if self.last_output.is_some() { 1 } else { 0 };
+		let points: Vec<_> = (first..=count)
+			.map(|i| match i {
+				i if i == count => end,
+				i => point(segment.t0 + (segment.t1 - segment.t0) * i as f32 / count as f32),
+			})
+			.collect();
+		self.output(points)
+	}
+
+	fn output(&mut self, points: impl IntoIterator<Item = InputPoint>) -> Vec<InputPoint> {
+		let points: Vec<_> = points.into_iter().collect();
+		if let Some(last) = points.last() {
+			self.last_output = Some(*last);
+		}
+		points
 	}
 }
 
@@ -799,4 +1206,220 @@ mod tests {
 			epsilon = EPSILON.sqrt()
 		);
 	}
+
+	#[test]
+	fn adaptive_interval_follows_input_rate() {
+		let mut spline = InputSplineBuilder::new(LinearInterpolator)
+			.with_min_interval(0.1)
+			.with_adaptive_interval(4.0);
+		assert_eq!(spline.interval(), 0.1);
+
+		// At 200 points per second, segments span four points rather than the full interval.
+		let mut segments = Vec::new();
+		for i in 0..40 {
+			let t = i as f32 / 200.0;
+			segments.extend(spline.add_point(InputPoint {
+				t,
+				x: t,
+				..Default::default()
+			}));
+		}
+		assert_abs_diff_eq!(spline.interval(), 0.02, epsilon = 1e-4);
+		assert!(segments.len() > 5);
+
+		// Slow input is held to the full interval.
+		for i in 1..40 {
+			let t = 0.2 + i as f32 / 5.0;
+			spline.add_point(InputPoint {
+				t,
+				x: t,
+				..Default::default()
+			});
+		}
+		assert_eq!(spline.interval(), 0.1);
+	}
+
+	#[test]
+	fn queued_points_are_bounded() {
+		/// Never fits, so every point stays queued.
+		struct Unfit;
+		impl Interpolator for Unfit {
+			fn fit(
+				&self,
+				_initial: Option<BezierPoint<f32>>,
+				_points: impl IntoIterator<Item = (f32, f32)>,
+			) -> Option<Bezier<f32>> {
+				None
+			}
+		}
+
+		let mut spline = InputSplineBuilder::new(Unfit).with_max_queued_points(8);
+		for i in 0..100 {
+			assert!(spline
+				.add_point(InputPoint {
+					t: i as f32,
+					..Default::default()
+				})
+				.is_none());
+		}
+		assert_eq!(spline.input_points.len(), 8);
+		assert_eq!(spline.input_points.front().map(|p| p.t), Some(92.0));
+	}
+
+	/// A line along x at 10 pixels per second, fit into a segment ending at `t = 0.2`, with a last
+	/// point at `t` which arrives too soon after it to be fit.
+	fn flick(t: f32, stroke_end: StrokeEnd) -> InputSplineBuilder<LinearInterpolator> {
+		let mut spline = InputSplineBuilder::new(LinearInterpolator)
+			.with_min_interval(0.1)
+			.with_stroke_end(stroke_end);
+		for t in [0.0, 0.2, t] {
+			spline.add_point(InputPoint {
+				t,
+				x: 10.0 * t,
+				pressure: 0.5,
+				..Default::default()
+			});
+		}
+		assert_eq!(spline.output_points.len(), 1);
+		spline
+	}
+
+	#[test]
+	fn finish_reaches_points_too_soon_to_fit() {
+		let end = glam::vec3(2.5, 0.0, 0.5);
+		let linear = flick(0.25, StrokeEnd::Linear).finish().unwrap();
+		assert_eq!(linear.control_points()[3], end);
+		assert!(linear
+			.evaluate(0.225)
+			.y
+			.abs_diff_eq(glam::vec3(2.25, 0.0, 0.5), 1e-5));
+
+		// Decaying ends on the same point, leaving the curve along its tangent and slowing to a stop.
+		let decaying = flick(0.25, StrokeEnd::Decaying { retained: 0.0 })
+			.finish()
+			.unwrap();
+		assert_eq!(decaying.control_points()[3], end);
+		assert!(decaying
+			.evaluate(0.2)
+			.dy_dt
+			.abs_diff_eq(glam::vec3(10.0, 0.0, 0.0), 1e-3));
+		assert!(decaying
+			.evaluate(0.25)
+			.dy_dt
+			.abs_diff_eq(glam::Vec3::ZERO, 1e-5));
+		let retained = flick(0.25, StrokeEnd::Decaying { retained: 0.5 })
+			.finish()
+			.unwrap();
+		assert!(retained
+			.evaluate(0.25)
+			.dy_dt
+			.abs_diff_eq(glam::vec3(5.0, 0.0, 0.0), 1e-3));
+
+		// There is nothing left to reach once the last point is fit.
+		let mut spline = flick(0.25, StrokeEnd::default());
+		spline.output_points.last_mut().unwrap().t = 0.25;
+		assert!(spline.finish().is_none());
+	}
+
+	#[test]
+	fn decaying_end_does_not_overshoot() {
+		// The pointer barely moves after the last segment, so carrying on at its speed would pass the
+		// end and turn back.
+		let segment = flick(0.21, StrokeEnd::Decaying { retained: 1.0 })
+			.finish()
+			.unwrap();
+		let xs = segment.control_points().map(|p| p.x);
+		assert_eq!(xs[3], 10.0 * 0.21);
+		// The curve stays within its control points.
+		assert!(
+			xs.iter().all(|x| (2.0 - 1e-5..=2.1 + 1e-5).contains(x)),
+			"{xs:?}"
+		);
+	}
+
+	fn jittery_line(count: usize) -> impl Iterator<Item = InputPoint> {
+		(0..count).map(|i| InputPoint {
+			t: i as f32 / 60.0,
+			x: 4.0 * i as f32,
+			y: (2.4 * i as f32).sin(),
+			pressure: 0.5,
+		})
+	}
+
+	fn total_variation(points: &[InputPoint]) -> f32 {
+		points.windows(2).map(|w| (w[1].y - w[0].y).abs()).sum()
+	}
+
+	#[test]
+	fn stabilizer_off_passes_points_through() {
+		let mut stabilizer = Stabilizer::new(0.0);
+		for point in jittery_line(8) {
+			assert_eq!(stabilizer.push(point), [point]);
+		}
+		assert!(stabilizer.finish().is_empty());
+	}
+
+	#[test]
+	fn stabilizer_smooths_jitter() {
+		let raw: Vec<_> = jittery_line(120).collect();
+		let mut stabilizer = Stabilizer::new(1.0);
+		let smoothed: Vec<_> = raw.iter().flat_map(|&p| stabilizer.push(p)).collect();
+		assert!(smoothed.len() > 10);
+		assert!(total_variation(&smoothed) < 0.25 * total_variation(&raw));
+		assert!(smoothed.iter().all(|p| (p.pressure - 0.5).abs() < 1e-2));
+		assert!(smoothed.windows(2).all(|w| w[0].t < w[1].t));
+
+		// The stroke is delayed, but finishing catches up with the pointer.
+		let last = *raw.last().unwrap();
+		assert!(smoothed.last().unwrap().t < last.t);
+		assert_eq!(stabilizer.finish().last(), Some(&last));
+	}
+
+	#[test]
+	fn stabilizer_records_connected_segments() {
+		let position = |p: &InputPoint| glam::vec3(p.x, p.y, p.pressure);
+		for strength in [0.0, 1.0] {
+			let raw: Vec<_> = jittery_line(120).collect();
+			let mut stabilizer = Stabilizer::new(strength);
+			let mut points: Vec<_> = raw.iter().flat_map(|&p| stabilizer.push(p)).collect();
+			points.extend(stabilizer.finish());
+			let segments = stabilizer.take_segments();
+			assert!(!segments.is_empty());
+			assert!(stabilizer.take_segments().is_empty());
+
+			// The segments run from the first point to the last without gaps.
+			let ends = |s: &Bezier<glam::Vec3>| (s.control_points()[0], s.control_points()[3]);
+			assert!(ends(&segments[0]).0.abs_diff_eq(position(&points[0]), 1e-3));
+			assert!(segments
+				.windows(2)
+				.all(|w| ends(&w[0]).1.abs_diff_eq(ends(&w[1]).0, 1e-3)));
+			let end = ends(segments.last().unwrap()).1;
+			assert!(end.abs_diff_eq(position(raw.last().unwrap()), 1e-3));
+		}
+	}
+
+	#[test]
+	fn stabilizer_extrapolates_along_the_stroke() {
+		for strength in [0.0, 1.0] {
+			let mut stabilizer = Stabilizer::new(strength);
+			assert_eq!(stabilizer.extrapolate(0.1), None);
+			let raw: Vec<_> = jittery_line(120).collect();
+			for &point in &raw {
+				stabilizer.push(point);
+			}
+			let last = *raw.last().unwrap();
+
+			// The line moves 240 pixels a second, so a frame ahead is about 4 pixels on.
+			let predicted = stabilizer.extrapolate(1.0 / 60.0).unwrap();
+			assert_eq!(predicted.t, last.t + 1.0 / 60.0);
+			assert_eq!(predicted.pressure, last.pressure);
+			assert!((predicted.x - last.x - 4.0).abs() < 2.0, "{predicted:?}");
+			assert!((predicted.y - last.y).abs() < 2.0, "{predicted:?}");
+
+			// Predictions far ahead are cut short.
+			let far = stabilizer.extrapolate(10.0).unwrap();
+			let distance = glam::vec2(far.x - last.x, far.y - last.y).length();
+			assert!(distance <= MAX_PREDICTION_DISTANCE + 1e-3, "{far:?}");
+		}
+	}
 }
@@ -0,0 +1,114 @@
+// Pen latency is the time from an input event to the frame that shows it. It is measured at each
+// stage of the way: when the frame is submitted, when the GPU finishes it, and at the next animation
+// frame after that, by when the browser has composited it. None of these include the display, so
+// they are lower bounds on what the user sees.
+
+/// How much each frame moves the average towards its latency.
+const LATENCY_SMOOTHING: f64 = 0.1;
+
+/// How far a frame showing some input has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, derive_more::Display)]
+pub enum LatencyStage {
+	/// The frame's commands were submitted to the GPU.
+	#[display("submit")]
+	Submitted,
+	/// The GPU finished the frame.
+	#[display("GPU")]
+	Finished,
+	/// The animation frame after the GPU finished, when the frame has been presented.
+	#[display("present")]
+	Presented,
+}
+
+impl LatencyStage {
+	pub const ALL: [Self; 3] = [Self::Submitted, Self::Finished, Self::Presented];
+
+	fn index(self) -> usize {
+		self as usize
+	}
+}
+
+/// The average latency, in milliseconds, to each stage, or `None` where nothing has been measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyAverages([Option<f64>; 3]);
+
+impl LatencyAverages {
+	pub fn get(&self, stage: LatencyStage) -> Option<f64> {
+		self.0[stage.index()]
+	}
+}
+
+/// Measures input latency, in milliseconds, averaged over recent frames.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyMeter {
+	// The time of the earliest input not yet drawn, so each frame reports its worst case.
+	pending: Option<f64>,
+	averages: LatencyAverages,
+}
+
+impl LatencyMeter {
+	/// Records input at `time`, such as an event's `timeStamp`.
+	pub fn input(&mut self, time: f64) {
+		self.pending.get_or_insert(time);
+	}
+
+	/// Takes the time of the earliest input since the previous frame, which the next frame shows.
+	pub fn take_pending(&mut self) -> Option<f64> {
+		self.pending.take()
+	}
+
+	/// Records that a frame showing input from `input_time` reached `stage` at `time`, and returns
+	/// its latency.
+	pub fn record(&mut self, stage: LatencyStage, input_time: f64, time: f64) -> f64 {
+		let latency = (time - input_time).max(0.0);
+		let average = &mut self.averages.0[stage.index()];
+		*average = Some(match *average {
+			Some(average) => average + LATENCY_SMOOTHING * (latency - average),
+			None => latency,
+		});
+		latency
+	}
+
+	/// The average latency to each stage.
+	pub fn averages(&self) -> LatencyAverages {
+		self.averages
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn latency_meter() {
+		let mut meter = LatencyMeter::default();
+		assert_eq!(meter.take_pending(), None);
+		assert_eq!(meter.averages(), LatencyAverages::default());
+
+		// Only the earliest input before a frame counts.
+		meter.input(100.0);
+		meter.input(108.0);
+		let input_time = meter.take_pending().unwrap();
+		assert_eq!(input_time, 100.0);
+		assert_eq!(meter.take_pending(), None);
+		assert_eq!(
+			meter.record(LatencyStage::Finished, input_time, 120.0),
+			20.0
+		);
+		assert_eq!(meter.averages().get(LatencyStage::Finished), Some(20.0));
+		assert_eq!(meter.averages().get(LatencyStage::Presented), None);
+
+		// Later frames are smoothed, separately for each stage.
+		meter.input(200.0);
+		let input_time = meter.take_pending().unwrap();
+		meter.record(LatencyStage::Finished, input_time, 230.0);
+		meter.record(LatencyStage::Presented, input_time, 240.0);
+		let averages = meter.averages();
+		approx::assert_abs_diff_eq!(
+			averages.get(LatencyStage::Finished).unwrap(),
+			21.0,
+			epsilon = 1e-9
+		);
+		assert_eq!(averages.get(LatencyStage::Presented), Some(40.0));
+	}
+}
@@ -0,0 +1,24 @@
+use leptos::prelude::window;
+use leptos::wasm_bindgen::JsValue;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{JsError, ResultExt};
+
+/// The value saved under `key` by [`save_to_local_storage`], if there is one and it still parses.
+pub fn load_from_local_storage<T: DeserializeOwned>(key: &str) -> Option<T> {
+	let storage = window().local_storage().ok_or_log()??;
+	let json = storage.get_item(key).ok_or_log()??;
+	serde_json::from_str(&json).ok_or_log()
+}
+
+/// Saves `value` as JSON under `key`. Local storage is synchronous and small, so this is only for
+/// settings rather than documents, which go in IndexedDB.
+pub fn save_to_local_storage<T: Serialize>(key: &str, value: &T) -> Result<(), JsError> {
+	let storage = window()
+		.local_storage()?
+		.ok_or_else(|| JsValue::from_str("local storage is unavailable"))?;
+	let json = serde_json::to_string(value).map_err(|err| JsValue::from_str(&err.to_string()))?;
+	storage.set_item(key, &json)?;
+	Ok(())
+}
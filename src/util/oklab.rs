@@ -1,9 +1,69 @@
 use glam::*;
+use std::sync::LazyLock;
+
+// Conversions between sRGB, Oklab, and Oklch. Oklch is represented as `(L, C, h)` with the hue in
+// radians. See https://bottosson.github.io/posts/oklab/.
+//
+// The `f32` versions are used on hot paths and mirror `lib/oklab.wgsl`. The `f64` versions exist for
+// palette computations (e.g. gradients) where `f32` error accumulates visibly.
 
 pub fn oklab_to_rgb(lab: Vec3) -> Vec3 {
 	return linear_srgb_to_rgb(oklab_to_linear_srgb(lab));
 }
 
+pub fn rgb_to_oklab(rgb: Vec3) -> Vec3 {
+	return linear_srgb_to_oklab(rgb_to_linear_srgb(rgb));
+}
+
+pub fn oklab_to_oklch(lab: Vec3) -> Vec3 {
+	let ab = lab.yz();
+	vec3(lab.x, ab.length(), ab.y.atan2(ab.x))
+}
+
+pub fn oklch_to_oklab(lch: Vec3) -> Vec3 {
+	let (sin, cos) = lch.z.sin_cos();
+	vec3(lch.x, lch.y * cos, lch.y * sin)
+}
+
+// HSV is represented as `(h, s, v)` with the hue in degrees from 0 up to 360, and the saturation and
+// value from 0 to 1. Like RGB, it's only meaningful within the sRGB gamut.
+
+pub fn rgb_to_hsv(rgb: Vec3) -> Vec3 {
+	let max = rgb.max_element();
+	let chroma = max - rgb.min_element();
+	// Hue is undefined for grays, so it's taken to be 0.
+	let sextant = if chroma <= 0.0 {
+		0.0
+	} else if max == rgb.x {
+		((rgb.y - rgb.z) / chroma).rem_euclid(6.0)
+	} else if max == rgb.y {
+		(rgb.z - rgb.x) / chroma + 2.0
+	} else {
+		(rgb.x - rgb.y) / chroma + 4.0
+	};
+	let saturation = if max > 0.0 { chroma / max } else { 0.0 };
+	vec3(60.0 * sextant, saturation, max)
+}
+
+pub fn hsv_to_rgb(hsv: Vec3) -> Vec3 {
+	let sextant = hsv.x.rem_euclid(360.0) / 60.0;
+	let chroma = hsv.z * hsv.y;
+	let channel = |n: f32| {
+		let k = (n + sextant).rem_euclid(6.0);
+		hsv.z - chroma * k.min(4.0 - k).clamp(0.0, 1.0)
+	};
+	vec3(channel(5.0), channel(3.0), channel(1.0))
+}
+
+// Formats `rgb` as a CSS hex color, like `#ff8000`, clamping it to the sRGB gamut.
+pub fn rgb_to_hex(rgb: Vec3) -> String {
+	let [r, g, b] = (rgb.clamp(Vec3::ZERO, Vec3::ONE) * 255.0)
+		.round()
+		.as_uvec3()
+		.to_array();
+	format!("#{r:02x}{g:02x}{b:02x}")
+}
+
 fn oklab_to_linear_srgb(lab: Vec3) -> Vec3 {
 	#[cfg_attr(rustfmt, rustfmt_skip)]
 	static A: Mat3 = Mat3::from_cols_array(&[
@@ -12,20 +72,359 @@ fn oklab_to_linear_srgb(lab: Vec3) -> Vec3 {
 		0.2158037573, -0.0638541728, -1.2914855480]);
 	#[cfg_attr(rustfmt, rustfmt_skip)]
   	static B: Mat3 = Mat3::from_cols_array(&[
-		4.0767416621, -1.2684380046, -0.0041960863, 
-		-3.3077115913, 2.6097574011, -0.7034186147, 
+		4.0767416621, -1.2684380046, -0.0041960863,
+		-3.3077115913, 2.6097574011, -0.7034186147,
 		0.2309699292, -0.3413193965, 1.7076147010]);
 	let v = A * lab;
 	return B * (v * v * v);
 }
 
+fn linear_srgb_to_oklab(srgb: Vec3) -> Vec3 {
+	#[cfg_attr(rustfmt, rustfmt_skip)]
+	static A: Mat3 = Mat3::from_cols_array(&[
+		0.4122214708, 0.2119034982, 0.0883024619,
+		0.5363325363, 0.6806995451, 0.2817188376,
+		0.0514459929, 0.1073969566, 0.6299787005]);
+	#[cfg_attr(rustfmt, rustfmt_skip)]
+	static B: Mat3 = Mat3::from_cols_array(&[
+		0.2104542553, 1.9779984951, 0.0259040371,
+		0.7936177850, -2.4285922050, 0.7827717662,
+		-0.0040720468, 0.4505937099, -0.8086757660]);
+	let v = A * srgb;
+	return B * vec3(v.x.cbrt(), v.y.cbrt(), v.z.cbrt());
+}
+
 fn linear_srgb_to_rgb(srgb: Vec3) -> Vec3 {
 	return vec3(srgb_gamma(srgb.x), srgb_gamma(srgb.y), srgb_gamma(srgb.z));
 }
 
+fn rgb_to_linear_srgb(rgb: Vec3) -> Vec3 {
+	return vec3(
+		srgb_inverse_gamma(rgb.x),
+		srgb_inverse_gamma(rgb.y),
+		srgb_inverse_gamma(rgb.z),
+	);
+}
+
 fn srgb_gamma(x: f32) -> f32 {
 	if x >= 0.0031308 {
 		return 1.055 * x.powf(1.0 / 2.4) - 0.055;
 	}
 	return 12.92 * x;
 }
+
+fn srgb_inverse_gamma(x: f32) -> f32 {
+	if x >= 0.04045 {
+		return ((x + 0.055) / 1.055).powf(2.4);
+	}
+	return x / 12.92;
+}
+
+pub fn oklab_to_rgb_f64(lab: DVec3) -> DVec3 {
+	return linear_srgb_to_rgb_f64(oklab_to_linear_srgb_f64(lab));
+}
+
+pub fn rgb_to_oklab_f64(rgb: DVec3) -> DVec3 {
+	return linear_srgb_to_oklab_f64(rgb_to_linear_srgb_f64(rgb));
+}
+
+pub fn oklab_to_oklch_f64(lab: DVec3) -> DVec3 {
+	let ab = lab.yz();
+	dvec3(lab.x, ab.length(), ab.y.atan2(ab.x))
+}
+
+pub fn oklch_to_oklab_f64(lch: DVec3) -> DVec3 {
+	let (sin, cos) = lch.z.sin_cos();
+	dvec3(lch.x, lch.y * cos, lch.y * sin)
+}
+
+// The published Oklab matrices are only given to 10 digits, so their products are not quite
+// inverses. To get round trips accurate to `f64` precision, we invert the forward matrices instead.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static LINEAR_SRGB_TO_LMS: DMat3 = DMat3::from_cols_array(&[
+	0.4122214708, 0.2119034982, 0.0883024619,
+	0.5363325363, 0.6806995451, 0.2817188376,
+	0.0514459929, 0.1073969566, 0.6299787005]);
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static LMS_TO_OKLAB: DMat3 = DMat3::from_cols_array(&[
+	0.2104542553, 1.9779984951, 0.0259040371,
+	0.7936177850, -2.4285922050, 0.7827717662,
+	-0.0040720468, 0.4505937099, -0.8086757660]);
+static LMS_TO_LINEAR_SRGB: LazyLock<DMat3> = LazyLock::new(|| LINEAR_SRGB_TO_LMS.inverse());
+static OKLAB_TO_LMS: LazyLock<DMat3> = LazyLock::new(|| LMS_TO_OKLAB.inverse());
+
+fn oklab_to_linear_srgb_f64(lab: DVec3) -> DVec3 {
+	let v = *OKLAB_TO_LMS * lab;
+	return *LMS_TO_LINEAR_SRGB * (v * v * v);
+}
+
+fn linear_srgb_to_oklab_f64(srgb: DVec3) -> DVec3 {
+	let v = LINEAR_SRGB_TO_LMS * srgb;
+	return LMS_TO_OKLAB * dvec3(v.x.cbrt(), v.y.cbrt(), v.z.cbrt());
+}
+
+fn linear_srgb_to_rgb_f64(srgb: DVec3) -> DVec3 {
+	return dvec3(
+		srgb_gamma_f64(srgb.x),
+		srgb_gamma_f64(srgb.y),
+		srgb_gamma_f64(srgb.z),
+	);
+}
+
+fn rgb_to_linear_srgb_f64(rgb: DVec3) -> DVec3 {
+	return dvec3(
+		srgb_inverse_gamma_f64(rgb.x),
+		srgb_inverse_gamma_f64(rgb.y),
+		srgb_inverse_gamma_f64(rgb.z),
+	);
+}
+
+fn srgb_gamma_f64(x: f64) -> f64 {
+	if x >= 0.0031308 {
+		return 1.055 * x.powf(1.0 / 2.4) - 0.055;
+	}
+	return 12.92 * x;
+}
+
+fn srgb_inverse_gamma_f64(x: f64) -> f64 {
+	if x >= 0.04045 {
+		return ((x + 0.055) / 1.055).powf(2.4);
+	}
+	return x / 12.92;
+}
+
+// How far outside of `[0, 1]` linear sRGB channels may be and still count as in gamut, allowing for
+// rounding.
+const GAMUT_TOLERANCE: f64 = 1e-6;
+
+// Bisecting chroma this many times finds the edge of the gamut to well within `f32` precision.
+const GAMUT_CLAMP_ITERATIONS: usize = 40;
+
+pub fn in_srgb_gamut(lab: Vec3) -> bool {
+	in_srgb_gamut_f64(lab.as_dvec3())
+}
+
+pub fn in_srgb_gamut_f64(lab: DVec3) -> bool {
+	srgb_gamut_excess_f64(lab) <= GAMUT_TOLERANCE
+}
+
+// How far the furthest linear sRGB channel of `lab` is outside of `[0, 1]`, or negative if all are
+// inside.
+fn srgb_gamut_excess_f64(lab: DVec3) -> f64 {
+	let rgb = oklab_to_linear_srgb_f64(lab);
+	(-rgb).max(rgb - 1.0).max_element()
+}
+
+pub fn clamp_to_srgb_gamut(lab: Vec3) -> Vec3 {
+	clamp_to_srgb_gamut_f64(lab.as_dvec3()).as_vec3()
+}
+
+// The color in the sRGB gamut with the lightness and hue of `lab`, projecting it toward the
+// lightness axis by reducing its chroma. Unlike clamping each channel, this keeps the hue, and is
+// how the color picker shows colors outside of the gamut. Lightness is clamped to `[0, 1]` first,
+// since no color outside of that is in gamut.
+pub fn clamp_to_srgb_gamut_f64(lab: DVec3) -> DVec3 {
+	if in_srgb_gamut_f64(lab) {
+		return lab;
+	}
+	let lightness = lab.x.clamp(0.0, 1.0);
+	let scaled = |s: f64| dvec3(lightness, s * lab.y, s * lab.z);
+	let (mut inside, mut outside) = (0.0, 1.0);
+	for _ in 0..GAMUT_CLAMP_ITERATIONS {
+		let s = 0.5 * (inside + outside);
+		// Strictly within the gamut, so that it stays within it once rounded.
+		if srgb_gamut_excess_f64(scaled(s)) <= 0.0 {
+			inside = s;
+		} else {
+			outside = s;
+		}
+	}
+	scaled(inside)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test::assert_near;
+	use itertools::Itertools;
+
+	fn rgb_grid() -> impl Iterator<Item = DVec3> {
+		let steps = (0..=8).map(|i| i as f64 / 8.0);
+		steps
+			.clone()
+			.cartesian_product(steps.clone())
+			.cartesian_product(steps)
+			.map(|((r, g), b)| dvec3(r, g, b))
+	}
+
+	#[test]
+	fn reference_values() {
+		// Reference values computed with https://github.com/color-js/color.js.
+		let cases = [
+			(dvec3(0.0, 0.0, 0.0), dvec3(0.0, 0.0, 0.0)),
+			(dvec3(1.0, 1.0, 1.0), dvec3(1.0, 0.0, 0.0)),
+			(dvec3(1.0, 0.0, 0.0), dvec3(0.627955, 0.224863, 0.125846)),
+			(dvec3(0.0, 1.0, 0.0), dvec3(0.866440, -0.233888, 0.179498)),
+			(dvec3(0.0, 0.0, 1.0), dvec3(0.452014, -0.032457, -0.311528)),
+		];
+		for (rgb, lab) in cases {
+			assert_near!(rgb_to_oklab_f64(rgb), lab, 1e-5);
+			assert_near!(oklab_to_rgb_f64(lab), rgb, 1e-4);
+			assert_near!(rgb_to_oklab(rgb.as_vec3()), lab.as_vec3(), 1e-4);
+			assert_near!(oklab_to_rgb(lab.as_vec3()), rgb.as_vec3(), 1e-3);
+		}
+	}
+
+	#[test]
+	fn rgb_round_trip() {
+		for rgb in rgb_grid() {
+			assert_near!(oklab_to_rgb_f64(rgb_to_oklab_f64(rgb)), rgb, 1e-9);
+			let rgb = rgb.as_vec3();
+			assert_near!(oklab_to_rgb(rgb_to_oklab(rgb)), rgb, 1e-4);
+		}
+	}
+
+	#[test]
+	fn oklch_round_trip() {
+		for rgb in rgb_grid() {
+			let lab = rgb_to_oklab_f64(rgb);
+			let lch = oklab_to_oklch_f64(lab);
+			assert!(lch.y >= 0.0);
+			assert_near!(oklch_to_oklab_f64(lch), lab, 1e-12);
+			assert_near!(oklab_to_rgb_f64(oklch_to_oklab_f64(lch)), rgb, 1e-9);
+
+			let lab = lab.as_vec3();
+			assert_near!(oklch_to_oklab(oklab_to_oklch(lab)), lab, 1e-6);
+		}
+	}
+
+	#[test]
+	fn extreme_values() {
+		// Out-of-gamut and out-of-range inputs should still produce finite, invertible results.
+		for rgb in [
+			dvec3(-1.0, 0.0, 0.0),
+			dvec3(2.0, 2.0, 2.0),
+			dvec3(-0.5, 1.5, 0.25),
+			dvec3(1e-12, 1e-12, 1e-12),
+			dvec3(100.0, 0.0, 100.0),
+		] {
+			let lab = rgb_to_oklab_f64(rgb);
+			assert!(lab.is_finite(), "{rgb} -> {lab}");
+			assert_near!(oklab_to_rgb_f64(lab), rgb, 1e-6 * rgb.abs().max_element().max(1.0));
+
+			let rgb = rgb.as_vec3();
+			let lab = rgb_to_oklab(rgb);
+			assert!(lab.is_finite(), "{rgb} -> {lab}");
+		}
+
+		for lab in [
+			dvec3(0.0, 0.4, -0.4),
+			dvec3(1.0, -0.4, 0.4),
+			dvec3(-0.1, 0.0, 0.0),
+			dvec3(2.0, 0.0, 0.0),
+		] {
+			assert!(oklab_to_rgb_f64(lab).is_finite());
+			assert!(oklab_to_rgb(lab.as_vec3()).is_finite());
+		}
+
+		// Hue is undefined for achromatic colors, but must not be NaN.
+		let lch = oklab_to_oklch_f64(DVec3::X);
+		assert_eq!(lch.y, 0.0);
+		assert!(lch.z.is_finite());
+	}
+
+	#[test]
+	fn hsv_round_trip() {
+		let cases = [
+			(vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 1.0)),
+			(vec3(0.0, 0.5, 0.0), vec3(120.0, 1.0, 0.5)),
+			(vec3(0.5, 0.5, 1.0), vec3(240.0, 0.5, 1.0)),
+			(vec3(1.0, 0.0, 1.0), vec3(300.0, 1.0, 1.0)),
+			(vec3(0.25, 0.25, 0.25), vec3(0.0, 0.0, 0.25)),
+			(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)),
+		];
+		for (rgb, hsv) in cases {
+			assert_near!(rgb_to_hsv(rgb), hsv, 1e-5);
+			assert_near!(hsv_to_rgb(hsv), rgb, 1e-6);
+		}
+		for rgb in rgb_grid() {
+			let rgb = rgb.as_vec3();
+			let hsv = rgb_to_hsv(rgb);
+			assert!((0.0..360.0).contains(&hsv.x), "{rgb} -> {hsv}");
+			assert_near!(hsv_to_rgb(hsv), rgb, 1e-5);
+		}
+		// Hue wraps around.
+		assert_near!(
+			hsv_to_rgb(vec3(-120.0, 1.0, 1.0)),
+			vec3(0.0, 0.0, 1.0),
+			1e-6
+		);
+	}
+
+	#[test]
+	fn hex_round_trip() {
+		assert_eq!(rgb_to_hex(vec3(1.0, 0.5, 0.0)), "#ff8000");
+		assert_eq!(rgb_to_hex(vec3(-0.2, 1.3, 0.0)), "#00ff00");
+		for rgb in rgb_grid() {
+			let rgb = rgb.as_vec3();
+			let parsed = crate::util::try_color_from_css_string(&rgb_to_hex(rgb)).unwrap();
+			assert_near!(parsed.xyz(), rgb, 0.5 / 255.0 + 1e-6);
+			assert_eq!(parsed.w, 1.0);
+		}
+	}
+
+	#[test]
+	fn gamut_clamping_keeps_lightness_and_hue() {
+		// Colors in gamut are left as they are.
+		for rgb in rgb_grid() {
+			let lab = rgb_to_oklab_f64(rgb);
+			assert!(in_srgb_gamut_f64(lab), "{rgb}");
+			assert_eq!(clamp_to_srgb_gamut_f64(lab), lab);
+		}
+
+		for lab in [
+			dvec3(0.7, 0.4, 0.0),
+			dvec3(0.3, -0.2, -0.3),
+			dvec3(0.95, 0.1, 0.2),
+		] {
+			assert!(!in_srgb_gamut_f64(lab));
+			let clamped = clamp_to_srgb_gamut_f64(lab);
+			assert!(in_srgb_gamut_f64(clamped), "{lab} -> {clamped}");
+			let (lch, clamped_lch) = (oklab_to_oklch_f64(lab), oklab_to_oklch_f64(clamped));
+			assert_eq!(clamped_lch.x, lch.x);
+			assert!((clamped_lch.z - lch.z).abs() < 1e-12, "{lab} -> {clamped}");
+			// On the edge of the gamut, not short of it.
+			assert!(clamped_lch.y < lch.y);
+			assert!(!in_srgb_gamut_f64(oklch_to_oklab_f64(
+				clamped_lch + dvec3(0.0, 1e-4, 0.0)
+			)));
+			assert!(in_srgb_gamut(clamp_to_srgb_gamut(lab.as_vec3())));
+		}
+
+		// Past white, only white is in gamut.
+		assert_near!(
+			clamp_to_srgb_gamut_f64(dvec3(1.2, 0.1, 0.0)),
+			dvec3(1.0, 0.0, 0.0),
+			1e-6
+		);
+	}
+
+	#[test]
+	fn f32_gradient_matches_f64() {
+		// Sample a gradient the way the color picker does and compare the f32 path against f64.
+		let a = rgb_to_oklab_f64(dvec3(0.1, 0.2, 0.9));
+		let b = rgb_to_oklab_f64(dvec3(0.95, 0.85, 0.05));
+		let max_error = (0..=1000)
+			.map(|i| i as f64 / 1000.0)
+			.map(|t| {
+				let lab = a.lerp(b, t);
+				let expected = oklab_to_rgb_f64(lab);
+				(oklab_to_rgb(lab.as_vec3()).as_dvec3() - expected)
+					.abs()
+					.max_element()
+			})
+			.fold(0.0, f64::max);
+		assert!(max_error > 0.0);
+		assert!(max_error < 1e-4, "{max_error}");
+	}
+}
@@ -0,0 +1,168 @@
+use glam::*;
+
+// Touch screens report each finger as a separate pointer. Two fingers moving together pan the
+// canvas, moving apart or together zoom it, and twisting rotates it, keeping the canvas point
+// between them fixed.
+
+/// The change in a two-pointer gesture since the previous update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PinchUpdate {
+	/// The midpoint of the two pointers, in screen pixels.
+	pub center: DVec2,
+	/// How far the midpoint moved, in screen pixels.
+	pub translation: DVec2,
+	/// The ratio of the distance between the pointers to what it was.
+	pub scale: f64,
+	/// How far the line between the pointers turned, in radians.
+	pub rotation: f64,
+}
+
+/// Tracks the pointers active on an element to recognize two-finger gestures.
+#[derive(Debug, Clone, Default)]
+pub struct ActivePointers {
+	// In the order they went down. Only the first two take part in a gesture.
+	pointers: Vec<(i32, DVec2)>,
+	// Whether the pointers have formed a gesture since they were all last lifted.
+	gesture: bool,
+}
+
+impl ActivePointers {
+	pub fn down(&mut self, id: i32, position: DVec2) {
+		self.pointers.retain(|&(other, _)| other != id);
+		self.pointers.push((id, position));
+		self.gesture |= self.pointers.len() >= 2;
+	}
+
+	/// Removes a pointer that was lifted or cancelled.
+	pub fn up(&mut self, id: i32) {
+		self.pointers.retain(|&(other, _)| other != id);
+		if self.pointers.is_empty() {
+			self.gesture = false;
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.pointers.len()
+	}
+
+	/// Whether more than one pointer has been down at once since they were all last lifted. A
+	/// gesture continues until every pointer is lifted, so lifting one finger doesn't start drawing.
+	pub fn is_gesture(&self) -> bool {
+		self.gesture
+	}
+
+	/// Records that a pointer moved, returning how the gesture changed if it is one of the first two.
+	pub fn moved(&mut self, id: i32, position: DVec2) -> Option<PinchUpdate> {
+		let index = self.pointers.iter().position(|&(other, _)| other == id)?;
+		let before = self.pair();
+		self.pointers[index].1 = position;
+		let (a0, b0) = before?;
+		if index >= 2 {
+			return None;
+		}
+		let (a1, b1) = self.pair()?;
+
+		let center = 0.5 * (a1 + b1);
+		let previous_distance = a0.distance(b0);
+		let (scale, rotation) = if previous_distance > f64::EPSILON {
+			(
+				a1.distance(b1) / previous_distance,
+				(b0 - a0).angle_between(b1 - a1),
+			)
+		} else {
+			(1.0, 0.0)
+		};
+		Some(PinchUpdate {
+			center,
+			translation: center - 0.5 * (a0 + b0),
+			scale,
+			rotation,
+		})
+	}
+
+	fn pair(&self) -> Option<(DVec2, DVec2)> {
+		match self.pointers.as_slice() {
+			[(_, a), (_, b), ..] => Some((*a, *b)),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn single_pointer_is_not_a_gesture() {
+		let mut pointers = ActivePointers::default();
+		pointers.down(1, dvec2(10.0, 10.0));
+		assert!(!pointers.is_gesture());
+		assert_eq!(pointers.moved(1, dvec2(20.0, 10.0)), None);
+		assert_eq!(pointers.moved(2, dvec2(20.0, 10.0)), None);
+	}
+
+	#[test]
+	fn pinch_and_pan() {
+		let mut pointers = ActivePointers::default();
+		pointers.down(1, dvec2(0.0, 0.0));
+		pointers.down(2, dvec2(100.0, 0.0));
+		assert!(pointers.is_gesture());
+
+		// Spreading one finger zooms about the new midpoint.
+		let update = pointers.moved(2, dvec2(200.0, 0.0)).unwrap();
+		assert_eq!(update.center, dvec2(100.0, 0.0));
+		assert_eq!(update.translation, dvec2(50.0, 0.0));
+		assert_eq!(update.scale, 2.0);
+		assert_eq!(update.rotation, 0.0);
+
+		// Moving a finger parallel to the other pans.
+		let update = pointers.moved(1, dvec2(0.0, 30.0)).unwrap();
+		assert_eq!(update.translation, dvec2(0.0, 15.0));
+		assert!(update.scale > 1.0);
+
+		// A third finger is ignored.
+		pointers.down(3, dvec2(50.0, 50.0));
+		assert_eq!(pointers.moved(3, dvec2(60.0, 60.0)), None);
+	}
+
+	#[test]
+	fn twist() {
+		let mut pointers = ActivePointers::default();
+		pointers.down(1, dvec2(-100.0, 0.0));
+		pointers.down(2, dvec2(100.0, 0.0));
+
+		// Turning both fingers a quarter turn about their midpoint rotates without zooming.
+		let first = pointers.moved(1, dvec2(0.0, -100.0)).unwrap();
+		let second = pointers.moved(2, dvec2(0.0, 100.0)).unwrap();
+		assert_eq!(second.center, DVec2::ZERO);
+		approx::assert_abs_diff_eq!(first.scale * second.scale, 1.0, epsilon = 1e-9);
+		approx::assert_abs_diff_eq!(
+			first.rotation + second.rotation,
+			std::f64::consts::FRAC_PI_2,
+			epsilon = 1e-9
+		);
+	}
+
+	#[test]
+	fn gesture_lasts_until_all_lifted() {
+		let mut pointers = ActivePointers::default();
+		pointers.down(1, DVec2::ZERO);
+		pointers.down(2, DVec2::ONE);
+		pointers.up(2);
+		assert!(pointers.is_gesture());
+		assert_eq!(pointers.moved(1, DVec2::ONE), None);
+		pointers.up(1);
+		assert_eq!(pointers.len(), 0);
+		assert!(!pointers.is_gesture());
+	}
+
+	#[test]
+	fn coincident_pointers_do_not_zoom() {
+		let mut pointers = ActivePointers::default();
+		pointers.down(1, DVec2::ONE);
+		pointers.down(2, DVec2::ONE);
+		let update = pointers.moved(2, dvec2(5.0, 1.0)).unwrap();
+		assert_eq!(update.scale, 1.0);
+		assert_eq!(update.rotation, 0.0);
+	}
+}
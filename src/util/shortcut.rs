@@ -0,0 +1,241 @@
+// Keyboard shortcuts, kept apart from how key events are received so they can be tested.
+
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// A key with modifiers, such as `Ctrl+Z`. Keys are as in `KeyboardEvent.key`, with letters
+/// compared without regard to case. Ctrl and the macOS Command key are treated alike.
+///
+/// Shift is ignored for keys which aren't letters, since it changes the key itself. Shift+[ is `{`
+/// on most layouts, for example.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+	key: String,
+	ctrl: bool,
+	alt: bool,
+	shift: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ShortcutError {
+	#[error("shortcut has no key")]
+	MissingKey,
+	#[error("unknown modifier {0:?}")]
+	UnknownModifier(String),
+}
+
+impl Shortcut {
+	pub fn new(key: &str, ctrl: bool, alt: bool, shift: bool) -> Self {
+		// Named keys, such as `ArrowUp`, are already consistently cased.
+		let is_named = key.chars().count() > 1;
+		let key = match is_named {
+			true => key.to_string(),
+			false => key.to_lowercase(),
+		};
+		let is_letter = !is_named && key.chars().all(char::is_alphabetic);
+		Self {
+			shift: shift && (is_letter || is_named),
+			key,
+			ctrl,
+			alt,
+		}
+	}
+
+	/// Whether this is only a modifier, which can't be a shortcut on its own.
+	pub fn is_modifier(&self) -> bool {
+		matches!(
+			self.key.as_str(),
+			"Control" | "Shift" | "Alt" | "Meta" | "AltGraph" | "OS"
+		)
+	}
+}
+
+impl FromStr for Shortcut {
+	type Err = ShortcutError;
+
+	/// Parses modifiers and a key separated by `+`, such as `Ctrl+Shift+Z`. A `+` key is written
+	/// last, as in `Ctrl++`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (modifiers, key) = match s.strip_suffix('+') {
+			Some(rest) if rest.is_empty() || rest.ends_with('+') => (rest, "+"),
+			_ => match s.rsplit_once('+') {
+				Some((modifiers, key)) => (modifiers, key),
+				None => ("", s),
+			},
+		};
+		if key.is_empty() {
+			return Err(ShortcutError::MissingKey);
+		}
+		let (mut ctrl, mut alt, mut shift) = (false, false, false);
+		for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+			match modifier.to_lowercase().as_str() {
+				"ctrl" | "control" | "cmd" | "meta" => ctrl = true,
+				"alt" | "option" => alt = true,
+				"shift" => shift = true,
+				_ => return Err(ShortcutError::UnknownModifier(modifier.to_string())),
+			}
+		}
+		Ok(Self::new(key, ctrl, alt, shift))
+	}
+}
+
+impl fmt::Display for Shortcut {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.ctrl {
+			write!(f, "Ctrl+")?;
+		}
+		if self.alt {
+			write!(f, "Alt+")?;
+		}
+		if self.shift {
+			write!(f, "Shift+")?;
+		}
+		let mut chars = self.key.chars();
+		match (chars.next(), chars.as_str()) {
+			(Some(first), rest) => write!(f, "{}{rest}", first.to_uppercase()),
+			(None, _) => Ok(()),
+		}
+	}
+}
+
+/// Which shortcuts trigger which actions. Each action may have several shortcuts, but each shortcut
+/// triggers at most one action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortcutRegistry<A> {
+	bindings: Vec<(Shortcut, A)>,
+}
+
+impl<A> Default for ShortcutRegistry<A> {
+	fn default() -> Self {
+		Self {
+			bindings: Vec::new(),
+		}
+	}
+}
+
+impl<A: Copy + PartialEq> ShortcutRegistry<A> {
+	/// Binds `shortcut` to `action`, in place of whatever it triggered before.
+	pub fn bind(&mut self, shortcut: Shortcut, action: A) {
+		self.bindings.retain(|(bound, _)| *bound != shortcut);
+		self.bindings.push((shortcut, action));
+	}
+
+	/// Makes `shortcut` the only one for `action`.
+	pub fn rebind(&mut self, action: A, shortcut: Shortcut) {
+		self.bindings.retain(|(_, bound)| *bound != action);
+		self.bind(shortcut, action);
+	}
+
+	/// The action `shortcut` triggers, if any.
+	pub fn action(&self, shortcut: &Shortcut) -> Option<A> {
+		self
+			.bindings
+			.iter()
+			.find(|(bound, _)| bound == shortcut)
+			.map(|&(_, action)| action)
+	}
+
+	/// The shortcuts for `action`, in the order they were bound.
+	pub fn shortcuts(&self, action: A) -> impl Iterator<Item = &Shortcut> {
+		self
+			.bindings
+			.iter()
+			.filter(move |(_, bound)| *bound == action)
+			.map(|(shortcut, _)| shortcut)
+	}
+}
+
+/// Steps `value` by `steps` on a logarithmic scale, where `steps_per_doubling` steps double it, and
+/// clamps the result to `min..=max`. Values below `min`, such as zero, step up from `min`.
+pub fn step_logarithmically(
+	value: f64,
+	steps: f64,
+	steps_per_doubling: f64,
+	min: f64,
+	max: f64,
+) -> f64 {
+	(value.max(min) * (steps / steps_per_doubling).exp2()).clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_shortcuts() {
+		assert_eq!("[".parse(), Ok(Shortcut::new("[", false, false, false)));
+		assert_eq!(
+			"Ctrl+Shift+Z".parse(),
+			Ok(Shortcut::new("z", true, false, true))
+		);
+		assert_eq!("cmd+z".parse(), Ok(Shortcut::new("Z", true, false, false)));
+		assert_eq!("Ctrl++".parse(), Ok(Shortcut::new("+", true, false, false)));
+		assert_eq!("+".parse(), Ok(Shortcut::new("+", false, false, false)));
+		assert_eq!(
+			"Hyper+Z".parse::<Shortcut>(),
+			Err(ShortcutError::UnknownModifier("Hyper".to_string()))
+		);
+		assert_eq!("".parse::<Shortcut>(), Err(ShortcutError::MissingKey));
+	}
+
+	#[test]
+	fn shift_only_matters_for_letters_and_named_keys() {
+		assert_eq!(
+			Shortcut::new("{", false, false, true),
+			Shortcut::new("{", false, false, false)
+		);
+		assert_ne!(
+			Shortcut::new("Z", false, false, true),
+			Shortcut::new("z", false, false, false)
+		);
+		assert_ne!(
+			Shortcut::new("ArrowUp", false, false, true),
+			Shortcut::new("ArrowUp", false, false, false)
+		);
+	}
+
+	#[test]
+	fn display_round_trips() {
+		for text in ["[", "Ctrl+Shift+Z", "Alt+ArrowUp", "Ctrl++"] {
+			let shortcut: Shortcut = text.parse().unwrap();
+			assert_eq!(shortcut.to_string(), text);
+			assert_eq!(shortcut.to_string().parse(), Ok(shortcut));
+		}
+	}
+
+	#[test]
+	fn registry() {
+		let mut registry = ShortcutRegistry::default();
+		let open_bracket: Shortcut = "[".parse().unwrap();
+		let close_bracket: Shortcut = "]".parse().unwrap();
+		let minus: Shortcut = "-".parse().unwrap();
+		registry.bind(open_bracket.clone(), 1);
+		registry.bind(minus.clone(), 1);
+		registry.bind(close_bracket.clone(), 2);
+		assert_eq!(registry.action(&open_bracket), Some(1));
+		assert_eq!(
+			registry.shortcuts(1).collect::<Vec<_>>(),
+			[&open_bracket, &minus]
+		);
+
+		// A shortcut triggers only the action it was last bound to.
+		registry.bind(minus.clone(), 2);
+		assert_eq!(registry.action(&minus), Some(2));
+		assert_eq!(registry.shortcuts(1).collect::<Vec<_>>(), [&open_bracket]);
+
+		registry.rebind(2, open_bracket.clone());
+		assert_eq!(registry.action(&open_bracket), Some(2));
+		assert_eq!(registry.action(&close_bracket), None);
+		assert_eq!(registry.shortcuts(1).count(), 0);
+	}
+
+	#[test]
+	fn steps_logarithmically() {
+		approx::assert_abs_diff_eq!(step_logarithmically(10.0, 4.0, 4.0, 1.0, 100.0), 20.0);
+		approx::assert_abs_diff_eq!(step_logarithmically(10.0, -8.0, 4.0, 1.0, 100.0), 2.5);
+		assert_eq!(step_logarithmically(80.0, 4.0, 4.0, 1.0, 100.0), 100.0);
+		approx::assert_abs_diff_eq!(step_logarithmically(0.0, 2.0, 2.0, 0.05, 2.0), 0.1);
+	}
+}
@@ -0,0 +1,256 @@
+//! Runs strokes through each interpolator and measures how long fitting takes, how far the curve
+//! strays from the pointer, and how smoothly it turns, to guide the choice of defaults and to notice
+//! when the solver gets slower.
+//!
+//! Recorded strokes come from action logs exported from the app. The `compare_interpolators` example
+//! prints a report for them, or for [`Trace::synthetic`] strokes without any. No recorded log is
+//! checked in yet, so the test measures a generated one instead.
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use crate::engine::{ActionLog, LoggedAction};
+
+use super::input_interpolate::{
+	Bezier, CubicInterpolator, InputPoint, InputSplineBuilder, Interpolator, LinearInterpolator,
+};
+
+/// How many samples each fitted segment is measured at.
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+/// A stroke as the pointer reported it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+	pub name: String,
+	pub points: Vec<InputPoint>,
+}
+
+impl Trace {
+	/// The strokes of `log`, named after `name` and their position in it.
+	pub fn from_action_log(name: &str, log: &ActionLog) -> Vec<Self> {
+		let strokes = log.actions().filter_map(|action| match action {
+			LoggedAction::Stroke(stroke) => Some(stroke),
+			_ => None,
+		});
+		strokes
+			.enumerate()
+			.map(|(index, stroke)| Self {
+				name: format!("{name}#{index}"),
+				points: stroke
+					.points
+					.iter()
+					.map(|point| InputPoint {
+						t: point.t,
+						x: point.x,
+						y: point.y,
+						pressure: point.pressure,
+					})
+					.collect(),
+			})
+			.collect()
+	}
+
+	/// Strokes standing in for recorded ones, sampled at a tablet's 120 Hz with a little jitter: a
+	/// slow spiral, a quick zigzag, and a slow straight line.
+	pub fn synthetic() -> Vec<Self> {
+		let mut rng = fastrand::Rng::with_seed(7);
+		let mut trace = |name: &str, duration: f32, path: &dyn Fn(f32) -> (f32, f32)| {
+			let count = (duration * 120.0) as usize;
+			let points = (0..=count)
+				.map(|i| {
+					let t = i as f32 / 120.0;
+					let (x, y) = path(t);
+					InputPoint {
+						t,
+						x: x + rng.f32() - 0.5,
+						y: y + rng.f32() - 0.5,
+						pressure: (t / duration).min(0.5) + 0.25,
+					}
+				})
+				.collect();
+			Self {
+				name: name.to_string(),
+				points,
+			}
+		};
+		vec![
+			trace("spiral", 2.0, &|t| {
+				let (radius, angle) = (20.0 + 90.0 * t, 1.5 * std::f32::consts::TAU * t / 2.0);
+				(radius * angle.cos(), radius * angle.sin())
+			}),
+			trace("zigzag", 1.0, &|t| {
+				let phase = (t / 0.2).fract();
+				(1500.0 * t, 80.0 * (2.0 * phase - 1.0).abs())
+			}),
+			trace("line", 2.0, &|t| (50.0 * t, 20.0 * t)),
+		]
+	}
+}
+
+/// How an interpolator fared on a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmoothingReport {
+	pub interpolator: String,
+	pub trace: String,
+	pub segments: usize,
+	/// The total time spent adding points and finishing the stroke.
+	pub fit_time: Duration,
+	/// The farthest, in pixels, any point the pointer reported is from the curve at the same time.
+	/// Points before the first segment aren't counted.
+	pub max_deviation: f32,
+	/// The root mean square change in direction between consecutive samples of the curve, in
+	/// radians. Straight lines and circles have none, and kinks and wobbles add to it.
+	pub roughness: f32,
+}
+
+/// Fits `trace` with `builder`, as the canvas would, and measures the result.
+pub fn measure<I: Interpolator>(
+	interpolator: &str,
+	mut builder: InputSplineBuilder<I>,
+	trace: &Trace,
+) -> SmoothingReport {
+	let start = Instant::now();
+	let mut segments: Vec<Bezier<glam::Vec3>> = trace
+		.points
+		.iter()
+		.filter_map(|&point| builder.add_point(point))
+		.collect();
+	segments.extend(builder.finish());
+	let fit_time = start.elapsed();
+
+	let max_deviation = trace
+		.points
+		.iter()
+		.filter_map(|point| {
+			let segment = segments
+				.iter()
+				.find(|segment| segment.time_range().contains(&point.t))?;
+			let fitted = segment.value_at(point.t).truncate();
+			Some(fitted.distance(glam::vec2(point.x, point.y)))
+		})
+		.fold(0.0, f32::max);
+
+	let samples = segments
+		.iter()
+		.flat_map(|segment| {
+			let (t0, t1) = (*segment.time_range().start(), *segment.time_range().end());
+			(0..SAMPLES_PER_SEGMENT).map(move |i| {
+				let s = i as f32 / SAMPLES_PER_SEGMENT as f32;
+				segment.value_at(t0 + s * (t1 - t0)).truncate()
+			})
+		})
+		.collect::<Vec<_>>();
+	let directions = samples
+		.windows(2)
+		.map(|pair| pair[1] - pair[0])
+		// The curve may pause, where it has no direction.
+		.filter(|step| step.length() > 1e-3)
+		.collect::<Vec<_>>();
+	let turns = directions
+		.windows(2)
+		.map(|pair| pair[0].angle_to(pair[1]))
+		.collect::<Vec<_>>();
+	let roughness = match turns.len() {
+		0 => 0.0,
+		count => (turns.iter().map(|turn| turn * turn).sum::<f32>() / count as f32).sqrt(),
+	};
+
+	SmoothingReport {
+		interpolator: interpolator.to_string(),
+		trace: trace.name.clone(),
+		segments: segments.len(),
+		fit_time,
+		max_deviation,
+		roughness,
+	}
+}
+
+/// Measures every interpolator on each of `traces`.
+pub fn compare_interpolators(traces: &[Trace]) -> Vec<SmoothingReport> {
+	traces
+		.iter()
+		.flat_map(|trace| {
+			[
+				measure("linear", InputSplineBuilder::new(LinearInterpolator), trace),
+				measure("cubic", InputSplineBuilder::new(CubicInterpolator), trace),
+				measure(
+					"cubic (adaptive)",
+					InputSplineBuilder::new(CubicInterpolator).with_adaptive_interval(6.0),
+					trace,
+				),
+			]
+		})
+		.collect()
+}
+
+/// Formats `reports` as a table, one row per report.
+pub fn format_reports(reports: &[SmoothingReport]) -> String {
+	let mut table = format!(
+		"{:<12} {:<18} {:>8} {:>12} {:>14} {:>10}\n",
+		"trace", "interpolator", "segments", "fit time", "max deviation", "roughness"
+	);
+	for report in reports {
+		writeln!(
+			table,
+			"{:<12} {:<18} {:>8} {:>9.3} ms {:>11.2} px {:>10.4}",
+			report.trace,
+			report.interpolator,
+			report.segments,
+			report.fit_time.as_secs_f64() * 1000.0,
+			report.max_deviation,
+			report.roughness,
+		)
+		.unwrap();
+	}
+	table
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn linear_segments_pass_through_fitted_points() {
+		let points = (0..=8)
+			.map(|i| InputPoint {
+				t: i as f32 * 0.25,
+				x: i as f32 * 10.0,
+				y: if i % 2 == 0 { 0.0 } else { 10.0 },
+				pressure: 1.0,
+			})
+			.collect();
+		let trace = Trace {
+			name: "zigzag".to_string(),
+			points,
+		};
+		let report = measure(
+			"linear",
+			InputSplineBuilder::new(LinearInterpolator),
+			&trace,
+		);
+		assert_eq!(report.segments, 8);
+		assert!(report.max_deviation < 1e-3, "{report:?}");
+		// Each corner turns by 90 degrees.
+		assert!(report.roughness > 0.1, "{report:?}");
+	}
+
+	#[test]
+	fn compares_each_interpolator() -> anyhow::Result<()> {
+		// Generated to imitate a tablet's uneven timing and pressure, not recorded from one. A
+		// log exported from a real tablet should replace it.
+		let path = "test/input/synthetic-tablet-strokes.json";
+		let log = ActionLog::decode(&std::fs::read_to_string(path)?)?;
+		let mut traces = Trace::from_action_log("synthetic-tablet", &log);
+		assert_eq!(traces.len(), 2);
+		traces.extend(Trace::synthetic());
+
+		let reports = compare_interpolators(&traces);
+		assert_eq!(reports.len(), 3 * traces.len());
+		for report in &reports {
+			assert!(report.segments > 0, "{report:?}");
+			assert!(report.max_deviation.is_finite(), "{report:?}");
+			assert!(report.roughness.is_finite(), "{report:?}");
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,103 @@
+use super::input_interpolate::InputPoint;
+use std::collections::VecDeque;
+
+// Comparing what the pointer reported with what the stabilizer made of it is the quickest way to
+// tell a jittery tablet from an over- or under-smoothed stroke.
+
+/// The most recent points going into and coming out of a [`super::input_interpolate::Stabilizer`],
+/// kept for graphing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeTrace {
+	// In seconds. Points older than this, relative to the newest, are dropped.
+	duration: f32,
+	raw: VecDeque<InputPoint>,
+	fitted: VecDeque<InputPoint>,
+}
+
+impl StrokeTrace {
+	/// Creates a trace keeping `duration` seconds of points.
+	pub fn new(duration: f32) -> Self {
+		Self {
+			duration,
+			raw: VecDeque::new(),
+			fitted: VecDeque::new(),
+		}
+	}
+
+	pub fn duration(&self) -> f32 {
+		self.duration
+	}
+
+	/// Records a point from the pointer.
+	pub fn record_raw(&mut self, point: InputPoint) {
+		self.raw.push_back(point);
+		self.trim();
+	}
+
+	/// Records points of the stabilized stroke.
+	pub fn record_fitted(&mut self, points: impl IntoIterator<Item = InputPoint>) {
+		self.fitted.extend(points);
+		self.trim();
+	}
+
+	pub fn raw(&self) -> impl Iterator<Item = &InputPoint> {
+		self.raw.iter()
+	}
+
+	pub fn fitted(&self) -> impl Iterator<Item = &InputPoint> {
+		self.fitted.iter()
+	}
+
+	/// The time of the newest point, which the graph ends at.
+	pub fn end_time(&self) -> Option<f32> {
+		match (self.raw.back(), self.fitted.back()) {
+			(Some(raw), Some(fitted)) => Some(raw.t.max(fitted.t)),
+			(raw, fitted) => raw.or(fitted).map(|p| p.t),
+		}
+	}
+
+	fn trim(&mut self) {
+		let Some(end_time) = self.end_time() else {
+			return;
+		};
+		let start_time = end_time - self.duration;
+		for points in [&mut self.raw, &mut self.fitted] {
+			while points.front().is_some_and(|p| p.t < start_time) {
+				points.pop_front();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn point(t: f32, pressure: f32) -> InputPoint {
+		InputPoint {
+			t,
+			x: 10.0 * t,
+			y: 0.0,
+			pressure,
+		}
+	}
+
+	#[test]
+	fn stroke_trace() {
+		let mut trace = StrokeTrace::new(1.0);
+		assert_eq!(trace.end_time(), None);
+
+		trace.record_raw(point(0.0, 0.5));
+		trace.record_fitted([point(0.0, 0.5)]);
+		trace.record_raw(point(0.8, 0.6));
+		assert_eq!(trace.end_time(), Some(0.8));
+		assert_eq!(trace.raw().count(), 2);
+
+		// Fitted points can lag behind the pointer, and old points of both are dropped together.
+		trace.record_raw(point(1.5, 0.7));
+		trace.record_fitted([point(0.4, 0.55), point(0.8, 0.6)]);
+		assert_eq!(trace.end_time(), Some(1.5));
+		assert_eq!(trace.raw().map(|p| p.t).collect::<Vec<_>>(), [0.8, 1.5]);
+		assert_eq!(trace.fitted().map(|p| p.t).collect::<Vec<_>>(), [0.8]);
+	}
+}
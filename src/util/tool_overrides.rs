@@ -0,0 +1,176 @@
+// Tools held on by a key, kept apart from how key and pointer events are received so they can be
+// tested.
+
+/// A tool that stands in for the selected one while its key is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemporaryTool {
+	/// Dragging pans the view. Held with Space.
+	Pan,
+	/// Dragging rotates the view about the center of the screen. Held with R.
+	Rotate,
+	/// Clicking and dragging samples the canvas color. Held with Alt.
+	Eyedropper,
+	/// Strokes erase rather than paint. Held with E.
+	Eraser,
+}
+
+impl TemporaryTool {
+	/// The tool held by `key`, as in `KeyboardEvent.key`. Letters are matched without regard to case,
+	/// so releasing a letter after Shift still releases its tool.
+	pub fn for_key(key: &str) -> Option<Self> {
+		match key {
+			" " => Some(Self::Pan),
+			"r" | "R" => Some(Self::Rotate),
+			"Alt" => Some(Self::Eyedropper),
+			"e" | "E" => Some(Self::Eraser),
+			_ => None,
+		}
+	}
+
+	/// Whether dragging the tool draws on the canvas, rather than sampling it or moving the view.
+	pub fn draws(self) -> bool {
+		self == Self::Eraser
+	}
+}
+
+/// Which temporary tools are held, and which one the current drag uses.
+///
+/// The most recently pressed tool still held is in effect, so releasing it restores the one held
+/// before it, or the selected tool once none are. A drag keeps the tool it began with until it ends,
+/// so pressing or releasing a key partway through doesn't switch a stroke into a pan.
+#[derive(Debug, Clone, Default)]
+pub struct ToolOverrides {
+	// In the order they were pressed.
+	held: Vec<TemporaryTool>,
+	// The tool the current drag began with, if a drag is in progress.
+	dragging: Option<Option<TemporaryTool>>,
+}
+
+impl ToolOverrides {
+	/// The tool in effect, or `None` for the selected tool.
+	pub fn active(&self) -> Option<TemporaryTool> {
+		match self.dragging {
+			Some(tool) => tool,
+			None => self.held.last().copied(),
+		}
+	}
+
+	/// Holds `tool`, returning whether it wasn't already held.
+	pub fn press(&mut self, tool: TemporaryTool) -> bool {
+		if self.held.contains(&tool) {
+			return false;
+		}
+		self.held.push(tool);
+		true
+	}
+
+	/// Stops holding `tool`, returning whether it was held.
+	pub fn release(&mut self, tool: TemporaryTool) -> bool {
+		let len = self.held.len();
+		self.held.retain(|&held| held != tool);
+		self.held.len() != len
+	}
+
+	/// Presses or releases `tool` to match a modifier reported by an event, in case its key event was
+	/// missed, such as when the key was released in another window.
+	pub fn set_held(&mut self, tool: TemporaryTool, held: bool) {
+		if held {
+			self.press(tool);
+		} else {
+			self.release(tool);
+		}
+	}
+
+	/// Releases every tool, as when the window loses focus and key releases can't be seen.
+	pub fn release_all(&mut self) {
+		self.held.clear();
+	}
+
+	/// Begins a drag with the tool in effect, returning it.
+	pub fn begin_drag(&mut self) -> Option<TemporaryTool> {
+		let tool = self.held.last().copied();
+		self.dragging = Some(tool);
+		tool
+	}
+
+	/// Ends the current drag, returning the tool it began with. Afterwards, the tool in effect is
+	/// again the most recently pressed one still held.
+	pub fn end_drag(&mut self) -> Option<TemporaryTool> {
+		self.dragging.take().flatten()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keys() {
+		assert_eq!(TemporaryTool::for_key(" "), Some(TemporaryTool::Pan));
+		assert_eq!(
+			TemporaryTool::for_key("Alt"),
+			Some(TemporaryTool::Eyedropper)
+		);
+		assert_eq!(TemporaryTool::for_key("e"), Some(TemporaryTool::Eraser));
+		assert_eq!(TemporaryTool::for_key("E"), Some(TemporaryTool::Eraser));
+		assert_eq!(TemporaryTool::for_key("R"), Some(TemporaryTool::Rotate));
+		assert_eq!(TemporaryTool::for_key("Shift"), None);
+	}
+
+	#[test]
+	fn release_restores_previous_tool() {
+		let mut tools = ToolOverrides::default();
+		assert_eq!(tools.active(), None);
+		assert!(tools.press(TemporaryTool::Eraser));
+		assert!(tools.press(TemporaryTool::Pan));
+		assert!(!tools.press(TemporaryTool::Pan));
+		assert_eq!(tools.active(), Some(TemporaryTool::Pan));
+
+		assert!(tools.release(TemporaryTool::Pan));
+		assert_eq!(tools.active(), Some(TemporaryTool::Eraser));
+		assert!(!tools.release(TemporaryTool::Pan));
+		tools.release(TemporaryTool::Eraser);
+		assert_eq!(tools.active(), None);
+	}
+
+	#[test]
+	fn releasing_an_earlier_tool_keeps_the_latest() {
+		let mut tools = ToolOverrides::default();
+		tools.press(TemporaryTool::Eyedropper);
+		tools.press(TemporaryTool::Eraser);
+		tools.release(TemporaryTool::Eyedropper);
+		assert_eq!(tools.active(), Some(TemporaryTool::Eraser));
+	}
+
+	#[test]
+	fn drag_keeps_its_tool() {
+		let mut tools = ToolOverrides::default();
+		tools.press(TemporaryTool::Pan);
+		assert_eq!(tools.begin_drag(), Some(TemporaryTool::Pan));
+		tools.release(TemporaryTool::Pan);
+		tools.press(TemporaryTool::Eraser);
+		assert_eq!(tools.active(), Some(TemporaryTool::Pan));
+		assert_eq!(tools.end_drag(), Some(TemporaryTool::Pan));
+		assert_eq!(tools.active(), Some(TemporaryTool::Eraser));
+
+		// A drag begun with the selected tool keeps it too.
+		tools.release(TemporaryTool::Eraser);
+		assert_eq!(tools.begin_drag(), None);
+		tools.press(TemporaryTool::Pan);
+		assert_eq!(tools.active(), None);
+		tools.end_drag();
+		assert_eq!(tools.active(), Some(TemporaryTool::Pan));
+	}
+
+	#[test]
+	fn modifiers_resync_held_tools() {
+		let mut tools = ToolOverrides::default();
+		tools.press(TemporaryTool::Eyedropper);
+		tools.set_held(TemporaryTool::Eyedropper, false);
+		assert_eq!(tools.active(), None);
+		tools.set_held(TemporaryTool::Eyedropper, true);
+		assert_eq!(tools.active(), Some(TemporaryTool::Eyedropper));
+		tools.release_all();
+		assert_eq!(tools.active(), None);
+	}
+}
@@ -0,0 +1,386 @@
+// Helpers for the Leptos UI and the browser APIs it runs on, which the engine doesn't need.
+
+use leptos::prelude::*;
+use leptos::wasm_bindgen;
+use leptos::web_sys;
+use wasm_bindgen::JsCast;
+
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use super::ResultExt;
+use crate::coordinates::ScreenPx;
+
+#[derive(Clone, Copy)]
+pub struct Unequal<T>(T);
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Unequal<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl<T> PartialEq for Unequal<T> {
+	fn eq(&self, _other: &Self) -> bool {
+		false
+	}
+}
+
+impl<T> std::ops::Deref for Unequal<T> {
+	type Target = T;
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+/// It is useful to think of signals as having two channels:
+///
+/// 1. A value that can be fetched.
+/// 2. An "event" that the value may have changed.
+///
+/// `create_derived` caches only the value, forwarding all notifications from the underlying signal.
+/// This differs from `leptos::create_memo` which additionally does not notify if the new value is
+/// equal to the previous one. In some cases, that is desirable, but it requires the type to
+/// implement `PartialEq` which is not always possible. In others, e.g. `Trigger`, it is actively
+/// undesirable. Leptos used to provide a lower-level primitive that made it trivial to separate
+/// the two, but 0.7 introduced a (completely unnecessary) `PartialEq` bound on those.
+pub fn create_derived<T: Clone + Send + Sync + 'static>(
+	f: impl Fn() -> T + Send + Sync + 'static,
+) -> Signal<T> {
+	let memo = Memo::new_owning(move |_| (Unequal(f()), true));
+	Signal::derive(move || memo.with(|m| m.0.clone()))
+}
+
+pub fn create_local_derived<T: Clone + 'static>(
+	f: impl Fn() -> T + 'static,
+) -> Signal<T, LocalStorage> {
+	use send_wrapper::SendWrapper;
+	// Ideally, we would just use a `Memo` with `LocalStorage` here, but that isn't implemented yet.
+	let f = SendWrapper::new(f);
+	let f = move || f();
+	let memo = Memo::new_owning(move |_| (Unequal(SendWrapper::new(f())), true));
+	Signal::derive_local(move || memo.with(|m| (*m.0).clone()))
+}
+
+/// Like [`create_local_derived`], for values created by a future, such as pipelines which compile in
+/// the background. The value is `None` until the future returned by `f` resolves, and again while
+/// any future from rerunning `f` is pending, so it is never stale. Results of superseded futures are
+/// dropped.
+pub fn create_local_async_derived<T, Fut>(
+	f: impl Fn() -> Option<Fut> + 'static,
+) -> Signal<Option<T>, LocalStorage>
+where
+	T: Clone + 'static,
+	Fut: std::future::Future<Output = Option<T>> + 'static,
+{
+	let value = RwSignal::new_local(None::<T>);
+	let generation = StoredValue::new_local(0u64);
+	Effect::new(move |_| {
+		let future = f();
+		generation.update_value(|g| *g += 1);
+		let current = generation.get_value();
+		value.set(None);
+		if let Some(future) = future {
+			leptos::task::spawn_local(async move {
+				let result = future.await;
+				if generation.try_get_value() == Some(current) {
+					value.try_set(result);
+				}
+			});
+		}
+	});
+	Signal::derive_local(move || value.get())
+}
+
+pub struct LocalCallback<In: 'static, Out: 'static = ()>(
+	StoredValue<Box<dyn Fn(In) -> Out>, LocalStorage>,
+);
+
+impl<In, Out> Copy for LocalCallback<In, Out> {}
+impl<In, Out> Clone for LocalCallback<In, Out> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<In, Out> LocalCallback<In, Out> {
+	pub fn new(value: impl Fn(In) -> Out + 'static) -> Self {
+		Self(StoredValue::new_local(Box::new(value)))
+	}
+}
+
+impl<In, Out, F: Fn(In) -> Out + 'static> From<F> for LocalCallback<In, Out> {
+	fn from(value: F) -> Self {
+		Self::new(value)
+	}
+}
+
+impl<In, Out> leptos::prelude::Callable<In, Out> for LocalCallback<In, Out> {
+	fn run(&self, input: In) -> Out {
+		self.0.with_value(|f| f(input))
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("javascript error")]
+pub struct JsError(String);
+
+impl From<wasm_bindgen::JsValue> for JsError {
+	fn from(value: wasm_bindgen::JsValue) -> Self {
+		JsError(format!("{:?}", value))
+	}
+}
+
+pub fn set_timeout_and_clean_up(
+	cb: impl FnOnce() + 'static,
+	duration: std::time::Duration,
+) -> Result<(), JsError> {
+	let handle = set_timeout_with_handle(cb, duration)?;
+	on_cleanup(move || handle.clear());
+	Ok(())
+}
+
+pub fn set_interval_and_clean_up(
+	cb: impl Fn() + 'static,
+	duration: std::time::Duration,
+) -> Result<(), JsError> {
+	let handle = set_interval_with_handle(cb, duration)?;
+	on_cleanup(move || handle.clear());
+	Ok(())
+}
+
+pub trait PointerCapture {
+	fn set_pointer_capture(&self) -> bool;
+	fn release_pointer_capture(&self) -> bool;
+}
+
+impl PointerCapture for leptos::ev::PointerEvent {
+	fn set_pointer_capture(&self) -> bool {
+		self
+			.current_target()
+			.and_then(|target| target.dyn_into::<web_sys::Element>().ok_or_log())
+			.and_then(|target| target.set_pointer_capture(self.pointer_id()).ok_or_log())
+			.is_some()
+	}
+
+	fn release_pointer_capture(&self) -> bool {
+		self
+			.current_target()
+			.and_then(|target| target.dyn_into::<web_sys::Element>().ok_or_log())
+			.and_then(|target| {
+				target
+					.release_pointer_capture(self.pointer_id())
+					.ok_or_log()
+			})
+			.is_some()
+	}
+}
+
+pub trait CoordinateSource {
+	fn size(&self) -> Option<glam::Vec2>;
+
+	fn pixel_position(&self) -> glam::Vec2;
+
+	fn pixel_movement(&self) -> glam::Vec2;
+
+	fn screen_position(&self) -> ScreenPx {
+		ScreenPx(self.pixel_position().as_dvec2())
+	}
+
+	fn position(&self) -> Option<glam::Vec2> {
+		self.size().map(|size| self.pixel_position() / size)
+	}
+
+	/// The position in the clip space of the target element.
+	fn target_position(&self) -> Option<glam::Vec2> {
+		let size = self.size()?.as_dvec2();
+		Some(self.screen_position().to_clip(size))
+	}
+
+	fn movement(&self) -> Option<glam::Vec2> {
+		self.size().map(|size| self.pixel_movement() / size)
+	}
+
+	/// The movement in the clip space of the target element.
+	fn target_movement(&self) -> Option<glam::Vec2> {
+		let size = self.size()?.as_dvec2();
+		let start = ScreenPx(self.screen_position().0 - self.pixel_movement().as_dvec2());
+		Some(self.screen_position().to_clip(size) - start.to_clip(size))
+	}
+}
+
+impl CoordinateSource for leptos::ev::PointerEvent {
+	fn size(&self) -> Option<glam::Vec2> {
+		let element = self
+			.current_target()
+			.and_then(|target| target.dyn_into::<web_sys::Element>().ok_or_log())?;
+		Some(glam::vec2(
+			element.client_width() as f32,
+			element.client_height() as f32,
+		))
+	}
+
+	fn pixel_position(&self) -> glam::Vec2 {
+		glam::vec2(self.offset_x() as f32, self.offset_y() as f32)
+	}
+
+	fn pixel_movement(&self) -> glam::Vec2 {
+		glam::vec2(self.movement_x() as f32, self.movement_y() as f32)
+	}
+}
+
+impl CoordinateSource for leptos::ev::WheelEvent {
+	fn size(&self) -> Option<glam::Vec2> {
+		let element = self
+			.current_target()
+			.and_then(|target| target.dyn_into::<web_sys::Element>().ok_or_log())?;
+		Some(glam::vec2(
+			element.client_width() as f32,
+			element.client_height() as f32,
+		))
+	}
+
+	fn pixel_position(&self) -> glam::Vec2 {
+		glam::vec2(self.offset_x() as f32, self.offset_y() as f32)
+	}
+
+	fn pixel_movement(&self) -> glam::Vec2 {
+		glam::vec2(self.movement_x() as f32, self.movement_y() as f32)
+	}
+}
+
+impl CoordinateSource for leptos::ev::DragEvent {
+	fn size(&self) -> Option<glam::Vec2> {
+		let element = self
+			.current_target()
+			.and_then(|target| target.dyn_into::<web_sys::Element>().ok_or_log())?;
+		Some(glam::vec2(
+			element.client_width() as f32,
+			element.client_height() as f32,
+		))
+	}
+
+	fn pixel_position(&self) -> glam::Vec2 {
+		glam::vec2(self.offset_x() as f32, self.offset_y() as f32)
+	}
+
+	fn pixel_movement(&self) -> glam::Vec2 {
+		glam::vec2(self.movement_x() as f32, self.movement_y() as f32)
+	}
+}
+
+fn animation_frame_throttle_filter<R>(
+) -> impl Fn(Arc<dyn Fn() -> R>) -> Arc<Mutex<Option<R>>> + Clone {
+	let is_available = Rc::new(std::cell::Cell::new(true));
+	let last_return_value: Arc<Mutex<Option<R>>> = Default::default();
+
+	move |invoke: Arc<dyn Fn() -> R>| {
+		let last_return_value = last_return_value.clone();
+		let is_available = is_available.clone();
+		if is_available.take() {
+			use leptos::reactive_graph::diagnostics::SpecialNonReactiveZone;
+
+			let return_value = {
+				#[cfg(debug_assertions)]
+				let _guard = SpecialNonReactiveZone::enter();
+				invoke()
+			};
+
+			*last_return_value.lock().unwrap() = Some(return_value);
+
+			request_animation_frame(move || is_available.set(true));
+		}
+		last_return_value
+	}
+}
+
+pub fn use_animation_frame_throttle<F, R>(func: F) -> impl Fn() -> Arc<Mutex<Option<R>>> + Clone
+where
+	F: Fn() -> R + Clone + 'static,
+	R: 'static,
+{
+	leptos_use::utils::create_filter_wrapper(Arc::new(animation_frame_throttle_filter()), func)
+}
+
+pub fn use_animation_frame_throttle_with_arg<F, Arg, R>(
+	func: F,
+) -> impl Fn(Arg) -> Arc<Mutex<Option<R>>> + Clone
+where
+	F: Fn(Arg) -> R + Clone + 'static,
+	Arg: Clone + 'static,
+	R: 'static,
+{
+	leptos_use::utils::create_filter_wrapper_with_arg(
+		Arc::new(animation_frame_throttle_filter()),
+		func,
+	)
+}
+
+/// Returns a function that starts calling `func` once per animation frame until it returns `false`.
+/// Starting while the loop is already running has no effect, so callers can simply update whatever
+/// state `func` reads and then start the loop.
+pub fn use_animation_frame_loop<F>(func: F) -> impl Fn() + Clone
+where
+	F: Fn() -> bool + 'static,
+{
+	fn schedule(func: Rc<dyn Fn() -> bool>, is_running: Rc<std::cell::Cell<bool>>) {
+		request_animation_frame(move || {
+			if func() {
+				schedule(func, is_running);
+			} else {
+				is_running.set(false);
+			}
+		});
+	}
+
+	let func: Rc<dyn Fn() -> bool> = Rc::new(func);
+	let is_running = Rc::new(std::cell::Cell::new(false));
+	move || {
+		if !is_running.replace(true) {
+			schedule(func.clone(), is_running.clone());
+		}
+	}
+}
+
+/// The current time in milliseconds, as used by `requestAnimationFrame`.
+pub fn performance_now() -> f64 {
+	window().performance().map(|p| p.now()).unwrap_or_default()
+}
+
+/// Offers `data` to the user as a file download.
+pub fn download(data: &[u8], filename: &str, mime_type: &str) -> Result<(), wasm_bindgen::JsValue> {
+	let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+	let options = web_sys::BlobPropertyBag::new();
+	options.set_type(mime_type);
+	let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+	let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+	let anchor: web_sys::HtmlAnchorElement = document().create_element("a")?.dyn_into()?;
+	anchor.set_href(&url);
+	anchor.set_download(filename);
+	anchor.click();
+	// Revoking the URL immediately can cancel the download in some browsers.
+	set_timeout(
+		move || {
+			web_sys::Url::revoke_object_url(&url).ok_or_log();
+		},
+		std::time::Duration::from_secs(10),
+	);
+	Ok(())
+}
+
+/// Reads the contents of a file chosen or dropped by the user.
+pub async fn read_file(file: &web_sys::File) -> Result<Vec<u8>, JsError> {
+	let buffer = wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await?;
+	Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Takes the files chosen with a file input, from its `change` event. The input is cleared, so
+/// choosing the same file again is still reported.
+pub fn take_chosen_files(e: &leptos::ev::Event) -> Vec<web_sys::File> {
+	let input: web_sys::HtmlInputElement = event_target(e);
+	let files = input.files().map_or_else(Vec::new, |files| {
+		(0..files.length()).filter_map(|i| files.get(i)).collect()
+	});
+	input.set_value("");
+	files
+}
@@ -0,0 +1,173 @@
+use glam::*;
+
+// Browsers report wheel deltas in pixels, lines, or pages depending on the device and platform,
+// and trackpads report pinches as wheel events with `ctrlKey` set. This normalizes all of that
+// into either a zoom or a pan.
+
+/// The `WheelEvent.deltaMode` constants.
+pub const DOM_DELTA_PIXEL: u32 = 0;
+pub const DOM_DELTA_LINE: u32 = 1;
+pub const DOM_DELTA_PAGE: u32 = 2;
+
+/// Pixels per line for `DOM_DELTA_LINE`. Browsers don't expose the real value, so this is chosen to
+/// make a three-line notch (Firefox) match a 100 pixel notch (Chromium).
+const LINE_HEIGHT: f64 = 100.0 / 3.0;
+
+/// Zoom per pixel of wheel movement. A typical mouse wheel notch is 100 pixels, which this maps to
+/// a factor of about 1.272.
+const WHEEL_ZOOM_SENSITIVITY: f64 = 0.0024;
+
+/// Zoom per pixel of pinch movement. Pinches report much smaller deltas than wheels do.
+const PINCH_ZOOM_SENSITIVITY: f64 = 0.01;
+
+/// Pixel-mode deltas at least this large, with no horizontal component, are assumed to come from a
+/// mouse wheel rather than a trackpad.
+const MIN_MOUSE_WHEEL_DELTA: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelInput {
+	pub delta: DVec2,
+	pub delta_mode: u32,
+	pub ctrl_key: bool,
+	/// The size of the scrolled element, used to interpret `DOM_DELTA_PAGE`.
+	pub page_size: DVec2,
+}
+
+impl WheelInput {
+	#[cfg(feature = "ui")]
+	pub fn from_event(e: &leptos::ev::WheelEvent, page_size: DVec2) -> Self {
+		Self {
+			delta: dvec2(e.delta_x(), e.delta_y()),
+			delta_mode: e.delta_mode(),
+			ctrl_key: e.ctrl_key(),
+			page_size,
+		}
+	}
+
+	/// The delta in pixels, regardless of `delta_mode`.
+	pub fn pixel_delta(&self) -> DVec2 {
+		match self.delta_mode {
+			DOM_DELTA_LINE => self.delta * LINE_HEIGHT,
+			DOM_DELTA_PAGE => self.delta * self.page_size,
+			_ => self.delta,
+		}
+	}
+
+	fn is_mouse_wheel(&self) -> bool {
+		if self.delta_mode != DOM_DELTA_PIXEL {
+			return true;
+		}
+		self.delta.x == 0.0
+			&& self.delta.y.fract() == 0.0
+			&& self.delta.y.abs() >= MIN_MOUSE_WHEEL_DELTA
+	}
+
+	pub fn gesture(&self) -> WheelGesture {
+		let delta = self.pixel_delta();
+		if self.ctrl_key {
+			// Trackpad pinches, as well as ctrl+wheel.
+			let sensitivity = if self.is_mouse_wheel() {
+				WHEEL_ZOOM_SENSITIVITY
+			} else {
+				PINCH_ZOOM_SENSITIVITY
+			};
+			WheelGesture::Zoom((-delta.y * sensitivity).exp())
+		} else if self.is_mouse_wheel() {
+			WheelGesture::Zoom((-delta.y * WHEEL_ZOOM_SENSITIVITY).exp())
+		} else {
+			// Two-finger trackpad scrolling moves the canvas with the fingers.
+			WheelGesture::Pan(-delta)
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WheelGesture {
+	/// Multiply the zoom by this factor about the pointer.
+	Zoom(f64),
+	/// Pan by this many screen pixels.
+	Pan(DVec2),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::assert_matches::assert_matches;
+
+	fn input(delta: DVec2, delta_mode: u32, ctrl_key: bool) -> WheelInput {
+		WheelInput {
+			delta,
+			delta_mode,
+			ctrl_key,
+			page_size: dvec2(800.0, 600.0),
+		}
+	}
+
+	fn zoom_factor(gesture: WheelGesture) -> f64 {
+		match gesture {
+			WheelGesture::Zoom(factor) => factor,
+			_ => panic!("expected zoom, got {gesture:?}"),
+		}
+	}
+
+	#[test]
+	fn pixel_delta() {
+		let delta = dvec2(1.0, -2.0);
+		assert_eq!(input(delta, DOM_DELTA_PIXEL, false).pixel_delta(), delta);
+		assert_eq!(
+			input(delta, DOM_DELTA_LINE, false).pixel_delta(),
+			dvec2(100.0 / 3.0, -200.0 / 3.0)
+		);
+		assert_eq!(
+			input(delta, DOM_DELTA_PAGE, false).pixel_delta(),
+			dvec2(800.0, -1200.0)
+		);
+	}
+
+	#[test]
+	fn mouse_wheel_zooms() {
+		let factor = zoom_factor(input(dvec2(0.0, -100.0), DOM_DELTA_PIXEL, false).gesture());
+		approx::assert_abs_diff_eq!(factor, 1.272, epsilon = 0.01);
+
+		// Firefox reports notches in lines. The result should match.
+		let line_factor = zoom_factor(input(dvec2(0.0, -3.0), DOM_DELTA_LINE, false).gesture());
+		approx::assert_abs_diff_eq!(line_factor, factor, epsilon = 1e-12);
+
+		// Scrolling the other way exactly undoes it.
+		let inverse = zoom_factor(input(dvec2(0.0, 100.0), DOM_DELTA_PIXEL, false).gesture());
+		approx::assert_abs_diff_eq!(factor * inverse, 1.0, epsilon = 1e-12);
+	}
+
+	#[test]
+	fn zoom_is_proportional() {
+		let small = zoom_factor(input(dvec2(0.0, -2.5), DOM_DELTA_PIXEL, true).gesture());
+		let large = zoom_factor(input(dvec2(0.0, -5.0), DOM_DELTA_PIXEL, true).gesture());
+		assert!(small > 1.0);
+		approx::assert_abs_diff_eq!(small * small, large, epsilon = 1e-12);
+	}
+
+	#[test]
+	fn trackpad_scroll_pans() {
+		assert_eq!(
+			input(dvec2(3.0, -7.5), DOM_DELTA_PIXEL, false).gesture(),
+			WheelGesture::Pan(dvec2(-3.0, 7.5))
+		);
+		// Horizontal-only scrolling pans too.
+		assert_eq!(
+			input(dvec2(120.0, 0.0), DOM_DELTA_PIXEL, false).gesture(),
+			WheelGesture::Pan(dvec2(-120.0, 0.0))
+		);
+	}
+
+	#[test]
+	fn pinch_zooms() {
+		assert_matches!(
+			input(dvec2(0.0, -1.5), DOM_DELTA_PIXEL, true).gesture(),
+			WheelGesture::Zoom(factor) if factor > 1.0
+		);
+		assert_matches!(
+			input(dvec2(0.0, 1.5), DOM_DELTA_PIXEL, true).gesture(),
+			WheelGesture::Zoom(factor) if factor < 1.0
+		);
+	}
+}
@@ -0,0 +1,731 @@
+//! Panning, zooming, and rotating the view of the canvas.
+
+use glam::*;
+
+/// The range of zoom levels a [`ViewTransform`] is allowed to reach.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomLimits {
+	pub min: f64,
+	pub max: f64,
+}
+
+impl Default for ZoomLimits {
+	fn default() -> Self {
+		Self {
+			min: 1.0 / 64.0,
+			max: 256.0,
+		}
+	}
+}
+
+impl ZoomLimits {
+	pub fn clamp(&self, zoom: f64) -> f64 {
+		zoom.clamp(self.min, self.max)
+	}
+}
+
+/// The mapping from canvas coordinates to screen (pixel) coordinates.
+///
+/// A canvas point `p` is displayed at `translation + zoom * rotate(rotation, p)`. Keeping the
+/// components separate, rather than storing a matrix, lets us clamp the zoom and compute inverses
+/// exactly.
+///
+/// Everything is stored in `f64` so that the view remains precise arbitrarily far from the canvas
+/// origin. Use [`Self::canvas_to_screen_matrix_relative_to`] to get an `f32` matrix for rendering.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ViewTransform {
+	/// The screen position of the canvas origin.
+	pub translation: DVec2,
+	/// The number of screen pixels per canvas unit.
+	pub zoom: f64,
+	/// The counter-clockwise rotation of the canvas on screen, in radians.
+	pub rotation: f64,
+	/// Limits are a property of the canvas rather than the view, so they are not serialized.
+	#[serde(skip)]
+	pub zoom_limits: ZoomLimits,
+}
+
+impl Default for ViewTransform {
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+impl ViewTransform {
+	pub const IDENTITY: Self = Self {
+		translation: DVec2::ZERO,
+		zoom: 1.0,
+		rotation: 0.0,
+		zoom_limits: ZoomLimits {
+			min: 1.0 / 64.0,
+			max: 256.0,
+		},
+	};
+
+	pub fn with_zoom_limits(self, zoom_limits: ZoomLimits) -> Self {
+		Self {
+			zoom: zoom_limits.clamp(self.zoom),
+			zoom_limits,
+			..self
+		}
+	}
+
+	pub fn canvas_to_screen(&self) -> DAffine2 {
+		DAffine2::from_scale_angle_translation(
+			DVec2::splat(self.zoom),
+			self.rotation,
+			self.translation,
+		)
+	}
+
+	pub fn screen_to_canvas(&self) -> DAffine2 {
+		let inverse_rotation = DAffine2::from_angle(-self.rotation);
+		DAffine2::from_scale(DVec2::splat(self.zoom.recip()))
+			* inverse_rotation
+			* DAffine2::from_translation(-self.translation)
+	}
+
+	/// The canvas-to-screen transform as an `f32` matrix suitable for passing to shaders, applied to
+	/// canvas coordinates relative to `origin`. Choosing an origin near the visible region keeps the
+	/// matrix small, which is what keeps rendering precise far from the canvas origin.
+	pub fn canvas_to_screen_matrix_relative_to(&self, origin: DVec2) -> Mat4 {
+		let translation = self.canvas_to_screen_point(origin).as_vec2();
+		Mat4::from_scale_rotation_translation(
+			vec3(self.zoom as f32, self.zoom as f32, 1.0),
+			Quat::from_rotation_z(self.rotation as f32),
+			translation.extend(0.0),
+		)
+	}
+
+	pub fn canvas_to_screen_point(&self, point: DVec2) -> DVec2 {
+		self.canvas_to_screen().transform_point2(point)
+	}
+
+	pub fn screen_to_canvas_point(&self, point: DVec2) -> DVec2 {
+		self.screen_to_canvas().transform_point2(point)
+	}
+
+	pub fn canvas_to_screen_vector(&self, vector: DVec2) -> DVec2 {
+		self.canvas_to_screen().transform_vector2(vector)
+	}
+
+	pub fn screen_to_canvas_vector(&self, vector: DVec2) -> DVec2 {
+		self.screen_to_canvas().transform_vector2(vector)
+	}
+
+	/// Moves the canvas by `screen_delta` pixels.
+	pub fn pan(&mut self, screen_delta: DVec2) {
+		self.translation += screen_delta;
+	}
+
+	/// Sets the zoom, clamped to the zoom limits, keeping the canvas point under `screen_anchor`
+	/// fixed.
+	pub fn set_zoom_about(&mut self, screen_anchor: DVec2, zoom: f64) {
+		let anchor = self.screen_to_canvas_point(screen_anchor);
+		self.zoom = self.zoom_limits.clamp(zoom);
+		self.translation += screen_anchor - self.canvas_to_screen_point(anchor);
+	}
+
+	/// Multiplies the zoom by `factor`, keeping the canvas point under `screen_anchor` fixed.
+	pub fn zoom_about(&mut self, screen_anchor: DVec2, factor: f64) {
+		self.set_zoom_about(screen_anchor, self.zoom * factor);
+	}
+
+	/// Rotates the canvas by `angle` radians about `screen_anchor`.
+	pub fn rotate_about(&mut self, screen_anchor: DVec2, angle: f64) {
+		let anchor = self.screen_to_canvas_point(screen_anchor);
+		self.rotation = (self.rotation + angle).rem_euclid(std::f64::consts::TAU);
+		self.translation += screen_anchor - self.canvas_to_screen_point(anchor);
+	}
+
+	/// Rotates the canvas about `screen_anchor` by the angle a pointer dragged from `from` to `to`
+	/// sweeps around it, so the canvas turns with the pointer.
+	pub fn rotate_dragged_about(&mut self, screen_anchor: DVec2, from: DVec2, to: DVec2) {
+		let angle = (from - screen_anchor).angle_between(to - screen_anchor);
+		self.rotate_about(screen_anchor, angle);
+	}
+
+	/// Returns to an unrotated view, keeping the canvas point under `screen_anchor` fixed.
+	pub fn reset_rotation_about(&mut self, screen_anchor: DVec2) {
+		self.rotate_about(screen_anchor, -self.rotation);
+	}
+
+	/// The view at the same rotation which shows the canvas rectangle from `min` to `max` centered on
+	/// a screen of `screen_size` pixels, as large as fits within `margin` pixels of its edges. The
+	/// zoom is clamped to the zoom limits.
+	pub fn framing(&self, min: DVec2, max: DVec2, screen_size: DVec2, margin: f64) -> Self {
+		let size = max - min;
+		let (sin, cos) = self.rotation.sin_cos();
+		let (sin, cos) = (sin.abs(), cos.abs());
+		let screen_extent = dvec2(cos * size.x + sin * size.y, sin * size.x + cos * size.y);
+		let available = (screen_size - 2.0 * margin).max(DVec2::ONE);
+		let zoom = (available / screen_extent.max(DVec2::splat(f64::EPSILON))).min_element();
+		let mut view = Self {
+			translation: DVec2::ZERO,
+			zoom: self.zoom_limits.clamp(zoom),
+			..*self
+		};
+		view.translation = 0.5 * screen_size - view.canvas_to_screen_point(0.5 * (min + max));
+		view
+	}
+
+	/// Interpolates between two views. Zoom is interpolated geometrically, rotation along the
+	/// shorter arc, and the canvas point under `screen_anchor` linearly, which makes large zoom
+	/// changes feel uniform rather than rushing through the first few steps.
+	pub fn interpolate(&self, other: &Self, t: f64, screen_anchor: DVec2) -> Self {
+		use std::f64::consts::{PI, TAU};
+		let anchor = self
+			.screen_to_canvas_point(screen_anchor)
+			.lerp(other.screen_to_canvas_point(screen_anchor), t);
+		let zoom = (self.zoom.ln() + t * (other.zoom.ln() - self.zoom.ln())).exp();
+		let rotation_delta = (other.rotation - self.rotation + PI).rem_euclid(TAU) - PI;
+		let mut view = Self {
+			translation: DVec2::ZERO,
+			zoom,
+			rotation: (self.rotation + t * rotation_delta).rem_euclid(TAU),
+			zoom_limits: other.zoom_limits,
+		};
+		view.translation = screen_anchor - view.canvas_to_screen_point(anchor);
+		view
+	}
+}
+
+fn ease_in_out_cubic(t: f64) -> f64 {
+	if t < 0.5 {
+		4.0 * t * t * t
+	} else {
+		1.0 - 0.5 * (2.0 - 2.0 * t).powi(3)
+	}
+}
+
+/// An eased transition between two views, sampled once per animation frame.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewAnimation {
+	from: ViewTransform,
+	to: ViewTransform,
+	screen_anchor: DVec2,
+	start_time: f64,
+	duration: f64,
+}
+
+impl ViewAnimation {
+	/// Times are in milliseconds, matching `performance.now()`.
+	pub fn new(
+		from: ViewTransform,
+		to: ViewTransform,
+		screen_anchor: DVec2,
+		start_time: f64,
+		duration: f64,
+	) -> Self {
+		Self {
+			from,
+			to,
+			screen_anchor,
+			start_time,
+			duration,
+		}
+	}
+
+	pub fn target(&self) -> &ViewTransform {
+		&self.to
+	}
+
+	/// Returns the view at `time` and whether the animation has finished.
+	pub fn sample(&self, time: f64) -> (ViewTransform, bool) {
+		let t = (time - self.start_time) / self.duration;
+		if self.duration <= 0.0 || t >= 1.0 {
+			return (self.to, true);
+		}
+		let t = ease_in_out_cubic(t.max(0.0));
+		(
+			self.from.interpolate(&self.to, t, self.screen_anchor),
+			false,
+		)
+	}
+}
+
+/// The time constant of [`ZoomEase`]'s approach to its target, in milliseconds. After this long,
+/// the zoom has gone about two thirds of the way there, in scale.
+const ZOOM_EASE_TIME_CONSTANT: f64 = 60.0;
+
+/// [`ZoomEase`] finishes once the zoom is within this fraction of its target.
+const ZOOM_EASE_TOLERANCE: f64 = 1e-3;
+
+/// Eases the zoom exponentially towards a target about a screen anchor, so that steps of the wheel
+/// blend into continuous motion. Further steps multiply the target, so fast scrolling adds up rather
+/// than restarting the ease.
+#[derive(Debug, Clone, Copy)]
+pub struct ZoomEase {
+	target_zoom: f64,
+	screen_anchor: DVec2,
+	last_time: f64,
+}
+
+impl ZoomEase {
+	/// Starts easing `view`'s zoom by `factor` about `screen_anchor`, at `time` in milliseconds.
+	pub fn new(view: &ViewTransform, screen_anchor: DVec2, factor: f64, time: f64) -> Self {
+		Self {
+			target_zoom: view.zoom_limits.clamp(view.zoom * factor),
+			screen_anchor,
+			last_time: time,
+		}
+	}
+
+	pub fn target_zoom(&self) -> f64 {
+		self.target_zoom
+	}
+
+	/// Multiplies the target zoom by `factor`, which `view` clamps, and eases about `screen_anchor`
+	/// from now on.
+	pub fn zoom_by(&mut self, view: &ViewTransform, screen_anchor: DVec2, factor: f64) {
+		self.target_zoom = view.zoom_limits.clamp(self.target_zoom * factor);
+		self.screen_anchor = screen_anchor;
+	}
+
+	/// Eases `view` on to `time`, in milliseconds, and returns whether it has reached the target.
+	/// The canvas point under the anchor stays fixed.
+	pub fn step(&mut self, view: &mut ViewTransform, time: f64) -> bool {
+		let elapsed = (time - self.last_time).max(0.0);
+		self.last_time = time;
+		// Easing the logarithm makes each frame the same proportional change, whatever the zoom.
+		let remaining = (self.target_zoom / view.zoom).ln();
+		let eased = remaining * (1.0 - (-elapsed / ZOOM_EASE_TIME_CONSTANT).exp());
+		if (remaining - eased).abs() <= ZOOM_EASE_TOLERANCE.ln_1p() {
+			view.set_zoom_about(self.screen_anchor, self.target_zoom);
+			return true;
+		}
+		view.set_zoom_about(self.screen_anchor, view.zoom * eased.exp());
+		false
+	}
+}
+
+/// How far back [`ViewMotion`] looks to estimate the view's velocity, in milliseconds.
+const VIEW_MOTION_WINDOW: f64 = 100.0;
+
+/// Tracks how the view has recently moved across the canvas, to predict where it is heading.
+#[derive(Debug, Clone, Default)]
+pub struct ViewMotion {
+	// The canvas point at the center of the screen at each time, oldest first.
+	samples: std::collections::VecDeque<(f64, DVec2)>,
+}
+
+impl ViewMotion {
+	/// Records the canvas point at the center of the screen at `time`, in milliseconds.
+	pub fn record(&mut self, time: f64, center: DVec2) {
+		self.samples.push_back((time, center));
+		while let Some(&(oldest, _)) = self.samples.front() {
+			if time - oldest <= VIEW_MOTION_WINDOW {
+				break;
+			}
+			self.samples.pop_front();
+		}
+	}
+
+	/// The recent velocity of the view, in canvas units per millisecond.
+	pub fn velocity(&self) -> DVec2 {
+		let (Some(&(t0, p0)), Some(&(t1, p1))) = (self.samples.front(), self.samples.back()) else {
+			return DVec2::ZERO;
+		};
+		if t1 <= t0 {
+			return DVec2::ZERO;
+		}
+		(p1 - p0) / (t1 - t0)
+	}
+
+	/// How far the view is predicted to move across the canvas in the next `lookahead`
+	/// milliseconds.
+	pub fn predicted_offset(&self, lookahead: f64) -> DVec2 {
+		self.velocity() * lookahead
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test::assert_near;
+
+	fn example() -> ViewTransform {
+		ViewTransform {
+			translation: dvec2(120.0, -35.0),
+			zoom: 2.5,
+			rotation: 0.7,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn identity() {
+		let view = ViewTransform::default();
+		let p = dvec2(3.0, -4.0);
+		assert_eq!(view.canvas_to_screen_point(p), p);
+		assert_eq!(view.screen_to_canvas_point(p), p);
+		assert_eq!(
+			view.canvas_to_screen_matrix_relative_to(DVec2::ZERO),
+			Mat4::IDENTITY
+		);
+	}
+
+	#[test]
+	fn point_round_trip() {
+		let view = example();
+		for p in [DVec2::ZERO, dvec2(1.0, 0.0), dvec2(-50.0, 75.0)] {
+			assert_near!(
+				view.screen_to_canvas_point(view.canvas_to_screen_point(p)),
+				p,
+				1e-9
+			);
+			assert_near!(
+				view.canvas_to_screen_point(view.screen_to_canvas_point(p)),
+				p,
+				1e-9
+			);
+		}
+	}
+
+	#[test]
+	fn matrix_matches_affine() {
+		let view = example();
+		let origin = dvec2(512.0, -256.0);
+		let p = dvec2(-7.0, 13.0);
+		let expected = view.canvas_to_screen_point(origin + p);
+		let actual =
+			view.canvas_to_screen_matrix_relative_to(origin) * p.as_vec2().extend(0.0).extend(1.0);
+		assert_near!(actual.xy().as_dvec2(), expected, 1e-3);
+	}
+
+	#[test]
+	fn pan_moves_by_screen_delta() {
+		let mut view = example();
+		let p = dvec2(10.0, 20.0);
+		let before = view.canvas_to_screen_point(p);
+		view.pan(dvec2(5.0, -3.0));
+		assert_near!(
+			view.canvas_to_screen_point(p),
+			before + dvec2(5.0, -3.0),
+			1e-9
+		);
+	}
+
+	#[test]
+	fn zoom_keeps_anchor_fixed() {
+		let mut view = example();
+		let anchor = dvec2(300.0, 200.0);
+		let canvas_anchor = view.screen_to_canvas_point(anchor);
+		view.zoom_about(anchor, 1.272);
+		approx::assert_abs_diff_eq!(view.zoom, 2.5 * 1.272, epsilon = 1e-12);
+		assert_near!(view.canvas_to_screen_point(canvas_anchor), anchor, 1e-9);
+	}
+
+	#[test]
+	fn zoom_is_clamped() {
+		let mut view = ViewTransform::default();
+		let limits = view.zoom_limits;
+		let anchor = dvec2(100.0, 100.0);
+		for _ in 0..100 {
+			view.zoom_about(anchor, 2.0);
+		}
+		assert_eq!(view.zoom, limits.max);
+		assert_near!(view.screen_to_canvas_point(anchor), anchor, 1e-9);
+
+		for _ in 0..100 {
+			view.zoom_about(anchor, 0.5);
+		}
+		assert_eq!(view.zoom, limits.min);
+		assert_near!(view.screen_to_canvas_point(anchor), anchor, 1e-9);
+	}
+
+	#[test]
+	fn custom_zoom_limits() {
+		let limits = ZoomLimits { min: 0.5, max: 4.0 };
+		let mut view = ViewTransform {
+			zoom: 8.0,
+			..Default::default()
+		}
+		.with_zoom_limits(limits);
+		assert_eq!(view.zoom, 4.0);
+
+		view.set_zoom_about(DVec2::ZERO, 0.1);
+		assert_eq!(view.zoom, 0.5);
+	}
+
+	#[test]
+	fn rotate_keeps_anchor_fixed() {
+		let mut view = example();
+		let anchor = dvec2(-40.0, 60.0);
+		let canvas_anchor = view.screen_to_canvas_point(anchor);
+		view.rotate_about(anchor, 1.0);
+		approx::assert_abs_diff_eq!(view.rotation, 1.7, epsilon = 1e-12);
+		assert_near!(view.canvas_to_screen_point(canvas_anchor), anchor, 1e-9);
+	}
+
+	#[test]
+	fn rotate_dragged_follows_pointer() {
+		let mut view = example();
+		let anchor = dvec2(400.0, 300.0);
+		let from = dvec2(500.0, 300.0);
+		let canvas_from = view.screen_to_canvas_point(from);
+		let to = dvec2(400.0, 250.0);
+		view.rotate_dragged_about(anchor, from, to);
+		// The canvas point under the pointer turns with it, though the distance from the anchor
+		// is kept.
+		assert_near!(
+			view.canvas_to_screen_point(canvas_from),
+			dvec2(400.0, 200.0),
+			1e-9
+		);
+
+		view.reset_rotation_about(anchor);
+		assert_eq!(view.rotation, 0.0);
+		assert_near!(
+			view.canvas_to_screen_point(canvas_from),
+			dvec2(400.0, 300.0) + DAffine2::from_angle(-0.7).transform_vector2(dvec2(100.0, 0.0)),
+			1e-9
+		);
+	}
+
+	#[test]
+	fn framing_fits_rectangle() {
+		let screen_size = dvec2(800.0, 600.0);
+		let (min, max) = (dvec2(-100.0, 50.0), dvec2(300.0, 150.0));
+		let view = ViewTransform::default().framing(min, max, screen_size, 50.0);
+		// The width is what limits the zoom, and the rectangle is centered.
+		assert_eq!(view.zoom, 1.75);
+		assert_near!(view.canvas_to_screen_point(min), dvec2(50.0, 212.5), 1e-9);
+		assert_near!(view.canvas_to_screen_point(max), dvec2(750.0, 387.5), 1e-9);
+
+		// Rotated a quarter turn, the height is.
+		let rotated = ViewTransform {
+			rotation: std::f64::consts::FRAC_PI_2,
+			..example()
+		}
+		.framing(min, max, screen_size, 50.0);
+		assert_near!(rotated.zoom, 1.25, 1e-9);
+		assert_near!(
+			rotated.canvas_to_screen_point(0.5 * (min + max)),
+			0.5 * screen_size,
+			1e-9
+		);
+
+		// Tiny rectangles are only magnified as far as the zoom limits allow.
+		let tiny = example().framing(DVec2::ZERO, DVec2::splat(0.001), screen_size, 0.0);
+		assert_eq!(tiny.zoom, tiny.zoom_limits.max);
+	}
+
+	#[test]
+	fn zoom_ease_approaches_target() {
+		let mut view = example();
+		let anchor = dvec2(300.0, 200.0);
+		let canvas_anchor = view.screen_to_canvas_point(anchor);
+		let mut ease = ZoomEase::new(&view, anchor, 2.0, 0.0);
+		assert_eq!(ease.target_zoom(), 5.0);
+
+		// Each frame covers the same fraction of what's left, in scale.
+		assert!(!ease.step(&mut view, ZOOM_EASE_TIME_CONSTANT));
+		approx::assert_abs_diff_eq!(
+			view.zoom,
+			2.5 * 2f64.powf(1.0 - (-1f64).exp()),
+			epsilon = 1e-9
+		);
+		assert_near!(view.canvas_to_screen_point(canvas_anchor), anchor, 1e-9);
+
+		// Another step of the wheel adds to the target rather than restarting from the current zoom.
+		ease.zoom_by(&view, anchor, 2.0);
+		assert_eq!(ease.target_zoom(), 10.0);
+		let mut time = ZOOM_EASE_TIME_CONSTANT;
+		while !ease.step(&mut view, time) {
+			time += 16.0;
+			assert!(time < 1000.0, "the ease never finished");
+		}
+		assert_eq!(view.zoom, 10.0);
+		assert_near!(view.canvas_to_screen_point(canvas_anchor), anchor, 1e-9);
+	}
+
+	#[test]
+	fn zoom_ease_is_limited() {
+		let view = example();
+		let mut ease = ZoomEase::new(&view, DVec2::ZERO, 1000.0, 0.0);
+		assert_eq!(ease.target_zoom(), view.zoom_limits.max);
+		ease.zoom_by(&view, DVec2::ZERO, 1e-9);
+		assert_eq!(ease.target_zoom(), view.zoom_limits.min);
+	}
+
+	#[test]
+	fn serialization_round_trip() {
+		let view = example();
+		let json = serde_json::to_string(&view).unwrap();
+		let restored: ViewTransform = serde_json::from_str(&json).unwrap();
+		assert_eq!(restored, view);
+
+		// Missing fields fall back to the identity.
+		let restored: ViewTransform = serde_json::from_str(r#"{"zoom":2.0}"#).unwrap();
+		assert_eq!(
+			restored,
+			ViewTransform {
+				zoom: 2.0,
+				..ViewTransform::IDENTITY
+			}
+		);
+	}
+
+	#[test]
+	fn interpolate_endpoints() {
+		let a = example();
+		let b = ViewTransform {
+			translation: dvec2(-500.0, 80.0),
+			zoom: 0.25,
+			rotation: 5.9,
+			..Default::default()
+		};
+		let anchor = dvec2(400.0, 300.0);
+		let start = a.interpolate(&b, 0.0, anchor);
+		assert_near!(start.translation, a.translation, 1e-9);
+		approx::assert_abs_diff_eq!(start.zoom, a.zoom, epsilon = 1e-12);
+		approx::assert_abs_diff_eq!(start.rotation, a.rotation, epsilon = 1e-12);
+		let end = a.interpolate(&b, 1.0, anchor);
+		assert_near!(end.translation, b.translation, 1e-9);
+		approx::assert_abs_diff_eq!(end.zoom, b.zoom, epsilon = 1e-12);
+		approx::assert_abs_diff_eq!(end.rotation, b.rotation, epsilon = 1e-12);
+	}
+
+	#[test]
+	fn interpolate_is_geometric_and_short() {
+		let a = ViewTransform {
+			zoom: 1.0,
+			rotation: 0.1,
+			..Default::default()
+		};
+		let b = ViewTransform {
+			zoom: 16.0,
+			rotation: std::f64::consts::TAU - 0.1,
+			..Default::default()
+		};
+		let mid = a.interpolate(&b, 0.5, DVec2::ZERO);
+		approx::assert_abs_diff_eq!(mid.zoom, 4.0, epsilon = 1e-12);
+		// The shorter arc passes through zero rather than pi.
+		approx::assert_abs_diff_eq!(mid.rotation.sin(), 0.0, epsilon = 1e-12);
+		approx::assert_abs_diff_eq!(mid.rotation.cos(), 1.0, epsilon = 1e-12);
+	}
+
+	#[test]
+	fn animation_samples() {
+		let from = example();
+		let to = ViewTransform::default();
+		let anchor = dvec2(400.0, 300.0);
+		let animation = ViewAnimation::new(from, to, anchor, 1000.0, 250.0);
+
+		let (view, finished) = animation.sample(1000.0);
+		assert!(!finished);
+		assert_near!(view.translation, from.translation, 1e-9);
+
+		// Eased, so the anchor has moved exactly halfway at the midpoint.
+		let (view, finished) = animation.sample(1125.0);
+		assert!(!finished);
+		let expected = from
+			.screen_to_canvas_point(anchor)
+			.lerp(to.screen_to_canvas_point(anchor), 0.5);
+		assert_near!(view.screen_to_canvas_point(anchor), expected, 1e-9);
+
+		assert_eq!(animation.sample(1250.0), (to, true));
+		assert_eq!(animation.sample(5000.0), (to, true));
+	}
+
+	// Regression tests for views far from the canvas origin, where `f32` has less than a pixel of
+	// precision.
+	const FAR: f64 = 3.0e9;
+
+	#[test]
+	fn pan_far_from_origin() {
+		let mut view = ViewTransform {
+			translation: dvec2(-FAR, FAR),
+			..Default::default()
+		};
+		let anchor = dvec2(400.0, 300.0);
+		let canvas_anchor = view.screen_to_canvas_point(anchor);
+
+		// Sub-pixel pans must accumulate exactly rather than being rounded away.
+		for _ in 0..1000 {
+			view.pan(dvec2(0.25, -0.25));
+		}
+		assert_near!(
+			view.screen_to_canvas_point(anchor),
+			canvas_anchor - dvec2(250.0, -250.0),
+			1e-3
+		);
+	}
+
+	#[test]
+	fn zoom_far_from_origin() {
+		let mut view = ViewTransform {
+			translation: dvec2(-FAR, -FAR),
+			..Default::default()
+		};
+		let anchor = dvec2(400.0, 300.0);
+		let canvas_anchor = view.screen_to_canvas_point(anchor);
+		for _ in 0..10 {
+			view.zoom_about(anchor, 1.272);
+		}
+		for _ in 0..10 {
+			view.zoom_about(anchor, 1.0 / 1.272);
+		}
+		assert_near!(view.canvas_to_screen_point(canvas_anchor), anchor, 1e-3);
+	}
+
+	#[test]
+	fn relative_matrix_far_from_origin() {
+		let view = ViewTransform {
+			translation: dvec2(-FAR, FAR),
+			zoom: 4.0,
+			rotation: 0.3,
+			..Default::default()
+		};
+		let screen_center = dvec2(400.0, 300.0);
+		let origin = view.screen_to_canvas_point(screen_center).floor();
+		let matrix = view.canvas_to_screen_matrix_relative_to(origin);
+
+		// Neighboring canvas points must map to distinct, correctly spaced screen points.
+		for offset in [
+			dvec2(0.0, 0.0),
+			dvec2(0.5, 0.0),
+			dvec2(-3.25, 7.75),
+			dvec2(100.0, -60.0),
+		] {
+			let expected = view.canvas_to_screen_point(origin + offset);
+			let actual = (matrix * offset.as_vec2().extend(0.0).extend(1.0)).xy();
+			assert_near!(actual.as_dvec2(), expected, 1e-2);
+		}
+
+		// The naive absolute `f32` matrix can't even resolve whole pixels here.
+		let absolute = DMat4::from_scale_rotation_translation(
+			dvec3(view.zoom, view.zoom, 1.0),
+			DQuat::from_rotation_z(view.rotation),
+			view.translation.extend(0.0),
+		)
+		.as_mat4();
+		let p = origin + dvec2(0.5, 0.0);
+		let naive = (absolute * p.as_vec2().extend(0.0).extend(1.0)).xy();
+		assert!(!naive
+			.as_dvec2()
+			.abs_diff_eq(view.canvas_to_screen_point(p), 1.0));
+	}
+
+	#[test]
+	fn view_motion() {
+		let mut motion = ViewMotion::default();
+		assert_eq!(motion.predicted_offset(50.0), DVec2::ZERO);
+		motion.record(0.0, dvec2(0.0, 0.0));
+		assert_eq!(motion.predicted_offset(50.0), DVec2::ZERO);
+
+		// Panning steadily to the right.
+		for t in 1..=10 {
+			motion.record(10.0 * t as f64, dvec2(20.0 * t as f64, 0.0));
+		}
+		assert_near!(motion.velocity(), dvec2(2.0, 0.0), 1e-9);
+		assert_near!(motion.predicted_offset(50.0), dvec2(100.0, 0.0), 1e-9);
+
+		// Only recent motion counts, so stopping soon stops the prediction.
+		motion.record(200.0, dvec2(200.0, 0.0));
+		motion.record(250.0, dvec2(200.0, 0.0));
+		assert_eq!(motion.predicted_offset(50.0), DVec2::ZERO);
+	}
+}
@@ -1,5 +1,8 @@
 use std::{future::Future, sync::Arc};
 
+use bon::bon;
+
+use crate::render::{report_gpu_error, FrameCapturer, GpuProfiler};
 use crate::util::DeviceExt as _;
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -7,6 +10,9 @@ pub enum WgpuContextError {
 	#[error("request adapter error")]
 	RequestAdapterError,
 
+	#[error("adapter is missing required features {0:?}")]
+	MissingFeatures(wgpu::Features),
+
 	#[error("request device error {0}")]
 	RequestDeviceError(String),
 }
@@ -19,48 +25,127 @@ impl From<wgpu::RequestDeviceError> for WgpuContextError {
 	}
 }
 
+/// What the device was created with, which may be more than was asked for.
+#[derive(Debug, Clone)]
+pub struct DeviceCapabilities {
+	pub adapter: wgpu::AdapterInfo,
+	pub features: wgpu::Features,
+	pub limits: wgpu::Limits,
+	/// See [`WgpuContext::is_compatibility_mode`].
+	pub compatibility_mode: bool,
+}
+
 #[derive(Debug)]
 pub struct WgpuContext {
 	instance: wgpu::Instance,
 	adapter: wgpu::Adapter,
 	device: Arc<wgpu::Device>,
 	queue: wgpu::Queue,
+	compatibility_mode: bool,
+	profiler: GpuProfiler,
+	frame_capture: FrameCapturer,
 }
 
+#[bon]
 impl WgpuContext {
+	/// Creates a device on the adapter best matching the options. Features the engine always needs
+	/// are added to `required_features`, and some only used where available are requested too.
+	///
+	/// In browsers without WebGPU, this falls back to WebGL2 in [compatibility
+	/// mode](Self::is_compatibility_mode).
+	#[builder(finish_fn = create)]
 	#[tracing::instrument(err)]
-	pub async fn new() -> Result<Self, WgpuContextError> {
+	pub async fn new(
+		#[builder(default)] power_preference: wgpu::PowerPreference,
+		#[builder(default)] required_features: wgpu::Features,
+		/// Defaults to what the adapter supports within WebGPU's defaults, or WebGL2's in
+		/// compatibility mode.
+		required_limits: Option<wgpu::Limits>,
+		/// Uses a software adapter, e.g. to tell driver bugs apart from our own.
+		#[builder(default)]
+		force_fallback_adapter: bool,
+		/// Defaults to every backend, or only GL in browsers without WebGPU.
+		backends: Option<wgpu::Backends>,
+	) -> Result<Self, WgpuContextError> {
+		let backends = match backends {
+			Some(backends) => backends,
+			None
+				if cfg!(target_arch = "wasm32") && !wgpu::util::is_browser_webgpu_supported().await =>
+			{
+				tracing::warn!("WebGPU is unsupported, falling back to WebGL2");
+				wgpu::Backends::GL
+			}
+			None => wgpu::Backends::all(),
+		};
 		let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+			backends,
 			flags: wgpu::InstanceFlags::from_build_config().with_env(),
 			..Default::default()
 		});
 		tracing::info!(?instance);
 
 		let adapter = instance
-			.request_adapter(&wgpu::RequestAdapterOptions::default())
+			.request_adapter(&wgpu::RequestAdapterOptions {
+				power_preference,
+				force_fallback_adapter,
+				compatible_surface: None,
+			})
 			.await
 			.ok_or(WgpuContextError::RequestAdapterError)?;
 		tracing::info!(?adapter);
 
+		let compatibility_mode = !adapter.get_downlevel_capabilities().is_webgpu_compliant();
+		let required_limits = required_limits.unwrap_or_else(|| match compatibility_mode {
+			true => wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+			false => wgpu::Limits::default(),
+		});
+		let required_features = match compatibility_mode {
+			true => required_features,
+			false => required_features | wgpu::Features::INDIRECT_FIRST_INSTANCE,
+		};
+		let missing_features = required_features - adapter.features();
+		if !missing_features.is_empty() {
+			Err(WgpuContextError::MissingFeatures(missing_features))?;
+		}
+		// These are only needed for some canvas formats, to save compiled pipelines between sessions,
+		// or to profile passes, so we request them opportunistically.
+		let optional_features = adapter.features()
+			& (wgpu::Features::FLOAT32_FILTERABLE
+				| wgpu::Features::PIPELINE_CACHE
+				| wgpu::Features::TIMESTAMP_QUERY);
+
 		let (device, queue) = adapter
 			.request_device(
 				&wgpu::DeviceDescriptor {
-					required_features: wgpu::Features::default()
-						| wgpu::Features::INDIRECT_FIRST_INSTANCE,
+					required_features: required_features | optional_features,
+					required_limits,
 					..Default::default()
 				},
 				None,
 			)
 			.await?;
 		tracing::info!(?device);
+		if compatibility_mode {
+			// Whatever needs storage buffers or textures fails validation here. The default handler
+			// panics, but the rest of the app may still work.
+			device.on_uncaptured_error(Box::new(|error| {
+				report_gpu_error("uncaptured error in compatibility mode", &error);
+			}));
+		}
 		let device = Arc::new(device);
+		let profiler = GpuProfiler::new(device.clone(), &queue);
 
-		Ok(Self {
+		let context = Self {
 			instance,
 			adapter,
 			device,
 			queue,
-		})
+			compatibility_mode,
+			profiler,
+			frame_capture: Default::default(),
+		};
+		tracing::info!(capabilities = ?context.capabilities());
+		Ok(context)
 	}
 
 	pub fn instance(&self) -> &wgpu::Instance {
@@ -79,6 +164,31 @@ impl WgpuContext {
 		&self.queue
 	}
 
+	/// Times passes of encoders created through it, for the profiler overlay.
+	pub fn profiler(&self) -> &GpuProfiler {
+		&self.profiler
+	}
+
+	/// Records the commands of a frame on request, for debugging in the browser.
+	pub fn frame_capture(&self) -> &FrameCapturer {
+		&self.frame_capture
+	}
+
+	/// Whether the adapter falls short of WebGPU, e.g. WebGL2. Storage buffers and textures may be
+	/// missing, so painting is unavailable, and compute-based features must be skipped.
+	pub fn is_compatibility_mode(&self) -> bool {
+		self.compatibility_mode
+	}
+
+	pub fn capabilities(&self) -> DeviceCapabilities {
+		DeviceCapabilities {
+			adapter: self.adapter.get_info(),
+			features: self.device.features(),
+			limits: self.device.limits(),
+			compatibility_mode: self.compatibility_mode,
+		}
+	}
+
 	pub fn get_buffer_data(
 		&self,
 		buffer: std::sync::Arc<wgpu::Buffer>,
@@ -90,36 +200,47 @@ impl WgpuContext {
 		&self,
 		texture: &wgpu::Texture,
 		layer_index: u32,
+	) -> impl Future<Output = anyhow::Result<Vec<u8>>> {
+		let origin = wgpu::Origin3d {
+			x: 0,
+			y: 0,
+			z: layer_index,
+		};
+		let size = wgpu::Extent3d {
+			depth_or_array_layers: 1,
+			..texture.size()
+		};
+		self.get_texture_region_data(texture, origin, size)
+	}
+
+	/// Reads back a region of the first mip level of `texture`, tightly packed in rows. This is
+	/// much cheaper than reading back whole layers when only a few texels are needed.
+	pub fn get_texture_region_data(
+		&self,
+		texture: &wgpu::Texture,
+		origin: wgpu::Origin3d,
+		size: wgpu::Extent3d,
 	) -> impl Future<Output = anyhow::Result<Vec<u8>>> {
 		let aspect = wgpu::TextureAspect::All;
 		let (block_width, block_height) = texture.format().block_dimensions();
 		let bytes_per_row =
-			texture.format().block_copy_size(Some(aspect)).unwrap() * (texture.width() / block_width);
-		let rows_per_image = texture.height() / block_height;
+			texture.format().block_copy_size(Some(aspect)).unwrap() * (size.width / block_width);
+		let rows_per_image = size.height / block_height;
 		let row_stride = wgpu::util::align_to(bytes_per_row, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
 
 		let device = self.device().clone();
 		let buffer = device.create_buffer(&wgpu::BufferDescriptor {
 			label: None,
-			size: (row_stride * texture.height()) as wgpu::BufferAddress,
+			size: (row_stride * rows_per_image * size.depth_or_array_layers) as wgpu::BufferAddress,
 			usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
 			mapped_at_creation: false,
 		});
 		let mut encoder = device.create_command_encoder(&Default::default());
-		let mip_level = 0;
-		let layer_size = wgpu::Extent3d {
-			depth_or_array_layers: 1u32,
-			..texture.size()
-		};
 		encoder.copy_texture_to_buffer(
 			wgpu::ImageCopyTexture {
 				texture,
-				mip_level,
-				origin: wgpu::Origin3d {
-					x: 0,
-					y: 0,
-					z: layer_index,
-				},
+				mip_level: 0,
+				origin,
 				aspect,
 			},
 			wgpu::ImageCopyBuffer {
@@ -130,7 +251,7 @@ impl WgpuContext {
 					rows_per_image: Some(rows_per_image),
 				},
 			},
-			layer_size.mip_level_size(mip_level, texture.dimension()),
+			size,
 		);
 		self.queue().submit([encoder.finish()]);
 
@@ -190,6 +311,49 @@ mod tests {
 		)
 	}
 
+	#[test]
+	fn get_texture_region_data() -> anyhow::Result<()> {
+		use wgpu::util::DeviceExt as _;
+		let context = test::WgpuTestContext::new()?;
+		// Each texel holds its own coordinates, so misplaced texels are caught.
+		let (width, height) = (8, 8);
+		let data: Vec<u8> = (0..height)
+			.flat_map(|y| (0..width).flat_map(move |x| [x, y, 0, 255]))
+			.collect();
+		let texture = context.device().create_texture_with_data(
+			context.queue(),
+			&wgpu::TextureDescriptor {
+				label: None,
+				size: wgpu::Extent3d {
+					width: width as u32,
+					height: height as u32,
+					depth_or_array_layers: 1,
+				},
+				mip_level_count: 1,
+				sample_count: 1,
+				dimension: wgpu::TextureDimension::D2,
+				format: wgpu::TextureFormat::Rgba8Unorm,
+				usage: wgpu::TextureUsages::COPY_SRC,
+				view_formats: &[],
+			},
+			Default::default(),
+			&data,
+		);
+
+		let origin = wgpu::Origin3d { x: 3, y: 5, z: 0 };
+		let size = wgpu::Extent3d {
+			width: 2,
+			height: 3,
+			depth_or_array_layers: 1,
+		};
+		let region = pollster::block_on(context.get_texture_region_data(&texture, origin, size))?;
+		let expected: Vec<u8> = (5..8)
+			.flat_map(|y| (3..5).flat_map(move |x| [x, y, 0, 255]))
+			.collect();
+		assert_eq!(region, expected);
+		Ok(())
+	}
+
 	#[test]
 	fn copy_texture_to_scaled_texture() -> anyhow::Result<()> {
 		let context = test::WgpuTestContext::new()?;
@@ -0,0 +1,34 @@
+//! Uses the engine as an embedding project would, through the crate's public API only.
+
+use glam::*;
+use stark::engine::{Airbrush, Atlas, CanvasFormat, ChartKey, InputPoint};
+use stark::render::Resources;
+use stark::WgpuContext;
+use std::sync::Arc;
+
+#[test]
+fn paint_through_public_api() -> anyhow::Result<()> {
+	let context = Arc::new(pollster::block_on(WgpuContext::builder().create())?);
+	let (device, queue) = (context.device(), context.queue());
+	let resources = Resources::new(device);
+	let format = CanvasFormat::Rgba8Unorm;
+	let mut atlas = Atlas::new(context.clone(), format);
+	let mut airbrush = Airbrush::new(device, queue, &resources, format);
+
+	let point = InputPoint {
+		position: Vec2::ZERO,
+		pressure: 1.0,
+		color: vec3(0.6, 0.1, -0.05),
+		size: 8.0,
+		opacity: 1.0,
+		rate: 25.0,
+		tilt: Vec2::ZERO,
+	};
+	let points =
+		[vec2(40.0, 40.0), vec2(80.0, 60.0)].map(|position| InputPoint { position, ..point });
+	airbrush.draw_stroke(queue, &mut atlas, points)?;
+
+	assert!(atlas.take_dirty().contains(&ChartKey(0, 0)));
+	assert!(pollster::block_on(atlas.sample(vec2(60.5, 50.5)))?.w > 0.0);
+	Ok(())
+}